@@ -0,0 +1,11 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // A vendored protoc, so building needs nothing installed
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/submission.proto"], &["proto"])?;
+    Ok(())
+}
@@ -0,0 +1,18 @@
+-- Jobs the DLQ worker could not recover, kept for manual review and replay
+CREATE TABLE IF NOT EXISTS failed_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    job_id UUID NOT NULL,
+    esign_id TEXT NOT NULL,
+    document_type TEXT NOT NULL,
+    status TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    retry_count INTEGER NOT NULL,
+    payload JSONB NOT NULL,
+    error_history JSONB NOT NULL DEFAULT '[]',
+    replayed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique__failed_jobs_job_id UNIQUE (job_id)
+);
+
+CREATE INDEX IF NOT EXISTS failed_jobs_status_idx ON failed_jobs(status, created_at DESC);
@@ -0,0 +1,9 @@
+-- Track email verification; accounts created before this migration are treated as verified
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS verification_token TEXT,
+    ADD COLUMN IF NOT EXISTS verification_token_expires_at TIMESTAMPTZ;
+
+UPDATE users SET email_verified_at = COALESCE(created_at, NOW()) WHERE email_verified_at IS NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_verification_token_idx ON users(verification_token);
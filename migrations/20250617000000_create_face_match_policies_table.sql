@@ -0,0 +1,20 @@
+-- Submissions belong to a tenant; rows created before tenancy belong to 'default'
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS tenant_id TEXT NOT NULL DEFAULT 'default',
+    ADD COLUMN IF NOT EXISTS face_match_score DOUBLE PRECISION;
+
+-- Face match decision bands per tenant and submission type. Scores at or above
+-- approve_threshold are auto-approved, scores below reject_threshold are
+-- auto-rejected and anything in between goes to manual review. The 'default'
+-- tenant row applies when a tenant has no row of its own.
+CREATE TABLE IF NOT EXISTS face_match_policies (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id TEXT NOT NULL,
+    submission_type TEXT NOT NULL,
+    approve_threshold DOUBLE PRECISION NOT NULL,
+    reject_threshold DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique__face_match_policies_tenant_type UNIQUE (tenant_id, submission_type),
+    CONSTRAINT check__face_match_policies_band CHECK (reject_threshold <= approve_threshold)
+);
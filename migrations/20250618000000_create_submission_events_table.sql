@@ -0,0 +1,22 @@
+-- Append-only audit trail of everything that happened to a submission
+CREATE TABLE IF NOT EXISTS submission_events (
+    id BIGSERIAL PRIMARY KEY,
+    submission_id UUID NOT NULL,
+    event_type TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    payload_diff JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS submission_events_submission_id_idx ON submission_events(submission_id, id);
+
+CREATE OR REPLACE FUNCTION reject_submission_event_mutation() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'submission_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS submission_events_append_only ON submission_events;
+CREATE TRIGGER submission_events_append_only
+    BEFORE UPDATE OR DELETE ON submission_events
+    FOR EACH ROW EXECUTE FUNCTION reject_submission_event_mutation();
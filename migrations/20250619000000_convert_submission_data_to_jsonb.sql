@@ -0,0 +1,11 @@
+-- submission_data holds a typed SubmissionDocuments object. Existing rows were
+-- written as serialized JSON text; empty or missing values become an empty object.
+ALTER TABLE submissions
+    ALTER COLUMN submission_data TYPE JSONB
+    USING CASE
+        WHEN submission_data IS NULL OR btrim(submission_data) = '' THEN '{}'::jsonb
+        ELSE submission_data::jsonb
+    END;
+
+ALTER TABLE submissions ALTER COLUMN submission_data SET DEFAULT '{}';
+ALTER TABLE submissions ALTER COLUMN submission_data SET NOT NULL;
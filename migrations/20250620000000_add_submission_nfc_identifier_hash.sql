@@ -0,0 +1,6 @@
+-- nfc_identifier is now stored encrypted, so lookups go through a keyed hash
+-- of the plaintext. Rows written before encryption keep a NULL hash and a
+-- plaintext identifier until they are re-saved.
+ALTER TABLE submissions ADD COLUMN IF NOT EXISTS nfc_identifier_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_submissions_nfc_identifier_hash ON submissions (nfc_identifier_hash);
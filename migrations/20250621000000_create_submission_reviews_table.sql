@@ -0,0 +1,16 @@
+-- Submissions in the face match gray zone, waiting for a reviewer's decision
+CREATE TABLE IF NOT EXISTS submission_reviews (
+    id BIGSERIAL PRIMARY KEY,
+    submission_id UUID NOT NULL,
+    status TEXT NOT NULL,
+    face_match_score DOUBLE PRECISION,
+    reason_code TEXT,
+    reviewer TEXT,
+    notes TEXT,
+    reviewed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique__submission_reviews_submission_id UNIQUE (submission_id)
+);
+
+CREATE INDEX IF NOT EXISTS submission_reviews_status_idx ON submission_reviews(status, created_at);
@@ -0,0 +1,3 @@
+-- Optional contact number users can set on their profile
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS phone TEXT;
@@ -0,0 +1,7 @@
+-- Aggregate statistics scan submissions by creation time and look up the
+-- first decision in each submission's audit trail
+CREATE INDEX IF NOT EXISTS idx_submissions_created_at ON submissions (created_at);
+
+CREATE INDEX IF NOT EXISTS idx_submission_events_status_changed
+    ON submission_events (submission_id, created_at)
+    WHERE event_type = 'STATUS_CHANGED';
@@ -0,0 +1,16 @@
+-- Accounts closed by their owner, kept until the retention window passes
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS users_deleted_at_idx ON users(deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- The audit trail stays append-only, except for the retention purge, which
+-- opts in for its own transaction with SET LOCAL app.purging_user_data = 'on'
+CREATE OR REPLACE FUNCTION reject_submission_event_mutation() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' AND current_setting('app.purging_user_data', true) = 'on' THEN
+        RETURN OLD;
+    END IF;
+    RAISE EXCEPTION 'submission_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,12 @@
+-- Back office search looks submissions up by the KTP NIK, the owner's email
+-- and status, newest first. The NIK is encrypted, so like nfc_identifier it is
+-- matched through a keyed hash of the plaintext; rows written before this
+-- migration have no hash and are only found through the other filters.
+ALTER TABLE submissions ADD COLUMN IF NOT EXISTS nik_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_submissions_nik_hash ON submissions (nik_hash);
+CREATE INDEX IF NOT EXISTS idx_submissions_user_id ON submissions (user_id, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_submissions_status_created_at ON submissions (status, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_submissions_created_at_id ON submissions (created_at DESC, id DESC);
+
+CREATE INDEX IF NOT EXISTS users_lower_email_idx ON users (LOWER(email));
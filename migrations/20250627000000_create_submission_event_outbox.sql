@@ -0,0 +1,20 @@
+-- Audit trail events waiting to be published to Kafka. The trigger adds each
+-- event in the transaction that writes it, so exactly the committed events
+-- get published; the publisher deletes the row once the broker acknowledged
+-- the event. Rows pile up while no publisher runs and are sent once one does.
+CREATE TABLE IF NOT EXISTS submission_event_outbox (
+    event_id BIGINT PRIMARY KEY REFERENCES submission_events(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE OR REPLACE FUNCTION enqueue_submission_event() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO submission_event_outbox (event_id, created_at) VALUES (NEW.id, NEW.created_at);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS submission_events_outbox ON submission_events;
+CREATE TRIGGER submission_events_outbox
+    AFTER INSERT ON submission_events
+    FOR EACH ROW EXECUTE FUNCTION enqueue_submission_event();
@@ -0,0 +1,23 @@
+-- When a submission went through each stage, for SLA tracking:
+-- processing_started_at on the first process request, decided_at when the
+-- first decision (automatic, scanner or review) was stored and completed_at
+-- once it reached a final outcome. The *_sla_breached_at columns record when
+-- the SLA monitor flagged a submission, so it is alerted on only once.
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS processing_started_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS decided_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS completed_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS decision_sla_breached_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS review_sla_breached_at TIMESTAMPTZ;
+
+-- Earlier submissions weren't tracked: take their last update as the stage
+-- time and don't alert on them
+UPDATE submissions SET decided_at = updated_at WHERE result IS NOT NULL AND decided_at IS NULL;
+UPDATE submissions SET completed_at = updated_at WHERE status <> 'MANUAL_REVIEW' AND result IS NOT NULL AND completed_at IS NULL;
+UPDATE submissions SET decision_sla_breached_at = NOW() WHERE decided_at IS NULL AND decision_sla_breached_at IS NULL;
+UPDATE submissions SET review_sla_breached_at = NOW() WHERE status = 'MANUAL_REVIEW' AND completed_at IS NULL AND review_sla_breached_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_submissions_awaiting_decision ON submissions (created_at)
+    WHERE decided_at IS NULL AND decision_sla_breached_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_submissions_awaiting_review ON submissions (decided_at)
+    WHERE status = 'MANUAL_REVIEW' AND completed_at IS NULL AND review_sla_breached_at IS NULL;
@@ -0,0 +1,21 @@
+-- Rules the decision engine applies on top of the face match band, per tenant
+-- and submission type. A failing rule sends the submission to on_failure
+-- (REJECTED or MANUAL_REVIEW); the band itself always applies. The 'default'
+-- tenant rows apply when a tenant has no rows of its own, and DECISION_RULES
+-- when neither has any.
+CREATE TABLE IF NOT EXISTS decision_rules (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id TEXT NOT NULL,
+    submission_type TEXT NOT NULL,
+    rule TEXT NOT NULL,
+    on_failure TEXT NOT NULL DEFAULT 'MANUAL_REVIEW',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique__decision_rules_tenant_type_rule UNIQUE (tenant_id, submission_type, rule),
+    CONSTRAINT check__decision_rules_rule CHECK (rule IN ('LIVENESS_PASSED', 'NIK_MATCHES_NFC', 'UNIQUE_NIK')),
+    CONSTRAINT check__decision_rules_on_failure CHECK (on_failure IN ('REJECTED', 'MANUAL_REVIEW'))
+);
+
+-- Why the decision engine decided the way it did, e.g. ["NIK_DUPLICATE"]
+ALTER TABLE submissions ADD COLUMN IF NOT EXISTS decision_reasons JSONB NOT NULL DEFAULT '[]';
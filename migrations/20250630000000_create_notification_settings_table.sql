@@ -0,0 +1,19 @@
+-- Channels a tenant's users are notified on when their submission is decided,
+-- with optional templates replacing the built-in ones. The 'default' tenant
+-- rows apply when a tenant has no rows of its own, and NOTIFICATION_CHANNELS
+-- when neither has any.
+CREATE TABLE IF NOT EXISTS notification_settings (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id TEXT NOT NULL,
+    channel TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    -- Only used by EMAIL
+    subject_template TEXT,
+    body_template TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique__notification_settings_tenant_channel_outcome UNIQUE (tenant_id, channel, outcome),
+    CONSTRAINT check__notification_settings_channel CHECK (channel IN ('EMAIL', 'SMS', 'WHATSAPP')),
+    CONSTRAINT check__notification_settings_outcome CHECK (outcome IN ('APPROVED', 'REJECTED'))
+);
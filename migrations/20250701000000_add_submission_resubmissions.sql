@@ -0,0 +1,23 @@
+-- A rejected submission can be resubmitted once: the child links back to it
+-- and counts how many resubmissions led to it (1 for the first).
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS parent_submission_id UUID,
+    ADD COLUMN IF NOT EXISTS resubmission_attempt INTEGER NOT NULL DEFAULT 0;
+
+CREATE UNIQUE INDEX IF NOT EXISTS unique__submissions_parent_submission_id
+    ON submissions (parent_submission_id)
+    WHERE parent_submission_id IS NOT NULL;
+
+-- How many times a rejected submission may be resubmitted, per tenant and
+-- submission type. The 'default' tenant rows apply when a tenant has no row
+-- of its own, and RESUBMISSION_MAX_ATTEMPTS when neither has one.
+CREATE TABLE IF NOT EXISTS resubmission_policies (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id TEXT NOT NULL,
+    submission_type TEXT NOT NULL,
+    max_attempts INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique__resubmission_policies_tenant_type UNIQUE (tenant_id, submission_type),
+    CONSTRAINT check__resubmission_policies_max_attempts CHECK (max_attempts >= 0)
+);
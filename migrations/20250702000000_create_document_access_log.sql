@@ -0,0 +1,23 @@
+-- Every download URL handed out for a stored document: who asked for it, for
+-- which submission and document, from where and why. Rows outlive the
+-- submission so access can still be proven after an account is purged.
+CREATE TABLE IF NOT EXISTS document_access_log (
+    id BIGSERIAL PRIMARY KEY,
+    submission_id UUID NOT NULL,
+    document_type TEXT NOT NULL,
+    accessor_type TEXT NOT NULL,
+    accessor_id TEXT NOT NULL,
+    purpose TEXT NOT NULL,
+    ip_address TEXT,
+    accessed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT check__document_access_log_accessor_type CHECK (accessor_type IN ('USER', 'ADMIN'))
+);
+
+CREATE INDEX IF NOT EXISTS document_access_log_submission_id_idx
+    ON document_access_log (submission_id, accessed_at DESC);
+
+CREATE INDEX IF NOT EXISTS document_access_log_accessor_idx
+    ON document_access_log (accessor_type, accessor_id, accessed_at DESC);
+
+CREATE INDEX IF NOT EXISTS document_access_log_accessed_at_idx
+    ON document_access_log (accessed_at DESC);
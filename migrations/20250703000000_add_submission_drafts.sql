@@ -0,0 +1,6 @@
+-- Form data the app autosaves before the submission is processed, so the
+-- user can pick up where they left off. PII fields are encrypted like
+-- request_data.
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS draft_data JSONB,
+    ADD COLUMN IF NOT EXISTS draft_updated_at TIMESTAMPTZ;
@@ -0,0 +1,27 @@
+-- One row per attempt a worker made at a job: who ran it, how long it waited
+-- and ran, and how it ended. Retries of a job are separate rows.
+CREATE TABLE IF NOT EXISTS job_executions (
+    id BIGSERIAL PRIMARY KEY,
+    job_id UUID NOT NULL,
+    job_type TEXT NOT NULL,
+    esign_id TEXT NOT NULL,
+    document_type TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    worker_id TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    error TEXT,
+    queue_wait_ms BIGINT,
+    lock_wait_ms BIGINT,
+    duration_ms BIGINT NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS job_executions_job_id_idx
+    ON job_executions (job_id, started_at DESC);
+
+CREATE INDEX IF NOT EXISTS job_executions_started_at_idx
+    ON job_executions (started_at DESC);
+
+CREATE INDEX IF NOT EXISTS job_executions_outcome_idx
+    ON job_executions (outcome, started_at DESC);
@@ -0,0 +1,4 @@
+-- Device the submission was created from, as reported by the app. The IP
+-- address is encrypted like the PII fields of request_data.
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS device_info JSONB;
@@ -0,0 +1,4 @@
+-- The device rules can be configured per tenant like the others
+ALTER TABLE decision_rules DROP CONSTRAINT IF EXISTS check__decision_rules_rule;
+ALTER TABLE decision_rules ADD CONSTRAINT check__decision_rules_rule
+    CHECK (rule IN ('LIVENESS_PASSED', 'NIK_MATCHES_NFC', 'UNIQUE_NIK', 'GENUINE_DEVICE', 'DEVICE_LOCALE_MATCHES'));
@@ -0,0 +1,26 @@
+-- Mapping curves from a provider's raw face match scores to a 0-100
+-- confidence, e.g. [{"raw": 0.3, "confidence": 0}, {"raw": 0.9, "confidence": 100}].
+-- Scores are interpolated linearly between points; providers without a row
+-- use their built-in scale.
+CREATE TABLE IF NOT EXISTS face_match_calibrations (
+    id BIGSERIAL PRIMARY KEY,
+    provider TEXT NOT NULL,
+    points JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique__face_match_calibrations_provider UNIQUE (provider),
+    CONSTRAINT check__face_match_calibrations_points CHECK (jsonb_typeof(points) = 'array' AND jsonb_array_length(points) >= 2)
+);
+
+-- face_match_score is the calibrated confidence; the provider's own score is
+-- kept next to it for audit
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS face_match_raw_score DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS face_match_provider TEXT;
+
+-- Scores and thresholds so far were similarities between 0 and 1
+UPDATE submissions SET face_match_score = face_match_score * 100 WHERE face_match_score IS NOT NULL;
+UPDATE submission_reviews SET face_match_score = face_match_score * 100 WHERE face_match_score IS NOT NULL;
+UPDATE face_match_policies
+SET approve_threshold = approve_threshold * 100, reject_threshold = reject_threshold * 100, updated_at = NOW()
+WHERE approve_threshold <= 1;
@@ -0,0 +1,4 @@
+-- Bumped on every write of submission_data, so a step can patch it only if
+-- nobody wrote it since the step read it
+ALTER TABLE submissions
+    ADD COLUMN IF NOT EXISTS submission_data_version BIGINT NOT NULL DEFAULT 0;
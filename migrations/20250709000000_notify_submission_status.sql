@@ -0,0 +1,22 @@
+-- Announces every status change on the submission_status channel, so the API
+-- can push it to clients following the submission. The notification is sent
+-- when the transaction commits, and not at all if it rolls back.
+CREATE OR REPLACE FUNCTION notify_submission_status() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('submission_status', json_build_object(
+        'submissionId', NEW.submission_id,
+        'status', NEW.status,
+        'result', NEW.result,
+        'reasonCode', NEW.reason_code,
+        'updatedAt', NEW.updated_at
+    )::TEXT);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS submissions_status_notify ON submissions;
+CREATE TRIGGER submissions_status_notify
+    AFTER UPDATE OF status ON submissions
+    FOR EACH ROW
+    WHEN (OLD.status IS DISTINCT FROM NEW.status)
+    EXECUTE FUNCTION notify_submission_status();
@@ -0,0 +1,30 @@
+-- Every time an admin viewed a user's account as the user sees it: who, why,
+-- from where and which submissions were shown. Kept in its own table rather
+-- than submission_events, whose rows are published as submission changes.
+-- Rows outlive the user so the access can still be proven after a purge.
+CREATE TABLE IF NOT EXISTS user_view_as_log (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    admin TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    ip_address TEXT,
+    submission_ids UUID[] NOT NULL DEFAULT '{}',
+    viewed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS user_view_as_log_user_id_idx
+    ON user_view_as_log (user_id, viewed_at DESC);
+
+CREATE INDEX IF NOT EXISTS user_view_as_log_admin_idx
+    ON user_view_as_log (admin, viewed_at DESC);
+
+CREATE OR REPLACE FUNCTION reject_user_view_as_log_mutation() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'user_view_as_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS user_view_as_log_append_only ON user_view_as_log;
+CREATE TRIGGER user_view_as_log_append_only
+    BEFORE UPDATE OR DELETE ON user_view_as_log
+    FOR EACH ROW EXECUTE FUNCTION reject_user_view_as_log_mutation();
@@ -0,0 +1,5 @@
+-- Identity card fields can be validated per tenant like the other rules
+ALTER TABLE decision_rules DROP CONSTRAINT IF EXISTS check__decision_rules_rule;
+ALTER TABLE decision_rules ADD CONSTRAINT check__decision_rules_rule
+    CHECK (rule IN ('LIVENESS_PASSED', 'NIK_MATCHES_NFC', 'UNIQUE_NIK', 'GENUINE_DEVICE', 'DEVICE_LOCALE_MATCHES',
+                    'DOCUMENT_FIELDS_VALID'));
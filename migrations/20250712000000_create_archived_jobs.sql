@@ -0,0 +1,20 @@
+-- Jobs that ran to completion, with how their last attempt went, kept for
+-- debugging and replay while JOB_ARCHIVE_ENABLED is on. The worker prunes
+-- rows past JOB_ARCHIVE_RETENTION_DAYS and beyond JOB_ARCHIVE_MAX_JOBS.
+CREATE TABLE IF NOT EXISTS archived_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    job_id UUID NOT NULL,
+    job_type TEXT NOT NULL,
+    esign_id TEXT NOT NULL,
+    document_type TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    worker_id TEXT NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    payload JSONB NOT NULL,
+    replayed_at TIMESTAMPTZ,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS archived_jobs_job_id_idx ON archived_jobs(job_id);
+CREATE INDEX IF NOT EXISTS archived_jobs_archived_at_idx ON archived_jobs(archived_at);
@@ -0,0 +1,15 @@
+-- The malware scanner's verdict on each stored submission document, for the
+-- object version (ETag) it scanned. Processing only accepts a document whose
+-- current object has a CLEAN verdict; PENDING rows wait for a scan job.
+CREATE TABLE IF NOT EXISTS document_scans (
+    document_name TEXT PRIMARY KEY,
+    submission_id UUID NOT NULL,
+    document_type TEXT NOT NULL,
+    verdict TEXT NOT NULL CHECK (verdict IN ('PENDING', 'CLEAN', 'INFECTED')),
+    etag TEXT,
+    signature TEXT,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    scanned_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS document_scans_submission_id_idx ON document_scans(submission_id);
@@ -0,0 +1,24 @@
+-- Policy thresholds are confidences between 0 and 100. Rows written on the
+-- old 0-1 similarity scale are converted as they are written, the way the
+-- calibration migration converted the rows there were, so reads never have
+-- to guess the scale.
+CREATE OR REPLACE FUNCTION convert_face_match_policy_thresholds() RETURNS trigger AS $$
+BEGIN
+    IF NEW.approve_threshold <= 1 AND NEW.reject_threshold <= 1 THEN
+        NEW.approve_threshold := NEW.approve_threshold * 100;
+        NEW.reject_threshold := NEW.reject_threshold * 100;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS face_match_policies_convert_thresholds ON face_match_policies;
+CREATE TRIGGER face_match_policies_convert_thresholds
+    BEFORE INSERT OR UPDATE OF approve_threshold, reject_threshold ON face_match_policies
+    FOR EACH ROW EXECUTE FUNCTION convert_face_match_policy_thresholds();
+
+ALTER TABLE face_match_policies
+    DROP CONSTRAINT IF EXISTS check__face_match_policies_confidence;
+ALTER TABLE face_match_policies
+    ADD CONSTRAINT check__face_match_policies_confidence
+    CHECK (reject_threshold BETWEEN 0 AND 100 AND approve_threshold BETWEEN 0 AND 100);
@@ -0,0 +1,5 @@
+-- A process request claims its submission with a short-lived, immediately
+-- committed marker when the Redis lock can't be taken, so a racing request
+-- sees the claim without a transaction being held open for the whole run.
+ALTER TABLE submissions ADD COLUMN IF NOT EXISTS processing_claim UUID;
+ALTER TABLE submissions ADD COLUMN IF NOT EXISTS processing_claimed_until TIMESTAMPTZ;
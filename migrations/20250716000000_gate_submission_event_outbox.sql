@@ -0,0 +1,11 @@
+-- Only add audit events to the Kafka outbox on connections of instances that
+-- publish to Kafka. They set app.event_outbox when they connect (see
+-- KAFKA_ENABLED); without a publisher the rows would never be removed.
+CREATE OR REPLACE FUNCTION enqueue_submission_event() RETURNS trigger AS $$
+BEGIN
+    IF current_setting('app.event_outbox', true) = 'on' THEN
+        INSERT INTO submission_event_outbox (event_id, created_at) VALUES (NEW.id, NEW.created_at);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
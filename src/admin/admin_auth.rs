@@ -0,0 +1,103 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    middleware::Next,
+    web, HttpRequest,
+};
+
+use anyhow::Context;
+use subtle::ConstantTimeEq;
+
+use crate::commons::app_error::error_response;
+
+pub const ADMIN_API_KEY_HEADER: &str = "x-admin-api-key";
+
+/// Personal key an admin sends to view accounts as their owner
+pub const ADMIN_VIEW_AS_KEY_HEADER: &str = "x-admin-view-as-key";
+
+/// Personal key an admin sends to be identified by endpoints that record
+/// which admin acted
+pub const ADMIN_USER_KEY_HEADER: &str = "x-admin-user-key";
+
+#[derive(Clone)]
+pub struct AdminConfig {
+    pub api_key: String,
+    /// Admins allowed to view accounts as their owner, each with their own
+    /// key; nobody when empty
+    pub view_as_keys: Vec<(String, String)>,
+    /// Every admin's own key, proving who they are; nobody when empty
+    pub user_keys: Vec<(String, String)>,
+}
+
+impl AdminConfig {
+    /// `ADMIN_API_KEY`, and `ADMIN_VIEW_AS_KEYS` and `ADMIN_USER_KEYS`,
+    /// comma separated `<admin>:<key>` pairs
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            api_key: std::env::var("ADMIN_API_KEY").unwrap_or_default(),
+            view_as_keys: admin_keys("ADMIN_VIEW_AS_KEYS")?,
+            user_keys: admin_keys("ADMIN_USER_KEYS")?,
+        })
+    }
+
+    /// The admin whose view-as key the request carries
+    pub fn view_as_admin(&self, req: &HttpRequest) -> Option<&str> {
+        find_admin(&self.view_as_keys, req, ADMIN_VIEW_AS_KEY_HEADER)
+    }
+
+    /// The admin whose own key the request carries in `x-admin-user-key`
+    pub fn authenticated_admin(&self, req: &HttpRequest) -> Option<&str> {
+        find_admin(&self.user_keys, req, ADMIN_USER_KEY_HEADER)
+    }
+}
+
+fn admin_keys(name: &str) -> anyhow::Result<Vec<(String, String)>> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (admin, key) = entry
+                .split_once(':')
+                .map(|(admin, key)| (admin.trim(), key.trim()))
+                .filter(|(admin, key)| !admin.is_empty() && !key.is_empty())
+                .with_context(|| format!("{} entries must look like <admin>:<key>, not {}", name, entry))?;
+            Ok((admin.to_string(), key.to_string()))
+        })
+        .collect()
+}
+
+fn find_admin<'a>(keys: &'a [(String, String)], req: &HttpRequest, header: &str) -> Option<&'a str> {
+    let key = req.headers().get(header)?.as_bytes();
+    keys.iter()
+        .find(|(_, allowed)| bool::from(allowed.as_bytes().ct_eq(key)))
+        .map(|(admin, _)| admin.as_str())
+}
+
+/// Whether the request carries the configured admin API key, compared in
+/// constant time. Never true while the key is empty.
+pub fn has_admin_key(req: &HttpRequest) -> bool {
+    match (
+        req.app_data::<web::Data<AdminConfig>>(),
+        req.headers().get(ADMIN_API_KEY_HEADER),
+    ) {
+        (Some(config), Some(key)) => !config.api_key.is_empty() && bool::from(key.as_bytes().ct_eq(config.api_key.as_bytes())),
+        _ => false,
+    }
+}
+
+/// Reject admin requests that don't carry the configured API key.
+/// An empty key disables the admin API entirely.
+pub async fn require_admin_key(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    if !has_admin_key(req.request()) {
+        let response = error_response(StatusCode::UNAUTHORIZED, "1007", "UNAUTHORIZED".to_string());
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
@@ -0,0 +1,166 @@
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    admin::admin_auth::{AdminConfig, ADMIN_USER_KEY_HEADER},
+    commons::app_error::{error_response, AppError},
+    models::user::ApiResponse,
+    submissions::submission_event_repository::{SubmissionEventRepository, ACTOR_ADMIN, EVENT_ADMIN_ACTION},
+    workers::{
+        job_archive::{ArchivedJob, ArchivedJobRepository},
+        FileUploadJob, JobStatus, RedisQueue,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListArchivedJobsQuery {
+    pub job_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayArchivedJobResponse {
+    pub job_id: String,
+    pub status: String,
+}
+
+/// Jobs that ran to completion, newest first, optionally of one job only
+#[actix_web::get("/archived-jobs")]
+async fn list_archived_jobs(
+    pool: web::Data<sqlx::PgPool>,
+    query: web::Query<ListArchivedJobsQuery>,
+) -> HttpResponse {
+    let repository = ArchivedJobRepository::new(pool.as_ref().clone());
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match repository.find_all(query.job_id, limit, offset).await {
+        Ok(jobs) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(jobs),
+            errors: None,
+        }),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+#[actix_web::get("/archived-jobs/{id}")]
+async fn get_archived_job(
+    pool: web::Data<sqlx::PgPool>,
+    path: web::Path<i64>,
+) -> HttpResponse {
+    let repository = ArchivedJobRepository::new(pool.as_ref().clone());
+
+    match repository.find_by_id(path.into_inner()).await {
+        Ok(Some(job)) => HttpResponse::Ok().json(ApiResponse::<ArchivedJob> {
+            success: true,
+            data: Some(job),
+            errors: None,
+        }),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "1004", "ARCHIVED_JOB_NOT_FOUND".to_string()),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+/// Put an archived job back on the queue to run it again as it was first
+/// run. Each archived job is replayed at most once, by the admin whose own
+/// key is in `x-admin-user-key`.
+#[actix_web::post("/archived-jobs/{id}/replay")]
+async fn replay_archived_job(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    queue: web::Data<RedisQueue>,
+    admin_config: web::Data<AdminConfig>,
+    path: web::Path<i64>,
+) -> HttpResponse {
+    let repository = ArchivedJobRepository::new(pool.as_ref().clone());
+    let id = path.into_inner();
+
+    let Some(admin) = admin_config.authenticated_admin(&req) else {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "1018",
+            format!("ADMIN_NOT_IDENTIFIED: {} is missing or unknown", ADMIN_USER_KEY_HEADER),
+        );
+    };
+
+    let archived_job = match repository.claim_for_replay(id).await {
+        Ok(Some(job)) => job,
+        Ok(None) => {
+            return match repository.find_by_id(id).await {
+                Ok(Some(_)) => error_response(StatusCode::CONFLICT, "1003", "ARCHIVED_JOB_ALREADY_REPLAYED".to_string()),
+                Ok(None) => error_response(StatusCode::NOT_FOUND, "1004", "ARCHIVED_JOB_NOT_FOUND".to_string()),
+                Err(e) => AppError::from(e).error_response(),
+            }
+        }
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    let mut job: FileUploadJob = match serde_json::from_value(archived_job.payload) {
+        Ok(job) => job,
+        Err(e) => {
+            release_replay_claim(&repository, id).await;
+            return error_response(StatusCode::UNPROCESSABLE_ENTITY, "1004", format!("INVALID_JOB_PAYLOAD: {}", e));
+        }
+    };
+
+    job.retry_count = 0;
+    job.updated_at = chrono::Utc::now();
+    job.set_progress(JobStatus::Pending, 0, "REPLAYED");
+
+    let mut queue = queue.as_ref().clone();
+
+    // The job completed once, so its dedup key may still be held
+    let enqueued = match queue.release_idempotency_key(&job).await {
+        Ok(()) => queue.enqueue_job(&job).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = enqueued {
+        release_replay_claim(&repository, id).await;
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string());
+    }
+
+    if let Some(submission_id) = job
+        .metadata
+        .get("submissionId")
+        .and_then(|v| v.as_str())
+        .and_then(|v| uuid::Uuid::parse_str(v).ok())
+    {
+        let event = SubmissionEventRepository::new(pool.as_ref().clone())
+            .append(
+                submission_id,
+                EVENT_ADMIN_ACTION,
+                ACTOR_ADMIN,
+                serde_json::json!({
+                    "action": "REPLAY_ARCHIVED_JOB",
+                    "admin": admin,
+                    "jobId": job.id,
+                    "archivedJobId": id,
+                }),
+            )
+            .await;
+        if let Err(e) = event {
+            log::error!("Failed to record replay of job {} for submission {}: {}", job.id, submission_id, e);
+        }
+    }
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(ReplayArchivedJobResponse {
+            job_id: job.id.to_string(),
+            status: "REPLAYED".to_string(),
+        }),
+        errors: None,
+    })
+}
+
+/// Let a replay that failed before reaching the queue be tried again
+async fn release_replay_claim(repository: &ArchivedJobRepository, id: i64) {
+    if let Err(e) = repository.release_replay_claim(id).await {
+        log::error!("Failed to release replay claim of archived job {}: {}", id, e);
+    }
+}
@@ -0,0 +1,88 @@
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+use crate::{
+    commons::{app_error::error_response, crypto::FieldCipher, minio_service::MinioService},
+    models::user::ApiResponse,
+    submissions::submission_repository::SubmissionRepository,
+    workers::{
+        backfill::{self, BackfillFilter, BackfillRun},
+        RedisQueue,
+    },
+};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkEnqueueResponse {
+    pub backfill_run_id: String,
+    pub status: String,
+}
+
+/// Re-enqueue upload jobs for every document of the matching submissions. The
+/// run continues in the background; poll it with the returned id.
+#[actix_web::post("/jobs/bulk")]
+async fn bulk_enqueue_jobs(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    queue: web::Data<RedisQueue>,
+    body: Result<web::Json<BackfillFilter>, actix_web::Error>,
+) -> HttpResponse {
+    let filter = match body {
+        Ok(b) => b.into_inner(),
+        Err(e) => return e.error_response(),
+    };
+
+    if let (Some(from), Some(to)) = (filter.created_from, filter.created_to) {
+        if from >= to {
+            return error_response(
+                actix_web::http::StatusCode::BAD_REQUEST,
+                "1003",
+                "INVALID_REQUEST_BODY: createdFrom must be before createdTo".to_string(),
+            );
+        }
+    }
+
+    let run = BackfillRun::new(filter);
+    let mut queue = queue.as_ref().clone();
+
+    if let Err(e) = queue.save_backfill_run(&run).await {
+        return error_response(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string());
+    }
+
+    let response = BulkEnqueueResponse {
+        backfill_run_id: run.id.to_string(),
+        status: run.status.clone(),
+    };
+
+    tokio::spawn(backfill::run(
+        run,
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        minio_service.as_ref().clone(),
+        queue,
+    ));
+
+    HttpResponse::Accepted().json(ApiResponse {
+        success: true,
+        data: Some(response),
+        errors: None,
+    })
+}
+
+#[actix_web::get("/jobs/bulk/{run_id}")]
+async fn get_backfill_run(
+    queue: web::Data<RedisQueue>,
+    path: web::Path<uuid::Uuid>,
+) -> HttpResponse {
+    let mut queue = queue.as_ref().clone();
+
+    match queue.get_backfill_run(path.into_inner()).await {
+        Ok(Some(run)) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(run),
+            errors: None,
+        }),
+        Ok(None) => error_response(actix_web::http::StatusCode::NOT_FOUND, "1004", "BACKFILL_RUN_NOT_FOUND".to_string()),
+        Err(e) => error_response(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string()),
+    }
+}
@@ -0,0 +1,143 @@
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, ResponseError};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    admin::admin_auth::{AdminConfig, ADMIN_USER_KEY_HEADER},
+    commons::{app_error::{error_response, AppError}, client_ip::client_ip, crypto::FieldCipher, minio_service::MinioService, url_expiry::UrlExpiryConfig},
+    models::user::ApiResponse,
+    policies::policy_repository::PolicyRepository,
+    services::metrics_service::MetricsService,
+    submissions::{
+        document_access_repository::{DocumentAccessFilter, DocumentAccessPurpose, DocumentAccessRepository, DocumentAccessor},
+        submission_event_repository::SubmissionEventRepository,
+        submission_repository::SubmissionRepository,
+        submission_review_repository::SubmissionReviewRepository,
+        submission_service::SubmissionService,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct DocumentDownloadQuery {
+    pub purpose: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentAccessLogQuery {
+    pub submission_id: Option<Uuid>,
+    pub accessor_type: Option<String>,
+    pub accessor_id: Option<String>,
+    pub document_type: Option<String>,
+    pub purpose: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Download URL of any submission's document for the admin whose own key is
+/// in `x-admin-user-key`, who has to give a purpose that covers the document
+/// type
+#[actix_web::get("/submissions/{submission_id}/documents/{document_type}/download-url")]
+#[allow(clippy::too_many_arguments)]
+async fn admin_document_download_url(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    admin_config: web::Data<AdminConfig>,
+    path: web::Path<(String, String)>,
+    query: web::Query<DocumentDownloadQuery>,
+) -> HttpResponse {
+    let (submission_id, document_type) = path.into_inner();
+    let ip_address = client_ip(&req);
+
+    let Some(admin) = admin_config.authenticated_admin(&req) else {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "1018",
+            format!("DOCUMENT_ACCESS_DENIED: {} is missing or unknown", ADMIN_USER_KEY_HEADER),
+        );
+    };
+
+    let purpose = match query.purpose.as_deref().map(str::parse::<DocumentAccessPurpose>) {
+        Some(Ok(purpose)) => purpose,
+        Some(Err(())) => return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_PURPOSE".to_string()),
+        None => return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_PURPOSE: purpose is required".to_string()),
+    };
+
+    let submission_service = SubmissionService::new(
+        minio_service.as_ref().clone(),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
+        metrics.as_ref().clone(),
+    )
+    .with_url_expiry(url_expiry.as_ref().clone());
+
+    match submission_service
+        .document_download_url(
+            submission_id,
+            document_type,
+            DocumentAccessor::Admin(admin.to_string()),
+            purpose,
+            ip_address,
+            &DocumentAccessRepository::new(pool.as_ref().clone()),
+        )
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(response),
+            errors: None,
+        }),
+        Err(e) => e.error_response(),
+    }
+}
+
+/// Who downloaded which documents, newest first, filtered by submission,
+/// accessor, document type, purpose and an access window `[from, to)`
+#[actix_web::get("/document-access-log")]
+async fn list_document_accesses(
+    pool: web::Data<sqlx::PgPool>,
+    query: Result<web::Query<DocumentAccessLogQuery>, actix_web::Error>,
+) -> HttpResponse {
+    let query = match query {
+        Ok(q) => q.into_inner(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_QUERY: {}", e)),
+    };
+
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_QUERY: from must be before to".to_string());
+        }
+    }
+
+    let non_empty = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let filter = DocumentAccessFilter {
+        submission_id: query.submission_id,
+        accessor_type: non_empty(query.accessor_type),
+        accessor_id: non_empty(query.accessor_id),
+        document_type: non_empty(query.document_type),
+        purpose: non_empty(query.purpose),
+        accessed_from: query.from,
+        accessed_to: query.to,
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let repository = DocumentAccessRepository::new(pool.as_ref().clone());
+    match repository.find_all(&filter, limit, offset).await {
+        Ok(accesses) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(accesses),
+            errors: None,
+        }),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
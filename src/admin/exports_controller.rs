@@ -0,0 +1,185 @@
+use actix_web::{
+    http::{header, StatusCode},
+    web, HttpResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    commons::{
+        app_error::error_response, crypto::FieldCipher, minio_service::MinioService, read_replica::ReadReplica,
+        url_expiry::UrlExpiryConfig,
+    },
+    models::user::ApiResponse,
+    submissions::{
+        submission_export::{self, ExportColumn, ExportFormat, ExportRun, SubmissionExport, EXPORT_STATUS_COMPLETED},
+        submission_repository::SubmissionRepository,
+    },
+    workers::RedisQueue,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ExportSubmissionsQuery {
+    pub format: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub status: Option<String>,
+    /// Comma separated column names; every column when missing
+    pub columns: Option<String>,
+    /// `sync` streams the export in the response, `async` stores it in MinIO
+    pub mode: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportAcceptedResponse {
+    pub export_id: String,
+    pub status: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRunResponse {
+    #[serde(flatten)]
+    pub run: ExportRun,
+    /// Presigned link to the stored export, once it is completed
+    pub download_url: Option<String>,
+    pub download_url_expires_at: Option<DateTime<Utc>>,
+}
+
+/// Submissions created in `[from, to)` as CSV or NDJSON, oldest first. In
+/// sync mode the export is streamed in the response as it is read; in async
+/// mode it is written to MinIO in the background, to be fetched through
+/// `GET /admin/exports/{export_id}`.
+#[actix_web::get("/exports/submissions")]
+#[allow(clippy::too_many_arguments)]
+async fn export_submissions(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    read_replica: web::Data<ReadReplica>,
+    minio_service: web::Data<MinioService>,
+    queue: web::Data<RedisQueue>,
+    query: Result<web::Query<ExportSubmissionsQuery>, actix_web::Error>,
+) -> HttpResponse {
+    let query = match query {
+        Ok(q) => q.into_inner(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_QUERY: {}", e)),
+    };
+
+    let format = match query.format.as_deref().map(ExportFormat::parse) {
+        Some(Some(format)) => format,
+        Some(None) => return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_QUERY: format must be csv or ndjson".to_string()),
+        None => ExportFormat::Csv,
+    };
+
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_QUERY: from must be before to".to_string());
+        }
+    }
+
+    let columns = match query.columns.as_deref().map(str::trim).filter(|columns| !columns.is_empty()) {
+        Some(columns) => {
+            let mut selected = Vec::new();
+            for name in columns.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                match ExportColumn::parse(name) {
+                    Some(column) => selected.push(column),
+                    None => return error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_QUERY: unknown column {}", name)),
+                }
+            }
+            selected
+        }
+        None => ExportColumn::ALL.to_vec(),
+    };
+
+    let export = SubmissionExport {
+        format,
+        columns,
+        created_from: query.from,
+        created_to: query.to,
+        status: query.status.map(|status| status.trim().to_string()).filter(|status| !status.is_empty()),
+    };
+    let repository = SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone())
+        .with_replica(read_replica.as_ref().clone());
+
+    match query.mode.as_deref().unwrap_or("sync") {
+        "sync" => {
+            let file_name = format!("submissions-{}.{}", Utc::now().format("%Y%m%dT%H%M%SZ"), format.extension());
+            HttpResponse::Ok()
+                .content_type(format.content_type())
+                .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)))
+                .streaming(export.stream(repository))
+        }
+        "async" => {
+            let run = ExportRun::new(export);
+            let mut queue = queue.as_ref().clone();
+
+            if let Err(e) = queue.save_export_run(&run).await {
+                return error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string());
+            }
+
+            let response = ExportAcceptedResponse {
+                export_id: run.id.to_string(),
+                status: run.status.clone(),
+            };
+
+            tokio::spawn(submission_export::run(run, repository, minio_service.as_ref().clone(), queue));
+
+            HttpResponse::Accepted().json(ApiResponse {
+                success: true,
+                data: Some(response),
+                errors: None,
+            })
+        }
+        _ => error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_QUERY: mode must be sync or async".to_string()),
+    }
+}
+
+/// State of an async export, with a download link once it is completed. A
+/// run that stopped sending heartbeats is reported as failed.
+#[actix_web::get("/exports/{export_id}")]
+async fn get_export(
+    queue: web::Data<RedisQueue>,
+    minio_service: web::Data<MinioService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let mut queue = queue.as_ref().clone();
+
+    let mut run = match queue.get_export_run(path.into_inner()).await {
+        Ok(Some(run)) => run,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "1004", "EXPORT_NOT_FOUND".to_string()),
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string()),
+    };
+
+    if run.is_orphaned(Utc::now()) {
+        run.mark_interrupted();
+        if let Err(e) = queue.save_export_run(&run).await {
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string());
+        }
+    }
+
+    let (download_url, download_url_expires_at) = if run.status == EXPORT_STATUS_COMPLETED {
+        let expiry = url_expiry.report;
+        match minio_service
+            .generate_presigned_url(run.object_key.clone(), url_expiry.signed_for(expiry))
+            .await
+        {
+            Ok(url) => (Some(url), Some(UrlExpiryConfig::expires_at(expiry))),
+            Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "1001", e.to_string()),
+        }
+    } else {
+        (None, None)
+    };
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(ExportRunResponse {
+            run,
+            download_url,
+            download_url_expires_at,
+        }),
+        errors: None,
+    })
+}
@@ -0,0 +1,147 @@
+use actix_web::{web, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commons::app_error::{error_response, AppError},
+    models::user::ApiResponse,
+    submissions::submission_event_repository::{SubmissionEventRepository, ACTOR_ADMIN, EVENT_ADMIN_ACTION},
+    workers::{
+        failed_job_repository::{FailedJob, FAILED_JOB_STATUS_REPLAYED},
+        FailedJobRepository, FileUploadJob, JobStatus, RedisQueue,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListFailedJobsQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayFailedJobResponse {
+    pub job_id: String,
+    pub status: String,
+}
+
+#[actix_web::get("/failed-jobs")]
+async fn list_failed_jobs(
+    pool: web::Data<sqlx::PgPool>,
+    query: web::Query<ListFailedJobsQuery>,
+) -> HttpResponse {
+    let repository = FailedJobRepository::new(pool.as_ref().clone());
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match repository.find_all(query.status.as_deref(), limit, offset).await {
+        Ok(jobs) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(jobs),
+            errors: None,
+        }),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+#[actix_web::get("/failed-jobs/{id}")]
+async fn get_failed_job(
+    pool: web::Data<sqlx::PgPool>,
+    path: web::Path<i64>,
+) -> HttpResponse {
+    let repository = FailedJobRepository::new(pool.as_ref().clone());
+
+    match repository.find_by_id(path.into_inner()).await {
+        Ok(Some(job)) => HttpResponse::Ok().json(ApiResponse::<FailedJob> {
+            success: true,
+            data: Some(job),
+            errors: None,
+        }),
+        Ok(None) => error_response(actix_web::http::StatusCode::NOT_FOUND, "1004", "FAILED_JOB_NOT_FOUND".to_string()),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+#[actix_web::post("/failed-jobs/{id}/replay")]
+async fn replay_failed_job(
+    pool: web::Data<sqlx::PgPool>,
+    queue: web::Data<RedisQueue>,
+    path: web::Path<i64>,
+) -> HttpResponse {
+    let repository = FailedJobRepository::new(pool.as_ref().clone());
+    let id = path.into_inner();
+
+    let failed_job = match repository.claim_for_replay(id).await {
+        Ok(Some(job)) => job,
+        Ok(None) => {
+            return match repository.find_by_id(id).await {
+                Ok(Some(_)) => error_response(actix_web::http::StatusCode::CONFLICT, "1003", "FAILED_JOB_ALREADY_REPLAYED".to_string()),
+                Ok(None) => error_response(actix_web::http::StatusCode::NOT_FOUND, "1004", "FAILED_JOB_NOT_FOUND".to_string()),
+                Err(e) => AppError::from(e).error_response(),
+            }
+        }
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    let mut job: FileUploadJob = match serde_json::from_value(failed_job.payload) {
+        Ok(job) => job,
+        Err(e) => {
+            release_replay_claim(&repository, id).await;
+            return error_response(actix_web::http::StatusCode::UNPROCESSABLE_ENTITY, "1004", format!("INVALID_JOB_PAYLOAD: {}", e));
+        }
+    };
+
+    // Give the replayed job a fresh retry budget, keeping its error history
+    job.retry_count = 0;
+    job.updated_at = chrono::Utc::now();
+    job.set_progress(JobStatus::Pending, 0, "REPLAYED");
+
+    let mut queue = queue.as_ref().clone();
+
+    // A replay is deliberate, so it must not be swallowed by enqueue dedup
+    let enqueued = match queue.release_idempotency_key(&job).await {
+        Ok(()) => queue.enqueue_job(&job).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = enqueued {
+        release_replay_claim(&repository, id).await;
+        return error_response(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string());
+    }
+
+    // Jobs that carry their submission show the replay in its audit trail
+    if let Some(submission_id) = job
+        .metadata
+        .get("submissionId")
+        .and_then(|v| v.as_str())
+        .and_then(|v| uuid::Uuid::parse_str(v).ok())
+    {
+        let event = SubmissionEventRepository::new(pool.as_ref().clone())
+            .append(
+                submission_id,
+                EVENT_ADMIN_ACTION,
+                ACTOR_ADMIN,
+                serde_json::json!({ "action": "REPLAY_FAILED_JOB", "jobId": job.id }),
+            )
+            .await;
+        if let Err(e) = event {
+            log::error!("Failed to record replay of job {} for submission {}: {}", job.id, submission_id, e);
+        }
+    }
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(ReplayFailedJobResponse {
+            job_id: job.id.to_string(),
+            status: FAILED_JOB_STATUS_REPLAYED.to_string(),
+        }),
+        errors: None,
+    })
+}
+
+/// Let a replay that failed before reaching the queue be tried again
+async fn release_replay_claim(repository: &FailedJobRepository, id: i64) {
+    if let Err(e) = repository.release_replay_claim(id).await {
+        log::error!("Failed to release replay claim of failed job {}: {}", id, e);
+    }
+}
@@ -0,0 +1,65 @@
+use actix_web::{http::StatusCode, web, HttpResponse, ResponseError};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    commons::app_error::{error_response, AppError},
+    models::user::ApiResponse,
+    workers::job_execution_repository::{JobExecutionFilter, JobExecutionRepository},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobExecutionsQuery {
+    pub job_id: Option<Uuid>,
+    /// Outcome of the attempt, e.g. SUCCEEDED, RETRIED or DEAD_LETTERED
+    pub state: Option<String>,
+    /// UPLOAD, REPORT, NOTIFICATION or COPY
+    #[serde(rename = "type")]
+    pub job_type: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Worker attempts at jobs, newest first, filtered by job, outcome, job type
+/// and a start window `[from, to)`
+#[actix_web::get("/jobs")]
+async fn list_job_executions(
+    pool: web::Data<sqlx::PgPool>,
+    query: Result<web::Query<JobExecutionsQuery>, actix_web::Error>,
+) -> HttpResponse {
+    let query = match query {
+        Ok(q) => q.into_inner(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_QUERY: {}", e)),
+    };
+
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_QUERY: from must be before to".to_string());
+        }
+    }
+
+    let normalized = |value: Option<String>| value.map(|v| v.trim().to_uppercase()).filter(|v| !v.is_empty());
+    let filter = JobExecutionFilter {
+        job_id: query.job_id,
+        outcome: normalized(query.state),
+        job_type: normalized(query.job_type),
+        started_from: query.from,
+        started_to: query.to,
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let repository = JobExecutionRepository::new(pool.as_ref().clone());
+    match repository.find_all(&filter, limit, offset).await {
+        Ok(executions) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(executions),
+            errors: None,
+        }),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
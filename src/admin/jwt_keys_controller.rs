@@ -0,0 +1,39 @@
+use actix_web::{http::StatusCode, web, HttpResponse};
+use serde::Serialize;
+
+use crate::{
+    commons::app_error::error_response,
+    models::user::ApiResponse,
+    services::key_provider::KeyProvider,
+};
+
+/// Ids of the loaded JWT keys; secrets are never returned
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JwtKeysResponse {
+    pub signing_key_id: String,
+    pub key_ids: Vec<String>,
+}
+
+fn keys_response(keys: &KeyProvider) -> HttpResponse {
+    let (signing_key_id, key_ids) = keys.key_ids();
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(JwtKeysResponse { signing_key_id, key_ids }),
+        errors: None,
+    })
+}
+
+#[actix_web::get("/jwt-keys")]
+async fn get_jwt_keys(keys: web::Data<KeyProvider>) -> HttpResponse {
+    keys_response(&keys)
+}
+
+/// Load the JWT keys again, e.g. after adding a key to rotate to
+#[actix_web::post("/jwt-keys/reload")]
+async fn reload_jwt_keys(keys: web::Data<KeyProvider>) -> HttpResponse {
+    match keys.reload() {
+        Ok(()) => keys_response(&keys),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_JWT_KEYS: {:#}", e)),
+    }
+}
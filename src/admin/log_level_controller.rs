@@ -0,0 +1,41 @@
+use actix_web::{http::StatusCode, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commons::{app_error::error_response, logging},
+    models::user::ApiResponse,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLevel {
+    /// `RUST_LOG` style directives, e.g. `info,sqlx=warn`
+    pub level: String,
+}
+
+#[actix_web::get("/log-level")]
+async fn get_log_level() -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(LogLevel { level: logging::current_level() }),
+        errors: None,
+    })
+}
+
+/// Change the log level of this process until it restarts
+#[actix_web::put("/log-level")]
+async fn set_log_level(body: Result<web::Json<LogLevel>, actix_web::Error>) -> HttpResponse {
+    let body = match body {
+        Ok(b) => b.into_inner(),
+        Err(e) => return e.error_response(),
+    };
+
+    match logging::set_level(body.level.trim()) {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(LogLevel { level: logging::current_level() }),
+            errors: None,
+        }),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_LOG_LEVEL: {}", e)),
+    }
+}
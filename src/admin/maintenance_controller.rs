@@ -0,0 +1,99 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{
+    commons::{
+        app_error::error_response,
+        maintenance::{MaintenanceMode, MaintenanceWindow},
+    },
+    models::user::ApiResponse,
+};
+
+/// Longest window that can be started at once, so a forgotten window ends
+const MAX_MAINTENANCE_SECONDS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartMaintenanceRequest {
+    pub duration_seconds: u64,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceStatus {
+    pub active: bool,
+    pub window: Option<MaintenanceWindow>,
+}
+
+impl MaintenanceStatus {
+    fn of(window: Option<MaintenanceWindow>) -> Self {
+        Self {
+            active: window.is_some(),
+            window,
+        }
+    }
+}
+
+#[actix_web::get("/maintenance")]
+async fn get_maintenance(maintenance: web::Data<MaintenanceMode>) -> HttpResponse {
+    match maintenance.refresh().await {
+        Ok(window) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(MaintenanceStatus::of(window)),
+            errors: None,
+        }),
+        Err(e) => error_response(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string()),
+    }
+}
+
+/// Turn away mutating requests and pause the workers for `durationSeconds`,
+/// replacing any window in force
+#[actix_web::put("/maintenance")]
+async fn start_maintenance(
+    maintenance: web::Data<MaintenanceMode>,
+    body: Result<web::Json<StartMaintenanceRequest>, actix_web::Error>,
+) -> HttpResponse {
+    let request = match body {
+        Ok(b) => b.into_inner(),
+        Err(e) => return e.error_response(),
+    };
+
+    if request.duration_seconds == 0 || request.duration_seconds > MAX_MAINTENANCE_SECONDS {
+        return error_response(
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "1003",
+            format!("INVALID_REQUEST_BODY: durationSeconds must be between 1 and {}", MAX_MAINTENANCE_SECONDS),
+        );
+    }
+
+    let reason = request.reason.filter(|reason| !reason.trim().is_empty());
+    match maintenance.start(Duration::from_secs(request.duration_seconds), reason).await {
+        Ok(window) => {
+            tracing::warn!("Maintenance mode started until {} ({:?})", window.until, window.reason);
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(MaintenanceStatus::of(Some(window))),
+                errors: None,
+            })
+        }
+        Err(e) => error_response(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string()),
+    }
+}
+
+/// End the window before it runs out
+#[actix_web::delete("/maintenance")]
+async fn end_maintenance(maintenance: web::Data<MaintenanceMode>) -> HttpResponse {
+    match maintenance.end().await {
+        Ok(()) => {
+            tracing::info!("Maintenance mode ended");
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(MaintenanceStatus::of(None)),
+                errors: None,
+            })
+        }
+        Err(e) => error_response(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string()),
+    }
+}
@@ -0,0 +1,16 @@
+pub mod admin_auth;
+pub mod failed_jobs_controller;
+pub mod backfill_controller;
+pub mod reviews_controller;
+pub mod log_level_controller;
+pub mod stats_controller;
+pub mod jwt_keys_controller;
+pub mod webhook_deliveries_controller;
+pub mod submissions_controller;
+pub mod storage_controller;
+pub mod document_access_controller;
+pub mod job_executions_controller;
+pub mod archived_jobs_controller;
+pub mod exports_controller;
+pub mod maintenance_controller;
+pub mod view_as_controller;
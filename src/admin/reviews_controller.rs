@@ -0,0 +1,239 @@
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    admin::admin_auth::{AdminConfig, ADMIN_USER_KEY_HEADER},
+    commons::{app_error::{error_response, AppError}, crypto::FieldCipher},
+    models::user::ApiResponse,
+    notifier::dispatcher::NotificationDispatcher,
+    services::webhook_service::{WebhookService, WEBHOOK_EVENT_REVIEW_APPROVED, WEBHOOK_EVENT_REVIEW_REJECTED},
+    submissions::{
+        submission_event_repository::{reviewer_actor, SubmissionEventRepository, EVENT_STATUS_CHANGED},
+        submission_repository::SubmissionRepository,
+        submission_review_repository::{
+            SubmissionReview, SubmissionReviewRepository, REVIEW_STATUS_APPROVED, REVIEW_STATUS_PENDING,
+            REVIEW_STATUS_REJECTED,
+        },
+    },
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListReviewsQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewDecisionBody {
+    pub notes: Option<String>,
+}
+
+/// What approving or rejecting a review does to its submission
+struct ReviewOutcome {
+    review_status: &'static str,
+    submission_status: &'static str,
+    result: &'static str,
+    reason_code: &'static str,
+    webhook_event: &'static str,
+}
+
+const APPROVE: ReviewOutcome = ReviewOutcome {
+    review_status: REVIEW_STATUS_APPROVED,
+    submission_status: "APPROVED",
+    result: "MANUAL_APPROVE",
+    reason_code: "MANUAL_REVIEW_APPROVED",
+    webhook_event: WEBHOOK_EVENT_REVIEW_APPROVED,
+};
+
+const REJECT: ReviewOutcome = ReviewOutcome {
+    review_status: REVIEW_STATUS_REJECTED,
+    submission_status: "REJECTED",
+    result: "MANUAL_REJECT",
+    reason_code: "MANUAL_REVIEW_REJECTED",
+    webhook_event: WEBHOOK_EVENT_REVIEW_REJECTED,
+};
+
+#[actix_web::get("/reviews")]
+async fn list_reviews(
+    pool: web::Data<sqlx::PgPool>,
+    query: web::Query<ListReviewsQuery>,
+) -> HttpResponse {
+    let repository = SubmissionReviewRepository::new(pool.as_ref().clone());
+    let status = query.status.as_deref().unwrap_or(REVIEW_STATUS_PENDING);
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match repository.find_all(Some(status), limit, offset).await {
+        Ok(reviews) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(reviews),
+            errors: None,
+        }),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+#[actix_web::post("/reviews/{id}/approve")]
+#[allow(clippy::too_many_arguments)]
+async fn approve_review(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    webhooks: web::Data<WebhookService>,
+    notifications: web::Data<NotificationDispatcher>,
+    admin_config: web::Data<AdminConfig>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: Result<web::Json<ReviewDecisionBody>, actix_web::Error>,
+) -> HttpResponse {
+    let Some(reviewer) = admin_config.authenticated_admin(&req) else {
+        return error_response(
+            actix_web::http::StatusCode::FORBIDDEN,
+            "1018",
+            format!("ADMIN_NOT_IDENTIFIED: {} is missing or unknown", ADMIN_USER_KEY_HEADER),
+        );
+    };
+    decide_review(&pool, &cipher, &webhooks, &notifications, reviewer, path.into_inner(), body, &APPROVE).await
+}
+
+#[actix_web::post("/reviews/{id}/reject")]
+#[allow(clippy::too_many_arguments)]
+async fn reject_review(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    webhooks: web::Data<WebhookService>,
+    notifications: web::Data<NotificationDispatcher>,
+    admin_config: web::Data<AdminConfig>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: Result<web::Json<ReviewDecisionBody>, actix_web::Error>,
+) -> HttpResponse {
+    let Some(reviewer) = admin_config.authenticated_admin(&req) else {
+        return error_response(
+            actix_web::http::StatusCode::FORBIDDEN,
+            "1018",
+            format!("ADMIN_NOT_IDENTIFIED: {} is missing or unknown", ADMIN_USER_KEY_HEADER),
+        );
+    };
+    decide_review(&pool, &cipher, &webhooks, &notifications, reviewer, path.into_inner(), body, &REJECT).await
+}
+
+/// Close a pending review, moving its submission to the outcome's status with
+/// an audit event in the same transaction, then notify downstream.
+/// `reviewer` is the admin whose key made the request.
+#[allow(clippy::too_many_arguments)]
+async fn decide_review(
+    pool: &sqlx::PgPool,
+    cipher: &FieldCipher,
+    webhooks: &WebhookService,
+    notifications: &NotificationDispatcher,
+    reviewer: &str,
+    id: i64,
+    body: Result<web::Json<ReviewDecisionBody>, actix_web::Error>,
+    outcome: &ReviewOutcome,
+) -> HttpResponse {
+    let body = match body {
+        Ok(b) => b.into_inner(),
+        Err(e) => return e.error_response(),
+    };
+
+    let notes = body.notes.as_deref().map(str::trim).filter(|notes| !notes.is_empty());
+
+    let review_repository = SubmissionReviewRepository::new(pool.clone());
+    let submission_repository = SubmissionRepository::new(pool.clone(), cipher.clone());
+    let event_repository = SubmissionEventRepository::new(pool.clone());
+
+    let result: Result<Result<SubmissionReview, HttpResponse>, sqlx::Error> = async {
+        // Dropping the transaction on an early return rolls it back
+        let mut tx = review_repository.begin().await?;
+
+        let review = match review_repository.lock(&mut tx, id).await? {
+            Some(review) => review,
+            None => {
+                return Ok(Err(error_response(
+                    actix_web::http::StatusCode::NOT_FOUND,
+                    "1004",
+                    "REVIEW_NOT_FOUND".to_string(),
+                )))
+            }
+        };
+
+        if review.status != REVIEW_STATUS_PENDING {
+            return Ok(Err(error_response(
+                actix_web::http::StatusCode::CONFLICT,
+                "1003",
+                format!("REVIEW_ALREADY_DECIDED: review is {}", review.status),
+            )));
+        }
+
+        review_repository
+            .record_decision(&mut tx, id, outcome.review_status, reviewer, notes)
+            .await?;
+        submission_repository
+            .update_submission_outcome(&mut tx, review.submission_id, outcome.submission_status, outcome.result, outcome.reason_code)
+            .await?;
+        event_repository
+            .append_in_tx(
+                &mut tx,
+                review.submission_id,
+                EVENT_STATUS_CHANGED,
+                &reviewer_actor(reviewer),
+                json!({
+                    "status": { "from": "MANUAL_REVIEW", "to": outcome.submission_status },
+                    "result": { "to": outcome.result },
+                    "reasonCode": { "to": outcome.reason_code },
+                    "notes": notes,
+                }),
+            )
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(Ok(review))
+    }
+    .await;
+
+    let review = match result {
+        Ok(Ok(review)) => review,
+        Ok(Err(response)) => return response,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    let decided = SubmissionReview {
+        status: outcome.review_status.to_string(),
+        reviewer: Some(reviewer.to_string()),
+        notes: notes.map(str::to_string),
+        reviewed_at: Some(chrono::Utc::now()),
+        ..review
+    };
+
+    webhooks.dispatch(
+        outcome.webhook_event,
+        json!({
+            "reviewId": decided.id,
+            "submissionId": decided.submission_id,
+            "tenantId": decided.tenant_id,
+            "submissionType": decided.submission_type,
+            "submissionStatus": outcome.submission_status,
+            "reviewer": decided.reviewer,
+            "notes": decided.notes,
+            "reviewedAt": decided.reviewed_at,
+        }),
+    );
+    notifications.notify(
+        &decided.submission_id.to_string(),
+        &decided.tenant_id,
+        &decided.submission_type,
+        outcome.submission_status,
+        outcome.reason_code,
+    );
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(decided),
+        errors: None,
+    })
+}
@@ -0,0 +1,132 @@
+use actix_web::{http::StatusCode, web, HttpResponse, ResponseError};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    commons::{app_error::{error_response, AppError}, crypto::FieldCipher, read_replica::ReadReplica},
+    models::user::ApiResponse,
+    submissions::submission_repository::SubmissionRepository,
+};
+
+/// Window reported when the query doesn't give one
+const DEFAULT_STATS_WINDOW_DAYS: i64 = 30;
+/// Longest window a single request may aggregate over
+const MAX_STATS_WINDOW_DAYS: i64 = 366;
+
+#[derive(Debug, Deserialize)]
+pub struct SubmissionStatsQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyCount {
+    pub day: NaiveDate,
+    pub status: String,
+    pub submission_type: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceMatchPassRate {
+    pub matched: i64,
+    pub passed: i64,
+    /// `passed / matched`, none when nothing was matched
+    pub pass_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionStatsResponse {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub total: i64,
+    pub by_status: BTreeMap<String, i64>,
+    pub by_type: BTreeMap<String, i64>,
+    pub by_day: Vec<DailyCount>,
+    pub decided: i64,
+    pub average_seconds_to_decision: Option<f64>,
+    pub face_match: FaceMatchPassRate,
+}
+
+/// Aggregate statistics of the submissions created in `[from, to)`, the last
+/// 30 days by default
+#[actix_web::get("/stats/submissions")]
+async fn get_submission_stats(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    read_replica: web::Data<ReadReplica>,
+    query: Result<web::Query<SubmissionStatsQuery>, actix_web::Error>,
+) -> HttpResponse {
+    let query = match query {
+        Ok(q) => q.into_inner(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_QUERY: {}", e)),
+    };
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(DEFAULT_STATS_WINDOW_DAYS));
+    if from >= to {
+        return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_QUERY: from must be before to".to_string());
+    }
+    if to - from > Duration::days(MAX_STATS_WINDOW_DAYS) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "1003",
+            format!("INVALID_QUERY: the window can't be longer than {} days", MAX_STATS_WINDOW_DAYS),
+        );
+    }
+
+    let repository = SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone())
+        .with_replica(read_replica.as_ref().clone());
+
+    let counts = match repository.count_by_day(from, to).await {
+        Ok(counts) => counts,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+    let decisions = match repository.decision_stats(from, to).await {
+        Ok(decisions) => decisions,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+    let face_match = match repository.face_match_stats(from, to).await {
+        Ok(face_match) => face_match,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    let mut by_status = BTreeMap::new();
+    let mut by_type = BTreeMap::new();
+    for count in &counts {
+        *by_status.entry(count.status.clone()).or_insert(0) += count.count;
+        *by_type.entry(count.submission_type.clone()).or_insert(0) += count.count;
+    }
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(SubmissionStatsResponse {
+            from,
+            to,
+            total: counts.iter().map(|count| count.count).sum(),
+            by_status,
+            by_type,
+            by_day: counts
+                .into_iter()
+                .map(|count| DailyCount {
+                    day: count.day,
+                    status: count.status,
+                    submission_type: count.submission_type,
+                    count: count.count,
+                })
+                .collect(),
+            decided: decisions.decided,
+            average_seconds_to_decision: decisions.average_seconds_to_decision,
+            face_match: FaceMatchPassRate {
+                matched: face_match.matched,
+                passed: face_match.passed,
+                pass_rate: (face_match.matched > 0).then(|| face_match.passed as f64 / face_match.matched as f64),
+            },
+        }),
+        errors: None,
+    })
+}
@@ -0,0 +1,94 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    commons::app_error::error_response,
+    models::user::ApiResponse,
+    workers::{queue::EnqueueResult, FileUploadJob, ObjectCopy, RedisQueue},
+};
+
+/// The latest orphan cleanup run of the worker, updated as it goes
+#[actix_web::get("/storage/orphans")]
+async fn get_orphan_cleanup_run(queue: web::Data<RedisQueue>) -> HttpResponse {
+    let mut queue = queue.as_ref().clone();
+    match queue.get_orphan_cleanup_run().await {
+        Ok(Some(run)) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(run),
+            errors: None,
+        }),
+        Ok(None) => error_response(actix_web::http::StatusCode::NOT_FOUND, "1004", "ORPHAN_CLEANUP_RUN_NOT_FOUND".to_string()),
+        Err(e) => error_response(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyObjectRequest {
+    /// Submission the object belongs to; its uploads and copies run one at a time
+    pub submission_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub copy: ObjectCopy,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyObjectResponse {
+    /// None when the same copy was already queued
+    pub job_id: Option<Uuid>,
+    pub status: String,
+}
+
+/// Queue a server-side copy or move of a stored object for the worker
+#[actix_web::post("/storage/copies")]
+async fn copy_object(
+    queue: web::Data<RedisQueue>,
+    body: Result<web::Json<CopyObjectRequest>, actix_web::Error>,
+) -> HttpResponse {
+    let request = match body {
+        Ok(b) => b.into_inner(),
+        Err(e) => return e.error_response(),
+    };
+
+    let invalid = |message: &str| {
+        error_response(
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "1003",
+            format!("INVALID_REQUEST_BODY: {}", message),
+        )
+    };
+    if request.copy.source_key.is_empty() || request.copy.destination_key.is_empty() {
+        return invalid("sourceKey and destinationKey are required");
+    }
+    if request.copy.is_in_place() {
+        return invalid("source and destination are the same object");
+    }
+
+    // Copies outside a submission are serialized per source object instead
+    let esign_id = match request.submission_id {
+        Some(submission_id) => submission_id.to_string(),
+        None => request.copy.source_key.clone(),
+    };
+    let job = FileUploadJob::copy(esign_id, request.copy, json!({ "submissionId": request.submission_id }));
+
+    let mut queue = queue.as_ref().clone();
+    let response = match queue.enqueue_job(&job).await {
+        Ok(EnqueueResult::Enqueued) => CopyObjectResponse {
+            job_id: Some(job.id),
+            status: "ENQUEUED".to_string(),
+        },
+        Ok(EnqueueResult::AlreadyEnqueued) => CopyObjectResponse {
+            job_id: None,
+            status: "ALREADY_ENQUEUED".to_string(),
+        },
+        Err(e) => return error_response(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string()),
+    };
+
+    HttpResponse::Accepted().json(ApiResponse {
+        success: true,
+        data: Some(response),
+        errors: None,
+    })
+}
@@ -0,0 +1,173 @@
+use actix_web::{http::StatusCode, web, HttpResponse, ResponseError};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::{
+    commons::{
+        app_error::{error_response, AppError},
+        crypto::{FieldCipher, PII_REQUEST_FIELDS},
+        read_replica::ReadReplica,
+    },
+    models::user::ApiResponse,
+    submissions::{
+        device_info::DeviceInfo,
+        submission_repository::{SubmissionRepository, SubmissionSearch, SubmissionSearchResult},
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SearchSubmissionsQuery {
+    pub nik: Option<String>,
+    pub email: Option<String>,
+    pub status: Option<String>,
+    pub date_from: Option<DateTime<Utc>>,
+    pub date_to: Option<DateTime<Utc>>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionSearchItem {
+    pub submission_id: Uuid,
+    pub tenant_id: String,
+    pub user_id: String,
+    pub user_email: Option<String>,
+    pub user_name: Option<String>,
+    pub user_deleted_at: Option<DateTime<Utc>>,
+    pub submission_type: String,
+    pub status: String,
+    pub result: Option<String>,
+    pub reason_code: Option<String>,
+    /// Calibrated confidence between 0 and 100
+    pub face_match_score: Option<f64>,
+    /// The score as the face match provider returned it
+    pub face_match_raw_score: Option<f64>,
+    pub face_match_provider: Option<String>,
+    /// KTP fields read from the submission's request data
+    pub ktp: Map<String, Value>,
+    /// Device the submission was created from, if the app reported it
+    pub device_info: Option<DeviceInfo>,
+    /// Risk heuristics the device trips, e.g. EMULATOR or LOCALE_MISMATCH.
+    /// Advisory: they rest on what the app reports, which nothing attests.
+    pub risk_signals: Vec<&'static str>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionSearchResponse {
+    pub submissions: Vec<SubmissionSearchItem>,
+    /// Pass as `cursor` for the next page; none on the last page
+    pub next_cursor: Option<String>,
+}
+
+impl From<SubmissionSearchResult> for SubmissionSearchItem {
+    fn from(result: SubmissionSearchResult) -> Self {
+        let ktp = PII_REQUEST_FIELDS
+            .iter()
+            .filter_map(|field| Some((field.to_string(), result.request_data.get(*field)?.clone())))
+            .collect();
+        let risk_signals = result.device_info.as_ref().map(DeviceInfo::risk_signals).unwrap_or_default();
+
+        Self {
+            submission_id: result.submission_id,
+            tenant_id: result.tenant_id,
+            user_id: result.user_id,
+            user_email: result.user_email,
+            user_name: result.user_name,
+            user_deleted_at: result.user_deleted_at,
+            submission_type: result.submission_type,
+            status: result.status,
+            result: result.result,
+            reason_code: result.reason_code,
+            face_match_score: result.face_match_score,
+            face_match_raw_score: result.face_match_raw_score,
+            face_match_provider: result.face_match_provider,
+            ktp,
+            device_info: result.device_info,
+            risk_signals,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        }
+    }
+}
+
+/// Opaque cursor of the position after a submission: `(created_at, id)`
+fn encode_cursor(created_at: DateTime<Utc>, id: i64) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}|{}", created_at.to_rfc3339_opts(SecondsFormat::Micros, true), id))
+}
+
+fn decode_cursor(cursor: &str) -> Option<(DateTime<Utc>, i64)> {
+    let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+    let (created_at, id) = decoded.split_once('|')?;
+    Some((DateTime::parse_from_rfc3339(created_at).ok()?.with_timezone(&Utc), id.parse().ok()?))
+}
+
+/// Submissions matching the given NIK, owner email, status and creation
+/// window `[date_from, date_to)`, newest first
+#[actix_web::get("/submissions")]
+async fn search_submissions(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    read_replica: web::Data<ReadReplica>,
+    query: Result<web::Query<SearchSubmissionsQuery>, actix_web::Error>,
+) -> HttpResponse {
+    let query = match query {
+        Ok(q) => q.into_inner(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_QUERY: {}", e)),
+    };
+
+    if let (Some(from), Some(to)) = (query.date_from, query.date_to) {
+        if from >= to {
+            return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_QUERY: date_from must be before date_to".to_string());
+        }
+    }
+
+    let after = match query.cursor.as_deref().filter(|cursor| !cursor.is_empty()) {
+        Some(cursor) => match decode_cursor(cursor) {
+            Some(after) => Some(after),
+            None => return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_CURSOR".to_string()),
+        },
+        None => None,
+    };
+
+    let non_empty = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let search = SubmissionSearch {
+        nik: non_empty(query.nik),
+        email: non_empty(query.email),
+        status: non_empty(query.status),
+        created_from: query.date_from,
+        created_to: query.date_to,
+        after,
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    let repository = SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone())
+        .with_replica(read_replica.as_ref().clone());
+
+    // One extra row tells whether another page follows
+    let mut results = match repository.search(&search, limit + 1).await {
+        Ok(results) => results,
+        Err(e) => return AppError::from(e).error_response(),
+    };
+    let next_cursor = if results.len() as i64 > limit {
+        results.truncate(limit as usize);
+        results.last().map(|last| encode_cursor(last.created_at, last.id))
+    } else {
+        None
+    };
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(SubmissionSearchResponse {
+            submissions: results.into_iter().map(SubmissionSearchItem::from).collect(),
+            next_cursor,
+        }),
+        errors: None,
+    })
+}
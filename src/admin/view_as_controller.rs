@@ -0,0 +1,148 @@
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::admin_auth::AdminConfig,
+    commons::{
+        app_error::{error_response, AppError}, client_ip::client_ip, crypto::FieldCipher, minio_service::MinioService, read_replica::ReadReplica,
+        url_expiry::UrlExpiryConfig,
+    },
+    models::user::{ApiResponse, User},
+    policies::policy_repository::PolicyRepository,
+    repositories::{user_repository::UserRepository, view_as_log_repository::ViewAsLogRepository},
+    services::metrics_service::MetricsService,
+    submissions::{
+        dto::user_submission_view::UserSubmissionView,
+        submission_event_repository::SubmissionEventRepository,
+        submission_repository::SubmissionRepository,
+        submission_review_repository::SubmissionReviewRepository,
+        submission_service::SubmissionService,
+        submission_ttl::SubmissionTtlPolicy,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ViewAsQuery {
+    /// Why the account is viewed, e.g. the support ticket
+    pub reason: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewAsLogQuery {
+    pub user_id: Option<i32>,
+    pub admin: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// What the user sees of their account: the profile `GET /v1/me` returns and
+/// their submissions as the status endpoint reports them
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewAsResponse {
+    pub profile: User,
+    pub submissions: Vec<UserSubmissionView>,
+}
+
+/// A user's account as they see it, read-only, for the admin whose
+/// `ADMIN_VIEW_AS_KEYS` key the request carries. Each view is recorded in the
+/// view-as log and the audit trail of the submissions shown before anything
+/// is returned.
+#[actix_web::get("/users/{user_id}/view-as")]
+#[allow(clippy::too_many_arguments)]
+async fn view_as_user(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    read_replica: web::Data<ReadReplica>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    ttl_policy: web::Data<SubmissionTtlPolicy>,
+    admin_config: web::Data<AdminConfig>,
+    path: web::Path<i32>,
+    query: web::Query<ViewAsQuery>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+    let ip_address = client_ip(&req);
+
+    let Some(admin) = admin_config.view_as_admin(&req) else {
+        return error_response(StatusCode::FORBIDDEN, "1018", "VIEW_AS_NOT_PERMITTED".to_string());
+    };
+    let Some(reason) = query.reason.as_deref().map(str::trim).filter(|reason| !reason.is_empty()) else {
+        return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_REQUEST: reason is required".to_string());
+    };
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let profile = match UserRepository::new(pool.as_ref().clone()).find_by_id(user_id).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "1004", "USER_NOT_FOUND".to_string()),
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    let submission_service = SubmissionService::new(
+        minio_service.as_ref().clone(),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
+        metrics.as_ref().clone(),
+    )
+    .with_read_replica(read_replica.as_ref().clone())
+    .with_url_expiry(url_expiry.as_ref().clone())
+    .with_ttl_policy(ttl_policy.as_ref().clone());
+
+    let submissions = match submission_service.submissions_as_seen_by_owner(&user_id.to_string(), limit, offset).await {
+        Ok(submissions) => submissions,
+        Err(e) => return e.error_response(),
+    };
+
+    // Nothing is shown unless the view was recorded
+    let submission_ids: Vec<_> = submissions.iter().map(|submission| submission.submission_id).collect();
+    if let Err(e) = ViewAsLogRepository::new(pool.as_ref().clone())
+        .record(user_id, admin, reason, ip_address.as_deref(), &submission_ids)
+        .await
+    {
+        log::error!("Failed to record {} viewing user {}: {}", admin, user_id, e);
+        return AppError::from(e).error_response();
+    }
+    log::info!("Admin {} viewed user {} as the user ({})", admin, user_id, reason);
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(ViewAsResponse { profile, submissions }),
+        errors: None,
+    })
+}
+
+/// Who viewed which accounts as their owner and why, newest first
+#[actix_web::get("/view-as-log")]
+async fn list_view_as_log(
+    pool: web::Data<sqlx::PgPool>,
+    query: Result<web::Query<ViewAsLogQuery>, actix_web::Error>,
+) -> HttpResponse {
+    let query = match query {
+        Ok(q) => q.into_inner(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_QUERY: {}", e)),
+    };
+
+    let admin = query.admin.as_deref().map(str::trim).filter(|admin| !admin.is_empty());
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match ViewAsLogRepository::new(pool.as_ref().clone())
+        .find_all(query.user_id, admin, limit, offset)
+        .await
+    {
+        Ok(entries) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(entries),
+            errors: None,
+        }),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
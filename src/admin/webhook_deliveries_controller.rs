@@ -0,0 +1,150 @@
+use actix_web::{web, HttpResponse, ResponseError};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    commons::app_error::{error_response, AppError},
+    models::user::ApiResponse,
+    services::{
+        webhook_delivery_repository::{WebhookDelivery, WebhookDeliveryRepository},
+        webhook_service::{WebhookError, WebhookService, WEBHOOK_EVENT_TEST},
+    },
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListWebhookDeliveriesQuery {
+    pub endpoint: Option<String>,
+    pub status: Option<String>,
+    pub event_type: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestWebhookBody {
+    /// Event whose sample is sent, `webhook.test` by default
+    pub event_type: Option<String>,
+}
+
+#[actix_web::get("/webhook-deliveries")]
+async fn list_webhook_deliveries(
+    pool: web::Data<sqlx::PgPool>,
+    query: web::Query<ListWebhookDeliveriesQuery>,
+) -> HttpResponse {
+    let repository = WebhookDeliveryRepository::new(pool.as_ref().clone());
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match repository
+        .find_all(query.endpoint.as_deref(), query.status.as_deref(), query.event_type.as_deref(), limit, offset)
+        .await
+    {
+        Ok(deliveries) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(deliveries),
+            errors: None,
+        }),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+#[actix_web::get("/webhook-deliveries/{id}")]
+async fn get_webhook_delivery(
+    pool: web::Data<sqlx::PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let repository = WebhookDeliveryRepository::new(pool.as_ref().clone());
+
+    match repository.find_by_id(path.into_inner()).await {
+        Ok(Some(delivery)) => HttpResponse::Ok().json(ApiResponse::<WebhookDelivery> {
+            success: true,
+            data: Some(delivery),
+            errors: None,
+        }),
+        Ok(None) => error_response(actix_web::http::StatusCode::NOT_FOUND, "1004", "WEBHOOK_DELIVERY_NOT_FOUND".to_string()),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+fn webhook_error_response(error: WebhookError) -> HttpResponse {
+    match error {
+        WebhookError::UnknownEndpoint(_) => {
+            error_response(actix_web::http::StatusCode::NOT_FOUND, "1004", error.to_string())
+        }
+        WebhookError::UnknownEventType(_) => {
+            error_response(actix_web::http::StatusCode::BAD_REQUEST, "1003", error.to_string())
+        }
+        WebhookError::Database(e) => AppError::from(e).error_response(),
+    }
+}
+
+/// The delivery as recorded after a test or replay attempt
+async fn delivery_response(repository: &WebhookDeliveryRepository, id: Uuid) -> HttpResponse {
+    match repository.find_by_id(id).await {
+        Ok(Some(delivery)) => HttpResponse::Ok().json(ApiResponse::<WebhookDelivery> {
+            success: true,
+            data: Some(delivery),
+            errors: None,
+        }),
+        Ok(None) => error_response(actix_web::http::StatusCode::NOT_FOUND, "1004", "WEBHOOK_DELIVERY_NOT_FOUND".to_string()),
+        Err(e) => AppError::from(e).error_response(),
+    }
+}
+
+/// Send a signed sample event to a configured endpoint once and return the
+/// recorded delivery, whether or not the endpoint accepted it
+#[actix_web::post("/webhooks/{endpoint}/test")]
+async fn test_webhook(
+    pool: web::Data<sqlx::PgPool>,
+    webhooks: web::Data<WebhookService>,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> HttpResponse {
+    // The body is optional
+    let body = if body.is_empty() {
+        TestWebhookBody::default()
+    } else {
+        match serde_json::from_slice::<TestWebhookBody>(&body) {
+            Ok(body) => body,
+            Err(e) => {
+                return error_response(
+                    actix_web::http::StatusCode::BAD_REQUEST,
+                    "1003",
+                    format!("INVALID_REQUEST_BODY: {}", e),
+                )
+            }
+        }
+    };
+    let event_type = body.event_type.as_deref().unwrap_or(WEBHOOK_EVENT_TEST);
+
+    match webhooks.send_test(&path.into_inner(), event_type).await {
+        Ok(id) => delivery_response(&WebhookDeliveryRepository::new(pool.as_ref().clone()), id).await,
+        Err(e) => webhook_error_response(e),
+    }
+}
+
+/// Send a recorded delivery again, once, and return it with the new attempt
+#[actix_web::post("/webhooks/deliveries/{id}/replay")]
+async fn replay_webhook_delivery(
+    pool: web::Data<sqlx::PgPool>,
+    webhooks: web::Data<WebhookService>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let repository = WebhookDeliveryRepository::new(pool.as_ref().clone());
+    let id = path.into_inner();
+
+    let delivery = match repository.find_by_id(id).await {
+        Ok(Some(delivery)) => delivery,
+        Ok(None) => {
+            return error_response(actix_web::http::StatusCode::NOT_FOUND, "1004", "WEBHOOK_DELIVERY_NOT_FOUND".to_string())
+        }
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    match webhooks.redeliver(&delivery).await {
+        Ok(()) => delivery_response(&repository, id).await,
+        Err(e) => webhook_error_response(e),
+    }
+}
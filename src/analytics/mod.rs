@@ -0,0 +1,223 @@
+//! Funnel analytics: one event per step a submission takes, sent to the sink
+//! set in `ANALYTICS_SINK`. Events never carry anything that identifies the
+//! user, their documents or their device; the submission is only named by a
+//! keyed hash, so a funnel can be followed without knowing whose it is.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rdkafka::{
+    config::ClientConfig,
+    producer::{FutureProducer, FutureRecord},
+    util::Timeout,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sha2::Sha256;
+use std::{fmt, sync::Arc, time::Duration};
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+use uuid::Uuid;
+
+use crate::services::metrics_service::{MetricTags, MetricsService};
+
+/// Topic used when no `ANALYTICS_KAFKA_TOPIC` is set
+pub const DEFAULT_TOPIC: &str = "analytics-events";
+
+/// How long to wait for the broker to acknowledge an event
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Steps of the submission funnel, in the order a submission reaches them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FunnelStep {
+    UrlsGenerated,
+    UploadConfirmed,
+    FaceMatchDone,
+    Decision,
+}
+
+impl fmt::Display for FunnelStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FunnelStep::UrlsGenerated => write!(f, "urls_generated"),
+            FunnelStep::UploadConfirmed => write!(f, "upload_confirmed"),
+            FunnelStep::FaceMatchDone => write!(f, "face_match_done"),
+            FunnelStep::Decision => write!(f, "decision"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsEvent {
+    pub event_id: Uuid,
+    pub step: FunnelStep,
+    pub occurred_at: DateTime<Utc>,
+    pub tenant_id: String,
+    pub submission_type: String,
+    /// Keyed hash of the submission id, the same for every event of a
+    /// submission
+    pub subject: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub properties: Map<String, Value>,
+}
+
+impl AnalyticsEvent {
+    /// Add a property; only pass values that can't identify the user
+    pub fn property(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.properties.insert(key.to_string(), value.into());
+        self
+    }
+
+    pub fn outcome(mut self, outcome: impl ToString) -> Self {
+        self.outcome = Some(outcome.to_string());
+        self
+    }
+}
+
+enum AnalyticsSink {
+    /// A counter per step, tagged with tenant, submission type and outcome
+    Statsd(MetricsService),
+    Kafka { producer: FutureProducer, topic: String },
+    /// One JSON event per line, appended
+    File { path: String, file: Mutex<File> },
+}
+
+/// Emits funnel events without holding up the request. Without a sink
+/// events are dropped.
+#[derive(Clone)]
+pub struct Analytics {
+    sink: Option<Arc<AnalyticsSink>>,
+    subject_key: Arc<Vec<u8>>,
+}
+
+impl Analytics {
+    pub fn disabled() -> Self {
+        Self {
+            sink: None,
+            subject_key: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Sink from `ANALYTICS_SINK` (`statsd`, `kafka` or `file`); disabled
+    /// when it isn't set
+    pub fn from_env(metrics: MetricsService) -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let Some(sink) = var("ANALYTICS_SINK") else {
+            return Ok(Self::disabled());
+        };
+
+        let subject_key = var("ANALYTICS_SUBJECT_KEY").context("ANALYTICS_SUBJECT_KEY must be set when ANALYTICS_SINK is")?;
+
+        let sink = match sink.as_str() {
+            "statsd" => AnalyticsSink::Statsd(metrics),
+            "kafka" => {
+                let brokers = var("KAFKA_BROKERS").context("KAFKA_BROKERS must be set when ANALYTICS_SINK=kafka")?;
+                let mut client_config = ClientConfig::new();
+                client_config
+                    .set("bootstrap.servers", &brokers)
+                    .set("message.timeout.ms", DELIVERY_TIMEOUT.as_millis().to_string());
+                for entry in var("KAFKA_PRODUCER_CONFIG").unwrap_or_default().split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                    let (key, value) = entry
+                        .split_once('=')
+                        .context("KAFKA_PRODUCER_CONFIG entries must look like <key>=<value>")?;
+                    client_config.set(key.trim(), value.trim());
+                }
+
+                AnalyticsSink::Kafka {
+                    producer: client_config.create().context("Failed to create Kafka producer")?,
+                    topic: var("ANALYTICS_KAFKA_TOPIC").unwrap_or_else(|| DEFAULT_TOPIC.to_string()),
+                }
+            }
+            "file" => {
+                let path = var("ANALYTICS_FILE_PATH").context("ANALYTICS_FILE_PATH must be set when ANALYTICS_SINK=file")?;
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .with_context(|| format!("Failed to open {}", path))?;
+
+                AnalyticsSink::File {
+                    path,
+                    file: Mutex::new(File::from_std(file)),
+                }
+            }
+            other => anyhow::bail!("ANALYTICS_SINK must be statsd, kafka or file, not {}", other),
+        };
+
+        Ok(Self {
+            sink: Some(Arc::new(sink)),
+            subject_key: Arc::new(subject_key.into_bytes()),
+        })
+    }
+
+    /// A `step` event for the submission, to fill in and pass to `emit`
+    pub fn event(&self, step: FunnelStep, tenant_id: &str, submission_type: impl ToString, submission_id: &str) -> AnalyticsEvent {
+        AnalyticsEvent {
+            event_id: Uuid::new_v4(),
+            step,
+            occurred_at: Utc::now(),
+            tenant_id: tenant_id.to_string(),
+            submission_type: submission_type.to_string(),
+            subject: self.subject(submission_id),
+            outcome: None,
+            properties: Map::new(),
+        }
+    }
+
+    /// Send the event in the background; a failed send is only logged
+    pub fn emit(&self, event: AnalyticsEvent) {
+        let Some(sink) = self.sink.clone() else {
+            return;
+        };
+
+        match sink.as_ref() {
+            AnalyticsSink::Statsd(metrics) => {
+                let mut tags = MetricTags::new().tenant(&event.tenant_id).submission_type(&event.submission_type);
+                if let Some(outcome) = &event.outcome {
+                    tags = tags.outcome(outcome);
+                }
+                metrics.increment(&format!("analytics.{}", event.step), Some(tags));
+            }
+            AnalyticsSink::Kafka { .. } | AnalyticsSink::File { .. } => {
+                tokio::spawn(async move {
+                    if let Err(e) = Self::send(&sink, &event).await {
+                        log::warn!("Failed to emit {} analytics event: {}", event.step, e);
+                    }
+                });
+            }
+        }
+    }
+
+    async fn send(sink: &AnalyticsSink, event: &AnalyticsEvent) -> Result<()> {
+        let body = serde_json::to_string(event)?;
+
+        match sink {
+            AnalyticsSink::Statsd(_) => {}
+            AnalyticsSink::Kafka { producer, topic } => {
+                producer
+                    .send(FutureRecord::to(topic).key(&event.subject).payload(&body), Timeout::After(DELIVERY_TIMEOUT))
+                    .await
+                    .map_err(|(e, _)| anyhow::anyhow!(e))?;
+            }
+            AnalyticsSink::File { path, file } => {
+                let mut file = file.lock().await;
+                file.write_all(format!("{}\n", body).as_bytes())
+                    .await
+                    .with_context(|| format!("Failed to write to {}", path))?;
+                file.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Hex HMAC-SHA256 of the submission id under `ANALYTICS_SUBJECT_KEY`
+    fn subject(&self, submission_id: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.subject_key).expect("HMAC accepts keys of any length");
+        mac.update(submission_id.as_bytes());
+        mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
@@ -0,0 +1,126 @@
+//! Typed HTTP client for the v1 submission API, for internal consumers such as
+//! the load-test harness and sibling services. Requests and responses are the
+//! same structs the API serializes, so callers never hand-write JSON.
+//!
+//! Enabled with the `client` feature.
+
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    commons::tenant::TENANT_HEADER,
+    models::user::{ApiError, ApiResponse},
+    services::face_match_service::FaceMatchResponse,
+    submissions::{
+        dto::presigned_urls_response::PresignedUrlsResponse,
+        submission_controller::{
+            FaceMatchBody, GetSubmissionStatusQuery, GetSubmissionStatusResponse, PresignedUrlsBody, SubmissionType,
+        },
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The API answered with its error envelope; `errors` is empty when the
+    /// body wasn't one
+    #[error("API answered {status}: {}", describe(.errors))]
+    Api { status: StatusCode, errors: Vec<ApiError> },
+}
+
+fn describe(errors: &[ApiError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{}: {}", error.code, error.cause))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Client for one user of the API. Cheap to clone; clones share connections.
+#[derive(Clone)]
+pub struct SubmissionsClient {
+    http: Client,
+    base_url: String,
+    token: Option<String>,
+    tenant_id: Option<String>,
+}
+
+impl SubmissionsClient {
+    /// `base_url` is the API root, e.g. `http://localhost:8080`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(Client::new(), base_url)
+    }
+
+    /// Share a `reqwest::Client` (and its connection pool) between clients
+    pub fn with_http_client(http: Client, base_url: impl Into<String>) -> Self {
+        Self {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+            tenant_id: None,
+        }
+    }
+
+    /// Bearer token sent with every request, as returned by `/v1/login`
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Tenant sent as `X-Tenant-Id`; the API's default tenant otherwise
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>) -> Self {
+        self.tenant_id = Some(tenant_id.into());
+        self
+    }
+
+    /// `POST /v1/submissions/urls`
+    pub async fn presigned_urls(&self, body: &PresignedUrlsBody) -> Result<PresignedUrlsResponse, ClientError> {
+        self.send(self.http.post(self.url("/v1/submissions/urls")).json(body)).await
+    }
+
+    /// `POST /v1/submissions/face-match`
+    pub async fn face_match(&self, body: &FaceMatchBody) -> Result<FaceMatchResponse, ClientError> {
+        self.send(self.http.post(self.url("/v1/submissions/face-match")).json(body)).await
+    }
+
+    /// `GET /v1/submissions/status`
+    pub async fn status(
+        &self,
+        submission_type: &SubmissionType,
+        nfc_identifier: &str,
+    ) -> Result<GetSubmissionStatusResponse, ClientError> {
+        let query = GetSubmissionStatusQuery {
+            submission_type: submission_type.to_string(),
+            nfc_identifier: nfc_identifier.to_string(),
+        };
+        self.send(self.http.get(self.url("/v1/submissions/status")).query(&query)).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, mut request: reqwest::RequestBuilder) -> Result<T, ClientError> {
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(tenant_id) = &self.tenant_id {
+            request = request.header(TENANT_HEADER, tenant_id);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+
+        match serde_json::from_slice::<ApiResponse<T>>(&body) {
+            Ok(ApiResponse { success: true, data: Some(data), .. }) => Ok(data),
+            Ok(ApiResponse { errors, .. }) => Err(ClientError::Api {
+                status,
+                errors: errors.unwrap_or_default(),
+            }),
+            Err(_) => Err(ClientError::Api { status, errors: Vec::new() }),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        header::{HeaderName, HeaderValue},
+        Uri,
+    },
+    middleware::Next,
+};
+
+/// Header clients send to pick a version for unversioned paths
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// Routes that exist in more than one version and may be called without a
+/// version prefix
+const NEGOTIATED_PREFIXES: &[&str] = &["/submissions"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    pub const LATEST: ApiVersion = ApiVersion::V2;
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/v1",
+            ApiVersion::V2 => "/v2",
+        }
+    }
+}
+
+impl std::str::FromStr for ApiVersion {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().trim_start_matches(['v', 'V']) {
+            "1" => Ok(ApiVersion::V1),
+            "2" => Ok(ApiVersion::V2),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Route unversioned submission paths to the version asked for in
+/// `X-API-Version`, or the latest one. Unknown versions fall through to a 404.
+pub async fn negotiate(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let path = req.path().to_string();
+    let negotiated = NEGOTIATED_PREFIXES
+        .iter()
+        .any(|prefix| path == *prefix || path.starts_with(&format!("{}/", prefix)));
+
+    if negotiated {
+        let version = match req.headers().get(API_VERSION_HEADER) {
+            None => Some(ApiVersion::LATEST),
+            Some(value) => value.to_str().ok().and_then(|v| v.parse().ok()),
+        };
+
+        if let Some(version) = version {
+            let path_and_query = match req.uri().query() {
+                Some(query) => format!("{}{}?{}", version.prefix(), path, query),
+                None => format!("{}{}", version.prefix(), path),
+            };
+            let mut parts = req.head().uri.clone().into_parts();
+            parts.path_and_query = path_and_query.parse().ok();
+
+            if let Ok(uri) = Uri::from_parts(parts) {
+                req.match_info_mut().get_mut().update(&uri);
+                req.head_mut().uri = uri;
+            }
+        }
+    }
+
+    next.call(req).await
+}
+
+/// Mark v1 submission responses as deprecated in favour of v2, with the
+/// `Sunset` date from `API_V1_SUNSET` when one is announced
+pub async fn deprecate_v1(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let deprecated = req.path().starts_with("/v1/submissions");
+    let mut response = next.call(req).await?;
+
+    if deprecated {
+        let headers = response.headers_mut();
+        headers.insert(HeaderName::from_static("deprecation"), HeaderValue::from_static("true"));
+        headers.insert(
+            HeaderName::from_static("link"),
+            HeaderValue::from_static("</v2/submissions>; rel=\"successor-version\""),
+        );
+
+        if let Some(sunset) = std::env::var("API_V1_SUNSET")
+            .ok()
+            .and_then(|sunset| HeaderValue::from_str(&sunset).ok())
+        {
+            headers.insert(HeaderName::from_static("sunset"), sunset);
+        }
+    }
+
+    Ok(response)
+}
@@ -0,0 +1,136 @@
+use actix_web::{
+    http::{header, StatusCode},
+    HttpResponse, ResponseError,
+};
+
+use crate::{
+    commons::{
+        db_pool::{DB_POOL_EXHAUSTED_CAUSE, SERVICE_BUSY_CODE, SERVICE_BUSY_RETRY_AFTER_SECONDS},
+        error_catalog::{self, Locale},
+    },
+    models::user::{ApiError, ApiResponse},
+    services::{
+        captcha_service::CAPTCHA_FAILED_CODE,
+        face_match_limiter::{RETRY_LATER_CODE, RETRY_LATER_SECONDS},
+        face_match_signing::SIGNATURE_REJECTED_CODE,
+    },
+    submissions::submission_token::SUBMISSION_TOKEN_CODE,
+};
+
+/// An error a handler or service answers with, rendered as the `ApiResponse`
+/// envelope by v1 and as a problem by v2. `cause` is a key from the error
+/// catalog, optionally followed by `": detail"`; `message` is its text in the
+/// language of the request being handled, filled in when the error is made.
+#[derive(Debug, Clone)]
+pub struct AppError {
+    pub status: StatusCode,
+    pub code: String,
+    pub cause: String,
+    pub message: String,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, code: &str, cause: impl Into<String>) -> Self {
+        let cause = cause.into();
+        Self {
+            status,
+            code: code.to_string(),
+            message: error_catalog::message(code, &cause, Locale::current()),
+            cause,
+        }
+    }
+
+    /// An error with the status its code usually has, see `status_for`
+    pub fn from_code(code: &str, cause: impl Into<String>) -> Self {
+        let cause = cause.into();
+        Self::new(status_for(code, &cause), code, cause)
+    }
+
+    /// The same error answered with another status
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Seconds a client should wait before retrying, for errors that say so
+    pub fn retry_after(&self) -> Option<u64> {
+        match self.code.as_str() {
+            RETRY_LATER_CODE => Some(RETRY_LATER_SECONDS),
+            SERVICE_BUSY_CODE => Some(SERVICE_BUSY_RETRY_AFTER_SECONDS),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.cause)
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status);
+        if let Some(seconds) = self.retry_after() {
+            response.insert_header((header::RETRY_AFTER, seconds));
+        }
+        let mut response = response.json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            errors: Some(vec![ApiError::from(self)]),
+        });
+        response.extensions_mut().insert(self.clone());
+        response
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    /// A query that waited too long for a connection is `SERVICE_BUSY`, so
+    /// clients back off and retry; any other database error is code 1002
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::PoolTimedOut => Self::from_code(SERVICE_BUSY_CODE, DB_POOL_EXHAUSTED_CAUSE),
+            error => Self::from_code("1002", error.to_string()),
+        }
+    }
+}
+
+impl From<&AppError> for ApiError {
+    fn from(error: &AppError) -> Self {
+        ApiError {
+            entity: "HACKATHON_BI_2025".to_string(),
+            code: error.code.clone(),
+            cause: error.cause.clone(),
+            message: Some(error.message.clone()),
+        }
+    }
+}
+
+/// Respond with a single error
+pub fn error_response(status: StatusCode, code: &str, cause: String) -> HttpResponse {
+    AppError::new(status, code, cause).error_response()
+}
+
+/// HTTP status an error code has unless whoever makes the error says
+/// otherwise; every v2 endpoint answers with it
+pub fn status_for(code: &str, cause: &str) -> StatusCode {
+    match code {
+        "1003" if cause == "CURRENTLY_PROCESSING" => StatusCode::CONFLICT,
+        "1003" => StatusCode::BAD_REQUEST,
+        "1004" if cause.starts_with("SUBMISSION_NOT_FOUND") => StatusCode::NOT_FOUND,
+        "1004" | "1014" => StatusCode::UNPROCESSABLE_ENTITY,
+        "1006" | SIGNATURE_REJECTED_CODE => StatusCode::BAD_GATEWAY,
+        "1007" => StatusCode::UNAUTHORIZED,
+        "1010" => StatusCode::PAYLOAD_TOO_LARGE,
+        "1013" => StatusCode::TOO_MANY_REQUESTS,
+        "1018" | SUBMISSION_TOKEN_CODE => StatusCode::FORBIDDEN,
+        CAPTCHA_FAILED_CODE if cause == "CAPTCHA_CHECK_FAILED" => StatusCode::SERVICE_UNAVAILABLE,
+        CAPTCHA_FAILED_CODE => StatusCode::FORBIDDEN,
+        "1015" | RETRY_LATER_CODE | SERVICE_BUSY_CODE => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
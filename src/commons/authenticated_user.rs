@@ -0,0 +1,120 @@
+use actix_web::{dev::Payload, http::StatusCode, web, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use futures::future::LocalBoxFuture;
+use sqlx::PgPool;
+use thiserror::Error;
+
+use crate::{
+    commons::{app_error::AppError, session_store::SessionStore},
+    repositories::user_repository::UserRepository,
+    services::key_provider::KeyProvider,
+};
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("UNAUTHORIZED")]
+    Unauthorized,
+
+    #[error("EMAIL_NOT_VERIFIED")]
+    EmailNotVerified,
+
+    #[error("SYSTEM_ERROR")]
+    System,
+}
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AuthError::EmailNotVerified => StatusCode::FORBIDDEN,
+            AuthError::System => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let code = match self {
+            AuthError::Unauthorized => "1007",
+            AuthError::EmailNotVerified => "1008",
+            AuthError::System => "1000",
+        };
+
+        AppError::new(self.status_code(), code, self.to_string()).error_response()
+    }
+}
+
+/// A caller holding a valid bearer token whose session hasn't been revoked
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user_id: i32,
+    pub session_id: String,
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = AuthError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let token = req
+            .headers()
+            .get("Authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::to_string);
+        let sessions = req.app_data::<web::Data<SessionStore>>().cloned();
+        let keys = req.app_data::<web::Data<KeyProvider>>().cloned();
+
+        Box::pin(async move {
+            let token = token.ok_or(AuthError::Unauthorized)?;
+            let sessions = sessions.ok_or(AuthError::System)?;
+            let keys = keys.ok_or(AuthError::System)?;
+
+            let claims = keys.validate(&token).map_err(|_| AuthError::Unauthorized)?;
+
+            // Fail closed: a token is only as good as its session
+            let active = sessions
+                .is_active(&claims.sid, claims.sub)
+                .await
+                .map_err(|_| AuthError::System)?;
+            if !active {
+                return Err(AuthError::Unauthorized);
+            }
+
+            Ok(AuthenticatedUser {
+                user_id: claims.sub,
+                session_id: claims.sid,
+            })
+        })
+    }
+}
+
+/// An authenticated caller whose email address has been verified
+#[derive(Debug, Clone)]
+pub struct VerifiedUser {
+    pub user_id: i32,
+}
+
+impl FromRequest for VerifiedUser {
+    type Error = AuthError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let authenticated = AuthenticatedUser::from_request(req, payload);
+        let pool = req.app_data::<web::Data<PgPool>>().cloned();
+
+        Box::pin(async move {
+            let authenticated = authenticated.await?;
+            let pool = pool.ok_or(AuthError::System)?;
+
+            let user = UserRepository::new(pool.get_ref().clone())
+                .find_by_id(authenticated.user_id)
+                .await
+                .map_err(|_| AuthError::System)?
+                .ok_or(AuthError::Unauthorized)?;
+
+            if user.email_verified_at.is_none() {
+                return Err(AuthError::EmailNotVerified);
+            }
+
+            Ok(VerifiedUser { user_id: user.id })
+        })
+    }
+}
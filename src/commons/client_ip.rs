@@ -0,0 +1,48 @@
+use std::net::IpAddr;
+
+use actix_web::{web, HttpRequest};
+use anyhow::Context;
+
+/// Proxies in front of the API whose `Forwarded` / `X-Forwarded-For` headers
+/// are believed. Anyone else could put any address in them, so requests from
+/// other peers are attributed to the peer itself.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    proxies: Vec<IpAddr>,
+}
+
+impl TrustedProxies {
+    /// `TRUSTED_PROXIES`, comma separated IP addresses; none by default
+    pub fn from_env() -> anyhow::Result<Self> {
+        let proxies = std::env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+            .map(|proxy| {
+                proxy
+                    .parse::<IpAddr>()
+                    .with_context(|| format!("TRUSTED_PROXIES entries must be IP addresses, not {}", proxy))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { proxies })
+    }
+
+    fn trusts(&self, peer: IpAddr) -> bool {
+        self.proxies.contains(&peer)
+    }
+}
+
+/// Address of the client behind a request: the peer, or the address a
+/// trusted proxy forwarded the request for
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    let peer = req.peer_addr()?.ip();
+
+    let forwarded = req
+        .app_data::<web::Data<TrustedProxies>>()
+        .filter(|proxies| proxies.trusts(peer))
+        .and_then(|_| req.connection_info().realip_remote_addr().map(str::to_string));
+
+    Some(forwarded.unwrap_or_else(|| peer.to_string()))
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Prefix of encrypted values: `enc:v1:<key id>:<base64(nonce || ciphertext)>`
+const ENVELOPE_PREFIX: &str = "enc:v1:";
+const NONCE_LENGTH: usize = 12;
+
+/// Key of the KTP NIK in `request_data`, also stored as a blind index for search
+pub const NIK_REQUEST_FIELD: &str = "nik";
+/// Keys in submission `request_data` holding personal data
+pub const PII_REQUEST_FIELDS: &[&str] = &["nik", "nfcNik", "name", "birthPlace", "birthDate", "address"];
+
+/// Where the data keys come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    /// `PII_ENCRYPTION_KEYS`, a comma separated list of `<key id>:<base64 key>`
+    Env,
+    /// Placeholder until keys are fetched from a KMS
+    Kms,
+}
+
+impl std::str::FromStr for KeySource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "env" => Ok(KeySource::Env),
+            "kms" => Ok(KeySource::Kms),
+            _ => Err(anyhow!("Unknown key source: {}", s)),
+        }
+    }
+}
+
+/// AES-256-GCM encryption of individual fields. Values carry the id of the key
+/// that encrypted them, so old keys can stay available for decryption while new
+/// writes use the active key.
+#[derive(Clone)]
+pub struct FieldCipher {
+    keys: HashMap<String, Aes256Gcm>,
+    active_key_id: String,
+    index_key: Vec<u8>,
+}
+
+impl FieldCipher {
+    pub fn new(keys: HashMap<String, [u8; 32]>, active_key_id: String, index_key: Vec<u8>) -> Result<Self> {
+        if !keys.contains_key(&active_key_id) {
+            bail!("Active key {} is not in the keyring", active_key_id);
+        }
+        if index_key.is_empty() {
+            bail!("Blind index key must not be empty");
+        }
+
+        let keys = keys
+            .into_iter()
+            .map(|(key_id, key)| (key_id, Aes256Gcm::new(&key.into())))
+            .collect();
+
+        Ok(Self {
+            keys,
+            active_key_id,
+            index_key,
+        })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let source = std::env::var("PII_KEY_SOURCE")
+            .unwrap_or_else(|_| "env".to_string())
+            .parse::<KeySource>()?;
+
+        let keys = match source {
+            KeySource::Env => {
+                let raw = std::env::var("PII_ENCRYPTION_KEYS").context("PII_ENCRYPTION_KEYS must be set")?;
+                parse_keys(&raw)?
+            }
+            KeySource::Kms => bail!("KMS key source is not available yet, use PII_KEY_SOURCE=env"),
+        };
+
+        let active_key_id = std::env::var("PII_ENCRYPTION_ACTIVE_KEY_ID").context("PII_ENCRYPTION_ACTIVE_KEY_ID must be set")?;
+        let index_key = STANDARD
+            .decode(std::env::var("PII_BLIND_INDEX_KEY").context("PII_BLIND_INDEX_KEY must be set")?)
+            .context("PII_BLIND_INDEX_KEY must be base64")?;
+
+        Self::new(keys, active_key_id, index_key)
+    }
+
+    /// Encrypt with the active key
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let cipher = &self.keys[&self.active_key_id];
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt field"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+
+        Ok(format!("{}{}:{}", ENVELOPE_PREFIX, self.active_key_id, STANDARD.encode(payload)))
+    }
+
+    /// Decrypt a value written by `encrypt`. Values without an envelope were
+    /// stored before encryption was enabled and are returned as they are.
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let Some(envelope) = value.strip_prefix(ENVELOPE_PREFIX) else {
+            return Ok(value.to_string());
+        };
+
+        let (key_id, payload) = envelope.split_once(':').ok_or_else(|| anyhow!("Malformed encrypted field"))?;
+        let cipher = self.keys.get(key_id).ok_or_else(|| anyhow!("Unknown encryption key {}", key_id))?;
+
+        let payload = STANDARD.decode(payload).context("Malformed encrypted field")?;
+        if payload.len() < NONCE_LENGTH {
+            bail!("Malformed encrypted field");
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LENGTH);
+        let nonce: [u8; NONCE_LENGTH] = nonce.try_into()?;
+
+        let plaintext = cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt field with key {}", key_id))?;
+
+        String::from_utf8(plaintext).context("Decrypted field is not UTF-8")
+    }
+
+    /// Keyed hash of a plaintext, stored next to its ciphertext for equality lookups
+    pub fn blind_index(&self, plaintext: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.index_key).expect("HMAC accepts keys of any length");
+        mac.update(plaintext.as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Encrypt the given top-level string fields of a JSON object in place
+    pub fn encrypt_fields(&self, value: &mut serde_json::Value, fields: &[&str]) -> Result<()> {
+        let Some(object) = value.as_object_mut() else {
+            return Ok(());
+        };
+
+        for field in fields {
+            if let Some(serde_json::Value::String(plaintext)) = object.get(*field) {
+                let encrypted = self.encrypt(plaintext)?;
+                object.insert(field.to_string(), serde_json::Value::String(encrypted));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decrypt the given top-level string fields of a JSON object in place
+    pub fn decrypt_fields(&self, value: &mut serde_json::Value, fields: &[&str]) -> Result<()> {
+        let Some(object) = value.as_object_mut() else {
+            return Ok(());
+        };
+
+        for field in fields {
+            if let Some(serde_json::Value::String(ciphertext)) = object.get(*field) {
+                let decrypted = self.decrypt(ciphertext)?;
+                object.insert(field.to_string(), serde_json::Value::String(decrypted));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_keys(raw: &str) -> Result<HashMap<String, [u8; 32]>> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (key_id, key) = entry
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Encryption keys must look like <key id>:<base64 key>"))?;
+            let key: [u8; 32] = STANDARD
+                .decode(key)
+                .with_context(|| format!("Key {} is not base64", key_id))?
+                .try_into()
+                .map_err(|_| anyhow!("Key {} must be 32 bytes", key_id))?;
+            Ok((key_id.to_string(), key))
+        })
+        .collect()
+}
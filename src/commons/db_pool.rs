@@ -0,0 +1,206 @@
+use std::{collections::HashMap, rc::Rc, sync::Arc, time::Duration};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Extensions, ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web,
+};
+use anyhow::{anyhow, Context};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use tracing::{info, warn};
+
+use crate::{
+    commons::app_error::AppError,
+    services::metrics_service::{MetricTags, MetricsService},
+};
+
+/// Code of requests turned away because the service is too busy to take them,
+/// e.g. because no database connection freed up in time
+pub const SERVICE_BUSY_CODE: &str = "1020";
+
+/// Cause of `SERVICE_BUSY_CODE` when no database connection freed up in time
+pub const DB_POOL_EXHAUSTED_CAUSE: &str = "SERVICE_BUSY: DB_POOL_EXHAUSTED";
+
+/// `Retry-After` sent with `SERVICE_BUSY_CODE`
+pub const SERVICE_BUSY_RETRY_AFTER_SECONDS: u64 = 2;
+
+/// How often pool saturation is reported
+const SATURATION_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Name of the shared pool in metrics
+const SHARED_POOL: &str = "shared";
+
+/// Sizing of the Postgres pools
+#[derive(Debug, Clone)]
+pub struct DbPoolConfig {
+    pub max_connections: u32,
+    /// How long a query waits for a free connection before giving up
+    pub acquire_timeout: Duration,
+    /// Connections of the dedicated pools of individual routes, keyed by
+    /// route pattern
+    pub route_pools: HashMap<String, u32>,
+    /// Whether connections add audit events to the Kafka outbox, i.e.
+    /// `KAFKA_ENABLED` is true
+    pub event_outbox: bool,
+}
+
+impl DbPoolConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let number = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<u64>().with_context(|| format!("{} must be a number", name)))
+                .transpose()
+                .map(|v| v.unwrap_or(default))
+        };
+
+        // e.g. "/v1/submissions/status=2,/v2/submissions/status=2"
+        let route_pools = std::env::var("DATABASE_ROUTE_POOLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (pattern, connections) = entry
+                    .rsplit_once('=')
+                    .ok_or_else(|| anyhow!("DATABASE_ROUTE_POOLS entries must look like <route pattern>=<connections>"))?;
+                let connections = connections
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|connections| *connections > 0)
+                    .ok_or_else(|| anyhow!("DATABASE_ROUTE_POOLS sizes must be positive numbers"))?;
+                Ok((pattern.to_string(), connections))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let max_connections = number("DATABASE_MAX_CONNECTIONS", 5)?;
+        Ok(Self {
+            max_connections: u32::try_from(max_connections).context("DATABASE_MAX_CONNECTIONS is too large")?,
+            acquire_timeout: Duration::from_millis(number("DATABASE_ACQUIRE_TIMEOUT_MILLISECONDS", 3000)?),
+            route_pools,
+            event_outbox: std::env::var("KAFKA_ENABLED").as_deref() == Ok("true"),
+        })
+    }
+
+    /// Options of a pool of `max_connections`
+    pub fn options(&self, max_connections: u32) -> PgPoolOptions {
+        let options = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .acquire_timeout(self.acquire_timeout);
+        if !self.event_outbox {
+            return options;
+        }
+
+        // Read by the trigger filling `submission_event_outbox`
+        options.after_connect(|connection, _| {
+            Box::pin(async move {
+                sqlx::query!("SET app.event_outbox = 'on'").execute(connection).await?;
+                Ok(())
+            })
+        })
+    }
+}
+
+/// The API's pools: the shared one, and the dedicated pools of routes that
+/// shouldn't compete with everything else for connections, such as status
+/// polling. A route with its own pool can't take more connections than it
+/// holds, and can't be starved by other routes.
+#[derive(Clone)]
+pub struct DbPools {
+    shared: PgPool,
+    routes: Arc<HashMap<String, PgPool>>,
+}
+
+impl DbPools {
+    /// Dedicated pools connect lazily, so an idle route holds no connections
+    pub fn new(shared: PgPool, config: &DbPoolConfig, database_url: &str) -> Result<Self, sqlx::Error> {
+        let routes = config
+            .route_pools
+            .iter()
+            .map(|(pattern, connections)| {
+                info!("Route {} has its own pool of {} connections", pattern, connections);
+                Ok((pattern.clone(), config.options(*connections).connect_lazy(database_url)?))
+            })
+            .collect::<Result<_, sqlx::Error>>()?;
+
+        Ok(Self {
+            shared,
+            routes: Arc::new(routes),
+        })
+    }
+
+    fn for_route(&self, pattern: Option<&str>) -> Option<&PgPool> {
+        pattern.and_then(|pattern| self.routes.get(pattern))
+    }
+
+    /// Report the connections in use and idle of every pool for as long as
+    /// the process runs
+    pub fn spawn_saturation_monitor(&self, metrics: MetricsService) {
+        let pools = self.clone();
+        tokio::spawn(async move {
+            loop {
+                report_saturation(&metrics, SHARED_POOL, &pools.shared);
+                for (pattern, pool) in pools.routes.iter() {
+                    report_saturation(&metrics, pattern, pool);
+                }
+                tokio::time::sleep(SATURATION_REPORT_INTERVAL).await;
+            }
+        });
+    }
+}
+
+fn report_saturation(metrics: &MetricsService, name: &str, pool: &PgPool) {
+    let max = pool.options().get_max_connections();
+    let idle = pool.num_idle() as u32;
+    let in_use = pool.size().saturating_sub(idle);
+    let tags = || Some(MetricTags::new().with("pool", name));
+
+    metrics.gauge("db_pool_max_connections", max as f64, tags());
+    metrics.gauge("db_pool_connections_idle", idle as f64, tags());
+    metrics.gauge("db_pool_connections_in_use", in_use as f64, tags());
+    metrics.gauge("db_pool_saturation", in_use as f64 / max.max(1) as f64, tags());
+}
+
+/// Middleware handing the handlers of routes with a dedicated pool that pool
+/// wherever they take `web::Data<PgPool>`
+pub async fn route_pool(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let pool = req
+        .app_data::<web::Data<DbPools>>()
+        .and_then(|pools| pools.for_route(req.match_pattern().as_deref()).cloned());
+
+    if let Some(pool) = pool {
+        let mut data = Extensions::new();
+        data.insert(web::Data::new(pool));
+        req.add_data_container(Rc::new(data));
+    }
+    next.call(req).await
+}
+
+/// Middleware logging and counting the requests answered with `SERVICE_BUSY`
+/// because no database connection freed up in time
+pub async fn report_busy(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let response = next.call(req).await?;
+    let pool_exhausted = response
+        .response()
+        .extensions()
+        .get::<AppError>()
+        .is_some_and(|error| error.cause == DB_POOL_EXHAUSTED_CAUSE);
+    if !pool_exhausted {
+        return Ok(response);
+    }
+
+    warn!("No database connection freed up in time for {}", response.request().path());
+    if let Some(metrics) = response.request().app_data::<web::Data<MetricsService>>() {
+        let tags = MetricTags::endpoint(response.request().match_pattern().as_deref().unwrap_or("unknown"));
+        metrics.increment("db_pool_exhausted", Some(tags));
+    }
+    Ok(response)
+}
@@ -0,0 +1,180 @@
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::commons::redis_connection::{RedisConnection, RedisTopology};
+
+/// A Redis lock held by one process at a time, released when dropped. Used by
+/// the upload workers per document and by the API per submission.
+pub struct DistributedLock {
+    connection_manager: RedisConnection,
+    lock_key: String,
+    lock_value: String,
+    lock_timeout: Duration,
+    /// Whether this instance holds the lock and has to release it
+    held: bool,
+}
+
+impl DistributedLock {
+    pub fn new(
+        connection_manager: RedisConnection,
+        lock_key: String,
+        lock_timeout: Duration,
+    ) -> Self {
+        // Use a random value as the lock identifier to ensure only the owner can release it
+        let lock_value = uuid::Uuid::new_v4().to_string();
+
+        Self {
+            connection_manager,
+            lock_key,
+            lock_value,
+            lock_timeout,
+            held: false,
+        }
+    }
+
+    pub async fn acquire(&mut self, retry_interval: Duration, max_wait: Duration) -> redis::RedisResult<bool> {
+        let start_time = Instant::now();
+
+        loop {
+            // Try to acquire the lock using SET NX EX (only set if key doesn't exist with expiration)
+            let options = SetOptions::default()
+                .conditional_set(ExistenceCheck::NX)
+                .with_expiration(SetExpiry::EX(self.lock_timeout.as_secs() as usize));
+
+            let acquired: bool = self.connection_manager
+                .set_options(&self.lock_key, &self.lock_value, options)
+                .await?;
+
+            if acquired {
+                self.held = true;
+                debug!("Lock acquired: {}", self.lock_key);
+                return Ok(true);
+            }
+
+            // Check if we've exceeded the maximum wait time
+            if start_time.elapsed() >= max_wait {
+                warn!("Failed to acquire lock after {:?}: {}", max_wait, self.lock_key);
+                return Ok(false);
+            }
+
+            // Wait before retrying
+            tokio::time::sleep(retry_interval).await;
+        }
+    }
+
+    pub async fn release(&mut self) -> redis::RedisResult<bool> {
+        self.held = false;
+        release_lock(self.connection_manager.clone(), &self.lock_key, &self.lock_value).await
+    }
+
+    pub async fn refresh(&mut self) -> redis::RedisResult<bool> {
+        // Only refresh if we still own the lock
+        let script = r#"
+            if redis.call('GET', KEYS[1]) == ARGV[1] then
+                return redis.call('EXPIRE', KEYS[1], ARGV[2])
+            else
+                return 0
+            end
+        "#;
+
+        let result: i32 = redis::Script::new(script)
+            .key(&self.lock_key)
+            .arg(&self.lock_value)
+            .arg(self.lock_timeout.as_secs())
+            .invoke_async(&mut self.connection_manager)
+            .await?;
+
+        let refreshed = result == 1;
+        if refreshed {
+            debug!("Lock refreshed: {}", self.lock_key);
+        } else {
+            warn!("Failed to refresh lock (possibly expired): {}", self.lock_key);
+        }
+
+        Ok(refreshed)
+    }
+}
+
+impl Drop for DistributedLock {
+    fn drop(&mut self) {
+        if !self.held {
+            return;
+        }
+
+        // Try to release the lock when the instance is dropped
+        // This is a best effort and might fail if the process is killed abruptly.
+        // Blocking on a nested runtime would panic inside a tokio worker, so the
+        // release is spawned onto the current runtime instead
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            warn!("No runtime available to release lock during drop: {}", self.lock_key);
+            return;
+        };
+
+        let connection_manager = self.connection_manager.clone();
+        let lock_key = self.lock_key.clone();
+        let lock_value = self.lock_value.clone();
+        handle.spawn(async move {
+            if let Err(e) = release_lock(connection_manager, &lock_key, &lock_value).await {
+                warn!("Failed to release lock during drop: {}: {}", lock_key, e);
+            }
+        });
+    }
+}
+
+/// Hands out `DistributedLock`s over one shared Redis connection, for code
+/// outside the worker that needs to serialize work across API instances
+#[derive(Clone)]
+pub struct LockManager {
+    connection_manager: RedisConnection,
+}
+
+impl LockManager {
+    pub async fn new(redis: &RedisTopology) -> redis::RedisResult<Self> {
+        let connection_manager = redis.connect().await?;
+
+        Ok(Self { connection_manager })
+    }
+
+    /// Take `key` for up to `ttl` without waiting; None if someone holds it
+    pub async fn try_lock(&self, key: String, ttl: Duration) -> redis::RedisResult<Option<DistributedLock>> {
+        let mut lock = DistributedLock::new(self.connection_manager.clone(), key, ttl);
+
+        if lock.acquire(Duration::ZERO, Duration::ZERO).await? {
+            Ok(Some(lock))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+async fn release_lock(
+    mut connection_manager: RedisConnection,
+    lock_key: &str,
+    lock_value: &str,
+) -> redis::RedisResult<bool> {
+    // Use a Lua script to ensure we only delete the key if it contains our lock value
+    // This prevents accidentally releasing someone else's lock if our lock expired
+    let script = r#"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            return redis.call('DEL', KEYS[1])
+        else
+            return 0
+        end
+    "#;
+
+    let result: i32 = redis::Script::new(script)
+        .key(lock_key)
+        .arg(lock_value)
+        .invoke_async(&mut connection_manager)
+        .await?;
+
+    let released = result == 1;
+    if released {
+        debug!("Lock released: {}", lock_key);
+    } else {
+        warn!("Failed to release lock (possibly expired): {}", lock_key);
+    }
+
+    Ok(released)
+}
@@ -0,0 +1,261 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderMap, HeaderValue},
+    middleware::Next,
+};
+
+use crate::commons::problem_details::PROBLEM_JSON;
+
+tokio::task_local! {
+    /// Language of the request being handled, set by `localize`
+    static REQUEST_LOCALE: Locale;
+}
+
+/// Languages error messages are available in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Id,
+}
+
+impl Locale {
+    pub const DEFAULT: Locale = Locale::En;
+
+    /// Language tag sent back in `Content-Language`
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Id => "id",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        if primary.eq_ignore_ascii_case("en") {
+            Some(Locale::En)
+        } else if primary.eq_ignore_ascii_case("id") || primary.eq_ignore_ascii_case("in") {
+            // "in" is the retired code for Indonesian some older clients still send
+            Some(Locale::Id)
+        } else {
+            None
+        }
+    }
+
+    /// The supported language the client prefers most in an `Accept-Language`
+    /// header, e.g. `id-ID,id;q=0.9,en;q=0.8`. Ties go to the one listed
+    /// first, and English is used when nothing listed is supported.
+    pub fn from_accept_language(value: &str) -> Self {
+        let mut best: Option<(Locale, f32)> = None;
+
+        for range in value.split(',') {
+            let mut parts = range.split(';');
+            let tag = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            let locale = if tag == "*" { Some(Self::DEFAULT) } else { Self::from_tag(tag) };
+            if let Some(locale) = locale.filter(|_| quality > 0.0) {
+                if best.is_none_or(|(_, best_quality)| quality > best_quality) {
+                    best = Some((locale, quality));
+                }
+            }
+        }
+
+        best.map(|(locale, _)| locale).unwrap_or(Self::DEFAULT)
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(Self::from_accept_language)
+            .unwrap_or(Self::DEFAULT)
+    }
+
+    /// Language of the request being handled, English outside of one
+    pub fn current() -> Self {
+        REQUEST_LOCALE.try_with(|locale| *locale).unwrap_or(Self::DEFAULT)
+    }
+}
+
+/// Message templates of an error cause. `{detail}` stands for whatever follows
+/// the cause key, as in `QUOTA_EXCEEDED: at most 5 KTP submissions per day`;
+/// templates only use it where the detail is a value rather than English prose.
+struct CatalogEntry {
+    cause: &'static str,
+    en: &'static str,
+    id: &'static str,
+}
+
+const fn entry(cause: &'static str, en: &'static str, id: &'static str) -> CatalogEntry {
+    CatalogEntry { cause, en, id }
+}
+
+/// Every stable cause key the API answers with. Keys never change once
+/// published; the messages can be reworded freely.
+const CATALOG: &[CatalogEntry] = &[
+    // Accounts and sessions
+    entry("INVALID_EMAIL_OR_PASSWORD", "The email or password is incorrect.", "Email atau kata sandi salah."),
+    entry("USER_ALREADY_EXISTS", "An account with this email already exists.", "Akun dengan email ini sudah terdaftar."),
+    entry("USER_NOT_FOUND", "The account was not found.", "Akun tidak ditemukan."),
+    entry("ACCOUNT_DELETED", "This account has been deleted.", "Akun ini telah dihapus."),
+    entry("EMAIL_NOT_VERIFIED", "Verify your email address before continuing.", "Verifikasi alamat email Anda sebelum melanjutkan."),
+    entry("INVALID_VERIFICATION_TOKEN", "The verification link is invalid or has expired.", "Tautan verifikasi tidak valid atau sudah kedaluwarsa."),
+    entry("EMAIL_ALREADY_VERIFIED", "This email address is already verified.", "Alamat email ini sudah terverifikasi."),
+    entry("VERIFICATION_RESEND_TOO_SOON", "A verification email was sent recently, please wait before asking for another.", "Email verifikasi baru saja dikirim, harap tunggu sebelum meminta lagi."),
+    entry("INVALID_CURRENT_PASSWORD", "The current password is incorrect.", "Kata sandi saat ini salah."),
+    entry("UNAUTHORIZED", "You need to sign in to do this.", "Anda perlu masuk untuk melakukan ini."),
+    entry("INVALID_TENANT", "The tenant is not recognized.", "Tenant tidak dikenali."),
+    entry("CAPTCHA_REQUIRED", "Complete the CAPTCHA to continue.", "Selesaikan CAPTCHA untuk melanjutkan."),
+    entry("CAPTCHA_INVALID", "The CAPTCHA was not solved, please try again.", "CAPTCHA tidak terselesaikan, silakan coba lagi."),
+    entry("CAPTCHA_CHECK_FAILED", "The CAPTCHA could not be checked, please try again shortly.", "CAPTCHA tidak dapat diperiksa, silakan coba lagi sebentar lagi."),
+    entry("SUBMISSION_TOKEN_REQUIRED", "This submission can only be changed with its submission token.", "Pengajuan ini hanya dapat diubah dengan token pengajuannya."),
+    entry("SUBMISSION_TOKEN_INVALID", "The submission token is not valid for this submission.", "Token pengajuan tidak berlaku untuk pengajuan ini."),
+    entry("SUBMISSION_TOKEN_EXPIRED", "The submission token has expired, request a new upload URL to get a new one.", "Token pengajuan telah kedaluwarsa, minta URL unggah baru untuk mendapatkan token baru."),
+    entry("SUBMISSION_TOKEN_USED", "The submission token has already been used, send the one returned by the last call.", "Token pengajuan sudah digunakan, kirim token yang dikembalikan oleh panggilan terakhir."),
+    // Requests
+    entry("INVALID_REQUEST_BODY", "The request body is invalid.", "Isi permintaan tidak valid."),
+    entry("INVALID_REQUEST", "The request is missing a required field.", "Permintaan tidak memiliki kolom yang wajib diisi."),
+    entry("INVALID_QUERY", "The query parameters are invalid.", "Parameter kueri tidak valid."),
+    entry("INVALID_CURSOR", "The page cursor is invalid.", "Kursor halaman tidak valid."),
+    entry("INVALID_UPLOAD_BODY", "The uploaded file could not be read.", "Berkas yang diunggah tidak dapat dibaca."),
+    entry("PAYLOAD_TOO_LARGE", "The request is too large.", "Permintaan terlalu besar."),
+    entry("FILE_TOO_LARGE", "The file is too large.", "Berkas terlalu besar."),
+    entry("REQUEST_TIMEOUT", "The request took too long to complete.", "Permintaan terlalu lama untuk diselesaikan."),
+    entry("QUOTA_EXCEEDED", "The daily submission limit has been reached.", "Batas pengajuan harian telah tercapai."),
+    entry("RETRY_LATER", "The service is busy, please try again shortly.", "Layanan sedang sibuk, silakan coba lagi sebentar lagi."),
+    entry("SERVICE_BUSY", "The service is busy, please try again shortly.", "Layanan sedang sibuk, silakan coba lagi sebentar lagi."),
+    entry("SERVICE_SHUTTING_DOWN", "The service is restarting, please try again shortly.", "Layanan sedang dimulai ulang, silakan coba lagi sebentar lagi."),
+    entry("DOCUMENT_STORAGE_UNAVAILABLE", "Uploaded documents can't be checked right now, please try again shortly.", "Dokumen yang diunggah tidak dapat diperiksa saat ini, silakan coba lagi sebentar lagi."),
+    entry("UNDER_MAINTENANCE", "The service is under maintenance until {detail}, please try again later.", "Layanan sedang dalam pemeliharaan hingga {detail}, silakan coba lagi nanti."),
+    // Submissions
+    entry("SUBMISSION_NOT_FOUND", "The submission was not found.", "Pengajuan tidak ditemukan."),
+    entry("SUBMISSION_ALREADY_PROCESSED", "The submission has already been processed.", "Pengajuan sudah diproses."),
+    entry("CURRENTLY_PROCESSING", "The submission is already being processed.", "Pengajuan sedang diproses."),
+    entry("SUBMISSION_NOT_APPROVED", "The submission has not been approved.", "Pengajuan belum disetujui."),
+    entry("SUBMISSION_NOT_REJECTED", "Only a rejected submission can be resubmitted.", "Hanya pengajuan yang ditolak yang dapat diajukan ulang."),
+    entry("SUBMISSION_ALREADY_RESUBMITTED", "The submission has already been resubmitted.", "Pengajuan sudah diajukan ulang."),
+    entry("RESUBMISSION_LIMIT_REACHED", "The submission cannot be resubmitted again.", "Pengajuan tidak dapat diajukan ulang lagi."),
+    entry("INVALID_SUBMISSION_TYPE", "The submission type is not supported.", "Jenis pengajuan tidak didukung."),
+    entry("INVALID_DOCUMENT_TYPE", "The document type is not supported.", "Jenis dokumen tidak didukung."),
+    entry("INVALID_CONSENT", "The consent given is invalid.", "Persetujuan yang diberikan tidak valid."),
+    entry("INVALID_DEVICE_INFO", "The device information is invalid.", "Informasi perangkat tidak valid."),
+    entry("INVALID_DRAFT", "The draft is invalid.", "Draf tidak valid."),
+    entry("DRAFT_TOO_LARGE", "The draft is too large.", "Draf terlalu besar."),
+    entry("SELFIE_DOES_NOT_EXIST", "Upload a selfie before continuing.", "Unggah swafoto sebelum melanjutkan."),
+    entry("NFC_DOES_NOT_EXIST", "Read the ID card chip before continuing.", "Baca cip KTP sebelum melanjutkan."),
+    entry("NFC_SIGNATURE_REQUIRED", "The ID card chip read must be signed.", "Hasil baca cip KTP harus ditandatangani."),
+    entry("NFC_SIGNATURE_INVALID", "The ID card chip read signature is invalid.", "Tanda tangan hasil baca cip KTP tidak valid."),
+    entry("NFC_READ_EXPIRED", "The ID card chip read has expired, please read the card again.", "Hasil baca cip KTP sudah kedaluwarsa, silakan baca ulang kartu."),
+    entry("NFC_READ_REPLAYED", "The ID card chip read has already been used.", "Hasil baca cip KTP sudah pernah digunakan."),
+    entry("NFC_NONCE_CHECK_FAILED", "The ID card chip read could not be checked.", "Hasil baca cip KTP tidak dapat diperiksa."),
+    entry("DOCUMENT_NOT_FOUND", "The document was not found.", "Dokumen tidak ditemukan."),
+    entry("OBJECT_NOT_FOUND", "The file was not found.", "Berkas tidak ditemukan."),
+    entry("DOCUMENT_SCAN_PENDING", "The {detail} document is still being checked, please try again shortly.", "Dokumen {detail} masih diperiksa, silakan coba lagi sebentar lagi."),
+    entry("URL_EXPIRED", "The link has expired.", "Tautan sudah kedaluwarsa."),
+    entry("SANDBOX_STORAGE_FULL", "The sandbox storage is full.", "Penyimpanan sandbox sudah penuh."),
+    entry("DOCUMENT_ACCESS_DENIED", "You are not allowed to access this document.", "Anda tidak diizinkan mengakses dokumen ini."),
+    entry("FACE_MATCH_SIGNATURE_REJECTED", "The face match provider rejected the request.", "Penyedia pencocokan wajah menolak permintaan."),
+    // Administration
+    entry("REVIEW_NOT_FOUND", "The review was not found.", "Tinjauan tidak ditemukan."),
+    entry("REVIEW_ALREADY_DECIDED", "The review has already been decided.", "Tinjauan sudah diputuskan."),
+    entry("INVALID_PURPOSE", "A valid access purpose is required.", "Tujuan akses yang valid wajib diisi."),
+    entry("ADMIN_NOT_IDENTIFIED", "Send your own admin key to do this.", "Kirim kunci admin Anda sendiri untuk melakukan ini."),
+    entry("VIEW_AS_NOT_PERMITTED", "You are not allowed to view accounts as their owner.", "Anda tidak diizinkan melihat akun sebagai pemiliknya."),
+    entry("FAILED_JOB_NOT_FOUND", "The failed job was not found.", "Pekerjaan gagal tidak ditemukan."),
+    entry("FAILED_JOB_ALREADY_REPLAYED", "The failed job has already been replayed.", "Pekerjaan gagal sudah diputar ulang."),
+    entry("JOB_NOT_FOUND", "The job was not found.", "Pekerjaan tidak ditemukan."),
+    entry("INVALID_JOB_PAYLOAD", "The job payload is invalid.", "Muatan pekerjaan tidak valid."),
+    entry("BACKFILL_RUN_NOT_FOUND", "The backfill run was not found.", "Proses backfill tidak ditemukan."),
+    entry("EXPORT_NOT_FOUND", "The export was not found.", "Ekspor tidak ditemukan."),
+    entry("ORPHAN_CLEANUP_RUN_NOT_FOUND", "The cleanup run was not found.", "Proses pembersihan tidak ditemukan."),
+    entry("WEBHOOK_DELIVERY_NOT_FOUND", "The webhook delivery was not found.", "Pengiriman webhook tidak ditemukan."),
+    entry("WEBHOOK_ENDPOINT_NOT_FOUND", "The webhook endpoint was not found.", "Endpoint webhook tidak ditemukan."),
+    entry("UNKNOWN_EVENT_TYPE", "Unknown event type: {detail}.", "Jenis peristiwa tidak dikenal: {detail}."),
+    entry("INVALID_LOG_LEVEL", "Invalid log level: {detail}.", "Level log tidak valid: {detail}."),
+    entry("INVALID_JWT_KEYS", "The signing keys are invalid.", "Kunci penandatanganan tidak valid."),
+];
+
+const SYSTEM_ERROR: &CatalogEntry = &entry(
+    "SYSTEM_ERROR",
+    "Something went wrong, please try again later.",
+    "Terjadi kesalahan, silakan coba lagi nanti.",
+);
+
+/// Message of an error whose cause isn't a catalog key, e.g. a database error
+/// passed through as is
+fn fallback_for_code(code: &str) -> &'static CatalogEntry {
+    const MINIO_ERROR: &CatalogEntry = &entry(
+        "MINIO_ERROR",
+        "The file storage is unavailable, please try again later.",
+        "Penyimpanan berkas tidak tersedia, silakan coba lagi nanti.",
+    );
+    const DATABASE_ERROR: &CatalogEntry = &entry(
+        "DATABASE_ERROR",
+        "The data could not be saved or read, please try again later.",
+        "Data tidak dapat disimpan atau dibaca, silakan coba lagi nanti.",
+    );
+    const PROVIDER_ERROR: &CatalogEntry = &entry(
+        "PROVIDER_ERROR",
+        "The verification provider is unavailable, please try again later.",
+        "Penyedia verifikasi tidak tersedia, silakan coba lagi nanti.",
+    );
+
+    match code {
+        "1001" => MINIO_ERROR,
+        "1002" => DATABASE_ERROR,
+        "1006" => PROVIDER_ERROR,
+        _ => SYSTEM_ERROR,
+    }
+}
+
+/// Localized message of an error, from its cause key when the catalog knows
+/// it and from its code otherwise
+pub fn message(code: &str, cause: &str, locale: Locale) -> String {
+    // Causes look like "KEY" or "KEY: detail"
+    let (key, detail) = match cause.split_once(": ") {
+        Some((key, detail)) => (key, detail),
+        None => (cause, ""),
+    };
+    let entry = match key {
+        "SYSTEM_ERROR" => SYSTEM_ERROR,
+        key => CATALOG
+            .iter()
+            .find(|entry| entry.cause == key)
+            .unwrap_or_else(|| fallback_for_code(code)),
+    };
+
+    let template = match locale {
+        Locale::En => entry.en,
+        Locale::Id => entry.id,
+    };
+    template.replace("{detail}", detail)
+}
+
+/// Make the language asked for in `Accept-Language` the one errors are worded
+/// in while the request is handled, see `Locale::current`. Causes stay the
+/// stable keys clients branch on; the `message` next to each is only meant to
+/// be shown to users.
+pub async fn localize(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let locale = Locale::from_headers(req.headers());
+    let mut response = REQUEST_LOCALE.scope(locale, next.call(req)).await?;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json") || content_type.starts_with(PROBLEM_JSON));
+    if (response.status().is_client_error() || response.status().is_server_error()) && is_json {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_LANGUAGE, HeaderValue::from_static(locale.tag()));
+    }
+
+    Ok(response)
+}
@@ -0,0 +1,55 @@
+use actix_web::{
+    http::{
+        header::{self, HeaderValue},
+        StatusCode,
+    },
+    HttpRequest, HttpResponse,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::commons::app_error::error_response;
+
+/// Weak ETag of a response body: the body as served, not byte for byte
+/// what was stored
+pub fn etag_of(body: &[u8]) -> String {
+    let digest: String = Sha256::digest(body).iter().take(16).map(|byte| format!("{:02x}", byte)).collect();
+    format!("W/\"{}\"", digest)
+}
+
+/// Whether `If-None-Match` names the tag; tags are compared weakly
+fn matches(req: &HttpRequest, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+
+    req.headers()
+        .get_all(header::IF_NONE_MATCH)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// `200` with `body` as JSON and its ETag, or an empty `304` when the client
+/// already has it. Clients polling the same resource only get a body when it
+/// changed.
+pub fn json_with_etag<T: Serialize>(req: &HttpRequest, body: &T) -> HttpResponse {
+    let bytes = match serde_json::to_vec(body) {
+        Ok(bytes) => bytes,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string()),
+    };
+    let etag = etag_of(&bytes);
+
+    let not_modified = matches(req, &etag);
+
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response.insert_header((header::CACHE_CONTROL, HeaderValue::from_static("private, no-cache")));
+    response.insert_header((header::ETAG, etag));
+
+    if not_modified {
+        return response.finish();
+    }
+    response.content_type("application/json").body(bytes)
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use actix_web::http::KeepAlive;
+
+/// Tuning of the API's HTTP server. Anything unset keeps actix's default:
+/// a worker per physical core, 5 second keep-alive and client request
+/// timeout, a backlog of 2048 and 25k connections per worker.
+#[derive(Debug, Clone, Default)]
+pub struct HttpServerConfig {
+    pub workers: Option<usize>,
+    pub keep_alive: Option<KeepAlive>,
+    /// How long a client has to send its request head
+    pub client_request_timeout: Option<Duration>,
+    /// Connections waiting to be accepted
+    pub backlog: Option<u32>,
+    /// Concurrent connections per worker
+    pub max_connections: Option<usize>,
+}
+
+impl HttpServerConfig {
+    /// `HTTP_WORKERS`, `HTTP_KEEP_ALIVE_SECONDS`,
+    /// `HTTP_CLIENT_REQUEST_TIMEOUT_MILLISECONDS` (0 turns either off),
+    /// `HTTP_BACKLOG` and `HTTP_MAX_CONNECTIONS`
+    pub fn from_env() -> anyhow::Result<Self> {
+        let number = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map_err(|_| anyhow::anyhow!("{} must be a number", name))
+        };
+        let positive = |name: &str| match number(name)? {
+            Some(0) => Err(anyhow::anyhow!("{} must be positive", name)),
+            value => Ok(value),
+        };
+
+        Ok(Self {
+            workers: positive("HTTP_WORKERS")?.map(|workers| workers as usize),
+            keep_alive: number("HTTP_KEEP_ALIVE_SECONDS")?.map(|seconds| match seconds {
+                0 => KeepAlive::Disabled,
+                seconds => KeepAlive::Timeout(Duration::from_secs(seconds)),
+            }),
+            client_request_timeout: number("HTTP_CLIENT_REQUEST_TIMEOUT_MILLISECONDS")?.map(Duration::from_millis),
+            backlog: positive("HTTP_BACKLOG")?
+                .map(|backlog| u32::try_from(backlog).map_err(|_| anyhow::anyhow!("HTTP_BACKLOG is too large")))
+                .transpose()?,
+            max_connections: positive("HTTP_MAX_CONNECTIONS")?.map(|connections| connections as usize),
+        })
+    }
+}
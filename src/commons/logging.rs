@@ -0,0 +1,205 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use rolling_file::{BasicRollingFileAppender, RollingConditionBasic};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{layer::Layered, reload, EnvFilter, Layer, Registry};
+
+/// Subscriber with the reloadable level filter applied, which the output
+/// layers are stacked on
+pub type FilteredRegistry = Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+
+pub type BoxedLayer = Box<dyn Layer<FilteredRegistry> + Send + Sync>;
+
+static LEVEL_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+static CURRENT_LEVEL: Mutex<String> = Mutex::new(String::new());
+
+/// Flushes the log file writer when dropped; taken by `flush`
+static FILE_GUARD: Mutex<Option<WorkerGuard>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Json,
+    Pretty,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogFileConfig {
+    pub directory: String,
+    pub prefix: String,
+    /// Roll over at midnight in addition to the size limit
+    pub daily: bool,
+    pub max_size_bytes: u64,
+    /// Rotated files kept next to the active one
+    pub max_files: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub level: String,
+    pub format: LogFormat,
+    pub file: Option<LogFileConfig>,
+}
+
+impl LogConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let format = match var("LOG_FORMAT").as_deref() {
+            None | Some("json") => LogFormat::Json,
+            Some("pretty") => LogFormat::Pretty,
+            Some(other) => anyhow::bail!("LOG_FORMAT must be json or pretty, got {}", other),
+        };
+
+        let file = match var("LOG_FILE_DIR") {
+            Some(directory) => Some(LogFileConfig {
+                directory,
+                prefix: var("LOG_FILE_PREFIX").unwrap_or_else(|| "hackathon-bi-2025".to_string()),
+                daily: match var("LOG_FILE_ROTATION").as_deref() {
+                    None | Some("daily") => true,
+                    Some("size") => false,
+                    Some(other) => anyhow::bail!("LOG_FILE_ROTATION must be daily or size, got {}", other),
+                },
+                max_size_bytes: var("LOG_FILE_MAX_BYTES")
+                    .map(|v| v.parse())
+                    .transpose()
+                    .map_err(|_| anyhow::anyhow!("LOG_FILE_MAX_BYTES must be a number"))?
+                    .unwrap_or(100 * 1024 * 1024),
+                max_files: var("LOG_FILE_MAX_FILES")
+                    .map(|v| v.parse())
+                    .transpose()
+                    .map_err(|_| anyhow::anyhow!("LOG_FILE_MAX_FILES must be a number"))?
+                    .unwrap_or(7),
+            }),
+            None => None,
+        };
+
+        Ok(Self {
+            level: var("RUST_LOG").unwrap_or_else(|| "info".to_string()),
+            format,
+            file,
+        })
+    }
+}
+
+/// Build the reloadable level filter and the stdout and file layers. The
+/// level can be changed later with `set_level`. Also returns what went wrong
+/// setting them up, to be logged once the subscriber is installed.
+pub fn layers(config: &LogConfig) -> (reload::Layer<EnvFilter, Registry>, Vec<BoxedLayer>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let filter = EnvFilter::try_new(&config.level).unwrap_or_else(|e| {
+        warnings.push(format!("Invalid log level {:?}, falling back to info: {}", config.level, e));
+        EnvFilter::new("info")
+    });
+    let (filter, handle) = reload::Layer::new(filter);
+    let _ = LEVEL_HANDLE.set(handle);
+    *CURRENT_LEVEL.lock().unwrap() = config.level.clone();
+
+    let mut layers: Vec<BoxedLayer> = vec![match config.format {
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().boxed(),
+        LogFormat::Pretty => tracing_subscriber::fmt::layer().pretty().boxed(),
+    }];
+
+    if let Some(file) = &config.file {
+        match file_layer(file) {
+            Ok(layer) => layers.push(layer),
+            Err(e) => warnings.push(format!("Failed to open log file in {}, logging to stdout only: {}", file.directory, e)),
+        }
+    }
+
+    (filter, layers, warnings)
+}
+
+/// Files are always JSON so they can be shipped as they are
+fn file_layer(config: &LogFileConfig) -> std::io::Result<BoxedLayer> {
+    std::fs::create_dir_all(&config.directory)?;
+
+    let mut condition = RollingConditionBasic::new().max_size(config.max_size_bytes);
+    if config.daily {
+        condition = condition.daily();
+    }
+    let path = std::path::Path::new(&config.directory).join(format!("{}.log", config.prefix));
+    let appender = BasicRollingFileAppender::new(path, condition, config.max_files)?;
+
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    *FILE_GUARD.lock().unwrap() = Some(guard);
+
+    Ok(tracing_subscriber::fmt::layer().json().with_ansi(false).with_writer(writer).boxed())
+}
+
+/// Replace the level filter, e.g. `info,sqlx=warn`
+pub fn set_level(level: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(level).map_err(|e| e.to_string())?;
+    let handle = LEVEL_HANDLE.get().ok_or("logging is not initialized")?;
+    handle.reload(filter).map_err(|e| e.to_string())?;
+
+    *CURRENT_LEVEL.lock().unwrap() = level.to_string();
+    tracing::info!("Log level changed to {}", level);
+    Ok(())
+}
+
+pub fn current_level() -> String {
+    CURRENT_LEVEL.lock().unwrap().clone()
+}
+
+/// File whose settings are read again when asked to, e.g. on SIGHUP; the
+/// working directory's `.env` is never read implicitly
+pub fn config_reload_file() -> Option<std::path::PathBuf> {
+    std::env::var("CONFIG_RELOAD_FILE").ok().filter(|v| !v.is_empty()).map(std::path::PathBuf::from)
+}
+
+/// The variables set in a `.env` style file, parsed like the `.env` read at
+/// startup
+pub fn read_config_file(path: &std::path::Path) -> Result<HashMap<String, String>, dotenvy::Error> {
+    dotenvy::from_path_iter(path)?.collect()
+}
+
+/// Re-read `RUST_LOG` from `CONFIG_RELOAD_FILE` on SIGHUP, so the level can
+/// be changed without a restart. Without that file SIGHUP isn't listened to.
+#[cfg(unix)]
+pub fn reload_on_sighup() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Some(path) = config_reload_file() else {
+        return;
+    };
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::warn!("Failed to listen for SIGHUP, log level reload is disabled: {}", e);
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            let level = match read_config_file(&path) {
+                Ok(mut values) => values.remove("RUST_LOG"),
+                Err(e) => {
+                    tracing::warn!("SIGHUP received but {} can't be read: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            match level {
+                Some(level) => {
+                    if let Err(e) = set_level(&level) {
+                        tracing::warn!("Ignoring invalid RUST_LOG {:?} from {}: {}", level, path.display(), e);
+                    }
+                }
+                None => tracing::warn!("SIGHUP received but {} has no RUST_LOG", path.display()),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn reload_on_sighup() {}
+
+/// Write out log lines still buffered for the log file
+pub fn flush() {
+    FILE_GUARD.lock().unwrap().take();
+}
@@ -0,0 +1,176 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    web,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::commons::{
+    app_error::error_response,
+    problem_details::ProblemDetails,
+    redis_connection::{RedisConnection, RedisTopology},
+};
+
+/// Redis key holding the current maintenance window; it expires with the window
+const MAINTENANCE_KEY: &str = "maintenance_mode";
+
+/// How often each process reads the flag back from Redis
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A maintenance window, shared by every API and worker instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceWindow {
+    /// Why the service is down, for operators; clients only get the end
+    pub reason: Option<String>,
+    pub started_at: DateTime<Utc>,
+    /// When the window ends on its own
+    pub until: DateTime<Utc>,
+}
+
+impl MaintenanceWindow {
+    pub fn is_active(&self) -> bool {
+        self.until > Utc::now()
+    }
+
+    /// Time left in the window, at least a second while it is active
+    pub fn remaining(&self) -> Duration {
+        (self.until - Utc::now()).to_std().unwrap_or_default().max(Duration::from_secs(1))
+    }
+}
+
+/// The maintenance flag in Redis, with a copy of it kept in memory so
+/// requests don't each ask Redis
+#[derive(Clone)]
+pub struct MaintenanceMode {
+    connection: RedisConnection,
+    current: Arc<RwLock<Option<MaintenanceWindow>>>,
+}
+
+impl MaintenanceMode {
+    pub async fn new(redis: &RedisTopology) -> redis::RedisResult<Self> {
+        Ok(Self::from_connection(redis.connect().await?))
+    }
+
+    pub fn from_connection(connection: RedisConnection) -> Self {
+        Self {
+            connection,
+            current: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// The window in force, if any. A window that ran out is over even before
+    /// its key expires.
+    pub fn current(&self) -> Option<MaintenanceWindow> {
+        self.current
+            .read()
+            .ok()
+            .and_then(|current| current.clone())
+            .filter(MaintenanceWindow::is_active)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.current().is_some()
+    }
+
+    /// Put every instance into maintenance for `duration`
+    pub async fn start(&self, duration: Duration, reason: Option<String>) -> anyhow::Result<MaintenanceWindow> {
+        let now = Utc::now();
+        let window = MaintenanceWindow {
+            reason,
+            started_at: now,
+            until: now + chrono::Duration::from_std(duration)?,
+        };
+
+        let mut connection = self.connection.clone();
+        connection
+            .set_ex::<_, _, ()>(MAINTENANCE_KEY, serde_json::to_string(&window)?, duration.as_secs().max(1))
+            .await?;
+        self.store(Some(window.clone()));
+
+        Ok(window)
+    }
+
+    /// End the window early
+    pub async fn end(&self) -> redis::RedisResult<()> {
+        let mut connection = self.connection.clone();
+        connection.del::<_, ()>(MAINTENANCE_KEY).await?;
+        self.store(None);
+        Ok(())
+    }
+
+    /// Read the flag back from Redis, returning the window in force
+    pub async fn refresh(&self) -> anyhow::Result<Option<MaintenanceWindow>> {
+        let mut connection = self.connection.clone();
+        let value: Option<String> = connection.get(MAINTENANCE_KEY).await?;
+        let window = value.map(|value| serde_json::from_str::<MaintenanceWindow>(&value)).transpose()?;
+        self.store(window);
+        Ok(self.current())
+    }
+
+    /// Follow the flag for as long as the process runs, so windows started or
+    /// ended on another instance apply here within a few seconds
+    pub fn spawn_refresh(&self) {
+        let maintenance = self.clone();
+        tokio::spawn(async move {
+            let mut active = false;
+            loop {
+                match maintenance.refresh().await {
+                    Ok(window) => {
+                        match &window {
+                            Some(window) if !active => info!("Maintenance mode is on until {}", window.until),
+                            None if active => info!("Maintenance mode is off"),
+                            _ => {}
+                        }
+                        active = window.is_some();
+                    }
+                    Err(e) => warn!("Failed to read the maintenance flag: {}", e),
+                }
+                tokio::time::sleep(REFRESH_INTERVAL).await;
+            }
+        });
+    }
+
+    fn store(&self, window: Option<MaintenanceWindow>) {
+        if let Ok(mut current) = self.current.write() {
+            *current = window;
+        }
+    }
+}
+
+/// Answer `503` with `Retry-After` to mutating requests during maintenance.
+/// Reads and the admin API still go through, so the window can be ended.
+pub async fn reject_during_maintenance(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let window = req
+        .app_data::<web::Data<MaintenanceMode>>()
+        .and_then(|maintenance| maintenance.current());
+    let mutating = !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+
+    if let (Some(window), true) = (window, mutating && !req.path().starts_with("/admin/")) {
+        let cause = format!("UNDER_MAINTENANCE: {}", window.until.to_rfc3339_opts(SecondsFormat::Secs, true));
+
+        let mut response = if req.path().starts_with("/v2/") {
+            ProblemDetails::new(StatusCode::SERVICE_UNAVAILABLE, "1021", &cause, req.path()).response()
+        } else {
+            error_response(StatusCode::SERVICE_UNAVAILABLE, "1021", cause)
+        };
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, header::HeaderValue::from(window.remaining().as_secs()));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
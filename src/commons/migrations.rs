@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use sqlx::{
+    migrate::{Migrate, MigrateError, Migrator},
+    PgPool,
+};
+
+/// The `migrations/` directory, compiled into the binary so every build
+/// carries the schema its queries were checked against
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the file changed since
+    ChecksumMismatch,
+    /// Started but never finished
+    Dirty,
+}
+
+impl std::fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationState::Applied => write!(f, "applied"),
+            MigrationState::Pending => write!(f, "pending"),
+            MigrationState::ChecksumMismatch => write!(f, "checksum mismatch"),
+            MigrationState::Dirty => write!(f, "dirty"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+    pub reversible: bool,
+}
+
+/// Compare the embedded migrations with what the database has applied
+pub async fn status(pool: &PgPool) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+
+    let dirty = conn.dirty_version().await?;
+    let applied: HashMap<i64, _> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| (m.version, m.checksum))
+        .collect();
+
+    let reversible: Vec<i64> = MIGRATOR
+        .iter()
+        .filter(|m| m.migration_type.is_down_migration())
+        .map(|m| m.version)
+        .collect();
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| {
+            let state = if dirty == Some(m.version) {
+                MigrationState::Dirty
+            } else {
+                match applied.get(&m.version) {
+                    None => MigrationState::Pending,
+                    Some(checksum) if *checksum != m.checksum => MigrationState::ChecksumMismatch,
+                    Some(_) => MigrationState::Applied,
+                }
+            };
+
+            MigrationStatus {
+                version: m.version,
+                description: m.description.to_string(),
+                state,
+                reversible: reversible.contains(&m.version),
+            }
+        })
+        .collect())
+}
+
+/// Apply pending migrations, returning the versions that were applied
+pub async fn run(pool: &PgPool) -> Result<Vec<i64>, MigrateError> {
+    let pending: Vec<i64> = status(pool)
+        .await?
+        .into_iter()
+        .filter(|m| m.state == MigrationState::Pending)
+        .map(|m| m.version)
+        .collect();
+
+    MIGRATOR.run(pool).await?;
+
+    Ok(pending)
+}
+
+/// Revert the latest applied migration. Only migrations with a `.down.sql`
+/// script can be reverted; returns the reverted version.
+pub async fn revert(pool: &PgPool) -> Result<i64, MigrateError> {
+    let latest = status(pool)
+        .await?
+        .into_iter()
+        .rev()
+        .find(|m| m.state != MigrationState::Pending)
+        .ok_or_else(|| MigrateError::Source("no migration has been applied".into()))?;
+
+    if !latest.reversible {
+        return Err(MigrateError::Source(
+            format!("migration {} ({}) has no down script", latest.version, latest.description).into(),
+        ));
+    }
+
+    MIGRATOR.undo(pool, latest.version - 1).await?;
+
+    Ok(latest.version)
+}
+
+/// `migrate [status|run|revert]`, printing a report to stdout
+pub async fn cli(pool: &PgPool, command: Option<&str>) -> Result<(), MigrateError> {
+    match command.unwrap_or("status") {
+        "status" => {
+            for m in status(pool).await? {
+                println!("{:<16} {:<20} {}", m.version, m.state.to_string(), m.description);
+            }
+        }
+        "run" => {
+            let applied = run(pool).await?;
+            if applied.is_empty() {
+                println!("Database is up to date");
+            }
+            for version in applied {
+                println!("Applied {}", version);
+            }
+        }
+        "revert" => {
+            let version = revert(pool).await?;
+            println!("Reverted {}", version);
+        }
+        other => {
+            return Err(MigrateError::Source(
+                format!("unknown migrate command {:?}, expected status, run or revert", other).into(),
+            ))
+        }
+    }
+
+    Ok(())
+}
@@ -3,14 +3,73 @@ use aws_sdk_s3::{
     Client,
     presigning::PresigningConfig,
     primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart},
 };
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::time::Duration;
 use anyhow::Result;
+use uuid::Uuid;
+
+use crate::commons::{sandbox::SandboxStorage, upload_policy::UploadPolicy};
+
+/// S3 rejects multipart parts smaller than this, except the last one
+const STREAM_PART_SIZE: usize = 5 * 1024 * 1024;
+
+const REGION: &str = "us-east-1";
+
+/// A presigned upload of one document slot. `url` takes a PUT with the
+/// policy's `Content-Type`; `form` takes a multipart POST and also enforces
+/// the size limit. Sandbox uploads have no form.
+#[derive(Debug, Clone)]
+pub struct PresignedUpload {
+    pub url: String,
+    pub form: Option<UploadForm>,
+}
+
+/// S3 POST policy upload: post `fields` followed by a `file` field to `url`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadForm {
+    pub url: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+/// An object found when listing the bucket
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub size_in_bytes: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadStreamError {
+    #[error("FILE_TOO_LARGE: limit is {limit} bytes")]
+    TooLarge { limit: u64 },
+
+    #[error("INVALID_UPLOAD_BODY: {0}")]
+    Body(String),
+
+    #[error(transparent)]
+    Storage(#[from] anyhow::Error),
+}
 
 #[derive(Clone)]
 pub struct MinioService {
     client: Client,
     bucket_name: String,
+    endpoint: String,
+    access_key: String,
+    secret_key: String,
+    /// Serve every call from memory instead of the bucket (sandbox mode)
+    memory: Option<SandboxStorage>,
 }
 
 impl MinioService {
@@ -20,10 +79,41 @@ impl MinioService {
 
         println!("Initializing MinIO service with endpoint: {}", endpoint);
         println!("Bucket name: {}", bucket_name);
-        
+
+        let client = Self::client(endpoint, access_key, secret_key);
+
+        // Test the connection by listing buckets
+        match client.list_buckets().send().await {
+            Ok(_) => println!("MinIO connection successful"),
+            Err(e) => println!("MinIO connection test failed: {:?}", e),
+        }
+
+        Ok(Self {
+            client,
+            bucket_name: bucket_name.to_string(),
+            endpoint: endpoint.to_string(),
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            memory: None,
+        })
+    }
+
+    /// A service keeping objects in `storage`. Its S3 client is never used.
+    pub fn in_memory(storage: SandboxStorage) -> Self {
+        Self {
+            client: Self::client("http://localhost", "sandbox", "sandbox"),
+            bucket_name: "sandbox".to_string(),
+            endpoint: "http://localhost".to_string(),
+            access_key: "sandbox".to_string(),
+            secret_key: "sandbox".to_string(),
+            memory: Some(storage),
+        }
+    }
+
+    fn client(endpoint: &str, access_key: &str, secret_key: &str) -> Client {
         let config = aws_sdk_s3::config::Builder::new()
             .endpoint_url(endpoint)
-            .region(Region::new("us-east-1"))
+            .region(Region::new(REGION))
             .credentials_provider(Credentials::new(
                 access_key,
                 secret_key,
@@ -35,21 +125,31 @@ impl MinioService {
             .behavior_version_latest()
             .build();
 
-        let client = Client::from_conf(config);
+        Client::from_conf(config)
+    }
 
-        // Test the connection by listing buckets
-        match client.list_buckets().send().await {
-            Ok(_) => println!("MinIO connection successful"),
-            Err(e) => println!("MinIO connection test failed: {:?}", e),
-        }
+    /// Object key of a submission document, laid out as
+    /// `{tenant}/{submission_id}/{document type}/{uuid}` so lifecycle rules,
+    /// browsing and cleanup can work per tenant or per submission by prefix
+    pub fn document_key(
+        tenant_id: &str,
+        submission_id: Uuid,
+        document_type: impl std::fmt::Display,
+        document_uuid: Uuid,
+    ) -> String {
+        format!("{}{}/{}", Self::submission_prefix(tenant_id, submission_id), document_type, document_uuid)
+    }
 
-        Ok(Self {
-            client,
-            bucket_name: bucket_name.to_string(),
-        })
+    /// Prefix shared by every document of a submission
+    pub fn submission_prefix(tenant_id: &str, submission_id: Uuid) -> String {
+        format!("{}/{}/", tenant_id, submission_id)
     }
 
     pub async fn generate_presigned_url(&self, file_name: String, expires_in: Duration) -> Result<String> {
+        if let Some(memory) = &self.memory {
+            return Ok(memory.object_url(&file_name));
+        }
+
         let object_key = format!("{}", file_name);
         let presigned_config = PresigningConfig::builder()
             .expires_in(expires_in)
@@ -67,6 +167,10 @@ impl MinioService {
     }
 
     pub async fn generate_view_url(&self, file_name: String) -> Result<String> {
+        if let Some(memory) = &self.memory {
+            return Ok(memory.object_url(&file_name));
+        }
+
         let presigned_config = PresigningConfig::builder()
             .expires_in(Duration::from_secs(3600))
             .build()?;
@@ -86,28 +190,96 @@ impl MinioService {
         Ok(url)
     }
 
-    pub async fn generate_upload_url(&self, file_name: String, expires_in: Duration) -> Result<String> {
+    /// Presigned upload of `file_name` that only accepts `policy`'s content
+    /// type and, through the form, sizes up to its limit
+    pub async fn generate_upload_url(&self, file_name: String, expires_in: Duration, policy: &UploadPolicy) -> Result<PresignedUpload> {
+        if let Some(memory) = &self.memory {
+            return Ok(PresignedUpload {
+                url: memory.object_url(&file_name),
+                form: None,
+            });
+        }
+
         let object_key = format!("{}", file_name);
         let presigned_config = PresigningConfig::builder()
             .expires_in(expires_in)
             .build()?;
 
+        // Content-Type is a signed header, so a PUT with any other type fails
         let presigned_request = self
             .client
             .put_object()
             .bucket(&self.bucket_name)
             .key(&object_key)
-            .content_type("image/jpeg")
+            .content_type(&policy.content_type)
             .presigned(presigned_config)
             .await?;
 
         // Log the generated URL for debugging
         println!("Generated presigned URL: {}", presigned_request.uri());
 
-        Ok(presigned_request.uri().to_string())
+        Ok(PresignedUpload {
+            url: presigned_request.uri().to_string(),
+            form: Some(self.upload_form(&object_key, expires_in, policy)),
+        })
+    }
+
+    /// Sign an S3 POST policy (SigV4) pinning the key, content type and
+    /// content-length-range
+    fn upload_form(&self, object_key: &str, expires_in: Duration, policy: &UploadPolicy) -> UploadForm {
+        let now = Utc::now();
+        let date = now.format("%Y%m%d").to_string();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let credential = format!("{}/{}/{}/s3/aws4_request", self.access_key, date, REGION);
+        let expiration = now + chrono::Duration::seconds(expires_in.as_secs() as i64);
+
+        let document = serde_json::json!({
+            "expiration": expiration.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            "conditions": [
+                { "bucket": self.bucket_name },
+                { "key": object_key },
+                { "Content-Type": policy.content_type },
+                ["content-length-range", 1, policy.max_size_in_bytes],
+                { "x-amz-algorithm": "AWS4-HMAC-SHA256" },
+                { "x-amz-credential": credential },
+                { "x-amz-date": amz_date },
+            ],
+        });
+        let encoded_policy = STANDARD.encode(document.to_string());
+
+        let hmac = |key: &[u8], data: &str| {
+            let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            mac.update(data.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        };
+        let date_key = hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date);
+        let region_key = hmac(&date_key, REGION);
+        let service_key = hmac(&region_key, "s3");
+        let signing_key = hmac(&service_key, "aws4_request");
+        let signature: String = hmac(&signing_key, &encoded_policy).iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        let fields = BTreeMap::from([
+            ("key".to_string(), object_key.to_string()),
+            ("Content-Type".to_string(), policy.content_type.clone()),
+            ("policy".to_string(), encoded_policy),
+            ("x-amz-algorithm".to_string(), "AWS4-HMAC-SHA256".to_string()),
+            ("x-amz-credential".to_string(), credential),
+            ("x-amz-date".to_string(), amz_date),
+            ("x-amz-signature".to_string(), signature),
+        ]);
+
+        UploadForm {
+            url: format!("{}/{}", self.endpoint, self.bucket_name),
+            fields,
+        }
     }
 
     pub async fn upload_file(&self, file_name: String, content: Vec<u8>, content_type: Option<String>) -> Result<String> {
+        if let Some(memory) = &self.memory {
+            memory.put(file_name.clone(), content, content_type)?;
+            return Ok(memory.object_url(&file_name));
+        }
+
         let object_key = format!("{}", file_name);
         let byte_stream = ByteStream::from(content);
 
@@ -131,6 +303,156 @@ impl MinioService {
         Ok(view_url)
     }
 
+    /// Stream a body of unknown length into `file_name`, holding at most one
+    /// part in memory. Bodies larger than `max_size` are rejected and nothing
+    /// is left behind in the bucket.
+    pub async fn upload_stream<S, E>(
+        &self,
+        file_name: String,
+        mut stream: S,
+        content_type: Option<String>,
+        max_size: u64,
+    ) -> std::result::Result<u64, UploadStreamError>
+    where
+        S: Stream<Item = std::result::Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let mut buffer = BytesMut::new();
+        let mut total: u64 = 0;
+
+        if let Some(memory) = &self.memory {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| UploadStreamError::Body(e.to_string()))?;
+                total += chunk.len() as u64;
+                if total > max_size {
+                    return Err(UploadStreamError::TooLarge { limit: max_size });
+                }
+                buffer.extend_from_slice(&chunk);
+            }
+            memory
+                .put(file_name, buffer.to_vec(), content_type)
+                .map_err(|e| UploadStreamError::Storage(e.into()))?;
+            return Ok(total);
+        }
+
+        let mut upload_id: Option<String> = None;
+        let mut parts = Vec::new();
+
+        let result = async {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| UploadStreamError::Body(e.to_string()))?;
+                total += chunk.len() as u64;
+                if total > max_size {
+                    return Err(UploadStreamError::TooLarge { limit: max_size });
+                }
+                buffer.extend_from_slice(&chunk);
+
+                if buffer.len() >= STREAM_PART_SIZE {
+                    let id = match &upload_id {
+                        Some(id) => id.clone(),
+                        None => {
+                            let id = self.create_multipart_upload(&file_name, content_type.clone()).await?;
+                            upload_id = Some(id.clone());
+                            id
+                        }
+                    };
+                    let part = buffer.split().freeze();
+                    parts.push(self.upload_part(&file_name, &id, parts.len() as i32 + 1, part).await?);
+                }
+            }
+
+            match &upload_id {
+                // Small enough for a single request
+                None => {
+                    let mut put_object = self
+                        .client
+                        .put_object()
+                        .bucket(&self.bucket_name)
+                        .key(&file_name)
+                        .body(ByteStream::from(buffer.split().freeze()));
+                    if let Some(ct) = content_type.clone() {
+                        put_object = put_object.content_type(ct);
+                    }
+                    put_object.send().await.map_err(anyhow::Error::from)?;
+                }
+                Some(id) => {
+                    if !buffer.is_empty() {
+                        let part = buffer.split().freeze();
+                        parts.push(self.upload_part(&file_name, id, parts.len() as i32 + 1, part).await?);
+                    }
+                    self.client
+                        .complete_multipart_upload()
+                        .bucket(&self.bucket_name)
+                        .key(&file_name)
+                        .upload_id(id)
+                        .multipart_upload(
+                            CompletedMultipartUpload::builder()
+                                .set_parts(Some(parts.clone()))
+                                .build(),
+                        )
+                        .send()
+                        .await
+                        .map_err(anyhow::Error::from)?;
+                }
+            }
+
+            Ok(total)
+        }
+        .await;
+
+        if result.is_err() {
+            if let Some(id) = &upload_id {
+                let abort = self
+                    .client
+                    .abort_multipart_upload()
+                    .bucket(&self.bucket_name)
+                    .key(&file_name)
+                    .upload_id(id)
+                    .send()
+                    .await;
+                if let Err(e) = abort {
+                    log::warn!("Failed to abort multipart upload {} for {}: {}", id, file_name, e);
+                }
+            }
+        }
+
+        result
+    }
+
+    async fn create_multipart_upload(&self, file_name: &str, content_type: Option<String>) -> Result<String> {
+        let output = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket_name)
+            .key(file_name)
+            .set_content_type(content_type)
+            .send()
+            .await?;
+
+        output
+            .upload_id()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("MinIO returned no upload id for {}", file_name))
+    }
+
+    async fn upload_part(&self, file_name: &str, upload_id: &str, part_number: i32, body: Bytes) -> Result<CompletedPart> {
+        let output = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket_name)
+            .key(file_name)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(body))
+            .send()
+            .await?;
+
+        Ok(CompletedPart::builder()
+            .set_e_tag(output.e_tag().map(str::to_string))
+            .part_number(part_number)
+            .build())
+    }
+
     pub async fn upload_file_with_metadata(
         &self, 
         file_name: String, 
@@ -138,6 +460,10 @@ impl MinioService {
         content_type: Option<String>,
         metadata: std::collections::HashMap<String, String>
     ) -> Result<String> {
+        if self.memory.is_some() {
+            return self.upload_file(file_name, content, content_type).await;
+        }
+
         let object_key = format!("{}", file_name);
         let byte_stream = ByteStream::from(content);
 
@@ -166,7 +492,116 @@ impl MinioService {
         Ok(view_url)
     }
 
+    /// Copy an object within the bucket, keeping its content type
+    pub async fn copy_file(&self, source: &str, destination: String) -> Result<()> {
+        self.copy_object(None, source, None, &destination).await
+    }
+
+    /// Copy an object server-side, possibly between buckets the credentials
+    /// can reach. A `None` bucket is this service's; in-memory storage has
+    /// a single bucket and ignores them.
+    pub async fn copy_object(
+        &self,
+        source_bucket: Option<&str>,
+        source: &str,
+        destination_bucket: Option<&str>,
+        destination: &str,
+    ) -> Result<()> {
+        if let Some(memory) = &self.memory {
+            let object = memory
+                .get(source)
+                .ok_or_else(|| anyhow::anyhow!("No sandbox object {}", source))?;
+            memory.put(destination.to_string(), object.content, object.content_type)?;
+            return Ok(());
+        }
+
+        self.client
+            .copy_object()
+            .bucket(destination_bucket.unwrap_or(&self.bucket_name))
+            .copy_source(format!("{}/{}", source_bucket.unwrap_or(&self.bucket_name), source))
+            .key(destination)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// ETag of an object, or None when it doesn't exist. Unlike
+    /// `file_exists`, failing to ask is an error rather than a missing object.
+    pub async fn object_etag(&self, bucket: Option<&str>, key: &str) -> Result<Option<String>> {
+        if let Some(memory) = &self.memory {
+            return Ok(memory.get(key).map(|object| format!("{:x}", Sha256::digest(&object.content))));
+        }
+
+        match self
+            .client
+            .head_object()
+            .bucket(bucket.unwrap_or(&self.bucket_name))
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(head) => Ok(Some(head.e_tag().unwrap_or_default().to_string())),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Size of an object in bytes, or None when it doesn't exist
+    pub async fn object_size(&self, key: &str) -> Result<Option<u64>> {
+        if let Some(memory) = &self.memory {
+            return Ok(memory.get(key).map(|object| object.content.len() as u64));
+        }
+
+        match self.client.head_object().bucket(&self.bucket_name).key(key).send().await {
+            Ok(head) => Ok(Some(head.content_length().unwrap_or_default().max(0) as u64)),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Delete an object of any bucket the credentials can reach
+    pub async fn delete_object(&self, bucket: Option<&str>, key: &str) -> Result<()> {
+        if let Some(memory) = &self.memory {
+            memory.delete(key);
+            return Ok(());
+        }
+
+        self.client
+            .delete_object()
+            .bucket(bucket.unwrap_or(&self.bucket_name))
+            .key(key)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn download_file(&self, file_name: String) -> Result<Vec<u8>> {
+        if let Some(memory) = &self.memory {
+            return memory
+                .get(&file_name)
+                .map(|object| object.content)
+                .ok_or_else(|| anyhow::anyhow!("No sandbox object {}", file_name));
+        }
+
+        let object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(&file_name)
+            .send()
+            .await?;
+
+        Ok(object.body.collect().await?.into_bytes().to_vec())
+    }
+
     pub async fn delete_file(&self, file_name: String) -> Result<()> {
+        if let Some(memory) = &self.memory {
+            memory.delete(&file_name);
+            return Ok(());
+        }
+
         let object_key = format!("{}", file_name);
         
         self
@@ -180,7 +615,87 @@ impl MinioService {
         Ok(())
     }
 
+    /// Delete every object under `prefix`, returning how many were deleted
+    pub async fn delete_prefix(&self, prefix: &str) -> Result<usize> {
+        if let Some(memory) = &self.memory {
+            return Ok(memory.delete_prefix(prefix));
+        }
+
+        let mut deleted = 0;
+        let mut continuation_token = None;
+        loop {
+            let page = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket_name)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await?;
+
+            for object in page.contents() {
+                if let Some(key) = object.key() {
+                    self.client
+                        .delete_object()
+                        .bucket(&self.bucket_name)
+                        .key(key)
+                        .send()
+                        .await?;
+                    deleted += 1;
+                }
+            }
+
+            match page.next_continuation_token() {
+                Some(token) if page.is_truncated().unwrap_or(false) => continuation_token = Some(token.to_string()),
+                _ => break,
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// One page of the bucket's objects, with the token of the next page if
+    /// there is one. In-memory storage is never listed.
+    pub async fn list_objects(&self, continuation_token: Option<String>) -> Result<(Vec<StoredObject>, Option<String>)> {
+        if self.memory.is_some() {
+            return Ok((Vec::new(), None));
+        }
+
+        let page = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket_name)
+            .set_continuation_token(continuation_token)
+            .send()
+            .await?;
+
+        let objects = page
+            .contents()
+            .iter()
+            .filter_map(|object| {
+                Some(StoredObject {
+                    key: object.key()?.to_string(),
+                    last_modified: object
+                        .last_modified()
+                        .and_then(|at| DateTime::from_timestamp(at.secs(), at.subsec_nanos())),
+                    size_in_bytes: object.size().unwrap_or_default(),
+                })
+            })
+            .collect();
+
+        let next = match page.next_continuation_token() {
+            Some(token) if page.is_truncated().unwrap_or(false) => Some(token.to_string()),
+            _ => None,
+        };
+
+        Ok((objects, next))
+    }
+
     pub async fn file_exists(&self, file_name: String) -> Result<bool> {
+        if let Some(memory) = &self.memory {
+            return Ok(memory.contains(&file_name));
+        }
+
         let object_key = format!("{}", file_name);
         
         match self
@@ -1 +1,28 @@
-pub mod minio_service; 
+pub mod authenticated_user;
+pub mod minio_service;
+pub mod tenant;
+pub mod telemetry;
+pub mod crypto;
+pub mod request_limits;
+pub mod session_store;
+pub mod logging;
+pub mod migrations;
+pub mod problem_details;
+pub mod api_version;
+pub mod distributed_lock;
+pub mod redis_connection;
+pub mod sandbox;
+pub mod pdf;
+pub mod shutdown;
+pub mod url_expiry;
+pub mod upload_policy;
+pub mod tls;
+pub mod read_replica;
+pub mod app_error;
+pub mod error_catalog;
+pub mod db_pool;
+pub mod maintenance;
+pub mod queue_backpressure;
+pub mod etag;
+pub mod http_server;
+pub mod client_ip;
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use lopdf::{
+    content::{Content, Operation},
+    dictionary, Document, Object, Stream, StringFormat,
+};
+
+/// A4 in PDF points
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+
+/// A JPEG embedded as is; PDF readers decode it themselves
+pub struct JpegImage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(&self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+/// A page of text and images, positioned in points from the bottom left
+#[derive(Default)]
+pub struct PdfPage {
+    operations: Vec<Operation>,
+    images: Vec<JpegImage>,
+}
+
+impl PdfPage {
+    pub fn text(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        self.operations.extend([
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec![font.resource().into(), size.into()]),
+            Operation::new("Td", vec![x.into(), y.into()]),
+            Operation::new("Tj", vec![Object::String(win_ansi(text), StringFormat::Literal)]),
+            Operation::new("ET", vec![]),
+        ]);
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.operations.extend([
+            Operation::new("w", vec![0.5.into()]),
+            Operation::new("m", vec![x1.into(), y1.into()]),
+            Operation::new("l", vec![x2.into(), y2.into()]),
+            Operation::new("S", vec![]),
+        ]);
+    }
+
+    /// Draw `image` scaled into a `width` x `height` box
+    pub fn image(&mut self, x: f32, y: f32, width: f32, height: f32, image: JpegImage) {
+        self.images.push(image);
+        self.operations.extend([
+            Operation::new("q", vec![]),
+            Operation::new("cm", vec![width.into(), 0.into(), 0.into(), height.into(), x.into(), y.into()]),
+            Operation::new("Do", vec![format!("Im{}", self.images.len()).into()]),
+            Operation::new("Q", vec![]),
+        ]);
+    }
+}
+
+/// Reports built with `lopdf`: Helvetica text, lines and JPEG images. Text
+/// outside Latin-1 is replaced with `?`.
+#[derive(Default)]
+pub struct PdfDocument {
+    pages: Vec<PdfPage>,
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_page(&mut self, page: PdfPage) {
+        self.pages.push(page);
+    }
+
+    pub fn render(self) -> Result<Vec<u8>> {
+        let mut document = Document::with_version("1.4");
+        let pages_id = document.new_object_id();
+        let font = |base_font: &str| {
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => base_font,
+                "Encoding" => "WinAnsiEncoding",
+            }
+        };
+        let fonts = dictionary! {
+            "F1" => document.add_object(font("Helvetica")),
+            "F2" => document.add_object(font("Helvetica-Bold")),
+        };
+
+        let mut kids = Vec::with_capacity(self.pages.len());
+        for page in self.pages {
+            let mut x_objects = lopdf::Dictionary::new();
+            for (i, image) in page.images.into_iter().enumerate() {
+                let dictionary = dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => image.width,
+                    "Height" => image.height,
+                    "ColorSpace" => "DeviceRGB",
+                    "BitsPerComponent" => 8,
+                    "Filter" => "DCTDecode",
+                };
+                let image_id = document.add_object(Stream::new(dictionary, image.data));
+                x_objects.set(format!("Im{}", i + 1), image_id);
+            }
+
+            let content = Content { operations: page.operations }.encode()?;
+            let content_id = document.add_object(Stream::new(dictionary! {}, content));
+            let page_id = document.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+                "Contents" => content_id,
+                "Resources" => dictionary! {
+                    "Font" => fonts.clone(),
+                    "XObject" => x_objects,
+                },
+            });
+            kids.push(page_id.into());
+        }
+
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+
+        let mut pdf = Vec::new();
+        document.save_to(&mut pdf)?;
+        Ok(pdf)
+    }
+}
+
+/// Text as WinAnsi bytes, the encoding of the standard fonts
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
@@ -0,0 +1,92 @@
+use actix_web::{
+    http::{header, StatusCode},
+    HttpRequest, HttpResponse,
+};
+use serde::Serialize;
+
+use crate::{
+    commons::{
+        app_error::AppError,
+        db_pool::{SERVICE_BUSY_CODE, SERVICE_BUSY_RETRY_AFTER_SECONDS},
+        error_catalog::{self, Locale},
+    },
+    services::face_match_limiter::{RETRY_LATER_CODE, RETRY_LATER_SECONDS},
+};
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// RFC 7807 error body used by the v2 API in place of the `ApiResponse`
+/// envelope. `code` keeps the numeric error code clients already know.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub instance: String,
+    pub code: String,
+    /// The cause worded for users, in the language the request asked for
+    pub message: String,
+    /// Further errors when the service reported more than one
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ProblemDetails>,
+}
+
+impl ProblemDetails {
+    pub fn new(status: StatusCode, code: &str, cause: &str, instance: &str) -> Self {
+        // Causes look like "TITLE" or "TITLE: detail"
+        let (title, detail) = match cause.split_once(": ") {
+            Some((title, detail)) => (title, detail),
+            None => (cause, cause),
+        };
+
+        Self {
+            problem_type: format!("urn:hackathon-bi-2025:error:{}", code),
+            title: title.to_string(),
+            status: status.as_u16(),
+            detail: detail.to_string(),
+            instance: instance.to_string(),
+            code: code.to_string(),
+            message: error_catalog::message(code, cause, Locale::current()),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn from_error(error: &AppError, instance: &str) -> Self {
+        Self {
+            message: error.message.clone(),
+            ..Self::new(error.status, &error.code, &error.cause, instance)
+        }
+    }
+
+    pub fn response(&self) -> HttpResponse {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = HttpResponse::build(status);
+        if self.code == RETRY_LATER_CODE {
+            response.insert_header((header::RETRY_AFTER, RETRY_LATER_SECONDS));
+        }
+        if self.code == SERVICE_BUSY_CODE {
+            response.insert_header((header::RETRY_AFTER, SERVICE_BUSY_RETRY_AFTER_SECONDS));
+        }
+        response.content_type(PROBLEM_JSON).json(self)
+    }
+}
+
+/// Respond with the problem for the error of a request
+pub fn problem_response(req: &HttpRequest, error: &AppError) -> HttpResponse {
+    let mut response = ProblemDetails::from_error(error, req.path()).response();
+    response.extensions_mut().insert(error.clone());
+    response
+}
+
+/// Respond to a body that couldn't be read or deserialized
+pub fn body_problem_response(req: &HttpRequest, error: actix_web::Error) -> HttpResponse {
+    let problem = if error.as_response_error().status_code() == StatusCode::PAYLOAD_TOO_LARGE {
+        ProblemDetails::new(StatusCode::PAYLOAD_TOO_LARGE, "1011", &format!("PAYLOAD_TOO_LARGE: {}", error), req.path())
+    } else {
+        ProblemDetails::new(StatusCode::BAD_REQUEST, "1003", &format!("INVALID_REQUEST_BODY: {}", error), req.path())
+    };
+    problem.response()
+}
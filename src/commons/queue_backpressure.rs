@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use tracing::{info, warn};
+
+use crate::{
+    services::metrics_service::{MetricTags, MetricsService},
+    workers::{RedisQueue, WorkerResult},
+};
+
+/// How often the upload queue depth is read back from Redis by default
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A depth older than this many refresh intervals is no longer trusted
+const STALE_AFTER_INTERVALS: u32 = 3;
+
+/// What happens to new work once the queue is at least `depth` deep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TierAction {
+    Delay(Duration),
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tier {
+    pub depth: u64,
+    pub action: TierAction,
+}
+
+/// Whether new work may be enqueued at the current depth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Accept,
+    /// Accept after holding the request this long
+    Delay(Duration),
+    /// Turn the request away with `SERVICE_BUSY`
+    Reject { depth: u64 },
+}
+
+/// Tiers of a tenant, from the shallowest to the deepest
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tiers(Vec<Tier>);
+
+impl Tiers {
+    pub fn new(mut tiers: Vec<Tier>) -> Self {
+        tiers.sort_by_key(|tier| tier.depth);
+        Self(tiers)
+    }
+
+    /// Parse "2000=250,5000=1000,10000=reject": past 2000 waiting jobs hold
+    /// requests for 250ms, past 5000 for a second, past 10000 reject them
+    pub fn parse(value: &str) -> Result<Self, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (depth, action) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("tiers must look like <depth>=<delay ms|reject>, not {}", entry))?;
+                let depth = depth
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| format!("tier depths must be numbers, not {}", depth.trim()))?;
+                let action = match action.trim() {
+                    "reject" => TierAction::Reject,
+                    delay => TierAction::Delay(Duration::from_millis(
+                        delay
+                            .parse::<u64>()
+                            .map_err(|_| format!("tier delays must be milliseconds or reject, not {}", delay))?,
+                    )),
+                };
+                Ok(Tier { depth, action })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::new)
+    }
+
+    /// The deepest tier the queue has reached, if any
+    fn reached(&self, depth: u64) -> Option<&Tier> {
+        self.0.iter().rev().find(|tier| depth >= tier.depth)
+    }
+}
+
+/// Slows down and then turns away new submissions while the upload queue is
+/// deep, so a backlog isn't made worse by accepting more work than the
+/// workers can take. The depth is a gauge refreshed in the background; a
+/// gauge that stopped refreshing lets everything through.
+#[derive(Clone)]
+pub struct QueueBackpressure {
+    default_tiers: Tiers,
+    tenant_tiers: HashMap<String, Tiers>,
+    refresh_interval: Duration,
+    /// Waiting jobs and when they were counted
+    depth: Arc<RwLock<Option<(u64, Instant)>>>,
+}
+
+impl QueueBackpressure {
+    pub fn new(default_tiers: Tiers, tenant_tiers: HashMap<String, Tiers>, refresh_interval: Duration) -> Self {
+        Self {
+            default_tiers,
+            tenant_tiers,
+            refresh_interval,
+            depth: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Tiers from `QUEUE_BACKPRESSURE_TIERS`, overridden per tenant by
+    /// `QUEUE_BACKPRESSURE_TENANT_TIERS` ("bank-a:5000=500,20000=reject;
+    /// bank-b:1000=reject"); unset means no backpressure. The depth is read
+    /// every `QUEUE_DEPTH_REFRESH_MILLISECONDS`.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let default_tiers = Tiers::parse(&var("QUEUE_BACKPRESSURE_TIERS").unwrap_or_default())
+            .map_err(|e| anyhow::anyhow!("QUEUE_BACKPRESSURE_TIERS: {}", e))?;
+
+        let mut tenant_tiers = HashMap::new();
+        for entry in var("QUEUE_BACKPRESSURE_TENANT_TIERS")
+            .unwrap_or_default()
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (tenant_id, tiers) = entry.split_once(':').ok_or_else(|| {
+                anyhow::anyhow!("QUEUE_BACKPRESSURE_TENANT_TIERS entries must look like <tenant>:<tiers>, not {}", entry)
+            })?;
+            let tiers = Tiers::parse(tiers).map_err(|e| anyhow::anyhow!("QUEUE_BACKPRESSURE_TENANT_TIERS: {}", e))?;
+            tenant_tiers.insert(tenant_id.trim().to_lowercase(), tiers);
+        }
+
+        let refresh_interval = var("QUEUE_DEPTH_REFRESH_MILLISECONDS")
+            .map(|v| v.parse::<u64>().map(Duration::from_millis))
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("QUEUE_DEPTH_REFRESH_MILLISECONDS must be a number"))?
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL);
+
+        Ok(Self::new(default_tiers, tenant_tiers, refresh_interval))
+    }
+
+    /// Whether any tenant has tiers; without any there is nothing to refresh
+    pub fn is_enabled(&self) -> bool {
+        !self.default_tiers.0.is_empty() || self.tenant_tiers.values().any(|tiers| !tiers.0.is_empty())
+    }
+
+    /// Count waiting upload jobs, ready and delayed, for as long as the
+    /// process runs
+    pub fn spawn_refresh(&self, queue: RedisQueue, metrics: MetricsService) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let backpressure = self.clone();
+        tokio::spawn(async move {
+            let mut queue = queue;
+            loop {
+                match Self::waiting_jobs(&mut queue).await {
+                    Ok(depth) => {
+                        backpressure.store(depth);
+                        metrics.gauge("queue_backpressure.depth", depth as f64, None);
+                    }
+                    Err(e) => warn!("Failed to read the upload queue depth: {}", e),
+                }
+                tokio::time::sleep(backpressure.refresh_interval).await;
+            }
+        });
+        info!("Applying queue backpressure, reading the depth every {:?}", self.refresh_interval);
+    }
+
+    async fn waiting_jobs(queue: &mut RedisQueue) -> WorkerResult<u64> {
+        Ok(queue.get_queue_length().await? + queue.get_delayed_length().await?)
+    }
+
+    /// Waiting jobs as last counted, unless the count is stale
+    pub fn depth(&self) -> Option<u64> {
+        let stale_after = self.refresh_interval * STALE_AFTER_INTERVALS;
+        self.depth
+            .read()
+            .ok()
+            .and_then(|depth| *depth)
+            .filter(|(_, counted_at)| counted_at.elapsed() <= stale_after)
+            .map(|(depth, _)| depth)
+    }
+
+    /// What to do with new work of the tenant at the current depth
+    pub fn admission(&self, tenant_id: &str) -> Admission {
+        let Some(depth) = self.depth() else {
+            return Admission::Accept;
+        };
+        let tiers = self.tenant_tiers.get(tenant_id).unwrap_or(&self.default_tiers);
+
+        match tiers.reached(depth).map(|tier| tier.action) {
+            None => Admission::Accept,
+            Some(TierAction::Delay(delay)) => Admission::Delay(delay),
+            Some(TierAction::Reject) => Admission::Reject { depth },
+        }
+    }
+
+    /// Hold the caller for the tenant's delay tier; `Err` with the depth
+    /// when the tenant's work is rejected instead
+    pub async fn admit(&self, tenant_id: &str, metrics: &MetricsService) -> Result<(), u64> {
+        let tags = || Some(MetricTags::new().tenant(tenant_id));
+        match self.admission(tenant_id) {
+            Admission::Accept => Ok(()),
+            Admission::Delay(delay) => {
+                metrics.increment("queue_backpressure.delayed", tags());
+                tokio::time::sleep(delay).await;
+                Ok(())
+            }
+            Admission::Reject { depth } => {
+                metrics.increment("queue_backpressure.rejected", tags());
+                warn!("Turned away work of tenant {}: {} upload jobs are waiting", tenant_id, depth);
+                Err(depth)
+            }
+        }
+    }
+
+    fn store(&self, depth: u64) {
+        if let Ok(mut current) = self.depth.write() {
+            *current = Some((depth, Instant::now()));
+        }
+    }
+}
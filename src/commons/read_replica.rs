@@ -0,0 +1,199 @@
+use serde::Serialize;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tracing::{info, warn};
+
+/// How often the replica's lag is measured
+const LAG_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Lag value while it hasn't been measured, or the last measurement failed
+const LAG_UNKNOWN: i64 = -1;
+
+/// Optional Postgres read replica for read-heavy queries. Reads only go to
+/// the replica while its last measured lag is within `max_lag`, and fall back
+/// to the primary when the replica errors, so a replica that's down or behind
+/// only costs the primary the load it had before.
+#[derive(Clone)]
+pub struct ReadReplica {
+    pool: Option<PgPool>,
+    pub max_lag: Duration,
+    /// Last measured lag in milliseconds, `LAG_UNKNOWN` if none
+    lag_ms: Arc<AtomicI64>,
+}
+
+/// Replica state reported by `/readyz`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaStatus {
+    /// Null while the lag is unknown
+    pub lag_seconds: Option<f64>,
+    pub max_lag_seconds: u64,
+    /// Whether reads are currently sent to the replica
+    pub serving_reads: bool,
+}
+
+impl ReadReplica {
+    /// Every read goes to the primary
+    pub fn disabled() -> Self {
+        Self {
+            pool: None,
+            max_lag: Duration::ZERO,
+            lag_ms: Arc::new(AtomicI64::new(LAG_UNKNOWN)),
+        }
+    }
+
+    /// Replica from `DATABASE_REPLICA_URL`, disabled when unset. The pool
+    /// connects lazily so an unreachable replica doesn't stop the API from
+    /// starting; `DATABASE_REPLICA_MAX_LAG_SECONDS` (10 by default) is the lag
+    /// past which reads go back to the primary.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let Some(url) = std::env::var("DATABASE_REPLICA_URL").ok().filter(|v| !v.is_empty()) else {
+            return Ok(Self::disabled());
+        };
+
+        let max_lag_seconds = std::env::var("DATABASE_REPLICA_MAX_LAG_SECONDS")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("DATABASE_REPLICA_MAX_LAG_SECONDS must be a number of seconds"))?
+            .unwrap_or(10);
+
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .acquire_timeout(Duration::from_secs(3))
+            .connect_lazy(&url)?;
+
+        Ok(Self {
+            pool: Some(pool),
+            max_lag: Duration::from_secs(max_lag_seconds),
+            lag_ms: Arc::new(AtomicI64::new(LAG_UNKNOWN)),
+        })
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.pool.is_some()
+    }
+
+    /// Last measured lag; None when there's no replica or it couldn't be
+    /// measured
+    pub fn lag(&self) -> Option<Duration> {
+        let lag_ms = self.lag_ms.load(Ordering::Relaxed);
+        (lag_ms >= 0).then(|| Duration::from_millis(lag_ms as u64))
+    }
+
+    /// Measure how far the replica is behind and remember it. A replica that
+    /// has replayed everything it received isn't behind, however long ago the
+    /// primary last wrote.
+    pub async fn check_lag(&self) -> Result<Option<Duration>, sqlx::Error> {
+        let Some(pool) = &self.pool else {
+            return Ok(None);
+        };
+
+        let result = sqlx::query_scalar!(
+            r#"
+            SELECT CASE
+                WHEN NOT pg_is_in_recovery() THEN 0
+                WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0
+                ELSE COALESCE(EXTRACT(EPOCH FROM NOW() - pg_last_xact_replay_timestamp()), 0)
+            END::DOUBLE PRECISION AS "lag_seconds!"
+            "#
+        )
+        .fetch_one(pool)
+        .await;
+
+        match result {
+            Ok(lag_seconds) => {
+                let lag = Duration::from_secs_f64(lag_seconds.max(0.0));
+                self.lag_ms.store(lag.as_millis() as i64, Ordering::Relaxed);
+                Ok(Some(lag))
+            }
+            Err(e) => {
+                self.lag_ms.store(LAG_UNKNOWN, Ordering::Relaxed);
+                Err(e)
+            }
+        }
+    }
+
+    /// Measure the lag every few seconds for as long as the process runs
+    pub fn spawn_lag_monitor(&self) {
+        if !self.is_configured() {
+            return;
+        }
+
+        let replica = self.clone();
+        tokio::spawn(async move {
+            info!("Sending reads to the replica while it lags less than {:?}", replica.max_lag);
+            let mut serving = false;
+            loop {
+                if let Err(e) = replica.check_lag().await {
+                    warn!("Failed to measure replica lag: {}", e);
+                }
+
+                let now_serving = replica.serving_reads();
+                if now_serving != serving {
+                    match replica.lag() {
+                        Some(lag) if now_serving => info!("Replica caught up ({:?} behind), reads go to it again", lag),
+                        Some(lag) => warn!("Replica is {:?} behind, reads go to the primary", lag),
+                        None => warn!("Replica is unavailable, reads go to the primary"),
+                    }
+                    serving = now_serving;
+                }
+                tokio::time::sleep(LAG_CHECK_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Whether reads currently go to the replica
+    pub fn serving_reads(&self) -> bool {
+        self.pool.is_some() && self.lag().is_some_and(|lag| lag <= self.max_lag)
+    }
+
+    pub fn status(&self) -> Option<ReplicaStatus> {
+        self.is_configured().then(|| ReplicaStatus {
+            lag_seconds: self.lag().map(|lag| lag.as_secs_f64()),
+            max_lag_seconds: self.max_lag.as_secs(),
+            serving_reads: self.serving_reads(),
+        })
+    }
+
+    /// Run a read on the replica when it's serving reads, and on `primary`
+    /// otherwise or if the replica fails
+    pub async fn fetch<T, F, Fut>(&self, primary: &PgPool, query: F) -> Result<T, sqlx::Error>
+    where
+        F: Fn(PgPool) -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        if let Some(replica) = self.pool.as_ref().filter(|_| self.serving_reads()) {
+            match query(replica.clone()).await {
+                Ok(result) => return Ok(result),
+                Err(e) => warn!("Read on the replica failed, retrying on the primary: {}", e),
+            }
+        }
+        query(primary.clone()).await
+    }
+
+    /// `fetch` for lookups of a single row, which also go to the primary when
+    /// the replica doesn't have the row yet
+    pub async fn fetch_optional<T, F, Fut>(&self, primary: &PgPool, query: F) -> Result<Option<T>, sqlx::Error>
+    where
+        F: Fn(PgPool) -> Fut,
+        Fut: Future<Output = Result<Option<T>, sqlx::Error>>,
+    {
+        if let Some(replica) = self.pool.as_ref().filter(|_| self.serving_reads()) {
+            match query(replica.clone()).await {
+                Ok(Some(result)) => return Ok(Some(result)),
+                Ok(None) => {}
+                Err(e) => warn!("Read on the replica failed, retrying on the primary: {}", e),
+            }
+        }
+        query(primary.clone()).await
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::Context;
+use redis::{
+    aio::{ConnectionLike, ConnectionManager, PubSub},
+    cluster::ClusterClient,
+    cluster_async::ClusterConnection,
+    sentinel::{Sentinel, SentinelNodeConnectionInfo},
+    Client, Cmd, ErrorKind, Pipeline, RedisConnectionInfo, RedisFuture, RedisResult, Value,
+};
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+const DEFAULT_RETRY_FACTOR_MS: u64 = 100;
+const DEFAULT_MAX_RETRIES: usize = 6;
+
+/// Where Redis runs, parsed from `REDIS_URL`:
+///
+/// - `redis://host:6379/0` - a single node
+/// - `redis+sentinel://[user:password@]host1:26379,host2:26379/<master name>[/<db>]`
+///   - the master a set of sentinels points to; credentials are the master's
+/// - `redis+cluster://[user:password@]host1:6379,host2:6379` - a cluster,
+///   discovered from any of the listed nodes
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedisTopology {
+    Standalone { url: String },
+    Sentinel {
+        sentinels: Vec<String>,
+        master_name: String,
+        db: i64,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    Cluster { nodes: Vec<String> },
+}
+
+impl RedisTopology {
+    pub fn parse(url: &str) -> anyhow::Result<Self> {
+        if let Some(rest) = url.strip_prefix("redis+sentinel://") {
+            let (credentials, rest) = split_credentials(rest);
+            let (hosts, path) = rest.split_once('/').context("Sentinel URLs must name the master after the hosts")?;
+            let mut path = path.split('/');
+            let master_name = path.next().filter(|name| !name.is_empty()).context("Sentinel URLs must name the master")?;
+            let db = path.next().filter(|db| !db.is_empty()).map(str::parse).transpose().context("Invalid database")?;
+
+            let (username, password) = match credentials {
+                Some((username, password)) => (username, Some(password)),
+                None => (None, None),
+            };
+
+            Ok(Self::Sentinel {
+                sentinels: split_hosts(hosts)?.into_iter().map(|host| format!("redis://{}", host)).collect(),
+                master_name: master_name.to_string(),
+                db: db.unwrap_or(0),
+                username,
+                password,
+            })
+        } else if let Some(rest) = url.strip_prefix("redis+cluster://") {
+            let (credentials, hosts) = split_credentials(rest);
+            let credentials = match credentials {
+                Some((username, password)) => format!("{}:{}@", username.unwrap_or_default(), password),
+                None => String::new(),
+            };
+
+            Ok(Self::Cluster {
+                nodes: split_hosts(hosts.trim_end_matches('/'))?
+                    .into_iter()
+                    .map(|host| format!("redis://{}{}", credentials, host))
+                    .collect(),
+            })
+        } else {
+            Ok(Self::Standalone { url: url.to_string() })
+        }
+    }
+
+    /// Connect with the same retries as `ConnectionManager::new`
+    pub async fn connect(&self) -> RedisResult<RedisConnection> {
+        self.connect_with_backoff(DEFAULT_RETRY_FACTOR_MS, DEFAULT_MAX_RETRIES).await
+    }
+
+    /// Connect, retrying with exponential backoff. Single nodes and sentinel
+    /// masters reconnect with the same backoff when the connection drops; a
+    /// cluster connection follows slot moves and reconnects on its own.
+    pub async fn connect_with_backoff(&self, retry_factor_ms: u64, max_retries: usize) -> RedisResult<RedisConnection> {
+        match self {
+            Self::Standalone { url } => {
+                let manager = ConnectionManager::new_with_backoff(Client::open(url.as_str())?, 2, retry_factor_ms, max_retries).await?;
+                Ok(RedisConnection::Standalone(manager))
+            }
+            Self::Sentinel { .. } => {
+                let manager = self.connect_to_master(retry_factor_ms, max_retries).await?;
+                Ok(RedisConnection::Sentinel(SentinelConnection {
+                    topology: Arc::new(self.clone()),
+                    current: Arc::new(RwLock::new(manager)),
+                    retry_factor_ms,
+                    max_retries,
+                }))
+            }
+            Self::Cluster { nodes } => {
+                let client = ClusterClient::builder(nodes.clone()).retries(max_retries as u32).build()?;
+                Ok(RedisConnection::Cluster(client.get_async_connection().await?))
+            }
+        }
+    }
+
+    /// A dedicated connection in subscriber mode. Published messages reach
+    /// subscribers on every node of a cluster, so any node will do there.
+    pub async fn subscribe(&self, channel: &str) -> RedisResult<PubSub> {
+        let mut pubsub = match self {
+            Self::Standalone { url } => Client::open(url.as_str())?.get_async_connection().await?.into_pubsub(),
+            Self::Sentinel { .. } => self.master_client().await?.get_async_connection().await?.into_pubsub(),
+            Self::Cluster { nodes } => {
+                let mut last_error = None;
+                let mut pubsub = None;
+                for node in nodes {
+                    match Client::open(node.as_str())?.get_async_connection().await {
+                        Ok(connection) => {
+                            pubsub = Some(connection.into_pubsub());
+                            break;
+                        }
+                        Err(e) => last_error = Some(e),
+                    }
+                }
+                match (pubsub, last_error) {
+                    (Some(pubsub), _) => pubsub,
+                    (None, Some(e)) => return Err(e),
+                    (None, None) => unreachable!("cluster topologies list at least one node"),
+                }
+            }
+        };
+        pubsub.subscribe(channel).await?;
+        Ok(pubsub)
+    }
+
+    /// Ask the sentinels for the current master and connect to it
+    async fn connect_to_master(&self, retry_factor_ms: u64, max_retries: usize) -> RedisResult<ConnectionManager> {
+        let client = self.master_client().await?;
+        ConnectionManager::new_with_backoff(client, 2, retry_factor_ms, max_retries).await
+    }
+
+    /// A client for the master the sentinels currently point to
+    async fn master_client(&self) -> RedisResult<Client> {
+        let Self::Sentinel { sentinels, master_name, db, username, password } = self else {
+            unreachable!("only sentinel topologies have a master to look up");
+        };
+
+        let node_info = SentinelNodeConnectionInfo {
+            tls_mode: None,
+            redis_connection_info: Some(RedisConnectionInfo {
+                db: *db,
+                username: username.clone(),
+                password: password.clone(),
+            }),
+        };
+        let client = Sentinel::build(sentinels.clone())?
+            .async_master_for(master_name, Some(&node_info))
+            .await?;
+        info!("Redis master {} is at {}", master_name, client.get_connection_info().addr);
+        Ok(client)
+    }
+}
+
+/// `user:password@` in front of the hosts of a URL, the username optional
+fn split_credentials(rest: &str) -> (Option<(Option<String>, String)>, &str) {
+    match rest.rsplit_once('@') {
+        Some((credentials, hosts)) => {
+            let (username, password) = credentials.split_once(':').unwrap_or(("", credentials));
+            let username = (!username.is_empty()).then(|| username.to_string());
+            (Some((username, password.to_string())), hosts)
+        }
+        None => (None, rest),
+    }
+}
+
+fn split_hosts(hosts: &str) -> anyhow::Result<Vec<&str>> {
+    let hosts: Vec<&str> = hosts.split(',').map(str::trim).filter(|host| !host.is_empty()).collect();
+    if hosts.is_empty() {
+        anyhow::bail!("Redis URL lists no hosts");
+    }
+    Ok(hosts)
+}
+
+/// Whether a key carries a `{...}` hash tag, which decides its cluster slot
+fn has_hash_tag(key: &str) -> bool {
+    key.find('{')
+        .and_then(|open| key[open + 1..].find('}'))
+        .is_some_and(|length| length > 0)
+}
+
+/// A multiplexed connection to whichever topology `REDIS_URL` describes.
+/// Cheap to clone; clones share the underlying connections.
+#[derive(Clone)]
+pub enum RedisConnection {
+    Standalone(ConnectionManager),
+    Sentinel(SentinelConnection),
+    Cluster(ClusterConnection),
+}
+
+impl RedisConnection {
+    pub fn is_cluster(&self) -> bool {
+        matches!(self, Self::Cluster(_))
+    }
+
+    /// `{base}:{suffix}`, hashed to the same cluster slot as `base` so both
+    /// can be used by one script or transaction. Outside a cluster, and for
+    /// a `base` with its own hash tag, the key is left untagged.
+    pub fn related_key(&self, base: &str, suffix: &str) -> String {
+        if self.is_cluster() && !has_hash_tag(base) {
+            format!("{{{}}}:{}", base, suffix)
+        } else {
+            format!("{}:{}", base, suffix)
+        }
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            Self::Standalone(connection) => connection.req_packed_command(cmd),
+            Self::Sentinel(connection) => Box::pin(connection.req_packed_command(cmd)),
+            Self::Cluster(connection) => connection.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(&'a mut self, cmd: &'a Pipeline, offset: usize, count: usize) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            Self::Standalone(connection) => connection.req_packed_commands(cmd, offset, count),
+            Self::Sentinel(connection) => Box::pin(connection.req_packed_commands(cmd, offset, count)),
+            Self::Cluster(connection) => connection.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Self::Standalone(connection) => connection.get_db(),
+            Self::Sentinel(connection) => connection.current().get_db(),
+            Self::Cluster(connection) => connection.get_db(),
+        }
+    }
+}
+
+/// The master behind a set of sentinels. A failed command asks the
+/// sentinels again, so the next command reaches a newly promoted master.
+#[derive(Clone)]
+pub struct SentinelConnection {
+    topology: Arc<RedisTopology>,
+    current: Arc<RwLock<ConnectionManager>>,
+    retry_factor_ms: u64,
+    max_retries: usize,
+}
+
+impl SentinelConnection {
+    fn current(&self) -> ConnectionManager {
+        self.current.read().unwrap().clone()
+    }
+
+    async fn req_packed_command(&self, cmd: &Cmd) -> RedisResult<Value> {
+        let mut connection = self.current();
+        let result = connection.req_packed_command(cmd).await;
+        self.check(result).await
+    }
+
+    async fn req_packed_commands(&self, cmd: &Pipeline, offset: usize, count: usize) -> RedisResult<Vec<Value>> {
+        let mut connection = self.current();
+        let result = connection.req_packed_commands(cmd, offset, count).await;
+        self.check(result).await
+    }
+
+    /// Look the master up again after an error that suggests it moved. The
+    /// error is still returned; callers retry as they would on a single node.
+    async fn check<T>(&self, result: RedisResult<T>) -> RedisResult<T> {
+        if let Err(e) = &result {
+            let moved = e.kind() == ErrorKind::ReadOnly || e.is_io_error() || e.is_connection_refusal();
+            if moved {
+                warn!("Redis master failed ({}), asking the sentinels for the current one", e);
+                match self.topology.connect_to_master(self.retry_factor_ms, self.max_retries).await {
+                    Ok(manager) => *self.current.write().unwrap() = manager,
+                    Err(e) => warn!("Failed to look up the Redis master: {}", e),
+                }
+            }
+        }
+        result
+    }
+}
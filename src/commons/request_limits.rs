@@ -0,0 +1,146 @@
+use std::{collections::HashMap, future::Future, pin::Pin, time::Duration};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{JsonBody, Payload, ServiceRequest, ServiceResponse},
+    error::{InternalError, JsonPayloadError},
+    http::StatusCode,
+    middleware::Next,
+    web, FromRequest, HttpRequest,
+};
+use serde::de::DeserializeOwned;
+
+use crate::commons::app_error::error_response;
+
+/// Body size limits and request timeouts for the API
+#[derive(Debug, Clone)]
+pub struct RequestLimits {
+    /// Limit for ordinary JSON bodies
+    pub json_limit: usize,
+    /// Limit for bodies read with `LargeJson`, which carry base64 images
+    pub large_json_limit: usize,
+    pub default_timeout: Duration,
+    /// Timeouts for individual routes, keyed by route pattern
+    pub timeouts: HashMap<String, Duration>,
+}
+
+impl RequestLimits {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let number = |name: &str, default: u64| -> anyhow::Result<u64> {
+            Ok(std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map_err(|_| anyhow::anyhow!("{} must be a number", name))?
+                .unwrap_or(default))
+        };
+
+        // e.g. "/v1/submissions/urls=60000,/v1/submissions/{submission_id}/documents/{document_type}=120000"
+        let timeouts = std::env::var("REQUEST_TIMEOUT_OVERRIDES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (pattern, millis) = entry.rsplit_once('=').ok_or_else(|| {
+                    anyhow::anyhow!("REQUEST_TIMEOUT_OVERRIDES entries must look like <route pattern>=<milliseconds>, got {}", entry)
+                })?;
+                let millis = millis
+                    .parse::<u64>()
+                    .map_err(|_| anyhow::anyhow!("REQUEST_TIMEOUT_OVERRIDES timeouts must be numbers, got {}", entry))?;
+                Ok((pattern.to_string(), Duration::from_millis(millis)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            json_limit: number("JSON_BODY_LIMIT_BYTES", 64 * 1024)? as usize,
+            large_json_limit: number("LARGE_JSON_BODY_LIMIT_BYTES", 16 * 1024 * 1024)? as usize,
+            default_timeout: Duration::from_millis(number("REQUEST_TIMEOUT_MILLIS", 60_000)?),
+            timeouts,
+        })
+    }
+
+    /// Extractor config for `web::Json`, reporting errors in the API format
+    pub fn json_config(&self) -> web::JsonConfig {
+        web::JsonConfig::default()
+            .limit(self.json_limit)
+            .error_handler(|err, _req| json_error(err))
+    }
+
+    fn timeout_for(&self, pattern: Option<&str>) -> Duration {
+        pattern
+            .and_then(|pattern| self.timeouts.get(pattern))
+            .copied()
+            .unwrap_or(self.default_timeout)
+    }
+}
+
+/// Oversized bodies get 413 with code 1011, anything else unreadable 400
+pub fn json_error(err: JsonPayloadError) -> actix_web::Error {
+    let response = match &err {
+        JsonPayloadError::Overflow { limit } | JsonPayloadError::OverflowKnownLength { limit, .. } => error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "1011",
+            format!("PAYLOAD_TOO_LARGE: body exceeds {} bytes", limit),
+        ),
+        _ => error_response(StatusCode::BAD_REQUEST, "1003", format!("INVALID_REQUEST_BODY: {}", err)),
+    };
+
+    InternalError::from_response(err, response).into()
+}
+
+/// JSON body extractor using `RequestLimits::large_json_limit`, for the few
+/// endpoints that take base64 images inline
+pub struct LargeJson<T>(pub T);
+
+impl<T> std::ops::Deref for LargeJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for LargeJson<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let limit = req
+            .app_data::<web::Data<RequestLimits>>()
+            .map(|limits| limits.large_json_limit)
+            .unwrap_or(16 * 1024 * 1024);
+        let body = JsonBody::<T>::new(req, payload, None, true).limit(limit);
+
+        Box::pin(async move { body.await.map(LargeJson).map_err(json_error) })
+    }
+}
+
+/// Middleware failing requests that run past their route's timeout with 504
+/// and code 1012
+pub async fn enforce_timeout(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let timeout = req
+        .app_data::<web::Data<RequestLimits>>()
+        .map(|limits| limits.timeout_for(req.match_pattern().as_deref()));
+
+    let Some(timeout) = timeout else {
+        return next.call(req).await;
+    };
+
+    match tokio::time::timeout(timeout, next.call(req)).await {
+        Ok(response) => response,
+        Err(_) => Err(InternalError::from_response(
+            "request timed out",
+            error_response(
+                StatusCode::GATEWAY_TIMEOUT,
+                "1012",
+                format!("REQUEST_TIMEOUT: no response within {} ms", timeout.as_millis()),
+            ),
+        )
+        .into()),
+    }
+}
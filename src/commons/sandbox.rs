@@ -0,0 +1,285 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{Extensions, ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    web,
+};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    commons::{minio_service::MinioService, tenant::TENANT_HEADER},
+    services::{face_match_calibration, face_match_service::FaceMatchService},
+};
+
+/// Header QA sends (`true`) to run a request in the sandbox
+pub const SANDBOX_HEADER: &str = "x-sandbox";
+
+/// How long sandbox object URLs stay valid, like the view URLs
+const OBJECT_URL_TTL_SECONDS: i64 = 3600;
+
+#[derive(Debug, thiserror::Error)]
+#[error("SANDBOX_STORAGE_FULL: the sandbox keeps at most {max_objects} objects and {max_bytes} bytes")]
+pub struct SandboxFull {
+    max_objects: usize,
+    max_bytes: usize,
+}
+
+#[derive(Clone)]
+pub struct SandboxObject {
+    pub content: Vec<u8>,
+    pub content_type: Option<String>,
+}
+
+/// Object storage kept in process memory, up to a number of objects and
+/// bytes. Objects are lost on restart and aren't shared between API instances.
+#[derive(Clone)]
+pub struct SandboxStorage {
+    objects: Arc<RwLock<HashMap<String, SandboxObject>>>,
+    base_url: String,
+    /// Signs object URLs; made up at startup since objects don't outlive it
+    url_secret: Arc<[u8; 32]>,
+    max_objects: usize,
+    max_bytes: usize,
+}
+
+impl SandboxStorage {
+    pub fn new(base_url: &str, max_objects: usize, max_bytes: usize) -> Self {
+        let mut url_secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut url_secret);
+
+        Self {
+            objects: Arc::new(RwLock::new(HashMap::new())),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            url_secret: Arc::new(url_secret),
+            max_objects,
+            max_bytes,
+        }
+    }
+
+    /// Where clients read and write the object, in place of a presigned URL.
+    /// Like one it is signed and expires.
+    pub fn object_url(&self, key: &str) -> String {
+        let expires = Utc::now().timestamp() + OBJECT_URL_TTL_SECONDS;
+        let signature: String = self
+            .url_mac(key, expires)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("{}/sandbox/objects/{}?expires={}&signature={}", self.base_url, key, expires, signature)
+    }
+
+    /// Whether an object URL for `key` was handed out by this instance and
+    /// hasn't expired
+    pub fn url_is_valid(&self, key: &str, expires: i64, signature: &str) -> bool {
+        let Some(signature) = (0..signature.len())
+            .step_by(2)
+            .map(|i| signature.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+        else {
+            return false;
+        };
+
+        expires >= Utc::now().timestamp() && self.url_mac(key, expires).verify_slice(&signature).is_ok()
+    }
+
+    fn url_mac(&self, key: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.url_secret.as_slice()).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}.{}", expires, key).as_bytes());
+        mac
+    }
+
+    pub fn put(&self, key: String, content: Vec<u8>, content_type: Option<String>) -> Result<(), SandboxFull> {
+        let mut objects = self.objects.write().unwrap();
+
+        // Replacing an object frees what it took
+        let replaced = objects.get(&key).map(|object| object.content.len());
+        let count = objects.len() + usize::from(replaced.is_none());
+        let bytes = objects.values().map(|object| object.content.len()).sum::<usize>() - replaced.unwrap_or(0) + content.len();
+        if count > self.max_objects || bytes > self.max_bytes {
+            return Err(SandboxFull {
+                max_objects: self.max_objects,
+                max_bytes: self.max_bytes,
+            });
+        }
+
+        objects.insert(key, SandboxObject { content, content_type });
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<SandboxObject> {
+        self.objects.read().unwrap().get(key).cloned()
+    }
+
+    pub fn delete(&self, key: &str) {
+        self.objects.write().unwrap().remove(key);
+    }
+
+    /// Remove every object under `prefix`, returning how many there were
+    pub fn delete_prefix(&self, prefix: &str) -> usize {
+        let mut objects = self.objects.write().unwrap();
+        let before = objects.len();
+        objects.retain(|key, _| !key.starts_with(prefix));
+        before - objects.len()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.objects.read().unwrap().contains_key(key)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    /// Tenants whose requests always run in the sandbox
+    pub tenants: Vec<String>,
+    /// Confidence every sandboxed face match returns
+    pub face_match_score: f64,
+    /// Public base URL of this API, used for sandbox object URLs
+    pub base_url: String,
+    /// Most objects the in-memory storage keeps
+    pub max_objects: usize,
+    /// Most bytes the in-memory storage keeps
+    pub max_bytes: usize,
+}
+
+impl SandboxConfig {
+    /// None unless `SANDBOX_ENABLED=true`. Enabling it is refused unless
+    /// `APP_ENV` names an environment other than `production`.
+    pub fn from_env() -> Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        if var("SANDBOX_ENABLED").as_deref() != Some("true") {
+            return Ok(None);
+        }
+
+        match var("APP_ENV").as_deref() {
+            None => anyhow::bail!("APP_ENV must be set when SANDBOX_ENABLED=true"),
+            Some("production") => anyhow::bail!("SANDBOX_ENABLED=true is refused when APP_ENV=production"),
+            Some(_) => {}
+        }
+
+        let tenants = var("SANDBOX_TENANTS")
+            .map(|tenants| {
+                tenants
+                    .split(',')
+                    .map(|tenant| tenant.trim().to_lowercase())
+                    .filter(|tenant| !tenant.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let face_match_score = var("SANDBOX_FACE_MATCH_SCORE")
+            .map(|v| v.parse::<f64>().context("SANDBOX_FACE_MATCH_SCORE must be a number"))
+            .transpose()?
+            .map(face_match_calibration::confidence_from_legacy)
+            .unwrap_or(95.0);
+
+        let base_url = match var("SANDBOX_BASE_URL") {
+            Some(url) => url,
+            None => format!(
+                "http://{}:{}",
+                var("HOST").context("HOST must be set")?,
+                var("PORT").context("PORT must be set")?
+            ),
+        };
+
+        let max_objects = var("SANDBOX_MAX_OBJECTS")
+            .map(|v| v.parse::<usize>().context("SANDBOX_MAX_OBJECTS must be a number"))
+            .transpose()?
+            .unwrap_or(1000);
+        let max_bytes = var("SANDBOX_MAX_BYTES")
+            .map(|v| v.parse::<usize>().context("SANDBOX_MAX_BYTES must be a number"))
+            .transpose()?
+            .unwrap_or(256 * 1024 * 1024);
+
+        Ok(Some(Self {
+            tenants,
+            face_match_score,
+            base_url,
+            max_objects,
+            max_bytes,
+        }))
+    }
+}
+
+/// Fake integrations handed to sandboxed requests in place of the real ones
+pub struct Sandbox {
+    config: SandboxConfig,
+    storage: SandboxStorage,
+    minio_service: web::Data<MinioService>,
+    face_match_service: web::Data<FaceMatchService>,
+}
+
+impl Sandbox {
+    pub fn new(config: SandboxConfig, face_match_service: &FaceMatchService) -> Self {
+        let storage = SandboxStorage::new(&config.base_url, config.max_objects, config.max_bytes);
+        let minio_service = MinioService::in_memory(storage.clone());
+        let face_match_service = face_match_service.sandboxed(minio_service.clone(), config.face_match_score);
+
+        Self {
+            config,
+            storage,
+            minio_service: web::Data::new(minio_service),
+            face_match_service: web::Data::new(face_match_service),
+        }
+    }
+
+    pub fn storage(&self) -> &SandboxStorage {
+        &self.storage
+    }
+
+    pub fn minio_service(&self) -> &MinioService {
+        &self.minio_service
+    }
+
+    fn is_sandboxed(&self, req: &ServiceRequest) -> bool {
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+
+        let by_header = header(SANDBOX_HEADER).map(|v| v.eq_ignore_ascii_case("true")).unwrap_or(false);
+        let by_tenant = header(TENANT_HEADER)
+            .map(|tenant| self.config.tenants.contains(&tenant.to_lowercase()))
+            .unwrap_or(false);
+
+        by_header || by_tenant
+    }
+}
+
+/// Swap the MinIO and face match services for their sandbox versions on
+/// sandboxed requests. Handlers keep extracting the same `web::Data` types.
+pub async fn route(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let sandboxed = match req.app_data::<web::Data<Sandbox>>().cloned() {
+        Some(sandbox) if sandbox.is_sandboxed(&req) => {
+            let mut services = Extensions::new();
+            services.insert(sandbox.minio_service.clone());
+            services.insert(sandbox.face_match_service.clone());
+            // Containers added last are searched first
+            req.add_data_container(Rc::new(services));
+            true
+        }
+        _ => false,
+    };
+
+    let mut response = next.call(req).await?;
+
+    if sandboxed {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(SANDBOX_HEADER), HeaderValue::from_static("true"));
+    }
+
+    Ok(response)
+}
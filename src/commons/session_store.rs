@@ -0,0 +1,118 @@
+use redis::AsyncCommands;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::commons::redis_connection::{RedisConnection, RedisTopology};
+
+fn user_sessions_key(user_id: i32) -> String {
+    format!("user_sessions:{}", user_id)
+}
+
+/// Sessions backing issued JWTs. A token is only accepted while its session
+/// key exists, so deleting the key revokes the token before it expires.
+#[derive(Clone)]
+pub struct SessionStore {
+    connection_manager: RedisConnection,
+}
+
+impl SessionStore {
+    pub async fn new(redis: &RedisTopology) -> redis::RedisResult<Self> {
+        let connection_manager = redis.connect().await?;
+
+        Ok(Self { connection_manager })
+    }
+
+    /// On a cluster a session shares its user's slot, so it can be changed
+    /// together with the user's session index
+    fn session_key(&self, user_id: i32, session_id: &str) -> String {
+        if self.connection_manager.is_cluster() {
+            self.connection_manager.related_key(&user_sessions_key(user_id), session_id)
+        } else {
+            format!("session:{}", session_id)
+        }
+    }
+
+    /// Open a session for a token living `ttl`, returning its id
+    pub async fn create(&self, user_id: i32, ttl: Duration) -> redis::RedisResult<String> {
+        let session_id = Uuid::new_v4().simple().to_string();
+        let ttl_seconds = ttl.as_secs().max(1);
+        let user_key = user_sessions_key(user_id);
+        let mut conn = self.connection_manager.clone();
+
+        // The user's session index lives as long as their newest session
+        redis::pipe()
+            .atomic()
+            .set_ex(self.session_key(user_id, &session_id), user_id, ttl_seconds)
+            .sadd(&user_key, &session_id)
+            .expire(&user_key, ttl_seconds as i64)
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+
+        Ok(session_id)
+    }
+
+    /// Whether the session exists and belongs to the user
+    pub async fn is_active(&self, session_id: &str, user_id: i32) -> redis::RedisResult<bool> {
+        let mut conn = self.connection_manager.clone();
+        let owner: Option<i32> = conn.get(self.session_key(user_id, session_id)).await?;
+
+        Ok(owner == Some(user_id))
+    }
+
+    pub async fn revoke(&self, session_id: &str, user_id: i32) -> redis::RedisResult<()> {
+        let mut conn = self.connection_manager.clone();
+
+        redis::pipe()
+            .atomic()
+            .del(self.session_key(user_id, session_id))
+            .srem(user_sessions_key(user_id), session_id)
+            .query_async::<_, ()>(&mut conn)
+            .await
+    }
+
+    /// Revoke every session of a user but `keep_session_id`, returning how
+    /// many were still active
+    pub async fn revoke_others(&self, user_id: i32, keep_session_id: &str) -> redis::RedisResult<usize> {
+        let user_key = user_sessions_key(user_id);
+        let mut conn = self.connection_manager.clone();
+        let session_ids: Vec<String> = conn.smembers(&user_key).await?;
+        let others: Vec<&String> = session_ids.iter().filter(|id| id.as_str() != keep_session_id).collect();
+
+        if others.is_empty() {
+            return Ok(0);
+        }
+
+        let keys: Vec<String> = others.iter().map(|id| self.session_key(user_id, id)).collect();
+        let (revoked,): (usize,) = redis::pipe()
+            .atomic()
+            .del(keys)
+            .srem(&user_key, others)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(revoked)
+    }
+
+    /// Revoke every session of a user, returning how many were still active
+    pub async fn revoke_all(&self, user_id: i32) -> redis::RedisResult<usize> {
+        let user_key = user_sessions_key(user_id);
+        let mut conn = self.connection_manager.clone();
+        let session_ids: Vec<String> = conn.smembers(&user_key).await?;
+
+        if session_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let keys: Vec<String> = session_ids.iter().map(|id| self.session_key(user_id, id)).collect();
+        let (revoked,): (usize,) = redis::pipe()
+            .atomic()
+            .del(keys)
+            .del(&user_key)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(revoked)
+    }
+}
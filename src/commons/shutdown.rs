@@ -0,0 +1,81 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    web,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::commons::{app_error::error_response, problem_details::ProblemDetails};
+
+/// Set once the API starts shutting down, so requests that would start new
+/// work are turned away while in-flight ones finish
+#[derive(Clone)]
+pub struct ShutdownState {
+    draining: Arc<AtomicBool>,
+    /// How long the API keeps answering reads after the shutdown signal
+    pub drain_period: Duration,
+}
+
+impl ShutdownState {
+    /// Drain period from `SHUTDOWN_DRAIN_SECONDS`, 5 seconds by default
+    pub fn from_env() -> anyhow::Result<Self> {
+        let drain_seconds = std::env::var("SHUTDOWN_DRAIN_SECONDS")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("SHUTDOWN_DRAIN_SECONDS must be a number of seconds"))?
+            .unwrap_or(5);
+
+        Ok(Self {
+            draining: Arc::new(AtomicBool::new(false)),
+            drain_period: Duration::from_secs(drain_seconds),
+        })
+    }
+
+    pub fn begin_drain(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+}
+
+/// Answer `503` with `Retry-After` to mutating requests once the API is
+/// draining. Reads still go through so clients can follow up on their
+/// submissions.
+pub async fn reject_when_draining(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let draining = req
+        .app_data::<web::Data<ShutdownState>>()
+        .filter(|state| state.is_draining());
+    let mutating = !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+
+    if let (Some(state), true) = (draining, mutating) {
+        let retry_after = state.drain_period.as_secs().max(1);
+        let cause = "SERVICE_SHUTTING_DOWN: retry the request shortly";
+
+        let mut response = if req.path().starts_with("/v2/") {
+            ProblemDetails::new(StatusCode::SERVICE_UNAVAILABLE, "1015", cause, req.path()).response()
+        } else {
+            error_response(StatusCode::SERVICE_UNAVAILABLE, "1015", cause.to_string())
+        };
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, header::HeaderValue::from(retry_after));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace, Resource};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::commons::logging::{self, LogConfig};
+
+/// Key in `FileUploadJob.metadata` holding the W3C trace context of the
+/// request that enqueued the job
+pub const TRACE_CONTEXT_KEY: &str = "traceContext";
+
+/// Install the log subscriber configured by `log_config` and, when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set, an OTLP/HTTP span exporter. Returns
+/// whether spans are exported.
+pub fn init(service_name: &str, log_config: &LogConfig) -> bool {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let (filter, layers, warnings) = logging::layers(log_config);
+    let registry = tracing_subscriber::registry().with(filter).with(layers);
+    let exporting = install(registry, service_name);
+
+    for warning in warnings {
+        tracing::warn!("{}", warning);
+    }
+    exporting
+}
+
+fn install<S>(registry: S, service_name: &str) -> bool
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span> + Send + Sync + 'static,
+{
+    let Some(endpoint) = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|e| !e.is_empty()) else {
+        registry.init();
+        return false;
+    };
+
+    let service_name = std::env::var("OTEL_SERVICE_NAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| service_name.to_string());
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/'))),
+        )
+        .with_trace_config(
+            trace::config().with_resource(Resource::new(vec![KeyValue::new("service.name", service_name)])),
+        )
+        .install_batch(runtime::Tokio);
+
+    match tracer {
+        Ok(tracer) => {
+            registry.with(tracing_opentelemetry::layer().with_tracer(tracer)).init();
+            true
+        }
+        Err(e) => {
+            registry.init();
+            tracing::warn!("Failed to install OTLP exporter, spans will not be exported: {}", e);
+            false
+        }
+    }
+}
+
+/// Flush spans and log lines that are still buffered
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+    logging::flush();
+}
+
+/// Store the current span's trace context in job metadata, unless the job
+/// already carries one from an earlier attempt
+pub fn inject_trace_context(metadata: &mut serde_json::Value) {
+    if !metadata.is_object() {
+        *metadata = serde_json::json!({});
+    }
+    let Some(metadata) = metadata.as_object_mut() else {
+        return;
+    };
+    if metadata.contains_key(TRACE_CONTEXT_KEY) {
+        return;
+    }
+
+    let mut carrier = HashMap::new();
+    let context = tracing::Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut carrier));
+
+    if !carrier.is_empty() {
+        metadata.insert(TRACE_CONTEXT_KEY.to_string(), serde_json::json!(carrier));
+    }
+}
+
+/// Make the current span a child of the trace stored in job metadata
+pub fn adopt_trace_context(metadata: &serde_json::Value) {
+    let Some(carrier) = metadata
+        .get(TRACE_CONTEXT_KEY)
+        .and_then(|value| serde_json::from_value::<HashMap<String, String>>(value.clone()).ok())
+    else {
+        return;
+    };
+
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&carrier));
+    tracing::Span::current().set_parent(parent);
+}
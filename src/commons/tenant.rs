@@ -0,0 +1,44 @@
+use actix_web::{dev::Payload, error::InternalError, http::StatusCode, FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+
+use crate::commons::app_error::error_response;
+
+pub const TENANT_HEADER: &str = "X-Tenant-Id";
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Tenant the request is made on behalf of, taken from the X-Tenant-Id header
+#[derive(Debug, Clone)]
+pub struct Tenant(pub String);
+
+impl Tenant {
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+
+    pub(crate) fn is_valid(tenant_id: &str) -> bool {
+        !tenant_id.is_empty()
+            && tenant_id.len() <= 64
+            && tenant_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+}
+
+impl FromRequest for Tenant {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let tenant_id = match req.headers().get(TENANT_HEADER) {
+            None => return ready(Ok(Tenant(DEFAULT_TENANT.to_string()))),
+            Some(value) => value.to_str().unwrap_or_default().to_lowercase(),
+        };
+
+        if !Tenant::is_valid(&tenant_id) {
+            let response = error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_TENANT".to_string());
+            return ready(Err(InternalError::from_response("INVALID_TENANT", response).into()));
+        }
+
+        ready(Ok(Tenant(tenant_id)))
+    }
+}
@@ -0,0 +1,98 @@
+use actix_web::{dev::Server, http::header, web, App, HttpRequest, HttpResponse, HttpServer};
+use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use std::sync::Arc;
+use tracing::info;
+
+/// HTTPS served by the API itself, for deployments without a load balancer
+/// terminating TLS in front of it
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first
+    pub cert_path: String,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: String,
+    /// Plain HTTP port redirecting every request to HTTPS, if any
+    pub redirect_port: Option<u16>,
+}
+
+impl TlsConfig {
+    /// None unless `TLS_ENABLED` is true
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        if !std::env::var("TLS_ENABLED").map(|v| v == "true").unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let required = |name: &str| var(name).ok_or_else(|| anyhow::anyhow!("{} must be set when TLS_ENABLED is true", name));
+
+        let redirect_port = var("TLS_REDIRECT_PORT")
+            .map(|v| v.parse::<u16>())
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("TLS_REDIRECT_PORT must be a port number"))?;
+
+        Ok(Some(Self {
+            cert_path: required("TLS_CERT_PATH")?,
+            key_path: required("TLS_KEY_PATH")?,
+            redirect_port,
+        }))
+    }
+
+    /// The rustls configuration of the HTTPS listener. actix advertises h2
+    /// and http/1.1 over ALPN on top of it, so clients supporting HTTP/2 get it.
+    pub fn server_config(&self) -> anyhow::Result<rustls::ServerConfig> {
+        let certs = CertificateDer::pem_file_iter(&self.cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| anyhow::anyhow!("Failed to read TLS_CERT_PATH {}: {}", self.cert_path, e))?;
+        if certs.is_empty() {
+            anyhow::bail!("TLS_CERT_PATH {} has no certificates", self.cert_path);
+        }
+        let key = PrivateKeyDer::from_pem_file(&self.key_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read TLS_KEY_PATH {}: {}", self.key_path, e))?;
+
+        // More than one crypto provider is compiled in, so pick one explicitly
+        let config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?;
+
+        Ok(config)
+    }
+}
+
+/// Start a plain HTTP server on `host:redirect_port` that answers everything
+/// with a permanent redirect to the same path over HTTPS on `https_port`
+pub fn start_redirect_server(host: &str, redirect_port: u16, https_port: u16) -> std::io::Result<Server> {
+    info!("Redirecting HTTP on {}:{} to HTTPS on port {}", host, redirect_port, https_port);
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(https_port))
+            .default_service(web::to(redirect_to_https))
+    })
+    .workers(1)
+    .disable_signals()
+    .bind((host, redirect_port))?
+    .run();
+
+    Ok(server)
+}
+
+async fn redirect_to_https(req: HttpRequest, https_port: web::Data<u16>) -> HttpResponse {
+    let connection = req.connection_info();
+    let host = connection.host();
+    // The Host header may carry the HTTP port, which the HTTPS URL replaces.
+    // A colon inside brackets belongs to an IPv6 address instead.
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') && port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    let authority = match **https_port {
+        443 => host.to_string(),
+        port => format!("{}:{}", host, port),
+    };
+    let path = req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/");
+
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, format!("https://{}{}", authority, path)))
+        .finish()
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::submissions::submission_documents::DocumentType;
+
+/// What a presigned upload of one document slot accepts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadPolicy {
+    pub content_type: String,
+    pub max_size_in_bytes: u64,
+}
+
+/// Content types and sizes presigned uploads are restricted to. Upload URLs
+/// are signed for the slot's content type and upload forms additionally
+/// carry a content-length-range, so the bucket rejects anything else.
+#[derive(Debug, Clone)]
+pub struct UploadPolicyConfig {
+    /// Content type of document types without an override
+    pub content_type: String,
+    pub content_type_overrides: HashMap<DocumentType, String>,
+    /// Size limit of document types without an override
+    pub max_size_in_bytes: u64,
+    pub max_size_overrides: HashMap<DocumentType, u64>,
+}
+
+impl Default for UploadPolicyConfig {
+    fn default() -> Self {
+        Self {
+            content_type: "image/jpeg".to_string(),
+            content_type_overrides: HashMap::new(),
+            max_size_in_bytes: 10 * 1024 * 1024,
+            max_size_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl UploadPolicyConfig {
+    pub fn from_env() -> Result<Self> {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        Ok(Self {
+            content_type: var("UPLOAD_CONTENT_TYPE").unwrap_or(defaults.content_type),
+            content_type_overrides: overrides("UPLOAD_CONTENT_TYPE_OVERRIDES", |v| Some(v.to_string()))?,
+            max_size_in_bytes: var("DOCUMENT_UPLOAD_MAX_SIZE_IN_BYTES")
+                .map(|v| v.parse::<u64>().context("DOCUMENT_UPLOAD_MAX_SIZE_IN_BYTES must be a number"))
+                .transpose()?
+                .unwrap_or(defaults.max_size_in_bytes),
+            max_size_overrides: overrides("UPLOAD_MAX_SIZE_OVERRIDES", |v| v.parse::<u64>().ok())?,
+        })
+    }
+
+    pub fn for_document(&self, document_type: DocumentType) -> UploadPolicy {
+        UploadPolicy {
+            content_type: self
+                .content_type_overrides
+                .get(&document_type)
+                .cloned()
+                .unwrap_or_else(|| self.content_type.clone()),
+            max_size_in_bytes: self.max_size_overrides.get(&document_type).copied().unwrap_or(self.max_size_in_bytes),
+        }
+    }
+}
+
+/// Per document type values, e.g. "KTP=image/jpeg,SELFIE=image/png"
+fn overrides<T>(name: &str, parse: impl Fn(&str) -> Option<T>) -> Result<HashMap<DocumentType, T>> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (document_type, value) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("{} entries must look like <document type>=<value>", name))?;
+            let document_type = document_type
+                .trim()
+                .parse::<DocumentType>()
+                .map_err(|_| anyhow!("{} names unknown document type {}", name, document_type.trim()))?;
+            let value = parse(value.trim()).ok_or_else(|| anyhow!("{} has an invalid value for {}", name, document_type))?;
+            Ok((document_type, value))
+        })
+        .collect()
+}
@@ -0,0 +1,102 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::submissions::submission_documents::DocumentType;
+
+/// Lifetimes of the presigned URLs handed out by the API. URLs are signed for
+/// their lifetime plus `grace`, while clients are told the lifetime alone, so
+/// a client whose clock runs a little behind still gets to use the URL until
+/// the time it was promised.
+#[derive(Debug, Clone)]
+pub struct UrlExpiryConfig {
+    /// Upload URL lifetime of document types without an override
+    pub upload: Duration,
+    pub upload_overrides: HashMap<DocumentType, Duration>,
+    /// Lifetime of the document URLs face match providers fetch from
+    pub download: Duration,
+    pub download_overrides: HashMap<DocumentType, Duration>,
+    /// Lifetime of report download links
+    pub report: Duration,
+    pub grace: Duration,
+}
+
+impl Default for UrlExpiryConfig {
+    fn default() -> Self {
+        Self {
+            upload: Duration::from_secs(600),
+            upload_overrides: HashMap::new(),
+            download: Duration::from_secs(60),
+            download_overrides: HashMap::new(),
+            report: Duration::from_secs(15 * 60),
+            grace: Duration::from_secs(30),
+        }
+    }
+}
+
+impl UrlExpiryConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let defaults = Self::default();
+        let seconds = |name: &str, default: Duration| -> anyhow::Result<Duration> {
+            Ok(std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map_err(|_| anyhow::anyhow!("{} must be a number", name))?
+                .map(Duration::from_secs)
+                .unwrap_or(default))
+        };
+
+        Ok(Self {
+            upload: seconds("UPLOAD_URL_EXPIRY_SECONDS", defaults.upload)?,
+            upload_overrides: overrides("UPLOAD_URL_EXPIRY_OVERRIDES")?,
+            download: seconds("DOWNLOAD_URL_EXPIRY_SECONDS", defaults.download)?,
+            download_overrides: overrides("DOWNLOAD_URL_EXPIRY_OVERRIDES")?,
+            report: seconds("REPORT_URL_EXPIRY_SECONDS", defaults.report)?,
+            grace: seconds("URL_EXPIRY_GRACE_SECONDS", defaults.grace)?,
+        })
+    }
+
+    pub fn upload_expiry(&self, document_type: DocumentType) -> Duration {
+        self.upload_overrides.get(&document_type).copied().unwrap_or(self.upload)
+    }
+
+    pub fn download_expiry(&self, document_type: DocumentType) -> Duration {
+        self.download_overrides.get(&document_type).copied().unwrap_or(self.download)
+    }
+
+    /// How long to sign a URL advertised as valid for `expiry`
+    pub fn signed_for(&self, expiry: Duration) -> Duration {
+        expiry + self.grace
+    }
+
+    /// When a URL advertised as valid for `expiry` from now expires
+    pub fn expires_at(expiry: Duration) -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::seconds(expiry.as_secs() as i64)
+    }
+}
+
+/// Per document type lifetimes, e.g. "KTP=900,SELFIE=300"
+fn overrides(name: &str) -> anyhow::Result<HashMap<DocumentType, Duration>> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (document_type, seconds) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("{} entries must look like <document type>=<seconds>, got {}", name, entry))?;
+            let document_type = document_type
+                .trim()
+                .parse::<DocumentType>()
+                .map_err(|_| anyhow::anyhow!("{} names unknown document type {}", name, document_type.trim()))?;
+            let seconds = seconds
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| anyhow::anyhow!("{} lifetimes must be numbers, got {}", name, entry))?;
+            Ok((document_type, Duration::from_secs(seconds)))
+        })
+        .collect()
+}
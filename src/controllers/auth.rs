@@ -1,49 +1,64 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
 use sqlx::PgPool;
 use tracing::{info, info_span};
 use validator::Validate;
-use std::collections::HashMap;
 
 use crate::{
-    models::user::{ApiError, ApiResponse, AuthResponse, LoginRequest, RegisterRequest},
-    services::{auth_service::AuthService, metrics_service::MetricsService},
+    commons::{app_error::error_response, authenticated_user::AuthenticatedUser, session_store::SessionStore},
+    models::user::{ApiResponse, LoginRequest, LogoutResponse, RegisterRequest, VerifyEmailQuery},
+    notifier::verification::VerificationMailer,
+    services::{
+        auth_service::AuthService,
+        password_hasher::PasswordHashConfig,
+        captcha_service::CaptchaService,
+        key_provider::KeyProvider,
+        metrics_service::{MetricTags, MetricsService},
+    },
 };
 
 #[actix_web::post("/register")]
+#[allow(clippy::too_many_arguments)]
 async fn register(
     pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
+    verification_mailer: web::Data<VerificationMailer>,
     metrics: web::Data<MetricsService>,
+    req: HttpRequest,
     request: web::Json<RegisterRequest>,
 ) -> HttpResponse {
     let start = std::time::Instant::now();
-    let mut tags = HashMap::new();
-    tags.insert("endpoint".to_string(), "register".to_string());
+    let mut tags = MetricTags::endpoint("register");
+
+    if let Some(captcha) = req.app_data::<web::Data<CaptchaService>>() {
+        if let Err(response) = captcha.check(&req).await {
+            tags.set("error", "captcha_failed");
+            metrics.increment("auth.register.failed", Some(tags.outcome("error")));
+            return response;
+        }
+    }
 
     // Validate request
     if let Err(_) = request.validate() {
-        metrics.increment("auth.validation.failed", Some(tags.clone()));
-        return HttpResponse::UnprocessableEntity().json(ApiResponse::<AuthResponse> {
-            success: false,
-            data: None,
-            errors: Some(vec![ApiError {
-                entity: "HACKATHON_BI_2025".to_string(),
-                code: "1001".to_string(),
-                cause: "INVALID_EMAIL_OR_PASSWORD".to_string(),
-            }]),
-        });
+        metrics.increment("auth.validation.failed", Some(tags.clone().outcome("error")));
+        return error_response(StatusCode::UNPROCESSABLE_ENTITY, "1001", "INVALID_EMAIL_OR_PASSWORD".to_string());
     }
 
-    // Get JWT secret from environment
-    let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-
     // Create auth service
-    let auth_service = AuthService::new(pool.get_ref().clone(), jwt_secret);
+    let auth_service = AuthService::new(
+        pool.get_ref().clone(),
+        keys.get_ref().clone(),
+        sessions.get_ref().clone(),
+        password_hash_config.get_ref().clone(),
+    )
+        .with_verification_mailer(verification_mailer.get_ref().clone());
 
     // Handle registration
     match auth_service.register(request.into_inner()).await {
         Ok(response) => {
-            metrics.increment("auth.register.success", Some(tags.clone()));
-            metrics.timing("auth.register.duration", start.elapsed(), Some(tags));
+            metrics.increment("auth.register.success", Some(tags.clone().outcome("success")));
+            metrics.timing("auth.register.duration", start.elapsed(), Some(tags.outcome("success")));
             HttpResponse::Ok().json(ApiResponse {
                 success: true,
                 data: Some(response),
@@ -52,31 +67,15 @@ async fn register(
         },
         Err(e) => {
             if e.to_string() == "User already exists" {
-                tags.insert("error".to_string(), "user_exists".to_string());
-                metrics.increment("auth.register.failed", Some(tags.clone()));
-                metrics.timing("auth.register.duration", start.elapsed(), Some(tags));
-                HttpResponse::UnprocessableEntity().json(ApiResponse::<AuthResponse> {
-                    success: false,
-                    data: None,
-                    errors: Some(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1002".to_string(),
-                        cause: "USER_ALREADY_EXISTS".to_string(),
-                    }]),
-                })
+                tags.set("error", "user_exists");
+                metrics.increment("auth.register.failed", Some(tags.clone().outcome("error")));
+                metrics.timing("auth.register.duration", start.elapsed(), Some(tags.outcome("error")));
+                error_response(StatusCode::UNPROCESSABLE_ENTITY, "1002", "USER_ALREADY_EXISTS".to_string())
             } else {
-                tags.insert("error".to_string(), "system_error".to_string());
-                metrics.increment("auth.register.failed", Some(tags.clone()));
-                metrics.timing("auth.register.duration", start.elapsed(), Some(tags));
-                HttpResponse::InternalServerError().json(ApiResponse::<AuthResponse> {
-                    success: false,
-                    data: None,
-                    errors: Some(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1000".to_string(),
-                        cause: "SYSTEM_ERROR".to_string(),
-                    }]),
-                })
+                tags.set("error", "system_error");
+                metrics.increment("auth.register.failed", Some(tags.clone().outcome("error")));
+                metrics.timing("auth.register.duration", start.elapsed(), Some(tags.outcome("error")));
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", "SYSTEM_ERROR".to_string())
             }
         }
     }
@@ -85,27 +84,30 @@ async fn register(
 #[actix_web::post("/login")]
 async fn login(
     pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
     metrics: web::Data<MetricsService>,
+    req: HttpRequest,
     request: web::Json<LoginRequest>,
 ) -> HttpResponse {
     let _span = info_span!("login-api", correlation_id = uuid::Uuid::new_v4().to_string()).entered();
     let start = std::time::Instant::now();
-    let mut tags = HashMap::new();
-    tags.insert("endpoint".to_string(), "login".to_string());
+    let mut tags = MetricTags::endpoint("login");
+
+    if let Some(captcha) = req.app_data::<web::Data<CaptchaService>>() {
+        if let Err(response) = captcha.check(&req).await {
+            tags.set("error", "captcha_failed");
+            metrics.increment("auth.login.failed", Some(tags.outcome("error")));
+            return response;
+        }
+    }
 
     let start = std::time::Instant::now();
     // Validate request
     if let Err(_) = request.validate() {
-        metrics.increment("auth.validation.failed", Some(tags.clone()));
-        return HttpResponse::UnprocessableEntity().json(ApiResponse::<AuthResponse> {
-            success: false,
-            data: None,
-            errors: Some(vec![ApiError {
-                entity: "HACKATHON_BI_2025".to_string(),
-                code: "1001".to_string(),
-                cause: "INVALID_EMAIL_OR_PASSWORD".to_string(),
-            }]),
-        });
+        metrics.increment("auth.validation.failed", Some(tags.clone().outcome("error")));
+        return error_response(StatusCode::UNPROCESSABLE_ENTITY, "1001", "INVALID_EMAIL_OR_PASSWORD".to_string());
     }
 
     info!(test = "uhuy", uhuy = "aaa", "Validation process took: {:?}", start.elapsed());
@@ -113,16 +115,14 @@ async fn login(
     let duration = start.elapsed();
     info!("Validation process took: {:?}", duration);
 
-    let start = std::time::Instant::now();
-    // Get JWT secret from environment
-    let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-
-    let duration = start.elapsed();
-    info!("JWT secret process took: {:?}", duration);
-
     let start = std::time::Instant::now();
     // Create auth service
-    let auth_service = AuthService::new(pool.get_ref().clone(), jwt_secret);
+    let auth_service = AuthService::new(
+        pool.get_ref().clone(),
+        keys.get_ref().clone(),
+        sessions.get_ref().clone(),
+        password_hash_config.get_ref().clone(),
+    );
 
     let duration = start.elapsed();
     info!("Auth service process took: {:?}", duration);
@@ -131,8 +131,8 @@ async fn login(
     let start = std::time::Instant::now();
     match auth_service.login(request.into_inner()).await {
         Ok(response) => {
-            metrics.increment("auth.login.success", Some(tags.clone()));
-            metrics.timing("auth.login.duration", start.elapsed(), Some(tags));
+            metrics.increment("auth.login.success", Some(tags.clone().outcome("success")));
+            metrics.timing("auth.login.duration", start.elapsed(), Some(tags.outcome("success")));
             HttpResponse::Ok().json(ApiResponse {
                 success: true,
                 data: Some(response),
@@ -141,32 +141,185 @@ async fn login(
         },
         Err(e) => {
             if e.to_string() == "Invalid email or password" {
-                tags.insert("error".to_string(), "invalid_credentials".to_string());
-                metrics.increment("auth.login.failed", Some(tags.clone()));
-                metrics.timing("auth.login.duration", start.elapsed(), Some(tags));
-                HttpResponse::UnprocessableEntity().json(ApiResponse::<AuthResponse> {
-                    success: false,
-                    data: None,
-                    errors: Some(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1001".to_string(),
-                        cause: "INVALID_EMAIL_OR_PASSWORD".to_string(),
-                    }]),
-                })
+                tags.set("error", "invalid_credentials");
+                metrics.increment("auth.login.failed", Some(tags.clone().outcome("error")));
+                metrics.timing("auth.login.duration", start.elapsed(), Some(tags.outcome("error")));
+                error_response(StatusCode::UNPROCESSABLE_ENTITY, "1001", "INVALID_EMAIL_OR_PASSWORD".to_string())
+            } else if e.to_string() == "Account deleted" {
+                tags.set("error", "account_deleted");
+                metrics.increment("auth.login.failed", Some(tags.clone().outcome("error")));
+                metrics.timing("auth.login.duration", start.elapsed(), Some(tags.outcome("error")));
+                error_response(StatusCode::FORBIDDEN, "1016", "ACCOUNT_DELETED".to_string())
+            } else {
+                tags.set("error", "system_error");
+                metrics.increment("auth.login.failed", Some(tags.clone().outcome("error")));
+                metrics.timing("auth.login.duration", start.elapsed(), Some(tags.outcome("error")));
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", "SYSTEM_ERROR".to_string())
+            }
+        }
+    }
+}
+
+#[actix_web::get("/verify-email")]
+async fn verify_email(
+    pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
+    metrics: web::Data<MetricsService>,
+    query: web::Query<VerifyEmailQuery>,
+) -> HttpResponse {
+    let start = std::time::Instant::now();
+    let mut tags = MetricTags::endpoint("verify_email");
+
+    let auth_service = AuthService::new(
+        pool.get_ref().clone(),
+        keys.get_ref().clone(),
+        sessions.get_ref().clone(),
+        password_hash_config.get_ref().clone(),
+    );
+
+    match auth_service.verify_email(&query.token).await {
+        Ok(response) => {
+            metrics.increment("auth.verify_email.success", Some(tags.clone().outcome("success")));
+            metrics.timing("auth.verify_email.duration", start.elapsed(), Some(tags.outcome("success")));
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(response),
+                errors: None,
+            })
+        },
+        Err(e) => {
+            if e.to_string() == "Invalid verification token" {
+                tags.set("error", "invalid_token");
+                metrics.increment("auth.verify_email.failed", Some(tags.clone().outcome("error")));
+                metrics.timing("auth.verify_email.duration", start.elapsed(), Some(tags.outcome("error")));
+                error_response(StatusCode::UNPROCESSABLE_ENTITY, "1009", "INVALID_VERIFICATION_TOKEN".to_string())
             } else {
-                tags.insert("error".to_string(), "system_error".to_string());
-                metrics.increment("auth.login.failed", Some(tags.clone()));
-                metrics.timing("auth.login.duration", start.elapsed(), Some(tags));
-                HttpResponse::InternalServerError().json(ApiResponse::<AuthResponse> {
-                    success: false,
-                    data: None,
-                    errors: Some(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1000".to_string(),
-                        cause: "SYSTEM_ERROR".to_string(),
-                    }]),
-                })
+                tags.set("error", "system_error");
+                metrics.increment("auth.verify_email.failed", Some(tags.clone().outcome("error")));
+                metrics.timing("auth.verify_email.duration", start.elapsed(), Some(tags.outcome("error")));
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", "SYSTEM_ERROR".to_string())
             }
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Email a new verification link to the signed in user. Resends to the same
+/// user are limited to one per `EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS`.
+#[actix_web::post("/verify-email/resend")]
+async fn resend_verification(
+    pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
+    verification_mailer: web::Data<VerificationMailer>,
+    metrics: web::Data<MetricsService>,
+    user: AuthenticatedUser,
+) -> HttpResponse {
+    let mut tags = MetricTags::endpoint("resend_verification");
+
+    let auth_service = AuthService::new(
+        pool.get_ref().clone(),
+        keys.get_ref().clone(),
+        sessions.get_ref().clone(),
+        password_hash_config.get_ref().clone(),
+    )
+        .with_verification_mailer(verification_mailer.get_ref().clone());
+
+    match auth_service.resend_verification(user.user_id).await {
+        Ok(response) => {
+            metrics.increment("auth.resend_verification.success", Some(tags.outcome("success")));
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(response),
+                errors: None,
+            })
+        }
+        Err(e) => {
+            let (error, response) = match e.to_string().as_str() {
+                "Email already verified" => (
+                    "already_verified",
+                    error_response(StatusCode::CONFLICT, "1009", "EMAIL_ALREADY_VERIFIED".to_string()),
+                ),
+                "Verification resent too soon" => (
+                    "too_soon",
+                    error_response(StatusCode::TOO_MANY_REQUESTS, "1013", "VERIFICATION_RESEND_TOO_SOON".to_string()),
+                ),
+                "User not found" => (
+                    "user_not_found",
+                    error_response(StatusCode::UNAUTHORIZED, "1007", "UNAUTHORIZED".to_string()),
+                ),
+                _ => {
+                    log::error!("Failed to resend verification email: {}", e);
+                    ("system_error", error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", "SYSTEM_ERROR".to_string()))
+                }
+            };
+            tags.set("error", error);
+            metrics.increment("auth.resend_verification.failed", Some(tags.outcome("error")));
+            response
+        }
+    }
+}
+
+#[actix_web::post("/logout")]
+async fn logout(
+    pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
+    metrics: web::Data<MetricsService>,
+    user: AuthenticatedUser,
+) -> HttpResponse {
+    let auth_service = AuthService::new(
+        pool.get_ref().clone(),
+        keys.get_ref().clone(),
+        sessions.get_ref().clone(),
+        password_hash_config.get_ref().clone(),
+    );
+
+    logout_response(
+        &metrics,
+        "logout",
+        auth_service.logout(user.user_id, &user.session_id).await.map(|_| 1),
+    )
+}
+
+#[actix_web::post("/logout-all")]
+async fn logout_all(
+    pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
+    metrics: web::Data<MetricsService>,
+    user: AuthenticatedUser,
+) -> HttpResponse {
+    let auth_service = AuthService::new(
+        pool.get_ref().clone(),
+        keys.get_ref().clone(),
+        sessions.get_ref().clone(),
+        password_hash_config.get_ref().clone(),
+    );
+
+    logout_response(&metrics, "logout_all", auth_service.logout_all(user.user_id).await)
+}
+
+fn logout_response(metrics: &MetricsService, endpoint: &str, result: Result<usize, anyhow::Error>) -> HttpResponse {
+    let tags = MetricTags::endpoint(endpoint);
+
+    match result {
+        Ok(revoked_sessions) => {
+            metrics.increment(&format!("auth.{}.success", endpoint), Some(tags.outcome("success")));
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(LogoutResponse { revoked_sessions }),
+                errors: None,
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to revoke sessions: {}", e);
+            metrics.increment(&format!("auth.{}.failed", endpoint), Some(tags.outcome("error")));
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", "SYSTEM_ERROR".to_string())
+        }
+    }
+}
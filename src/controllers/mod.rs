@@ -1 +1,4 @@
-pub mod auth; 
\ No newline at end of file
+pub mod auth;
+pub mod profile;
+pub mod readiness;
+pub mod sandbox;
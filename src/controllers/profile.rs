@@ -0,0 +1,139 @@
+use actix_web::{http::StatusCode, web, HttpResponse};
+use sqlx::PgPool;
+use validator::Validate;
+
+use crate::{
+    commons::{app_error::error_response, authenticated_user::AuthenticatedUser, session_store::SessionStore},
+    models::user::{ApiResponse, ChangePasswordRequest, UpdateProfileRequest},
+    services::{
+        auth_service::AuthService,
+        key_provider::KeyProvider,
+        metrics_service::{MetricTags, MetricsService},
+        password_hasher::PasswordHashConfig,
+    },
+};
+
+fn auth_service(
+    pool: &PgPool,
+    keys: &KeyProvider,
+    sessions: &SessionStore,
+    password_hash_config: &PasswordHashConfig,
+) -> AuthService {
+    AuthService::new(pool.clone(), keys.clone(), sessions.clone(), password_hash_config.clone())
+}
+
+/// Map a profile operation's result to a response, counting it under
+/// `profile.<endpoint>`
+fn profile_response<T: serde::Serialize>(
+    metrics: &MetricsService,
+    endpoint: &str,
+    result: Result<T, anyhow::Error>,
+) -> HttpResponse {
+    let tags = MetricTags::endpoint(endpoint);
+
+    match result {
+        Ok(data) => {
+            metrics.increment(&format!("profile.{}.success", endpoint), Some(tags.outcome("success")));
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(data),
+                errors: None,
+            })
+        }
+        Err(e) => {
+            metrics.increment(&format!("profile.{}.failed", endpoint), Some(tags.outcome("error")));
+            match e.to_string().as_str() {
+                "User not found" => error_response(StatusCode::NOT_FOUND, "1004", "USER_NOT_FOUND".to_string()),
+                "Invalid current password" => {
+                    error_response(StatusCode::UNPROCESSABLE_ENTITY, "1001", "INVALID_CURRENT_PASSWORD".to_string())
+                }
+                _ => {
+                    log::error!("Profile {} failed: {}", endpoint, e);
+                    error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", "SYSTEM_ERROR".to_string())
+                }
+            }
+        }
+    }
+}
+
+#[actix_web::get("/me")]
+async fn get_profile(
+    pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
+    metrics: web::Data<MetricsService>,
+    user: AuthenticatedUser,
+) -> HttpResponse {
+    let auth_service = auth_service(&pool, &keys, &sessions, &password_hash_config);
+
+    profile_response(&metrics, "get", auth_service.get_profile(user.user_id).await)
+}
+
+/// Close the caller's account. Login is refused from now on and the account
+/// with its submissions is purged after the retention window.
+#[actix_web::delete("/me")]
+async fn delete_account(
+    pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
+    metrics: web::Data<MetricsService>,
+    user: AuthenticatedUser,
+) -> HttpResponse {
+    let auth_service = auth_service(&pool, &keys, &sessions, &password_hash_config);
+
+    profile_response(&metrics, "delete", auth_service.delete_account(user.user_id).await)
+}
+
+#[actix_web::patch("/me")]
+async fn update_profile(
+    pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
+    metrics: web::Data<MetricsService>,
+    user: AuthenticatedUser,
+    body: Result<web::Json<UpdateProfileRequest>, actix_web::Error>,
+) -> HttpResponse {
+    let request = match body {
+        Ok(b) => b.into_inner(),
+        Err(e) => return e.error_response(),
+    };
+
+    if let Err(e) = request.validate() {
+        return error_response(StatusCode::UNPROCESSABLE_ENTITY, "1003", format!("INVALID_REQUEST_BODY: {}", e));
+    }
+
+    let auth_service = auth_service(&pool, &keys, &sessions, &password_hash_config);
+
+    profile_response(&metrics, "update", auth_service.update_profile(user.user_id, request).await)
+}
+
+#[actix_web::post("/me/password")]
+async fn change_password(
+    pool: web::Data<PgPool>,
+    sessions: web::Data<SessionStore>,
+    keys: web::Data<KeyProvider>,
+    password_hash_config: web::Data<PasswordHashConfig>,
+    metrics: web::Data<MetricsService>,
+    user: AuthenticatedUser,
+    body: Result<web::Json<ChangePasswordRequest>, actix_web::Error>,
+) -> HttpResponse {
+    let request = match body {
+        Ok(b) => b.into_inner(),
+        Err(e) => return e.error_response(),
+    };
+
+    if let Err(e) = request.validate() {
+        return error_response(StatusCode::UNPROCESSABLE_ENTITY, "1003", format!("INVALID_REQUEST_BODY: {}", e));
+    }
+
+    let auth_service = auth_service(&pool, &keys, &sessions, &password_hash_config);
+
+    profile_response(
+        &metrics,
+        "change_password",
+        auth_service.change_password(user.user_id, &user.session_id, request).await,
+    )
+}
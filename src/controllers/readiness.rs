@@ -0,0 +1,66 @@
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use sqlx::Connection;
+use std::time::Duration;
+
+use crate::{
+    commons::{
+        read_replica::{ReadReplica, ReplicaStatus},
+        shutdown::ShutdownState,
+    },
+    models::user::ApiResponse,
+};
+
+/// How long the primary gets to answer before the API reports itself unready
+const PRIMARY_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessResponse {
+    /// READY, DRAINING or DATABASE_UNAVAILABLE
+    pub status: String,
+    /// Only when a replica is configured. A lagging or unreachable replica
+    /// doesn't make the API unready, reads just go to the primary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replica: Option<ReplicaStatus>,
+}
+
+/// `200` while the API can take traffic, `503` while it drains or can't
+/// reach the primary database
+#[actix_web::get("/readyz")]
+async fn readyz(
+    pool: web::Data<sqlx::PgPool>,
+    read_replica: web::Data<ReadReplica>,
+    shutdown_state: web::Data<ShutdownState>,
+) -> HttpResponse {
+    let primary_reachable = tokio::time::timeout(PRIMARY_CHECK_TIMEOUT, async {
+        let mut connection = pool.acquire().await?;
+        connection.ping().await
+    })
+    .await
+    .is_ok_and(|result| result.is_ok());
+
+    let status = if shutdown_state.is_draining() {
+        "DRAINING"
+    } else if !primary_reachable {
+        "DATABASE_UNAVAILABLE"
+    } else {
+        "READY"
+    };
+    let ready = status == "READY";
+
+    let response = ApiResponse {
+        success: ready,
+        data: Some(ReadinessResponse {
+            status: status.to_string(),
+            replica: read_replica.status(),
+        }),
+        errors: None,
+    };
+
+    if ready {
+        HttpResponse::Ok().json(response)
+    } else {
+        HttpResponse::ServiceUnavailable().json(response)
+    }
+}
@@ -0,0 +1,82 @@
+use actix_web::{
+    http::{header::CONTENT_TYPE, StatusCode},
+    web, HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+
+use crate::{
+    commons::{app_error::error_response, minio_service::UploadStreamError, sandbox::Sandbox},
+    submissions::submission_controller::DocumentUploadConfig,
+};
+
+// Stand-ins for the MinIO presigned URLs handed out to sandboxed requests.
+// Like presigned URLs they need no credentials but are signed and expire.
+
+#[derive(Debug, Deserialize)]
+pub struct SignedObjectQuery {
+    pub expires: Option<i64>,
+    pub signature: Option<String>,
+}
+
+/// Whether the URL is one the sandbox handed out for `key`
+fn is_signed(sandbox: &Sandbox, key: &str, query: &SignedObjectQuery) -> bool {
+    match (query.expires, query.signature.as_deref()) {
+        (Some(expires), Some(signature)) => sandbox.storage().url_is_valid(key, expires, signature),
+        _ => false,
+    }
+}
+
+#[actix_web::put("/objects/{key:.*}")]
+async fn put_object(
+    req: HttpRequest,
+    sandbox: web::Data<Sandbox>,
+    upload_config: web::Data<DocumentUploadConfig>,
+    path: web::Path<String>,
+    query: web::Query<SignedObjectQuery>,
+    payload: web::Payload,
+) -> HttpResponse {
+    let key = path.into_inner();
+    if !is_signed(&sandbox, &key, &query) {
+        return error_response(StatusCode::FORBIDDEN, "1018", "URL_EXPIRED".to_string());
+    }
+
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    match sandbox
+        .minio_service()
+        .upload_stream(key, payload, content_type, upload_config.max_size_in_bytes)
+        .await
+    {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e @ UploadStreamError::TooLarge { .. }) => {
+            error_response(StatusCode::PAYLOAD_TOO_LARGE, "1010", e.to_string())
+        }
+        Err(e @ UploadStreamError::Storage(_)) => {
+            error_response(StatusCode::INSUFFICIENT_STORAGE, "1010", e.to_string())
+        }
+        Err(e) => error_response(StatusCode::BAD_REQUEST, "1003", e.to_string()),
+    }
+}
+
+#[actix_web::get("/objects/{key:.*}")]
+async fn get_object(
+    sandbox: web::Data<Sandbox>,
+    path: web::Path<String>,
+    query: web::Query<SignedObjectQuery>,
+) -> HttpResponse {
+    let key = path.into_inner();
+    if !is_signed(&sandbox, &key, &query) {
+        return error_response(StatusCode::FORBIDDEN, "1018", "URL_EXPIRED".to_string());
+    }
+
+    match sandbox.storage().get(&key) {
+        Some(object) => HttpResponse::Ok()
+            .content_type(object.content_type.unwrap_or_else(|| "application/octet-stream".to_string()))
+            .body(object.content),
+        None => error_response(StatusCode::NOT_FOUND, "1004", "OBJECT_NOT_FOUND".to_string()),
+    }
+}
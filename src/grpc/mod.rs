@@ -0,0 +1,67 @@
+pub mod proto;
+pub mod submission_service;
+
+use std::net::SocketAddr;
+
+use subtle::ConstantTimeEq;
+use tonic::{service::interceptor::InterceptedService, transport::Server, Request, Status};
+use tracing::info;
+
+pub const GRPC_API_KEY_METADATA: &str = "x-api-key";
+
+/// Optional gRPC server for internal callers, next to the HTTP API
+#[derive(Debug, Clone)]
+pub struct GrpcConfig {
+    pub port: u16,
+    /// Key internal callers send as `x-api-key` metadata
+    pub api_key: String,
+}
+
+impl GrpcConfig {
+    /// None unless `GRPC_ENABLED` is true
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        if !std::env::var("GRPC_ENABLED").map(|v| v == "true").unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let port = std::env::var("GRPC_PORT")
+            .unwrap_or_else(|_| "50051".to_string())
+            .parse::<u16>()
+            .map_err(|_| anyhow::anyhow!("GRPC_PORT must be a port number"))?;
+        let api_key = std::env::var("GRPC_API_KEY").unwrap_or_default();
+        if api_key.is_empty() {
+            anyhow::bail!("GRPC_API_KEY must be set when GRPC_ENABLED is true");
+        }
+
+        Ok(Some(Self { port, api_key }))
+    }
+}
+
+/// Serve until `shutdown` resolves, letting in-flight calls finish
+pub async fn serve(
+    config: GrpcConfig,
+    host: &str,
+    service: submission_service::GrpcSubmissionService,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> anyhow::Result<()> {
+    let addr: SocketAddr = format!("{}:{}", host, config.port).parse()?;
+    let api_key = config.api_key;
+    let server = InterceptedService::new(
+        proto::submission_service_server::SubmissionServiceServer::new(service),
+        move |request: Request<()>| require_api_key(&api_key, request),
+    );
+
+    info!("gRPC server starting at {}", addr);
+    Server::builder()
+        .add_service(server)
+        .serve_with_shutdown(addr, shutdown)
+        .await?;
+    Ok(())
+}
+
+fn require_api_key(api_key: &str, request: Request<()>) -> Result<Request<()>, Status> {
+    match request.metadata().get(GRPC_API_KEY_METADATA) {
+        Some(key) if bool::from(key.as_bytes().ct_eq(api_key.as_bytes())) => Ok(request),
+        _ => Err(Status::unauthenticated("1007: UNAUTHORIZED")),
+    }
+}
@@ -0,0 +1,4 @@
+//! Messages and server glue generated by `tonic-build` from
+//! `proto/submission.proto`, see `build.rs`.
+
+tonic::include_proto!("hackathon.submission.v1");
@@ -0,0 +1,292 @@
+use actix_web::http::StatusCode;
+use chrono::SecondsFormat;
+use tonic::{Code, Request, Response, Status};
+use uuid::Uuid;
+
+use crate::{
+    analytics::Analytics,
+    commons::{
+        app_error::AppError,
+        crypto::FieldCipher,
+        db_pool::SERVICE_BUSY_CODE,
+        maintenance::MaintenanceMode,
+        minio_service::MinioService,
+        queue_backpressure::QueueBackpressure,
+        read_replica::ReadReplica,
+        tenant::{Tenant, DEFAULT_TENANT},
+        upload_policy::UploadPolicyConfig,
+        url_expiry::UrlExpiryConfig,
+    },
+    grpc::proto::{self, submission_service_server},
+    policies::policy_repository::PolicyRepository,
+    repositories::user_repository::UserRepository,
+    services::metrics_service::MetricsService,
+    submissions::{
+        dto::v2::SubmissionCreated,
+        nfc_replay::{NfcEnvelope, NfcIdentifier, NfcReplayGuard},
+        submission_controller::SubmissionType,
+        submission_documents::DocumentType,
+        submission_event_repository::SubmissionEventRepository,
+        submission_quota::SubmissionQuota,
+        submission_repository::SubmissionRepository,
+        submission_review_repository::SubmissionReviewRepository,
+        submission_service::SubmissionService,
+    },
+    workers::{queue::EnqueueResult, upload_source::UploadSourcePolicy, FileUploadJob, RedisQueue},
+};
+
+/// gRPC face of the submission service layer, for internal callers that act
+/// on behalf of a user they name instead of presenting the user's token
+pub struct GrpcSubmissionService {
+    pub pool: sqlx::PgPool,
+    pub read_replica: ReadReplica,
+    pub cipher: FieldCipher,
+    pub minio_service: MinioService,
+    pub metrics: MetricsService,
+    pub url_expiry: UrlExpiryConfig,
+    pub upload_policy: UploadPolicyConfig,
+    pub nfc_replay_guard: Option<NfcReplayGuard>,
+    pub quota: SubmissionQuota,
+    pub queue: RedisQueue,
+    /// Hosts `EnqueueUploadJob` may point the worker at
+    pub upload_sources: UploadSourcePolicy,
+    pub backpressure: QueueBackpressure,
+    pub maintenance: MaintenanceMode,
+    pub analytics: Analytics,
+}
+
+impl GrpcSubmissionService {
+    fn submission_service(&self) -> SubmissionService {
+        SubmissionService::new(
+            self.minio_service.clone(),
+            SubmissionRepository::new(self.pool.clone(), self.cipher.clone()),
+            SubmissionEventRepository::new(self.pool.clone()),
+            SubmissionReviewRepository::new(self.pool.clone()),
+            PolicyRepository::new(self.pool.clone()),
+            self.metrics.clone(),
+        )
+    }
+
+    /// Mutating calls are turned away during maintenance, like mutating requests
+    fn reject_during_maintenance(&self) -> Result<(), Status> {
+        match self.maintenance.current() {
+            Some(window) => Err(Status::unavailable(format!(
+                "1021: UNDER_MAINTENANCE: {}",
+                window.until.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The gRPC code closest to the HTTP status the API answers an error with;
+/// the message keeps the API's `<code>: <cause>`
+fn status(error: &AppError) -> Status {
+    let code = match error.status {
+        StatusCode::BAD_REQUEST => Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::CONFLICT => Code::Aborted,
+        StatusCode::UNPROCESSABLE_ENTITY => Code::FailedPrecondition,
+        StatusCode::PAYLOAD_TOO_LARGE | StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+        _ => Code::Internal,
+    };
+    Status::new(code, format!("{}: {}", error.code, error.cause))
+}
+
+fn invalid_argument(cause: &str) -> Status {
+    Status::invalid_argument(format!("1003: {}", cause))
+}
+
+#[tonic::async_trait]
+impl submission_service_server::SubmissionService for GrpcSubmissionService {
+    async fn create_submission(
+        &self,
+        request: Request<proto::CreateSubmissionRequest>,
+    ) -> Result<Response<proto::CreateSubmissionResponse>, Status> {
+        self.reject_during_maintenance()?;
+        let request = request.into_inner();
+
+        let submission_type = request
+            .submission_type
+            .parse::<SubmissionType>()
+            .map_err(|_| invalid_argument("INVALID_SUBMISSION_TYPE"))?;
+
+        let tenant_id = match request.tenant_id.to_lowercase() {
+            tenant_id if tenant_id.is_empty() => DEFAULT_TENANT.to_string(),
+            tenant_id if Tenant::is_valid(&tenant_id) => tenant_id,
+            _ => return Err(invalid_argument("INVALID_TENANT")),
+        };
+
+        // A signature makes the read a signed envelope
+        let nfc_identifier = if request.nfc_signature.is_empty() {
+            NfcIdentifier::Raw(request.nfc_identifier)
+        } else {
+            NfcIdentifier::Signed(NfcEnvelope {
+                payload: request.nfc_identifier,
+                nonce: request.nfc_nonce,
+                timestamp: request.nfc_timestamp,
+                signature: request.nfc_signature,
+            })
+        };
+
+        // Same rule as the HTTP API: only verified, open accounts submit
+        let user = UserRepository::new(self.pool.clone())
+            .find_by_id(request.user_id)
+            .await
+            .map_err(|e| Status::internal(format!("1002: {}", e)))?;
+        match user {
+            Some(user) if user.deleted_at.is_some() => return Err(Status::failed_precondition("1016: ACCOUNT_DELETED")),
+            Some(user) if user.email_verified_at.is_none() => {
+                return Err(Status::failed_precondition("1008: EMAIL_NOT_VERIFIED"))
+            }
+            Some(_) => {}
+            None => return Err(Status::not_found("1004: USER_NOT_FOUND")),
+        }
+
+        let response = self
+            .submission_service()
+            .with_url_expiry(self.url_expiry.clone())
+            .with_upload_policy(self.upload_policy.clone())
+            .with_quota(self.quota.clone())
+            .with_backpressure(self.backpressure.clone())
+            .with_nfc_replay_guard(self.nfc_replay_guard.clone())
+            .with_analytics(self.analytics.clone())
+            .generate_presigned_urls(
+                Uuid::new_v4().to_string(),
+                request.user_id.to_string(),
+                tenant_id,
+                submission_type,
+                nfc_identifier,
+                None,
+            )
+            .await
+            .map_err(|e| status(&e))?;
+
+        // Same fixed document order as v2
+        let created = SubmissionCreated::from(response);
+        Ok(Response::new(proto::CreateSubmissionResponse {
+            submission_id: created.submission_id,
+            documents: created
+                .documents
+                .into_iter()
+                .map(|slot| proto::UploadSlot {
+                    document_type: slot.document_type,
+                    document_reference: slot.document_reference,
+                    upload_url: slot.upload_url,
+                    expires_in_seconds: slot.expires_in_seconds,
+                    expires_at: slot.expires_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                    content_type: slot.content_type,
+                    max_size_in_bytes: slot.max_size_in_bytes,
+                    upload_form: slot.upload_form.map(|form| proto::UploadForm {
+                        url: form.url,
+                        fields: form.fields.into_iter().collect(),
+                    }),
+                })
+                .collect(),
+        }))
+    }
+
+    async fn get_submission_status(
+        &self,
+        request: Request<proto::GetSubmissionStatusRequest>,
+    ) -> Result<Response<proto::GetSubmissionStatusResponse>, Status> {
+        let request = request.into_inner();
+
+        let submission_type = request
+            .submission_type
+            .parse::<SubmissionType>()
+            .map_err(|_| invalid_argument("INVALID_SUBMISSION_TYPE"))?;
+
+        let response = self
+            .submission_service()
+            .with_read_replica(self.read_replica.clone())
+            .get_submission_status(submission_type, request.nfc_identifier, None)
+            .await
+            .map_err(|e| status(&e))?;
+
+        // Documents the worker hasn't picked up yet have an empty status
+        Ok(Response::new(proto::GetSubmissionStatusResponse {
+            submission_status: response.submission_status,
+            decision_reasons: response.decision_reasons,
+            documents: response
+                .documents
+                .into_iter()
+                .map(|(document_type, progress)| {
+                    let upload = progress
+                        .map(|progress| proto::DocumentUpload {
+                            status: serde_json::to_value(progress.status)
+                                .ok()
+                                .and_then(|status| status.as_str().map(str::to_string))
+                                .unwrap_or_default(),
+                            last_error: progress.last_error.unwrap_or_default(),
+                            updated_at: progress.updated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                        })
+                        .unwrap_or_default();
+                    (document_type, upload)
+                })
+                .collect(),
+        }))
+    }
+
+    async fn enqueue_upload_job(
+        &self,
+        request: Request<proto::EnqueueUploadJobRequest>,
+    ) -> Result<Response<proto::EnqueueUploadJobResponse>, Status> {
+        self.reject_during_maintenance()?;
+        let request = request.into_inner();
+
+        let submission_id = Uuid::parse_str(&request.submission_id).map_err(|_| invalid_argument("INVALID_SUBMISSION_ID"))?;
+        let document_type = request
+            .document_type
+            .parse::<DocumentType>()
+            .map_err(|_| invalid_argument("INVALID_DOCUMENT_TYPE"))?;
+        if request.document_url.is_empty() || request.document_name.is_empty() {
+            return Err(invalid_argument("DOCUMENT_URL_AND_NAME_REQUIRED"));
+        }
+        // Refused here rather than dead-lettered by the worker later
+        if let Err(e) = self.upload_sources.check(&request.document_url) {
+            return Err(invalid_argument(&format!("DOCUMENT_URL_NOT_ALLOWED: {}", e)));
+        }
+        let metadata = match request.metadata_json.as_str() {
+            "" => serde_json::json!({}),
+            metadata => match serde_json::from_str::<serde_json::Value>(metadata) {
+                Ok(metadata) if metadata.is_object() => metadata,
+                _ => return Err(invalid_argument("INVALID_METADATA")),
+            },
+        };
+
+        let job = FileUploadJob::new(
+            submission_id.to_string(),
+            request.document_url,
+            request.document_name,
+            document_type.to_string(),
+            metadata,
+        );
+
+        // Held or turned away like new submissions of the submission's tenant
+        if self.backpressure.is_enabled() {
+            let tenant_id = SubmissionRepository::new(self.pool.clone(), self.cipher.clone())
+                .find_tenant(submission_id)
+                .await
+                .map_err(|e| Status::internal(format!("1002: {}", e)))?
+                .unwrap_or_else(|| DEFAULT_TENANT.to_string());
+            if self.backpressure.admit(&tenant_id, &self.metrics).await.is_err() {
+                return Err(Status::unavailable(format!("{}: SERVICE_BUSY", SERVICE_BUSY_CODE)));
+            }
+        }
+
+        let mut queue = self.queue.clone();
+        let enqueued = match queue.enqueue_job(&job).await {
+            Ok(EnqueueResult::Enqueued) => true,
+            Ok(EnqueueResult::AlreadyEnqueued) => false,
+            Err(e) => return Err(Status::unavailable(format!("1000: {}", e))),
+        };
+
+        Ok(Response::new(proto::EnqueueUploadJobResponse {
+            job_id: job.id.to_string(),
+            enqueued,
+        }))
+    }
+}
@@ -0,0 +1,74 @@
+use actix_web::{http::StatusCode, web, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use uuid::Uuid;
+
+use crate::{
+    admin::admin_auth::has_admin_key,
+    commons::{
+        app_error::{error_response, AppError},
+        authenticated_user::AuthenticatedUser,
+        crypto::FieldCipher,
+    },
+    models::user::ApiResponse,
+    submissions::submission_repository::SubmissionRepository,
+    workers::{job::JobProgressSnapshot, RedisQueue},
+};
+
+/// Whether the job works for a submission the user created
+async fn owns_job(repository: &SubmissionRepository, progress: &JobProgressSnapshot, user: &AuthenticatedUser) -> Result<bool, AppError> {
+    let Some(submission_id) = progress.submission_id.as_deref().and_then(|id| Uuid::parse_str(id).ok()) else {
+        return Ok(false);
+    };
+
+    let owner = repository.find_owner(submission_id).await?;
+    Ok(owner == Some(user.user_id.to_string()))
+}
+
+/// Progress of an upload job: percent complete, current step, retries and
+/// last error. Admins authenticate with the admin API key and see any job,
+/// clients with their bearer token and only see jobs of their own
+/// submissions; anyone else's job is reported as not found.
+#[actix_web::get("/jobs/{job_id}")]
+async fn get_job(
+    req: HttpRequest,
+    queue: web::Data<RedisQueue>,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let user = if has_admin_key(&req) {
+        None
+    } else {
+        match AuthenticatedUser::extract(&req).await {
+            Ok(user) => Some(user),
+            Err(_) => return error_response(StatusCode::UNAUTHORIZED, "1007", "UNAUTHORIZED".to_string()),
+        }
+    };
+
+    let not_found = || error_response(StatusCode::NOT_FOUND, "1004", "JOB_NOT_FOUND".to_string());
+
+    let Ok(job_id) = Uuid::parse_str(&path.into_inner()) else {
+        return not_found();
+    };
+
+    let mut queue = queue.as_ref().clone();
+    let progress = match queue.get_job_progress(job_id).await {
+        Ok(Some(progress)) => progress,
+        Ok(None) => return not_found(),
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "1000", e.to_string()),
+    };
+
+    if let Some(user) = &user {
+        let repository = SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone());
+        match owns_job(&repository, &progress, user).await {
+            Ok(true) => {}
+            Ok(false) => return not_found(),
+            Err(e) => return e.error_response(),
+        }
+    }
+
+    HttpResponse::Ok().json(ApiResponse::<JobProgressSnapshot> {
+        success: true,
+        data: Some(progress),
+        errors: None,
+    })
+}
@@ -0,0 +1,17 @@
+pub mod admin;
+pub mod analytics;
+pub mod jobs;
+pub mod commons;
+pub mod controllers;
+pub mod grpc;
+pub mod models;
+pub mod notifier;
+pub mod policies;
+pub mod repositories;
+pub mod services;
+pub mod utils;
+pub mod submissions;
+pub mod workers;
+
+#[cfg(feature = "client")]
+pub mod client;
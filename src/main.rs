@@ -1,37 +1,80 @@
-use actix_web::{web, App, HttpServer};
+use actix_web::{middleware::from_fn, web, App, HttpServer};
 use std::env;
 use sqlx::postgres::PgPoolOptions;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
-use crate::services::{metrics_service::MetricsService, face_match_service::FaceMatchService};
-use crate::workers::{WorkerConfig};
+use hackathon_bi_2025::{admin, analytics, commons, controllers, grpc, jobs, notifier, policies, services, submissions, workers};
+use hackathon_bi_2025::services::{metrics_service::MetricsService, face_match_service::FaceMatchService};
+use hackathon_bi_2025::workers::{PollStrategy, RedisQueue, WorkerConfig};
 use tracing::{info, warn};
 use std::sync::Arc;
 use tokio::signal;
-use crate::workers::main_worker::MainWorker;
-
-mod commons;
-mod controllers;
-mod models;
-mod repositories;
-mod services;
-mod utils;
-mod submissions;
-mod workers;
+use hackathon_bi_2025::workers::main_worker::MainWorker;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    dotenv::dotenv().ok();
+    dotenvy::dotenv().ok();
     
-    // Initialize tracing with JSON format
-    tracing_subscriber::registry()
-        .with(EnvFilter::from_default_env())
-        .with(tracing_subscriber::fmt::layer().json())
-        .init();
-
-    // Determine the application mode from environment variable
-    let app_mode = env::var("APP_MODE").unwrap_or_else(|_| "api".to_string());
+    // `migrate [status|run|revert]` manages the schema and exits
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("migrate") {
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create pool");
+
+        return commons::migrations::cli(&pool, args.get(2).map(String::as_str))
+            .await
+            .map_err(|e| {
+                eprintln!("migrate failed: {}", e);
+                std::io::Error::other(e)
+            });
+    }
+
+    // `backfill nik-hash` indexes the NIK of submissions written before
+    // `nik_hash` existed and exits
+    if args.get(1).map(String::as_str) == Some("backfill") {
+        if args.get(2).map(String::as_str) != Some("nik-hash") {
+            return Err(std::io::Error::other("usage: backfill nik-hash"));
+        }
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&database_url)
+            .await
+            .expect("Failed to create pool");
+        let cipher = commons::crypto::FieldCipher::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load PII encryption keys: {}", e)))?;
+
+        let filled = submissions::submission_repository::SubmissionRepository::new(pool, cipher)
+            .backfill_nik_hashes(500)
+            .await
+            .map_err(std::io::Error::other)?;
+        println!("Filled in the NIK hash of {} submissions", filled);
+        return Ok(());
+    }
+
+    // Determine the application mode from environment variable; `--once` runs
+    // a worker in drain mode
+    let mut app_mode = env::var("APP_MODE").unwrap_or_else(|_| "api".to_string());
+    if env::args().any(|arg| arg == "--once") {
+        app_mode = "drain".to_string();
+    }
+
+    // Initialize tracing with JSON format, exporting spans over OTLP if
+    // configured. Bad log settings can only be reported before the logger is
+    // installed, so they fail startup here.
+    let log_config = commons::logging::LogConfig::from_env()
+        .map_err(|e| std::io::Error::other(format!("Failed to load log settings: {}", e)))?;
+    if commons::telemetry::init(&format!("hackathon-bi-2025-{}", app_mode), &log_config) {
+        info!("Exporting traces over OTLP");
+    }
+    commons::logging::reload_on_sighup();
     info!("Starting application in {} mode", app_mode);
 
+    policies::identity_document::load_regions()
+        .map_err(|e| std::io::Error::other(format!("Failed to load KTP regions: {}", e)))?;
+
     // Initialize worker configuration regardless of mode
     // This is needed for both API mode (if workers are enabled) and worker mode
     let worker_config = match WorkerConfig::from_env() {
@@ -47,19 +90,53 @@ async fn main() -> std::io::Result<()> {
 
     // In worker mode, force worker threads to be enabled regardless of config
     let mut worker_config_final = worker_config.clone();
-    if app_mode == "worker" {
-        info!("Running in worker mode - forcing worker threads to be enabled");
+    if app_mode == "worker" || app_mode == "drain" {
+        info!("Running in {} mode - forcing worker threads to be enabled", app_mode);
         worker_config_final.background_worker_thread_enabled = true;
         // Optionally enable DLQ processing in worker mode
         worker_config_final.file_upload_worker_dlq_thread_enabled = true;
     }
 
+    // Both the API and the DLQ worker need the database
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let db_pool_config = commons::db_pool::DbPoolConfig::from_env()
+        .map_err(|e| std::io::Error::other(format!("Failed to load database pool configuration: {}", e)))?;
+    let pool = db_pool_config
+        .options(db_pool_config.max_connections)
+        .connect(&database_url)
+        .await
+        .expect("Failed to create pool");
+
+    // Bring the schema up to date, or at least say when it isn't
+    if env::var("DATABASE_RUN_MIGRATIONS").map(|v| v == "true").unwrap_or(false) {
+        match commons::migrations::run(&pool).await {
+            Ok(applied) => info!("Applied {} pending migrations", applied.len()),
+            Err(e) => {
+                warn!("Failed to run migrations: {}", e);
+                return Err(std::io::Error::other("Failed to run migrations"));
+            }
+        }
+    } else {
+        match commons::migrations::status(&pool).await {
+            Ok(migrations) => {
+                let outdated = migrations
+                    .iter()
+                    .filter(|m| m.state != commons::migrations::MigrationState::Applied)
+                    .count();
+                if outdated > 0 {
+                    warn!("{} migrations are pending or out of sync; run `migrate status`", outdated);
+                }
+            }
+            Err(e) => warn!("Failed to check migration status: {}", e),
+        }
+    }
+
     // Initialize the worker
-    let mut main_worker = MainWorker::new(worker_config_final);
+    let mut main_worker = MainWorker::new(worker_config_final, pool.clone());
     
     // Always start the worker in worker mode
     // In API mode, only start if enabled in config
-    if app_mode == "worker" || worker_config.background_worker_thread_enabled {
+    if app_mode == "worker" || app_mode == "drain" || worker_config.background_worker_thread_enabled {
         match main_worker.start().await {
             Ok(_) => info!("File Upload Worker System started successfully"),
             Err(e) => {
@@ -69,33 +146,64 @@ async fn main() -> std::io::Result<()> {
         }
     }
 
+    // In drain mode, consume until both queues are empty and exit with a summary
+    if app_mode == "drain" {
+        info!("Running in drain mode - workers exit once the queues are empty");
+
+        tokio::select! {
+            result = main_worker.run_until_drained() => match result {
+                Ok(summary) => info!("Drain completed: {}", summary),
+                Err(e) => {
+                    warn!("Drain failed: {}", e);
+                    commons::telemetry::shutdown();
+                    return Err(std::io::Error::other("Drain failed"));
+                }
+            },
+            _ = shutdown_signal() => {
+                info!("Shutdown signal received, stopping drain");
+                main_worker.signal_shutdown();
+                if let Err(e) = main_worker.await_shutdown().await {
+                    warn!("Error during worker shutdown: {}", e);
+                }
+            }
+        }
+
+        commons::telemetry::shutdown();
+        return Ok(());
+    }
+
     // In worker mode, we only need to set up shutdown handling for the worker
     if app_mode == "worker" {
         info!("Running in worker mode - API server will not be started");
         
-        // Set up graceful shutdown for worker only
         let main_worker_ref = Arc::new(main_worker);
-        tokio::spawn(async move {
-            match signal::ctrl_c().await {
-                Ok(()) => {
-                    info!("Shutdown signal received, starting graceful worker shutdown");
-                    main_worker_ref.signal_shutdown();
-                    
-                    if let Err(e) = main_worker_ref.await_shutdown().await {
-                        warn!("Error during worker shutdown: {}", e);
-                    }
-                    info!("Worker graceful shutdown completed");
-                },
-                Err(e) => warn!("Error waiting for interrupt signal: {}", e),
-            }
-        });
 
-        // Keep the application running until Ctrl+C is received
-        match signal::ctrl_c().await {
-            Ok(()) => info!("Shutdown signal received, application will exit"),
-            Err(e) => warn!("Error waiting for Ctrl+C: {}", e),
+        // Health, metrics and drain endpoints live on a separate port; the
+        // ones changing the worker need the admin API key
+        let admin_config = admin::admin_auth::AdminConfig::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load admin configuration: {}", e)))?;
+        let admin_server = workers::admin_server::start(Arc::clone(&main_worker_ref), admin_config)?;
+        let admin_server_handle = admin_server.handle();
+        tokio::spawn(admin_server);
+
+        // Keep the application running until Ctrl+C or SIGTERM is received,
+        // then let in-flight jobs finish before exiting
+        match shutdown_signal().await {
+            Ok(()) => {
+                info!("Shutdown signal received, starting graceful worker shutdown");
+                main_worker_ref.signal_shutdown();
+
+                if let Err(e) = main_worker_ref.await_shutdown().await {
+                    warn!("Error during worker shutdown: {}", e);
+                }
+                info!("Worker graceful shutdown completed");
+            },
+            Err(e) => warn!("Error waiting for interrupt signal: {}", e),
         }
 
+        admin_server_handle.stop(true).await;
+        commons::telemetry::shutdown();
+
         return Ok(());
     }
 
@@ -104,13 +212,12 @@ async fn main() -> std::io::Result<()> {
 
     let host = std::env::var("HOST").expect("HOST must be set");
     let port = std::env::var("PORT").expect("PORT must be set");
-
-    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
-        .await
-        .expect("Failed to create pool");
+    let http_server_config = commons::http_server::HttpServerConfig::from_env()
+        .map_err(|e| std::io::Error::other(format!("Failed to load HTTP server configuration: {}", e)))?;
+    info!(
+        "HTTP server tuning (unset keeps the actix default): {:?}, database pool of {} connections",
+        http_server_config, db_pool_config.max_connections
+    );
 
     let pool = web::Data::new(pool);
 
@@ -120,13 +227,6 @@ async fn main() -> std::io::Result<()> {
         &std::env::var("STATSD_PREFIX").expect("STATSD_PREFIX must be set")
     ));
 
-    let face_match_service = web::Data::new(FaceMatchService::new(
-        std::env::var("FACE_MATCH_HOST").expect("FACE_MATCH_HOST must be set"),
-        std::env::var("FACE_MATCH_THRESHOLD").expect("FACE_MATCH_THRESHOLD must be set").parse::<f64>().unwrap(),
-        std::env::var("FACE_MATCH_TIMEOUT_MILLIS").expect("FACE_MATCH_TIMEOUT_MILLIS must be set").parse::<u64>().unwrap(),
-        metrics_service.as_ref().clone(),
-    ));
-
     let minio_service = commons::minio_service::MinioService::new(
         &env::var("MINIO_ENDPOINT").expect("MINIO_ENDPOINT must be set"),
         &env::var("MINIO_ACCESS_KEY").expect("MINIO_ACCESS_KEY must be set"),
@@ -134,25 +234,452 @@ async fn main() -> std::io::Result<()> {
         &env::var("MINIO_BUCKET_NAME").expect("MINIO_BUCKET_NAME must be set"),
     ).await.expect("Failed to initialize MinIO service");
 
+    if let Some(spill) = submissions::submission_data_spill::SubmissionDataSpill::from_env(minio_service.clone())
+        .expect("Failed to load the submission data spill configuration")
+    {
+        spill.install();
+    }
+
+    let face_match_threshold = std::env::var("FACE_MATCH_THRESHOLD").expect("FACE_MATCH_THRESHOLD must be set").parse::<f64>().unwrap();
+    let face_match_threshold = match services::face_match_calibration::confidence_from_legacy(face_match_threshold) {
+        confidence if confidence != face_match_threshold => {
+            warn!("FACE_MATCH_THRESHOLD {} is on the old 0-1 scale, using a confidence of {}", face_match_threshold, confidence);
+            confidence
+        }
+        confidence => confidence,
+    };
+    // Providers can be recorded, or replayed from recordings without calling them
+    let face_match_recorder = services::face_match_recorder::FaceMatchRecorder::from_env(&minio_service)
+        .expect("Failed to load face match recording configuration");
+    let face_match_providers = match &face_match_recorder {
+        Some(recorder) if recorder.mode() == services::face_match_recorder::RecordingMode::Replay => {
+            warn!("Face matches are replayed from recordings, no provider is called");
+            recorder.replaying(
+                services::face_match_provider::provider_configs_from_env().expect("Failed to load face match providers"),
+            )
+        }
+        recorder => services::face_match_provider::providers_from_env(
+            std::env::var("FACE_MATCH_TIMEOUT_MILLIS").expect("FACE_MATCH_TIMEOUT_MILLIS must be set").parse::<u64>().unwrap(),
+        )
+        .map(|providers| match recorder {
+            Some(recorder) => {
+                warn!("Face match provider answers are being recorded");
+                recorder.recording(providers)
+            }
+            None => providers,
+        }),
+    }
+    .expect("Failed to load face match providers");
+    let mut face_match_service = FaceMatchService::new(
+        face_match_providers,
+        face_match_threshold,
+        minio_service.clone(),
+        metrics_service.as_ref().clone(),
+    )
+    .with_calibrations(policies::policy_repository::PolicyRepository::new(pool.as_ref().clone()));
+    if let Some(cache) = services::face_match_cache::FaceMatchCache::from_env(&worker_config.redis)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Failed to initialize face match cache: {}", e)))?
+    {
+        face_match_service = face_match_service.with_cache(cache);
+    }
+    if let Some(limiter) = services::face_match_limiter::FaceMatchLimiter::from_env()
+        .expect("Failed to load face match concurrency limits")
+    {
+        face_match_service = face_match_service.with_limiter(limiter);
+    }
+    let sandbox = commons::sandbox::SandboxConfig::from_env()
+        .expect("Failed to load sandbox configuration")
+        .map(|config| {
+            warn!("Sandbox mode is enabled for tenants {:?} and X-Sandbox requests", config.tenants);
+            web::Data::new(commons::sandbox::Sandbox::new(config, &face_match_service))
+        });
+    let face_match_service = web::Data::new(face_match_service);
+
+    let captcha = services::captcha_service::CaptchaConfig::from_env()
+        .expect("Failed to load CAPTCHA configuration")
+        .map(|config| {
+            info!("CAPTCHA is required on register and login");
+            web::Data::new(services::captcha_service::CaptchaService::new(config))
+        });
+
+    let redis_queue = web::Data::new(RedisQueue::new(
+        &worker_config.redis,
+        worker_config.worker_upload_file_queue.clone(),
+        worker_config.worker_upload_file_dlq.clone(),
+    ).await.expect("Failed to initialize Redis queue")
+        .with_dedup_ttl(worker_config.enqueue_dedup_ttl)
+        .with_wakeups(worker_config.poll_strategy == PollStrategy::Pubsub));
+
+    // Async exports run inside the API process; the ones a previous process
+    // left behind will never finish
+    match redis_queue.as_ref().clone().fail_orphaned_export_runs().await {
+        Ok(0) => {}
+        Ok(failed) => warn!("Marked {} interrupted export runs as failed", failed),
+        Err(e) => warn!("Failed to check for interrupted export runs: {}", e),
+    }
+
+    let document_upload_config = web::Data::new(submissions::submission_controller::DocumentUploadConfig {
+        max_size_in_bytes: env::var("DOCUMENT_UPLOAD_MAX_SIZE_IN_BYTES")
+            .unwrap_or_else(|_| "10485760".to_string())
+            .parse::<u64>()
+            .expect("DOCUMENT_UPLOAD_MAX_SIZE_IN_BYTES must be a number"),
+    });
+
+    let field_cipher = web::Data::new(
+        commons::crypto::FieldCipher::from_env().expect("Failed to load PII encryption keys"),
+    );
+
+    let request_limits = web::Data::new(
+        commons::request_limits::RequestLimits::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load request limits: {}", e)))?,
+    );
+
+    let trusted_proxies = web::Data::new(
+        commons::client_ip::TrustedProxies::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load trusted proxies: {}", e)))?,
+    );
+
+    let webhook_service = web::Data::new(
+        services::webhook_service::WebhookService::from_env(pool.as_ref().clone())
+            .expect("Failed to load webhook endpoints"),
+    );
+
+    let notification_dispatcher = web::Data::new(
+        notifier::dispatcher::NotificationDispatcher::from_env(pool.as_ref().clone(), redis_queue.as_ref().clone())
+            .expect("Failed to load notification channels"),
+    );
+
+    let analytics = web::Data::new(
+        analytics::Analytics::from_env(metrics_service.as_ref().clone()).expect("Failed to load analytics sink"),
+    );
+
+    let status_stream = web::Data::new(submissions::status_stream::StatusStream::start(pool.as_ref().clone()));
+
+    let submission_quota = web::Data::new(
+        submissions::submission_quota::SubmissionQuota::from_env(&worker_config.redis)
+            .await
+            .map_err(|e| std::io::Error::other(format!("Failed to initialize submission quotas: {}", e)))?,
+    );
+
+    // New submissions are held or turned away while the upload queue is deep
+    let queue_backpressure = web::Data::new(
+        commons::queue_backpressure::QueueBackpressure::from_env().expect("Failed to load queue backpressure tiers"),
+    );
+    queue_backpressure.spawn_refresh(redis_queue.as_ref().clone(), metrics_service.as_ref().clone());
+
+    let session_store = web::Data::new(
+        commons::session_store::SessionStore::new(&worker_config.redis)
+            .await
+            .expect("Failed to initialize session store"),
+    );
+
+    let password_hash_config = web::Data::new(
+        services::password_hasher::PasswordHashConfig::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load password hash settings: {}", e)))?,
+    );
+
+    let verification_mailer = web::Data::new(
+        notifier::verification::VerificationMailer::from_env(&worker_config.redis)
+            .await
+            .map_err(|e| std::io::Error::other(format!("Failed to load email verification settings: {}", e)))?,
+    );
+
+    let key_provider = web::Data::new(
+        services::key_provider::KeyProvider::from_env().expect("Failed to load JWT keys"),
+    );
+
+    let submission_tokens = web::Data::new(
+        submissions::submission_token::SubmissionTokens::from_env(key_provider.as_ref().clone(), &worker_config.redis)
+            .await
+            .expect("Failed to load submission token settings"),
+    );
+
+    let lock_manager = web::Data::new(
+        commons::distributed_lock::LockManager::new(&worker_config.redis)
+            .await
+            .expect("Failed to initialize lock manager"),
+    );
+
+    let admin_config = web::Data::new(
+        admin::admin_auth::AdminConfig::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load admin configuration: {}", e)))?,
+    );
+
+    let url_expiry = web::Data::new(
+        commons::url_expiry::UrlExpiryConfig::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load URL expiry settings: {}", e)))?,
+    );
+
+    let upload_policy = web::Data::new(
+        commons::upload_policy::UploadPolicyConfig::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load upload policy: {}", e)))?,
+    );
+
+    let decision_rules = web::Data::new(
+        policies::decision::RuleSet::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load decision rules: {}", e)))?,
+    );
+    let resubmission_policy = web::Data::new(
+        policies::resubmission::ResubmissionPolicy::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load resubmission policy: {}", e)))?,
+    );
+    let draft_limits = web::Data::new(
+        submissions::draft::DraftLimits::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load submission draft limits: {}", e)))?,
+    );
+    let ttl_policy = web::Data::new(
+        submissions::submission_ttl::SubmissionTtlPolicy::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load submission TTLs: {}", e)))?,
+    );
+
+    let nfc_replay_guard = web::Data::new(
+        submissions::nfc_replay::NfcReplayGuard::from_env(&worker_config.redis)
+            .await
+            .map_err(|e| std::io::Error::other(format!("Failed to initialize NFC replay protection: {}", e)))?,
+    );
+
+    // Documents wait for a clean scan before processing when a scanner is set up
+    let document_scans = web::Data::new(
+        submissions::document_scans::DocumentScans::from_env(pool.as_ref().clone(), redis_queue.as_ref().clone())
+            .map_err(|e| std::io::Error::other(format!("Failed to load document scanning configuration: {}", e)))?,
+    );
+
+    let shutdown_state = web::Data::new(
+        commons::shutdown::ShutdownState::from_env()
+            .map_err(|e| std::io::Error::other(format!("Failed to load shutdown settings: {}", e)))?,
+    );
+
+    // Maintenance windows are shared through Redis with the other instances
+    let maintenance_mode = web::Data::new(
+        commons::maintenance::MaintenanceMode::new(&worker_config.redis)
+            .await
+            .expect("Failed to initialize maintenance mode"),
+    );
+    maintenance_mode.spawn_refresh();
+
+    // Status polling, stats and search read from the replica when there is one
+    let read_replica = web::Data::new(
+        commons::read_replica::ReadReplica::from_env().expect("Failed to configure the read replica"),
+    );
+    read_replica.spawn_lag_monitor();
+
+    // Routes such as status polling can get a pool of their own
+    let db_pools = web::Data::new(
+        commons::db_pool::DbPools::new(pool.as_ref().clone(), &db_pool_config, &database_url)
+            .expect("Failed to configure the route database pools"),
+    );
+    db_pools.spawn_saturation_monitor(metrics_service.as_ref().clone());
+
+    let tls_config = commons::tls::TlsConfig::from_env().expect("Failed to load TLS configuration");
+
+    // Internal callers can reach the same service layer over gRPC
+    let (grpc_shutdown, grpc_server) = match grpc::GrpcConfig::from_env().expect("Failed to load gRPC configuration") {
+        Some(grpc_config) => {
+            let service = grpc::submission_service::GrpcSubmissionService {
+                pool: pool.as_ref().clone(),
+                read_replica: read_replica.as_ref().clone(),
+                cipher: field_cipher.as_ref().clone(),
+                minio_service: minio_service.clone(),
+                metrics: metrics_service.as_ref().clone(),
+                url_expiry: url_expiry.as_ref().clone(),
+                upload_policy: upload_policy.as_ref().clone(),
+                nfc_replay_guard: nfc_replay_guard.as_ref().clone(),
+                quota: submission_quota.as_ref().clone(),
+                queue: redis_queue.as_ref().clone(),
+                upload_sources: worker_config.upload_sources.clone(),
+                backpressure: queue_backpressure.as_ref().clone(),
+                maintenance: maintenance_mode.as_ref().clone(),
+                analytics: analytics.as_ref().clone(),
+            };
+            let (shutdown_tx, mut shutdown_rx) = tokio::sync::watch::channel(false);
+            let grpc_host = host.clone();
+            let handle = tokio::spawn(async move {
+                let shutdown = async move {
+                    let _ = shutdown_rx.changed().await;
+                };
+                if let Err(e) = grpc::serve(grpc_config, &grpc_host, service, shutdown).await {
+                    warn!("gRPC server failed: {}", e);
+                }
+            });
+            (Some(shutdown_tx), Some(handle))
+        }
+        None => (None, None),
+    };
+    let server_shutdown_state = shutdown_state.clone();
+
     let server = HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(commons::sandbox::route))
+            .wrap(from_fn(commons::api_version::negotiate))
+            .wrap(from_fn(commons::request_limits::enforce_timeout))
+            .wrap(from_fn(commons::shutdown::reject_when_draining))
+            .wrap(from_fn(commons::maintenance::reject_during_maintenance))
+            .wrap(from_fn(commons::db_pool::route_pool))
+            .wrap(from_fn(commons::db_pool::report_busy))
+            .wrap(from_fn(commons::error_catalog::localize))
+            .wrap(tracing_actix_web::TracingLogger::default())
             .app_data(pool.clone())
             .app_data(metrics_service.clone())
             .app_data(face_match_service.clone())
             .app_data(web::Data::new(minio_service.clone()))
+            .app_data(redis_queue.clone())
+            .app_data(admin_config.clone())
+            .app_data(document_upload_config.clone())
+            .app_data(url_expiry.clone())
+            .app_data(upload_policy.clone())
+            .app_data(decision_rules.clone())
+            .app_data(resubmission_policy.clone())
+            .app_data(draft_limits.clone())
+            .app_data(nfc_replay_guard.clone())
+            .app_data(document_scans.clone())
+            .app_data(field_cipher.clone())
+            .app_data(request_limits.clone())
+            .app_data(trusted_proxies.clone())
+            .app_data(webhook_service.clone())
+            .app_data(notification_dispatcher.clone())
+            .app_data(analytics.clone())
+            .app_data(session_store.clone())
+            .app_data(key_provider.clone())
+            .app_data(verification_mailer.clone())
+            .app_data(password_hash_config.clone())
+            .app_data(submission_tokens.clone())
+            .app_data(status_stream.clone())
+            .app_data(submission_quota.clone())
+            .app_data(queue_backpressure.clone())
+            .app_data(ttl_policy.clone())
+            .app_data(lock_manager.clone())
+            .app_data(server_shutdown_state.clone())
+            .app_data(maintenance_mode.clone())
+            .app_data(read_replica.clone())
+            .app_data(db_pools.clone())
+            .app_data(request_limits.json_config())
+            .configure(|cfg| {
+                if let Some(captcha) = &captcha {
+                    cfg.app_data(captcha.clone());
+                }
+                if let Some(sandbox) = &sandbox {
+                    cfg.app_data(sandbox.clone()).service(
+                        web::scope("/sandbox")
+                            .service(controllers::sandbox::put_object)
+                            .service(controllers::sandbox::get_object),
+                    );
+                }
+            })
+            .service(controllers::readiness::readyz)
             .service(
                 web::scope("/v1")
+                    .wrap(from_fn(commons::api_version::deprecate_v1))
                     .service(controllers::auth::register)
                     .service(controllers::auth::login)
+                    .service(controllers::auth::verify_email)
+                    .service(controllers::auth::resend_verification)
+                    .service(controllers::auth::logout)
+                    .service(controllers::auth::logout_all)
+                    .service(controllers::profile::get_profile)
+                    .service(controllers::profile::update_profile)
+                    .service(controllers::profile::delete_account)
+                    .service(controllers::profile::change_password)
                     .service(submissions::submission_controller::presigned_urls)
                     .service(submissions::submission_controller::face_match)
                     .service(submissions::submission_controller::process_submission)
                     .service(submissions::submission_controller::get_submission_status)
+                    .service(submissions::submission_controller::get_submission_events)
+                    .service(submissions::submission_controller::stream_submission_status)
+                    .service(submissions::submission_controller::get_submission_report)
+                    .service(submissions::submission_controller::upload_document)
+                    .service(submissions::submission_controller::refresh_upload_url)
+                    .service(submissions::submission_controller::document_download_url)
+                    .service(submissions::submission_controller::resubmit_submission)
+                    .service(submissions::submission_controller::save_submission_draft)
+                    .service(jobs::job_controller::get_job)
+            )
+            .service(
+                web::scope("/v2")
+                    .service(submissions::submission_controller_v2::create_submission)
+                    .service(submissions::submission_controller_v2::get_submission_status)
+                    .service(submissions::submission_controller_v2::process_submission)
+                    .service(submissions::submission_controller_v2::face_match)
+            )
+            .service(
+                web::scope("/admin")
+                    .wrap(from_fn(admin::admin_auth::require_admin_key))
+                    .service(admin::failed_jobs_controller::list_failed_jobs)
+                    .service(admin::failed_jobs_controller::get_failed_job)
+                    .service(admin::failed_jobs_controller::replay_failed_job)
+                    .service(admin::backfill_controller::bulk_enqueue_jobs)
+                    .service(admin::backfill_controller::get_backfill_run)
+                    .service(admin::job_executions_controller::list_job_executions)
+                    .service(admin::archived_jobs_controller::list_archived_jobs)
+                    .service(admin::archived_jobs_controller::get_archived_job)
+                    .service(admin::archived_jobs_controller::replay_archived_job)
+                    .service(admin::reviews_controller::list_reviews)
+                    .service(admin::reviews_controller::approve_review)
+                    .service(admin::reviews_controller::reject_review)
+                    .service(admin::log_level_controller::get_log_level)
+                    .service(admin::log_level_controller::set_log_level)
+                    .service(admin::stats_controller::get_submission_stats)
+                    .service(admin::jwt_keys_controller::get_jwt_keys)
+                    .service(admin::jwt_keys_controller::reload_jwt_keys)
+                    .service(admin::webhook_deliveries_controller::list_webhook_deliveries)
+                    .service(admin::webhook_deliveries_controller::get_webhook_delivery)
+                    .service(admin::webhook_deliveries_controller::test_webhook)
+                    .service(admin::webhook_deliveries_controller::replay_webhook_delivery)
+                    .service(admin::submissions_controller::search_submissions)
+                    .service(admin::exports_controller::export_submissions)
+                    .service(admin::exports_controller::get_export)
+                    .service(admin::document_access_controller::admin_document_download_url)
+                    .service(admin::document_access_controller::list_document_accesses)
+                    .service(admin::view_as_controller::view_as_user)
+                    .service(admin::view_as_controller::list_view_as_log)
+                    .service(admin::storage_controller::get_orphan_cleanup_run)
+                    .service(admin::storage_controller::copy_object)
+                    .service(admin::maintenance_controller::get_maintenance)
+                    .service(admin::maintenance_controller::start_maintenance)
+                    .service(admin::maintenance_controller::end_maintenance)
             )
     })
-    .bind(format!("{}:{}", host, port))?
+    // Shutdown is driven by the task below so the API can drain first
+    .disable_signals();
+
+    // Tuning left unset keeps actix's defaults
+    let mut server = server;
+    if let Some(workers) = http_server_config.workers {
+        server = server.workers(workers);
+    }
+    if let Some(keep_alive) = http_server_config.keep_alive {
+        server = server.keep_alive(keep_alive);
+    }
+    if let Some(client_request_timeout) = http_server_config.client_request_timeout {
+        server = server.client_request_timeout(client_request_timeout);
+    }
+    if let Some(backlog) = http_server_config.backlog {
+        server = server.backlog(backlog);
+    }
+    if let Some(max_connections) = http_server_config.max_connections {
+        server = server.max_connections(max_connections);
+    }
+
+    let server = match &tls_config {
+        Some(tls_config) => server.bind_rustls_0_23(
+            format!("{}:{}", host, port),
+            tls_config.server_config().expect("Failed to load TLS certificate"),
+        )?,
+        None => server.bind(format!("{}:{}", host, port))?,
+    }
     .run();
 
+    let redirect_server = match tls_config.as_ref().and_then(|tls_config| tls_config.redirect_port) {
+        Some(redirect_port) => {
+            let https_port = port.parse::<u16>().expect("PORT must be a port number");
+            let redirect_server = commons::tls::start_redirect_server(&host, redirect_port, https_port)?;
+            let handle = redirect_server.handle();
+            tokio::spawn(redirect_server);
+            Some(handle)
+        }
+        None => None,
+    };
+
     // Set up graceful shutdown for both the server and worker (if enabled)
     let server_handle = server.handle();
     let main_worker_ref = Arc::new(main_worker);
@@ -163,9 +690,13 @@ async fn main() -> std::io::Result<()> {
     // Handle graceful shutdown
     tokio::spawn(async move {
         // Wait for interrupt signal
-        match signal::ctrl_c().await {
+        match shutdown_signal().await {
             Ok(()) => {
                 info!("Shutdown signal received, starting graceful shutdown");
+                let drain_started = tokio::time::Instant::now();
+
+                // Turn away new submissions while in-flight requests finish
+                shutdown_state.begin_drain();
                 
                 // Signal the worker to stop (if it's running)
                 if worker_config.background_worker_thread_enabled {
@@ -178,9 +709,18 @@ async fn main() -> std::io::Result<()> {
                     }
                 }
                 
-                // Stop the HTTP server gracefully
+                // Keep answering reads for the rest of the drain period
+                tokio::time::sleep_until(drain_started + shutdown_state.drain_period).await;
+
+                // Stop the HTTP and gRPC servers gracefully
                 info!("Shutting down HTTP server");
+                if let Some(grpc_shutdown) = grpc_shutdown {
+                    let _ = grpc_shutdown.send(true);
+                }
                 server_handle.stop(true).await;
+                if let Some(redirect_server) = redirect_server {
+                    redirect_server.stop(true).await;
+                }
                 info!("Graceful shutdown completed");
             }
             Err(e) => warn!("Error waiting for interrupt signal: {}", e),
@@ -188,8 +728,33 @@ async fn main() -> std::io::Result<()> {
     });
 
     // Start the server and wait for it to finish
-    info!("API server starting at {}:{}", host, port);
+    info!(
+        "API server starting at {}://{}:{}",
+        if tls_config.is_some() { "https" } else { "http" },
+        host,
+        port
+    );
     server.await?;
+    if let Some(grpc_server) = grpc_server {
+        let _ = grpc_server.await;
+    }
+    commons::telemetry::shutdown();
     
     Ok(())
 }
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())?;
+
+        tokio::select! {
+            result = signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    signal::ctrl_c().await
+}
@@ -7,8 +7,13 @@ pub struct User {
     pub id: i32,
     pub name: String,
     pub email: String,
+    pub phone: Option<String>,
     #[serde(skip_serializing)]
     pub password_hash: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// Set when the owner closed the account; it is purged after the retention window
+    #[serde(skip_serializing, default)]
+    pub deleted_at: Option<DateTime<Utc>>,
     // pub created_at: Option<DateTime<Utc>>,
     // pub updated_at: Option<DateTime<Utc>>,
 }
@@ -31,6 +36,38 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProfileRequest {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: Option<String>,
+    #[validate(length(min = 6, max = 20, message = "Phone must be between 6 and 20 characters"))]
+    pub phone: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangePasswordResponse {
+    /// Other sessions of the user that were ended
+    pub revoked_sessions: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyEmailResponse {
+    pub email: String,
+    pub email_verified_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
@@ -38,6 +75,17 @@ pub struct AuthResponse {
 }
 
 #[derive(Debug, Serialize)]
+pub struct ResendVerificationResponse {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogoutResponse {
+    pub revoked_sessions: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "client", derive(Deserialize))]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
@@ -45,8 +93,12 @@ pub struct ApiResponse<T> {
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "client", derive(Deserialize))]
 pub struct ApiError {
     pub entity: String,
     pub code: String,
     pub cause: String,
+    /// `cause` worded for users, in the language the request asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
 } 
\ No newline at end of file
@@ -0,0 +1,91 @@
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::{
+    notifier::{notification_repository::NotificationRepository, Channel, Outcome},
+    workers::{FileUploadJob, RedisQueue},
+};
+
+/// Queues a notification job per channel when a submission is decided. The
+/// worker renders and sends them, retrying like any other job.
+#[derive(Clone)]
+pub struct NotificationDispatcher {
+    repository: Arc<NotificationRepository>,
+    queue: RedisQueue,
+    /// Channels of tenants without settings, from `NOTIFICATION_CHANNELS`
+    default_channels: Vec<Channel>,
+}
+
+impl NotificationDispatcher {
+    pub fn from_env(pool: PgPool, queue: RedisQueue) -> anyhow::Result<Self> {
+        let default_channels = std::env::var("NOTIFICATION_CHANNELS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|channel| !channel.is_empty())
+            .map(|channel| channel.parse::<Channel>().map_err(anyhow::Error::msg))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            repository: Arc::new(NotificationRepository::new(pool)),
+            queue,
+            default_channels,
+        })
+    }
+
+    /// Notify the owner of a submission that moved to `status`, in the
+    /// background. Statuses other than APPROVED and REJECTED are ignored.
+    pub fn notify(&self, submission_id: &str, tenant_id: &str, submission_type: &str, status: &str, reason_code: &str) {
+        let Some(outcome) = Outcome::from_status(status) else {
+            return;
+        };
+
+        let dispatcher = self.clone();
+        let metadata = json!({
+            "submissionId": submission_id,
+            "tenantId": tenant_id,
+            "submissionType": submission_type,
+            "outcome": outcome.as_str(),
+            "reasonCode": reason_code,
+        });
+        let submission_id = submission_id.to_string();
+        let tenant_id = tenant_id.to_string();
+
+        tokio::spawn(async move {
+            if let Err(e) = dispatcher.enqueue(&submission_id, &tenant_id, outcome, metadata).await {
+                error!("Failed to queue {} notifications of submission {}: {}", outcome, submission_id, e);
+            }
+        });
+    }
+
+    async fn enqueue(
+        &self,
+        submission_id: &str,
+        tenant_id: &str,
+        outcome: Outcome,
+        metadata: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let channels: Vec<Channel> = match self.repository.find_settings(tenant_id, outcome).await? {
+            Some(settings) => settings
+                .into_iter()
+                .filter(|setting| setting.enabled)
+                .map(|setting| setting.channel)
+                .collect(),
+            None => self.default_channels.clone(),
+        };
+        if channels.is_empty() {
+            return Ok(());
+        }
+
+        let jobs: Vec<FileUploadJob> = channels
+            .iter()
+            .map(|channel| FileUploadJob::notification(submission_id.to_string(), *channel, metadata.clone()))
+            .collect();
+        let enqueued = self.queue.clone().enqueue_jobs(&jobs).await?;
+
+        info!("Queued {} {} notifications of submission {}", enqueued, outcome, submission_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::Context;
+use futures::future::BoxFuture;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
+};
+
+use super::{Channel, Notification, NotificationChannel, NotifyError};
+
+/// Sends notifications as plain text email over SMTP
+pub struct SmtpChannel {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpChannel {
+    /// None unless `SMTP_HOST` is set. `SMTP_SECURITY` is `starttls` (the
+    /// default), `tls` for implicit TLS, or `none` for a local relay.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let Some(host) = var("SMTP_HOST") else {
+            return Ok(None);
+        };
+
+        let mut builder = match var("SMTP_SECURITY").as_deref().unwrap_or("starttls") {
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            other => anyhow::bail!("SMTP_SECURITY must be starttls, tls or none, got {}", other),
+        };
+        if let Some(port) = var("SMTP_PORT") {
+            builder = builder.port(port.parse().context("SMTP_PORT must be a port number")?);
+        }
+        if let Some(username) = var("SMTP_USERNAME") {
+            builder = builder.credentials(Credentials::new(username, var("SMTP_PASSWORD").unwrap_or_default()));
+        }
+
+        let from = var("SMTP_FROM")
+            .context("SMTP_FROM must be set when SMTP_HOST is set")?
+            .parse()
+            .context("SMTP_FROM must be an email address")?;
+
+        Ok(Some(Self {
+            transport: builder.build(),
+            from,
+        }))
+    }
+}
+
+impl NotificationChannel for SmtpChannel {
+    fn channel(&self) -> Channel {
+        Channel::Email
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<(), NotifyError>> {
+        Box::pin(async move {
+            let to: Mailbox = notification
+                .recipient
+                .parse()
+                .map_err(|e| NotifyError::Rejected(format!("Invalid email address: {}", e)))?;
+
+            let email = lettre::Message::builder()
+                .from(self.from.clone())
+                .to(to)
+                .subject(&notification.subject)
+                .header(ContentType::TEXT_PLAIN)
+                .body(notification.body.clone())
+                .map_err(|e| NotifyError::Rejected(format!("Failed to build email: {}", e)))?;
+
+            match self.transport.send(email).await {
+                Ok(_) => Ok(()),
+                Err(e) if e.is_permanent() => Err(NotifyError::Rejected(format!("SMTP server rejected the email: {}", e))),
+                Err(e) => Err(NotifyError::Transient(format!("Failed to send email: {}", e))),
+            }
+        })
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Context;
+use futures::future::BoxFuture;
+use serde_json::json;
+use std::time::Duration;
+
+use super::{Channel, Notification, NotificationChannel, NotifyError};
+
+/// Sends SMS or WhatsApp messages through an HTTP gateway. The gateway gets a
+/// JSON `{to, message, channel, reference}` POST with the API key as a bearer
+/// token, and the reference as `Idempotency-Key` so retries aren't sent twice.
+pub struct HttpGatewayChannel {
+    channel: Channel,
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl HttpGatewayChannel {
+    /// None unless `<PREFIX>_GATEWAY_URL` is set, e.g. `SMS_GATEWAY_URL` with
+    /// `SMS_GATEWAY_API_KEY`
+    pub fn from_env(channel: Channel, prefix: &str) -> anyhow::Result<Option<Self>> {
+        let var = |name: &str| std::env::var(format!("{}_{}", prefix, name)).ok().filter(|v| !v.is_empty());
+
+        let Some(url) = var("GATEWAY_URL") else {
+            return Ok(None);
+        };
+        let timeout_millis = std::env::var("NOTIFICATION_GATEWAY_TIMEOUT_MILLIS")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .context("NOTIFICATION_GATEWAY_TIMEOUT_MILLIS must be a number")?
+            .unwrap_or(10_000);
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(timeout_millis))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Some(Self {
+            channel,
+            client,
+            url,
+            api_key: var("GATEWAY_API_KEY"),
+        }))
+    }
+}
+
+impl NotificationChannel for HttpGatewayChannel {
+    fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<(), NotifyError>> {
+        Box::pin(async move {
+            let mut request = self
+                .client
+                .post(&self.url)
+                .header("Idempotency-Key", &notification.reference)
+                .json(&json!({
+                    "to": notification.recipient,
+                    "message": notification.body,
+                    "channel": self.channel.as_str(),
+                    "reference": notification.reference,
+                }));
+            if let Some(api_key) = &self.api_key {
+                request = request.bearer_auth(api_key);
+            }
+
+            let response = request
+                .send()
+                .await
+                .map_err(|e| NotifyError::Transient(format!("{} gateway request failed: {}", self.channel, e)))?;
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+            let body = response.text().await.unwrap_or_default();
+            let message = format!("{} gateway returned {}: {}", self.channel, status, body.chars().take(500).collect::<String>());
+            // Client errors won't change on retry, except timeouts and rate limits
+            if status.is_client_error()
+                && status != reqwest::StatusCode::REQUEST_TIMEOUT
+                && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+            {
+                Err(NotifyError::Rejected(message))
+            } else {
+                Err(NotifyError::Transient(message))
+            }
+        })
+    }
+}
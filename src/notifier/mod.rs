@@ -0,0 +1,109 @@
+pub mod dispatcher;
+pub mod email;
+pub mod gateway;
+pub mod notification_repository;
+pub mod templates;
+pub mod verification;
+
+use futures::future::BoxFuture;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// How a user is told about their submission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Email,
+    Sms,
+    WhatsApp,
+}
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Email => "EMAIL",
+            Channel::Sms => "SMS",
+            Channel::WhatsApp => "WHATSAPP",
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "EMAIL" => Ok(Channel::Email),
+            "SMS" => Ok(Channel::Sms),
+            "WHATSAPP" => Ok(Channel::WhatsApp),
+            other => Err(format!("Unknown notification channel {}", other)),
+        }
+    }
+}
+
+/// Submission statuses users are notified of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Approved,
+    Rejected,
+}
+
+impl Outcome {
+    /// None for statuses that aren't a final decision
+    pub fn from_status(status: &str) -> Option<Self> {
+        match status {
+            "APPROVED" => Some(Outcome::Approved),
+            "REJECTED" => Some(Outcome::Rejected),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Approved => "APPROVED",
+            Outcome::Rejected => "REJECTED",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A rendered message on its way to one recipient
+#[derive(Debug, Clone)]
+pub struct Notification {
+    /// Stable across retries, so gateways can drop duplicates
+    pub reference: String,
+    /// Email address or phone number, depending on the channel
+    pub recipient: String,
+    /// Only used by email
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    /// The provider may accept the message on another attempt
+    #[error("{0}")]
+    Transient(String),
+
+    /// The provider refused the message for good, e.g. an invalid recipient
+    #[error("{0}")]
+    Rejected(String),
+}
+
+/// A provider delivering notifications on one channel
+pub trait NotificationChannel: Send + Sync {
+    fn channel(&self) -> Channel;
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<(), NotifyError>>;
+}
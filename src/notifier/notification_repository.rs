@@ -0,0 +1,109 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    commons::tenant::DEFAULT_TENANT,
+    notifier::{templates::Template, Channel, Outcome},
+};
+
+/// A channel an outcome is sent on, with the tenant's templates if it has any
+#[derive(Debug, Clone)]
+pub struct NotificationSetting {
+    pub channel: Channel,
+    pub enabled: bool,
+    pub subject_template: Option<String>,
+    pub body_template: Option<String>,
+}
+
+impl NotificationSetting {
+    /// The tenant's templates, filling in missing parts from the built-in one
+    pub fn template(&self, outcome: Outcome) -> Template {
+        let default = Template::default_for(self.channel, outcome);
+        Template {
+            subject: self.subject_template.clone().unwrap_or(default.subject),
+            body: self.body_template.clone().unwrap_or(default.body),
+        }
+    }
+}
+
+/// Who a submission's notifications go to
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    pub name: String,
+    pub email: String,
+    pub phone: Option<String>,
+}
+
+pub struct NotificationRepository {
+    pool: PgPool,
+}
+
+impl NotificationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Notification settings of a tenant for an outcome, or of the default
+    /// tenant when the tenant has none; None when neither has rows. Disabled
+    /// rows count as the tenant's own, so a tenant can opt out of a channel.
+    pub async fn find_settings(
+        &self,
+        tenant_id: &str,
+        outcome: Outcome,
+    ) -> Result<Option<Vec<NotificationSetting>>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT tenant_id, channel, enabled, subject_template, body_template
+            FROM notification_settings
+            WHERE outcome = $2 AND tenant_id IN ($1, $3)
+            ORDER BY id
+            "#,
+            tenant_id,
+            outcome.as_str(),
+            DEFAULT_TENANT
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let owner = if rows.iter().any(|r| r.tenant_id == tenant_id) { tenant_id } else { DEFAULT_TENANT };
+        if !rows.iter().any(|r| r.tenant_id == owner) {
+            return Ok(None);
+        }
+
+        let settings = rows
+            .into_iter()
+            .filter(|r| r.tenant_id == owner)
+            .filter_map(|r| {
+                Some(NotificationSetting {
+                    channel: r.channel.parse().ok()?,
+                    enabled: r.enabled,
+                    subject_template: r.subject_template,
+                    body_template: r.body_template,
+                })
+            })
+            .collect();
+
+        Ok(Some(settings))
+    }
+
+    /// The owner of a submission, unless their account was closed
+    pub async fn find_recipient(&self, submission_id: Uuid) -> Result<Option<Recipient>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT u.name, u.email, u.phone
+            FROM submissions s
+            JOIN users u ON u.id::TEXT = s.user_id
+            WHERE s.submission_id = $1 AND u.deleted_at IS NULL
+            "#,
+            submission_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result.map(|r| Recipient {
+            name: r.name,
+            email: r.email,
+            phone: r.phone,
+        }))
+    }
+}
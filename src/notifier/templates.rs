@@ -0,0 +1,61 @@
+use super::{Channel, Outcome};
+
+/// A message before its placeholders are filled in. Placeholders are
+/// `{{name}}`, `{{submissionId}}`, `{{submissionType}}`, `{{outcome}}` and
+/// `{{reasonCode}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pub subject: String,
+    pub body: String,
+}
+
+/// Values substituted into a template
+#[derive(Debug, Clone)]
+pub struct TemplateValues<'a> {
+    pub name: &'a str,
+    pub submission_id: &'a str,
+    pub submission_type: &'a str,
+    pub outcome: Outcome,
+    pub reason_code: &'a str,
+}
+
+impl Template {
+    /// Built-in message of an outcome; text messages stay short
+    pub fn default_for(channel: Channel, outcome: Outcome) -> Self {
+        let (subject, email_body, text_body) = match outcome {
+            Outcome::Approved => (
+                "Your identity verification was approved",
+                "Hi {{name}},\n\nYour {{submissionType}} verification ({{submissionId}}) was approved. No further action is needed.",
+                "Hi {{name}}, your {{submissionType}} verification was approved.",
+            ),
+            Outcome::Rejected => (
+                "Your identity verification was rejected",
+                "Hi {{name}},\n\nYour {{submissionType}} verification ({{submissionId}}) was rejected ({{reasonCode}}). You can submit your documents again.",
+                "Hi {{name}}, your {{submissionType}} verification was rejected ({{reasonCode}}). You can submit again.",
+            ),
+        };
+
+        Self {
+            subject: subject.to_string(),
+            body: match channel {
+                Channel::Email => email_body,
+                Channel::Sms | Channel::WhatsApp => text_body,
+            }
+            .to_string(),
+        }
+    }
+
+    /// The subject and body with placeholders filled in; unknown ones are kept as is
+    pub fn render(&self, values: &TemplateValues) -> (String, String) {
+        (render(&self.subject, values), render(&self.body, values))
+    }
+}
+
+fn render(template: &str, values: &TemplateValues) -> String {
+    template
+        .replace("{{name}}", values.name)
+        .replace("{{submissionId}}", values.submission_id)
+        .replace("{{submissionType}}", values.submission_type)
+        .replace("{{outcome}}", values.outcome.as_str())
+        .replace("{{reasonCode}}", values.reason_code)
+}
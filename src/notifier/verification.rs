@@ -0,0 +1,94 @@
+use anyhow::Context;
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+
+use crate::{
+    commons::redis_connection::{RedisConnection, RedisTopology},
+    models::user::User,
+    notifier::{email::SmtpChannel, Notification, NotificationChannel},
+    services::auth_service::VERIFICATION_TOKEN_TTL_HOURS,
+};
+
+/// Emails new users the link verifying their address, over the same SMTP
+/// channel submission notifications use
+#[derive(Clone)]
+pub struct VerificationMailer {
+    /// None without `SMTP_HOST`; links are then only logged as not sent
+    channel: Option<Arc<dyn NotificationChannel>>,
+    /// Page the link points at, `EMAIL_VERIFICATION_URL`; the token is
+    /// appended as the `token` query parameter
+    link_base: String,
+    connection_manager: RedisConnection,
+    /// Minimum time between two resends to the same user
+    resend_cooldown: Duration,
+}
+
+impl VerificationMailer {
+    pub async fn from_env(redis: &RedisTopology) -> anyhow::Result<Self> {
+        let channel = SmtpChannel::from_env()?.map(|channel| Arc::new(channel) as Arc<dyn NotificationChannel>);
+        if channel.is_none() {
+            warn!("SMTP_HOST is not set, verification emails won't be sent");
+        }
+
+        let link_base = std::env::var("EMAIL_VERIFICATION_URL")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "http://localhost:8080/v1/verify-email".to_string());
+        let resend_cooldown = Duration::from_secs(
+            std::env::var("EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .context("EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS must be a number of seconds")?,
+        );
+
+        Ok(Self {
+            channel,
+            link_base,
+            connection_manager: redis.connect().await?,
+            resend_cooldown,
+        })
+    }
+
+    /// Whether another link may be sent to the user now. A true answer starts
+    /// the cooldown.
+    pub async fn claim_resend(&self, user_id: i32) -> redis::RedisResult<bool> {
+        let mut conn = self.connection_manager.clone();
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(format!("verification_resend:{}", user_id))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(self.resend_cooldown.as_secs().max(1))
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(claimed.is_some())
+    }
+
+    /// Send the link with `token` to the user in the background
+    pub fn send(&self, user: &User, token: &str) {
+        let Some(channel) = self.channel.clone() else {
+            warn!("Verification email for user {} not sent, no SMTP server configured", user.id);
+            return;
+        };
+
+        let link = format!("{}?token={}", self.link_base, token);
+        let notification = Notification {
+            reference: format!("email_verification:{}", user.id),
+            recipient: user.email.clone(),
+            subject: "Verify your email address".to_string(),
+            body: format!(
+                "Hi {},\n\nOpen this link to verify your email address:\n\n{}\n\nThe link expires in {} hours.",
+                user.name, link, VERIFICATION_TOKEN_TTL_HOURS
+            ),
+        };
+        let user_id = user.id;
+
+        tokio::spawn(async move {
+            match channel.send(&notification).await {
+                Ok(()) => info!("Sent verification email to user {}", user_id),
+                Err(e) => error!("Failed to send verification email to user {}: {}", user_id, e),
+            }
+        });
+    }
+}
@@ -0,0 +1,229 @@
+use serde_json::Value;
+
+use crate::{
+    commons::crypto::NIK_REQUEST_FIELD,
+    policies::{
+        face_match_policy::{FaceMatchDecision, FaceMatchPolicy},
+        identity_document::{self, ExtractedFields},
+    },
+    submissions::{device_info::DeviceInfo, submission_documents::DocumentType},
+};
+
+/// Key of the liveness check outcome (a boolean) in `request_data`
+pub const LIVENESS_REQUEST_FIELD: &str = "livenessPassed";
+/// Key of the NIK read from the identity card's chip in `request_data`
+pub const NFC_NIK_REQUEST_FIELD: &str = "nfcNik";
+
+/// A check the decision engine runs besides the face match band
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionRule {
+    /// The liveness check passed
+    LivenessPassed,
+    /// The NIK read from the KTP photo matches the one on the chip
+    NikMatchesNfc,
+    /// No other user has an approved submission with the same NIK
+    UniqueNik,
+    /// The app didn't report an emulator or a rooted device
+    GenuineDevice,
+    /// The device's locale region and time zone are Indonesian
+    DeviceLocaleMatches,
+    /// The fields read from the identity card hold together under the rules
+    /// of the country that issued it
+    DocumentFieldsValid,
+}
+
+impl DecisionRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecisionRule::LivenessPassed => "LIVENESS_PASSED",
+            DecisionRule::NikMatchesNfc => "NIK_MATCHES_NFC",
+            DecisionRule::UniqueNik => "UNIQUE_NIK",
+            DecisionRule::GenuineDevice => "GENUINE_DEVICE",
+            DecisionRule::DeviceLocaleMatches => "DEVICE_LOCALE_MATCHES",
+            DecisionRule::DocumentFieldsValid => "DOCUMENT_FIELDS_VALID",
+        }
+    }
+
+    /// Rules on what the app says about its own device. Nothing attests it
+    /// (no Play Integrity or App Attest verdict), so a failure only ever
+    /// sends the submission to review and a pass vouches for nothing.
+    pub fn is_advisory(&self) -> bool {
+        matches!(self, DecisionRule::GenuineDevice | DecisionRule::DeviceLocaleMatches)
+    }
+}
+
+impl std::str::FromStr for DecisionRule {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "LIVENESS_PASSED" => Ok(DecisionRule::LivenessPassed),
+            "NIK_MATCHES_NFC" => Ok(DecisionRule::NikMatchesNfc),
+            "UNIQUE_NIK" => Ok(DecisionRule::UniqueNik),
+            "GENUINE_DEVICE" => Ok(DecisionRule::GenuineDevice),
+            "DEVICE_LOCALE_MATCHES" => Ok(DecisionRule::DeviceLocaleMatches),
+            "DOCUMENT_FIELDS_VALID" => Ok(DecisionRule::DocumentFieldsValid),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A rule and where a submission failing it goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleConfig {
+    pub rule: DecisionRule,
+    /// `AutoReject` or `ManualReview`
+    pub on_failure: FaceMatchDecision,
+}
+
+/// What the rules are evaluated against. Missing signals fail their rule.
+#[derive(Debug, Clone, Default)]
+pub struct DecisionSignals {
+    pub liveness_passed: Option<bool>,
+    /// NIK read from the KTP photo
+    pub ocr_nik: Option<String>,
+    /// NIK read from the chip
+    pub nfc_nik: Option<String>,
+    /// Whether another user has an approved submission with `ocr_nik`
+    pub duplicate_nik: Option<bool>,
+    /// Device the submission was created from, as the app reported it
+    pub device: Option<DeviceInfo>,
+    /// What was read from the identity card
+    pub document_fields: ExtractedFields,
+}
+
+impl DecisionSignals {
+    /// Signals stored in a submission's decrypted `request_data` by the OCR
+    /// and liveness integrations
+    pub fn from_request_data(request_data: &Value, duplicate_nik: Option<bool>) -> Self {
+        let text = |key: &str| request_data.get(key).and_then(Value::as_str).filter(|v| !v.is_empty()).map(str::to_string);
+
+        Self {
+            liveness_passed: request_data.get(LIVENESS_REQUEST_FIELD).and_then(Value::as_bool),
+            ocr_nik: text(NIK_REQUEST_FIELD),
+            nfc_nik: text(NFC_NIK_REQUEST_FIELD),
+            duplicate_nik,
+            device: None,
+            document_fields: ExtractedFields::from_request_data(request_data),
+        }
+    }
+}
+
+/// The outcome of a rule set with the reasons behind it; approvals have none
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub decision: FaceMatchDecision,
+    pub reasons: Vec<String>,
+}
+
+/// Rules applied to submissions of a tenant and type, on top of the face
+/// match band
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    pub rules: Vec<RuleConfig>,
+}
+
+impl RuleSet {
+    /// Rules from `DECISION_RULES`, e.g. "UNIQUE_NIK=REJECTED,LIVENESS_PASSED"
+    /// (failing rules without a target go to manual review); none when unset
+    pub fn from_env() -> anyhow::Result<Self> {
+        let rules = std::env::var("DECISION_RULES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (rule, on_failure) = entry.split_once('=').unwrap_or((entry, "MANUAL_REVIEW"));
+                Ok(RuleConfig {
+                    rule: rule
+                        .trim()
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("DECISION_RULES names unknown rule {}", rule.trim()))?,
+                    on_failure: on_failure_decision(on_failure.trim()).ok_or_else(|| {
+                        anyhow::anyhow!("DECISION_RULES targets must be REJECTED or MANUAL_REVIEW, got {}", entry)
+                    })?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { rules })
+    }
+
+    pub fn requires(&self, rule: DecisionRule) -> bool {
+        self.rules.iter().any(|config| config.rule == rule)
+    }
+
+    /// Combine the face match band with every rule: any rejection rejects,
+    /// otherwise any review reviews
+    pub fn evaluate(&self, policy: &FaceMatchPolicy, band: FaceMatchDecision, signals: &DecisionSignals) -> Decision {
+        let mut decision = band;
+        let mut reasons = Vec::new();
+
+        if band != FaceMatchDecision::AutoApprove {
+            reasons.push(policy.reason_code(band));
+        }
+
+        for config in &self.rules {
+            let Some(reason) = failure(config.rule, signals) else {
+                continue;
+            };
+            // Rules sharing a signal fail the same way without it
+            if !reasons.iter().any(|r| r == reason) {
+                reasons.push(reason.to_string());
+            }
+            let on_failure = if config.rule.is_advisory() {
+                FaceMatchDecision::ManualReview
+            } else {
+                config.on_failure
+            };
+            decision = match (decision, on_failure) {
+                (FaceMatchDecision::AutoReject, _) | (_, FaceMatchDecision::AutoReject) => FaceMatchDecision::AutoReject,
+                _ => FaceMatchDecision::ManualReview,
+            };
+        }
+
+        Decision { decision, reasons }
+    }
+}
+
+/// Decision a failing rule leads to, from its stored name
+pub fn on_failure_decision(on_failure: &str) -> Option<FaceMatchDecision> {
+    match on_failure {
+        "REJECTED" => Some(FaceMatchDecision::AutoReject),
+        "MANUAL_REVIEW" => Some(FaceMatchDecision::ManualReview),
+        _ => None,
+    }
+}
+
+/// Reason a rule fails for, if it does
+fn failure(rule: DecisionRule, signals: &DecisionSignals) -> Option<&'static str> {
+    match rule {
+        DecisionRule::LivenessPassed => match signals.liveness_passed {
+            Some(true) => None,
+            Some(false) => Some("LIVENESS_FAILED"),
+            None => Some("LIVENESS_NOT_CHECKED"),
+        },
+        DecisionRule::NikMatchesNfc => match (&signals.ocr_nik, &signals.nfc_nik) {
+            (Some(ocr_nik), Some(nfc_nik)) if ocr_nik == nfc_nik => None,
+            (Some(_), Some(_)) => Some("NIK_MISMATCH"),
+            _ => Some("NIK_NOT_AVAILABLE"),
+        },
+        DecisionRule::UniqueNik => match (&signals.ocr_nik, signals.duplicate_nik) {
+            (Some(_), Some(false)) => None,
+            (Some(_), Some(true)) => Some("NIK_DUPLICATE"),
+            _ => Some("NIK_NOT_AVAILABLE"),
+        },
+        DecisionRule::GenuineDevice => match &signals.device {
+            Some(device) if device.emulator == Some(true) => Some("DEVICE_EMULATOR"),
+            Some(device) if device.rooted == Some(true) => Some("DEVICE_ROOTED"),
+            Some(_) => None,
+            None => Some("DEVICE_INFO_NOT_AVAILABLE"),
+        },
+        DecisionRule::DeviceLocaleMatches => match &signals.device {
+            Some(device) if device.locale_mismatch() => Some("DEVICE_LOCALE_MISMATCH"),
+            Some(_) => None,
+            None => Some("DEVICE_INFO_NOT_AVAILABLE"),
+        },
+        DecisionRule::DocumentFieldsValid => identity_document::validate(DocumentType::Ktp, &signals.document_fields).err(),
+    }
+}
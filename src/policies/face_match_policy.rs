@@ -0,0 +1,71 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FaceMatchDecision {
+    AutoApprove,
+    ManualReview,
+    AutoReject,
+}
+
+impl FaceMatchDecision {
+    /// Submission status stored for this decision
+    pub fn submission_status(&self) -> &'static str {
+        match self {
+            FaceMatchDecision::AutoApprove => "APPROVED",
+            FaceMatchDecision::ManualReview => "MANUAL_REVIEW",
+            FaceMatchDecision::AutoReject => "REJECTED",
+        }
+    }
+}
+
+impl std::fmt::Display for FaceMatchDecision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FaceMatchDecision::AutoApprove => write!(f, "AUTO_APPROVE"),
+            FaceMatchDecision::ManualReview => write!(f, "MANUAL_REVIEW"),
+            FaceMatchDecision::AutoReject => write!(f, "AUTO_REJECT"),
+        }
+    }
+}
+
+/// Decision bands for a tenant and submission type
+#[derive(Debug, Clone)]
+pub struct FaceMatchPolicy {
+    pub id: Option<i64>,
+    pub tenant_id: String,
+    pub submission_type: String,
+    pub approve_threshold: f64,
+    pub reject_threshold: f64,
+}
+
+impl FaceMatchPolicy {
+    /// A policy without a manual review band, used when nothing is configured
+    pub fn single_threshold(tenant_id: &str, submission_type: &str, threshold: f64) -> Self {
+        Self {
+            id: None,
+            tenant_id: tenant_id.to_string(),
+            submission_type: submission_type.to_string(),
+            approve_threshold: threshold,
+            reject_threshold: threshold,
+        }
+    }
+
+    pub fn decide(&self, score: f64) -> FaceMatchDecision {
+        if score >= self.approve_threshold {
+            FaceMatchDecision::AutoApprove
+        } else if score < self.reject_threshold {
+            FaceMatchDecision::AutoReject
+        } else {
+            FaceMatchDecision::ManualReview
+        }
+    }
+
+    /// Reason code stored alongside the decision, e.g. `POLICY_3_MANUAL_REVIEW`
+    pub fn reason_code(&self, decision: FaceMatchDecision) -> String {
+        match self.id {
+            Some(id) => format!("POLICY_{}_{}", id, decision),
+            None => format!("DEFAULT_THRESHOLD_{}", decision),
+        }
+    }
+}
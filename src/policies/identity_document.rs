@@ -0,0 +1,153 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use chrono::{Datelike, NaiveDate};
+use serde_json::Value;
+
+use crate::{commons::crypto::NIK_REQUEST_FIELD, submissions::submission_documents::DocumentType};
+
+/// Key of the birth date read from the identity card in `request_data`
+pub const BIRTH_DATE_REQUEST_FIELD: &str = "birthDate";
+/// Key of the ISO 3166 country that issued the identity card in `request_data`
+pub const DOCUMENT_COUNTRY_REQUEST_FIELD: &str = "documentCountry";
+/// Country assumed when the app doesn't say; the KTP is Indonesian
+pub const DEFAULT_DOCUMENT_COUNTRY: &str = "ID";
+
+/// Province codes of the NIK, with the highest regency and city code issued
+const KTP_REGIONS: &str = include_str!("ktp_regions.csv");
+
+/// Women's NIKs carry their day of birth plus 40
+const FEMALE_DAY_OFFSET: u32 = 40;
+
+/// Fields the OCR integration read from an identity card
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractedFields {
+    pub country: String,
+    pub nik: Option<String>,
+    pub birth_date: Option<String>,
+}
+
+impl ExtractedFields {
+    pub fn from_request_data(request_data: &Value) -> Self {
+        let text = |key: &str| request_data.get(key).and_then(Value::as_str).map(str::trim).filter(|v| !v.is_empty());
+
+        Self {
+            country: text(DOCUMENT_COUNTRY_REQUEST_FIELD)
+                .unwrap_or(DEFAULT_DOCUMENT_COUNTRY)
+                .to_uppercase(),
+            nik: text(NIK_REQUEST_FIELD).map(str::to_string),
+            birth_date: text(BIRTH_DATE_REQUEST_FIELD).map(str::to_string),
+        }
+    }
+}
+
+/// Check the fields against the rules of the country and kind of document
+/// they were read from; `Err` is the reason they fail
+pub fn validate(document_type: DocumentType, fields: &ExtractedFields) -> Result<(), &'static str> {
+    match (fields.country.as_str(), document_type) {
+        ("ID", DocumentType::Ktp) => validate_ktp(fields),
+        _ => Err("DOCUMENT_COUNTRY_NOT_SUPPORTED"),
+    }
+}
+
+/// A NIK is `PPRRDDddmmyySSSS`: province, regency or city, district, date of
+/// birth (day plus 40 for women) and a serial. It has no check digit, so each
+/// part is checked for what it may hold instead.
+fn validate_ktp(fields: &ExtractedFields) -> Result<(), &'static str> {
+    let nik = fields.nik.as_deref().ok_or("NIK_NOT_AVAILABLE")?;
+    if nik.len() != 16 || !nik.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("NIK_INVALID_FORMAT");
+    }
+    let part = |range: std::ops::Range<usize>| nik[range].parse::<u32>().unwrap_or_default();
+
+    if part(4..6) == 0 || part(12..16) == 0 {
+        return Err("NIK_INVALID_FORMAT");
+    }
+    if !known_region(part(0..2), part(2..4)) {
+        return Err("NIK_UNKNOWN_REGION");
+    }
+
+    let (day, month, year) = (part(6..8), part(8..10), part(10..12));
+    let day = if day > FEMALE_DAY_OFFSET { day - FEMALE_DAY_OFFSET } else { day };
+    // Only the last two digits of the year are there; either century will do
+    if [1900, 2000]
+        .into_iter()
+        .all(|century| NaiveDate::from_ymd_opt(century + year as i32, month, day).is_none())
+    {
+        return Err("NIK_INVALID_FORMAT");
+    }
+
+    // Nothing has to send the printed birth date; without one the NIK's own
+    // date of birth is all there is to check
+    let Some(birth_date) = fields.birth_date.as_deref() else {
+        return Ok(());
+    };
+    let birth_date = parse_birth_date(birth_date).ok_or("BIRTH_DATE_INVALID")?;
+    if (birth_date.day(), birth_date.month(), birth_date.year().rem_euclid(100) as u32) != (day, month, year) {
+        return Err("NIK_BIRTH_DATE_MISMATCH");
+    }
+
+    Ok(())
+}
+
+/// Birth dates as printed on the card (`17-08-1990`) or as ISO dates
+fn parse_birth_date(value: &str) -> Option<NaiveDate> {
+    ["%d-%m-%Y", "%Y-%m-%d", "%d/%m/%Y"]
+        .into_iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+/// Codes a province has issued
+#[derive(Debug, Clone)]
+struct Province {
+    last_regency: u32,
+    last_city: Option<u32>,
+}
+
+/// Whether the province has issued the regency or city code: regencies count
+/// up from 01 and cities from 71
+fn known_region(province: u32, region: u32) -> bool {
+    let Some(province) = provinces().get(&province) else {
+        return false;
+    };
+    match region {
+        1..=70 => region <= province.last_regency,
+        _ => province.last_city.is_some_and(|last_city| region >= 71 && region <= last_city),
+    }
+}
+
+/// Provinces from the bundled `ktp_regions.csv`, read by `load_regions` at
+/// startup
+fn provinces() -> &'static HashMap<u32, Province> {
+    PROVINCES.get_or_init(|| parse_regions(KTP_REGIONS).unwrap_or_default())
+}
+
+static PROVINCES: OnceLock<HashMap<u32, Province>> = OnceLock::new();
+
+/// Read the bundled `ktp_regions.csv`, so a malformed row stops startup
+/// instead of failing NIK checks later
+pub fn load_regions() -> anyhow::Result<()> {
+    let provinces = parse_regions(KTP_REGIONS)?;
+    let _ = PROVINCES.set(provinces);
+    Ok(())
+}
+
+fn parse_regions(csv: &str) -> anyhow::Result<HashMap<u32, Province>> {
+    csv.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let columns: Vec<&str> = line.split(',').map(str::trim).collect();
+            let number = |column: usize| columns.get(column).filter(|v| !v.is_empty()).map(|v| v.parse::<u32>());
+            let code = number(0)
+                .and_then(Result::ok)
+                .ok_or_else(|| anyhow::anyhow!("ktp_regions.csv rows start with a province code, got {}", line))?;
+            let last_regency = number(2)
+                .and_then(Result::ok)
+                .ok_or_else(|| anyhow::anyhow!("ktp_regions.csv rows need a last regency code, got {}", line))?;
+            let last_city = number(3)
+                .transpose()
+                .map_err(|_| anyhow::anyhow!("ktp_regions.csv city codes must be numbers, got {}", line))?;
+            Ok((code, Province { last_regency, last_city }))
+        })
+        .collect()
+}
@@ -0,0 +1,6 @@
+pub mod face_match_policy;
+pub mod policy_repository;
+pub mod submission_flow;
+pub mod decision;
+pub mod resubmission;
+pub mod identity_document;
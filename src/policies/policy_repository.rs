@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use sqlx::{types::Json, PgPool};
+
+use crate::{
+    commons::tenant::DEFAULT_TENANT,
+    policies::{
+        decision::{on_failure_decision, RuleConfig, RuleSet},
+        face_match_policy::FaceMatchPolicy,
+        resubmission::ResubmissionPolicy,
+    },
+    services::face_match_calibration::{CalibrationCurve, CalibrationPoint},
+};
+
+#[derive(Clone)]
+pub struct PolicyRepository {
+    pool: PgPool,
+}
+
+impl PolicyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Resolve the face match policy for a tenant, falling back to the default tenant's row
+    pub async fn find_face_match_policy(
+        &self,
+        tenant_id: &str,
+        submission_type: &str,
+    ) -> Result<Option<FaceMatchPolicy>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT id, tenant_id, submission_type, approve_threshold, reject_threshold
+            FROM face_match_policies
+            WHERE submission_type = $2 AND tenant_id IN ($1, $3)
+            ORDER BY (tenant_id = $1) DESC
+            LIMIT 1
+            "#,
+            tenant_id,
+            submission_type,
+            DEFAULT_TENANT
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result.map(|r| FaceMatchPolicy {
+            id: Some(r.id),
+            tenant_id: r.tenant_id,
+            submission_type: r.submission_type,
+            approve_threshold: r.approve_threshold,
+            reject_threshold: r.reject_threshold,
+        }))
+    }
+
+    /// Decision rules of a tenant, or of the default tenant when the tenant
+    /// has none; None when neither has rows. Disabled rows count as the
+    /// tenant's own, so a tenant can turn off every default rule.
+    pub async fn find_decision_rules(&self, tenant_id: &str, submission_type: &str) -> Result<Option<RuleSet>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT tenant_id, rule, on_failure, enabled
+            FROM decision_rules
+            WHERE submission_type = $2 AND tenant_id IN ($1, $3)
+            ORDER BY id
+            "#,
+            tenant_id,
+            submission_type,
+            DEFAULT_TENANT
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let owner = if rows.iter().any(|r| r.tenant_id == tenant_id) { tenant_id } else { DEFAULT_TENANT };
+        if !rows.iter().any(|r| r.tenant_id == owner) {
+            return Ok(None);
+        }
+
+        let rules = rows
+            .into_iter()
+            .filter(|r| r.tenant_id == owner && r.enabled)
+            .filter_map(|r| {
+                Some(RuleConfig {
+                    rule: r.rule.parse().ok()?,
+                    on_failure: on_failure_decision(&r.on_failure)?,
+                })
+            })
+            .collect();
+
+        Ok(Some(RuleSet { rules }))
+    }
+
+    /// Resubmission policy of a tenant, falling back to the default tenant's row
+    pub async fn find_resubmission_policy(
+        &self,
+        tenant_id: &str,
+        submission_type: &str,
+    ) -> Result<Option<ResubmissionPolicy>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT max_attempts
+            FROM resubmission_policies
+            WHERE submission_type = $2 AND tenant_id IN ($1, $3)
+            ORDER BY (tenant_id = $1) DESC
+            LIMIT 1
+            "#,
+            tenant_id,
+            submission_type,
+            DEFAULT_TENANT
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result.map(|r| ResubmissionPolicy {
+            max_attempts: r.max_attempts,
+        }))
+    }
+
+    /// Calibration curves of the face match providers that have one, by
+    /// provider name. A curve that doesn't hold together fails the lookup
+    /// rather than scoring with the wrong scale.
+    pub async fn find_face_match_calibrations(&self) -> Result<HashMap<String, CalibrationCurve>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT provider, points as "points: Json<Vec<CalibrationPoint>>"
+            FROM face_match_calibrations
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|r| {
+                let curve = CalibrationCurve::new(r.points.0).map_err(|e| {
+                    sqlx::Error::Decode(format!("Face match calibration of {} is invalid: {}", r.provider, e).into())
+                })?;
+                Ok((r.provider, curve))
+            })
+            .collect()
+    }
+}
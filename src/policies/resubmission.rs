@@ -0,0 +1,80 @@
+use crate::{
+    policies::submission_flow::SubmissionFlow,
+    submissions::submission_documents::{DocumentType, DocumentUploadStatus, SubmissionDocuments},
+};
+
+/// How many times a rejected submission may be resubmitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResubmissionPolicy {
+    /// 0 turns resubmission off
+    pub max_attempts: i32,
+}
+
+impl Default for ResubmissionPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3 }
+    }
+}
+
+impl ResubmissionPolicy {
+    /// `RESUBMISSION_MAX_ATTEMPTS`, used where neither the tenant nor the
+    /// default tenant has a row in `resubmission_policies`
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("RESUBMISSION_MAX_ATTEMPTS").ok().filter(|v| !v.is_empty()) {
+            Some(value) => Ok(Self {
+                max_attempts: value
+                    .parse()
+                    .ok()
+                    .filter(|attempts: &i32| *attempts >= 0)
+                    .ok_or_else(|| anyhow::anyhow!("RESUBMISSION_MAX_ATTEMPTS must be a number of attempts, got {}", value))?,
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Whether the resubmission numbered `attempt` (1 for the first) is allowed
+    pub fn allows(&self, attempt: i32) -> bool {
+        attempt <= self.max_attempts
+    }
+}
+
+/// Document a decision reason points at, if any. Face match band reasons
+/// (`POLICY_*`, `DEFAULT_THRESHOLD_*`) and liveness blame the selfie, NIK
+/// and identity card field checks the identity card photo.
+fn blamed_document(reason: &str) -> Option<DocumentType> {
+    match reason {
+        "LIVENESS_FAILED" => Some(DocumentType::Selfie),
+        "NIK_MISMATCH" | "NIK_DUPLICATE" | "NIK_INVALID_FORMAT" | "NIK_UNKNOWN_REGION" | "NIK_BIRTH_DATE_MISMATCH"
+        | "BIRTH_DATE_INVALID" | "DOCUMENT_COUNTRY_NOT_SUPPORTED" => Some(DocumentType::Ktp),
+        // Only found on decisions made while a missing birth date failed the check
+        "BIRTH_DATE_NOT_AVAILABLE" => Some(DocumentType::Ktp),
+        reason if reason.starts_with("POLICY_") || reason.starts_with("DEFAULT_THRESHOLD_") => Some(DocumentType::Selfie),
+        _ => None,
+    }
+}
+
+/// Documents of a rejected submission the user has to upload again: those
+/// the decision reasons blame and those whose upload failed. When none of
+/// them is one the client uploads, e.g. a reviewer's own reason code, every
+/// uploaded document is asked for again. NFC is never among them; it comes
+/// from the chip read of the original submission.
+pub fn rejected_documents(flow: &SubmissionFlow, reasons: &[String], documents: &SubmissionDocuments) -> Vec<DocumentType> {
+    let rejected: Vec<DocumentType> = flow
+        .upload_documents
+        .iter()
+        .copied()
+        .filter(|document_type| {
+            let failed = documents
+                .get(*document_type)
+                .and_then(|document| document.upload.as_ref())
+                .is_some_and(|upload| upload.status == DocumentUploadStatus::Failed);
+            failed || reasons.iter().any(|reason| blamed_document(reason) == Some(*document_type))
+        })
+        .collect();
+
+    if rejected.is_empty() {
+        flow.upload_documents.to_vec()
+    } else {
+        rejected
+    }
+}
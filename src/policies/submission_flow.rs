@@ -0,0 +1,88 @@
+use crate::submissions::{
+    submission_controller::SubmissionType,
+    submission_documents::DocumentType,
+};
+
+/// The face the selfie is compared against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceMatchReference {
+    /// The photo read from the identity card's NFC chip
+    NfcChipPhoto,
+    /// The selfie of an earlier approved submission for the same NFC identifier
+    ApprovedSelfie,
+}
+
+/// A step `process_submission` runs, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineStep {
+    ConfirmDocuments,
+    FaceMatch(FaceMatchReference),
+    ApplyDecisionPolicy,
+}
+
+/// How a submission type is collected, processed and reported
+#[derive(Debug, Clone)]
+pub struct SubmissionFlow {
+    pub submission_type: SubmissionType,
+    /// Documents the client uploads through presigned URLs
+    pub upload_documents: &'static [DocumentType],
+    /// Documents that must be present before processing
+    pub required_documents: &'static [DocumentType],
+    pub steps: &'static [PipelineStep],
+    /// Values returned by the status endpoint for approved and other submissions
+    pub approved_status: &'static str,
+    pub not_approved_status: &'static str,
+}
+
+impl SubmissionFlow {
+    pub fn for_type(submission_type: &SubmissionType) -> Self {
+        use DocumentType::{Ktp, Nfc, Selfie};
+        use FaceMatchReference::{ApprovedSelfie, NfcChipPhoto};
+        use PipelineStep::{ApplyDecisionPolicy, ConfirmDocuments, FaceMatch};
+
+        let submission_type = submission_type.clone();
+        match submission_type {
+            SubmissionType::KYC => Self {
+                submission_type,
+                upload_documents: &[Ktp, Selfie],
+                required_documents: &[Selfie, Nfc],
+                steps: &[ConfirmDocuments, FaceMatch(NfcChipPhoto), ApplyDecisionPolicy],
+                approved_status: "KYC",
+                not_approved_status: "NOT_KYC",
+            },
+            SubmissionType::ON_DEMAND => Self {
+                submission_type,
+                upload_documents: &[Selfie],
+                required_documents: &[Selfie],
+                steps: &[ConfirmDocuments, FaceMatch(ApprovedSelfie), ApplyDecisionPolicy],
+                approved_status: "VERIFIED",
+                not_approved_status: "NOT_VERIFIED",
+            },
+            SubmissionType::SELF_ONBOARDING => Self {
+                submission_type,
+                upload_documents: &[Selfie],
+                required_documents: &[Selfie, Nfc],
+                steps: &[ConfirmDocuments, FaceMatch(NfcChipPhoto), ApplyDecisionPolicy],
+                approved_status: "ONBOARDED",
+                not_approved_status: "NOT_ONBOARDED",
+            },
+            SubmissionType::ACCOUNT_RECOVERY => Self {
+                submission_type,
+                upload_documents: &[Selfie],
+                required_documents: &[Selfie],
+                steps: &[ConfirmDocuments, FaceMatch(ApprovedSelfie), ApplyDecisionPolicy],
+                approved_status: "RECOVERED",
+                not_approved_status: "NOT_RECOVERED",
+            },
+        }
+    }
+
+    /// Status reported to clients for a stored submission status
+    pub fn reported_status(&self, submission_status: &str) -> &'static str {
+        if submission_status == "APPROVED" {
+            self.approved_status
+        } else {
+            self.not_approved_status
+        }
+    }
+}
@@ -1 +1,2 @@
-pub mod user_repository; 
\ No newline at end of file
+pub mod user_repository;
+pub mod view_as_log_repository;
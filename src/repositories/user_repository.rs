@@ -1,4 +1,5 @@
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
 use crate::models::user::User;
 
 pub struct UserRepository {
@@ -14,11 +15,14 @@ impl UserRepository {
         sqlx::query_as!(
             User,
             r#"
-            SELECT 
-                id, 
-                name, 
-                email, 
-                password_hash
+            SELECT
+                id,
+                name,
+                email,
+                phone,
+                password_hash,
+                email_verified_at,
+                deleted_at
             FROM users
             WHERE email = $1
             "#,
@@ -28,23 +32,212 @@ impl UserRepository {
         .await
     }
 
-    pub async fn create(&self, name: &str, email: &str, password_hash: &str) -> Result<User, sqlx::Error> {
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<User>, sqlx::Error> {
         sqlx::query_as!(
             User,
             r#"
-            INSERT INTO users (name, email, password_hash)
-            VALUES ($1, $2, $3)
-            RETURNING 
-                id, 
-                name, 
-                email, 
-                password_hash
+            SELECT
+                id,
+                name,
+                email,
+                phone,
+                password_hash,
+                email_verified_at,
+                deleted_at
+            FROM users
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn create(
+        &self,
+        name: &str,
+        email: &str,
+        password_hash: &str,
+        verification_token: &str,
+        verification_token_expires_at: DateTime<Utc>,
+    ) -> Result<User, sqlx::Error> {
+        sqlx::query_as!(
+            User,
+            r#"
+            INSERT INTO users (name, email, password_hash, verification_token, verification_token_expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id,
+                name,
+                email,
+                phone,
+                password_hash,
+                email_verified_at,
+                deleted_at
             "#,
             name,
             email,
-            password_hash
+            password_hash,
+            verification_token,
+            verification_token_expires_at
         )
         .fetch_one(&self.pool)
         .await
     }
-} 
\ No newline at end of file
+
+    pub async fn update_password_hash(&self, id: i32, password_hash: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            password_hash
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Change the fields of a profile that are given, returning the updated
+    /// user or None if it doesn't exist
+    pub async fn update_profile(&self, id: i32, name: Option<&str>, phone: Option<&str>) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET name = COALESCE($2, name),
+                phone = COALESCE($3, phone),
+                updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING
+                id,
+                name,
+                email,
+                phone,
+                password_hash,
+                email_verified_at,
+                deleted_at
+            "#,
+            id,
+            name,
+            phone
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Replace the verification token of a user who hasn't verified their
+    /// email yet. Returns None when there is no such user.
+    pub async fn reissue_verification_token(
+        &self,
+        id: i32,
+        verification_token: &str,
+        verification_token_expires_at: DateTime<Utc>,
+    ) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET verification_token = $2,
+                verification_token_expires_at = $3,
+                updated_at = NOW()
+            WHERE id = $1 AND email_verified_at IS NULL AND deleted_at IS NULL
+            RETURNING
+                id,
+                name,
+                email,
+                phone,
+                password_hash,
+                email_verified_at,
+                deleted_at
+            "#,
+            id,
+            verification_token,
+            verification_token_expires_at
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Mark the owner of an unexpired verification token as verified and
+    /// consume the token. Returns None when the token is unknown or expired.
+    pub async fn verify_email(&self, verification_token: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET email_verified_at = NOW(),
+                verification_token = NULL,
+                verification_token_expires_at = NULL,
+                updated_at = NOW()
+            WHERE verification_token = $1 AND verification_token_expires_at > NOW() AND deleted_at IS NULL
+            RETURNING
+                id,
+                name,
+                email,
+                phone,
+                password_hash,
+                email_verified_at,
+                deleted_at
+            "#,
+            verification_token
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Close an account, keeping its data until the retention purge. Returns
+    /// false when the user doesn't exist or is already closed.
+    pub async fn soft_delete(&self, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET deleted_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Ids of accounts closed before `cutoff`, oldest first
+    pub async fn find_purgeable(&self, cutoff: DateTime<Utc>, skip: &[i32], limit: i64) -> Result<Vec<i32>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id
+            FROM users
+            WHERE deleted_at IS NOT NULL AND deleted_at < $1 AND id <> ALL($2)
+            ORDER BY deleted_at
+            LIMIT $3
+            "#,
+            cutoff,
+            skip,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.id).collect())
+    }
+
+    /// Remove a closed account for good
+    pub async fn hard_delete(&self, tx: &mut Transaction<'_, Postgres>, id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            "#,
+            id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::submissions::submission_event_repository::{SubmissionEventRepository, ACTOR_ADMIN, EVENT_ADMIN_ACTION};
+
+/// An admin viewing a user's account as the user sees it
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewAsEntry {
+    pub id: i64,
+    pub user_id: i32,
+    pub admin: String,
+    pub reason: String,
+    pub ip_address: Option<String>,
+    /// Submissions the admin was shown
+    pub submission_ids: Vec<Uuid>,
+    pub viewed_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct ViewAsLogRepository {
+    pool: PgPool,
+}
+
+impl ViewAsLogRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(
+        &self,
+        user_id: i32,
+        admin: &str,
+        reason: &str,
+        ip_address: Option<&str>,
+        submission_ids: &[Uuid],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_view_as_log (user_id, admin, reason, ip_address, submission_ids)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            user_id,
+            admin,
+            reason,
+            ip_address,
+            submission_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // Also on the audit trail of every submission shown
+        let events = SubmissionEventRepository::new(self.pool.clone());
+        for submission_id in submission_ids {
+            events
+                .append_in_tx(
+                    &mut tx,
+                    *submission_id,
+                    EVENT_ADMIN_ACTION,
+                    ACTOR_ADMIN,
+                    serde_json::json!({ "action": "VIEW_AS", "admin": admin, "reason": reason }),
+                )
+                .await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Views of a user's account and by an admin, newest first; unset
+    /// filters match everything
+    pub async fn find_all(
+        &self,
+        user_id: Option<i32>,
+        admin: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ViewAsEntry>, sqlx::Error> {
+        sqlx::query_as!(
+            ViewAsEntry,
+            r#"
+            SELECT id, user_id, admin, reason, ip_address, submission_ids, viewed_at
+            FROM user_view_as_log
+            WHERE ($1::INTEGER IS NULL OR user_id = $1)
+              AND ($2::TEXT IS NULL OR admin = $2)
+            ORDER BY viewed_at DESC, id DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            user_id,
+            admin,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+}
@@ -1,33 +1,49 @@
-use argon2::{self, password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString}};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{encode, EncodingKey, Header};
-use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
 use crate::{
-    models::user::{AuthResponse, LoginRequest, RegisterRequest},
+    commons::session_store::SessionStore,
+    models::user::{
+        AuthResponse, ChangePasswordRequest, ChangePasswordResponse, LoginRequest, RegisterRequest, ResendVerificationResponse,
+        UpdateProfileRequest, User, VerifyEmailResponse,
+    },
+    notifier::verification::VerificationMailer,
     repositories::user_repository::UserRepository,
+    services::{
+        key_provider::KeyProvider,
+        password_hasher::{self, PasswordCheck, PasswordHashConfig},
+    },
+    utils::Claims,
 };
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: i32,
-    exp: i64,
-}
+pub const VERIFICATION_TOKEN_TTL_HOURS: i64 = 24;
+const ACCESS_TOKEN_TTL_HOURS: i64 = 24;
 
 pub struct AuthService {
     user_repository: UserRepository,
-    jwt_secret: String,
+    keys: KeyProvider,
+    password_hash_config: PasswordHashConfig,
+    sessions: SessionStore,
+    verification_mailer: Option<VerificationMailer>,
 }
 
 impl AuthService {
-    pub fn new(pool: PgPool, jwt_secret: String) -> Self {
+    pub fn new(pool: PgPool, keys: KeyProvider, sessions: SessionStore, password_hash_config: PasswordHashConfig) -> Self {
         Self {
             user_repository: UserRepository::new(pool),
-            jwt_secret,
+            keys,
+            password_hash_config,
+            sessions,
+            verification_mailer: None,
         }
     }
 
+    /// Email verification links on registration and resend
+    pub fn with_verification_mailer(mut self, mailer: VerificationMailer) -> Self {
+        self.verification_mailer = Some(mailer);
+        self
+    }
+
     pub async fn register(&self, request: RegisterRequest) -> Result<AuthResponse, anyhow::Error> {
         let start = std::time::Instant::now();
         // Check if user exists
@@ -40,29 +56,36 @@ impl AuthService {
 
         let start = std::time::Instant::now();
         // Hash password with Argon2
-        let salt = SaltString::generate(&mut argon2::password_hash::rand_core::OsRng);
-        let argon2 = argon2::Argon2::default();
-        let password_hash = PasswordHasher::hash_password(
-            &argon2,
-            request.password.as_bytes(),
-            &salt,
-        ).map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+        let password_hash = password_hasher::hash_password(&self.password_hash_config, request.password.clone()).await?;
 
         let duration = start.elapsed();
         log::info!("Password hash process took: {:?}", duration);
 
         let start = std::time::Instant::now();
-        // Create user
+        // Create user in the unverified state with a one-time verification token
+        let verification_token = uuid::Uuid::new_v4().simple().to_string();
+        let verification_token_expires_at = Utc::now() + Duration::hours(VERIFICATION_TOKEN_TTL_HOURS);
         let user = self
             .user_repository
-            .create(&request.name, &request.email, &password_hash.to_string())
+            .create(
+                &request.name,
+                &request.email,
+                &password_hash,
+                &verification_token,
+                verification_token_expires_at,
+            )
             .await?;
 
         let duration = start.elapsed();
         log::info!("User creation process took: {:?}", duration);
 
+        // The token is a credential, so it only goes out in the email
+        if let Some(mailer) = &self.verification_mailer {
+            mailer.send(&user, &verification_token);
+        }
+
         // Generate token
-        self.generate_token(user.id)
+        self.generate_token(user.id).await
     }
 
     pub async fn login(&self, request: LoginRequest) -> Result<AuthResponse, anyhow::Error> {
@@ -79,33 +102,165 @@ impl AuthService {
 
         let start = std::time::Instant::now();
         // Verify password with Argon2
-        let parsed_hash = PasswordHash::new(&user.password_hash)
-            .map_err(|e| anyhow::anyhow!("Invalid password hash: {}", e))?;
-        let argon2 = argon2::Argon2::default();
-        if PasswordVerifier::verify_password(&argon2, request.password.as_bytes(), &parsed_hash).is_err() {
-            return Err(anyhow::anyhow!("Invalid email or password"));
-        }
+        let check = password_hasher::verify_password(
+            &self.password_hash_config,
+            request.password.clone(),
+            user.password_hash.clone(),
+        ).await?;
 
         let duration = start.elapsed();
         log::info!("Password verify process took: {:?}", duration);
 
+        // Only callers who know the password learn that the account is closed
+        if !matches!(check, PasswordCheck::Invalid) && user.deleted_at.is_some() {
+            return Err(anyhow::anyhow!("Account deleted"));
+        }
+
+        match check {
+            PasswordCheck::Invalid => return Err(anyhow::anyhow!("Invalid email or password")),
+            PasswordCheck::Valid => {}
+            PasswordCheck::ValidNeedsRehash => {
+                // Upgrade the stored hash to the current cost while we have the plaintext
+                let rehashed = password_hasher::hash_password(&self.password_hash_config, request.password.clone()).await;
+                let updated = match rehashed {
+                    Ok(hash) => self.user_repository.update_password_hash(user.id, &hash).await.map_err(anyhow::Error::from),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = updated {
+                    log::warn!("Failed to rehash password for user {}: {}", user.id, e);
+                }
+            }
+        }
+
         // Generate token
-        self.generate_token(user.id)
+        self.generate_token(user.id).await
+    }
+
+    pub async fn verify_email(&self, token: &str) -> Result<VerifyEmailResponse, anyhow::Error> {
+        let user = self
+            .user_repository
+            .verify_email(token)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Invalid verification token"))?;
+
+        Ok(VerifyEmailResponse {
+            email: user.email,
+            email_verified_at: user.email_verified_at.unwrap_or_else(Utc::now),
+        })
+    }
+
+    /// End the session behind a token
+    /// Email a fresh verification link to a user who hasn't verified yet,
+    /// at most once per resend cooldown. The previous link stops working.
+    pub async fn resend_verification(&self, user_id: i32) -> Result<ResendVerificationResponse, anyhow::Error> {
+        let user = self
+            .user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("User not found"))?;
+        if user.email_verified_at.is_some() {
+            return Err(anyhow::anyhow!("Email already verified"));
+        }
+
+        let mailer = self
+            .verification_mailer
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Verification mailer not configured"))?;
+        if !mailer.claim_resend(user.id).await? {
+            return Err(anyhow::anyhow!("Verification resent too soon"));
+        }
+
+        let verification_token = uuid::Uuid::new_v4().simple().to_string();
+        let verification_token_expires_at = Utc::now() + Duration::hours(VERIFICATION_TOKEN_TTL_HOURS);
+        let Some(user) = self
+            .user_repository
+            .reissue_verification_token(user.id, &verification_token, verification_token_expires_at)
+            .await?
+        else {
+            // Verified in the meantime
+            return Err(anyhow::anyhow!("Email already verified"));
+        };
+
+        mailer.send(&user, &verification_token);
+        Ok(ResendVerificationResponse { email: user.email })
+    }
+
+    pub async fn logout(&self, user_id: i32, session_id: &str) -> Result<(), anyhow::Error> {
+        self.sessions.revoke(session_id, user_id).await?;
+        Ok(())
+    }
+
+    /// End every session of a user, returning how many were active
+    pub async fn logout_all(&self, user_id: i32) -> Result<usize, anyhow::Error> {
+        Ok(self.sessions.revoke_all(user_id).await?)
+    }
+
+    /// Close the user's account and end all of its sessions. Its data is
+    /// purged once the retention window has passed.
+    pub async fn delete_account(&self, user_id: i32) -> Result<(), anyhow::Error> {
+        if !self.user_repository.soft_delete(user_id).await? {
+            return Err(anyhow::anyhow!("User not found"));
+        }
+
+        let revoked_sessions = self.sessions.revoke_all(user_id).await?;
+        log::info!("Account of user {} deleted, {} sessions revoked", user_id, revoked_sessions);
+        Ok(())
+    }
+
+    pub async fn get_profile(&self, user_id: i32) -> Result<User, anyhow::Error> {
+        self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("User not found"))
+    }
+
+    pub async fn update_profile(&self, user_id: i32, request: UpdateProfileRequest) -> Result<User, anyhow::Error> {
+        self.user_repository
+            .update_profile(user_id, request.name.as_deref(), request.phone.as_deref())
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("User not found"))
+    }
+
+    /// Replace the password after checking the current one. Every other
+    /// session is ended, so a leaked token stops working with the old password.
+    pub async fn change_password(
+        &self,
+        user_id: i32,
+        session_id: &str,
+        request: ChangePasswordRequest,
+    ) -> Result<ChangePasswordResponse, anyhow::Error> {
+        let user = self.get_profile(user_id).await?;
+
+        let check = password_hasher::verify_password(
+            &self.password_hash_config,
+            request.current_password,
+            user.password_hash,
+        ).await?;
+        if let PasswordCheck::Invalid = check {
+            return Err(anyhow::anyhow!("Invalid current password"));
+        }
+
+        let password_hash = password_hasher::hash_password(&self.password_hash_config, request.new_password).await?;
+        self.user_repository.update_password_hash(user_id, &password_hash).await?;
+
+        let revoked_sessions = self.sessions.revoke_others(user_id, session_id).await?;
+        log::info!("Password changed for user {}, {} other sessions revoked", user_id, revoked_sessions);
+
+        Ok(ChangePasswordResponse { revoked_sessions })
     }
 
-    fn generate_token(&self, user_id: i32) -> Result<AuthResponse, anyhow::Error> {
+    async fn generate_token(&self, user_id: i32) -> Result<AuthResponse, anyhow::Error> {
         let start = std::time::Instant::now();
-        let expiration = Utc::now() + Duration::hours(24);
+        let ttl = Duration::hours(ACCESS_TOKEN_TTL_HOURS);
+        let expiration = Utc::now() + ttl;
+        let session_id = self.sessions.create(user_id, ttl.to_std()?).await?;
         let claims = Claims {
             sub: user_id,
             exp: expiration.timestamp(),
+            sid: session_id,
         };
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.jwt_secret.as_bytes()),
-        )?;
+        let token = self.keys.sign(&claims)?;
 
         let duration = start.elapsed();
         log::info!("Token generate process took: {:?}", duration);
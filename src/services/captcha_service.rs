@@ -0,0 +1,135 @@
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+
+use crate::commons::{app_error::error_response, client_ip::client_ip};
+
+/// Header carrying the token the CAPTCHA widget gave the client
+pub const CAPTCHA_TOKEN_HEADER: &str = "x-captcha-token";
+
+/// Header trusted internal callers send with `CAPTCHA_BYPASS_KEY` to skip the
+/// check
+pub const CAPTCHA_BYPASS_HEADER: &str = "x-internal-caller-key";
+
+pub const CAPTCHA_FAILED_CODE: &str = "1022";
+
+/// Answer of a reCAPTCHA/hCaptcha style `siteverify` endpoint
+#[derive(Debug, Deserialize)]
+struct VerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptchaConfig {
+    pub verify_url: String,
+    pub secret: String,
+    pub bypass_key: Option<String>,
+    pub timeout: Duration,
+}
+
+impl CaptchaConfig {
+    /// None unless `CAPTCHA_ENABLED=true`
+    pub fn from_env() -> Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        if var("CAPTCHA_ENABLED").as_deref() != Some("true") {
+            return Ok(None);
+        }
+
+        // Register and login have no verified tenant, only the X-Tenant-Id the
+        // client picks, so a tenant list would let anyone skip the check
+        if var("CAPTCHA_TENANTS").is_some() {
+            anyhow::bail!("CAPTCHA_TENANTS is not supported: every tenant is checked when CAPTCHA_ENABLED=true");
+        }
+
+        let timeout = Duration::from_millis(
+            var("CAPTCHA_TIMEOUT_MILLIS")
+                .map(|v| v.parse::<u64>().context("CAPTCHA_TIMEOUT_MILLIS must be a number"))
+                .transpose()?
+                .unwrap_or(5000),
+        );
+
+        Ok(Some(Self {
+            verify_url: var("CAPTCHA_VERIFY_URL").context("CAPTCHA_VERIFY_URL must be set when CAPTCHA_ENABLED=true")?,
+            secret: var("CAPTCHA_SECRET").context("CAPTCHA_SECRET must be set when CAPTCHA_ENABLED=true")?,
+            bypass_key: var("CAPTCHA_BYPASS_KEY"),
+            timeout,
+        }))
+    }
+}
+
+/// Checks the CAPTCHA token of register and login requests against the
+/// provider's verify endpoint
+#[derive(Clone)]
+pub struct CaptchaService {
+    config: CaptchaConfig,
+    client: reqwest::Client,
+}
+
+impl CaptchaService {
+    pub fn new(config: CaptchaConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self { config, client }
+    }
+
+    /// Let the request through, or answer it with the response to send.
+    /// Trusted internal callers aren't checked.
+    pub async fn check(&self, req: &HttpRequest) -> Result<(), HttpResponse> {
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok()).filter(|v| !v.is_empty());
+
+        if let (Some(key), Some(bypass_key)) = (header(CAPTCHA_BYPASS_HEADER), &self.config.bypass_key) {
+            if bool::from(key.as_bytes().ct_eq(bypass_key.as_bytes())) {
+                return Ok(());
+            }
+        }
+
+        let Some(token) = header(CAPTCHA_TOKEN_HEADER) else {
+            return Err(error_response(StatusCode::FORBIDDEN, CAPTCHA_FAILED_CODE, "CAPTCHA_REQUIRED".to_string()));
+        };
+
+        let remote_ip = client_ip(req);
+        match self.verify(token, remote_ip.as_deref()).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(error_response(StatusCode::FORBIDDEN, CAPTCHA_FAILED_CODE, "CAPTCHA_INVALID".to_string())),
+            Err(e) => {
+                log::error!("Failed to verify CAPTCHA token: {}", e);
+                Err(error_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    CAPTCHA_FAILED_CODE,
+                    "CAPTCHA_CHECK_FAILED".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Whether the provider accepts `token`
+    pub async fn verify(&self, token: &str, remote_ip: Option<&str>) -> Result<bool> {
+        let mut form = vec![("secret", self.config.secret.as_str()), ("response", token)];
+        if let Some(remote_ip) = remote_ip {
+            form.push(("remoteip", remote_ip));
+        }
+
+        let response: VerifyResponse = self
+            .client
+            .post(&self.config.verify_url)
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if !response.success {
+            log::info!("CAPTCHA token rejected: {:?}", response.error_codes);
+        }
+        Ok(response.success)
+    }
+}
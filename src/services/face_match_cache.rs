@@ -0,0 +1,71 @@
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::{
+    commons::redis_connection::{RedisConnection, RedisTopology},
+    services::face_match_service::FaceMatchResponse,
+};
+
+/// Provider responses by image pair, so retries of the same comparison don't
+/// pay for another provider call
+#[derive(Clone)]
+pub struct FaceMatchCache {
+    connection_manager: RedisConnection,
+    ttl: Duration,
+}
+
+impl FaceMatchCache {
+    pub async fn new(redis: &RedisTopology, ttl: Duration) -> redis::RedisResult<Self> {
+        let connection_manager = redis.connect().await?;
+
+        Ok(Self { connection_manager, ttl })
+    }
+
+    /// `FACE_MATCH_CACHE_TTL_SECONDS` sets how long results are reused
+    /// (default a day); 0 turns the cache off
+    pub async fn from_env(redis: &RedisTopology) -> anyhow::Result<Option<Self>> {
+        let ttl_seconds = std::env::var("FACE_MATCH_CACHE_TTL_SECONDS")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("FACE_MATCH_CACHE_TTL_SECONDS must be a number"))?
+            .unwrap_or(24 * 60 * 60);
+
+        if ttl_seconds == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::new(redis, Duration::from_secs(ttl_seconds)).await?))
+    }
+
+    /// Key for an ordered image pair compared at `threshold`
+    pub fn key(image1_reference: &str, image2_reference: &str, threshold: f64) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(image1_reference.as_bytes());
+        hasher.update([0]);
+        hasher.update(image2_reference.as_bytes());
+        hasher.update([0]);
+        hasher.update(threshold.to_be_bytes());
+
+        format!("face_match_cache:{:x}", hasher.finalize())
+    }
+
+    pub async fn get(&self, key: &str) -> redis::RedisResult<Option<FaceMatchResponse>> {
+        let mut conn = self.connection_manager.clone();
+        let cached: Option<String> = conn.get(key).await?;
+
+        // An entry we can't read is as good as a miss
+        Ok(cached.and_then(|value| serde_json::from_str(&value).ok()))
+    }
+
+    pub async fn put(&self, key: &str, response: &FaceMatchResponse) -> redis::RedisResult<()> {
+        let value = serde_json::to_string(response).map_err(|e| {
+            redis::RedisError::from((redis::ErrorKind::TypeError, "failed to serialize face match response", e.to_string()))
+        })?;
+        let mut conn = self.connection_manager.clone();
+
+        conn.set_ex(key, value, self.ttl.as_secs().max(1)).await
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+/// Top of the confidence scale provider scores are mapped onto
+pub const MAX_CONFIDENCE: f64 = 100.0;
+
+/// A raw provider score and the confidence it stands for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationPoint {
+    pub raw: f64,
+    pub confidence: f64,
+}
+
+/// How a provider's raw scores map to a 0-100 confidence: linear between
+/// points sorted by raw score, flat beyond the first and last
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationCurve {
+    points: Vec<CalibrationPoint>,
+}
+
+impl CalibrationCurve {
+    /// Raw scores must strictly increase and confidences must not decrease,
+    /// so a better raw score never lowers the confidence
+    pub fn new(mut points: Vec<CalibrationPoint>) -> Result<Self, String> {
+        if points.len() < 2 {
+            return Err("a calibration curve needs at least two points".to_string());
+        }
+        if points
+            .iter()
+            .any(|p| !p.raw.is_finite() || !(0.0..=MAX_CONFIDENCE).contains(&p.confidence))
+        {
+            return Err(format!("confidences must be between 0 and {}", MAX_CONFIDENCE));
+        }
+
+        points.sort_by(|a, b| a.raw.total_cmp(&b.raw));
+        for pair in points.windows(2) {
+            if pair[0].raw == pair[1].raw {
+                return Err(format!("raw score {} is mapped twice", pair[0].raw));
+            }
+            if pair[0].confidence > pair[1].confidence {
+                return Err(format!("confidence drops after raw score {}", pair[0].raw));
+            }
+        }
+
+        Ok(Self { points })
+    }
+
+    /// A straight line from raw 0 to `max_raw` at full confidence
+    pub fn linear(max_raw: f64) -> Self {
+        Self {
+            points: vec![
+                CalibrationPoint { raw: 0.0, confidence: 0.0 },
+                CalibrationPoint { raw: max_raw, confidence: MAX_CONFIDENCE },
+            ],
+        }
+    }
+
+    pub fn points(&self) -> &[CalibrationPoint] {
+        &self.points
+    }
+
+    /// The confidence of a raw score
+    pub fn normalize(&self, raw: f64) -> f64 {
+        interpolate(&self.points, raw, |p| (p.raw, p.confidence))
+    }
+
+    /// The lowest raw score reaching `confidence`, for providers that are
+    /// told the threshold on their own scale
+    pub fn raw_for(&self, confidence: f64) -> f64 {
+        // Flat stretches map many raw scores to one confidence; take the first
+        let mut points: Vec<CalibrationPoint> = Vec::with_capacity(self.points.len());
+        for point in &self.points {
+            if points.last().is_none_or(|last| last.confidence < point.confidence) {
+                points.push(*point);
+            }
+        }
+        interpolate(&points, confidence, |p| (p.confidence, p.raw))
+    }
+}
+
+/// Linear interpolation of `x` over points sorted by their x, clamped to the
+/// ends
+fn interpolate(points: &[CalibrationPoint], x: f64, axes: impl Fn(&CalibrationPoint) -> (f64, f64)) -> f64 {
+    let (first_x, first_y) = axes(&points[0]);
+    let (last_x, last_y) = axes(&points[points.len() - 1]);
+    if x <= first_x {
+        return first_y;
+    }
+    if x >= last_x {
+        return last_y;
+    }
+
+    points
+        .windows(2)
+        .map(|pair| (axes(&pair[0]), axes(&pair[1])))
+        .find(|((_, _), (x1, _))| x <= *x1)
+        .map(|((x0, y0), (x1, y1))| y0 + (x - x0) / (x1 - x0) * (y1 - y0))
+        .unwrap_or(last_y)
+}
+
+/// Thresholds and scores were configured as similarities between 0 and 1
+/// before calibration; such values are read as a fraction of full confidence
+pub fn confidence_from_legacy(value: f64) -> f64 {
+    if value <= 1.0 {
+        value * MAX_CONFIDENCE
+    } else {
+        value
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Context;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Error code of a comparison shed because the provider is at capacity
+pub const RETRY_LATER_CODE: &str = "1017";
+
+/// `Retry-After` sent with `RETRY_LATER_CODE`
+pub const RETRY_LATER_SECONDS: u64 = 5;
+
+/// A comparison turned away before reaching the provider
+#[derive(Debug, Error)]
+pub enum FaceMatchOverloaded {
+    #[error("RETRY_LATER: too many face match requests are waiting")]
+    QueueFull,
+
+    #[error("RETRY_LATER: no face match slot freed up within {0:?}")]
+    WaitTimedOut(Duration),
+}
+
+impl FaceMatchOverloaded {
+    /// Metric tag value
+    pub fn reason(&self) -> &'static str {
+        match self {
+            FaceMatchOverloaded::QueueFull => "queue_full",
+            FaceMatchOverloaded::WaitTimedOut(_) => "timeout",
+        }
+    }
+}
+
+/// Caps the comparisons in flight to the providers across the process.
+/// Callers over the cap wait their turn, up to `max_queued` of them for at
+/// most `max_wait` each; the rest are turned away straight away.
+#[derive(Clone)]
+pub struct FaceMatchLimiter {
+    semaphore: Arc<Semaphore>,
+    waiting: Arc<AtomicUsize>,
+    max_queued: usize,
+    max_wait: Duration,
+}
+
+impl FaceMatchLimiter {
+    pub fn new(max_concurrent: usize, max_queued: usize, max_wait: Duration) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            waiting: Arc::new(AtomicUsize::new(0)),
+            max_queued,
+            max_wait,
+        }
+    }
+
+    /// None unless `FACE_MATCH_MAX_CONCURRENT` is set above 0
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let number = |name: &str, default: u64| -> anyhow::Result<u64> {
+            std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .with_context(|| format!("{} must be a number", name))
+                .map(|v| v.unwrap_or(default))
+        };
+
+        let max_concurrent = number("FACE_MATCH_MAX_CONCURRENT", 0)?;
+        if max_concurrent == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::new(
+            max_concurrent as usize,
+            number("FACE_MATCH_MAX_QUEUED", 100)? as usize,
+            Duration::from_millis(number("FACE_MATCH_QUEUE_TIMEOUT_MILLIS", 10_000)?),
+        )))
+    }
+
+    /// A slot for one comparison, held until the permit is dropped
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, FaceMatchOverloaded> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        if self.waiting.fetch_add(1, Ordering::SeqCst) >= self.max_queued {
+            self.waiting.fetch_sub(1, Ordering::SeqCst);
+            return Err(FaceMatchOverloaded::QueueFull);
+        }
+        let permit = tokio::time::timeout(self.max_wait, self.semaphore.clone().acquire_owned()).await;
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+
+        match permit {
+            // The semaphore is never closed
+            Ok(permit) => Ok(permit.expect("face match semaphore closed")),
+            Err(_) => Err(FaceMatchOverloaded::WaitTimedOut(self.max_wait)),
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::services::{
+    face_match_calibration::CalibrationCurve,
+    face_match_signing::{self, RequestSigner, SignatureRejected, SigningKey},
+};
+
+/// Name of the provider configured through `FACE_MATCH_HOST`
+pub const DEFAULT_PROVIDER: &str = "default";
+
+/// What a provider is asked to compare
+#[derive(Debug, Clone)]
+pub struct ComparisonRequest {
+    pub image1_url: String,
+    pub image2_url: String,
+    pub submission_id: String,
+    /// On the provider's own scale
+    pub threshold: f64,
+}
+
+/// A face match vendor. Adapters map the comparison to the vendor's API and
+/// return the vendor's score as is; calibration turns it into a confidence.
+pub trait FaceMatchProvider: Send + Sync {
+    /// Name used in logs, metric tags and `face_match_calibrations`
+    fn name(&self) -> &str;
+
+    /// Mapping of the vendor's scores used when the provider has no
+    /// calibration configured
+    fn default_calibration(&self) -> CalibrationCurve;
+
+    fn compare<'a>(&'a self, request: &'a ComparisonRequest) -> BoxFuture<'a, Result<f64>>;
+}
+
+/// A provider as listed in `FACE_MATCH_PROVIDERS`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderConfig {
+    pub name: String,
+    /// `internal` or `hosted`
+    pub kind: String,
+    pub url: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Falls back to `FACE_MATCH_TIMEOUT_MILLIS`
+    #[serde(default)]
+    pub timeout_millis: Option<u64>,
+    /// Requests are signed when set, the active key first
+    #[serde(default)]
+    pub signing_keys: Vec<SigningKey>,
+}
+
+impl ProviderConfig {
+    /// Mapping of the scores of a provider of this kind, without building it
+    pub fn default_calibration(&self) -> Result<CalibrationCurve> {
+        match self.kind.as_str() {
+            "internal" => Ok(CalibrationCurve::linear(1.0)),
+            "hosted" => Ok(CalibrationCurve::linear(100.0)),
+            kind => anyhow::bail!("Face match provider {} has unknown kind {}", self.name, kind),
+        }
+    }
+
+    fn build(self, default_timeout_millis: u64) -> Result<Arc<dyn FaceMatchProvider>> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(self.timeout_millis.unwrap_or(default_timeout_millis)))
+            .build()
+            .context("Failed to create HTTP client")?;
+        let signer = match self.signing_keys.is_empty() {
+            true => None,
+            false => Some(
+                RequestSigner::new(self.signing_keys)
+                    .with_context(|| format!("Face match provider {} has invalid signing keys", self.name))?,
+            ),
+        };
+
+        Ok(match self.kind.as_str() {
+            "internal" => Arc::new(InternalProvider {
+                name: self.name,
+                client,
+                base_url: self.url,
+                api_key: self.api_key,
+                signer,
+            }),
+            "hosted" => Arc::new(HostedProvider {
+                api_key: self
+                    .api_key
+                    .with_context(|| format!("Face match provider {} needs an apiKey", self.name))?,
+                name: self.name,
+                client,
+                base_url: self.url,
+                signer,
+            }),
+            kind => anyhow::bail!("Face match provider {} has unknown kind {}", self.name, kind),
+        })
+    }
+}
+
+/// Providers in the order they are tried: `FACE_MATCH_HOST` as `default`
+/// followed by the `FACE_MATCH_PROVIDERS` JSON list, reordered by the names
+/// in `FACE_MATCH_PROVIDER_ORDER`
+pub fn providers_from_env(default_timeout_millis: u64) -> Result<Vec<Arc<dyn FaceMatchProvider>>> {
+    provider_configs_from_env()?
+        .into_iter()
+        .map(|config| config.build(default_timeout_millis))
+        .collect()
+}
+
+/// The configuration of the providers `providers_from_env` builds, in order
+pub fn provider_configs_from_env() -> Result<Vec<ProviderConfig>> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+    let mut configs = Vec::new();
+    if let Some(url) = var("FACE_MATCH_HOST") {
+        configs.push(ProviderConfig {
+            name: DEFAULT_PROVIDER.to_string(),
+            kind: "internal".to_string(),
+            url,
+            api_key: var("FACE_MATCH_API_KEY"),
+            timeout_millis: None,
+            signing_keys: var("FACE_MATCH_SIGNING_KEYS")
+                .map(|raw| face_match_signing::parse_keys(&raw))
+                .transpose()?
+                .unwrap_or_default(),
+        });
+    }
+    if let Some(list) = var("FACE_MATCH_PROVIDERS") {
+        let listed: Vec<ProviderConfig> = serde_json::from_str(&list)
+            .context("FACE_MATCH_PROVIDERS must be a JSON list of {name, kind, url, apiKey, timeoutMillis, signingKeys}")?;
+        configs.extend(listed);
+    }
+
+    for (i, config) in configs.iter().enumerate() {
+        if configs[..i].iter().any(|other| other.name == config.name) {
+            anyhow::bail!("Face match provider {} is configured twice", config.name);
+        }
+    }
+
+    if let Some(order) = var("FACE_MATCH_PROVIDER_ORDER") {
+        let mut ordered = Vec::new();
+        for name in order.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let position = configs
+                .iter()
+                .position(|config| config.name == name)
+                .with_context(|| format!("FACE_MATCH_PROVIDER_ORDER names unknown provider {}", name))?;
+            ordered.push(configs.remove(position));
+        }
+        // Providers left out of the order are dropped
+        configs = ordered;
+    }
+
+    if configs.is_empty() {
+        anyhow::bail!("FACE_MATCH_HOST or FACE_MATCH_PROVIDERS must be set");
+    }
+
+    Ok(configs)
+}
+
+/// Whether a provider failed by running out of time
+pub fn is_timeout(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| cause.downcast_ref::<reqwest::Error>().is_some_and(reqwest::Error::is_timeout))
+}
+
+/// POST `body` to `url`, signed when the provider has a signer. A `401` to a
+/// signed request is taken as a rejected signature and the request is sent
+/// again with the next key, failing with `SignatureRejected` once none is left.
+async fn post(
+    provider: &str,
+    client: &reqwest::Client,
+    signer: Option<&RequestSigner>,
+    url: &str,
+    body: String,
+    headers: impl Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response> {
+    let Some(signer) = signer else {
+        return headers(client.post(url).body(body)).send().await.context("HTTP request failed");
+    };
+
+    let path = reqwest::Url::parse(url).context("Invalid face match URL")?.path().to_string();
+    let mut rejected_key = None;
+    for key in signer.keys() {
+        if let Some(rejected_key) = rejected_key {
+            tracing::warn!(
+                "Face match provider {} rejected the signature with key {}, retrying with key {}",
+                provider,
+                rejected_key,
+                key.id
+            );
+        }
+
+        let request = headers(client.post(url).body(body.clone()));
+        let response = RequestSigner::apply(key, request, "POST", &path, body.as_bytes())
+            .send()
+            .await
+            .context("HTTP request failed")?;
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        rejected_key = Some(&key.id);
+    }
+
+    Err(SignatureRejected {
+        provider: provider.to_string(),
+        key_id: rejected_key.cloned().unwrap_or_default(),
+    }
+    .into())
+}
+
+/// The in-house comparison service: `POST {url}/compare-faces` with the
+/// similarity in `similarity_score`, between 0 and 1
+struct InternalProvider {
+    name: String,
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    signer: Option<RequestSigner>,
+}
+
+#[derive(Deserialize)]
+struct InternalResponse {
+    similarity_score: f64,
+}
+
+impl FaceMatchProvider for InternalProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn default_calibration(&self) -> CalibrationCurve {
+        CalibrationCurve::linear(1.0)
+    }
+
+    fn compare<'a>(&'a self, request: &'a ComparisonRequest) -> BoxFuture<'a, Result<f64>> {
+        Box::pin(async move {
+            let body = json!({
+                "image1_url": request.image1_url,
+                "image2_url": request.image2_url,
+                "threshold": request.threshold,
+            });
+
+            let url = format!("{}/compare-faces", self.base_url);
+            let response = post(&self.name, &self.client, self.signer.as_ref(), &url, body.to_string(), |http_request| {
+                let http_request = http_request.header("x-submission-id", &request.submission_id);
+                match &self.api_key {
+                    Some(api_key) => http_request.header("x-api-key", api_key),
+                    None => http_request,
+                }
+            })
+            .await?;
+            if !response.status().is_success() {
+                anyhow::bail!("Face match API returned error status: {}", response.status());
+            }
+
+            let parsed: InternalResponse = response.json().await.context("Failed to parse response")?;
+            Ok(parsed.similarity_score)
+        })
+    }
+}
+
+/// A hosted vendor: `POST {url}/v1/face/compare` with a bearer key, answering
+/// with a similarity percentage in `data.similarity`
+struct HostedProvider {
+    name: String,
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    signer: Option<RequestSigner>,
+}
+
+impl FaceMatchProvider for HostedProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn default_calibration(&self) -> CalibrationCurve {
+        CalibrationCurve::linear(100.0)
+    }
+
+    fn compare<'a>(&'a self, request: &'a ComparisonRequest) -> BoxFuture<'a, Result<f64>> {
+        Box::pin(async move {
+            let body = json!({
+                "source_image_url": request.image1_url,
+                "target_image_url": request.image2_url,
+                "reference_id": request.submission_id,
+            });
+
+            let url = format!("{}/v1/face/compare", self.base_url);
+            let response = post(&self.name, &self.client, self.signer.as_ref(), &url, body.to_string(), |http_request| {
+                http_request
+                    .bearer_auth(&self.api_key)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+            })
+            .await?;
+            if !response.status().is_success() {
+                anyhow::bail!("Face match API returned error status: {}", response.status());
+            }
+
+            let body: Value = response.json().await.context("Failed to parse response")?;
+            body.pointer("/data/similarity")
+                .and_then(Value::as_f64)
+                .context("Response has no data.similarity")
+        })
+    }
+}
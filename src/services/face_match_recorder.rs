@@ -0,0 +1,323 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{
+    commons::minio_service::MinioService,
+    services::{
+        face_match_calibration::CalibrationCurve,
+        face_match_provider::{ComparisonRequest, FaceMatchProvider, ProviderConfig},
+    },
+};
+
+/// Prefix of recordings kept in MinIO when no `FACE_MATCH_RECORDINGS_DIR` is set
+pub const DEFAULT_RECORDINGS_PREFIX: &str = "face-match-recordings/";
+
+/// What `FACE_MATCH_RECORDING_MODE` asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingMode {
+    /// Call the providers and keep what they answered
+    Record,
+    /// Answer from recordings without calling any provider
+    Replay,
+}
+
+/// A comparison and what the provider answered, with nothing that lets the
+/// images be fetched again or names the submission
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recording {
+    pub provider: String,
+    pub recorded_at: DateTime<Utc>,
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedRequest {
+    /// Only the origin of the image URLs: their query holds the signature
+    /// and their path names the tenant and submission
+    pub image1_url: String,
+    pub image2_url: String,
+    pub image1_sha256: String,
+    pub image2_sha256: String,
+    pub threshold: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecordedResponse {
+    RawScore(f64),
+    /// The provider failed; replayed as a failure so failover plays out the
+    /// same way
+    Error(String),
+}
+
+/// Where recordings are kept, one JSON file per provider and image pair
+#[derive(Clone)]
+pub enum RecordingStore {
+    Dir(PathBuf),
+    Minio { minio_service: MinioService, prefix: String },
+}
+
+impl RecordingStore {
+    fn path(&self, name: &str) -> String {
+        match self {
+            RecordingStore::Dir(dir) => dir.join(name).to_string_lossy().into_owned(),
+            RecordingStore::Minio { prefix, .. } => format!("{}{}", prefix, name),
+        }
+    }
+
+    async fn get(&self, name: &str) -> Result<Option<Recording>> {
+        let bytes = match self {
+            RecordingStore::Dir(dir) => match tokio::fs::read(dir.join(name)).await {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.path(name))),
+            },
+            RecordingStore::Minio { minio_service, .. } => {
+                if !minio_service.file_exists(self.path(name)).await? {
+                    return Ok(None);
+                }
+                minio_service.download_file(self.path(name)).await?
+            }
+        };
+
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .with_context(|| format!("Recording {} is not valid", self.path(name)))
+    }
+
+    async fn put(&self, name: &str, recording: &Recording) -> Result<()> {
+        let body = serde_json::to_vec_pretty(recording)?;
+        match self {
+            RecordingStore::Dir(dir) => {
+                let path = dir.join(name);
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(&path, body)
+                    .await
+                    .with_context(|| format!("Failed to write {}", path.display()))
+            }
+            RecordingStore::Minio { minio_service, .. } => {
+                minio_service
+                    .upload_file(self.path(name), body, Some("application/json".to_string()))
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Records what face match providers answer, or answers for them from
+/// recordings, so integration tests and demos run without the vendors.
+/// Recordings are matched on the provider and the content of both images,
+/// since the URLs are signed anew for every comparison.
+#[derive(Clone)]
+pub struct FaceMatchRecorder {
+    mode: RecordingMode,
+    store: RecordingStore,
+    client: reqwest::Client,
+}
+
+impl FaceMatchRecorder {
+    pub fn new(mode: RecordingMode, store: RecordingStore) -> Self {
+        Self {
+            mode,
+            store,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// `FACE_MATCH_RECORDING_MODE` (`record` or `replay`), keeping recordings
+    /// in `FACE_MATCH_RECORDINGS_DIR`, or in MinIO under
+    /// `FACE_MATCH_RECORDINGS_PREFIX` without one; `None` when unset.
+    /// Replaying is refused with `APP_ENV=production`.
+    pub fn from_env(minio_service: &MinioService) -> Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let mode = match var("FACE_MATCH_RECORDING_MODE").as_deref() {
+            None => return Ok(None),
+            Some("record") => RecordingMode::Record,
+            Some("replay") => RecordingMode::Replay,
+            Some(other) => anyhow::bail!("FACE_MATCH_RECORDING_MODE must be record or replay, not {}", other),
+        };
+        if mode == RecordingMode::Replay && var("APP_ENV").as_deref() == Some("production") {
+            anyhow::bail!("FACE_MATCH_RECORDING_MODE=replay is refused when APP_ENV=production");
+        }
+
+        let store = match var("FACE_MATCH_RECORDINGS_DIR") {
+            Some(dir) => RecordingStore::Dir(PathBuf::from(dir)),
+            None => RecordingStore::Minio {
+                minio_service: minio_service.clone(),
+                prefix: var("FACE_MATCH_RECORDINGS_PREFIX").unwrap_or_else(|| DEFAULT_RECORDINGS_PREFIX.to_string()),
+            },
+        };
+
+        Ok(Some(Self::new(mode, store)))
+    }
+
+    pub fn mode(&self) -> RecordingMode {
+        self.mode
+    }
+
+    /// Wrap providers built from the configuration so their answers are kept
+    pub fn recording(&self, providers: Vec<Arc<dyn FaceMatchProvider>>) -> Vec<Arc<dyn FaceMatchProvider>> {
+        providers
+            .into_iter()
+            .map(|provider| {
+                Arc::new(RecordingProvider {
+                    inner: provider,
+                    recorder: self.clone(),
+                }) as Arc<dyn FaceMatchProvider>
+            })
+            .collect()
+    }
+
+    /// Stand-ins for the configured providers that only answer from
+    /// recordings; their hosts and keys are never used
+    pub fn replaying(&self, configs: Vec<ProviderConfig>) -> Result<Vec<Arc<dyn FaceMatchProvider>>> {
+        configs
+            .into_iter()
+            .map(|config| {
+                Ok(Arc::new(ReplayProvider {
+                    calibration: config.default_calibration()?,
+                    name: config.name,
+                    recorder: self.clone(),
+                }) as Arc<dyn FaceMatchProvider>)
+            })
+            .collect()
+    }
+
+    /// Scrubbed request, and the name its recording is kept under
+    async fn describe(&self, provider: &str, request: &ComparisonRequest) -> Result<(String, RecordedRequest)> {
+        let image1_sha256 = self.image_digest(&request.image1_url).await?;
+        let image2_sha256 = self.image_digest(&request.image2_url).await?;
+
+        let mut key = Sha256::new();
+        key.update(image1_sha256.as_bytes());
+        key.update(b":");
+        key.update(image2_sha256.as_bytes());
+        let key: String = key.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        Ok((
+            format!("{}/{}.json", provider, key),
+            RecordedRequest {
+                image1_url: scrubbed_url(&request.image1_url),
+                image2_url: scrubbed_url(&request.image2_url),
+                image1_sha256,
+                image2_sha256,
+                threshold: request.threshold,
+            },
+        ))
+    }
+
+    /// Hex SHA-256 of the image behind a URL
+    async fn image_digest(&self, url: &str) -> Result<String> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {}", scrubbed_url(url)))?;
+        if !response.status().is_success() {
+            anyhow::bail!("Fetching {} returned {}", scrubbed_url(url), response.status());
+        }
+        let bytes = response.bytes().await?;
+        Ok(Sha256::digest(&bytes).iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+}
+
+/// Only the URL's scheme, host and port; the rest can carry credentials,
+/// signatures or the ids of whoever the image belongs to
+fn scrubbed_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(url) => url.origin().ascii_serialization(),
+        Err(_) => "<invalid url>".to_string(),
+    }
+}
+
+/// A provider whose answers are recorded as they are returned
+struct RecordingProvider {
+    inner: Arc<dyn FaceMatchProvider>,
+    recorder: FaceMatchRecorder,
+}
+
+impl FaceMatchProvider for RecordingProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn default_calibration(&self) -> CalibrationCurve {
+        self.inner.default_calibration()
+    }
+
+    fn compare<'a>(&'a self, request: &'a ComparisonRequest) -> BoxFuture<'a, Result<f64>> {
+        Box::pin(async move {
+            let result = self.inner.compare(request).await;
+
+            // A comparison that can't be recorded still counts
+            let recorded = async {
+                let (name, recorded_request) = self.recorder.describe(self.name(), request).await?;
+                let recording = Recording {
+                    provider: self.name().to_string(),
+                    recorded_at: Utc::now(),
+                    request: recorded_request,
+                    response: match &result {
+                        Ok(raw_score) => RecordedResponse::RawScore(*raw_score),
+                        Err(e) => RecordedResponse::Error(format!("{:#}", e)),
+                    },
+                };
+                self.recorder.store.put(&name, &recording).await?;
+                anyhow::Ok(name)
+            };
+            match recorded.await {
+                Ok(name) => tracing::info!("Recorded face match for submission {} as {}", request.submission_id, name),
+                Err(e) => tracing::warn!("Failed to record face match for submission {}: {:#}", request.submission_id, e),
+            }
+
+            result
+        })
+    }
+}
+
+/// A provider answering only from recordings; comparisons nobody recorded
+/// fail, so the next provider is tried
+struct ReplayProvider {
+    name: String,
+    calibration: CalibrationCurve,
+    recorder: FaceMatchRecorder,
+}
+
+impl FaceMatchProvider for ReplayProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn default_calibration(&self) -> CalibrationCurve {
+        self.calibration.clone()
+    }
+
+    fn compare<'a>(&'a self, request: &'a ComparisonRequest) -> BoxFuture<'a, Result<f64>> {
+        Box::pin(async move {
+            let (name, _) = self.recorder.describe(&self.name, request).await?;
+            let recording = self
+                .recorder
+                .store
+                .get(&name)
+                .await?
+                .with_context(|| format!("No recording {} for provider {}", self.recorder.store.path(&name), self.name))?;
+
+            tracing::info!("Replaying face match {} for submission {}", name, request.submission_id);
+            match recording.response {
+                RecordedResponse::RawScore(raw_score) => Ok(raw_score),
+                RecordedResponse::Error(error) => Err(anyhow::anyhow!("Recorded failure: {}", error)),
+            }
+        })
+    }
+}
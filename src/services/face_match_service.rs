@@ -1,10 +1,28 @@
-use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
-use serde_json::json;
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
 
-use crate::services::metrics_service::MetricsService;
+use crate::{
+    commons::{minio_service::MinioService, url_expiry::UrlExpiryConfig},
+    policies::{
+        face_match_policy::{FaceMatchDecision, FaceMatchPolicy},
+        policy_repository::PolicyRepository,
+    },
+    services::{
+        face_match_cache::FaceMatchCache,
+        face_match_calibration::CalibrationCurve,
+        face_match_limiter::{self, FaceMatchLimiter, FaceMatchOverloaded},
+        face_match_provider::{self, ComparisonRequest, FaceMatchProvider},
+        face_match_signing::{self, SignatureRejected},
+        image_processing_service::processed_document_name,
+        metrics_service::{MetricTags, MetricsService},
+    },
+    submissions::submission_documents::DocumentType,
+};
 
 #[derive(Debug, Serialize)]
 pub struct FaceMatchRequest {
@@ -13,39 +31,153 @@ pub struct FaceMatchRequest {
     pub submission_id: String,
 }
 
+/// Provider named on sandbox results
+pub const SANDBOX_PROVIDER: &str = "sandbox";
+
+/// How long calibration curves are reused before they are read again
+const CALIBRATION_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+type CalibrationCurves = Arc<HashMap<String, CalibrationCurve>>;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FaceMatchResponse {
     pub submission_id: String,
+    /// Calibrated confidence between 0 and 100
     pub similarity_score: f64,
+    /// The score as the provider returned it, on its own scale
+    pub raw_score: f64,
+    /// The provider that answered
+    pub provider: String,
     pub is_match: bool,
     pub threshold: f64,
 }
 
 #[derive(Clone)]
 pub struct FaceMatchService {
-    client: reqwest::Client,
-    base_url: String,
+    /// Tried in order until one answers
+    providers: Arc<Vec<Arc<dyn FaceMatchProvider>>>,
+    /// Confidence between 0 and 100
     threshold: f64,
+    minio_service: MinioService,
     metrics: MetricsService,
+    cache: Option<FaceMatchCache>,
+    /// Skip cached results for this call; the fresh result is still stored
+    bypass_cache: bool,
+    /// Score returned without calling the provider (sandbox mode)
+    canned_score: Option<f64>,
+    /// Shared by every copy of the service, so the cap is process-wide
+    limiter: Option<FaceMatchLimiter>,
+    /// Where calibration curves are configured; providers use their
+    /// built-in scale without it
+    calibrations: Option<PolicyRepository>,
+    /// Curves last read and when, shared by every copy of the service
+    calibration_cache: Arc<RwLock<Option<(CalibrationCurves, Instant)>>>,
 }
 
 impl FaceMatchService {
     pub fn new(
-        base_url: String,
+        providers: Vec<Arc<dyn FaceMatchProvider>>,
         threshold: f64,
-        timeout_millis: u64,
+        minio_service: MinioService,
         metrics: MetricsService,
     ) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_millis(timeout_millis))
-            .build()
-            .expect("Failed to create HTTP client");
-
         Self {
-            client,
-            base_url,
+            providers: Arc::new(providers),
             threshold,
+            minio_service,
             metrics,
+            cache: None,
+            bypass_cache: false,
+            canned_score: None,
+            limiter: None,
+            calibrations: None,
+            calibration_cache: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Calibrate provider scores with the curves in `face_match_calibrations`
+    pub fn with_calibrations(mut self, repository: PolicyRepository) -> Self {
+        self.calibrations = Some(repository);
+        self
+    }
+
+    /// Reuse provider results for image pairs compared before
+    pub fn with_cache(mut self, cache: FaceMatchCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Cap the comparisons in flight to the providers
+    pub fn with_limiter(mut self, limiter: FaceMatchLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// A copy of the service that always calls the provider
+    pub fn bypassing_cache(&self) -> Self {
+        Self {
+            bypass_cache: true,
+            ..self.clone()
+        }
+    }
+
+    /// A copy of the service answering every comparison with `score`, reading
+    /// documents from `minio_service`. It has no cache so sandbox results
+    /// never reach real submissions.
+    pub fn sandboxed(&self, minio_service: MinioService, score: f64) -> Self {
+        Self {
+            minio_service,
+            cache: None,
+            canned_score: Some(score),
+            ..self.clone()
+        }
+    }
+
+    /// Compare two documents stored in MinIO, given with their type. The
+    /// provider gets presigned GET URLs that expire shortly after the call.
+    pub async fn compare_documents(
+        &self,
+        (image1_type, image1_document): (DocumentType, String),
+        (image2_type, image2_document): (DocumentType, String),
+        submission_id: String,
+        url_expiry: &UrlExpiryConfig,
+    ) -> Result<FaceMatchResponse> {
+        let image1_document = self.provider_document(image1_document).await;
+        let image2_document = self.provider_document(image2_document).await;
+        let image1_url = self
+            .minio_service
+            .generate_presigned_url(image1_document.clone(), url_expiry.signed_for(url_expiry.download_expiry(image1_type)))
+            .await?;
+        let image2_url = self
+            .minio_service
+            .generate_presigned_url(image2_document.clone(), url_expiry.signed_for(url_expiry.download_expiry(image2_type)))
+            .await?;
+
+        self.compare_stored_faces(&image1_document, &image2_document, image1_url, image2_url, submission_id)
+            .await
+    }
+
+    /// Compare two stored documents through URLs the caller already signed.
+    /// Results are cached by document name, since the URLs change per call.
+    pub async fn compare_stored_faces(
+        &self,
+        image1_document: &str,
+        image2_document: &str,
+        image1_url: String,
+        image2_url: String,
+        submission_id: String,
+    ) -> Result<FaceMatchResponse> {
+        let cache_key = FaceMatchCache::key(image1_document, image2_document, self.threshold);
+        self.compare_cached(cache_key, image1_url, image2_url, submission_id).await
+    }
+
+    /// The document to send to the provider: the worker's processed variant
+    /// once it exists, the original otherwise
+    pub async fn provider_document(&self, document_name: String) -> String {
+        let processed = processed_document_name(&document_name);
+        match self.minio_service.file_exists(processed.clone()).await {
+            Ok(true) => processed,
+            _ => document_name,
         }
     }
 
@@ -54,59 +186,203 @@ impl FaceMatchService {
         image1_url: String,
         image2_url: String,
         submission_id: String,
+    ) -> Result<FaceMatchResponse> {
+        let cache_key = FaceMatchCache::key(&image1_url, &image2_url, self.threshold);
+        self.compare_cached(cache_key, image1_url, image2_url, submission_id).await
+    }
+
+    async fn compare_cached(
+        &self,
+        cache_key: String,
+        image1_url: String,
+        image2_url: String,
+        submission_id: String,
+    ) -> Result<FaceMatchResponse> {
+        let Some(cache) = &self.cache else {
+            return self.request_comparison(image1_url, image2_url, submission_id).await;
+        };
+
+        let tags = MetricTags::endpoint("face_match");
+
+        if self.bypass_cache {
+            self.metrics.increment("face_match.cache.bypass", Some(tags));
+        } else {
+            match cache.get(&cache_key).await {
+                Ok(Some(cached)) => {
+                    self.metrics.increment("face_match.cache.hit", Some(tags));
+                    tracing::info!("Face match cache hit for submission {}", submission_id);
+                    // Scored again so a calibration changed since applies
+                    let curves = self.calibration_curves().await?;
+                    return Ok(match self.curve(&curves, &cached.provider) {
+                        Some(curve) => self.calibrated(submission_id, cached.provider, cached.raw_score, &curve),
+                        None => FaceMatchResponse { submission_id, ..cached },
+                    });
+                }
+                Ok(None) => self.metrics.increment("face_match.cache.miss", Some(tags)),
+                Err(e) => {
+                    self.metrics.increment("face_match.cache.error", Some(tags));
+                    tracing::warn!("Face match cache lookup failed, calling the provider: {}", e);
+                }
+            }
+        }
+
+        let response = self.request_comparison(image1_url, image2_url, submission_id).await?;
+
+        if let Err(e) = cache.put(&cache_key, &response).await {
+            tracing::warn!("Failed to cache face match result: {}", e);
+        }
+
+        Ok(response)
+    }
+
+    async fn request_comparison(
+        &self,
+        image1_url: String,
+        image2_url: String,
+        submission_id: String,
     ) -> Result<FaceMatchResponse> {
         let start = std::time::Instant::now();
-        let mut tags = HashMap::new();
-        tags.insert("endpoint".to_string(), "face_match".to_string());
-
-        let url = format!(
-            "{}/compare-faces", self.base_url
-        );
-
-        let body = json!({
-            "image1_url": image1_url,
-            "image2_url": image2_url,
-            "threshold": self.threshold,
-        });
-
-        let response = match self
-            .client
-            .post(&url)
-            .header("x-submission-id", &submission_id)
-            .body(body.to_string())
-            .send()
-            .await
-        {
-            Ok(resp) => resp,
-            Err(e) => {
-                self.metrics.increment("face_match.error", Some(tags.clone()));
-                self.metrics.timing("face_match.duration", start.elapsed(), Some(tags));
-                return Err(anyhow::anyhow!("HTTP request failed: {}", e));
+        let tags = MetricTags::endpoint("face_match");
+
+        if let Some(score) = self.canned_score {
+            self.metrics.increment("face_match.sandbox", Some(tags));
+            return Ok(FaceMatchResponse {
+                submission_id,
+                similarity_score: score,
+                raw_score: score,
+                provider: SANDBOX_PROVIDER.to_string(),
+                is_match: score >= self.threshold,
+                threshold: self.threshold,
+            });
+        }
+
+        let curves = self.calibration_curves().await?;
+
+        // Held until the last provider has answered
+        let _permit = match &self.limiter {
+            Some(limiter) => {
+                let wait_start = std::time::Instant::now();
+                let permit = limiter.acquire().await;
+                self.metrics.timing("face_match.limiter.wait", wait_start.elapsed(), Some(tags.clone()));
+                match permit {
+                    Ok(permit) => Some(permit),
+                    Err(e) => {
+                        self.metrics.increment("face_match.limiter.rejected", Some(tags.clone().with("reason", e.reason())));
+                        tracing::warn!("Shedding face match for submission {}: {}", submission_id, e);
+                        return Err(e.into());
+                    }
+                }
             }
+            None => None,
         };
 
-        if !response.status().is_success() {
-            self.metrics.increment("face_match.error", Some(tags.clone()));
-            self.metrics.timing("face_match.duration", start.elapsed(), Some(tags));
-            return Err(anyhow::anyhow!(
-                "Face match API returned error status: {}",
-                response.status()
-            ));
-        }
+        // Fall over to the next provider when one errors or times out
+        let mut last_error = None;
+        for (position, provider) in self.providers.iter().enumerate() {
+            let provider_start = std::time::Instant::now();
+            let provider_tags = tags.clone().with("provider", provider.name());
+            if position > 0 {
+                self.metrics.increment("face_match.provider.failover", Some(provider_tags.clone()));
+            }
 
-        let face_match_response: FaceMatchResponse = match response.json().await {
-            Ok(resp) => resp,
-            Err(e) => {
-                self.metrics.increment("face_match.error", Some(tags.clone()));
-                self.metrics.timing("face_match.duration", start.elapsed(), Some(tags));
-                return Err(anyhow::anyhow!("Failed to parse response: {}", e));
+            let curve = curves.get(provider.name()).cloned().unwrap_or_else(|| provider.default_calibration());
+            let request = ComparisonRequest {
+                image1_url: image1_url.clone(),
+                image2_url: image2_url.clone(),
+                submission_id: submission_id.clone(),
+                threshold: curve.raw_for(self.threshold),
+            };
+
+            match provider.compare(&request).await {
+                Ok(raw_score) => {
+                    self.metrics.timing(
+                        "face_match.provider.duration",
+                        provider_start.elapsed(),
+                        Some(provider_tags.outcome("success")),
+                    );
+                    let response = self.calibrated(request.submission_id, provider.name().to_string(), raw_score, &curve);
+                    return Ok(self.comparison_result(response, tags, start));
+                }
+                Err(e) => {
+                    let reason = if face_match_provider::is_timeout(&e) {
+                        "timeout"
+                    } else if e.downcast_ref::<SignatureRejected>().is_some() {
+                        "signature_rejected"
+                    } else {
+                        "error"
+                    };
+                    self.metrics.increment(
+                        "face_match.provider.error",
+                        Some(provider_tags.clone().with("reason", reason)),
+                    );
+                    self.metrics.timing(
+                        "face_match.provider.duration",
+                        provider_start.elapsed(),
+                        Some(provider_tags.outcome("error")),
+                    );
+                    tracing::warn!(
+                        "Face match provider {} failed for submission {}: {:#}",
+                        provider.name(),
+                        submission_id,
+                        e
+                    );
+                    last_error = Some(e);
+                }
             }
+        }
+
+        self.metrics.increment("face_match.error", Some(tags.clone().outcome("error")));
+        self.metrics.timing("face_match.duration", start.elapsed(), Some(tags.outcome("error")));
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No face match provider is configured")))
+    }
+
+    /// Calibration curves configured in the database, by provider name,
+    /// read again once they are older than `CALIBRATION_REFRESH_INTERVAL`
+    async fn calibration_curves(&self) -> Result<CalibrationCurves> {
+        let Some(repository) = &self.calibrations else {
+            return Ok(CalibrationCurves::default());
         };
 
-        // Check if the match meets our threshold
-        let is_above_threshold = face_match_response.similarity_score >= self.threshold;
-        
-        if is_above_threshold {
+        if let Some((curves, read_at)) = self.calibration_cache.read().unwrap().as_ref() {
+            if read_at.elapsed() < CALIBRATION_REFRESH_INTERVAL {
+                return Ok(curves.clone());
+            }
+        }
+
+        let curves = Arc::new(repository.find_face_match_calibrations().await?);
+        *self.calibration_cache.write().unwrap() = Some((curves.clone(), Instant::now()));
+        Ok(curves)
+    }
+
+    /// The curve of a provider: configured, or the provider's built-in one.
+    /// None for a provider that is no longer configured.
+    fn curve(&self, curves: &HashMap<String, CalibrationCurve>, provider: &str) -> Option<CalibrationCurve> {
+        curves.get(provider).cloned().or_else(|| {
+            self.providers
+                .iter()
+                .find(|configured| configured.name() == provider)
+                .map(|configured| configured.default_calibration())
+        })
+    }
+
+    /// A provider's raw score with its confidence, checked against the threshold
+    fn calibrated(&self, submission_id: String, provider: String, raw_score: f64, curve: &CalibrationCurve) -> FaceMatchResponse {
+        let similarity_score = curve.normalize(raw_score);
+
+        FaceMatchResponse {
+            submission_id,
+            similarity_score,
+            raw_score,
+            provider,
+            is_match: similarity_score >= self.threshold,
+            threshold: self.threshold,
+        }
+    }
+
+    fn comparison_result(&self, response: FaceMatchResponse, tags: MetricTags, start: std::time::Instant) -> FaceMatchResponse {
+        // The provider answered either way; the metric name says whether it matched
+        let tags = tags.outcome("success");
+        if response.is_match {
             self.metrics.increment("face_match.success", Some(tags.clone()));
         } else {
             self.metrics.increment("face_match.failure", Some(tags.clone()));
@@ -114,10 +390,41 @@ impl FaceMatchService {
 
         self.metrics.timing("face_match.duration", start.elapsed(), Some(tags));
 
-        Ok(face_match_response)
+        response
     }
 
     pub fn get_threshold(&self) -> f64 {
         self.threshold
     }
-} 
\ No newline at end of file
+
+    /// Policy used when no decision bands are configured for a tenant and
+    /// submission type: the global threshold with no manual review band
+    pub fn default_policy(&self, tenant_id: &str, submission_type: &str) -> FaceMatchPolicy {
+        FaceMatchPolicy::single_threshold(tenant_id, submission_type, self.get_threshold())
+    }
+
+    /// Evaluate a provider result against a decision policy
+    pub fn evaluate(&self, response: &FaceMatchResponse, policy: &FaceMatchPolicy) -> FaceMatchDecision {
+        let decision = policy.decide(response.similarity_score);
+
+        let tags = MetricTags::endpoint("face_match")
+            .tenant(&policy.tenant_id)
+            .submission_type(&policy.submission_type)
+            .with("decision", decision);
+        self.metrics.increment("face_match.decision", Some(tags));
+
+        decision
+    }
+} 
+/// The error code for a failed comparison: RETRY_LATER when the limiter
+/// turned it away, its own code when the provider refused our signature, the
+/// generic face match error otherwise
+pub fn error_code(error: &anyhow::Error) -> &'static str {
+    if error.downcast_ref::<FaceMatchOverloaded>().is_some() {
+        face_match_limiter::RETRY_LATER_CODE
+    } else if error.downcast_ref::<SignatureRejected>().is_some() {
+        face_match_signing::SIGNATURE_REJECTED_CODE
+    } else {
+        "1006"
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use thiserror::Error;
+
+/// Error code of a comparison the provider refused because of its signature
+pub const SIGNATURE_REJECTED_CODE: &str = "1019";
+
+pub const KEY_ID_HEADER: &str = "X-Key-Id";
+pub const TIMESTAMP_HEADER: &str = "X-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Keys a provider accepts at once: the active one and, while a rotation
+/// rolls out, the one it replaces
+const MAX_SIGNING_KEYS: usize = 2;
+
+/// A provider answered a signed request with `401`, with every key tried
+#[derive(Debug, Error)]
+#[error("FACE_MATCH_SIGNATURE_REJECTED: provider {provider} rejected the request signature (key {key_id})")]
+pub struct SignatureRejected {
+    pub provider: String,
+    /// The last key tried
+    pub key_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SigningKey {
+    pub id: String,
+    pub secret: String,
+}
+
+/// `<key id>:<secret>` pairs separated by commas, the active key first, as in
+/// `FACE_MATCH_SIGNING_KEYS`
+pub fn parse_keys(raw: &str) -> anyhow::Result<Vec<SigningKey>> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (id, secret) = entry
+                .split_once(':')
+                .context("Face match signing keys must be <key id>:<secret>")?;
+            Ok(SigningKey {
+                id: id.trim().to_string(),
+                secret: secret.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Signs outbound face match calls with HMAC-SHA256. The first key is used
+/// first; the second one, if any, is tried when the provider rejects the
+/// signature, so either side of a rotation can go first.
+#[derive(Debug, Clone)]
+pub struct RequestSigner {
+    keys: Vec<SigningKey>,
+}
+
+impl RequestSigner {
+    pub fn new(keys: Vec<SigningKey>) -> anyhow::Result<Self> {
+        if keys.is_empty() || keys.len() > MAX_SIGNING_KEYS {
+            anyhow::bail!("Face match signing needs one or two keys, got {}", keys.len());
+        }
+        if keys.iter().any(|key| key.id.is_empty() || key.secret.is_empty()) {
+            anyhow::bail!("Face match signing keys need an id and a secret");
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn keys(&self) -> &[SigningKey] {
+        &self.keys
+    }
+
+    /// `v1=` followed by the hex HMAC-SHA256 of `{timestamp}.{method}.{path}.{body}`
+    pub fn sign(key: &SigningKey, timestamp: i64, method: &str, path: &str, body: &[u8]) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.secret.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}.{}.{}.", timestamp, method, path).as_bytes());
+        mac.update(body);
+
+        let digest: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("v1={}", digest)
+    }
+
+    /// Add the signature headers for `key` to a request
+    pub fn apply(
+        key: &SigningKey,
+        request: reqwest::RequestBuilder,
+        method: &str,
+        path: &str,
+        body: &[u8],
+    ) -> reqwest::RequestBuilder {
+        let timestamp = chrono::Utc::now().timestamp();
+        request
+            .header(KEY_ID_HEADER, &key.id)
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(SIGNATURE_HEADER, Self::sign(key, timestamp, method, path, body))
+    }
+}
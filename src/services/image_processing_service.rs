@@ -0,0 +1,69 @@
+use anyhow::Result;
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageDecoder, ImageReader};
+use std::io::Cursor;
+
+pub const PROCESSED_CONTENT_TYPE: &str = "image/jpeg";
+
+/// Key of the processed variant stored next to an original document
+pub fn processed_document_name(document_name: &str) -> String {
+    format!("{}_PROCESSED", document_name)
+}
+
+/// Turns camera-sized document images into what the face match provider
+/// needs: upright, no larger than `max_dimension` on either side, JPEG.
+#[derive(Debug, Clone)]
+pub struct ImageProcessingService {
+    max_dimension: u32,
+    jpeg_quality: u8,
+}
+
+impl ImageProcessingService {
+    pub fn new(max_dimension: u32, jpeg_quality: u8) -> Self {
+        Self {
+            max_dimension,
+            jpeg_quality: jpeg_quality.clamp(1, 100),
+        }
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let max_dimension = std::env::var("IMAGE_MAX_DIMENSION")
+            .unwrap_or_else(|_| "1280".to_string())
+            .parse::<u32>()
+            .map_err(|_| anyhow::anyhow!("IMAGE_MAX_DIMENSION must be a number"))?;
+        let jpeg_quality = std::env::var("IMAGE_JPEG_QUALITY")
+            .unwrap_or_else(|_| "85".to_string())
+            .parse::<u8>()
+            .ok()
+            .filter(|quality| (1..=100).contains(quality))
+            .ok_or_else(|| anyhow::anyhow!("IMAGE_JPEG_QUALITY must be a number between 1 and 100"))?;
+
+        Ok(Self::new(max_dimension, jpeg_quality))
+    }
+
+    /// Decode an image, rotate it according to its EXIF orientation, downscale
+    /// it and re-encode it as JPEG. Decoding is CPU bound, so it runs on the
+    /// blocking pool.
+    pub async fn process(&self, content: Vec<u8>) -> Result<Vec<u8>> {
+        let service = self.clone();
+        tokio::task::spawn_blocking(move || service.process_blocking(&content)).await?
+    }
+
+    fn process_blocking(&self, content: &[u8]) -> Result<Vec<u8>> {
+        let mut decoder = ImageReader::new(Cursor::new(content))
+            .with_guessed_format()?
+            .into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+
+        if image.width() > self.max_dimension || image.height() > self.max_dimension {
+            image = image.resize(self.max_dimension, self.max_dimension, FilterType::Lanczos3);
+        }
+
+        // JPEG has no alpha channel
+        let mut encoded = Vec::new();
+        JpegEncoder::new_with_quality(&mut encoded, self.jpeg_quality).encode_image(&image.to_rgb8())?;
+
+        Ok(encoded)
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{bail, Context, Result};
+use jsonwebtoken::{decode, decode_header, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::{commons::logging, utils::Claims};
+
+/// Key id of the single key configured through `JWT_SECRET`
+pub const LEGACY_KEY_ID: &str = "default";
+
+struct SigningKey {
+    kid: String,
+    secret: String,
+}
+
+struct KeySet {
+    /// Key new tokens are signed with
+    signing_kid: String,
+    /// Every key tokens are accepted from, the signing key included
+    keys: Vec<SigningKey>,
+}
+
+impl KeySet {
+    fn get(&self, kid: &str) -> Option<&SigningKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+}
+
+/// JWT signing keys, loaded once and kept in memory. Several keys can be
+/// active at once so a new key can take over signing while tokens signed with
+/// the old one stay valid; tokens name their key in the `kid` header.
+///
+/// Keys come from `JWT_KEYS_FILE`, else `JWT_KEYS` (`kid:secret,...`), else
+/// `JWT_SECRET`. New tokens are signed with `JWT_SIGNING_KEY_ID`, or the first
+/// key listed.
+#[derive(Clone)]
+pub struct KeyProvider {
+    keys: Arc<RwLock<KeySet>>,
+}
+
+impl KeyProvider {
+    pub fn from_env() -> Result<Self> {
+        let keys = load(|name| std::env::var(name).ok())?;
+        Ok(Self {
+            keys: Arc::new(RwLock::new(keys)),
+        })
+    }
+
+    /// Load the keys again, reading the variables from `CONFIG_RELOAD_FILE`
+    /// before the process environment so rotated keys apply without a
+    /// restart. The current keys stay in place if the new ones are invalid.
+    pub fn reload(&self) -> Result<()> {
+        let file = match logging::config_reload_file() {
+            Some(path) => logging::read_config_file(&path).with_context(|| format!("Failed to read {}", path.display()))?,
+            None => HashMap::new(),
+        };
+        let keys = load(|name| file.get(name).cloned().or_else(|| std::env::var(name).ok()))?;
+
+        tracing::info!("Reloaded JWT keys {:?}, signing with {}", key_ids(&keys), keys.signing_kid);
+        *self.keys.write().unwrap() = keys;
+        Ok(())
+    }
+
+    /// The signing key id and the ids of every active key
+    pub fn key_ids(&self) -> (String, Vec<String>) {
+        let keys = self.keys.read().unwrap();
+        (keys.signing_kid.clone(), key_ids(&keys))
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+        let keys = self.keys.read().unwrap();
+        let key = keys.get(&keys.signing_kid).expect("signing key is always loaded");
+
+        let header = Header {
+            kid: Some(key.kid.clone()),
+            ..Header::default()
+        };
+        encode(&header, claims, &EncodingKey::from_secret(key.secret.as_bytes()))
+    }
+
+    /// Validate a token against the key named in its header. Tokens issued
+    /// before keys had ids are tried against every active key.
+    pub fn validate(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        self.validate_as(token)
+    }
+
+    /// Validate a token carrying claims other than a session's
+    pub fn validate_as<T: DeserializeOwned>(&self, token: &str) -> Result<T, jsonwebtoken::errors::Error> {
+        let kid = decode_header(token)?.kid;
+        let keys = self.keys.read().unwrap();
+
+        let candidates: Vec<&SigningKey> = match &kid {
+            Some(kid) => keys.get(kid).into_iter().collect(),
+            None => keys.keys.iter().collect(),
+        };
+
+        let mut result = Err(jsonwebtoken::errors::ErrorKind::InvalidSignature.into());
+        for key in candidates {
+            result = decode::<T>(token, &DecodingKey::from_secret(key.secret.as_bytes()), &Validation::default())
+                .map(|data| data.claims);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+}
+
+fn key_ids(keys: &KeySet) -> Vec<String> {
+    keys.keys.iter().map(|key| key.kid.clone()).collect()
+}
+
+fn load(var: impl Fn(&str) -> Option<String>) -> Result<KeySet> {
+    let var = |name: &str| var(name).filter(|v| !v.trim().is_empty());
+
+    let keys = if let Some(path) = var("JWT_KEYS_FILE") {
+        let contents = std::fs::read_to_string(&path).with_context(|| format!("Failed to read JWT_KEYS_FILE {}", path))?;
+        parse_keys(&contents).context("Invalid JWT_KEYS_FILE")?
+    } else if let Some(keys) = var("JWT_KEYS") {
+        parse_keys(&keys).context("Invalid JWT_KEYS")?
+    } else {
+        let secret = var("JWT_SECRET").context("One of JWT_KEYS_FILE, JWT_KEYS or JWT_SECRET must be set")?;
+        vec![SigningKey {
+            kid: LEGACY_KEY_ID.to_string(),
+            secret,
+        }]
+    };
+
+    let signing_kid = var("JWT_SIGNING_KEY_ID").unwrap_or_else(|| keys[0].kid.clone());
+    let key_set = KeySet { signing_kid, keys };
+    if key_set.get(&key_set.signing_kid).is_none() {
+        bail!("JWT_SIGNING_KEY_ID {} is not one of the configured keys", key_set.signing_kid);
+    }
+
+    Ok(key_set)
+}
+
+/// `kid:secret` pairs separated by commas or newlines
+fn parse_keys(value: &str) -> Result<Vec<SigningKey>> {
+    let mut keys: Vec<SigningKey> = Vec::new();
+
+    for entry in value.split([',', '\n']).map(str::trim).filter(|entry| !entry.is_empty()) {
+        let Some((kid, secret)) = entry.split_once(':') else {
+            bail!("expected kid:secret, got an entry without a key id");
+        };
+        let (kid, secret) = (kid.trim(), secret.trim());
+        if kid.is_empty() || secret.is_empty() {
+            bail!("key id and secret can't be empty");
+        }
+        if keys.iter().any(|key| key.kid == kid) {
+            bail!("key id {} is listed twice", kid);
+        }
+        keys.push(SigningKey {
+            kid: kid.to_string(),
+            secret: secret.to_string(),
+        });
+    }
+
+    if keys.is_empty() {
+        bail!("no keys configured");
+    }
+    Ok(keys)
+}
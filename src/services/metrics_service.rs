@@ -1,7 +1,70 @@
-use std::collections::HashMap;
 use statsd::Client;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+/// Dimensions of a metric. The well-known ones have their own setters so API
+/// and worker metrics name them the same way; keys are kept sorted so the
+/// same tags always make the same metric name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MetricTags(BTreeMap<&'static str, String>);
+
+impl MetricTags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tags of a metric recorded by `endpoint`
+    pub fn endpoint(endpoint: &str) -> Self {
+        Self::new().with("endpoint", endpoint)
+    }
+
+    pub fn tenant(self, tenant_id: &str) -> Self {
+        self.with("tenant", tenant_id)
+    }
+
+    pub fn submission_type(self, submission_type: impl ToString) -> Self {
+        self.with("submission_type", submission_type)
+    }
+
+    pub fn document_type(self, document_type: impl ToString) -> Self {
+        self.with("document_type", document_type)
+    }
+
+    /// How the operation ended, e.g. `success` or `error`
+    pub fn outcome(self, outcome: &str) -> Self {
+        self.with("outcome", outcome)
+    }
+
+    pub fn with(mut self, key: &'static str, value: impl ToString) -> Self {
+        self.set(key, value);
+        self
+    }
+
+    /// Add a tag once it is known, for tags built before the value was
+    pub fn set(&mut self, key: &'static str, value: impl ToString) {
+        self.0.insert(key, value.to_string());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.0.iter().map(|(key, value)| (*key, value.as_str()))
+    }
+
+    /// `metric#key=value,...`, or the bare metric without tags
+    fn metric_name(metric: &str, tags: Option<MetricTags>) -> String {
+        match tags.filter(|tags| !tags.0.is_empty()) {
+            Some(tags) => {
+                let tag_string = tags
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<String>>()
+                    .join(",");
+                format!("{}#{}", metric, tag_string)
+            }
+            None => metric.to_string(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct MetricsService {
     client: Arc<Client>,
@@ -13,42 +76,15 @@ impl MetricsService {
         Self { client }
     }
 
-    pub fn increment(&self, metric: &str, tags: Option<HashMap<String, String>>) {
-        let mut metric_name = metric.to_string();
-        if let Some(tags) = tags {
-            let tag_string = tags
-                .iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect::<Vec<String>>()
-                .join(",");
-            metric_name = format!("{}#{}", metric_name, tag_string);
-        }
-        self.client.incr(&metric_name);
-    }
-
-    pub fn gauge(&self, metric: &str, value: f64, tags: Option<HashMap<String, String>>) {
-        let mut metric_name = metric.to_string();
-        if let Some(tags) = tags {
-            let tag_string = tags
-                .iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect::<Vec<String>>()
-                .join(",");
-            metric_name = format!("{}#{}", metric_name, tag_string);
-        }
-        self.client.gauge(&metric_name, value);
-    }
-
-    pub fn timing(&self, metric: &str, duration: std::time::Duration, tags: Option<HashMap<String, String>>) {
-        let mut metric_name = metric.to_string();
-        if let Some(tags) = tags {
-            let tag_string = tags
-                .iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect::<Vec<String>>()
-                .join(",");
-            metric_name = format!("{}#{}", metric_name, tag_string);
-        }
-        self.client.timer(&metric_name, duration.as_millis() as f64);
+    pub fn increment(&self, metric: &str, tags: Option<MetricTags>) {
+        self.client.incr(&MetricTags::metric_name(metric, tags));
+    }
+
+    pub fn gauge(&self, metric: &str, value: f64, tags: Option<MetricTags>) {
+        self.client.gauge(&MetricTags::metric_name(metric, tags), value);
     }
-} 
\ No newline at end of file
+
+    pub fn timing(&self, metric: &str, duration: std::time::Duration, tags: Option<MetricTags>) {
+        self.client.timer(&MetricTags::metric_name(metric, tags), duration.as_millis() as f64);
+    }
+}
@@ -1,3 +1,17 @@
 pub mod auth_service;
 pub mod metrics_service;
-pub mod face_match_service; 
\ No newline at end of file
+pub mod face_match_service;
+pub mod face_match_provider;
+pub mod password_hasher;
+pub mod webhook_service;
+pub mod webhook_delivery_repository;
+pub mod image_processing_service;
+pub mod face_match_cache;
+pub mod face_match_limiter;
+pub mod scanner_service;
+pub mod report_service;
+pub mod key_provider;
+pub mod face_match_signing;
+pub mod face_match_calibration;
+pub mod face_match_recorder;
+pub mod captcha_service;
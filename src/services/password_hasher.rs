@@ -0,0 +1,106 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+
+/// Argon2id cost parameters, read from PASSWORD_HASH_* env vars. The defaults
+/// match the argon2 crate's recommended minimums.
+#[derive(Debug, Clone)]
+pub struct PasswordHashConfig {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl PasswordHashConfig {
+    pub fn from_env() -> Result<Self, anyhow::Error> {
+        let read = |name: &str, default: u32| {
+            std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<u32>())
+                .transpose()
+                .map_err(|_| anyhow::anyhow!("{} must be a number", name))
+                .map(|value| value.unwrap_or(default))
+        };
+
+        let config = Self {
+            memory_kib: read("PASSWORD_HASH_MEMORY_KIB", Params::DEFAULT_M_COST)?,
+            iterations: read("PASSWORD_HASH_ITERATIONS", Params::DEFAULT_T_COST)?,
+            parallelism: read("PASSWORD_HASH_PARALLELISM", Params::DEFAULT_P_COST)?,
+        };
+        // Costs argon2 refuses would otherwise only fail at the first login
+        config.argon2()?;
+        Ok(config)
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>, anyhow::Error> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|e| anyhow::anyhow!("Invalid password hash parameters: {}", e))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    /// Whether a stored hash was made with a different algorithm or cost
+    fn is_outdated(&self, hash: &PasswordHash) -> bool {
+        let params = match Params::try_from(hash) {
+            Ok(params) => params,
+            Err(_) => return true,
+        };
+
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || params.m_cost() != self.memory_kib
+            || params.t_cost() != self.iterations
+            || params.p_cost() != self.parallelism
+    }
+}
+
+pub enum PasswordCheck {
+    Invalid,
+    Valid,
+    /// The password matched a hash made with outdated parameters
+    ValidNeedsRehash,
+}
+
+/// Hash a password on the blocking pool so it doesn't stall the executor
+pub async fn hash_password(config: &PasswordHashConfig, password: String) -> Result<String, anyhow::Error> {
+    let config = config.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut argon2::password_hash::rand_core::OsRng);
+        config
+            .argon2()?
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))
+    })
+    .await?
+}
+
+/// Verify a password on the blocking pool. Stored hashes carry their own
+/// parameters, so older hashes keep verifying after the cost changes.
+pub async fn verify_password(
+    config: &PasswordHashConfig,
+    password: String,
+    password_hash: String,
+) -> Result<PasswordCheck, anyhow::Error> {
+    let config = config.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let parsed_hash = PasswordHash::new(&password_hash)
+            .map_err(|e| anyhow::anyhow!("Invalid password hash: {}", e))?;
+
+        if Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_err()
+        {
+            return Ok(PasswordCheck::Invalid);
+        }
+
+        if config.is_outdated(&parsed_hash) {
+            Ok(PasswordCheck::ValidNeedsRehash)
+        } else {
+            Ok(PasswordCheck::Valid)
+        }
+    })
+    .await?
+}
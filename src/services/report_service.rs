@@ -0,0 +1,188 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use image::ImageReader;
+use std::io::Cursor;
+use uuid::Uuid;
+
+use crate::{
+    commons::{
+        minio_service::MinioService,
+        pdf::{Font, JpegImage, PdfDocument, PdfPage, PAGE_HEIGHT, PAGE_WIDTH},
+    },
+    services::image_processing_service::ImageProcessingService,
+    submissions::{submission_documents::DocumentType, submission_repository::SubmissionSummary},
+};
+
+pub const REPORT_CONTENT_TYPE: &str = "application/pdf";
+
+/// Where the report of a submission is stored, next to its documents
+pub fn report_document_name(tenant_id: &str, submission_id: Uuid) -> String {
+    format!("{}REPORT/kyc-report.pdf", MinioService::submission_prefix(tenant_id, submission_id))
+}
+
+const MARGIN: f32 = 50.0;
+const LINE_HEIGHT: f32 = 16.0;
+const LABEL_WIDTH: f32 = 150.0;
+const THUMBNAIL_SIZE: u32 = 240;
+/// Thumbnails are drawn into boxes of this many points
+const THUMBNAIL_BOX: f32 = 150.0;
+/// Values are cut off rather than wrapped
+const MAX_VALUE_LENGTH: usize = 70;
+
+/// Renders the KYC report handed to partners once a submission is approved
+#[derive(Clone)]
+pub struct ReportService {
+    images: ImageProcessingService,
+}
+
+impl Default for ReportService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReportService {
+    pub fn new() -> Self {
+        Self {
+            images: ImageProcessingService::new(THUMBNAIL_SIZE, 80),
+        }
+    }
+
+    /// Downscale a document image for the report
+    pub async fn thumbnail(&self, content: Vec<u8>) -> Result<JpegImage> {
+        let data = self.images.process(content).await?;
+        let (width, height) = ImageReader::new(Cursor::new(&data))
+            .with_guessed_format()?
+            .into_dimensions()?;
+
+        Ok(JpegImage { data, width, height })
+    }
+
+    pub fn render(
+        &self,
+        summary: &SubmissionSummary,
+        thumbnails: Vec<(DocumentType, JpegImage)>,
+        generated_at: DateTime<Utc>,
+    ) -> Result<Vec<u8>> {
+        let mut layout = Layout::new();
+
+        layout.title("KYC Submission Report");
+        layout.field("Generated at", &timestamp(generated_at));
+
+        layout.heading("Submission");
+        layout.field("Submission ID", &summary.submission_id.to_string());
+        layout.field("Tenant", &summary.tenant_id);
+        layout.field("Submission type", &summary.submission_type);
+        layout.field("User ID", &summary.user_id);
+
+        layout.heading("Decision");
+        layout.field("Status", &summary.status);
+        layout.field("Result", summary.result.as_deref().unwrap_or("-"));
+        layout.field("Reason code", summary.reason_code.as_deref().unwrap_or("-"));
+        layout.field(
+            "Face match score",
+            &summary
+                .face_match_score
+                .map(|score| format!("{:.2}", score))
+                .unwrap_or_else(|| "-".to_string()),
+        );
+
+        layout.heading("Timestamps");
+        layout.field("Created at", &timestamp(summary.created_at));
+        layout.field("Last updated at", &timestamp(summary.updated_at));
+
+        if let Some(data) = summary.request_data.as_object().filter(|data| !data.is_empty()) {
+            layout.heading("Submitted data");
+            let mut fields: Vec<_> = data.iter().collect();
+            fields.sort_by_key(|(key, _)| key.as_str());
+            for (key, value) in fields {
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                layout.field(key, &value);
+            }
+        }
+
+        if !thumbnails.is_empty() {
+            layout.heading("Documents");
+            layout.thumbnails(thumbnails);
+        }
+
+        layout.finish()
+    }
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+/// Lays content out top to bottom, starting a new page when one is full
+struct Layout {
+    document: PdfDocument,
+    page: PdfPage,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            document: PdfDocument::new(),
+            page: PdfPage::default(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// Move down by `height`, on a new page if it doesn't fit on this one
+    fn advance(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.document.add_page(std::mem::take(&mut self.page));
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+    }
+
+    fn title(&mut self, text: &str) {
+        self.advance(24.0);
+        self.page.text(MARGIN, self.y, 20.0, Font::Bold, text);
+        self.advance(8.0);
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.advance(LINE_HEIGHT * 2.0);
+        self.page.text(MARGIN, self.y, 13.0, Font::Bold, text);
+        self.page.line(MARGIN, self.y - 4.0, PAGE_WIDTH - MARGIN, self.y - 4.0);
+        self.advance(6.0);
+    }
+
+    fn field(&mut self, label: &str, value: &str) {
+        let value = if value.chars().count() > MAX_VALUE_LENGTH {
+            format!("{}...", value.chars().take(MAX_VALUE_LENGTH).collect::<String>())
+        } else {
+            value.to_string()
+        };
+
+        self.advance(LINE_HEIGHT);
+        self.page.text(MARGIN, self.y, 10.0, Font::Bold, label);
+        self.page.text(MARGIN + LABEL_WIDTH, self.y, 10.0, Font::Regular, &value);
+    }
+
+    /// Thumbnails side by side with their document type underneath
+    fn thumbnails(&mut self, thumbnails: Vec<(DocumentType, JpegImage)>) {
+        self.advance(THUMBNAIL_BOX + LINE_HEIGHT * 2.0);
+
+        for (i, (document_type, image)) in thumbnails.into_iter().enumerate() {
+            let x = MARGIN + i as f32 * (THUMBNAIL_BOX + 20.0);
+            let scale = THUMBNAIL_BOX / image.width.max(image.height).max(1) as f32;
+            let (width, height) = (image.width as f32 * scale, image.height as f32 * scale);
+
+            self.page.image(x, self.y + LINE_HEIGHT + (THUMBNAIL_BOX - height), width, height, image);
+            self.page.text(x, self.y, 10.0, Font::Bold, document_type.as_str());
+        }
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>> {
+        self.document.add_page(self.page);
+        self.document.render()
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// Prefix infected documents are moved under, out of reach of the submission
+/// flow but kept for investigation
+pub const QUARANTINE_PREFIX: &str = "quarantine/";
+
+/// Where a document is kept once it has been quarantined
+pub fn quarantined_document_name(document_name: &str) -> String {
+    format!("{}{}", QUARANTINE_PREFIX, document_name)
+}
+
+/// clamd reads INSTREAM data in chunks prefixed by their length
+const CLAMAV_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    Infected { signature: String },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HttpScanResponse {
+    infected: bool,
+    signature: Option<String>,
+}
+
+#[derive(Clone)]
+enum ScannerBackend {
+    /// clamd listening on TCP, spoken to with INSTREAM
+    ClamAv { address: String },
+    /// A service taking the document as the request body and answering
+    /// `{ "infected": bool, "signature": string }`
+    Http { client: reqwest::Client, url: String },
+}
+
+/// Checks documents for malware before they are accepted
+#[derive(Clone)]
+pub struct ScannerService {
+    backend: ScannerBackend,
+    timeout: Duration,
+}
+
+impl ScannerService {
+    pub fn clamav(address: String, timeout: Duration) -> Self {
+        Self {
+            backend: ScannerBackend::ClamAv { address },
+            timeout,
+        }
+    }
+
+    pub fn http(url: String, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            backend: ScannerBackend::Http { client, url },
+            timeout,
+        }
+    }
+
+    /// `DOCUMENT_SCANNER` selects `clamav` (at `CLAMAV_ADDRESS`) or `http`
+    /// (at `DOCUMENT_SCANNER_URL`); unset disables scanning
+    pub fn from_env() -> Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let timeout = Duration::from_millis(
+            var("DOCUMENT_SCANNER_TIMEOUT_MILLIS")
+                .map(|v| v.parse::<u64>().context("DOCUMENT_SCANNER_TIMEOUT_MILLIS must be a number"))
+                .transpose()?
+                .unwrap_or(30000),
+        );
+
+        match var("DOCUMENT_SCANNER").as_deref() {
+            None => Ok(None),
+            Some("clamav") => Ok(Some(Self::clamav(
+                var("CLAMAV_ADDRESS").unwrap_or_else(|| "localhost:3310".to_string()),
+                timeout,
+            ))),
+            Some("http") => {
+                let url = var("DOCUMENT_SCANNER_URL").context("DOCUMENT_SCANNER_URL must be set for the http scanner")?;
+                Ok(Some(Self::http(url, timeout)))
+            }
+            Some(other) => Err(anyhow!("DOCUMENT_SCANNER must be clamav or http, got {}", other)),
+        }
+    }
+
+    pub async fn scan(&self, content: &[u8]) -> Result<ScanVerdict> {
+        match &self.backend {
+            ScannerBackend::ClamAv { address } => {
+                tokio::time::timeout(self.timeout, Self::scan_clamav(address, content))
+                    .await
+                    .map_err(|_| anyhow!("ClamAV did not answer within {:?}", self.timeout))?
+            }
+            ScannerBackend::Http { client, url } => {
+                let response: HttpScanResponse = client
+                    .post(url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                    .body(content.to_vec())
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                Ok(if response.infected {
+                    ScanVerdict::Infected {
+                        signature: response.signature.unwrap_or_else(|| "UNKNOWN".to_string()),
+                    }
+                } else {
+                    ScanVerdict::Clean
+                })
+            }
+        }
+    }
+
+    async fn scan_clamav(address: &str, content: &[u8]) -> Result<ScanVerdict> {
+        let mut stream = TcpStream::connect(address)
+            .await
+            .with_context(|| format!("Failed to connect to ClamAV at {}", address))?;
+
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in content.chunks(CLAMAV_CHUNK_SIZE) {
+            stream.write_all(&(chunk.len() as u32).to_be_bytes()).await?;
+            stream.write_all(chunk).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        let reply = String::from_utf8_lossy(&reply);
+        let reply = reply.trim_end_matches(['\0', '\n']);
+
+        // "stream: OK", "stream: <signature> FOUND" or "<message> ERROR"
+        match reply.strip_prefix("stream: ") {
+            Some("OK") => Ok(ScanVerdict::Clean),
+            Some(found) if found.ends_with(" FOUND") => Ok(ScanVerdict::Infected {
+                signature: found.trim_end_matches(" FOUND").to_string(),
+            }),
+            _ => Err(anyhow!("ClamAV scan failed: {}", reply)),
+        }
+    }
+}
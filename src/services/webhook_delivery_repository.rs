@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{types::Json, PgPool};
+use uuid::Uuid;
+
+pub const DELIVERY_STATUS_PENDING: &str = "PENDING";
+pub const DELIVERY_STATUS_DELIVERED: &str = "DELIVERED";
+pub const DELIVERY_STATUS_FAILED: &str = "FAILED";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub endpoint: String,
+    pub url: String,
+    pub event_type: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i32,
+    pub last_status_code: Option<i32>,
+    pub last_latency_ms: Option<i64>,
+    pub attempt_history: Value,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A single POST of a delivery, appended to its history
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryAttempt {
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    pub attempted_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct WebhookDeliveryRepository {
+    pool: PgPool,
+}
+
+impl WebhookDeliveryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, id: Uuid, endpoint: &str, url: &str, event_type: &str, payload: &Value) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (id, endpoint, url, event_type, payload, status)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            id,
+            endpoint,
+            url,
+            event_type,
+            payload,
+            DELIVERY_STATUS_PENDING
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Append an attempt to the delivery's history and move it to `status`
+    pub async fn record_attempt(&self, id: Uuid, attempt: &DeliveryAttempt, status: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $2,
+                attempts = $3,
+                last_status_code = $4,
+                last_latency_ms = $5,
+                attempt_history = attempt_history || jsonb_build_array($6::JSONB),
+                delivered_at = CASE WHEN $2 = $7 THEN NOW() ELSE delivered_at END,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            status,
+            attempt.attempt as i32,
+            attempt.status_code.map(i32::from),
+            attempt.latency_ms as i64,
+            Json(attempt) as _,
+            DELIVERY_STATUS_DELIVERED
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_all(
+        &self,
+        endpoint: Option<&str>,
+        status: Option<&str>,
+        event_type: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+        sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT
+                id,
+                endpoint,
+                url,
+                event_type,
+                payload,
+                status,
+                attempts,
+                last_status_code,
+                last_latency_ms,
+                attempt_history,
+                delivered_at,
+                created_at,
+                updated_at
+            FROM webhook_deliveries
+            WHERE ($1::TEXT IS NULL OR endpoint = $1)
+              AND ($2::TEXT IS NULL OR status = $2)
+              AND ($3::TEXT IS NULL OR event_type = $3)
+            ORDER BY created_at DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            endpoint,
+            status,
+            event_type,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<WebhookDelivery>, sqlx::Error> {
+        sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT
+                id,
+                endpoint,
+                url,
+                event_type,
+                payload,
+                status,
+                attempts,
+                last_status_code,
+                last_latency_ms,
+                attempt_history,
+                delivered_at,
+                created_at,
+                updated_at
+            FROM webhook_deliveries
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+}
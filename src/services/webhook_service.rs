@@ -0,0 +1,349 @@
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::services::webhook_delivery_repository::{
+    DeliveryAttempt, WebhookDelivery, WebhookDeliveryRepository, DELIVERY_STATUS_DELIVERED, DELIVERY_STATUS_FAILED, DELIVERY_STATUS_PENDING,
+};
+
+pub const WEBHOOK_EVENT_REVIEW_APPROVED: &str = "submission.review.approved";
+pub const WEBHOOK_EVENT_REVIEW_REJECTED: &str = "submission.review.rejected";
+pub const WEBHOOK_EVENT_SLA_BREACHED: &str = "submission.sla.breached";
+pub const WEBHOOK_EVENT_SUBMISSION_DECIDED: &str = "submission.decided";
+/// Sent on request to check an endpoint's integration
+pub const WEBHOOK_EVENT_TEST: &str = "webhook.test";
+
+/// Name of the endpoint configured through `WEBHOOK_URL`
+pub const DEFAULT_ENDPOINT: &str = "default";
+
+pub const DELIVERY_ID_HEADER: &str = "X-Webhook-Id";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// A receiver of webhook events. Deliveries to endpoints with a secret are
+/// signed with it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookEndpoint {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// `v1=` followed by the hex HMAC-SHA256 of `{timestamp}.{body}`. Receivers
+/// recompute it, reject timestamps too far from their clock and ignore
+/// delivery ids they have already seen, so a captured request can't be
+/// replayed.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    let digest: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("v1={}", digest)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("WEBHOOK_ENDPOINT_NOT_FOUND: {0}")]
+    UnknownEndpoint(String),
+
+    #[error("UNKNOWN_EVENT_TYPE: {0}")]
+    UnknownEventType(String),
+
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// Data of a made-up event of `event_type`, shaped like the real one, for
+/// receivers to test their handling against
+pub fn sample_data(event_type: &str) -> Option<Value> {
+    let submission_id = Uuid::nil();
+    let data = match event_type {
+        WEBHOOK_EVENT_TEST => json!({ "message": "Test event, no action needed" }),
+        WEBHOOK_EVENT_SUBMISSION_DECIDED => json!({
+            "submissionId": submission_id,
+            "tenantId": "default",
+            "submissionType": "KYC",
+            "submissionStatus": "APPROVED",
+            "result": "AUTO_APPROVE",
+            "reasonCode": null,
+            "decisionReasons": [],
+            "faceMatchScore": 0.92,
+        }),
+        WEBHOOK_EVENT_REVIEW_APPROVED | WEBHOOK_EVENT_REVIEW_REJECTED => json!({
+            "reviewId": 1,
+            "submissionId": submission_id,
+            "tenantId": "default",
+            "submissionType": "KYC",
+            "submissionStatus": if event_type == WEBHOOK_EVENT_REVIEW_APPROVED { "APPROVED" } else { "REJECTED" },
+            "reviewer": "reviewer@example.com",
+            "notes": "Sample review",
+            "reviewedAt": chrono::Utc::now(),
+        }),
+        WEBHOOK_EVENT_SLA_BREACHED => json!({
+            "submissionId": submission_id,
+            "tenantId": "default",
+            "submissionType": "KYC",
+            "status": "INITIATED",
+            "sla": "DECISION",
+            "slaSeconds": 900,
+            "elapsedSeconds": 960,
+            "stages": {},
+        }),
+        _ => return None,
+    };
+
+    Some(data)
+}
+
+/// Posts events to the configured endpoints, retrying failed deliveries and
+/// recording every attempt in `webhook_deliveries`. Without endpoints events
+/// are dropped.
+#[derive(Clone)]
+pub struct WebhookService {
+    client: reqwest::Client,
+    endpoints: Arc<Vec<WebhookEndpoint>>,
+    deliveries: WebhookDeliveryRepository,
+    max_attempts: u32,
+    retry_backoff: Duration,
+}
+
+impl WebhookService {
+    pub fn new(
+        endpoints: Vec<WebhookEndpoint>,
+        deliveries: WebhookDeliveryRepository,
+        timeout_millis: u64,
+        max_attempts: u32,
+        retry_backoff: Duration,
+    ) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(timeout_millis))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            endpoints: Arc::new(endpoints),
+            deliveries,
+            max_attempts: max_attempts.max(1),
+            retry_backoff,
+        }
+    }
+
+    /// Endpoints come from `WEBHOOK_URL` and `WEBHOOK_SECRET`, plus the JSON
+    /// list in `WEBHOOK_ENDPOINTS`
+    pub fn from_env(pool: PgPool) -> anyhow::Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let mut endpoints = Vec::new();
+        if let Some(url) = var("WEBHOOK_URL") {
+            endpoints.push(WebhookEndpoint {
+                name: DEFAULT_ENDPOINT.to_string(),
+                url,
+                secret: var("WEBHOOK_SECRET"),
+            });
+        }
+        if let Some(list) = var("WEBHOOK_ENDPOINTS") {
+            let listed: Vec<WebhookEndpoint> =
+                serde_json::from_str(&list).context("WEBHOOK_ENDPOINTS must be a JSON list of {name, url, secret}")?;
+            endpoints.extend(listed);
+        }
+
+        for (i, endpoint) in endpoints.iter().enumerate() {
+            if endpoints[..i].iter().any(|other| other.name == endpoint.name) {
+                anyhow::bail!("Webhook endpoint {} is configured twice", endpoint.name);
+            }
+            if endpoint.secret.is_none() {
+                log::warn!("Webhook endpoint {} has no secret, its deliveries are unsigned", endpoint.name);
+            }
+        }
+
+        let timeout_millis = var("WEBHOOK_TIMEOUT_MILLIS")
+            .unwrap_or_else(|| "5000".to_string())
+            .parse::<u64>()
+            .context("WEBHOOK_TIMEOUT_MILLIS must be a number")?;
+        let max_attempts = var("WEBHOOK_MAX_ATTEMPTS")
+            .unwrap_or_else(|| "3".to_string())
+            .parse::<u32>()
+            .context("WEBHOOK_MAX_ATTEMPTS must be a number")?;
+        let retry_backoff = var("WEBHOOK_RETRY_BACKOFF_MILLIS")
+            .unwrap_or_else(|| "1000".to_string())
+            .parse::<u64>()
+            .context("WEBHOOK_RETRY_BACKOFF_MILLIS must be a number")?;
+
+        Ok(Self::new(
+            endpoints,
+            WebhookDeliveryRepository::new(pool),
+            timeout_millis,
+            max_attempts,
+            Duration::from_millis(retry_backoff),
+        ))
+    }
+
+    /// Send an event to every endpoint in the background; the caller's change
+    /// is already committed, so a failed delivery is only recorded and logged
+    pub fn dispatch(&self, event_type: &str, data: Value) {
+        for endpoint in self.endpoints.iter() {
+            let service = self.clone();
+            let endpoint = endpoint.clone();
+            let id = Uuid::new_v4();
+            let body = json!({
+                "id": id,
+                "eventType": event_type,
+                "occurredAt": chrono::Utc::now(),
+                "data": data,
+            });
+            let event_type = event_type.to_string();
+
+            tokio::spawn(async move { service.deliver(id, &endpoint, &event_type, body).await });
+        }
+    }
+
+    pub fn endpoint(&self, name: &str) -> Option<&WebhookEndpoint> {
+        self.endpoints.iter().find(|endpoint| endpoint.name == name)
+    }
+
+    /// Send a sample `event_type` event to one endpoint, once, and wait for
+    /// the outcome. The delivery is recorded like any other.
+    pub async fn send_test(&self, endpoint_name: &str, event_type: &str) -> Result<Uuid, WebhookError> {
+        let endpoint = self
+            .endpoint(endpoint_name)
+            .ok_or_else(|| WebhookError::UnknownEndpoint(endpoint_name.to_string()))?;
+        let data = sample_data(event_type).ok_or_else(|| WebhookError::UnknownEventType(event_type.to_string()))?;
+
+        let id = Uuid::new_v4();
+        let body = json!({
+            "id": id,
+            "eventType": event_type,
+            "occurredAt": chrono::Utc::now(),
+            "test": true,
+            "data": data,
+        });
+        self.deliveries.create(id, &endpoint.name, &endpoint.url, event_type, &body).await?;
+
+        let (record, _) = self.attempt(id, endpoint, 1, &body.to_string().into_bytes()).await;
+        self.finish_attempt(id, endpoint, event_type, &record, false).await?;
+        Ok(id)
+    }
+
+    /// Send a recorded delivery again, once, with its original id and body so
+    /// receivers can tell it apart from a new event. Goes to the endpoint's
+    /// current URL; the attempt is added to the delivery's history.
+    pub async fn redeliver(&self, delivery: &WebhookDelivery) -> Result<(), WebhookError> {
+        let endpoint = self
+            .endpoint(&delivery.endpoint)
+            .ok_or_else(|| WebhookError::UnknownEndpoint(delivery.endpoint.clone()))?;
+
+        let attempt = delivery.attempts.max(0) as u32 + 1;
+        let (record, _) = self.attempt(delivery.id, endpoint, attempt, &delivery.payload.to_string().into_bytes()).await;
+        self.finish_attempt(delivery.id, endpoint, &delivery.event_type, &record, false).await?;
+        Ok(())
+    }
+
+    async fn deliver(&self, id: Uuid, endpoint: &WebhookEndpoint, event_type: &str, body: Value) {
+        if let Err(e) = self.deliveries.create(id, &endpoint.name, &endpoint.url, event_type, &body).await {
+            log::error!("Failed to record {} webhook delivery {}: {}", event_type, id, e);
+        }
+
+        // Signatures cover these exact bytes
+        let body = body.to_string().into_bytes();
+        let mut backoff = self.retry_backoff;
+
+        for attempt in 1..=self.max_attempts {
+            let (record, retryable) = self.attempt(id, endpoint, attempt, &body).await;
+            let retry = retryable && attempt < self.max_attempts;
+            if let Err(e) = self.finish_attempt(id, endpoint, event_type, &record, retry).await {
+                log::error!("Failed to record attempt {} of webhook delivery {}: {}", attempt, id, e);
+            }
+
+            match record.error {
+                None => return,
+                Some(_) if retry => {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Some(_) => return,
+            }
+        }
+    }
+
+    /// POST `body` to the endpoint once. The flag tells whether a failure is
+    /// worth retrying.
+    async fn attempt(&self, id: Uuid, endpoint: &WebhookEndpoint, attempt: u32, body: &[u8]) -> (DeliveryAttempt, bool) {
+        let timestamp = chrono::Utc::now().timestamp();
+        let mut request = self
+            .client
+            .post(&endpoint.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(DELIVERY_ID_HEADER, id.to_string())
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .body(body.to_vec());
+        if let Some(secret) = &endpoint.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, timestamp, body));
+        }
+
+        let start = Instant::now();
+        let result = request.send().await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+
+        let (status_code, error, retryable) = match &result {
+            Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None, false),
+            Ok(response) => {
+                let status = response.status();
+                let retryable = status.is_server_error()
+                    || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                    || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                (Some(status.as_u16()), Some(format!("rejected with status {}", status)), retryable)
+            }
+            Err(e) => (None, Some(e.to_string()), true),
+        };
+
+        let record = DeliveryAttempt {
+            attempt,
+            status_code,
+            latency_ms,
+            error,
+            attempted_at: chrono::Utc::now(),
+        };
+        (record, retryable)
+    }
+
+    /// Record an attempt and log its outcome; `retry` leaves a failed
+    /// delivery pending
+    async fn finish_attempt(
+        &self,
+        id: Uuid,
+        endpoint: &WebhookEndpoint,
+        event_type: &str,
+        record: &DeliveryAttempt,
+        retry: bool,
+    ) -> Result<(), sqlx::Error> {
+        let status = match (&record.error, retry) {
+            (None, _) => DELIVERY_STATUS_DELIVERED,
+            (Some(_), true) => DELIVERY_STATUS_PENDING,
+            (Some(_), false) => DELIVERY_STATUS_FAILED,
+        };
+
+        match &record.error {
+            None => log::info!("Delivered {} webhook {} to {}", event_type, id, endpoint.name),
+            Some(error) if retry => log::warn!(
+                "{} webhook {} to {} failed ({}), retrying",
+                event_type, id, endpoint.name, error
+            ),
+            Some(error) => log::error!(
+                "Failed to deliver {} webhook {} to {} on attempt {}: {}",
+                event_type, id, endpoint.name, record.attempt, error
+            ),
+        }
+
+        self.deliveries.record_attempt(id, record, status).await
+    }
+}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::submissions::device_info::DeviceInfo;
+
+/// Key of the consent in a submission's `request_data`
+pub const CONSENT_REQUEST_FIELD: &str = "consent";
+
+/// How far ahead of the server clock a device may report consenting
+const CLOCK_SKEW_MINUTES: i64 = 5;
+
+/// Consent the user gave before their submission was processed, kept for
+/// compliance with the exact text version they agreed to
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct Consent {
+    #[validate(length(min = 1, max = 64, message = "Consent text version must be between 1 and 64 characters"))]
+    pub text_version: String,
+    #[validate(custom = "not_in_future")]
+    pub consented_at: DateTime<Utc>,
+    /// BCP 47 tag of the language the consent text was shown in
+    #[validate(length(min = 2, max = 35, message = "Locale must be between 2 and 35 characters"))]
+    pub locale: String,
+    #[validate]
+    pub device_info: Option<DeviceInfo>,
+}
+
+fn not_in_future(consented_at: &DateTime<Utc>) -> Result<(), ValidationError> {
+    if *consented_at > Utc::now() + Duration::minutes(CLOCK_SKEW_MINUTES) {
+        return Err(ValidationError::new("consented_at_in_future"));
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+/// Fields of a stored device that are encrypted like PII
+pub const DEVICE_INFO_PII_FIELDS: &[&str] = &["ip"];
+
+/// The app reported running on an emulator
+pub const RISK_EMULATOR: &str = "EMULATOR";
+/// The app reported running on a rooted or jailbroken device
+pub const RISK_ROOTED: &str = "ROOTED";
+/// The device's locale region or time zone is outside Indonesia
+pub const RISK_LOCALE_MISMATCH: &str = "LOCALE_MISMATCH";
+
+/// Time zones of Indonesia (WIB, WITA, WIT)
+const INDONESIAN_TIME_ZONES: &[&str] = &["Asia/Jakarta", "Asia/Pontianak", "Asia/Makassar", "Asia/Jayapura"];
+
+/// Device the app runs on, as the app reports it. Everything is optional so
+/// older app versions keep working. None of it is attested, so a modified app
+/// can report anything; the risk signals are advisory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    /// Operating system, e.g. android or ios
+    #[validate(length(max = 32))]
+    pub platform: Option<String>,
+    #[validate(length(max = 32))]
+    pub os_version: Option<String>,
+    #[validate(length(max = 32))]
+    pub app_version: Option<String>,
+    #[validate(length(max = 64))]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom = "ip_address")]
+    pub ip: Option<String>,
+    /// BCP 47 tag of the device's language and region, e.g. id-ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(length(min = 2, max = 35))]
+    pub locale: Option<String>,
+    /// IANA time zone, e.g. Asia/Jakarta
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(length(max = 64))]
+    pub timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emulator: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rooted: Option<bool>,
+}
+
+impl DeviceInfo {
+    /// Whether the locale's region or the time zone point outside Indonesia.
+    /// A locale without a region, like `en`, says nothing about where the
+    /// device is.
+    pub fn locale_mismatch(&self) -> bool {
+        let region = self
+            .locale
+            .as_deref()
+            .and_then(|locale| locale.split(['-', '_']).skip(1).find(|subtag| subtag.len() == 2));
+        let foreign_region = region.is_some_and(|region| !region.eq_ignore_ascii_case("ID"));
+        let foreign_timezone = self
+            .timezone
+            .as_deref()
+            .is_some_and(|timezone| !INDONESIAN_TIME_ZONES.contains(&timezone));
+
+        foreign_region || foreign_timezone
+    }
+
+    /// Risk heuristics the device trips, for reviewers and the decision engine
+    pub fn risk_signals(&self) -> Vec<&'static str> {
+        let mut signals = Vec::new();
+        if self.emulator == Some(true) {
+            signals.push(RISK_EMULATOR);
+        }
+        if self.rooted == Some(true) {
+            signals.push(RISK_ROOTED);
+        }
+        if self.locale_mismatch() {
+            signals.push(RISK_LOCALE_MISMATCH);
+        }
+        signals
+    }
+}
+
+fn ip_address(ip: &str) -> Result<(), ValidationError> {
+    ip.parse::<std::net::IpAddr>()
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("invalid_ip_address"))
+}
@@ -0,0 +1,193 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::submissions::submission_documents::DocumentType;
+
+pub const ACCESSOR_USER: &str = "USER";
+pub const ACCESSOR_ADMIN: &str = "ADMIN";
+
+/// Why a stored document was downloaded. Owners always download their own
+/// documents as `SELF_SERVICE`; admins have to name one of the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentAccessPurpose {
+    SelfService,
+    ManualReview,
+    FraudInvestigation,
+    CustomerSupport,
+    ComplianceAudit,
+    LegalRequest,
+}
+
+impl DocumentAccessPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentAccessPurpose::SelfService => "SELF_SERVICE",
+            DocumentAccessPurpose::ManualReview => "MANUAL_REVIEW",
+            DocumentAccessPurpose::FraudInvestigation => "FRAUD_INVESTIGATION",
+            DocumentAccessPurpose::CustomerSupport => "CUSTOMER_SUPPORT",
+            DocumentAccessPurpose::ComplianceAudit => "COMPLIANCE_AUDIT",
+            DocumentAccessPurpose::LegalRequest => "LEGAL_REQUEST",
+        }
+    }
+
+    /// Whether an admin may download `document_type` for this purpose.
+    /// Support only needs the selfie to recognise a customer; the identity
+    /// card and chip data are kept for reviews, investigations and audits.
+    pub fn permits_admin(&self, document_type: DocumentType) -> bool {
+        match self {
+            DocumentAccessPurpose::SelfService => false,
+            DocumentAccessPurpose::CustomerSupport => document_type == DocumentType::Selfie,
+            DocumentAccessPurpose::ManualReview
+            | DocumentAccessPurpose::FraudInvestigation
+            | DocumentAccessPurpose::ComplianceAudit
+            | DocumentAccessPurpose::LegalRequest => true,
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentAccessPurpose {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for DocumentAccessPurpose {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SELF_SERVICE" => Ok(DocumentAccessPurpose::SelfService),
+            "MANUAL_REVIEW" => Ok(DocumentAccessPurpose::ManualReview),
+            "FRAUD_INVESTIGATION" => Ok(DocumentAccessPurpose::FraudInvestigation),
+            "CUSTOMER_SUPPORT" => Ok(DocumentAccessPurpose::CustomerSupport),
+            "COMPLIANCE_AUDIT" => Ok(DocumentAccessPurpose::ComplianceAudit),
+            "LEGAL_REQUEST" => Ok(DocumentAccessPurpose::LegalRequest),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Who asked for a document download URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentAccessor {
+    /// The submission's owner, by user id
+    User(String),
+    /// An admin, by the name they gave
+    Admin(String),
+}
+
+impl DocumentAccessor {
+    pub fn accessor_type(&self) -> &'static str {
+        match self {
+            DocumentAccessor::User(_) => ACCESSOR_USER,
+            DocumentAccessor::Admin(_) => ACCESSOR_ADMIN,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            DocumentAccessor::User(id) | DocumentAccessor::Admin(id) => id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentAccess {
+    pub id: i64,
+    pub submission_id: Uuid,
+    pub document_type: String,
+    pub accessor_type: String,
+    pub accessor_id: String,
+    pub purpose: String,
+    pub ip_address: Option<String>,
+    pub accessed_at: DateTime<Utc>,
+}
+
+/// Filters of the access report; unset fields match everything and
+/// `accessed_to` is exclusive
+#[derive(Debug, Default)]
+pub struct DocumentAccessFilter {
+    pub submission_id: Option<Uuid>,
+    pub accessor_type: Option<String>,
+    pub accessor_id: Option<String>,
+    pub document_type: Option<String>,
+    pub purpose: Option<String>,
+    pub accessed_from: Option<DateTime<Utc>>,
+    pub accessed_to: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone)]
+pub struct DocumentAccessRepository {
+    pool: PgPool,
+}
+
+impl DocumentAccessRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(
+        &self,
+        submission_id: Uuid,
+        document_type: DocumentType,
+        accessor: &DocumentAccessor,
+        purpose: DocumentAccessPurpose,
+        ip_address: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO document_access_log (submission_id, document_type, accessor_type, accessor_id, purpose, ip_address)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            submission_id,
+            document_type.as_str(),
+            accessor.accessor_type(),
+            accessor.id(),
+            purpose.as_str(),
+            ip_address
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Accesses matching `filter`, newest first
+    pub async fn find_all(
+        &self,
+        filter: &DocumentAccessFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DocumentAccess>, sqlx::Error> {
+        sqlx::query_as!(
+            DocumentAccess,
+            r#"
+            SELECT id, submission_id, document_type, accessor_type, accessor_id, purpose, ip_address, accessed_at
+            FROM document_access_log
+            WHERE ($1::UUID IS NULL OR submission_id = $1)
+              AND ($2::TEXT IS NULL OR accessor_type = $2)
+              AND ($3::TEXT IS NULL OR accessor_id = $3)
+              AND ($4::TEXT IS NULL OR document_type = $4)
+              AND ($5::TEXT IS NULL OR purpose = $5)
+              AND ($6::TIMESTAMPTZ IS NULL OR accessed_at >= $6)
+              AND ($7::TIMESTAMPTZ IS NULL OR accessed_at < $7)
+            ORDER BY accessed_at DESC, id DESC
+            LIMIT $8 OFFSET $9
+            "#,
+            filter.submission_id,
+            filter.accessor_type,
+            filter.accessor_id,
+            filter.document_type,
+            filter.purpose,
+            filter.accessed_from,
+            filter.accessed_to,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+}
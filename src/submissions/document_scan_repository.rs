@@ -0,0 +1,93 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// No verdict yet; a scan job has been enqueued
+pub const SCAN_PENDING: &str = "PENDING";
+pub const SCAN_CLEAN: &str = "CLEAN";
+pub const SCAN_INFECTED: &str = "INFECTED";
+
+/// The scanner's verdict on a stored document
+#[derive(Debug, Clone)]
+pub struct DocumentScan {
+    pub verdict: String,
+    /// ETag of the object version the verdict is for; None while pending
+    pub etag: Option<String>,
+}
+
+impl DocumentScan {
+    /// Whether the verdict is for the object as it is stored now
+    pub fn is_for(&self, etag: &str) -> bool {
+        self.etag.as_deref() == Some(etag)
+    }
+}
+
+#[derive(Clone)]
+pub struct DocumentScanRepository {
+    pool: PgPool,
+}
+
+impl DocumentScanRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find(&self, document_name: &str) -> Result<Option<DocumentScan>, sqlx::Error> {
+        sqlx::query_as!(
+            DocumentScan,
+            "SELECT verdict, etag FROM document_scans WHERE document_name = $1",
+            document_name
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Forget any earlier verdict on the document until it is scanned again
+    pub async fn mark_pending(&self, submission_id: Uuid, document_type: &str, document_name: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO document_scans (document_name, submission_id, document_type, verdict)
+            VALUES ($1, $2, $3, 'PENDING')
+            ON CONFLICT (document_name) DO UPDATE
+            SET verdict = 'PENDING', etag = NULL, signature = NULL, requested_at = NOW(), scanned_at = NULL
+            "#,
+            document_name,
+            submission_id,
+            document_type
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record the verdict on the object version with `etag`
+    pub async fn record(
+        &self,
+        submission_id: Uuid,
+        document_type: &str,
+        document_name: &str,
+        etag: &str,
+        verdict: &str,
+        signature: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO document_scans (document_name, submission_id, document_type, verdict, etag, signature, scanned_at)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW())
+            ON CONFLICT (document_name) DO UPDATE
+            SET verdict = EXCLUDED.verdict, etag = EXCLUDED.etag, signature = EXCLUDED.signature,
+                scanned_at = EXCLUDED.scanned_at
+            "#,
+            document_name,
+            submission_id,
+            document_type,
+            verdict,
+            etag,
+            signature
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use actix_web::{web, HttpRequest};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    services::scanner_service::ScannerService,
+    submissions::document_scan_repository::{DocumentScanRepository, SCAN_CLEAN, SCAN_INFECTED},
+    workers::{FileUploadJob, RedisQueue},
+};
+
+/// Where a stored document stands with the malware scanner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanStatus {
+    Clean,
+    Infected,
+    /// Not scanned as it is stored now; a scan has been enqueued
+    Pending,
+}
+
+/// Holds documents back from processing until the scanner has passed the
+/// version that is stored. Set up when `DOCUMENT_SCANNER` is, as the worker's
+/// scanner is; without it documents are accepted unscanned.
+#[derive(Clone)]
+pub struct DocumentScans {
+    repository: DocumentScanRepository,
+    queue: RedisQueue,
+}
+
+impl DocumentScans {
+    pub fn new(pool: PgPool, queue: RedisQueue) -> Self {
+        Self {
+            repository: DocumentScanRepository::new(pool),
+            queue,
+        }
+    }
+
+    pub fn from_env(pool: PgPool, queue: RedisQueue) -> anyhow::Result<Option<Self>> {
+        Ok(ScannerService::from_env()?.map(|_| Self::new(pool, queue)))
+    }
+
+    /// The app's scans, taken from the request because handlers are limited
+    /// to 16 extractors
+    pub fn of(req: &HttpRequest) -> Option<Self> {
+        req.app_data::<web::Data<Option<DocumentScans>>>()
+            .expect("DocumentScans is registered with the app")
+            .get_ref()
+            .clone()
+    }
+
+    /// Forget the document's verdict and enqueue a scan of it
+    pub async fn request(
+        &self,
+        submission_id: Uuid,
+        document_type: &str,
+        document_name: &str,
+        metadata: Value,
+    ) -> anyhow::Result<()> {
+        self.repository.mark_pending(submission_id, document_type, document_name).await?;
+
+        // A scan already waiting for the document is enough; it reads the
+        // object as it is when it runs
+        let job = FileUploadJob::scan(submission_id.to_string(), document_name.to_string(), document_type.to_string(), metadata);
+        self.queue.clone().enqueue_job(&job).await?;
+        Ok(())
+    }
+
+    /// Status of the document stored as `etag`, enqueueing a scan when
+    /// nothing was recorded for that version
+    pub async fn status(
+        &self,
+        submission_id: Uuid,
+        document_type: &str,
+        document_name: &str,
+        etag: &str,
+        metadata: Value,
+    ) -> anyhow::Result<ScanStatus> {
+        match self.repository.find(document_name).await? {
+            Some(scan) if scan.is_for(etag) && scan.verdict == SCAN_CLEAN => Ok(ScanStatus::Clean),
+            Some(scan) if scan.is_for(etag) && scan.verdict == SCAN_INFECTED => Ok(ScanStatus::Infected),
+            _ => {
+                self.request(submission_id, document_type, document_name, metadata).await?;
+                Ok(ScanStatus::Pending)
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::commons::crypto::PII_REQUEST_FIELDS;
+
+/// Longest field name accepted in a draft
+const MAX_KEY_LENGTH: usize = 64;
+
+/// Longest value accepted for the KTP fields of a draft
+const MAX_PII_FIELD_LENGTH: usize = 512;
+
+/// Why a draft was refused
+#[derive(Debug, Error)]
+pub enum DraftError {
+    #[error("INVALID_DRAFT: the draft must be a JSON object")]
+    NotAnObject,
+
+    #[error("INVALID_DRAFT: the draft nests deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("INVALID_DRAFT: field names must be 1 to {MAX_KEY_LENGTH} characters")]
+    InvalidKey,
+
+    #[error("INVALID_DRAFT: {0} must be a string of at most {MAX_PII_FIELD_LENGTH} characters")]
+    InvalidField(String),
+
+    #[error("DRAFT_TOO_LARGE: the draft is {size} bytes, at most {limit} are allowed")]
+    TooLarge { size: usize, limit: usize },
+}
+
+impl DraftError {
+    /// 1010 like other oversized payloads, 1003 for a malformed draft
+    pub fn code(&self) -> &'static str {
+        match self {
+            DraftError::TooLarge { .. } => "1010",
+            _ => "1003",
+        }
+    }
+}
+
+/// What a submission draft may look like. Drafts are free-form objects; the
+/// KTP fields the submission is later processed with must be strings when
+/// present, so a draft can't smuggle in values they'd never accept.
+#[derive(Debug, Clone, Copy)]
+pub struct DraftLimits {
+    pub max_size_in_bytes: usize,
+    pub max_depth: usize,
+}
+
+impl Default for DraftLimits {
+    fn default() -> Self {
+        Self {
+            max_size_in_bytes: 16 * 1024,
+            max_depth: 5,
+        }
+    }
+}
+
+impl DraftLimits {
+    /// `SUBMISSION_DRAFT_MAX_BYTES` and `SUBMISSION_DRAFT_MAX_DEPTH`
+    pub fn from_env() -> anyhow::Result<Self> {
+        let number = |name: &str, default: usize| -> anyhow::Result<usize> {
+            Ok(std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<usize>())
+                .transpose()
+                .map_err(|_| anyhow::anyhow!("{} must be a number", name))?
+                .unwrap_or(default))
+        };
+
+        let defaults = Self::default();
+        Ok(Self {
+            max_size_in_bytes: number("SUBMISSION_DRAFT_MAX_BYTES", defaults.max_size_in_bytes)?,
+            max_depth: number("SUBMISSION_DRAFT_MAX_DEPTH", defaults.max_depth)?,
+        })
+    }
+
+    pub fn validate(&self, draft: &Value) -> Result<(), DraftError> {
+        let Some(fields) = draft.as_object() else {
+            return Err(DraftError::NotAnObject);
+        };
+
+        let size = serde_json::to_vec(draft).map(|bytes| bytes.len()).unwrap_or(usize::MAX);
+        if size > self.max_size_in_bytes {
+            return Err(DraftError::TooLarge {
+                size,
+                limit: self.max_size_in_bytes,
+            });
+        }
+
+        for field in PII_REQUEST_FIELDS {
+            match fields.get(*field) {
+                None | Some(Value::Null) => {}
+                Some(Value::String(value)) if value.chars().count() <= MAX_PII_FIELD_LENGTH => {}
+                Some(_) => return Err(DraftError::InvalidField(field.to_string())),
+            }
+        }
+
+        self.validate_nested(draft, 1)
+    }
+
+    fn validate_nested(&self, value: &Value, depth: usize) -> Result<(), DraftError> {
+        let children: Box<dyn Iterator<Item = &Value>> = match value {
+            Value::Object(fields) => {
+                if fields.keys().any(|key| key.is_empty() || key.chars().count() > MAX_KEY_LENGTH) {
+                    return Err(DraftError::InvalidKey);
+                }
+                Box::new(fields.values())
+            }
+            Value::Array(items) => Box::new(items.iter()),
+            _ => return Ok(()),
+        };
+
+        if depth > self.max_depth {
+            return Err(DraftError::TooDeep(self.max_depth));
+        }
+        children.into_iter().try_for_each(|child| self.validate_nested(child, depth + 1))
+    }
+}
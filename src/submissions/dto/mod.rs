@@ -1 +1,6 @@
 pub mod presigned_urls_response;
+pub mod upload_document_response;
+pub mod v2;
+pub mod submission_report_response;
+pub mod submission_draft_response;
+pub mod user_submission_view;
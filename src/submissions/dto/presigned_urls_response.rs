@@ -1,25 +1,66 @@
 use std::collections::HashMap;
 
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+use crate::{commons::minio_service::UploadForm, submissions::submission_documents::DocumentUploadProgress};
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Document {
     pub document_url: String,
     pub document_reference: String,
     pub expiry_in_seconds: String,
+    pub expires_at: DateTime<Utc>,
+    /// Content-Type the upload must be sent with
+    pub content_type: String,
+    pub max_size_in_bytes: u64,
+    /// POST alternative to `document_url` that also enforces the size limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload_form: Option<UploadForm>,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "client", derive(Deserialize))]
 #[serde(rename_all = "camelCase")]
 pub struct PresignedUrlsResponse {
     pub submission_id: String,
     pub documents: HashMap<String, Document>,
+    /// Token the upload and process calls of the submission have to send in
+    /// `X-Submission-Token`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission_token_expires_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubmissionData {
     pub document_name: String,
     pub document_reference: String,
-}
\ No newline at end of file
+    /// When the latest upload URL handed out for the document expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_url_expires_at: Option<DateTime<Utc>>,
+    /// Set by the upload worker once it picks the document up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload: Option<DocumentUploadProgress>,
+}
+/// A resubmission of a rejected submission: upload URLs for the documents
+/// that have to be uploaded again, the others having been carried over
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResubmissionResponse {
+    pub submission_id: String,
+    pub parent_submission_id: String,
+    pub resubmission_attempt: i32,
+    pub documents: HashMap<String, Document>,
+    /// Types of the documents copied from the rejected submission
+    pub carried_over_documents: Vec<String>,
+    /// Token the upload and process calls of the submission have to send in
+    /// `X-Submission-Token`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission_token_expires_at: Option<DateTime<Utc>>,
+}
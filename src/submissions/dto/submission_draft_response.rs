@@ -0,0 +1,9 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionDraftResponse {
+    pub submission_id: String,
+    pub draft_updated_at: DateTime<Utc>,
+}
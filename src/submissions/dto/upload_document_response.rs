@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::commons::minio_service::UploadForm;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadDocumentResponse {
+    pub submission_id: String,
+    pub document_type: String,
+    pub document_reference: String,
+    pub size_in_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshUploadUrlResponse {
+    pub submission_id: String,
+    pub document_type: String,
+    pub document_url: String,
+    pub document_reference: String,
+    pub expiry_in_seconds: String,
+    pub expires_at: DateTime<Utc>,
+    pub content_type: String,
+    pub max_size_in_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload_form: Option<UploadForm>,
+    /// A new token the upload and process calls of the submission have to send in
+    /// `X-Submission-Token`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission_token_expires_at: Option<DateTime<Utc>>,
+}
+
+/// Short-lived link to a stored document, handed out once the access is logged
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentDownloadResponse {
+    pub submission_id: String,
+    pub document_type: String,
+    pub download_url: String,
+    pub expiry_in_seconds: String,
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::submissions::submission_controller::GetSubmissionStatusResponse;
+
+/// One of a user's submissions as the status endpoint shows it to them
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSubmissionView {
+    pub submission_id: Uuid,
+    pub submission_type: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub status: GetSubmissionStatusResponse,
+}
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    commons::minio_service::UploadForm,
+    submissions::{
+        consent::Consent, device_info::DeviceInfo, dto::presigned_urls_response::PresignedUrlsResponse,
+        nfc_replay::NfcIdentifier, submission_controller::SubmissionType, submission_documents::DocumentUploadProgress,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSubmissionRequest {
+    pub submission_type: SubmissionType,
+    /// Bare base64 read or a signed envelope
+    pub nfc_identifier: NfcIdentifier,
+    #[serde(default)]
+    pub device_info: Option<DeviceInfo>,
+}
+
+/// Optional body of a process request; an empty body processes without consent
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessSubmissionRequest {
+    #[serde(default)]
+    pub consent: Option<Consent>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadSlot {
+    pub document_type: String,
+    pub document_reference: String,
+    pub upload_url: String,
+    pub expires_in_seconds: u64,
+    pub expires_at: DateTime<Utc>,
+    pub content_type: String,
+    pub max_size_in_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload_form: Option<UploadForm>,
+}
+
+/// v2 shape of a new submission: documents are a list carrying their type
+/// rather than a map keyed by it, and the expiry is a number
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionCreated {
+    pub submission_id: String,
+    pub documents: Vec<UploadSlot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submission_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submission_token_expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<PresignedUrlsResponse> for SubmissionCreated {
+    fn from(response: PresignedUrlsResponse) -> Self {
+        let mut documents: Vec<UploadSlot> = response
+            .documents
+            .into_iter()
+            .map(|(document_type, document)| UploadSlot {
+                document_type,
+                document_reference: document.document_reference,
+                upload_url: document.document_url,
+                expires_in_seconds: document.expiry_in_seconds.parse().unwrap_or_default(),
+                expires_at: document.expires_at,
+                content_type: document.content_type,
+                max_size_in_bytes: document.max_size_in_bytes,
+                upload_form: document.upload_form,
+            })
+            .collect();
+        // HashMap order isn't stable; clients get the documents in a fixed order
+        documents.sort_by(|a, b| a.document_type.cmp(&b.document_type));
+
+        Self {
+            submission_id: response.submission_id,
+            documents,
+            submission_token: response.submission_token,
+            submission_token_expires_at: response.submission_token_expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceMatchRequest {
+    pub image1_reference: String,
+    pub image2_reference: String,
+    /// Call the provider even when this image pair was compared before
+    #[serde(default)]
+    pub bypass_cache: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceMatchResult {
+    pub submission_id: String,
+    /// Calibrated confidence between 0 and 100
+    pub similarity_score: f64,
+    /// The score as the provider returned it
+    pub raw_score: f64,
+    pub provider: String,
+    pub is_match: bool,
+    pub threshold: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessedSubmission {
+    pub submission_id: String,
+    pub submission_status: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionStatus {
+    pub submission_status: String,
+    pub decision_reasons: Vec<String>,
+    pub documents: BTreeMap<String, Option<DocumentUploadProgress>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draft: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draft_updated_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub uploads_expire_at: BTreeMap<String, DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionStatusQuery {
+    pub submission_type: SubmissionType,
+    pub nfc_identifier: String,
+}
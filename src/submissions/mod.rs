@@ -2,3 +2,22 @@ pub mod dto;
 pub mod submission_controller;
 pub mod submission_service;
 pub mod submission_repository;
+pub mod submission_event_repository;
+pub mod submission_documents;
+pub mod submission_review_repository;
+pub mod submission_quota;
+pub mod submission_controller_v2;
+pub mod consent;
+pub mod nfc_replay;
+pub mod document_access_repository;
+pub mod document_scan_repository;
+pub mod document_scans;
+pub mod draft;
+pub mod device_info;
+pub mod submission_export;
+pub mod nfc_payload;
+pub mod submission_data_spill;
+pub mod submission_data_patch;
+pub mod submission_token;
+pub mod status_stream;
+pub mod submission_ttl;
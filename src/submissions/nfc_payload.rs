@@ -0,0 +1,33 @@
+use base64::{engine::general_purpose::STANDARD, DecodeError, Engine as _};
+use bytes::Bytes;
+use futures::Stream;
+
+/// Prefix some apps put in front of the base64 NFC image
+const DATA_URL_PREFIX: &str = "data:image/jpeg;base64,";
+
+/// Base64 characters decoded at a time. A multiple of 4, so every chunk but
+/// the last one holds whole base64 quanta and only the last may be padded.
+const CHUNK_CHARS: usize = 64 * 1024;
+
+/// The base64 of an NFC read, without the data URL prefix if it has one
+pub fn encoded_image(payload: &str) -> &str {
+    payload.strip_prefix(DATA_URL_PREFIX).unwrap_or(payload)
+}
+
+/// Size `encoded` decodes to, worked out without decoding it. Payloads that
+/// aren't base64 are caught while decoding.
+pub fn decoded_len(encoded: &str) -> u64 {
+    let padding = encoded.bytes().rev().take(2).take_while(|b| *b == b'=').count() as u64;
+    (encoded.len() as u64).div_ceil(4) * 3 - padding
+}
+
+/// Decode `encoded` a chunk at a time, so the image is never held in memory
+/// whole next to its base64
+pub fn decode_chunks(encoded: &str) -> impl Stream<Item = Result<Bytes, DecodeError>> + Unpin + '_ {
+    futures::stream::iter(
+        encoded
+            .as_bytes()
+            .chunks(CHUNK_CHARS)
+            .map(|chunk| STANDARD.decode(chunk).map(Bytes::from)),
+    )
+}
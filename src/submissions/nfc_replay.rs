@@ -0,0 +1,166 @@
+use anyhow::Context;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::commons::redis_connection::{RedisConnection, RedisTopology};
+
+/// Nonces longer than this are rejected rather than stored
+const MAX_NONCE_LENGTH: usize = 128;
+
+/// The NFC read a client submits: either the bare base64 payload, or an
+/// envelope signed by the reading app so a captured read can't be sent again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NfcIdentifier {
+    Raw(String),
+    Signed(NfcEnvelope),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NfcEnvelope {
+    pub payload: String,
+    /// Unique per read
+    pub nonce: String,
+    /// Unix seconds at which the card was read
+    pub timestamp: i64,
+    /// Hex HMAC-SHA256 of `{timestamp}.{nonce}.{payload}`
+    pub signature: String,
+}
+
+impl NfcIdentifier {
+    pub fn payload(&self) -> &str {
+        match self {
+            NfcIdentifier::Raw(payload) => payload,
+            NfcIdentifier::Signed(envelope) => &envelope.payload,
+        }
+    }
+}
+
+impl From<String> for NfcIdentifier {
+    fn from(payload: String) -> Self {
+        NfcIdentifier::Raw(payload)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NfcRejection {
+    #[error("NFC_SIGNATURE_REQUIRED")]
+    Unsigned,
+
+    #[error("NFC_SIGNATURE_INVALID")]
+    InvalidSignature,
+
+    #[error("NFC_READ_EXPIRED: reads must be submitted within {max_age_seconds} seconds")]
+    Stale { max_age_seconds: i64 },
+
+    #[error("NFC_READ_REPLAYED")]
+    Replayed,
+
+    #[error("NFC_NONCE_CHECK_FAILED: {0}")]
+    Redis(#[from] redis::RedisError),
+}
+
+/// Verifies signed NFC envelopes: the signature must match, the read must be
+/// recent, and its nonce must not have been seen before. Nonces are kept in
+/// Redis for as long as their read could still pass the timestamp check.
+#[derive(Clone)]
+pub struct NfcReplayGuard {
+    connection_manager: RedisConnection,
+    secret: String,
+    max_age_seconds: i64,
+    /// Reject bare payloads instead of accepting them unverified
+    require_signed: bool,
+}
+
+impl NfcReplayGuard {
+    pub fn new(connection_manager: RedisConnection, secret: String, max_age_seconds: i64, require_signed: bool) -> Self {
+        Self {
+            connection_manager,
+            secret,
+            max_age_seconds,
+            require_signed,
+        }
+    }
+
+    /// None unless `NFC_SIGNING_SECRET` is set, which it has to be with
+    /// `APP_ENV=production`. Bare reads are rejected unless
+    /// `NFC_REQUIRE_SIGNED=false`, which production refuses.
+    pub async fn from_env(redis: &RedisTopology) -> anyhow::Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let production = var("APP_ENV").as_deref() == Some("production");
+
+        let Some(secret) = var("NFC_SIGNING_SECRET") else {
+            if production {
+                anyhow::bail!("NFC_SIGNING_SECRET must be set when APP_ENV=production");
+            }
+            return Ok(None);
+        };
+
+        let max_age_seconds = var("NFC_MAX_AGE_SECONDS")
+            .map(|v| v.parse::<i64>().context("NFC_MAX_AGE_SECONDS must be a number"))
+            .transpose()?
+            .unwrap_or(300);
+        let require_signed = var("NFC_REQUIRE_SIGNED").as_deref() != Some("false");
+        if production && !require_signed {
+            anyhow::bail!("NFC_REQUIRE_SIGNED=false is refused when APP_ENV=production");
+        }
+
+        let connection_manager = redis.connect().await?;
+
+        Ok(Some(Self::new(connection_manager, secret, max_age_seconds, require_signed)))
+    }
+
+    /// The payload of `identifier` once it passes every check. A nonce is
+    /// only used up by a read whose signature and timestamp are valid.
+    pub async fn verify<'a>(&self, identifier: &'a NfcIdentifier) -> Result<&'a str, NfcRejection> {
+        let envelope = match identifier {
+            NfcIdentifier::Raw(_) if self.require_signed => return Err(NfcRejection::Unsigned),
+            NfcIdentifier::Raw(payload) => return Ok(payload),
+            NfcIdentifier::Signed(envelope) => envelope,
+        };
+
+        if envelope.nonce.is_empty() || envelope.nonce.len() > MAX_NONCE_LENGTH || !self.signature_matches(envelope) {
+            return Err(NfcRejection::InvalidSignature);
+        }
+
+        // Clocks of the reading device may run ahead too
+        if (Utc::now().timestamp() - envelope.timestamp).abs() > self.max_age_seconds {
+            return Err(NfcRejection::Stale {
+                max_age_seconds: self.max_age_seconds,
+            });
+        }
+
+        let mut conn = self.connection_manager.clone();
+        let fresh: bool = redis::cmd("SET")
+            .arg(format!("nfc_nonce:{}", envelope.nonce))
+            .arg(envelope.timestamp)
+            .arg("NX")
+            .arg("EX")
+            .arg(self.max_age_seconds * 2)
+            .query_async::<_, Option<String>>(&mut conn)
+            .await?
+            .is_some();
+        if !fresh {
+            return Err(NfcRejection::Replayed);
+        }
+
+        Ok(&envelope.payload)
+    }
+
+    fn signature_matches(&self, envelope: &NfcEnvelope) -> bool {
+        let Some(signature) = (0..envelope.signature.len())
+            .step_by(2)
+            .map(|i| envelope.signature.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+        else {
+            return false;
+        };
+
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.secret.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}.{}.{}", envelope.timestamp, envelope.nonce, envelope.payload).as_bytes());
+        mac.verify_slice(&signature).is_ok()
+    }
+}
@@ -0,0 +1,144 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgListener, PgPool};
+use std::{convert::Infallible, time::Duration};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+/// Channel the `submissions_status_notify` trigger announces status changes on
+pub const STATUS_CHANNEL: &str = "submission_status";
+
+/// Changes kept for subscribers that fall behind before they miss some
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Wait before listening again after the connection dropped
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Comment sent on idle streams so proxies and clients don't time them out
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Statuses a submission doesn't leave; its stream ends once it reaches one
+const FINAL_STATUSES: [&str; 2] = ["APPROVED", "REJECTED"];
+
+/// A submission's status as pushed to clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionStatusChange {
+    pub submission_id: Uuid,
+    pub status: String,
+    pub result: Option<String>,
+    pub reason_code: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Fans the status changes Postgres announces out to the server-sent event
+/// streams of this instance. One connection listens for all of them.
+#[derive(Clone)]
+pub struct StatusStream {
+    sender: broadcast::Sender<SubmissionStatusChange>,
+}
+
+impl StatusStream {
+    /// Start listening in the background
+    pub fn start(pool: PgPool) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        tokio::spawn(listen(pool, sender.clone()));
+        Self { sender }
+    }
+
+    /// Changes from now on. Subscribe before reading the current status, so
+    /// a change between the two isn't missed.
+    pub fn subscribe(&self) -> broadcast::Receiver<SubmissionStatusChange> {
+        self.sender.subscribe()
+    }
+
+    /// Server-sent events for one submission: `current` first, then every
+    /// change of it until it reaches a final status
+    pub fn events(
+        current: SubmissionStatusChange,
+        receiver: broadcast::Receiver<SubmissionStatusChange>,
+    ) -> impl Stream<Item = Result<Bytes, Infallible>> {
+        struct State {
+            submission_id: Uuid,
+            next: Option<SubmissionStatusChange>,
+            receiver: broadcast::Receiver<SubmissionStatusChange>,
+            done: bool,
+        }
+
+        let state = State {
+            submission_id: current.submission_id,
+            next: Some(current),
+            receiver,
+            done: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+
+            let change = match state.next.take() {
+                Some(change) => change,
+                None => loop {
+                    match tokio::time::timeout(KEEP_ALIVE_INTERVAL, state.receiver.recv()).await {
+                        Ok(Ok(change)) if change.submission_id == state.submission_id => break change,
+                        Ok(Ok(_)) => continue,
+                        Ok(Err(RecvError::Lagged(missed))) => {
+                            log::warn!("Status stream of {} missed {} changes", state.submission_id, missed);
+                            continue;
+                        }
+                        Ok(Err(RecvError::Closed)) => return None,
+                        Err(_) => return Some((Ok(Bytes::from_static(b": keep-alive\n\n")), state)),
+                    }
+                },
+            };
+
+            state.done = FINAL_STATUSES.contains(&change.status.as_str());
+            let data = serde_json::to_string(&change).unwrap_or_default();
+            Some((Ok(Bytes::from(format!("event: status\ndata: {}\n\n", data))), state))
+        })
+    }
+}
+
+/// Forward notifications until the process exits, listening again whenever
+/// the connection drops. Changes made while it was down aren't replayed.
+async fn listen(pool: PgPool, sender: broadcast::Sender<SubmissionStatusChange>) {
+    loop {
+        let mut listener = match PgListener::connect_with(&pool).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::warn!("Failed to connect the status listener: {}", e);
+                tokio::time::sleep(RECONNECT_BACKOFF).await;
+                continue;
+            }
+        };
+        if let Err(e) = listener.listen(STATUS_CHANNEL).await {
+            log::warn!("Failed to listen on {}: {}", STATUS_CHANNEL, e);
+            tokio::time::sleep(RECONNECT_BACKOFF).await;
+            continue;
+        }
+
+        loop {
+            match listener.try_recv().await {
+                Ok(Some(notification)) => match serde_json::from_str::<SubmissionStatusChange>(notification.payload()) {
+                    // No subscribers is fine, nobody is following a submission
+                    Ok(change) => {
+                        let _ = sender.send(change);
+                    }
+                    Err(e) => log::warn!("Ignoring malformed status notification: {}", e),
+                },
+                Ok(None) => {
+                    log::warn!("Status listener connection dropped, listening again");
+                    break;
+                }
+                Err(e) => {
+                    log::warn!("Status listener failed: {}", e);
+                    tokio::time::sleep(RECONNECT_BACKOFF).await;
+                    break;
+                }
+            }
+        }
+    }
+}
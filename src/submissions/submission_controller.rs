@@ -1,45 +1,100 @@
-use actix_web::{web, HttpResponse};
+use actix_multipart::Multipart;
+use actix_web::{
+    http::{header, StatusCode},
+    web, HttpRequest, HttpResponse, ResponseError,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 use crate::{
-    commons::minio_service::MinioService,
-    models::user::{ApiResponse, ApiError},
-    services::{metrics_service::MetricsService, face_match_service::FaceMatchService},
+    analytics::Analytics,
+    commons::{
+        app_error::{error_response, AppError}, authenticated_user::VerifiedUser, client_ip::client_ip, crypto::FieldCipher,
+        distributed_lock::LockManager, etag::json_with_etag,
+        minio_service::MinioService, queue_backpressure::QueueBackpressure, read_replica::ReadReplica, request_limits::LargeJson,
+        tenant::Tenant, upload_policy::UploadPolicyConfig, url_expiry::UrlExpiryConfig,
+    },
+    models::user::ApiResponse,
+    notifier::dispatcher::NotificationDispatcher,
+    policies::{decision::RuleSet, policy_repository::PolicyRepository, resubmission::ResubmissionPolicy},
+    services::{
+        face_match_service::{self, FaceMatchService},
+        metrics_service::MetricsService,
+        webhook_service::WebhookService,
+    },
     submissions::{
+        consent::Consent,
+        device_info::DeviceInfo,
+        document_access_repository::{DocumentAccessPurpose, DocumentAccessRepository, DocumentAccessor},
+        document_scans::DocumentScans,
+        draft::DraftLimits,
+        submission_event_repository::{user_actor, SubmissionEvent, SubmissionEventRepository},
         submission_repository::SubmissionRepository,
+        submission_review_repository::SubmissionReviewRepository,
+        nfc_replay::{NfcIdentifier, NfcReplayGuard},
+        submission_quota::SubmissionQuota,
+        submission_ttl::SubmissionTtlPolicy,
+        submission_documents::DocumentUploadProgress,
+        status_stream::StatusStream,
         submission_service::SubmissionService,
+        submission_token::SubmissionTokens,
     },
+    workers::RedisQueue,
 };
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "client", derive(Serialize))]
 #[serde(rename_all = "camelCase")]
 pub struct PresignedUrlsBody {
     pub submission_type: SubmissionType,
-    pub nfc_identifier: String,
+    /// Bare base64 read or a signed envelope
+    pub nfc_identifier: NfcIdentifier,
+    #[serde(default)]
+    pub device_info: Option<DeviceInfo>,
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "client", derive(Serialize))]
 #[serde(rename_all = "camelCase")]
 pub struct FaceMatchBody {
-    pub image1_url: String,
-    pub image2_url: String,
+    pub image1_url: Option<String>,
+    pub image2_url: Option<String>,
+    /// Document references from the presigned URLs response, used instead of
+    /// image URLs; the documents must belong to `submission_id`
+    pub image1_reference: Option<String>,
+    pub image2_reference: Option<String>,
     pub submission_id: String,
+    /// Call the provider even when this image pair was compared before
+    #[serde(default)]
+    pub bypass_cache: bool,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProcessSubmissionBody {
     pub submission_id: String,
+    /// Consent the user gave before submitting, stored for compliance
+    #[serde(default)]
+    pub consent: Option<Consent>,
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "client", derive(Serialize))]
 #[serde(rename_all = "camelCase")]
 pub struct GetSubmissionStatusQuery {
     pub submission_type: String,
     pub nfc_identifier: String,
 }
 
+/// Upload limits for documents proxied through the API
+#[derive(Debug, Clone)]
+pub struct DocumentUploadConfig {
+    pub max_size_in_bytes: u64,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProcessSubmissionResponse {
@@ -47,15 +102,39 @@ pub struct ProcessSubmissionResponse {
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "client", derive(Deserialize))]
 #[serde(rename_all = "camelCase")]
 pub struct GetSubmissionStatusResponse {
     pub submission_status: String,
+    /// Why the submission was decided the way it was; empty when approved
+    /// outright or not decided yet
+    #[serde(default)]
+    pub decision_reasons: Vec<String>,
+    /// Upload worker progress per document type, null until the worker picks
+    /// the document up
+    pub documents: BTreeMap<String, Option<DocumentUploadProgress>>,
+    /// The caller's autosaved draft, only while they own the submission
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draft: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draft_updated_at: Option<DateTime<Utc>>,
+    /// When the user is due to have uploaded everything, while the
+    /// submission is `INITIATED` and its type has a TTL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// When the upload URL of each document not uploaded yet expires, at the
+    /// latest at `expiresAt`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub uploads_expire_at: BTreeMap<String, DateTime<Utc>>,
 }
 
+#[allow(non_camel_case_types)]
 #[derive(Debug, Deserialize, Clone, Serialize)]
 pub enum SubmissionType {
     KYC,
     ON_DEMAND,
+    SELF_ONBOARDING,
+    ACCOUNT_RECOVERY,
 }
 
 impl std::fmt::Display for SubmissionType {
@@ -63,141 +142,226 @@ impl std::fmt::Display for SubmissionType {
         match self {
             SubmissionType::KYC => write!(f, "KYC"),
             SubmissionType::ON_DEMAND => write!(f, "ON_DEMAND"),
+            SubmissionType::SELF_ONBOARDING => write!(f, "SELF_ONBOARDING"),
+            SubmissionType::ACCOUNT_RECOVERY => write!(f, "ACCOUNT_RECOVERY"),
+        }
+    }
+}
+
+impl std::str::FromStr for SubmissionType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "KYC" => Ok(SubmissionType::KYC),
+            "ON_DEMAND" => Ok(SubmissionType::ON_DEMAND),
+            "SELF_ONBOARDING" => Ok(SubmissionType::SELF_ONBOARDING),
+            "ACCOUNT_RECOVERY" => Ok(SubmissionType::ACCOUNT_RECOVERY),
+            _ => Err(()),
         }
     }
 }
 
 #[actix_web::post("/submissions/urls")]
+#[allow(clippy::too_many_arguments)]
 async fn presigned_urls(
     pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
     minio_service: web::Data<MinioService>,
     metrics: web::Data<MetricsService>,
-    body: Result<web::Json<PresignedUrlsBody>, actix_web::Error>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    upload_policy: web::Data<UploadPolicyConfig>,
+    quota: web::Data<SubmissionQuota>,
+    backpressure: web::Data<QueueBackpressure>,
+    nfc_replay_guard: web::Data<Option<NfcReplayGuard>>,
+    analytics: web::Data<Analytics>,
+    submission_tokens: web::Data<SubmissionTokens>,
+    user: VerifiedUser,
+    tenant: Tenant,
+    body: Result<LargeJson<PresignedUrlsBody>, actix_web::Error>,
 ) -> HttpResponse {
     let body = match body {
         Ok(b) => b,
-        Err(e) => {
-            return HttpResponse::BadRequest().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                errors: Some(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1003".to_string(),
-                    cause: format!("INVALID_REQUEST_BODY: {}", e),
-                }]),
-            });
-        }
+        Err(e) => return e.error_response(),
     };
 
-    // TODO: Get these from auth middleware
     let session_id = Uuid::new_v4().to_string();
-    let user_id = "1".to_string();
+    let user_id = user.user_id.to_string();
 
     let submission_service = SubmissionService::new(
         minio_service.as_ref().clone(),
-        SubmissionRepository::new(pool.as_ref().clone()),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
         metrics.get_ref().clone()
-    );
+    )
+    .with_url_expiry(url_expiry.as_ref().clone())
+    .with_upload_policy(upload_policy.as_ref().clone())
+    .with_quota(quota.as_ref().clone())
+    .with_backpressure(backpressure.as_ref().clone())
+    .with_nfc_replay_guard(nfc_replay_guard.as_ref().clone())
+    .with_analytics(analytics.as_ref().clone());
 
     match submission_service
         .generate_presigned_urls(
             session_id,
             user_id,
+            tenant.id().to_string(),
             body.submission_type.clone(),
             body.nfc_identifier.clone(),
+            body.device_info.clone(),
         )
         .await
     {
-        Ok(response) => HttpResponse::Ok().json(ApiResponse {
-            success: true,
-            data: Some(response),
-            errors: None,
-        }),
-        Err(errors) => HttpResponse::InternalServerError().json(ApiResponse::<()> {
-            success: false,
-            data: None,
-            errors: Some(errors),
-        }),
+        Ok(mut response) => {
+            let token = match submission_tokens.issue(&response.submission_id, user.user_id) {
+                Ok(token) => token,
+                Err(e) => return e.error_response(),
+            };
+            response.submission_token = Some(token.token);
+            response.submission_token_expires_at = Some(token.expires_at);
+
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(response),
+                errors: None,
+            })
+        }
+        Err(e) => e.error_response(),
     }
 }
 
 #[actix_web::post("/submissions/face-match")]
+#[allow(clippy::too_many_arguments)]
 async fn face_match(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
     face_match_service: web::Data<FaceMatchService>,
+    metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    user: VerifiedUser,
     body: Result<web::Json<FaceMatchBody>, actix_web::Error>,
 ) -> HttpResponse {
     let body = match body {
-        Ok(b) => b,
-        Err(e) => {
-            return HttpResponse::BadRequest().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                errors: Some(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1003".to_string(),
-                    cause: format!("INVALID_REQUEST_BODY: {}", e),
-                }]),
-            });
+        Ok(b) => b.into_inner(),
+        Err(e) => return e.error_response(),
+    };
+
+    let face_match_service = if body.bypass_cache {
+        face_match_service.bypassing_cache()
+    } else {
+        face_match_service.as_ref().clone()
+    };
+
+    let result = match body {
+        FaceMatchBody {
+            image1_reference: Some(image1_reference),
+            image2_reference: Some(image2_reference),
+            image1_url: None,
+            image2_url: None,
+            submission_id,
+            ..
+        } => {
+            let submission_service = SubmissionService::new(
+                minio_service.as_ref().clone(),
+                SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+                SubmissionEventRepository::new(pool.as_ref().clone()),
+                SubmissionReviewRepository::new(pool.as_ref().clone()),
+                PolicyRepository::new(pool.as_ref().clone()),
+                metrics.as_ref().clone()
+            )
+            .with_url_expiry(url_expiry.as_ref().clone());
+
+            submission_service
+                .face_match_documents(
+                    submission_id,
+                    user.user_id.to_string(),
+                    image1_reference,
+                    image2_reference,
+                    face_match_service,
+                )
+                .await
+        }
+        FaceMatchBody {
+            image1_url: Some(image1_url),
+            image2_url: Some(image2_url),
+            image1_reference: None,
+            image2_reference: None,
+            submission_id,
+            ..
+        } => face_match_service
+            .compare_faces(image1_url, image2_url, submission_id)
+            .await
+            .map_err(|e| AppError::from_code(face_match_service::error_code(&e), e.to_string())),
+        _ => {
+            return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_REQUEST_BODY: send either both image URLs or both image references".to_string());
         }
     };
 
-    match face_match_service
-        .compare_faces(
-            body.image1_url.clone(),
-            body.image2_url.clone(),
-            body.submission_id.clone(),
-        )
-        .await
-    {
+    match result {
         Ok(response) => HttpResponse::Ok().json(ApiResponse {
             success: true,
             data: Some(response),
             errors: None,
         }),
-        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()> {
-            success: false,
-            data: None,
-            errors: Some(vec![ApiError {
-                entity: "HACKATHON_BI_2025".to_string(),
-                code: "1006".to_string(),
-                cause: e.to_string(),
-            }]),
-        }),
+        Err(e) => e.error_response(),
     }
 }
 
 #[actix_web::put("/submissions/urls")]
+#[allow(clippy::too_many_arguments)]
 async fn process_submission(
+    req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
     minio_service: web::Data<MinioService>,
     face_match_service: web::Data<FaceMatchService>,
     metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    upload_policy: web::Data<UploadPolicyConfig>,
+    lock_manager: web::Data<LockManager>,
+    decision_rules: web::Data<RuleSet>,
+    webhooks: web::Data<WebhookService>,
+    notifications: web::Data<NotificationDispatcher>,
+    analytics: web::Data<Analytics>,
+    submission_tokens: web::Data<SubmissionTokens>,
+    user: VerifiedUser,
     body: Result<web::Json<ProcessSubmissionBody>, actix_web::Error>,
 ) -> HttpResponse {
     let body = match body {
         Ok(b) => b,
-        Err(e) => {
-            return HttpResponse::BadRequest().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                errors: Some(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1003".to_string(),
-                    cause: format!("INVALID_REQUEST_BODY: {}", e),
-                }]),
-            });
-        }
+        Err(e) => return e.error_response(),
+    };
+
+    let next_token = match submission_tokens.redeem(&req, &body.submission_id, user.user_id).await {
+        Ok(token) => token,
+        Err(e) => return e.error_response(),
     };
 
     let submission_service = SubmissionService::new(
         minio_service.as_ref().clone(),
-        SubmissionRepository::new(pool.as_ref().clone()),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
         metrics.as_ref().clone()
-    );
+    )
+    .with_url_expiry(url_expiry.as_ref().clone())
+    .with_upload_policy(upload_policy.as_ref().clone())
+    .with_process_lock(lock_manager.as_ref().clone())
+    .with_decision_rules(decision_rules.as_ref().clone())
+    .with_webhooks(webhooks.as_ref().clone())
+    .with_notifications(notifications.as_ref().clone())
+    .with_analytics(analytics.as_ref().clone())
+    .with_document_scans(DocumentScans::of(&req));
 
-    match submission_service
+    let mut response = match submission_service
         .process_submission(
             body.submission_id.clone(),
+            user_actor(user.user_id),
+            body.consent.clone(),
             face_match_service.as_ref().clone()
         )
         .await
@@ -207,63 +371,427 @@ async fn process_submission(
             data: Some(response),
             errors: None,
         }),
-        Err(errors) => {
-            let status_code = if errors.iter().any(|e| e.code == "1004") {
-                HttpResponse::UnprocessableEntity
-            } else {
-                HttpResponse::InternalServerError
-            };
-            
-            status_code().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                errors: Some(errors),
-            })
-        }
-    }
+        Err(e) => e.error_response(),
+    };
+    SubmissionTokens::attach(&mut response, next_token);
+    response
 }
 
+/// Answered with an ETag, and `304` when `If-None-Match` has it
 #[actix_web::get("/submissions/status")]
+#[allow(clippy::too_many_arguments)]
 async fn get_submission_status(
+    req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
     minio_service: web::Data<MinioService>,
     metrics: web::Data<MetricsService>,
+    read_replica: web::Data<ReadReplica>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    ttl_policy: web::Data<SubmissionTtlPolicy>,
+    user: VerifiedUser,
     query: web::Query<GetSubmissionStatusQuery>,
 ) -> HttpResponse {
 
-    let submission_type = match query.submission_type.as_str() {
-        "KYC" => SubmissionType::KYC,
-        _ => return HttpResponse::BadRequest().json(ApiResponse::<()> {
-            success: false,
-            data: None,
-            errors: Some(vec![ApiError {
-                entity: "HACKATHON_BI_2025".to_string(),
-                code: "1003".to_string(),
-                cause: "INVALID_SUBMISSION_TYPE".to_string(),
-            }]),
-        }),
+    let submission_type = match query.submission_type.parse::<SubmissionType>() {
+        Ok(submission_type) => submission_type,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_SUBMISSION_TYPE".to_string()),
     };
 
     let nfc_identifier = query.nfc_identifier.clone();
 
     let submission_service = SubmissionService::new(
         minio_service.as_ref().clone(),
-        SubmissionRepository::new(pool.as_ref().clone()),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
+        metrics.as_ref().clone()
+    )
+    .with_read_replica(read_replica.as_ref().clone())
+    .with_url_expiry(url_expiry.as_ref().clone())
+    .with_ttl_policy(ttl_policy.as_ref().clone());
+
+    let viewer = user.user_id.to_string();
+    match submission_service.get_submission_status(submission_type, nfc_identifier, Some(&viewer)).await {
+        Ok(response) => json_with_etag(&req, &ApiResponse {
+            success: true,
+            data: Some(response),
+            errors: None,
+        }),
+        Err(e) => e.error_response(),
+    }
+}
+
+/// Server-sent events with the submission's status, pushed as it changes
+/// instead of polled. The stream ends once the submission is approved or
+/// rejected.
+#[actix_web::get("/submissions/{submission_id}/events/stream")]
+async fn stream_submission_status(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    status_stream: web::Data<StatusStream>,
+    user: VerifiedUser,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let Ok(submission_id) = Uuid::parse_str(&path.into_inner()) else {
+        return error_response(StatusCode::NOT_FOUND, "1004", "SUBMISSION_NOT_FOUND".to_string());
+    };
+
+    // Before the current status is read, so no change falls in between
+    let receiver = status_stream.subscribe();
+
+    let current = match SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone())
+        .find_status_change(submission_id)
+        .await
+    {
+        // Someone else's submission looks the same as a missing one
+        Ok(Some((owner_id, current))) if owner_id == user.user_id.to_string() => current,
+        Ok(_) => return error_response(StatusCode::NOT_FOUND, "1004", "SUBMISSION_NOT_FOUND".to_string()),
+        Err(e) => return AppError::from(e).error_response(),
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(StatusStream::events(current, receiver))
+}
+
+#[actix_web::post("/submissions/{submission_id}/documents/{document_type}/refresh-url")]
+#[allow(clippy::too_many_arguments)]
+async fn refresh_upload_url(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    upload_policy: web::Data<UploadPolicyConfig>,
+    submission_tokens: web::Data<SubmissionTokens>,
+    user: VerifiedUser,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let (submission_id, document_type) = path.into_inner();
+
+    let next_token = match submission_tokens.redeem(&req, &submission_id, user.user_id).await {
+        Ok(token) => token,
+        Err(e) => return e.error_response(),
+    };
+
+    let submission_service = SubmissionService::new(
+        minio_service.as_ref().clone(),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
+        metrics.as_ref().clone()
+    )
+    .with_url_expiry(url_expiry.as_ref().clone())
+    .with_upload_policy(upload_policy.as_ref().clone());
+
+    let mut response = match submission_service
+        .refresh_upload_url(submission_id, user.user_id.to_string(), document_type)
+        .await
+    {
+        Ok(mut response) => {
+            if let Some(token) = &next_token {
+                response.submission_token = Some(token.token.clone());
+                response.submission_token_expires_at = Some(token.expires_at);
+            }
+
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(response),
+                errors: None,
+            })
+        }
+        Err(e) => e.error_response(),
+    };
+    SubmissionTokens::attach(&mut response, next_token);
+    response
+}
+
+/// Short-lived download URL of one of the caller's own documents, each one
+/// recorded in the document access log
+#[actix_web::get("/submissions/{submission_id}/documents/{document_type}/download-url")]
+#[allow(clippy::too_many_arguments)]
+async fn document_download_url(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    user: VerifiedUser,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let (submission_id, document_type) = path.into_inner();
+    let ip_address = client_ip(&req);
+
+    let submission_service = SubmissionService::new(
+        minio_service.as_ref().clone(),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
+        metrics.as_ref().clone()
+    )
+    .with_url_expiry(url_expiry.as_ref().clone());
+
+    match submission_service
+        .document_download_url(
+            submission_id,
+            document_type,
+            DocumentAccessor::User(user.user_id.to_string()),
+            DocumentAccessPurpose::SelfService,
+            ip_address,
+            &DocumentAccessRepository::new(pool.as_ref().clone()),
+        )
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(response),
+            errors: None,
+        }),
+        Err(e) => e.error_response(),
+    }
+}
+
+/// Autosave the caller's draft of a submission that hasn't been processed yet,
+/// replacing any earlier one
+#[actix_web::put("/submissions/{submission_id}/draft")]
+#[allow(clippy::too_many_arguments)]
+async fn save_submission_draft(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    draft_limits: web::Data<DraftLimits>,
+    submission_tokens: web::Data<SubmissionTokens>,
+    user: VerifiedUser,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+) -> HttpResponse {
+    let submission_id = path.into_inner();
+
+    let next_token = match submission_tokens.redeem(&req, &submission_id, user.user_id).await {
+        Ok(token) => token,
+        Err(e) => return e.error_response(),
+    };
+
+    let submission_service = SubmissionService::new(
+        minio_service.as_ref().clone(),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
         metrics.as_ref().clone()
     );
 
-    match submission_service.get_submission_status(submission_type, nfc_identifier).await {
+    let mut response = match submission_service
+        .save_draft(submission_id, user.user_id.to_string(), body.into_inner(), draft_limits.as_ref())
+        .await
+    {
         Ok(response) => HttpResponse::Ok().json(ApiResponse {
             success: true,
             data: Some(response),
             errors: None,
         }),
-        Err(errors) => {
-            HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                success: false,
-                data: None,
-                errors: Some(errors),
+        Err(e) => e.error_response(),
+    };
+    SubmissionTokens::attach(&mut response, next_token);
+    response
+}
+
+/// Resubmit a rejected submission, carrying over the documents that weren't
+/// the reason it was rejected
+#[actix_web::post("/submissions/{submission_id}/resubmit")]
+#[allow(clippy::too_many_arguments)]
+async fn resubmit_submission(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    upload_policy: web::Data<UploadPolicyConfig>,
+    resubmission_policy: web::Data<ResubmissionPolicy>,
+    submission_tokens: web::Data<SubmissionTokens>,
+    user: VerifiedUser,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let submission_service = SubmissionService::new(
+        minio_service.as_ref().clone(),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
+        metrics.as_ref().clone()
+    )
+    .with_url_expiry(url_expiry.as_ref().clone())
+    .with_upload_policy(upload_policy.as_ref().clone())
+    .with_resubmission_policy(*resubmission_policy.as_ref());
+
+    match submission_service
+        .resubmit(path.into_inner(), user.user_id.to_string())
+        .await
+    {
+        Ok(mut response) => {
+            let token = match submission_tokens.issue(&response.submission_id, user.user_id) {
+                Ok(token) => token,
+                Err(e) => return e.error_response(),
+            };
+            response.submission_token = Some(token.token);
+            response.submission_token_expires_at = Some(token.expires_at);
+
+            HttpResponse::Created().json(ApiResponse {
+                success: true,
+                data: Some(response),
+                errors: None,
             })
         }
+        Err(e) => e.error_response(),
+    }
+}
+
+#[actix_web::get("/submissions/{submission_id}/report")]
+#[allow(clippy::too_many_arguments)]
+async fn get_submission_report(
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    queue: web::Data<RedisQueue>,
+    user: VerifiedUser,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let submission_service = SubmissionService::new(
+        minio_service.as_ref().clone(),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
+        metrics.as_ref().clone()
+    )
+    .with_url_expiry(url_expiry.as_ref().clone());
+
+    match submission_service
+        .get_report(path.into_inner(), user.user_id.to_string(), queue.as_ref())
+        .await
+    {
+        Ok(response) => {
+            // The report is rendered in the background; poll again for the URL
+            let mut status_code = if response.report_url.is_some() {
+                HttpResponse::Ok()
+            } else {
+                HttpResponse::Accepted()
+            };
+
+            status_code.json(ApiResponse {
+                success: true,
+                data: Some(response),
+                errors: None,
+            })
+        }
+        Err(e) => e.error_response(),
+    }
+}
+
+#[actix_web::post("/submissions/{submission_id}/documents/{document_type}")]
+#[allow(clippy::too_many_arguments)]
+async fn upload_document(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    upload_config: web::Data<DocumentUploadConfig>,
+    submission_tokens: web::Data<SubmissionTokens>,
+    user: VerifiedUser,
+    path: web::Path<(String, String)>,
+    mut payload: Multipart,
+) -> HttpResponse {
+    let (submission_id, document_type) = path.into_inner();
+
+    let next_token = match submission_tokens.redeem(&req, &submission_id, user.user_id).await {
+        Ok(token) => token,
+        Err(e) => return e.error_response(),
+    };
+
+    // The document is the first file part of the form
+    let field = loop {
+        match payload.next().await {
+            Some(Ok(field)) if field.content_disposition().and_then(|cd| cd.get_filename()).is_some() => break Some(field),
+            Some(Ok(_)) => continue,
+            Some(Err(_)) | None => break None,
+        }
+    };
+    let Some(field) = field else {
+        let mut response = error_response(StatusCode::BAD_REQUEST, "1003", "INVALID_REQUEST_BODY: missing file part".to_string());
+        SubmissionTokens::attach(&mut response, next_token);
+        return response;
+    };
+    let content_type = field.content_type().map(|mime| mime.to_string());
+
+    let submission_service = SubmissionService::new(
+        minio_service.as_ref().clone(),
+        SubmissionRepository::new(pool.as_ref().clone(), cipher.as_ref().clone()),
+        SubmissionEventRepository::new(pool.as_ref().clone()),
+        SubmissionReviewRepository::new(pool.as_ref().clone()),
+        PolicyRepository::new(pool.as_ref().clone()),
+        metrics.as_ref().clone()
+    )
+    .with_document_scans(DocumentScans::of(&req));
+
+    let mut response = match submission_service
+        .upload_document(
+            submission_id,
+            user.user_id.to_string(),
+            document_type,
+            field,
+            content_type,
+            upload_config.max_size_in_bytes,
+        )
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            data: Some(response),
+            errors: None,
+        }),
+        Err(e) => e.error_response(),
+    };
+    SubmissionTokens::attach(&mut response, next_token);
+    response
+}
+
+/// Audit trail of a submission for support staff, oldest event first
+#[actix_web::get(
+    "/submissions/{submission_id}/events",
+    wrap = "actix_web::middleware::from_fn(crate::admin::admin_auth::require_admin_key)"
+)]
+async fn get_submission_events(
+    pool: web::Data<sqlx::PgPool>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let submission_id = match Uuid::parse_str(&path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::NOT_FOUND, "1004", "SUBMISSION_NOT_FOUND".to_string()),
+    };
+
+    match SubmissionEventRepository::new(pool.as_ref().clone())
+        .find_by_submission_id(submission_id)
+        .await
+    {
+        Ok(events) if events.is_empty() => error_response(StatusCode::NOT_FOUND, "1004", "SUBMISSION_NOT_FOUND".to_string()),
+        Ok(events) => HttpResponse::Ok().json(ApiResponse::<Vec<SubmissionEvent>> {
+            success: true,
+            data: Some(events),
+            errors: None,
+        }),
+        Err(e) => AppError::from(e).error_response(),
     }
 }
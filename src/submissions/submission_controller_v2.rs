@@ -0,0 +1,271 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::{
+    analytics::Analytics,
+    commons::{
+        authenticated_user::VerifiedUser,
+        crypto::FieldCipher,
+        distributed_lock::LockManager,
+        etag::json_with_etag,
+        minio_service::MinioService,
+        queue_backpressure::QueueBackpressure,
+        read_replica::ReadReplica,
+        problem_details::{body_problem_response, problem_response},
+        request_limits::LargeJson,
+        tenant::Tenant,
+        upload_policy::UploadPolicyConfig,
+        url_expiry::UrlExpiryConfig,
+    },
+    notifier::dispatcher::NotificationDispatcher,
+    policies::{decision::RuleSet, policy_repository::PolicyRepository},
+    services::{face_match_service::FaceMatchService, metrics_service::MetricsService, webhook_service::WebhookService},
+    submissions::{
+        dto::v2::{
+            CreateSubmissionRequest, FaceMatchRequest, FaceMatchResult, ProcessSubmissionRequest, ProcessedSubmission,
+            SubmissionCreated, SubmissionStatus, SubmissionStatusQuery,
+        },
+        document_scans::DocumentScans,
+        submission_event_repository::{user_actor, SubmissionEventRepository},
+        nfc_replay::NfcReplayGuard,
+        submission_quota::SubmissionQuota,
+        submission_ttl::SubmissionTtlPolicy,
+        submission_repository::SubmissionRepository,
+        submission_review_repository::SubmissionReviewRepository,
+        submission_service::SubmissionService,
+        submission_token::SubmissionTokens,
+    },
+};
+
+// v2 shares `SubmissionService` with v1 and only changes the contract around
+// it: resources are returned without the `ApiResponse` envelope and errors
+// are RFC 7807 problems.
+
+fn submission_service(
+    pool: &sqlx::PgPool,
+    cipher: &FieldCipher,
+    minio_service: &MinioService,
+    metrics: &MetricsService,
+) -> SubmissionService {
+    SubmissionService::new(
+        minio_service.clone(),
+        SubmissionRepository::new(pool.clone(), cipher.clone()),
+        SubmissionEventRepository::new(pool.clone()),
+        SubmissionReviewRepository::new(pool.clone()),
+        PolicyRepository::new(pool.clone()),
+        metrics.clone(),
+    )
+}
+
+#[actix_web::post("/submissions")]
+#[allow(clippy::too_many_arguments)]
+async fn create_submission(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    upload_policy: web::Data<UploadPolicyConfig>,
+    quota: web::Data<SubmissionQuota>,
+    backpressure: web::Data<QueueBackpressure>,
+    nfc_replay_guard: web::Data<Option<NfcReplayGuard>>,
+    analytics: web::Data<Analytics>,
+    submission_tokens: web::Data<SubmissionTokens>,
+    user: VerifiedUser,
+    tenant: Tenant,
+    body: Result<LargeJson<CreateSubmissionRequest>, actix_web::Error>,
+) -> HttpResponse {
+    let body = match body {
+        Ok(b) => b.0,
+        Err(e) => return body_problem_response(&req, e),
+    };
+
+    let submission_service =
+        submission_service(&pool, &cipher, &minio_service, &metrics)
+            .with_url_expiry(url_expiry.as_ref().clone())
+            .with_upload_policy(upload_policy.as_ref().clone())
+            .with_quota(quota.as_ref().clone())
+            .with_backpressure(backpressure.as_ref().clone())
+            .with_nfc_replay_guard(nfc_replay_guard.as_ref().clone())
+            .with_analytics(analytics.as_ref().clone());
+
+    match submission_service
+        .generate_presigned_urls(
+            uuid::Uuid::new_v4().to_string(),
+            user.user_id.to_string(),
+            tenant.id().to_string(),
+            body.submission_type,
+            body.nfc_identifier,
+            body.device_info,
+        )
+        .await
+    {
+        Ok(mut response) => match submission_tokens.issue(&response.submission_id, user.user_id) {
+            Ok(token) => {
+                response.submission_token = Some(token.token);
+                response.submission_token_expires_at = Some(token.expires_at);
+                HttpResponse::Created().json(SubmissionCreated::from(response))
+            }
+            Err(e) => problem_response(&req, &e),
+        },
+        Err(e) => problem_response(&req, &e),
+    }
+}
+
+#[actix_web::post("/submissions/{submission_id}/process")]
+#[allow(clippy::too_many_arguments)]
+async fn process_submission(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    face_match_service: web::Data<FaceMatchService>,
+    metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    upload_policy: web::Data<UploadPolicyConfig>,
+    lock_manager: web::Data<LockManager>,
+    decision_rules: web::Data<RuleSet>,
+    webhooks: web::Data<WebhookService>,
+    notifications: web::Data<NotificationDispatcher>,
+    analytics: web::Data<Analytics>,
+    user: VerifiedUser,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> HttpResponse {
+    let submission_id = path.into_inner();
+
+    // Taken from the request because handlers are limited to 16 extractors
+    let submission_tokens = req
+        .app_data::<web::Data<SubmissionTokens>>()
+        .expect("SubmissionTokens is registered with the app");
+    let next_token = match submission_tokens.redeem(&req, &submission_id, user.user_id).await {
+        Ok(token) => token,
+        Err(e) => return problem_response(&req, &e),
+    };
+
+    let body = if body.is_empty() {
+        ProcessSubmissionRequest::default()
+    } else {
+        match serde_json::from_slice::<ProcessSubmissionRequest>(&body) {
+            Ok(body) => body,
+            Err(e) => {
+                let mut response = body_problem_response(&req, actix_web::error::ErrorBadRequest(e));
+                SubmissionTokens::attach(&mut response, next_token);
+                return response;
+            }
+        }
+    };
+
+    let mut response = match submission_service(&pool, &cipher, &minio_service, &metrics)
+        .with_url_expiry(url_expiry.as_ref().clone())
+        .with_upload_policy(upload_policy.as_ref().clone())
+        .with_process_lock(lock_manager.as_ref().clone())
+        .with_decision_rules(decision_rules.as_ref().clone())
+        .with_webhooks(webhooks.as_ref().clone())
+        .with_notifications(notifications.as_ref().clone())
+        .with_analytics(analytics.as_ref().clone())
+        .with_document_scans(DocumentScans::of(&req))
+        .process_submission(
+            submission_id.clone(),
+            user_actor(user.user_id),
+            body.consent,
+            face_match_service.as_ref().clone(),
+        )
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(ProcessedSubmission {
+            submission_id,
+            submission_status: response.submission_status,
+        }),
+        Err(e) => problem_response(&req, &e),
+    };
+    SubmissionTokens::attach(&mut response, next_token);
+    response
+}
+
+#[actix_web::post("/submissions/{submission_id}/face-match")]
+#[allow(clippy::too_many_arguments)]
+async fn face_match(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    face_match_service: web::Data<FaceMatchService>,
+    metrics: web::Data<MetricsService>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    user: VerifiedUser,
+    path: web::Path<String>,
+    body: Result<web::Json<FaceMatchRequest>, actix_web::Error>,
+) -> HttpResponse {
+    let body = match body {
+        Ok(b) => b.into_inner(),
+        Err(e) => return body_problem_response(&req, e),
+    };
+
+    let face_match_service = if body.bypass_cache {
+        face_match_service.bypassing_cache()
+    } else {
+        face_match_service.as_ref().clone()
+    };
+
+    match submission_service(&pool, &cipher, &minio_service, &metrics)
+        .with_url_expiry(url_expiry.as_ref().clone())
+        .face_match_documents(
+            path.into_inner(),
+            user.user_id.to_string(),
+            body.image1_reference,
+            body.image2_reference,
+            face_match_service,
+        )
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(FaceMatchResult {
+            submission_id: response.submission_id,
+            similarity_score: response.similarity_score,
+            raw_score: response.raw_score,
+            provider: response.provider,
+            is_match: response.is_match,
+            threshold: response.threshold,
+        }),
+        Err(e) => problem_response(&req, &e),
+    }
+}
+
+#[actix_web::get("/submissions/status")]
+#[allow(clippy::too_many_arguments)]
+async fn get_submission_status(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    cipher: web::Data<FieldCipher>,
+    minio_service: web::Data<MinioService>,
+    metrics: web::Data<MetricsService>,
+    read_replica: web::Data<ReadReplica>,
+    url_expiry: web::Data<UrlExpiryConfig>,
+    ttl_policy: web::Data<SubmissionTtlPolicy>,
+    user: VerifiedUser,
+    query: Result<web::Query<SubmissionStatusQuery>, actix_web::Error>,
+) -> HttpResponse {
+    let query = match query {
+        Ok(q) => q.into_inner(),
+        Err(e) => return body_problem_response(&req, e),
+    };
+
+    match submission_service(&pool, &cipher, &minio_service, &metrics)
+        .with_read_replica(read_replica.as_ref().clone())
+        .with_url_expiry(url_expiry.as_ref().clone())
+        .with_ttl_policy(ttl_policy.as_ref().clone())
+        .get_submission_status(query.submission_type, query.nfc_identifier, Some(&user.user_id.to_string()))
+        .await
+    {
+        Ok(response) => json_with_etag(&req, &SubmissionStatus {
+            submission_status: response.submission_status,
+            decision_reasons: response.decision_reasons,
+            documents: response.documents,
+            draft: response.draft,
+            draft_updated_at: response.draft_updated_at,
+            expires_at: response.expires_at,
+            uploads_expire_at: response.uploads_expire_at,
+        }),
+        Err(e) => problem_response(&req, &e),
+    }
+}
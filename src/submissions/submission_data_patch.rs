@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::submissions::{
+    dto::presigned_urls_response::SubmissionData,
+    submission_documents::{DocumentType, DocumentUploadProgress, SubmissionDocuments},
+};
+
+/// Changes to some keys of `submission_data`, applied by
+/// `SubmissionRepository::patch_submission_data` in one UPDATE so steps
+/// writing different keys don't overwrite each other. Keys the patch doesn't
+/// name are left as they are in the row.
+#[derive(Debug, Clone, Default)]
+pub struct SubmissionDataPatch {
+    /// Documents written whole
+    documents: Map<String, Value>,
+    /// Fields set on documents the row already has
+    fields: Map<String, Value>,
+}
+
+impl SubmissionDataPatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the whole document, replacing whatever is in its slot
+    pub fn document(mut self, document_type: DocumentType, document: &SubmissionData) -> Self {
+        self.fields.remove(document_type.as_str());
+        self.documents.insert(document_type.to_string(), to_value(document));
+        self
+    }
+
+    /// The upload worker's progress on the document
+    pub fn upload(self, document_type: DocumentType, progress: &DocumentUploadProgress) -> Self {
+        self.field(document_type, "upload", to_value(progress))
+    }
+
+    /// When the latest upload URL handed out for the document expires
+    pub fn upload_url_expires_at(self, document_type: DocumentType, expires_at: DateTime<Utc>) -> Self {
+        self.field(document_type, "uploadUrlExpiresAt", to_value(expires_at))
+    }
+
+    fn field(mut self, document_type: DocumentType, field: &str, value: Value) -> Self {
+        let target = match self.documents.get_mut(document_type.as_str()) {
+            Some(document) => document,
+            None => self
+                .fields
+                .entry(document_type.to_string())
+                .or_insert_with(|| Value::Object(Map::new())),
+        };
+        if let Value::Object(target) = target {
+            target.insert(field.to_string(), value);
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty() && self.fields.is_empty()
+    }
+
+    /// Documents written whole, as a JSONB object to concatenate
+    pub(crate) fn documents(&self) -> Value {
+        Value::Object(self.documents.clone())
+    }
+
+    /// Fields per document, as a JSONB object of objects to merge into them
+    pub(crate) fn fields(&self) -> Value {
+        Value::Object(self.fields.clone())
+    }
+
+    /// Documents the patch sets fields on, which the row must already have
+    pub(crate) fn patched_documents(&self) -> Vec<String> {
+        self.fields.keys().cloned().collect()
+    }
+
+    /// The patch applied to documents read from the row, for rows the UPDATE
+    /// can't patch in place. None when a patched document is missing.
+    pub(crate) fn apply(&self, documents: &SubmissionDocuments) -> serde_json::Result<Option<SubmissionDocuments>> {
+        let mut value = serde_json::to_value(documents)?;
+        let Value::Object(stored) = &mut value else {
+            return Ok(None);
+        };
+
+        for (key, document) in &self.documents {
+            stored.insert(key.clone(), document.clone());
+        }
+        for (key, fields) in &self.fields {
+            let (Some(Value::Object(document)), Value::Object(fields)) = (stored.get_mut(key), fields) else {
+                return Ok(None);
+            };
+            document.extend(fields.clone());
+        }
+
+        serde_json::from_value(value).map(Some)
+    }
+}
+
+fn to_value(value: impl Serialize) -> Value {
+    serde_json::to_value(value).expect("Submission documents serialize to JSON")
+}
@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+use crate::{
+    commons::minio_service::MinioService,
+    submissions::submission_documents::SubmissionDocuments,
+};
+
+static SPILL: OnceLock<SubmissionDataSpill> = OnceLock::new();
+
+/// Where a version of the full `submission_data` of a spilled submission is
+/// kept in MinIO, named by its checksum so a new version never overwrites the
+/// one a committed row points at. It sits under the submission's prefix so
+/// the user purge removes it too.
+pub fn spilled_object_name(tenant_id: &str, submission_id: Uuid, sha256: &str) -> String {
+    format!("{}SUBMISSION_DATA/{}.json", MinioService::submission_prefix(tenant_id, submission_id), sha256)
+}
+
+/// A spilled copy a write replaced. It is only deleted once the write is
+/// committed, so a rolled back write leaves the row's copy in place; copies
+/// that are never deleted are left to the orphan cleanup.
+#[must_use = "delete the replaced copy once the transaction is committed"]
+#[derive(Debug, Default)]
+pub struct ReplacedSpill(Option<String>);
+
+impl ReplacedSpill {
+    /// `previous` unless the row still points at it
+    pub fn of(previous: Option<&SpilledSubmissionData>, current: &SubmissionDocuments) -> Self {
+        let current = current.spilled.as_ref().map(|spilled| spilled.object_name.as_str());
+        Self(previous.map(|spilled| spilled.object_name.clone()).filter(|previous| Some(previous.as_str()) != current))
+    }
+
+    pub async fn delete(self) {
+        let (Some(object_name), Some(spill)) = (self.0, SubmissionDataSpill::installed()) else {
+            return;
+        };
+
+        if let Err(e) = spill.minio_service.delete_file(object_name.clone()).await {
+            log::warn!("Failed to delete replaced submission data {}: {}", object_name, e);
+        }
+    }
+}
+
+/// Pointer left in Postgres to the full `submission_data` in MinIO
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpilledSubmissionData {
+    pub object_name: String,
+    pub size_in_bytes: u64,
+    /// Hex SHA-256 of the object, checked when it is read back
+    pub sha256: String,
+}
+
+/// Moves `submission_data` over `SUBMISSION_DATA_SPILL_THRESHOLD_BYTES` out
+/// of Postgres. The row keeps a summary, the documents without their error
+/// details, next to a pointer to the full data.
+#[derive(Clone)]
+pub struct SubmissionDataSpill {
+    minio_service: MinioService,
+    threshold_bytes: usize,
+}
+
+impl SubmissionDataSpill {
+    pub fn new(minio_service: MinioService, threshold_bytes: usize) -> Self {
+        Self {
+            minio_service,
+            threshold_bytes,
+        }
+    }
+
+    /// Threshold from `SUBMISSION_DATA_SPILL_THRESHOLD_BYTES` (256 KiB by
+    /// default); None when it is 0, which keeps everything in Postgres
+    pub fn from_env(minio_service: MinioService) -> Result<Option<Self>> {
+        let threshold_bytes = std::env::var("SUBMISSION_DATA_SPILL_THRESHOLD_BYTES")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<usize>().context("SUBMISSION_DATA_SPILL_THRESHOLD_BYTES must be a number"))
+            .transpose()?
+            .unwrap_or(256 * 1024);
+
+        Ok((threshold_bytes > 0).then(|| Self::new(minio_service, threshold_bytes)))
+    }
+
+    /// Make this the spill every submission repository of the process uses
+    pub fn install(self) {
+        if SPILL.set(self).is_err() {
+            log::warn!("Submission data spill is already installed");
+        }
+    }
+
+    pub fn installed() -> Option<&'static Self> {
+        SPILL.get()
+    }
+
+    pub fn exceeds_threshold(&self, documents: &SubmissionDocuments) -> Result<bool> {
+        Ok(serde_json::to_vec(documents)?.len() > self.threshold_bytes)
+    }
+
+    /// Store the full documents in MinIO and return what the row keeps
+    pub async fn spill(&self, tenant_id: &str, submission_id: Uuid, documents: &SubmissionDocuments) -> Result<SubmissionDocuments> {
+        let mut full = documents.clone();
+        full.spilled = None;
+        let content = serde_json::to_vec(&full)?;
+
+        let sha256 = hex_sha256(&content);
+        let object_name = spilled_object_name(tenant_id, submission_id, &sha256);
+        let pointer = SpilledSubmissionData {
+            object_name: object_name.clone(),
+            size_in_bytes: content.len() as u64,
+            sha256,
+        };
+        self.minio_service
+            .upload_file(object_name, content, Some("application/json".to_string()))
+            .await
+            .context("Failed to store spilled submission data")?;
+
+        let mut summary = full;
+        for document in [&mut summary.ktp, &mut summary.selfie, &mut summary.nfc].into_iter().flatten() {
+            if let Some(upload) = document.upload.as_mut() {
+                upload.last_error = None;
+            }
+        }
+        summary.spilled = Some(pointer);
+        Ok(summary)
+    }
+
+    /// The full documents behind a summary. Upload progress the worker
+    /// recorded on the summary since the spill wins over the stored copy.
+    pub async fn resolve(&self, summary: SubmissionDocuments) -> Result<SubmissionDocuments> {
+        let Some(pointer) = summary.spilled.as_ref() else {
+            return Ok(summary);
+        };
+
+        let content = self
+            .minio_service
+            .download_file(pointer.object_name.clone())
+            .await
+            .with_context(|| format!("Failed to read spilled submission data {}", pointer.object_name))?;
+        if hex_sha256(&content) != pointer.sha256 {
+            anyhow::bail!("Spilled submission data {} doesn't match its checksum", pointer.object_name);
+        }
+        let mut full: SubmissionDocuments = serde_json::from_slice(&content)?;
+
+        for (document_type, summarized) in summary.iter() {
+            if let (Some(document), Some(upload)) = (full.get_mut(document_type), summarized.upload.as_ref()) {
+                let changed = document.upload.as_ref().map(|stored| stored.updated_at < upload.updated_at).unwrap_or(true);
+                if changed {
+                    document.upload = Some(upload.clone());
+                }
+            }
+        }
+        Ok(full)
+    }
+}
+
+fn hex_sha256(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::submissions::{dto::presigned_urls_response::SubmissionData, submission_data_spill::SpilledSubmissionData};
+
+/// A document slot within a submission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DocumentType {
+    Ktp,
+    Selfie,
+    Nfc,
+}
+
+impl DocumentType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentType::Ktp => "KTP",
+            DocumentType::Selfie => "SELFIE",
+            DocumentType::Nfc => "NFC",
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for DocumentType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "KTP" => Ok(DocumentType::Ktp),
+            "SELFIE" => Ok(DocumentType::Selfie),
+            "NFC" => Ok(DocumentType::Nfc),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Where the upload worker is with a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DocumentUploadStatus {
+    /// Picked up by the worker, or waiting for a retry after a failed attempt
+    Uploading,
+    Uploaded,
+    /// Dead-lettered or quarantined
+    Failed,
+}
+
+/// Worker progress on a document, written as its upload job moves along
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentUploadProgress {
+    pub status: DocumentUploadStatus,
+    /// Error of the latest failed attempt, cleared once the document is uploaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The documents stored in `submissions.submission_data`, keyed by document type
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubmissionDocuments {
+    #[serde(rename = "KTP", default, skip_serializing_if = "Option::is_none")]
+    pub ktp: Option<SubmissionData>,
+    #[serde(rename = "SELFIE", default, skip_serializing_if = "Option::is_none")]
+    pub selfie: Option<SubmissionData>,
+    #[serde(rename = "NFC", default, skip_serializing_if = "Option::is_none")]
+    pub nfc: Option<SubmissionData>,
+    /// Set on a summary whose full data was moved to MinIO; the repository
+    /// resolves it before handing the documents out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spilled: Option<SpilledSubmissionData>,
+}
+
+impl SubmissionDocuments {
+    pub fn get(&self, document_type: DocumentType) -> Option<&SubmissionData> {
+        match document_type {
+            DocumentType::Ktp => self.ktp.as_ref(),
+            DocumentType::Selfie => self.selfie.as_ref(),
+            DocumentType::Nfc => self.nfc.as_ref(),
+        }
+    }
+
+    pub fn get_mut(&mut self, document_type: DocumentType) -> Option<&mut SubmissionData> {
+        match document_type {
+            DocumentType::Ktp => self.ktp.as_mut(),
+            DocumentType::Selfie => self.selfie.as_mut(),
+            DocumentType::Nfc => self.nfc.as_mut(),
+        }
+    }
+
+    /// Stored documents with their type
+    pub fn iter(&self) -> impl Iterator<Item = (DocumentType, &SubmissionData)> {
+        [DocumentType::Ktp, DocumentType::Selfie, DocumentType::Nfc]
+            .into_iter()
+            .filter_map(|document_type| self.get(document_type).map(|document| (document_type, document)))
+    }
+
+    /// Look up a document and its type by the reference handed out with its upload URL
+    pub fn find_by_reference(&self, document_reference: &str) -> Option<(DocumentType, &SubmissionData)> {
+        self.iter()
+            .find(|(_, document)| document.document_reference == document_reference)
+    }
+
+    pub fn insert(&mut self, document_type: DocumentType, document: SubmissionData) {
+        match document_type {
+            DocumentType::Ktp => self.ktp = Some(document),
+            DocumentType::Selfie => self.selfie = Some(document),
+            DocumentType::Nfc => self.nfc = Some(document),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+pub const EVENT_SUBMISSION_CREATED: &str = "SUBMISSION_CREATED";
+pub const EVENT_DOCUMENTS_CONFIRMED: &str = "DOCUMENTS_CONFIRMED";
+pub const EVENT_DOCUMENT_UPLOADED: &str = "DOCUMENT_UPLOADED";
+pub const EVENT_UPLOAD_URL_REFRESHED: &str = "UPLOAD_URL_REFRESHED";
+pub const EVENT_FACE_MATCH_CALLED: &str = "FACE_MATCH_CALLED";
+pub const EVENT_STATUS_CHANGED: &str = "STATUS_CHANGED";
+pub const EVENT_ADMIN_ACTION: &str = "ADMIN_ACTION";
+pub const EVENT_CONSENT_RECORDED: &str = "CONSENT_RECORDED";
+pub const EVENT_SUBMISSION_RESUBMITTED: &str = "SUBMISSION_RESUBMITTED";
+
+pub const ACTOR_ADMIN: &str = "admin";
+
+/// Actor recorded when the document scanner rejects a submission
+pub const ACTOR_SCANNER: &str = "scanner";
+
+/// Actor recorded for uploads detected from MinIO bucket notifications
+pub const ACTOR_STORAGE: &str = "storage";
+
+/// Actor recorded for events caused by an authenticated end user
+pub fn user_actor(user_id: impl std::fmt::Display) -> String {
+    format!("user:{}", user_id)
+}
+
+/// Actor recorded for manual review decisions
+pub fn reviewer_actor(reviewer: &str) -> String {
+    format!("reviewer:{}", reviewer)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionEvent {
+    pub id: i64,
+    pub submission_id: Uuid,
+    pub event_type: String,
+    pub actor: String,
+    pub payload_diff: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An event waiting in the outbox, with the submission it belongs to
+#[derive(Debug)]
+pub struct UnpublishedEvent {
+    pub id: i64,
+    pub submission_id: Uuid,
+    pub event_type: String,
+    pub actor: String,
+    pub payload_diff: Value,
+    pub created_at: DateTime<Utc>,
+    pub tenant_id: Option<String>,
+    pub submission_type: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct SubmissionEventRepository {
+    pool: PgPool,
+}
+
+impl SubmissionEventRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn append(
+        &self,
+        submission_id: Uuid,
+        event_type: &str,
+        actor: &str,
+        payload_diff: Value,
+    ) -> Result<(), sqlx::Error> {
+        Self::insert(&self.pool, submission_id, event_type, actor, payload_diff).await
+    }
+
+    /// Append an event as part of a larger transaction, so it is only kept
+    /// if the change it describes is committed
+    pub async fn append_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: Uuid,
+        event_type: &str,
+        actor: &str,
+        payload_diff: Value,
+    ) -> Result<(), sqlx::Error> {
+        Self::insert(&mut **tx, submission_id, event_type, actor, payload_diff).await
+    }
+
+    async fn insert<'e, E>(
+        executor: E,
+        submission_id: Uuid,
+        event_type: &str,
+        actor: &str,
+        payload_diff: Value,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'e, Database = Postgres>,
+    {
+        sqlx::query!(
+            r#"
+            INSERT INTO submission_events (submission_id, event_type, actor, payload_diff)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            submission_id,
+            event_type,
+            actor,
+            payload_diff
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_by_submission_id(&self, submission_id: Uuid) -> Result<Vec<SubmissionEvent>, sqlx::Error> {
+        sqlx::query_as!(
+            SubmissionEvent,
+            r#"
+            SELECT id, submission_id, event_type, actor, payload_diff, created_at
+            FROM submission_events
+            WHERE submission_id = $1
+            ORDER BY id
+            "#,
+            submission_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Oldest events still in the outbox, in the order they were written
+    pub async fn find_unpublished(&self, limit: i64) -> Result<Vec<UnpublishedEvent>, sqlx::Error> {
+        sqlx::query_as!(
+            UnpublishedEvent,
+            r#"
+            SELECT
+                e.id,
+                e.submission_id,
+                e.event_type,
+                e.actor,
+                e.payload_diff,
+                e.created_at,
+                s.tenant_id AS "tenant_id?",
+                s.submission_type AS "submission_type?"
+            FROM submission_event_outbox o
+            JOIN submission_events e ON e.id = o.event_id
+            LEFT JOIN submissions s ON s.submission_id = e.submission_id
+            ORDER BY o.event_id
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn mark_published(&self, ids: &[i64]) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM submission_event_outbox
+            WHERE event_id = ANY($1)
+            "#,
+            ids
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// How many events wait in the outbox, and when the oldest was written
+    pub async fn outbox_backlog(&self) -> Result<(i64, Option<DateTime<Utc>>), sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!", MIN(created_at) AS oldest
+            FROM submission_event_outbox
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((row.count, row.oldest))
+    }
+}
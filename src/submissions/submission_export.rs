@@ -0,0 +1,405 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    commons::minio_service::MinioService,
+    submissions::submission_repository::{SubmissionExportRecord, SubmissionRepository},
+    workers::RedisQueue,
+};
+
+/// Submissions read per query; each page is written out before the next is read
+pub const EXPORT_PAGE_SIZE: i64 = 500;
+
+/// How long an export run stays readable after its last update
+pub const EXPORT_RUN_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// How often a running export records that it is still alive
+pub const EXPORT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A running export without a heartbeat for this long was interrupted, most
+/// likely by a restart of the instance running it
+pub const EXPORT_STALE_AFTER: Duration = Duration::from_secs(3 * 60);
+
+/// Error recorded on a run that stopped without finishing
+pub const EXPORT_INTERRUPTED_ERROR: &str = "Export was interrupted before it finished";
+
+pub const EXPORT_STATUS_RUNNING: &str = "RUNNING";
+pub const EXPORT_STATUS_COMPLETED: &str = "COMPLETED";
+pub const EXPORT_STATUS_FAILED: &str = "FAILED";
+
+/// Set of the ids of exports that are still running
+pub const EXPORT_RUNNING_KEY: &str = "export_runs:running";
+
+pub fn export_run_key(run_id: Uuid) -> String {
+    format!("export_run:{}", run_id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "ndjson" => Some(Self::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// A column of an export, named as in the NDJSON output and the CSV header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportColumn {
+    SubmissionId,
+    TenantId,
+    UserId,
+    SubmissionType,
+    Status,
+    Result,
+    ReasonCode,
+    FaceMatchScore,
+    FaceMatchRawScore,
+    FaceMatchProvider,
+    DecisionReasons,
+    ResubmissionAttempt,
+    CreatedAt,
+    ProcessingStartedAt,
+    DecidedAt,
+    CompletedAt,
+    /// Seconds from creation to the first decision
+    DecisionSeconds,
+    /// Seconds from creation to the final outcome
+    CompletionSeconds,
+}
+
+impl ExportColumn {
+    /// Every column, in the order of a default export
+    pub const ALL: &'static [ExportColumn] = &[
+        Self::SubmissionId,
+        Self::TenantId,
+        Self::UserId,
+        Self::SubmissionType,
+        Self::Status,
+        Self::Result,
+        Self::ReasonCode,
+        Self::FaceMatchScore,
+        Self::FaceMatchRawScore,
+        Self::FaceMatchProvider,
+        Self::DecisionReasons,
+        Self::ResubmissionAttempt,
+        Self::CreatedAt,
+        Self::ProcessingStartedAt,
+        Self::DecidedAt,
+        Self::CompletedAt,
+        Self::DecisionSeconds,
+        Self::CompletionSeconds,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SubmissionId => "submissionId",
+            Self::TenantId => "tenantId",
+            Self::UserId => "userId",
+            Self::SubmissionType => "submissionType",
+            Self::Status => "status",
+            Self::Result => "result",
+            Self::ReasonCode => "reasonCode",
+            Self::FaceMatchScore => "faceMatchScore",
+            Self::FaceMatchRawScore => "faceMatchRawScore",
+            Self::FaceMatchProvider => "faceMatchProvider",
+            Self::DecisionReasons => "decisionReasons",
+            Self::ResubmissionAttempt => "resubmissionAttempt",
+            Self::CreatedAt => "createdAt",
+            Self::ProcessingStartedAt => "processingStartedAt",
+            Self::DecidedAt => "decidedAt",
+            Self::CompletedAt => "completedAt",
+            Self::DecisionSeconds => "decisionSeconds",
+            Self::CompletionSeconds => "completionSeconds",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|column| column.name().eq_ignore_ascii_case(name))
+    }
+
+    fn value(self, record: &SubmissionExportRecord) -> Value {
+        let since_created = |at: Option<DateTime<Utc>>| {
+            at.map(|at| (at - record.created_at).num_milliseconds() as f64 / 1000.0)
+        };
+
+        match self {
+            Self::SubmissionId => json!(record.submission_id),
+            Self::TenantId => json!(record.tenant_id),
+            Self::UserId => json!(record.user_id),
+            Self::SubmissionType => json!(record.submission_type),
+            Self::Status => json!(record.status),
+            Self::Result => json!(record.result),
+            Self::ReasonCode => json!(record.reason_code),
+            Self::FaceMatchScore => json!(record.face_match_score),
+            Self::FaceMatchRawScore => json!(record.face_match_raw_score),
+            Self::FaceMatchProvider => json!(record.face_match_provider),
+            Self::DecisionReasons => json!(record.decision_reasons),
+            Self::ResubmissionAttempt => json!(record.resubmission_attempt),
+            Self::CreatedAt => json!(record.created_at),
+            Self::ProcessingStartedAt => json!(record.processing_started_at),
+            Self::DecidedAt => json!(record.decided_at),
+            Self::CompletedAt => json!(record.completed_at),
+            Self::DecisionSeconds => json!(since_created(record.decided_at)),
+            Self::CompletionSeconds => json!(since_created(record.completed_at)),
+        }
+    }
+}
+
+/// What to export: submissions created in `[created_from, created_to)`,
+/// optionally with one status, as the given columns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionExport {
+    pub format: ExportFormat,
+    pub columns: Vec<ExportColumn>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub status: Option<String>,
+}
+
+impl SubmissionExport {
+    /// The export's body, a page of submissions per chunk, so it is never
+    /// held in memory whole
+    pub fn stream(self, repository: SubmissionRepository) -> impl Stream<Item = Result<Bytes, sqlx::Error>> {
+        self.pages(repository).map_ok(|(chunk, _)| chunk)
+    }
+
+    /// Chunks of the body with the number of submissions in each
+    fn pages(self, repository: SubmissionRepository) -> impl Stream<Item = Result<(Bytes, usize), sqlx::Error>> {
+        struct Cursor {
+            export: SubmissionExport,
+            repository: SubmissionRepository,
+            after: Option<(DateTime<Utc>, i64)>,
+            first: bool,
+        }
+
+        let cursor = Cursor { export: self, repository, after: None, first: true };
+
+        stream::try_unfold(cursor, |mut cursor| async move {
+            if !cursor.first && cursor.after.is_none() {
+                return Ok(None);
+            }
+
+            let page = cursor
+                .repository
+                .export_page(
+                    cursor.export.created_from,
+                    cursor.export.created_to,
+                    cursor.export.status.as_deref(),
+                    cursor.after,
+                    EXPORT_PAGE_SIZE,
+                )
+                .await?;
+
+            let mut chunk = String::new();
+            if cursor.first && cursor.export.format == ExportFormat::Csv {
+                chunk.push_str(&csv_header(&cursor.export.columns));
+            }
+            for record in &page {
+                chunk.push_str(&cursor.export.render(record));
+            }
+
+            // A short page is the last one
+            let rows = page.len();
+            cursor.first = false;
+            cursor.after = match page.last() {
+                Some(last) if rows as i64 == EXPORT_PAGE_SIZE => Some((last.created_at, last.id)),
+                _ => None,
+            };
+
+            if chunk.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(((Bytes::from(chunk), rows), cursor)))
+        })
+    }
+
+    /// One line of the export
+    fn render(&self, record: &SubmissionExportRecord) -> String {
+        match self.format {
+            ExportFormat::Csv => {
+                let fields: Vec<String> = self
+                    .columns
+                    .iter()
+                    .map(|column| csv_field(&column.value(record)))
+                    .collect();
+                format!("{}\n", fields.join(","))
+            }
+            ExportFormat::Ndjson => {
+                // Built by hand to keep the columns in the requested order
+                let fields: Vec<String> = self
+                    .columns
+                    .iter()
+                    .map(|column| format!("{}:{}", Value::from(column.name()), column.value(record)))
+                    .collect();
+                format!("{{{}}}\n", fields.join(","))
+            }
+        }
+    }
+}
+
+fn csv_header(columns: &[ExportColumn]) -> String {
+    let names: Vec<&str> = columns.iter().map(|column| column.name()).collect();
+    format!("{}\n", names.join(","))
+}
+
+/// A value as a CSV field: nulls empty, lists joined with `;`, quoted when
+/// it holds a separator, quote or line break
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string()))
+            .collect::<Vec<_>>()
+            .join(";"),
+        other => other.to_string(),
+    };
+
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRun {
+    pub id: Uuid,
+    pub status: String,
+    pub export: SubmissionExport,
+    /// Where the finished export is stored in the bucket
+    pub object_key: String,
+    pub rows_exported: u64,
+    pub size_bytes: u64,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    /// Last time the instance running the export reported progress
+    #[serde(default)]
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl ExportRun {
+    pub fn new(export: SubmissionExport) -> Self {
+        let id = Uuid::new_v4();
+        Self {
+            id,
+            status: EXPORT_STATUS_RUNNING.to_string(),
+            object_key: format!("exports/submissions/{}.{}", id, export.format.extension()),
+            export,
+            rows_exported: 0,
+            size_bytes: 0,
+            error: None,
+            started_at: Utc::now(),
+            heartbeat_at: None,
+            finished_at: None,
+        }
+    }
+
+    /// Still running, but nothing has run it for `EXPORT_STALE_AFTER`
+    pub fn is_orphaned(&self, now: DateTime<Utc>) -> bool {
+        let last_seen = self.heartbeat_at.unwrap_or(self.started_at);
+        self.status == EXPORT_STATUS_RUNNING
+            && now.signed_duration_since(last_seen).to_std().is_ok_and(|idle| idle >= EXPORT_STALE_AFTER)
+    }
+
+    /// Record that the run stopped without finishing
+    pub fn mark_interrupted(&mut self) {
+        self.status = EXPORT_STATUS_FAILED.to_string();
+        self.error = Some(EXPORT_INTERRUPTED_ERROR.to_string());
+        self.finished_at = Some(Utc::now());
+    }
+}
+
+/// Stream the export into MinIO and publish the run once it is stored. While
+/// it streams, the run is saved every `EXPORT_HEARTBEAT_INTERVAL` so a run
+/// left behind by a restart can be told apart from a slow one.
+pub async fn run(mut run: ExportRun, repository: SubmissionRepository, minio_service: MinioService, mut queue: RedisQueue) {
+    info!("Export run {} started", run.id);
+
+    let rows_exported = Arc::new(AtomicU64::new(0));
+    let counter = rows_exported.clone();
+    let body = run.export.clone().pages(repository).map_ok(move |(chunk, rows)| {
+        counter.fetch_add(rows as u64, Ordering::Relaxed);
+        chunk
+    });
+    let upload = minio_service.upload_stream(
+        run.object_key.clone(),
+        Box::pin(body),
+        Some(run.export.format.content_type().to_string()),
+        u64::MAX,
+    );
+    tokio::pin!(upload);
+
+    let mut heartbeat = tokio::time::interval(EXPORT_HEARTBEAT_INTERVAL);
+    let result = loop {
+        tokio::select! {
+            result = &mut upload => break result,
+            _ = heartbeat.tick() => {
+                run.heartbeat_at = Some(Utc::now());
+                run.rows_exported = rows_exported.load(Ordering::Relaxed);
+                if let Err(e) = queue.save_export_run(&run).await {
+                    error!("Failed to save heartbeat of export run {}: {}", run.id, e);
+                }
+            }
+        }
+    };
+
+    run.rows_exported = rows_exported.load(Ordering::Relaxed);
+    run.finished_at = Some(Utc::now());
+    match result {
+        Ok(size_bytes) => {
+            run.status = EXPORT_STATUS_COMPLETED.to_string();
+            run.size_bytes = size_bytes;
+            info!("Export run {} stored {} submissions in {}", run.id, run.rows_exported, run.object_key);
+        }
+        Err(e) => {
+            run.status = EXPORT_STATUS_FAILED.to_string();
+            run.error = Some(e.to_string());
+            error!("Export run {} failed: {}", run.id, e);
+        }
+    }
+
+    if let Err(e) = queue.save_export_run(&run).await {
+        error!("Failed to save export run {}: {}", run.id, e);
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use redis::AsyncCommands;
+
+use crate::commons::redis_connection::{RedisConnection, RedisTopology};
+
+/// Counters outlive their day a little so a request at midnight still finds
+/// the key it incremented
+const QUOTA_KEY_TTL_SECONDS: i64 = 26 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuotaCheck {
+    /// The submission was counted; `key` undoes it if creation fails
+    Allowed { key: Option<String> },
+    Exceeded { limit: u64 },
+}
+
+/// Daily limits on how many submissions of a type a user may start, counted
+/// in Redis per UTC day
+#[derive(Clone)]
+pub struct SubmissionQuota {
+    connection_manager: RedisConnection,
+    limits: HashMap<String, u64>,
+    default_limit: Option<u64>,
+}
+
+impl SubmissionQuota {
+    pub fn new(connection_manager: RedisConnection, limits: HashMap<String, u64>, default_limit: Option<u64>) -> Self {
+        Self {
+            connection_manager,
+            limits,
+            default_limit,
+        }
+    }
+
+    /// Limits come from `SUBMISSION_DAILY_QUOTAS` ("KYC=3,ACCOUNT_RECOVERY=5")
+    /// and `SUBMISSION_DAILY_QUOTA_DEFAULT` for the other types; 0 or unset
+    /// means unlimited
+    pub async fn from_env(redis: &RedisTopology) -> anyhow::Result<Self> {
+        let limits = std::env::var("SUBMISSION_DAILY_QUOTAS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (submission_type, limit) = entry.split_once('=').ok_or_else(|| {
+                    anyhow::anyhow!("SUBMISSION_DAILY_QUOTAS entries must look like <submission type>=<limit>, got {}", entry)
+                })?;
+                let limit = limit
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow::anyhow!("SUBMISSION_DAILY_QUOTAS limits must be numbers, got {}", entry))?;
+                Ok((submission_type.trim().to_string(), limit))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let default_limit = std::env::var("SUBMISSION_DAILY_QUOTA_DEFAULT")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("SUBMISSION_DAILY_QUOTA_DEFAULT must be a number"))?;
+
+        let connection_manager = redis.connect().await?;
+
+        Ok(Self::new(connection_manager, limits, default_limit))
+    }
+
+    fn limit_for(&self, submission_type: &str) -> Option<u64> {
+        self.limits
+            .get(submission_type)
+            .copied()
+            .or(self.default_limit)
+            .filter(|limit| *limit > 0)
+    }
+
+    /// Count a new submission against the user's quota for today
+    pub async fn try_consume(&self, user_id: &str, submission_type: &str) -> redis::RedisResult<QuotaCheck> {
+        let Some(limit) = self.limit_for(submission_type) else {
+            return Ok(QuotaCheck::Allowed { key: None });
+        };
+
+        let key = format!(
+            "submission_quota:{}:{}:{}",
+            user_id,
+            submission_type,
+            Utc::now().format("%Y-%m-%d")
+        );
+        let mut conn = self.connection_manager.clone();
+
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire(&key, QUOTA_KEY_TTL_SECONDS)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        if count > limit {
+            // Rejected attempts don't use up the quota
+            conn.decr::<_, _, ()>(&key, 1).await?;
+            return Ok(QuotaCheck::Exceeded { limit });
+        }
+
+        Ok(QuotaCheck::Allowed { key: Some(key) })
+    }
+
+    /// Give back a submission counted by `try_consume` that was never created
+    pub async fn release(&self, key: &str) -> redis::RedisResult<()> {
+        let mut conn = self.connection_manager.clone();
+        conn.decr(key, 1).await
+    }
+}
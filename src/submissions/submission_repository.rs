@@ -1,49 +1,402 @@
-use sqlx::PgPool;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::future::Future;
+use std::time::Duration;
+use futures::{stream::BoxStream, StreamExt};
+use sqlx::{types::Json, PgPool, Postgres, Transaction};
 use uuid::Uuid;
-use serde_json::{Value, json};
+use serde_json::Value;
 
+use crate::{
+    commons::{
+        app_error::AppError,
+        crypto::{FieldCipher, NIK_REQUEST_FIELD, PII_REQUEST_FIELDS},
+        read_replica::ReadReplica,
+    },
+    submissions::{
+        consent::{Consent, CONSENT_REQUEST_FIELD},
+        device_info::{DeviceInfo, DEVICE_INFO_PII_FIELDS},
+        submission_data_patch::SubmissionDataPatch,
+        submission_data_spill::{ReplacedSpill, SpilledSubmissionData, SubmissionDataSpill},
+        status_stream::SubmissionStatusChange,
+        submission_documents::SubmissionDocuments,
+    },
+};
+
+/// What the status endpoint reports about a submission, with its draft
+/// decrypted
+#[derive(Debug, Clone)]
+pub struct SubmissionStatusRecord {
+    pub user_id: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub documents: SubmissionDocuments,
+    pub decision_reasons: Vec<String>,
+    pub draft: Option<Value>,
+    pub draft_updated_at: Option<DateTime<Utc>>,
+}
+
+/// A user's submission with what the status endpoint reports about it
+#[derive(Debug, Clone)]
+pub struct UserSubmissionRecord {
+    pub submission_id: Uuid,
+    pub submission_type: String,
+    pub status: SubmissionStatusRecord,
+}
+
+/// Why a write of `submission_data` failed
+#[derive(Debug, thiserror::Error)]
+pub enum SubmissionWriteError {
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+
+    /// The documents outgrew the spill threshold and couldn't be moved to MinIO
+    #[error("Failed to spill submission data: {0}")]
+    Spill(anyhow::Error),
+}
+
+impl From<SubmissionWriteError> for AppError {
+    fn from(error: SubmissionWriteError) -> Self {
+        match error {
+            SubmissionWriteError::Database(e) => e.into(),
+            e @ SubmissionWriteError::Spill(_) => Self::from_code("1001", e.to_string()),
+        }
+    }
+}
+
+/// What became of a `patch_submission_data` call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchOutcome {
+    /// Applied; the row's `submission_data_version` is now this one
+    Applied(i64),
+    /// The submission, or a document the patch sets fields on, doesn't exist
+    NotFound,
+    /// The row is at this version instead of the expected one
+    VersionConflict(i64),
+}
+
+/// Everything a submission report shows, with personal data decrypted
+#[derive(Debug, Clone)]
+pub struct SubmissionSummary {
+    pub submission_id: Uuid,
+    pub tenant_id: String,
+    pub user_id: String,
+    pub submission_type: String,
+    pub status: String,
+    pub result: Option<String>,
+    pub reason_code: Option<String>,
+    pub face_match_score: Option<f64>,
+    pub request_data: Value,
+    pub documents: SubmissionDocuments,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Submissions created on a day (UTC) with a status and type
+#[derive(Debug, Clone)]
+pub struct DailySubmissionCount {
+    pub day: NaiveDate,
+    pub status: String,
+    pub submission_type: String,
+    pub count: i64,
+}
+
+/// Submissions that reached `APPROVED` or `REJECTED`, and how long it took
+/// from creation on average
+#[derive(Debug, Clone)]
+pub struct DecisionStats {
+    pub decided: i64,
+    pub average_seconds_to_decision: Option<f64>,
+}
+
+/// Submissions with a face match score, and how many were auto-approved on it
+#[derive(Debug, Clone)]
+pub struct FaceMatchStats {
+    pub matched: i64,
+    pub passed: i64,
+}
+
+/// Filters of a back office search; all optional, `created_to` exclusive
+#[derive(Debug, Clone, Default)]
+pub struct SubmissionSearch {
+    pub nik: Option<String>,
+    pub email: Option<String>,
+    pub status: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    /// `(created_at, id)` of the last submission on the previous page
+    pub after: Option<(DateTime<Utc>, i64)>,
+}
+
+/// A submission as exported for reporting: its decision and when it went
+/// through each stage, without personal data
+#[derive(Debug, Clone)]
+pub struct SubmissionExportRecord {
+    pub id: i64,
+    pub submission_id: Uuid,
+    pub tenant_id: String,
+    pub user_id: String,
+    pub submission_type: String,
+    pub status: String,
+    pub result: Option<String>,
+    pub reason_code: Option<String>,
+    pub face_match_score: Option<f64>,
+    pub face_match_raw_score: Option<f64>,
+    pub face_match_provider: Option<String>,
+    pub decision_reasons: Vec<String>,
+    pub resubmission_attempt: i32,
+    pub created_at: DateTime<Utc>,
+    pub processing_started_at: Option<DateTime<Utc>>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// A submission found by a search with its owner, personal data decrypted
+#[derive(Debug, Clone)]
+pub struct SubmissionSearchResult {
+    pub id: i64,
+    pub submission_id: Uuid,
+    pub tenant_id: String,
+    pub user_id: String,
+    pub user_email: Option<String>,
+    pub user_name: Option<String>,
+    pub user_deleted_at: Option<DateTime<Utc>>,
+    pub submission_type: String,
+    pub status: String,
+    pub result: Option<String>,
+    pub reason_code: Option<String>,
+    pub face_match_score: Option<f64>,
+    pub face_match_raw_score: Option<f64>,
+    pub face_match_provider: Option<String>,
+    pub request_data: Value,
+    pub device_info: Option<DeviceInfo>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What the decision engine needs of a submission, with personal data
+/// decrypted
+#[derive(Debug, Clone)]
+pub struct DecisionSignalsRecord {
+    pub request_data: Value,
+    /// Whether another user has an approved submission with the NIK; None
+    /// without a NIK
+    pub duplicate_nik: Option<bool>,
+    pub device_info: Option<DeviceInfo>,
+}
+
+/// A submission the SLA monitor flagged, with the stages it went through
+#[derive(Debug, Clone)]
+pub struct SlaBreach {
+    pub submission_id: Uuid,
+    pub tenant_id: String,
+    pub submission_type: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub processing_started_at: Option<DateTime<Utc>>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+/// A submission someone asked to resubmit, locked until the transaction ends
+#[derive(Debug, Clone)]
+pub struct ResubmissionSource {
+    pub user_id: String,
+    pub tenant_id: String,
+    pub submission_type: String,
+    pub status: String,
+    pub reason_code: Option<String>,
+    pub decision_reasons: Vec<String>,
+    /// 0 for an original submission
+    pub resubmission_attempt: i32,
+    /// Whether it was already resubmitted
+    pub resubmitted: bool,
+    pub documents: SubmissionDocuments,
+}
+
+/// A submission claimed for processing, given back when dropped
+pub struct ProcessingClaim {
+    pool: PgPool,
+    submission_id: Uuid,
+    claim: Uuid,
+}
+
+impl Drop for ProcessingClaim {
+    fn drop(&mut self) {
+        // Best effort, like the Redis lock; an unreleased claim runs out
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            log::warn!("No runtime available to release the processing claim of {}", self.submission_id);
+            return;
+        };
+
+        let pool = self.pool.clone();
+        let (submission_id, claim) = (self.submission_id, self.claim);
+        handle.spawn(async move {
+            let released = sqlx::query!(
+                r#"
+                UPDATE submissions
+                SET processing_claim = NULL, processing_claimed_until = NULL
+                WHERE submission_id = $1 AND processing_claim = $2
+                "#,
+                submission_id,
+                claim
+            )
+            .execute(&pool)
+            .await;
+            if let Err(e) = released {
+                log::warn!("Failed to release the processing claim of {}: {}", submission_id, e);
+            }
+        });
+    }
+}
+
+/// Personal data (the NFC identifier and PII keys of `request_data`) is
+/// encrypted on write and decrypted on read
 pub struct SubmissionRepository {
     pool: PgPool,
+    cipher: FieldCipher,
+    replica: ReadReplica,
 }
 
 impl SubmissionRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, cipher: FieldCipher) -> Self {
+        Self {
+            pool,
+            cipher,
+            replica: ReadReplica::disabled(),
+        }
+    }
+
+    /// Send lookups, the status query, stats and search to the read replica
+    pub fn with_replica(mut self, replica: ReadReplica) -> Self {
+        self.replica = replica;
+        self
+    }
+
+    /// Stored device info with its IP address decrypted
+    fn decrypt_device_info(&self, device_info: Option<Value>) -> Result<Option<DeviceInfo>, sqlx::Error> {
+        device_info
+            .map(|mut device_info| {
+                self.cipher
+                    .decrypt_fields(&mut device_info, DEVICE_INFO_PII_FIELDS)
+                    .map_err(|e| sqlx::Error::Decode(e.into()))?;
+                serde_json::from_value(device_info).map_err(|e| sqlx::Error::Decode(e.into()))
+            })
+            .transpose()
+    }
+
+    /// The full documents of a row whose `submission_data` may be a spilled
+    /// summary. Bulk reads skip this; the summary still lists every document.
+    async fn resolve_documents(&self, documents: SubmissionDocuments) -> Result<SubmissionDocuments, sqlx::Error> {
+        if documents.spilled.is_none() {
+            return Ok(documents);
+        }
+
+        match SubmissionDataSpill::installed() {
+            Some(spill) => spill.resolve(documents).await.map_err(|e| sqlx::Error::Decode(e.into())),
+            None => {
+                log::warn!("Submission data is spilled but no spill is configured, using its summary");
+                Ok(documents)
+            }
+        }
+    }
+
+    /// What `submission_data` is stored as: the documents, or a summary of
+    /// them once they outgrow the spill threshold. `tenant_id` is only
+    /// awaited for documents that get spilled.
+    async fn stored_documents(
+        &self,
+        submission_id: Uuid,
+        documents: &SubmissionDocuments,
+        tenant_id: impl Future<Output = Result<String, sqlx::Error>>,
+    ) -> Result<SubmissionDocuments, SubmissionWriteError> {
+        let spill = match SubmissionDataSpill::installed() {
+            Some(spill) if spill.exceeds_threshold(documents).map_err(SubmissionWriteError::Spill)? => spill,
+            _ => return Ok(documents.clone()),
+        };
+
+        let tenant_id = tenant_id.await?;
+        spill
+            .spill(&tenant_id, submission_id, documents)
+            .await
+            .map_err(SubmissionWriteError::Spill)
+    }
+
+    async fn tenant_of(&self, submission_id: Uuid) -> Result<String, sqlx::Error> {
+        sqlx::query_scalar!("SELECT tenant_id FROM submissions WHERE submission_id = $1", submission_id)
+            .fetch_one(&self.pool)
+            .await
     }
 
+    /// Start a transaction for writes that have to land together
+    pub async fn begin(&self) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        self.pool.begin().await
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn create(
         &self,
         submission_id: Uuid,
+        tenant_id: &str,
         submission_type: &str,
         session_id: &str,
         user_id: &str,
         status: &str,
-        submission_data: Value,
-        request_data: Value,
+        submission_data: &SubmissionDocuments,
+        mut request_data: Value,
         nfc_identifier: String,
-    ) -> Result<(), sqlx::Error> {
+        device_info: Option<&DeviceInfo>,
+    ) -> Result<(), SubmissionWriteError> {
+        let nfc_identifier_hash = self.cipher.blind_index(&nfc_identifier);
+        let nik_hash = request_data
+            .get(NIK_REQUEST_FIELD)
+            .and_then(Value::as_str)
+            .map(|nik| self.cipher.blind_index(nik));
+        let nfc_identifier = self.cipher.encrypt(&nfc_identifier).map_err(|e| sqlx::Error::Configuration(e.into()))?;
+        self.cipher
+            .encrypt_fields(&mut request_data, PII_REQUEST_FIELDS)
+            .map_err(|e| sqlx::Error::Configuration(e.into()))?;
+        let device_info = device_info
+            .map(|device_info| {
+                let mut device_info = serde_json::to_value(device_info).map_err(|e| sqlx::Error::Configuration(e.into()))?;
+                self.cipher
+                    .encrypt_fields(&mut device_info, DEVICE_INFO_PII_FIELDS)
+                    .map_err(|e| sqlx::Error::Configuration(e.into()))?;
+                Ok::<_, sqlx::Error>(device_info)
+            })
+            .transpose()?;
+        let submission_data = self
+            .stored_documents(submission_id, submission_data, async { Ok(tenant_id.to_string()) })
+            .await?;
+
         sqlx::query!(
             r#"
             INSERT INTO submissions (
                 submission_id,
+                tenant_id,
                 submission_type,
                 session_id,
                 user_id,
                 status,
                 submission_data,
                 request_data,
-                nfc_identifier
+                nfc_identifier,
+                nfc_identifier_hash,
+                nik_hash,
+                device_info
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             "#,
             submission_id,
+            tenant_id,
             submission_type,
             session_id,
             user_id,
             status,
-            submission_data as _,
+            Json(&submission_data) as _,
             request_data as _,
-            nfc_identifier
+            nfc_identifier,
+            nfc_identifier_hash,
+            nik_hash,
+            device_info
         )
         .execute(&self.pool)
         .await?;
@@ -51,12 +404,104 @@ impl SubmissionRepository {
         Ok(())
     }
 
-    pub async fn find_submission_by_id(&self, submission_id: &str) -> Result<Option<(String, String, Value)>, sqlx::Error> {
+    pub async fn find_submission_by_id(&self, submission_id: &str) -> Result<Option<(String, String, String, String, SubmissionDocuments)>, sqlx::Error> {
         let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
-        
+
+        let result = self
+            .replica
+            .fetch_optional(&self.pool, |pool| async move {
+                sqlx::query!(
+                    r#"
+                    SELECT submission_data as "submission_data: Json<SubmissionDocuments>", submission_type, nfc_identifier, tenant_id, status
+                    FROM submissions
+                    WHERE submission_id = $1
+                    "#,
+                    submission_uuid
+                )
+                .fetch_optional(&pool)
+                .await
+            })
+            .await?;
+
+        let Some(r) = result else {
+            return Ok(None);
+        };
+
+        let nfc_identifier = self
+            .cipher
+            .decrypt(&r.nfc_identifier.unwrap_or_default())
+            .map_err(|e| sqlx::Error::Decode(e.into()))?;
+        let documents = self.resolve_documents(r.submission_data.0).await?;
+        Ok(Some((r.tenant_id, r.submission_type, nfc_identifier, r.status, documents)))
+    }
+
+    /// Tenant of a submission, `None` when there is no such submission
+    pub async fn find_tenant(&self, submission_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!("SELECT tenant_id FROM submissions WHERE submission_id = $1", submission_id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// User who created the submission
+    pub async fn find_owner(&self, submission_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!("SELECT user_id FROM submissions WHERE submission_id = $1", submission_id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn find_summary(&self, submission_id: Uuid) -> Result<Option<SubmissionSummary>, sqlx::Error> {
         let result = sqlx::query!(
             r#"
-            SELECT submission_data, submission_type, nfc_identifier
+            SELECT submission_id, tenant_id, user_id, submission_type, status, result, reason_code, face_match_score,
+                request_data, submission_data as "submission_data: Json<SubmissionDocuments>", created_at, updated_at
+            FROM submissions
+            WHERE submission_id = $1
+            "#,
+            submission_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(r) = result else {
+            return Ok(None);
+        };
+
+        let mut request_data = r
+            .request_data
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_else(|| Value::Object(Default::default()));
+        self.cipher
+            .decrypt_fields(&mut request_data, PII_REQUEST_FIELDS)
+            .map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+        Ok(Some(SubmissionSummary {
+            submission_id: r.submission_id,
+            tenant_id: r.tenant_id,
+            user_id: r.user_id,
+            submission_type: r.submission_type,
+            status: r.status,
+            result: r.result,
+            reason_code: r.reason_code,
+            face_match_score: r.face_match_score,
+            request_data,
+            documents: self.resolve_documents(r.submission_data.0).await?,
+            created_at: r.created_at,
+            updated_at: r.updated_at,
+        }))
+    }
+
+    /// Owner, tenant, type, status and documents of a submission, used to
+    /// authorize uploads proxied through the API
+    pub async fn find_submission_for_upload(
+        &self,
+        submission_id: &str,
+    ) -> Result<Option<(String, String, String, String, SubmissionDocuments)>, sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+
+        let result = sqlx::query!(
+            r#"
+            SELECT user_id, tenant_id, submission_type, status,
+                   submission_data as "submission_data: Json<SubmissionDocuments>"
             FROM submissions
             WHERE submission_id = $1
             "#,
@@ -65,27 +510,408 @@ impl SubmissionRepository {
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(result.map(|r| {
-            let submission_type = r.submission_type;
-            let nfc_identifier = r.nfc_identifier.unwrap_or_default();
-            let data = r.submission_data
-                .and_then(|s| serde_json::from_str(&s).ok())
-                .unwrap_or(json!({}));
-            (submission_type, nfc_identifier, data)
+        let Some(r) = result else {
+            return Ok(None);
+        };
+        let documents = self.resolve_documents(r.submission_data.0).await?;
+        Ok(Some((r.user_id, r.tenant_id, r.submission_type, r.status, documents)))
+    }
+
+    /// Lock the submission row until the transaction ends and return its status
+    pub async fn lock_submission(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+
+        let result = sqlx::query!(
+            r#"
+            SELECT status
+            FROM submissions
+            WHERE submission_id = $1
+            FOR UPDATE
+            "#,
+            submission_uuid
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(result.map(|r| r.status))
+    }
+
+    /// `find_submission_for_upload` for a change of the submission's
+    /// documents, locking the row until the transaction ends
+    pub async fn lock_submission_for_upload(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: &str,
+    ) -> Result<Option<(String, String, SubmissionDocuments)>, sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+
+        let result = sqlx::query!(
+            r#"
+            SELECT user_id, status, submission_data as "submission_data: Json<SubmissionDocuments>"
+            FROM submissions
+            WHERE submission_id = $1
+            FOR UPDATE
+            "#,
+            submission_uuid
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        let Some(r) = result else {
+            return Ok(None);
+        };
+        let documents = self.resolve_documents(r.submission_data.0).await?;
+        Ok(Some((r.user_id, r.status, documents)))
+    }
+
+    /// What a resubmission copies from a submission, locking its row until
+    /// the transaction ends
+    pub async fn lock_for_resubmission(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: Uuid,
+    ) -> Result<Option<ResubmissionSource>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT s.user_id, s.tenant_id, s.submission_type, s.status, s.reason_code, s.resubmission_attempt,
+                   s.decision_reasons as "decision_reasons: Json<Vec<String>>",
+                   s.submission_data as "submission_data: Json<SubmissionDocuments>",
+                   EXISTS (SELECT 1 FROM submissions c WHERE c.parent_submission_id = s.submission_id) as "resubmitted!"
+            FROM submissions s
+            WHERE s.submission_id = $1
+            FOR UPDATE OF s
+            "#,
+            submission_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        let Some(r) = result else {
+            return Ok(None);
+        };
+        Ok(Some(ResubmissionSource {
+            user_id: r.user_id,
+            tenant_id: r.tenant_id,
+            submission_type: r.submission_type,
+            status: r.status,
+            reason_code: r.reason_code,
+            decision_reasons: r.decision_reasons.0,
+            resubmission_attempt: r.resubmission_attempt,
+            resubmitted: r.resubmitted,
+            documents: self.resolve_documents(r.submission_data.0).await?,
         }))
     }
 
-    pub async fn update_submission_status(&self, submission_id: &str, status: &str) -> Result<(), sqlx::Error> {
+    /// Create the resubmission of `parent_submission_id` as a new INITIATED
+    /// submission of the same owner, tenant, type and NFC identifier. The
+    /// encrypted columns are copied as they are, less the `request_data`
+    /// fields in `dropped_request_fields`; dropping the NIK drops its hash.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_resubmission(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        parent_submission_id: Uuid,
+        submission_id: Uuid,
+        session_id: &str,
+        resubmission_attempt: i32,
+        submission_data: &SubmissionDocuments,
+        dropped_request_fields: &[&str],
+    ) -> Result<(), SubmissionWriteError> {
+        let dropped_request_fields: Vec<String> = dropped_request_fields.iter().map(|field| field.to_string()).collect();
+        let submission_data = self
+            .stored_documents(submission_id, submission_data, self.tenant_of(parent_submission_id))
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO submissions (
+                submission_id,
+                tenant_id,
+                submission_type,
+                session_id,
+                user_id,
+                status,
+                submission_data,
+                request_data,
+                nfc_identifier,
+                nfc_identifier_hash,
+                nik_hash,
+                parent_submission_id,
+                resubmission_attempt,
+                device_info
+            )
+            SELECT $2, tenant_id, submission_type, $3, user_id, 'INITIATED', $4,
+                   (COALESCE(NULLIF(btrim(request_data), '')::jsonb, '{}'::jsonb) - $6::text[])::text,
+                   nfc_identifier, nfc_identifier_hash,
+                   CASE WHEN $7 = ANY($6::text[]) THEN NULL ELSE nik_hash END,
+                   submission_id, $5, device_info
+            FROM submissions
+            WHERE submission_id = $1
+            "#,
+            parent_submission_id,
+            submission_id,
+            session_id,
+            Json(&submission_data) as _,
+            resubmission_attempt,
+            &dropped_request_fields,
+            NIK_REQUEST_FIELD
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replace the submission's documents. The spilled copy they replace is
+    /// handed back to delete once `tx` is committed.
+    pub async fn update_submission_documents(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: &str,
+        submission_data: &SubmissionDocuments,
+    ) -> Result<ReplacedSpill, SubmissionWriteError> {
         let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
-        
+        let submission_data = self
+            .stored_documents(submission_uuid, submission_data, self.tenant_of(submission_uuid))
+            .await?;
+
+        let previous = sqlx::query_scalar!(
+            r#"
+            WITH previous AS (
+                SELECT submission_data -> 'spilled' AS spilled
+                FROM submissions
+                WHERE submission_id = $1
+                FOR UPDATE
+            )
+            UPDATE submissions
+            SET submission_data = $2, submission_data_version = submission_data_version + 1, updated_at = NOW()
+            WHERE submission_id = $1
+            RETURNING (SELECT spilled FROM previous) as "spilled: Json<SpilledSubmissionData>"
+            "#,
+            submission_uuid,
+            Json(&submission_data) as _
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .flatten();
+
+        Ok(ReplacedSpill::of(previous.as_ref().map(|previous| &previous.0), &submission_data))
+    }
+
+    /// Apply `patch` to the submission's `submission_data`, leaving the keys
+    /// it doesn't name as they are. With `expected_version` the patch is only
+    /// applied if nothing wrote `submission_data` since that version was read.
+    pub async fn patch_submission_data(
+        &self,
+        submission_id: Uuid,
+        patch: &SubmissionDataPatch,
+        expected_version: Option<i64>,
+    ) -> Result<PatchOutcome, SubmissionWriteError> {
+        let mut tx = self.pool.begin().await?;
+        let (outcome, replaced) = self.patch_submission_data_in_tx(&mut tx, submission_id, patch, expected_version).await?;
+        tx.commit().await?;
+        replaced.delete().await;
+        Ok(outcome)
+    }
+
+    /// Apply a patch as part of a larger transaction, e.g. one already
+    /// holding the submission's row lock. The spilled copy the patch replaces
+    /// is handed back to delete once `tx` is committed.
+    pub async fn patch_submission_data_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: Uuid,
+        patch: &SubmissionDataPatch,
+        expected_version: Option<i64>,
+    ) -> Result<(PatchOutcome, ReplacedSpill), SubmissionWriteError> {
+        // Documents are written whole with ||, fields are merged into the
+        // stored document, so concurrent patches of other keys survive
+        let applied = sqlx::query_scalar!(
+            r#"
+            UPDATE submissions
+            SET submission_data = submission_data || $2::JSONB || COALESCE(
+                    (SELECT jsonb_object_agg(patch.key, (submission_data -> patch.key) || patch.value)
+                     FROM jsonb_each($3::JSONB) patch),
+                    '{}'::JSONB
+                ),
+                submission_data_version = submission_data_version + 1,
+                updated_at = NOW()
+            WHERE submission_id = $1
+              AND NOT submission_data ? 'spilled'
+              AND submission_data ?& $4::TEXT[]
+              AND ($5::BIGINT IS NULL OR submission_data_version = $5)
+            RETURNING submission_data_version
+            "#,
+            submission_id,
+            patch.documents(),
+            patch.fields(),
+            &patch.patched_documents(),
+            expected_version,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        if let Some(version) = applied {
+            return Ok((PatchOutcome::Applied(version), ReplacedSpill::default()));
+        }
+
+        let row = sqlx::query!(
+            r#"
+            SELECT submission_data as "submission_data: Json<SubmissionDocuments>", submission_data_version, tenant_id
+            FROM submissions
+            WHERE submission_id = $1
+            FOR UPDATE
+            "#,
+            submission_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok((PatchOutcome::NotFound, ReplacedSpill::default()));
+        };
+        if expected_version.is_some_and(|expected| expected != row.submission_data_version) {
+            return Ok((PatchOutcome::VersionConflict(row.submission_data_version), ReplacedSpill::default()));
+        }
+        let Some(previous) = row.submission_data.spilled.clone() else {
+            return Ok((PatchOutcome::NotFound, ReplacedSpill::default()));
+        };
+
+        // A spilled row only has a summary in place; patch the full data and
+        // store it again, under the row lock taken above
+        let documents = self.resolve_documents(row.submission_data.0).await?;
+        let Some(documents) = patch.apply(&documents).map_err(|e| sqlx::Error::Decode(e.into()))? else {
+            return Ok((PatchOutcome::NotFound, ReplacedSpill::default()));
+        };
+        let tenant_id = row.tenant_id;
+        let documents = self.stored_documents(submission_id, &documents, async { Ok(tenant_id) }).await?;
+
+        let version = sqlx::query_scalar!(
+            r#"
+            UPDATE submissions
+            SET submission_data = $2, submission_data_version = submission_data_version + 1, updated_at = NOW()
+            WHERE submission_id = $1
+            RETURNING submission_data_version
+            "#,
+            submission_id,
+            Json(&documents) as _
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok((PatchOutcome::Applied(version), ReplacedSpill::of(Some(&previous), &documents)))
+    }
+
+    /// Version of the submission's `submission_data`, bumped by every write,
+    /// to pass to `patch_submission_data` as the expected version
+    pub async fn submission_data_version(&self, submission_id: Uuid) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar!(
+            "SELECT submission_data_version FROM submissions WHERE submission_id = $1",
+            submission_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Owner and current status of a submission, which its status stream
+    /// starts with. Read from the primary, the stream follows it.
+    pub async fn find_status_change(&self, submission_id: Uuid) -> Result<Option<(String, SubmissionStatusChange)>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT user_id, status, result, reason_code, updated_at
+            FROM submissions
+            WHERE submission_id = $1
+            "#,
+            submission_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result.map(|r| {
+            (
+                r.user_id,
+                SubmissionStatusChange {
+                    submission_id,
+                    status: r.status,
+                    result: r.result,
+                    reason_code: r.reason_code,
+                    updated_at: r.updated_at,
+                },
+            )
+        }))
+    }
+
+    /// Store the user's consent under `consent` in the submission's request
+    /// data, replacing consent given on an earlier attempt
+    pub async fn update_consent(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: Uuid,
+        consent: &Consent,
+    ) -> Result<(), sqlx::Error> {
         sqlx::query!(
             r#"
             UPDATE submissions
-            SET status = $2, updated_at = NOW()
+            SET request_data = (COALESCE(NULLIF(request_data, ''), '{}')::JSONB || jsonb_build_object($2::TEXT, $3::JSONB))::TEXT,
+                updated_at = NOW()
             WHERE submission_id = $1
             "#,
+            submission_id,
+            CONSENT_REQUEST_FIELD,
+            Json(consent) as _
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Claim the submission for processing for up to `ttl`, committed
+    /// straight away; None when another request's claim hasn't run out or
+    /// the submission doesn't exist. Stands in for the Redis lock when Redis
+    /// can't be reached.
+    pub async fn claim_for_processing(&self, submission_id: &str, ttl: Duration) -> Result<Option<ProcessingClaim>, sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+        let claim = Uuid::new_v4();
+
+        let claimed = sqlx::query_scalar!(
+            r#"
+            UPDATE submissions
+            SET processing_claim = $2,
+                processing_claimed_until = NOW() + make_interval(secs => $3)
+            WHERE submission_id = $1
+              AND (processing_claimed_until IS NULL OR processing_claimed_until < NOW())
+            RETURNING submission_id
+            "#,
             submission_uuid,
-            status
+            claim,
+            ttl.as_secs_f64()
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(claimed.map(|_| ProcessingClaim {
+            pool: self.pool.clone(),
+            submission_id: submission_uuid,
+            claim,
+        }))
+    }
+
+    /// Note when the submission was first sent for processing
+    pub async fn mark_processing_started(&self, submission_id: &str) -> Result<(), sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE submissions
+            SET processing_started_at = COALESCE(processing_started_at, NOW())
+            WHERE submission_id = $1
+            "#,
+            submission_uuid
         )
         .execute(&self.pool)
         .await?;
@@ -93,44 +919,753 @@ impl SubmissionRepository {
         Ok(())
     }
 
-    pub async fn find_submission_by_nfc_identifier_and_status(&self, nfc_identifier: &str, status: &str) -> Result<Option<Value>, sqlx::Error> {
+    /// Store the automatic decision with the face match it was based on, as
+    /// `(confidence, raw score, provider)`; `completed` is false when it
+    /// leaves the outcome to a reviewer
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_submission_decision(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: &str,
+        status: &str,
+        result: &str,
+        reason_code: &str,
+        (face_match_score, face_match_raw_score, face_match_provider): (f64, f64, &str),
+        decision_reasons: &[String],
+        completed: bool,
+    ) -> Result<(), sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE submissions
+            SET status = $2, result = $3, reason_code = $4, face_match_score = $5, decision_reasons = $6, updated_at = NOW(),
+                decided_at = COALESCE(decided_at, NOW()),
+                completed_at = CASE WHEN $7 THEN NOW() ELSE completed_at END,
+                face_match_raw_score = $8, face_match_provider = $9
+            WHERE submission_id = $1
+            "#,
+            submission_uuid,
+            status,
+            result,
+            reason_code,
+            face_match_score,
+            Json(decision_reasons) as _,
+            completed,
+            face_match_raw_score,
+            face_match_provider
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store a reviewer's outcome, keeping the face match score of the
+    /// decision that sent the submission to review
+    pub async fn update_submission_outcome(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: Uuid,
+        status: &str,
+        result: &str,
+        reason_code: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE submissions
+            SET status = $2, result = $3, reason_code = $4, updated_at = NOW(),
+                decided_at = COALESCE(decided_at, NOW()),
+                completed_at = NOW()
+            WHERE submission_id = $1
+            "#,
+            submission_id,
+            status,
+            result,
+            reason_code
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_submission_by_nfc_identifier_and_status(&self, nfc_identifier: &str, status: &str) -> Result<Option<SubmissionDocuments>, sqlx::Error> {
         
         let result = sqlx::query!(
             r#"
-            SELECT submission_data
+            SELECT submission_data as "submission_data: Json<SubmissionDocuments>"
             FROM submissions
-            WHERE nfc_identifier = $1 AND status = $2
+            WHERE (nfc_identifier_hash = $1 OR (nfc_identifier_hash IS NULL AND nfc_identifier = $2)) AND status = $3
             order by id desc limit 1
             "#,
+            self.cipher.blind_index(nfc_identifier),
             nfc_identifier,
             status
         )
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(result.map(|r| {
-            let data = r.submission_data
-                .and_then(|s| serde_json::from_str(&s).ok())
-                .unwrap_or(json!({}));
-            data
+        match result {
+            Some(r) => self.resolve_documents(r.submission_data.0).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Status, documents, decision reasons and draft of the latest submission
+    /// of a type for an NFC identifier
+    pub async fn find_submission_by_nfc_identifier_and_submission_type(
+        &self,
+        submission_type: &str,
+        nfc_identifier: &str,
+    ) -> Result<Option<SubmissionStatusRecord>, sqlx::Error> {
+        let nfc_identifier_hash = self.cipher.blind_index(nfc_identifier);
+        let nfc_identifier_hash = nfc_identifier_hash.as_str();
+
+        let result = self
+            .replica
+            .fetch_optional(&self.pool, |pool| async move {
+                sqlx::query!(
+                    r#"
+                    SELECT user_id, status, created_at, submission_data as "submission_data: Json<SubmissionDocuments>",
+                        decision_reasons as "decision_reasons: Json<Vec<String>>",
+                        draft_data, draft_updated_at
+                    FROM submissions
+                    WHERE submission_type = $1
+                        AND (nfc_identifier_hash = $2 OR (nfc_identifier_hash IS NULL AND nfc_identifier = $3))
+                    order by id desc limit 1
+                    "#,
+                    submission_type,
+                    nfc_identifier_hash,
+                    nfc_identifier
+                )
+                .fetch_optional(&pool)
+                .await
+            })
+            .await?;
+
+        let Some(r) = result else {
+            return Ok(None);
+        };
+
+        let draft = match r.draft_data {
+            Some(mut draft) => {
+                self.cipher
+                    .decrypt_fields(&mut draft, PII_REQUEST_FIELDS)
+                    .map_err(|e| sqlx::Error::Decode(e.into()))?;
+                Some(draft)
+            }
+            None => None,
+        };
+
+        Ok(Some(SubmissionStatusRecord {
+            user_id: r.user_id,
+            status: r.status,
+            created_at: r.created_at,
+            documents: self.resolve_documents(r.submission_data.0).await?,
+            decision_reasons: r.decision_reasons.0,
+            draft,
+            draft_updated_at: r.draft_updated_at,
         }))
     }
 
-    pub async fn find_submission_by_nfc_identifier_and_submission_type(&self, submission_type: &str, nfc_identifier: &str) -> Result<Option<String>, sqlx::Error> {
-        
+    /// Every submission of a user, newest first, as the status endpoint
+    /// would read it
+    pub async fn find_status_records_by_user(&self, user_id: &str, limit: i64, offset: i64) -> Result<Vec<UserSubmissionRecord>, sqlx::Error> {
+        let rows = self
+            .replica
+            .fetch(&self.pool, |pool| async move {
+                sqlx::query!(
+                    r#"
+                    SELECT submission_id, submission_type, created_at, status,
+                        submission_data as "submission_data: Json<SubmissionDocuments>",
+                        decision_reasons as "decision_reasons: Json<Vec<String>>",
+                        draft_data, draft_updated_at
+                    FROM submissions
+                    WHERE user_id = $1
+                    ORDER BY id DESC
+                    LIMIT $2 OFFSET $3
+                    "#,
+                    user_id,
+                    limit,
+                    offset
+                )
+                .fetch_all(&pool)
+                .await
+            })
+            .await?;
+
+        let mut records = Vec::with_capacity(rows.len());
+        for r in rows {
+            let draft = match r.draft_data {
+                Some(mut draft) => {
+                    self.cipher
+                        .decrypt_fields(&mut draft, PII_REQUEST_FIELDS)
+                        .map_err(|e| sqlx::Error::Decode(e.into()))?;
+                    Some(draft)
+                }
+                None => None,
+            };
+
+            records.push(UserSubmissionRecord {
+                submission_id: r.submission_id,
+                submission_type: r.submission_type,
+                status: SubmissionStatusRecord {
+                    user_id: user_id.to_string(),
+                    status: r.status,
+                    created_at: r.created_at,
+                    documents: self.resolve_documents(r.submission_data.0).await?,
+                    decision_reasons: r.decision_reasons.0,
+                    draft,
+                    draft_updated_at: r.draft_updated_at,
+                },
+            });
+        }
+        Ok(records)
+    }
+
+    /// Replace the draft of a submission that hasn't been processed yet,
+    /// returning when it was saved; None once processing has started
+    pub async fn save_draft(&self, submission_id: &str, mut draft: Value) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+        self.cipher
+            .encrypt_fields(&mut draft, PII_REQUEST_FIELDS)
+            .map_err(|e| sqlx::Error::Configuration(e.into()))?;
+
         let result = sqlx::query!(
             r#"
-            SELECT status
-            FROM submissions
-            WHERE submission_type = $1 AND nfc_identifier = $2
-            order by id desc limit 1
+            UPDATE submissions
+            SET draft_data = $2, draft_updated_at = NOW()
+            WHERE submission_id = $1 AND status = 'INITIATED'
+            RETURNING draft_updated_at as "draft_updated_at!"
             "#,
-            submission_type,
-            nfc_identifier
+            submission_uuid,
+            draft
         )
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(result.map(|r| r.status))
+        Ok(result.map(|r| r.draft_updated_at))
+    }
+
+    /// What the decision engine evaluates a submission's rules against
+    pub async fn find_decision_signals(&self, submission_id: &str) -> Result<DecisionSignalsRecord, sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+
+        let row = sqlx::query!(
+            r#"SELECT user_id, request_data, device_info FROM submissions WHERE submission_id = $1"#,
+            submission_uuid
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let mut request_data = row
+            .request_data
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_else(|| Value::Object(Default::default()));
+        self.cipher
+            .decrypt_fields(&mut request_data, PII_REQUEST_FIELDS)
+            .map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+        let device_info = self.decrypt_device_info(row.device_info)?;
+
+        let Some(nik) = request_data.get(NIK_REQUEST_FIELD).and_then(Value::as_str).filter(|nik| !nik.is_empty()) else {
+            return Ok(DecisionSignalsRecord {
+                request_data,
+                duplicate_nik: None,
+                device_info,
+            });
+        };
+
+        let duplicate = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM submissions
+                WHERE nik_hash = $1 AND status = 'APPROVED' AND user_id <> $2 AND submission_id <> $3
+            ) AS "duplicate!"
+            "#,
+            self.cipher.blind_index(nik),
+            row.user_id,
+            submission_uuid
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(DecisionSignalsRecord {
+            request_data,
+            duplicate_nik: Some(duplicate),
+            device_info,
+        })
+    }
+
+    /// Fill in `nik_hash` for submissions written before it existed, in
+    /// batches of `batch_size` in id order. Returns how many were filled in.
+    pub async fn backfill_nik_hashes(&self, batch_size: i64) -> Result<u64, sqlx::Error> {
+        let mut after_id = 0;
+        let mut filled = 0;
+
+        loop {
+            let rows = sqlx::query!(
+                r#"
+                SELECT id, request_data AS "request_data!"
+                FROM submissions
+                WHERE nik_hash IS NULL AND request_data IS NOT NULL AND id > $1
+                ORDER BY id
+                LIMIT $2
+                "#,
+                after_id,
+                batch_size
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            let Some(last) = rows.last() else {
+                return Ok(filled);
+            };
+            after_id = last.id;
+
+            let mut ids = Vec::with_capacity(rows.len());
+            let mut hashes = Vec::with_capacity(rows.len());
+            for row in rows {
+                // Submissions without a NIK keep a NULL hash
+                let Some(nik) = serde_json::from_str::<Value>(&row.request_data)
+                    .ok()
+                    .and_then(|request_data| request_data.get(NIK_REQUEST_FIELD)?.as_str().map(str::to_string))
+                else {
+                    continue;
+                };
+                match self.cipher.decrypt(&nik) {
+                    Ok(nik) => {
+                        ids.push(row.id);
+                        hashes.push(self.cipher.blind_index(&nik));
+                    }
+                    Err(e) => log::warn!("Skipping the NIK hash of submission {}: {}", row.id, e),
+                }
+            }
+
+            let result = sqlx::query!(
+                r#"
+                UPDATE submissions s
+                SET nik_hash = v.nik_hash
+                FROM UNNEST($1::BIGINT[], $2::TEXT[]) AS v(id, nik_hash)
+                WHERE s.id = v.id AND s.nik_hash IS NULL
+                "#,
+                &ids,
+                &hashes
+            )
+            .execute(&self.pool)
+            .await?;
+            filled += result.rows_affected();
+        }
+    }
+
+    /// Submissions matching a backfill filter, oldest first, as
+    /// `(submission_id, tenant_id, documents)`
+    pub fn stream_for_backfill(
+        &self,
+        created_from: Option<DateTime<Utc>>,
+        created_to: Option<DateTime<Utc>>,
+        status: Option<String>,
+    ) -> BoxStream<'_, Result<(Uuid, String, SubmissionDocuments), sqlx::Error>> {
+        sqlx::query!(
+            r#"
+            SELECT submission_id, tenant_id, submission_data as "submission_data: Json<SubmissionDocuments>"
+            FROM submissions
+            WHERE ($1::timestamptz IS NULL OR created_at >= $1)
+                AND ($2::timestamptz IS NULL OR created_at < $2)
+                AND ($3::text IS NULL OR status = $3)
+            ORDER BY id
+            "#,
+            created_from,
+            created_to,
+            status
+        )
+        .fetch(&self.pool)
+        .map(|row| row.map(|r| (r.submission_id, r.tenant_id, r.submission_data.0)))
+        .boxed()
+    }
+
+    /// Submissions created in `[from, to)` counted per day, status and type
+    pub async fn count_by_day(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<DailySubmissionCount>, sqlx::Error> {
+        let rows = self
+            .replica
+            .fetch(&self.pool, |pool| async move {
+                sqlx::query!(
+                    r#"
+                    SELECT (created_at AT TIME ZONE 'UTC')::date AS "day!", status, submission_type, COUNT(*) AS "count!"
+                    FROM submissions
+                    WHERE created_at >= $1 AND created_at < $2
+                    GROUP BY 1, 2, 3
+                    ORDER BY 1, 2, 3
+                    "#,
+                    from,
+                    to
+                )
+                .fetch_all(&pool)
+                .await
+            })
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| DailySubmissionCount {
+                day: r.day,
+                status: r.status,
+                submission_type: r.submission_type,
+                count: r.count,
+            })
+            .collect())
+    }
+
+    /// Time to decision of submissions created in `[from, to)`, measured to
+    /// the first status change to `APPROVED` or `REJECTED` in the audit trail
+    pub async fn decision_stats(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<DecisionStats, sqlx::Error> {
+        let row = self
+            .replica
+            .fetch(&self.pool, |pool| async move {
+                sqlx::query!(
+                    r#"
+                    SELECT COUNT(*) AS "decided!",
+                           AVG(EXTRACT(EPOCH FROM decision.decided_at - s.created_at))::DOUBLE PRECISION AS average_seconds
+                    FROM submissions s
+                    CROSS JOIN LATERAL (
+                        SELECT MIN(e.created_at) AS decided_at
+                        FROM submission_events e
+                        WHERE e.submission_id = s.submission_id
+                            AND e.event_type = 'STATUS_CHANGED'
+                            AND e.payload_diff->'status'->>'to' IN ('APPROVED', 'REJECTED')
+                    ) decision
+                    WHERE s.created_at >= $1 AND s.created_at < $2
+                        AND decision.decided_at IS NOT NULL
+                    "#,
+                    from,
+                    to
+                )
+                .fetch_one(&pool)
+                .await
+            })
+            .await?;
+
+        Ok(DecisionStats {
+            decided: row.decided,
+            average_seconds_to_decision: row.average_seconds,
+        })
+    }
+
+    /// Face match outcomes of submissions created in `[from, to)`. Only an
+    /// `AUTO_APPROVE` result counts as a pass; reviewed submissions don't.
+    pub async fn face_match_stats(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<FaceMatchStats, sqlx::Error> {
+        let row = self
+            .replica
+            .fetch(&self.pool, |pool| async move {
+                sqlx::query!(
+                    r#"
+                    SELECT COUNT(*) AS "matched!",
+                           COUNT(*) FILTER (WHERE result = 'AUTO_APPROVE') AS "passed!"
+                    FROM submissions
+                    WHERE created_at >= $1 AND created_at < $2
+                        AND face_match_score IS NOT NULL
+                    "#,
+                    from,
+                    to
+                )
+                .fetch_one(&pool)
+                .await
+            })
+            .await?;
+
+        Ok(FaceMatchStats {
+            matched: row.matched,
+            passed: row.passed,
+        })
+    }
+
+    /// Submissions matching a search, newest first, joined with their owner.
+    /// Pages continue after `search.after` on `(created_at, id)`.
+    pub async fn search(&self, search: &SubmissionSearch, limit: i64) -> Result<Vec<SubmissionSearchResult>, sqlx::Error> {
+        let nik_hash = search.nik.as_deref().map(|nik| self.cipher.blind_index(nik));
+        let nik_hash = nik_hash.as_deref();
+        let (after_created_at, after_id) = search.after.unzip();
+
+        let rows = self
+            .replica
+            .fetch(&self.pool, |pool| async move {
+                sqlx::query!(
+                    r#"
+                    SELECT s.id, s.submission_id, s.tenant_id, s.user_id, s.submission_type, s.status, s.result,
+                           s.reason_code, s.face_match_score, s.face_match_raw_score, s.face_match_provider,
+                           s.request_data, s.device_info, s.created_at, s.updated_at,
+                           u.email AS "user_email?", u.name AS "user_name?", u.deleted_at AS "user_deleted_at?"
+                    FROM submissions s
+                    LEFT JOIN users u ON u.id::TEXT = s.user_id
+                    WHERE ($1::TEXT IS NULL OR s.nik_hash = $1)
+                        AND ($2::TEXT IS NULL OR LOWER(u.email) = LOWER($2))
+                        AND ($3::TEXT IS NULL OR s.status = $3)
+                        AND ($4::TIMESTAMPTZ IS NULL OR s.created_at >= $4)
+                        AND ($5::TIMESTAMPTZ IS NULL OR s.created_at < $5)
+                        AND ($6::TIMESTAMPTZ IS NULL OR (s.created_at, s.id) < ($6, $7::BIGINT))
+                    ORDER BY s.created_at DESC, s.id DESC
+                    LIMIT $8
+                    "#,
+                    nik_hash,
+                    search.email,
+                    search.status,
+                    search.created_from,
+                    search.created_to,
+                    after_created_at,
+                    after_id,
+                    limit
+                )
+                .fetch_all(&pool)
+                .await
+            })
+            .await?;
+
+        rows.into_iter()
+            .map(|r| {
+                let mut request_data = r
+                    .request_data
+                    .and_then(|data| serde_json::from_str(&data).ok())
+                    .unwrap_or_else(|| Value::Object(Default::default()));
+                self.cipher
+                    .decrypt_fields(&mut request_data, PII_REQUEST_FIELDS)
+                    .map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+                Ok(SubmissionSearchResult {
+                    id: r.id,
+                    submission_id: r.submission_id,
+                    tenant_id: r.tenant_id,
+                    user_id: r.user_id,
+                    user_email: r.user_email,
+                    user_name: r.user_name,
+                    user_deleted_at: r.user_deleted_at,
+                    submission_type: r.submission_type,
+                    status: r.status,
+                    result: r.result,
+                    reason_code: r.reason_code,
+                    face_match_score: r.face_match_score,
+                    face_match_raw_score: r.face_match_raw_score,
+                    face_match_provider: r.face_match_provider,
+                    request_data,
+                    device_info: self.decrypt_device_info(r.device_info)?,
+                    created_at: r.created_at,
+                    updated_at: r.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    /// A page of the submissions created in `[created_from, created_to)`
+    /// with `status`, oldest first, after the `(created_at, id)` of the last
+    /// one of the previous page
+    pub async fn export_page(
+        &self,
+        created_from: Option<DateTime<Utc>>,
+        created_to: Option<DateTime<Utc>>,
+        status: Option<&str>,
+        after: Option<(DateTime<Utc>, i64)>,
+        limit: i64,
+    ) -> Result<Vec<SubmissionExportRecord>, sqlx::Error> {
+        let (after_created_at, after_id) = after.unzip();
+
+        let rows = self
+            .replica
+            .fetch(&self.pool, |pool| async move {
+                sqlx::query!(
+                    r#"
+                    SELECT id, submission_id, tenant_id, user_id, submission_type, status, result, reason_code,
+                           face_match_score, face_match_raw_score, face_match_provider,
+                           decision_reasons as "decision_reasons: Json<Vec<String>>",
+                           resubmission_attempt, created_at, processing_started_at, decided_at, completed_at
+                    FROM submissions
+                    WHERE ($1::TIMESTAMPTZ IS NULL OR created_at >= $1)
+                        AND ($2::TIMESTAMPTZ IS NULL OR created_at < $2)
+                        AND ($3::TEXT IS NULL OR status = $3)
+                        AND ($4::TIMESTAMPTZ IS NULL OR (created_at, id) > ($4, $5::BIGINT))
+                    ORDER BY created_at, id
+                    LIMIT $6
+                    "#,
+                    created_from,
+                    created_to,
+                    status,
+                    after_created_at,
+                    after_id,
+                    limit
+                )
+                .fetch_all(&pool)
+                .await
+            })
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| SubmissionExportRecord {
+                id: r.id,
+                submission_id: r.submission_id,
+                tenant_id: r.tenant_id,
+                user_id: r.user_id,
+                submission_type: r.submission_type,
+                status: r.status,
+                result: r.result,
+                reason_code: r.reason_code,
+                face_match_score: r.face_match_score,
+                face_match_raw_score: r.face_match_raw_score,
+                face_match_provider: r.face_match_provider,
+                decision_reasons: r.decision_reasons.0,
+                resubmission_attempt: r.resubmission_attempt,
+                created_at: r.created_at,
+                processing_started_at: r.processing_started_at,
+                decided_at: r.decided_at,
+                completed_at: r.completed_at,
+            })
+            .collect())
+    }
+
+    /// Tenant and documents of the submissions among `submission_ids` that exist
+    pub async fn find_documents_by_ids(
+        &self,
+        submission_ids: &[Uuid],
+    ) -> Result<std::collections::HashMap<Uuid, (String, SubmissionDocuments)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT submission_id, tenant_id, submission_data as "submission_data: Json<SubmissionDocuments>"
+            FROM submissions
+            WHERE submission_id = ANY($1)
+            "#,
+            submission_ids
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| (r.submission_id, (r.tenant_id, r.submission_data.0)))
+            .collect())
+    }
+
+    /// Id, tenant and documents of every submission a user made
+    pub async fn find_documents_by_user(&self, user_id: &str) -> Result<Vec<(Uuid, String, SubmissionDocuments)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT submission_id, tenant_id, submission_data as "submission_data: Json<SubmissionDocuments>"
+            FROM submissions
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.submission_id, r.tenant_id, r.submission_data.0)).collect())
+    }
+
+    /// Delete a user's submissions with their audit events, reviews and
+    /// failed jobs, returning how many submissions were removed
+    pub async fn delete_by_user(&self, tx: &mut Transaction<'_, Postgres>, user_id: &str) -> Result<u64, sqlx::Error> {
+        // submission_events rejects deletes unless the purge opts in
+        sqlx::query!("SET LOCAL app.purging_user_data = 'on'")
+            .execute(&mut **tx)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM submission_events
+            WHERE submission_id IN (SELECT submission_id FROM submissions WHERE user_id = $1)
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM submission_reviews
+            WHERE submission_id IN (SELECT submission_id FROM submissions WHERE user_id = $1)
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM failed_jobs
+            WHERE esign_id IN (SELECT submission_id::TEXT FROM submissions WHERE user_id = $1)
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM submissions
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Flag up to `limit` submissions created before `cutoff` that still have
+    /// no decision, returning them. Each submission is flagged only once.
+    pub async fn flag_decision_sla_breaches(&self, cutoff: DateTime<Utc>, limit: i64) -> Result<Vec<SlaBreach>, sqlx::Error> {
+        sqlx::query_as!(
+            SlaBreach,
+            r#"
+            UPDATE submissions
+            SET decision_sla_breached_at = NOW()
+            WHERE id IN (
+                SELECT id
+                FROM submissions
+                WHERE decided_at IS NULL
+                  AND decision_sla_breached_at IS NULL
+                  AND created_at < $1
+                ORDER BY created_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING submission_id, tenant_id, submission_type, status, created_at, processing_started_at, decided_at
+            "#,
+            cutoff,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Flag up to `limit` submissions sent to manual review before `cutoff`
+    /// that no reviewer has settled yet, returning them
+    pub async fn flag_review_sla_breaches(&self, cutoff: DateTime<Utc>, limit: i64) -> Result<Vec<SlaBreach>, sqlx::Error> {
+        sqlx::query_as!(
+            SlaBreach,
+            r#"
+            UPDATE submissions
+            SET review_sla_breached_at = NOW()
+            WHERE id IN (
+                SELECT id
+                FROM submissions
+                WHERE status = 'MANUAL_REVIEW'
+                  AND completed_at IS NULL
+                  AND review_sla_breached_at IS NULL
+                  AND decided_at < $1
+                ORDER BY decided_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING submission_id, tenant_id, submission_type, status, created_at, processing_started_at, decided_at
+            "#,
+            cutoff,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
     }
 }
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+pub const REVIEW_STATUS_PENDING: &str = "PENDING";
+pub const REVIEW_STATUS_APPROVED: &str = "APPROVED";
+pub const REVIEW_STATUS_REJECTED: &str = "REJECTED";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionReview {
+    pub id: i64,
+    pub submission_id: Uuid,
+    pub tenant_id: String,
+    pub submission_type: String,
+    pub status: String,
+    pub face_match_score: Option<f64>,
+    pub reason_code: Option<String>,
+    pub reviewer: Option<String>,
+    pub notes: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct SubmissionReviewRepository {
+    pool: PgPool,
+}
+
+impl SubmissionReviewRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn begin(&self) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        self.pool.begin().await
+    }
+
+    /// Queue a submission for review as part of the transaction storing its
+    /// MANUAL_REVIEW decision. A re-processed submission goes back to pending.
+    pub async fn enqueue_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        submission_id: Uuid,
+        face_match_score: f64,
+        reason_code: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO submission_reviews (submission_id, status, face_match_score, reason_code)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (submission_id) DO UPDATE
+            SET status = EXCLUDED.status,
+                face_match_score = EXCLUDED.face_match_score,
+                reason_code = EXCLUDED.reason_code,
+                reviewer = NULL,
+                notes = NULL,
+                reviewed_at = NULL,
+                updated_at = NOW()
+            "#,
+            submission_id,
+            REVIEW_STATUS_PENDING,
+            face_match_score,
+            reason_code
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Reviews oldest first, so the queue is worked in arrival order
+    pub async fn find_all(&self, status: Option<&str>, limit: i64, offset: i64) -> Result<Vec<SubmissionReview>, sqlx::Error> {
+        sqlx::query_as!(
+            SubmissionReview,
+            r#"
+            SELECT
+                r.id,
+                r.submission_id,
+                s.tenant_id,
+                s.submission_type,
+                r.status,
+                r.face_match_score,
+                r.reason_code,
+                r.reviewer,
+                r.notes,
+                r.reviewed_at,
+                r.created_at,
+                r.updated_at
+            FROM submission_reviews r
+            JOIN submissions s ON s.submission_id = r.submission_id
+            WHERE ($1::TEXT IS NULL OR r.status = $1)
+            ORDER BY r.created_at
+            LIMIT $2 OFFSET $3
+            "#,
+            status,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Lock a review until the transaction ends, so two reviewers can't
+    /// decide the same submission
+    pub async fn lock(&self, tx: &mut Transaction<'_, Postgres>, id: i64) -> Result<Option<SubmissionReview>, sqlx::Error> {
+        sqlx::query_as!(
+            SubmissionReview,
+            r#"
+            SELECT
+                r.id,
+                r.submission_id,
+                s.tenant_id,
+                s.submission_type,
+                r.status,
+                r.face_match_score,
+                r.reason_code,
+                r.reviewer,
+                r.notes,
+                r.reviewed_at,
+                r.created_at,
+                r.updated_at
+            FROM submission_reviews r
+            JOIN submissions s ON s.submission_id = r.submission_id
+            WHERE r.id = $1
+            FOR UPDATE OF r
+            "#,
+            id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+    }
+
+    pub async fn record_decision(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        id: i64,
+        status: &str,
+        reviewer: &str,
+        notes: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE submission_reviews
+            SET status = $2, reviewer = $3, notes = $4, reviewed_at = NOW(), updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            status,
+            reviewer,
+            notes
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -1,49 +1,271 @@
+use actix_web::http::StatusCode;
 use std::{collections::HashMap, time::Duration};
 use uuid::Uuid;
 use serde_json::json;
-use base64::{Engine as _, engine::general_purpose::STANDARD};
+use validator::Validate;
 
 use crate::{
-    commons::minio_service::{self, MinioService},
-    models::user::ApiError,
-    services::{face_match_service::FaceMatchService, metrics_service::MetricsService},
+    analytics::{Analytics, FunnelStep},
+    commons::{
+        app_error::AppError,
+        crypto::NIK_REQUEST_FIELD,
+        distributed_lock::{DistributedLock, LockManager},
+        db_pool::SERVICE_BUSY_CODE,
+        minio_service::{MinioService, UploadStreamError},
+        queue_backpressure::QueueBackpressure,
+        read_replica::ReadReplica,
+        upload_policy::UploadPolicyConfig,
+        url_expiry::UrlExpiryConfig,
+    },
+    policies::{
+        decision::{DecisionRule, DecisionSignals, RuleSet, LIVENESS_REQUEST_FIELD},
+        face_match_policy::FaceMatchDecision,
+        identity_document::BIRTH_DATE_REQUEST_FIELD,
+        policy_repository::PolicyRepository,
+        resubmission::{rejected_documents, ResubmissionPolicy},
+        submission_flow::{FaceMatchReference, PipelineStep, SubmissionFlow},
+    },
+    services::{
+        face_match_limiter::RETRY_LATER_CODE,
+        face_match_service::{self, FaceMatchResponse, FaceMatchService},
+        metrics_service::{MetricTags, MetricsService},
+        report_service::report_document_name,
+        scanner_service::quarantined_document_name,
+        webhook_service::{WebhookService, WEBHOOK_EVENT_SUBMISSION_DECIDED},
+    },
     submissions::{
-        dto::presigned_urls_response::{Document, PresignedUrlsResponse, SubmissionData}, 
+        consent::Consent,
+        device_info::DeviceInfo,
+        document_access_repository::{DocumentAccessPurpose, DocumentAccessRepository, DocumentAccessor},
+        document_scans::{DocumentScans, ScanStatus},
+        draft::DraftLimits,
+        nfc_payload,
+        dto::{
+            presigned_urls_response::{Document, PresignedUrlsResponse, ResubmissionResponse, SubmissionData},
+            submission_draft_response::SubmissionDraftResponse,
+            submission_report_response::{SubmissionReportResponse, REPORT_STATUS_GENERATING, REPORT_STATUS_READY},
+            upload_document_response::{DocumentDownloadResponse, RefreshUploadUrlResponse, UploadDocumentResponse},
+            user_submission_view::UserSubmissionView,
+        },
         submission_controller::{GetSubmissionStatusResponse, ProcessSubmissionResponse, SubmissionType}, 
-        submission_repository::SubmissionRepository
+        submission_event_repository::{
+            user_actor, SubmissionEventRepository, EVENT_CONSENT_RECORDED, EVENT_DOCUMENTS_CONFIRMED, EVENT_DOCUMENT_UPLOADED, EVENT_FACE_MATCH_CALLED,
+            EVENT_STATUS_CHANGED, EVENT_SUBMISSION_CREATED, EVENT_SUBMISSION_RESUBMITTED, EVENT_UPLOAD_URL_REFRESHED,
+        },
+        nfc_replay::{NfcIdentifier, NfcRejection, NfcReplayGuard},
+        submission_documents::{DocumentType, SubmissionDocuments},
+        submission_quota::{QuotaCheck, SubmissionQuota},
+        submission_ttl::SubmissionTtlPolicy,
+        submission_data_patch::SubmissionDataPatch,
+        submission_repository::{PatchOutcome, ProcessingClaim, SubmissionRepository, SubmissionStatusRecord},
+        submission_review_repository::SubmissionReviewRepository,
     },
+    notifier::dispatcher::NotificationDispatcher,
+    workers::{queue::EnqueueResult, report_generation::{REPORTABLE_STATUS, REPORT_ENQUEUE_DEDUP_TTL}, FileUploadJob, RedisQueue},
 };
 
 pub struct SubmissionService {
     minio_service: MinioService,
     submission_repository: SubmissionRepository,
+    submission_event_repository: SubmissionEventRepository,
+    submission_review_repository: SubmissionReviewRepository,
+    policy_repository: PolicyRepository,
     metrics: MetricsService,
+    quota: Option<SubmissionQuota>,
+    ttl_policy: SubmissionTtlPolicy,
+    backpressure: Option<QueueBackpressure>,
+    process_lock: Option<LockManager>,
+    url_expiry: UrlExpiryConfig,
+    upload_policy: UploadPolicyConfig,
+    nfc_replay_guard: Option<NfcReplayGuard>,
+    document_scans: Option<DocumentScans>,
+    decision_rules: RuleSet,
+    resubmission_policy: ResubmissionPolicy,
+    webhooks: Option<WebhookService>,
+    notifications: Option<NotificationDispatcher>,
+    analytics: Analytics,
+}
+
+/// How long a process_submission call may hold its submission's lock; longer
+/// than a face match can take so the lock doesn't expire mid-run
+const PROCESS_LOCK_TTL: Duration = Duration::from_secs(120);
+
+/// Whatever keeps a second process request for the same submission out,
+/// released when dropped; empty when no lock manager is configured
+#[derive(Default)]
+struct ProcessLock {
+    _redis: Option<DistributedLock>,
+    _postgres: Option<ProcessingClaim>,
 }
 
 impl SubmissionService {
     pub fn new(
         minio_service: MinioService, 
         submission_repository: SubmissionRepository, 
+        submission_event_repository: SubmissionEventRepository,
+        submission_review_repository: SubmissionReviewRepository,
+        policy_repository: PolicyRepository,
         metrics: MetricsService
     ) -> Self {
         Self {
             minio_service,
             submission_repository,
+            submission_event_repository,
+            submission_review_repository,
+            policy_repository,
             metrics,
+            quota: None,
+            ttl_policy: SubmissionTtlPolicy::default(),
+            backpressure: None,
+            process_lock: None,
+            url_expiry: UrlExpiryConfig::default(),
+            upload_policy: UploadPolicyConfig::default(),
+            nfc_replay_guard: None,
+            document_scans: None,
+            decision_rules: RuleSet::default(),
+            resubmission_policy: ResubmissionPolicy::default(),
+            webhooks: None,
+            notifications: None,
+            analytics: Analytics::disabled(),
         }
     }
 
+    /// Enforce daily submission quotas in `generate_presigned_urls`
+    pub fn with_quota(mut self, quota: SubmissionQuota) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Deadlines reported by `get_submission_status`, instead of none
+    pub fn with_ttl_policy(mut self, ttl_policy: SubmissionTtlPolicy) -> Self {
+        self.ttl_policy = ttl_policy;
+        self
+    }
+
+    /// Hold or turn away new submissions in `generate_presigned_urls` while
+    /// the upload queue is deep
+    pub fn with_backpressure(mut self, backpressure: QueueBackpressure) -> Self {
+        self.backpressure = Some(backpressure);
+        self
+    }
+
+    /// Read submissions from the replica where the repository allows it
+    pub fn with_read_replica(mut self, replica: ReadReplica) -> Self {
+        self.submission_repository = self.submission_repository.with_replica(replica);
+        self
+    }
+
+    /// Lifetimes of the presigned URLs handed out, instead of the defaults
+    pub fn with_url_expiry(mut self, url_expiry: UrlExpiryConfig) -> Self {
+        self.url_expiry = url_expiry;
+        self
+    }
+
+    /// Content types and sizes presigned uploads accept, instead of the defaults
+    pub fn with_upload_policy(mut self, upload_policy: UploadPolicyConfig) -> Self {
+        self.upload_policy = upload_policy;
+        self
+    }
+
+    /// Verify signed NFC reads in `generate_presigned_urls`, rejecting stale
+    /// and replayed ones
+    pub fn with_nfc_replay_guard(mut self, guard: Option<NfcReplayGuard>) -> Self {
+        self.nfc_replay_guard = guard;
+        self
+    }
+
+    /// Scan uploaded documents, and process only documents the scanner passed
+    pub fn with_document_scans(mut self, document_scans: Option<DocumentScans>) -> Self {
+        self.document_scans = document_scans;
+        self
+    }
+
+    /// Rules applied where neither the tenant nor the default tenant has any
+    /// in `decision_rules`
+    pub fn with_decision_rules(mut self, decision_rules: RuleSet) -> Self {
+        self.decision_rules = decision_rules;
+        self
+    }
+
+    /// Resubmission cap applied where neither the tenant nor the default
+    /// tenant has a row in `resubmission_policies`
+    pub fn with_resubmission_policy(mut self, resubmission_policy: ResubmissionPolicy) -> Self {
+        self.resubmission_policy = resubmission_policy;
+        self
+    }
+
+    /// Send a `submission.decided` webhook for every decision
+    pub fn with_webhooks(mut self, webhooks: WebhookService) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Notify the submission's owner when it is approved or rejected
+    pub fn with_notifications(mut self, notifications: NotificationDispatcher) -> Self {
+        self.notifications = Some(notifications);
+        self
+    }
+
+    /// Emit funnel analytics events as submissions move along
+    pub fn with_analytics(mut self, analytics: Analytics) -> Self {
+        self.analytics = analytics;
+        self
+    }
+
+    /// Reject concurrent `process_submission` calls for the same submission
+    pub fn with_process_lock(mut self, lock_manager: LockManager) -> Self {
+        self.process_lock = Some(lock_manager);
+        self
+    }
+
     pub async fn generate_presigned_urls(
         &self,
         session_id: String,
         user_id: String,
+        tenant_id: String,
         submission_type: SubmissionType,
-        nfc_identifier: String,
-    ) -> Result<PresignedUrlsResponse, Vec<ApiError>> {
+        nfc_identifier: NfcIdentifier,
+        device_info: Option<DeviceInfo>,
+    ) -> Result<PresignedUrlsResponse, AppError> {
         let start = std::time::Instant::now();
-        let mut tags = HashMap::new();
-        tags.insert("endpoint".to_string(), "presigned_urls".to_string());
-        tags.insert("submission_type".to_string(), submission_type.to_string());
+        let tags = MetricTags::endpoint("presigned_urls")
+            .tenant(&tenant_id)
+            .submission_type(&submission_type);
+
+        if let Some(Err(e)) = device_info.as_ref().map(|device_info| device_info.validate()) {
+            self.metrics.increment("api_error", Some(tags.clone().outcome("error")));
+            return Err(AppError::from_code("1003", format!("INVALID_DEVICE_INFO: {}", e)));
+        }
+
+        let flow = SubmissionFlow::for_type(&submission_type);
+
+        // Before anything is verified or counted, so a rejected request can
+        // simply be retried
+        if let Some(backpressure) = &self.backpressure {
+            if backpressure.admit(&tenant_id, &self.metrics).await.is_err() {
+                return Err(AppError::from_code(SERVICE_BUSY_CODE, "SERVICE_BUSY"));
+            }
+        }
+
+        // Before the quota, so replayed reads don't use it up
+        let nfc_identifier = match self.verify_nfc(&nfc_identifier, &tags).await {
+            Ok(payload) => payload.to_string(),
+            Err(errors) => return Err(errors),
+        };
+
+        // Oversized reads are turned away before anything is decoded or counted
+        let nfc_image = nfc_payload::encoded_image(&nfc_identifier);
+        let nfc_policy = self.upload_policy.for_document(DocumentType::Nfc);
+        if nfc_payload::decoded_len(nfc_image) > nfc_policy.max_size_in_bytes {
+            self.metrics.increment("api_error", Some(tags.clone().outcome("error")));
+            return Err(AppError::from_code("1010", UploadStreamError::TooLarge { limit: nfc_policy.max_size_in_bytes }.to_string()));
+        }
+
+        // Count the submission against the user's daily quota
+        let quota_key = match self.consume_quota(&user_id, &submission_type, &tags).await {
+            Ok(key) => key,
+            Err(errors) => return Err(errors),
+        };
 
         // Generate a new submission ID
         let submission_id = Uuid::new_v4();
@@ -51,87 +273,82 @@ impl SubmissionService {
         // Generate document references and presigned URLs
         let mut documents = HashMap::new();
 
-        let mut documents_data = HashMap::new();
+        let mut documents_data = SubmissionDocuments::default();
 
-        // KYC document
-        if submission_type.to_string() == "KYC" {
-            let ktp_uuid = Uuid::new_v4();
-            let ktp_filename = ktp_uuid.to_string() + "_KTP";
-            let ktp_url = match self.minio_service
-                .generate_upload_url(ktp_filename.clone(), Duration::from_secs(600))
+        // Documents the client uploads for this submission type
+        for document_type in flow.upload_documents {
+            let document_uuid = Uuid::new_v4();
+            let document_filename = MinioService::document_key(&tenant_id, submission_id, document_type, document_uuid);
+            let expiry = self.url_expiry.upload_expiry(*document_type);
+            let expires_at = UrlExpiryConfig::expires_at(expiry);
+            let policy = self.upload_policy.for_document(*document_type);
+            let upload = match self.minio_service
+                .generate_upload_url(document_filename.clone(), self.url_expiry.signed_for(expiry), &policy)
                 .await
             {
-                Ok(url) => url,
+                Ok(upload) => upload,
                 Err(e) => {
-                    self.metrics.increment("api_error", Some(tags.clone()));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1001".to_string(),
-                        cause: e.to_string(),
-                    }]);
+                    self.release_quota(quota_key).await;
+                    self.metrics.increment("api_error", Some(tags.clone().outcome("error")));
+                    return Err(AppError::from_code("1001", e.to_string()));
                 }
             };
 
             documents.insert(
-                "KTP".to_string(),
+                document_type.to_string(),
                 Document {
-                    document_url: ktp_url,
-                    document_reference: ktp_uuid.to_string(),
-                    expiry_in_seconds: "600".to_string(),
+                    document_url: upload.url,
+                    document_reference: document_uuid.to_string(),
+                    expiry_in_seconds: expiry.as_secs().to_string(),
+                    expires_at,
+                    content_type: policy.content_type,
+                    max_size_in_bytes: policy.max_size_in_bytes,
+                    upload_form: upload.form,
                 },
             );
 
-            documents_data.insert("KTP", SubmissionData {
-                document_name: ktp_filename.clone(),
-                document_reference: ktp_uuid.to_string(),
+            documents_data.insert(*document_type, SubmissionData {
+                document_name: document_filename,
+                document_reference: document_uuid.to_string(),
+                upload_url_expires_at: Some(expires_at),
+                upload: None,
             });
         }
 
-        // Selfie document
-        let selfie_uuid: Uuid = Uuid::new_v4();
-        let selfie_filename = selfie_uuid.to_string() + "_SELFIE";
-        let selfie_url = match self.minio_service
-            .generate_upload_url(selfie_filename.clone(), Duration::from_secs(600))
+        // NFC document, decoded into the bucket a chunk at a time
+        let nfc_uuid = Uuid::new_v4();
+        let nfc_identifier_filename = MinioService::document_key(&tenant_id, submission_id, DocumentType::Nfc, nfc_uuid);
+        if let Err(e) = self
+            .minio_service
+            .upload_stream(
+                nfc_identifier_filename.clone(),
+                nfc_payload::decode_chunks(nfc_image),
+                Some(nfc_policy.content_type.clone()),
+                nfc_policy.max_size_in_bytes,
+            )
             .await
         {
-            Ok(url) => url,
-            Err(e) => {
-                self.metrics.increment("api_error", Some(tags.clone()));
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1001".to_string(),
-                    cause: e.to_string(),
-                }]);
-            }
-        };
-
-        documents.insert(
-            "SELFIE".to_string(),
-            Document {
-                document_url: selfie_url,
-                document_reference: selfie_uuid.to_string(),
-                expiry_in_seconds: "600".to_string(),
-            },
-        );
-        documents_data.insert("SELFIE", SubmissionData {
-            document_name: selfie_filename.clone(),
-            document_reference: selfie_uuid.to_string()
-        });
-
-        // NFC document
-        let nfc_identifier_clean = nfc_identifier.replace("data:image/jpeg;base64,", "");
-        let nfc_identifier_base64 = STANDARD.decode(&nfc_identifier_clean).unwrap();
-        let nfc_uuid = Uuid::new_v4();
-        let nfc_identifier_filename = nfc_uuid.to_string() + "_NFC";
-        self.minio_service.upload_file(nfc_identifier_filename.clone(), nfc_identifier_base64, Some("image/jpeg".to_string())).await.unwrap();
-        documents_data.insert("NFC", SubmissionData {
+            self.release_quota(quota_key).await;
+            self.metrics.increment("api_error", Some(tags.clone().outcome("error")));
+            let code = match e {
+                UploadStreamError::TooLarge { .. } => "1010",
+                UploadStreamError::Body(_) => "1003",
+                UploadStreamError::Storage(_) => "1001",
+            };
+            return Err(AppError::from_code(code, e.to_string()));
+        }
+        documents_data.insert(DocumentType::Nfc, SubmissionData {
             document_name: nfc_identifier_filename.clone(),
             document_reference: nfc_uuid.to_string(),
+            upload_url_expires_at: None,
+            upload: None,
         });
 
         let response = PresignedUrlsResponse {
             submission_id: submission_id.to_string(),
             documents,
+            submission_token: None,
+            submission_token_expires_at: None,
         };
 
         // Save to database
@@ -139,26 +356,42 @@ impl SubmissionService {
             .submission_repository
             .create(
                 submission_id,
-                &format!("{:?}", submission_type),
+                &tenant_id,
+                &submission_type.to_string(),
                 &session_id,
                 &user_id,
                 "INITIATED",
-                json!(documents_data),
+                &documents_data,
                 json!({}),
-                nfc_identifier_clean.clone().chars().take(500).collect::<String>(),
+                nfc_image.chars().take(500).collect::<String>(),
+                device_info.as_ref(),
             )
             .await
         {
-            self.metrics.increment("api_error", Some(tags.clone()));
-            return Err(vec![ApiError {
-                entity: "HACKATHON_BI_2025".to_string(),
-                code: "1002".to_string(),
-                cause: e.to_string(),
-            }]);
+            self.release_quota(quota_key).await;
+            self.metrics.increment("api_error", Some(tags.clone().outcome("error")));
+            return Err(AppError::from(e));
         }
 
-        self.metrics.increment("api_success", Some(tags.clone()));
-        self.metrics.timing("api_latency", start.elapsed(), Some(tags));
+        self.record_event(
+            &submission_id.to_string(),
+            EVENT_SUBMISSION_CREATED,
+            &user_actor(&user_id),
+            json!({
+                "status": { "from": null, "to": "INITIATED" },
+                "tenantId": tenant_id,
+                "submissionType": submission_type.to_string(),
+            }),
+        ).await;
+
+        self.analytics.emit(
+            self.analytics
+                .event(FunnelStep::UrlsGenerated, &tenant_id, &submission_type, &submission_id.to_string())
+                .property("documentCount", documents_data.iter().count()),
+        );
+
+        self.metrics.increment("api_success", Some(tags.clone().outcome("success")));
+        self.metrics.timing("api_latency", start.elapsed(), Some(tags.outcome("success")));
 
         Ok(response)
     }
@@ -166,331 +399,1371 @@ impl SubmissionService {
     pub async fn process_submission(
         &self,
         submission_id: String,
+        actor: String,
+        consent: Option<Consent>,
         face_match_service: FaceMatchService,
-    ) -> Result<ProcessSubmissionResponse, Vec<ApiError>> {
+    ) -> Result<ProcessSubmissionResponse, AppError> {
         let start = std::time::Instant::now();
-        let mut tags = HashMap::new();
-        tags.insert("endpoint".to_string(), "process_submission".to_string());
+        let mut tags = MetricTags::endpoint("process_submission");
+
+        if let Some(Err(e)) = consent.as_ref().map(|consent| consent.validate()) {
+            return Err(self.process_error(&tags, start, "1003", format!("INVALID_CONSENT: {}", e)));
+        }
+
+        // Held until the submission has been processed
+        let _lock = match self.lock_for_processing(&submission_id, &tags).await {
+            Ok(Some(lock)) => lock,
+            Ok(None) => return Err(self.process_error(&tags, start, "1003", "CURRENTLY_PROCESSING".to_string())),
+            Err(e) => return Err(self.process_failed(&tags, start, e)),
+        };
 
         // 1. Check if submission exists in database
-        let (submission_type, nfc_identifier, submission_data) = match self.submission_repository.find_submission_by_id(&submission_id).await {
-            Ok(Some((submission_type, nfc_identifier, data))) => (submission_type, nfc_identifier, data),
-            Ok(None) => {
-                self.metrics.increment("process_submission.error", Some(tags.clone()));
-                self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1004".to_string(),
-                    cause: "SUBMISSION_NOT_FOUND".to_string(),
-                }]);
+        let (tenant_id, submission_type, nfc_identifier, previous_status, submission_data) = match self.submission_repository.find_submission_by_id(&submission_id).await {
+            Ok(Some((tenant_id, submission_type, nfc_identifier, status, data))) => (tenant_id, submission_type, nfc_identifier, status, data),
+            Ok(None) => return Err(self.process_error(&tags, start, "1004", "SUBMISSION_NOT_FOUND".to_string())),
+            Err(e) => return Err(self.process_failed(&tags, start, e.into())),
+        };
+
+        if let Err(e) = self.submission_repository.mark_processing_started(&submission_id).await {
+            log::warn!("Failed to record when submission {} started processing: {}", submission_id, e);
+        }
+
+        // 2. Resolve how this submission type is processed
+        let flow = match submission_type.parse::<SubmissionType>() {
+            Ok(submission_type) => SubmissionFlow::for_type(&submission_type),
+            Err(_) => return Err(self.process_error(&tags, start, "1004", "INVALID_SUBMISSION_TYPE".to_string())),
+        };
+        tags = tags.tenant(&tenant_id).submission_type(&flow.submission_type);
+
+        // Consent is kept even if processing fails further on
+        if let Some(consent) = &consent {
+            match self.record_consent(&submission_id, consent, &actor).await {
+                Ok(true) => {}
+                Ok(false) => return Err(self.process_error(&tags, start, "1004", "SUBMISSION_NOT_FOUND".to_string())),
+                Err(e) => return Err(self.process_failed(&tags, start, e.into())),
             }
+        }
+
+        let mut face_match_result = None;
+        let mut new_status = None;
+
+        // Events are written together with the decision they lead to
+        let mut pending_events = Vec::new();
+
+        // 3. Run the pipeline steps of the flow in order
+        for step in flow.steps {
+            match step {
+                PipelineStep::ConfirmDocuments => {
+                    let mut confirmed = HashMap::new();
+                    for document_type in flow.required_documents {
+                        let missing = || format!("{}_DOES_NOT_EXIST", document_type);
+                        let document = match submission_data.get(*document_type) {
+                            Some(document) => document,
+                            None => return Err(self.process_error(&tags, start, "1004", missing())),
+                        };
+
+                        // A storage outage is not a missing document; the client
+                        // should retry rather than upload again
+                        let storage_unavailable = |e: &dyn std::fmt::Display| {
+                            log::error!("Failed to look up {} {}: {}", document_type, document.document_name, e);
+                            AppError::from_code("1000", "DOCUMENT_STORAGE_UNAVAILABLE").with_status(StatusCode::SERVICE_UNAVAILABLE)
+                        };
+
+                        // Presigned PUTs can't limit their size, so it is checked here
+                        let size = match self.minio_service.object_size(&document.document_name).await {
+                            Ok(size) => size,
+                            Err(e) => return Err(self.process_failed(&tags, start, storage_unavailable(&e))),
+                        };
+                        match size {
+                            Some(size) => {
+                                let limit = self.upload_policy.for_document(*document_type).max_size_in_bytes;
+                                if size > limit {
+                                    return Err(self.process_error(
+                                        &tags,
+                                        start,
+                                        "1010",
+                                        format!("{}_TOO_LARGE: limit is {} bytes", document_type, limit),
+                                    ));
+                                }
+                            }
+                            None => {
+                                // The scanner moves infected documents out of the way
+                                let quarantined = quarantined_document_name(&document.document_name);
+                                let is_quarantined = match self.minio_service.file_exists(quarantined).await {
+                                    Ok(exists) => exists,
+                                    Err(e) => return Err(self.process_failed(&tags, start, storage_unavailable(&e))),
+                                };
+                                if is_quarantined {
+                                    return Err(self.process_error(&tags, start, "1014", format!("{}_INFECTED", document_type)));
+                                }
+                                return Err(self.process_error(&tags, start, "1004", missing()));
+                            }
+                        }
+
+                        if let Some(document_scans) = &self.document_scans {
+                            let Ok(submission_uuid) = Uuid::parse_str(&submission_id) else {
+                                return Err(self.process_error(&tags, start, "1004", "SUBMISSION_NOT_FOUND".to_string()));
+                            };
+                            let status = match self.minio_service.object_etag(None, &document.document_name).await {
+                                Ok(Some(etag)) => {
+                                    document_scans
+                                        .status(
+                                            submission_uuid,
+                                            &document_type.to_string(),
+                                            &document.document_name,
+                                            &etag,
+                                            json!({ "tenantId": tenant_id, "submissionType": submission_type }),
+                                        )
+                                        .await
+                                }
+                                Ok(None) => return Err(self.process_error(&tags, start, "1004", missing())),
+                                Err(e) => return Err(self.process_error(&tags, start, "1001", e.to_string())),
+                            };
+                            match status {
+                                Ok(ScanStatus::Clean) => {}
+                                Ok(ScanStatus::Infected) => {
+                                    return Err(self.process_error(&tags, start, "1014", format!("{}_INFECTED", document_type)));
+                                }
+                                // Missing and pending verdicts both hold the document back
+                                Ok(ScanStatus::Pending) => {
+                                    return Err(self.process_error(
+                                        &tags,
+                                        start,
+                                        RETRY_LATER_CODE,
+                                        format!("DOCUMENT_SCAN_PENDING: {}", document_type),
+                                    ));
+                                }
+                                Err(e) => return Err(self.process_error(&tags, start, "1000", e.to_string())),
+                            }
+                        }
+
+                        confirmed.insert(document_type.to_string(), document.document_name.clone());
+                    }
+
+                    self.analytics.emit(
+                        self.analytics
+                            .event(FunnelStep::UploadConfirmed, &tenant_id, &submission_type, &submission_id)
+                            .property("documentCount", confirmed.len()),
+                    );
+                    pending_events.push((EVENT_DOCUMENTS_CONFIRMED, json!({ "documents": confirmed })));
+                }
+                PipelineStep::FaceMatch(reference) => {
+                    let selfie_filename = match submission_data.get(DocumentType::Selfie) {
+                        Some(doc) => doc.document_name.clone(),
+                        None => return Err(self.process_error(&tags, start, "1004", "SELFIE_DOES_NOT_EXIST".to_string())),
+                    };
+
+                    let reference_type = match reference {
+                        FaceMatchReference::NfcChipPhoto => DocumentType::Nfc,
+                        FaceMatchReference::ApprovedSelfie => DocumentType::Selfie,
+                    };
+                    let reference_filename = match reference {
+                        FaceMatchReference::NfcChipPhoto => match submission_data.get(DocumentType::Nfc) {
+                            Some(doc) => doc.document_name.clone(),
+                            None => return Err(self.process_error(&tags, start, "1004", "NFC_DOES_NOT_EXIST".to_string())),
+                        },
+                        FaceMatchReference::ApprovedSelfie => {
+                            let approved = match self.submission_repository.find_submission_by_nfc_identifier_and_status(&nfc_identifier, "APPROVED").await {
+                                Ok(Some(approved)) => approved,
+                                Ok(None) => return Err(self.process_error(&tags, start, "1004", "SUBMISSION_NOT_FOUND".to_string())),
+                                Err(e) => return Err(self.process_failed(&tags, start, e.into())),
+                            };
+
+                            let approved_selfie = match approved.get(DocumentType::Selfie) {
+                                Some(doc) => doc.document_name.clone(),
+                                None => return Err(self.process_error(&tags, start, "1004", "SELFIE_DOES_NOT_EXIST".to_string())),
+                            };
+
+                            if !self.minio_service.file_exists(approved_selfie.clone()).await.unwrap_or(false) {
+                                return Err(self.process_error(&tags, start, "1004", "SELFIE_DOES_NOT_EXIST".to_string()));
+                            }
+
+                            approved_selfie
+                        }
+                    };
+
+                    // Generate URLs for face matching, preferring the preprocessed images
+                    let selfie_filename = face_match_service.provider_document(selfie_filename).await;
+                    let reference_filename = face_match_service.provider_document(reference_filename).await;
+                    let selfie_expiry = self.url_expiry.signed_for(self.url_expiry.download_expiry(DocumentType::Selfie));
+                    let selfie_url = match self.minio_service.generate_presigned_url(selfie_filename.clone(), selfie_expiry).await {
+                        Ok(url) => url,
+                        Err(e) => return Err(self.process_error(&tags, start, "1001", e.to_string())),
+                    };
+                    let reference_expiry = self.url_expiry.signed_for(self.url_expiry.download_expiry(reference_type));
+                    let reference_url = match self.minio_service.generate_presigned_url(reference_filename.clone(), reference_expiry).await {
+                        Ok(url) => url,
+                        Err(e) => return Err(self.process_error(&tags, start, "1001", e.to_string())),
+                    };
+
+                    log::info!("selfie_url: {:?}, reference_url: {:?}", selfie_url, reference_url);
+
+                    // Perform face matching
+                    match face_match_service.compare_stored_faces(
+                        &reference_filename,
+                        &selfie_filename,
+                        reference_url,
+                        selfie_url,
+                        submission_id.clone(),
+                    ).await {
+                        Ok(result) => {
+                            self.analytics.emit(
+                                self.analytics
+                                    .event(FunnelStep::FaceMatchDone, &tenant_id, &submission_type, &submission_id)
+                                    .outcome(if result.is_match { "match" } else { "no_match" })
+                                    .property("provider", result.provider.clone())
+                                    .property("similarityScore", result.similarity_score),
+                            );
+                            pending_events.push((EVENT_FACE_MATCH_CALLED, json!({
+                                "similarityScore": result.similarity_score,
+                                "rawScore": result.raw_score,
+                                "provider": result.provider,
+                                "isMatch": result.is_match,
+                                "threshold": result.threshold,
+                            })));
+                            face_match_result = Some(result);
+                        }
+                        Err(e) => {
+                            self.analytics.emit(
+                                self.analytics
+                                    .event(FunnelStep::FaceMatchDone, &tenant_id, &submission_type, &submission_id)
+                                    .outcome("error")
+                                    .property("errorCode", face_match_service::error_code(&e)),
+                            );
+                            // Nothing changes on the submission, but the failed call is still worth auditing
+                            pending_events.push((EVENT_FACE_MATCH_CALLED, json!({ "error": e.to_string() })));
+                            for (event_type, payload_diff) in pending_events {
+                                self.record_event(&submission_id, event_type, &actor, payload_diff).await;
+                            }
+                            return Err(self.process_error(&tags, start, face_match_service::error_code(&e), e.to_string()));
+                        }
+                    }
+                }
+                PipelineStep::ApplyDecisionPolicy => {
+                    let Some(face_match_result) = face_match_result.as_ref() else {
+                        return Err(self.process_error(&tags, start, "1000", "SYSTEM_ERROR".to_string()));
+                    };
+
+                    // Decide using the tenant's policy for this submission type
+                    let policy = match self.policy_repository.find_face_match_policy(&tenant_id, &submission_type).await {
+                        Ok(Some(policy)) => policy,
+                        Ok(None) => face_match_service.default_policy(&tenant_id, &submission_type),
+                        Err(e) => return Err(self.process_failed(&tags, start, e.into())),
+                    };
+
+                    let rules = match self.policy_repository.find_decision_rules(&tenant_id, &submission_type).await {
+                        Ok(Some(rules)) => rules,
+                        Ok(None) => self.decision_rules.clone(),
+                        Err(e) => return Err(self.process_failed(&tags, start, e.into())),
+                    };
+
+                    // Only rules beyond the face match band need the submission's signals
+                    let signals = if rules.rules.is_empty() {
+                        DecisionSignals::default()
+                    } else {
+                        match self.submission_repository.find_decision_signals(&submission_id).await {
+                            Ok(record) => DecisionSignals {
+                                device: record.device_info,
+                                ..DecisionSignals::from_request_data(
+                                    &record.request_data,
+                                    record.duplicate_nik.filter(|_| rules.requires(DecisionRule::UniqueNik)),
+                                )
+                            },
+                            Err(e) => return Err(self.process_failed(&tags, start, e.into())),
+                        }
+                    };
+
+                    let band = face_match_service.evaluate(face_match_result, &policy);
+                    let outcome = rules.evaluate(&policy, band, &signals);
+                    let decision = outcome.decision;
+                    let status = decision.submission_status();
+                    // The band's code unless another rule changed the decision
+                    let reason_code = if decision == band {
+                        policy.reason_code(decision)
+                    } else {
+                        outcome.reasons.last().cloned().unwrap_or_else(|| policy.reason_code(decision))
+                    };
+
+                    pending_events.push((EVENT_STATUS_CHANGED, json!({
+                        "status": { "from": previous_status, "to": status },
+                        "result": { "to": decision.to_string() },
+                        "reasonCode": { "to": reason_code },
+                        "decisionReasons": { "to": outcome.reasons },
+                        "faceMatchScore": { "to": face_match_result.similarity_score },
+                    })));
+
+                    // Store the decision alongside the score it was based on, with its events
+                    match self.commit_decision(
+                        &submission_id,
+                        status,
+                        &decision.to_string(),
+                        &reason_code,
+                        &outcome.reasons,
+                        face_match_result,
+                        &actor,
+                        std::mem::take(&mut pending_events),
+                    ).await {
+                        Ok(true) => {}
+                        Ok(false) => return Err(self.process_error(&tags, start, "1004", "SUBMISSION_NOT_FOUND".to_string())),
+                        Err(e) => return Err(self.process_failed(&tags, start, e.into())),
+                    }
+
+                    if let Some(webhooks) = &self.webhooks {
+                        webhooks.dispatch(
+                            WEBHOOK_EVENT_SUBMISSION_DECIDED,
+                            json!({
+                                "submissionId": submission_id,
+                                "tenantId": tenant_id,
+                                "submissionType": submission_type,
+                                "submissionStatus": status,
+                                "result": decision.to_string(),
+                                "reasonCode": reason_code,
+                                "decisionReasons": outcome.reasons,
+                                "faceMatchScore": face_match_result.similarity_score,
+                            }),
+                        );
+                    }
+                    if let Some(notifications) = &self.notifications {
+                        notifications.notify(&submission_id, &tenant_id, &submission_type, status, &reason_code);
+                    }
+                    self.analytics.emit(
+                        self.analytics
+                            .event(FunnelStep::Decision, &tenant_id, &submission_type, &submission_id)
+                            .outcome(decision)
+                            .property("reasonCode", reason_code.clone()),
+                    );
+
+                    new_status = Some(status);
+                }
+            }
+        }
+
+        let Some(new_status) = new_status else {
+            return Err(self.process_error(&tags, start, "1000", "SYSTEM_ERROR".to_string()));
+        };
+
+        // 4. Return response
+        let response = ProcessSubmissionResponse {
+            submission_status: new_status.to_string(),
+        };
+
+        self.metrics.increment("process_submission.success", Some(tags.clone().outcome("success")));
+        self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags.outcome("success")));
+
+        Ok(response)
+    }
+
+    /// Store the consent with its audit event. Returns false if the
+    /// submission no longer exists.
+    async fn record_consent(&self, submission_id: &str, consent: &Consent, actor: &str) -> Result<bool, sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+
+        let mut tx = self.submission_repository.begin().await?;
+
+        if self.submission_repository.lock_submission(&mut tx, submission_id).await?.is_none() {
+            return Ok(false);
+        }
+
+        self.submission_repository.update_consent(&mut tx, submission_uuid, consent).await?;
+
+        self.submission_event_repository
+            .append_in_tx(&mut tx, submission_uuid, EVENT_CONSENT_RECORDED, actor, json!({ "consent": { "to": consent } }))
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Write a decision and the events leading to it in one transaction,
+    /// queueing MANUAL_REVIEW decisions for a reviewer. Returns false if the
+    /// submission no longer exists.
+    #[allow(clippy::too_many_arguments)]
+    async fn commit_decision(
+        &self,
+        submission_id: &str,
+        status: &str,
+        result: &str,
+        reason_code: &str,
+        decision_reasons: &[String],
+        face_match: &FaceMatchResponse,
+        actor: &str,
+        events: Vec<(&str, serde_json::Value)>,
+    ) -> Result<bool, sqlx::Error> {
+        let submission_uuid = Uuid::parse_str(submission_id).map_err(|_| sqlx::Error::RowNotFound)?;
+
+        // Dropping the transaction on an early return rolls it back
+        let mut tx = self.submission_repository.begin().await?;
+
+        if self.submission_repository.lock_submission(&mut tx, submission_id).await?.is_none() {
+            return Ok(false);
+        }
+
+        self.submission_repository
+            .update_submission_decision(
+                &mut tx,
+                submission_id,
+                status,
+                result,
+                reason_code,
+                (face_match.similarity_score, face_match.raw_score, &face_match.provider),
+                decision_reasons,
+                status != FaceMatchDecision::ManualReview.submission_status(),
+            )
+            .await?;
+
+        if status == FaceMatchDecision::ManualReview.submission_status() {
+            self.submission_review_repository
+                .enqueue_in_tx(&mut tx, submission_uuid, face_match.similarity_score, reason_code)
+                .await?;
+        }
+
+        for (event_type, payload_diff) in events {
+            self.submission_event_repository
+                .append_in_tx(&mut tx, submission_uuid, event_type, actor, payload_diff)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Returns the counter to release if the submission isn't created. An
+    /// unreachable Redis doesn't block submissions.
+    async fn consume_quota(
+        &self,
+        user_id: &str,
+        submission_type: &SubmissionType,
+        tags: &MetricTags,
+    ) -> Result<Option<String>, AppError> {
+        let Some(quota) = &self.quota else {
+            return Ok(None);
+        };
+
+        match quota.try_consume(user_id, &submission_type.to_string()).await {
+            Ok(QuotaCheck::Allowed { key }) => Ok(key),
+            Ok(QuotaCheck::Exceeded { limit }) => {
+                self.metrics.increment("submission_quota.rejected", Some(tags.clone()));
+                Err(AppError::from_code("1013", format!("QUOTA_EXCEEDED: at most {} {} submissions per day", limit, submission_type)))
+            }
+            Err(e) => {
+                self.metrics.increment("submission_quota.error", Some(tags.clone()));
+                log::error!("Failed to check submission quota for user {}, allowing the submission: {}", user_id, e);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn verify_nfc<'a>(&self, nfc_identifier: &'a NfcIdentifier, tags: &MetricTags) -> Result<&'a str, AppError> {
+        let Some(guard) = &self.nfc_replay_guard else {
+            return Ok(nfc_identifier.payload());
+        };
+
+        guard.verify(nfc_identifier).await.map_err(|rejection| {
+            let (code, reason) = match &rejection {
+                NfcRejection::Unsigned => ("1003", "unsigned"),
+                NfcRejection::InvalidSignature => ("1003", "invalid_signature"),
+                NfcRejection::Stale { .. } => ("1003", "stale"),
+                NfcRejection::Replayed => ("1003", "replayed"),
+                NfcRejection::Redis(_) => ("1000", "error"),
+            };
+            self.metrics.increment("nfc_replay.rejected", Some(tags.clone().with("reason", reason)));
+            log::warn!("Rejected NFC read: {}", rejection);
+            AppError::from_code(code, rejection.to_string())
+        })
+    }
+
+    async fn release_quota(&self, key: Option<String>) {
+        if let (Some(quota), Some(key)) = (&self.quota, key) {
+            if let Err(e) = quota.release(&key).await {
+                log::error!("Failed to release submission quota {}: {}", key, e);
+            }
+        }
+    }
+
+    /// Lock the submission against a concurrent process request, in Redis or,
+    /// when Redis fails, with a claim in Postgres. None when another request
+    /// holds it; when neither can be reached the request is turned away as
+    /// `SERVICE_BUSY` rather than processed unlocked.
+    async fn lock_for_processing(&self, submission_id: &str, tags: &MetricTags) -> Result<Option<ProcessLock>, AppError> {
+        let Some(lock_manager) = &self.process_lock else {
+            return Ok(Some(ProcessLock::default()));
+        };
+
+        match lock_manager.try_lock(format!("submission_process_lock:{}", submission_id), PROCESS_LOCK_TTL).await {
+            Ok(lock) => {
+                return Ok(lock.map(|lock| ProcessLock {
+                    _redis: Some(lock),
+                    ..Default::default()
+                }))
+            }
+            Err(e) => {
+                self.metrics.increment("process_submission.lock_error", Some(tags.clone()));
+                log::warn!("Failed to lock submission {} in Redis, locking it in Postgres: {}", submission_id, e);
+            }
+        }
+
+        match self.submission_repository.claim_for_processing(submission_id, PROCESS_LOCK_TTL).await {
+            Ok(claim) => Ok(claim.map(|claim| ProcessLock {
+                _postgres: Some(claim),
+                ..Default::default()
+            })),
             Err(e) => {
-                self.metrics.increment("process_submission.error", Some(tags.clone()));
-                self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1002".to_string(),
-                    cause: e.to_string(),
-                }]);
+                log::error!("Failed to lock submission {}, turning the request away: {}", submission_id, e);
+                Err(AppError::from_code(SERVICE_BUSY_CODE, "SERVICE_BUSY: PROCESS_LOCK_UNAVAILABLE"))
             }
+        }
+    }
+
+    /// Record a failed process_submission call and build its error
+    fn process_error(&self, tags: &MetricTags, start: std::time::Instant, code: &str, cause: String) -> AppError {
+        self.process_failed(tags, start, AppError::from_code(code, cause))
+    }
+
+    fn process_failed(&self, tags: &MetricTags, start: std::time::Instant, error: AppError) -> AppError {
+        self.metrics.increment("process_submission.error", Some(tags.clone().outcome("error")));
+        self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags.clone().outcome("error")));
+        error
+    }
+
+    /// Append to the submission's audit trail. A failed write is logged rather
+    /// than failing the request the event describes.
+    async fn record_event(&self, submission_id: &str, event_type: &str, actor: &str, payload_diff: serde_json::Value) {
+        let Ok(submission_uuid) = Uuid::parse_str(submission_id) else {
+            return;
         };
 
+        if let Err(e) = self
+            .submission_event_repository
+            .append(submission_uuid, event_type, actor, payload_diff)
+            .await
+        {
+            self.metrics.increment("submission_event.error", None);
+            log::error!("Failed to record {} event for submission {}: {}", event_type, submission_id, e);
+        }
+    }
 
-        let mut image_url_1 = String::new();
-        let mut image_url_2 = String::new();
+    /// Face match two documents of a submission, identified by the references
+    /// returned with their upload URLs
+    pub async fn face_match_documents(
+        &self,
+        submission_id: String,
+        user_id: String,
+        image1_reference: String,
+        image2_reference: String,
+        face_match_service: FaceMatchService,
+    ) -> Result<FaceMatchResponse, AppError> {
+        let mut tags = MetricTags::endpoint("face_match_documents");
 
-        // 2. Extract document names from submission data
-        let documents_data = match submission_data.as_object() {
-            Some(obj) => obj,
-            None => {
-                self.metrics.increment("process_submission.error", Some(tags.clone()));
-                self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1004".to_string(),
-                    cause: "INVALID_SUBMISSION_DATA".to_string(),
-                }]);
+        let not_found = |cause: &str| AppError::from_code("1004", cause).with_status(StatusCode::NOT_FOUND);
+
+        let submission_data = match self.submission_repository.find_submission_for_upload(&submission_id).await {
+            // Someone else's submission looks the same as a missing one
+            Ok(Some((owner_id, tenant_id, submission_type, _, submission_data))) if owner_id == user_id => {
+                tags = tags.tenant(&tenant_id).submission_type(&submission_type);
+                submission_data
+            }
+            Ok(_) | Err(sqlx::Error::RowNotFound) => {
+                self.metrics.increment("api_error", Some(tags.clone().outcome("error")));
+                return Err(not_found("SUBMISSION_NOT_FOUND"));
+            }
+            Err(e) => {
+                self.metrics.increment("api_error", Some(tags.clone().outcome("error")));
+                return Err(AppError::from(e));
             }
         };
 
-        // 3. Get selfie document name
-        let selfie_doc = match documents_data.get("SELFIE") {
-            Some(doc) => doc,
-            None => {
-                self.metrics.increment("process_submission.error", Some(tags.clone()));
-                self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1004".to_string(),
-                    cause: "SELFIE_DOES_NOT_EXIST".to_string(),
-                }]);
+        let (Some(image1), Some(image2)) = (
+            submission_data.find_by_reference(&image1_reference),
+            submission_data.find_by_reference(&image2_reference),
+        ) else {
+            self.metrics.increment("api_error", Some(tags.clone().outcome("error")));
+            return Err(not_found("DOCUMENT_NOT_FOUND"));
+        };
+
+        match face_match_service
+            .compare_documents(
+                (image1.0, image1.1.document_name.clone()),
+                (image2.0, image2.1.document_name.clone()),
+                submission_id,
+                &self.url_expiry,
+            )
+            .await
+        {
+            Ok(response) => {
+                self.metrics.increment("api_success", Some(tags.outcome("success")));
+                Ok(response)
             }
+            Err(e) => {
+                self.metrics.increment("api_error", Some(tags.outcome("error")));
+                Err(AppError::from_code(face_match_service::error_code(&e), e.to_string()))
+            }
+        }
+    }
+
+    /// Store a document for clients that can't PUT to a presigned URL. The body
+    /// is streamed to MinIO under the name reserved when the URLs were issued.
+    pub async fn upload_document<S, E>(
+        &self,
+        submission_id: String,
+        user_id: String,
+        document_type: String,
+        body: S,
+        content_type: Option<String>,
+        max_size: u64,
+    ) -> Result<UploadDocumentResponse, AppError>
+    where
+        S: futures::Stream<Item = Result<bytes::Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let start = std::time::Instant::now();
+        let mut tags = MetricTags::endpoint("upload_document").document_type(&document_type);
+
+        let not_found = || AppError::from_code("1004", "SUBMISSION_NOT_FOUND");
+
+        // NFC is uploaded by the API itself when the submission is created
+        let parsed_document_type = match document_type.parse::<DocumentType>() {
+            Ok(DocumentType::Nfc) | Err(_) => None,
+            Ok(document_type) => Some(document_type),
+        };
+        let Some(parsed_document_type) = parsed_document_type else {
+            self.metrics.increment("upload_document.error", Some(tags.clone().outcome("error")));
+            return Err(AppError::from_code("1003", "INVALID_DOCUMENT_TYPE"));
         };
 
-        let selfie_filename = match selfie_doc.get("documentName") {
-            Some(name) => name.as_str().unwrap_or(""),
-            None => {
-                self.metrics.increment("process_submission.error", Some(tags.clone()));
-                self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1004".to_string(),
-                    cause: "SELFIE_DOES_NOT_EXIST".to_string(),
-                }]);
+        let (owner_id, tenant_id, submission_type, status, submission_data) = match self.submission_repository.find_submission_for_upload(&submission_id).await {
+            Ok(Some(submission)) => submission,
+            Ok(None) | Err(sqlx::Error::RowNotFound) => {
+                self.metrics.increment("upload_document.error", Some(tags.clone().outcome("error")));
+                return Err(not_found());
+            }
+            Err(e) => {
+                self.metrics.increment("upload_document.error", Some(tags.clone().outcome("error")));
+                return Err(AppError::from(e));
             }
         };
 
-        // 4. Check if selfie exists in MinIO
-        if !self.minio_service.file_exists(selfie_filename.to_string()).await.unwrap_or(false) {
-            self.metrics.increment("process_submission.error", Some(tags.clone()));
-            self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-            return Err(vec![ApiError {
-                entity: "HACKATHON_BI_2025".to_string(),
-                code: "1004".to_string(),
-                cause: "SELFIE_DOES_NOT_EXIST".to_string(),
-            }]);
+        // Someone else's submission looks the same as a missing one
+        if owner_id != user_id {
+            self.metrics.increment("upload_document.error", Some(tags.clone().outcome("error")));
+            return Err(not_found());
         }
+        tags = tags.tenant(&tenant_id).submission_type(&submission_type);
 
-        // 6. Generate URLs for face matching
-        let selfie_url = match self.minio_service.generate_view_url(selfie_filename.to_string()).await {
-            Ok(url) => url,
+        if status != "INITIATED" {
+            self.metrics.increment("upload_document.error", Some(tags.clone().outcome("error")));
+            return Err(AppError::from_code("1004", "SUBMISSION_ALREADY_PROCESSED"));
+        }
+
+        let Some(document) = submission_data.get(parsed_document_type) else {
+            self.metrics.increment("upload_document.error", Some(tags.clone().outcome("error")));
+            return Err(AppError::from_code("1003", "INVALID_DOCUMENT_TYPE"));
+        };
+
+        let size_in_bytes = match self
+            .minio_service
+            .upload_stream(document.document_name.clone(), body, content_type.clone(), max_size)
+            .await
+        {
+            Ok(size) => size,
             Err(e) => {
-                self.metrics.increment("process_submission.error", Some(tags.clone()));
-                self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1001".to_string(),
-                    cause: e.to_string(),
-                }]);
+                self.metrics.increment("upload_document.error", Some(tags.clone().outcome("error")));
+                let code = match e {
+                    UploadStreamError::TooLarge { .. } => "1010",
+                    UploadStreamError::Body(_) => "1003",
+                    UploadStreamError::Storage(_) => "1001",
+                };
+                return Err(AppError::from_code(code, e.to_string()));
             }
         };
 
-        log::info!("selfie_url: {:?}", selfie_url);
+        // Processing enqueues the scan again if this one doesn't make it
+        if let (Some(document_scans), Ok(submission_uuid)) = (&self.document_scans, Uuid::parse_str(&submission_id)) {
+            let metadata = json!({ "tenantId": tenant_id, "submissionType": submission_type });
+            if let Err(e) = document_scans
+                .request(submission_uuid, &parsed_document_type.to_string(), &document.document_name, metadata)
+                .await
+            {
+                log::warn!("Failed to enqueue the scan of {}: {}", document.document_name, e);
+            }
+        }
 
-        if submission_type == "KYC" {
+        self.record_event(
+            &submission_id,
+            EVENT_DOCUMENT_UPLOADED,
+            &user_actor(&user_id),
+            json!({
+                "documentType": document_type,
+                "sizeInBytes": size_in_bytes,
+                "contentType": content_type,
+            }),
+        ).await;
 
-            // 5. Get NFC document name
-            let nfc_doc = match documents_data.get("NFC") {
-                Some(doc) => doc,
-                None => {
-                    self.metrics.increment("process_submission.error", Some(tags.clone()));
-                    self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1004".to_string(),
-                        cause: "NFC_DOES_NOT_EXIST".to_string(),
-                    }]);
-                }
-            };
+        self.metrics.increment("upload_document.success", Some(tags.clone().outcome("success")));
+        self.metrics.timing("upload_document.duration", start.elapsed(), Some(tags.outcome("success")));
 
-            let nfc_filename = match nfc_doc.get("documentName") {
-                Some(name) => name.as_str().unwrap_or(""),
-                None => {
-                    self.metrics.increment("process_submission.error", Some(tags.clone()));
-                    self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1004".to_string(),
-                        cause: "NFC_DOES_NOT_EXIST".to_string(),
-                    }]);
-                }
-            };
+        Ok(UploadDocumentResponse {
+            submission_id,
+            document_type,
+            document_reference: document.document_reference.clone(),
+            size_in_bytes,
+        })
+    }
 
-            let nfc_url = match self.minio_service.generate_view_url(nfc_filename.to_string()).await {
-                Ok(url) => url,
-                Err(e) => {
-                    self.metrics.increment("process_submission.error", Some(tags.clone()));
-                    self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1001".to_string(),
-                        cause: e.to_string(),
-                    }]);
-                }
-            };
+    /// Issue a new presigned upload URL for a document slot of a submission
+    /// still waiting for its documents, for clients whose URL expired
+    pub async fn refresh_upload_url(
+        &self,
+        submission_id: String,
+        user_id: String,
+        document_type: String,
+    ) -> Result<RefreshUploadUrlResponse, AppError> {
+        let tags = MetricTags::endpoint("refresh_upload_url").document_type(&document_type);
 
-            log::info!("nfc_url: {:?}", nfc_url);
+        let failed = |error: AppError| {
+            self.metrics.increment("refresh_upload_url.error", Some(tags.clone().outcome("error")));
+            error
+        };
+        let error = |code: &str, cause: &str| failed(AppError::from_code(code, cause));
 
-            image_url_1 = nfc_url;
-            image_url_2 = selfie_url;
+        // NFC is uploaded by the API itself when the submission is created
+        let parsed_document_type = match document_type.parse::<DocumentType>() {
+            Ok(DocumentType::Nfc) | Err(_) => return Err(error("1003", "INVALID_DOCUMENT_TYPE")),
+            Ok(document_type) => document_type,
+        };
 
-        } else if submission_type == "ON_DEMAND" {
+        let mut tx = match self.submission_repository.begin().await {
+            Ok(tx) => tx,
+            Err(e) => return Err(failed(e.into())),
+        };
 
-            // 1. Check if submission exists in database
-            let submission_data_existing = match self.submission_repository.find_submission_by_nfc_identifier_and_status(&nfc_identifier, "APPROVED").await {
-                Ok(Some(submission_data_existing)) => submission_data_existing,
-                Ok(None) => {
-                    self.metrics.increment("process_submission.error", Some(tags.clone()));
-                    self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1004".to_string(),
-                        cause: "SUBMISSION_NOT_FOUND".to_string(),
-                    }]);
-                }
-                Err(e) => {
-                    self.metrics.increment("process_submission.error", Some(tags.clone()));
-                    self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1002".to_string(),
-                        cause: e.to_string(),
-                    }]);
-                }
+        let (owner_id, status, submission_data) =
+            match self.submission_repository.lock_submission_for_upload(&mut tx, &submission_id).await {
+                Ok(Some(submission)) => submission,
+                Ok(None) | Err(sqlx::Error::RowNotFound) => return Err(error("1004", "SUBMISSION_NOT_FOUND")),
+                Err(e) => return Err(failed(e.into())),
             };
 
-            // 2. Extract document names from submission data
-            let documents_data_existing = match submission_data_existing.as_object() {
-                Some(obj) => obj,
-                None => {
-                    self.metrics.increment("process_submission.error", Some(tags.clone()));
-                    self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1004".to_string(),
-                        cause: "INVALID_SUBMISSION_DATA".to_string(),
-                    }]);
-                }
-            };
+        // Someone else's submission looks the same as a missing one
+        if owner_id != user_id {
+            return Err(error("1004", "SUBMISSION_NOT_FOUND"));
+        }
 
-            // 3. Get selfie document name
-            let selfie_doc_existing = match documents_data_existing.get("SELFIE") {
-                Some(doc) => doc,
-                None => {
-                    self.metrics.increment("process_submission.error", Some(tags.clone()));
-                    self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1004".to_string(),
-                        cause: "SELFIE_DOES_NOT_EXIST".to_string(),
-                    }]);
-                }
+        if status != "INITIATED" {
+            return Err(error("1004", "SUBMISSION_ALREADY_PROCESSED"));
+        }
+
+        let Some(document) = submission_data.get(parsed_document_type).cloned() else {
+            return Err(error("1003", "INVALID_DOCUMENT_TYPE"));
+        };
+
+        // The slot keeps its object key, so the document reference stays valid
+        let expiry = self.url_expiry.upload_expiry(parsed_document_type);
+        let expires_at = UrlExpiryConfig::expires_at(expiry);
+        let policy = self.upload_policy.for_document(parsed_document_type);
+        let upload = match self
+            .minio_service
+            .generate_upload_url(document.document_name.clone(), self.url_expiry.signed_for(expiry), &policy)
+            .await
+        {
+            Ok(upload) => upload,
+            Err(e) => return Err(error("1001", &e.to_string())),
+        };
+
+        // Only the expiry changes, so upload progress written meanwhile stays
+        let patch = SubmissionDataPatch::new().upload_url_expires_at(parsed_document_type, expires_at);
+        let submission_uuid = Uuid::parse_str(&submission_id).map_err(|_| error("1004", "SUBMISSION_NOT_FOUND"))?;
+        let replaced = match self
+            .submission_repository
+            .patch_submission_data_in_tx(&mut tx, submission_uuid, &patch, None)
+            .await
+        {
+            Ok((PatchOutcome::Applied(_), replaced)) => replaced,
+            Ok(_) => return Err(error("1004", "SUBMISSION_NOT_FOUND")),
+            Err(e) => return Err(failed(e.into())),
+        };
+
+        if let Err(e) = self
+            .submission_event_repository
+            .append_in_tx(
+                &mut tx,
+                submission_uuid,
+                EVENT_UPLOAD_URL_REFRESHED,
+                &user_actor(&user_id),
+                json!({
+                    "documentType": document_type,
+                    "uploadUrlExpiresAt": { "from": document.upload_url_expires_at, "to": expires_at },
+                }),
+            )
+            .await
+        {
+            return Err(failed(e.into()));
+        }
+
+        if let Err(e) = tx.commit().await {
+            return Err(failed(e.into()));
+        }
+        replaced.delete().await;
+
+        self.metrics.increment("refresh_upload_url.success", Some(tags.outcome("success")));
+
+        Ok(RefreshUploadUrlResponse {
+            submission_id,
+            document_type,
+            document_url: upload.url,
+            document_reference: document.document_reference,
+            expiry_in_seconds: expiry.as_secs().to_string(),
+            expires_at,
+            content_type: policy.content_type,
+            max_size_in_bytes: policy.max_size_in_bytes,
+            upload_form: upload.form,
+            submission_token: None,
+            submission_token_expires_at: None,
+        })
+    }
+
+    /// Start over from a rejected submission: a new submission linked to it
+    /// gets upload URLs for the rejected documents and copies of the others
+    pub async fn resubmit(&self, submission_id: String, user_id: String) -> Result<ResubmissionResponse, AppError> {
+        let mut tags = MetricTags::endpoint("resubmit");
+
+        let failed = |tags: &MetricTags, error: AppError| {
+            self.metrics.increment("resubmit.error", Some(tags.clone().outcome("error")));
+            error
+        };
+        let error = |tags: &MetricTags, code: &str, cause: &str| failed(tags, AppError::from_code(code, cause));
+
+        let Ok(parent_id) = Uuid::parse_str(&submission_id) else {
+            return Err(error(&tags, "1004", "SUBMISSION_NOT_FOUND"));
+        };
+
+        // The parent stays locked until the resubmission is stored, so it
+        // can't be resubmitted twice
+        let mut tx = match self.submission_repository.begin().await {
+            Ok(tx) => tx,
+            Err(e) => return Err(failed(&tags, e.into())),
+        };
+
+        let parent = match self.submission_repository.lock_for_resubmission(&mut tx, parent_id).await {
+            Ok(Some(parent)) if parent.user_id == user_id => parent,
+            // Someone else's submission looks the same as a missing one
+            Ok(_) => return Err(error(&tags, "1004", "SUBMISSION_NOT_FOUND")),
+            Err(e) => return Err(failed(&tags, e.into())),
+        };
+        tags = tags.tenant(&parent.tenant_id).submission_type(&parent.submission_type);
+
+        if parent.status != "REJECTED" {
+            return Err(error(&tags, "1003", "SUBMISSION_NOT_REJECTED").with_status(StatusCode::CONFLICT));
+        }
+        if parent.resubmitted {
+            return Err(error(&tags, "1003", "SUBMISSION_ALREADY_RESUBMITTED").with_status(StatusCode::CONFLICT));
+        }
+
+        let flow = match parent.submission_type.parse::<SubmissionType>() {
+            Ok(submission_type) => SubmissionFlow::for_type(&submission_type),
+            Err(_) => return Err(error(&tags, "1004", "INVALID_SUBMISSION_TYPE")),
+        };
+
+        let policy = match self
+            .policy_repository
+            .find_resubmission_policy(&parent.tenant_id, &parent.submission_type)
+            .await
+        {
+            Ok(policy) => policy.unwrap_or(self.resubmission_policy),
+            Err(e) => return Err(failed(&tags, e.into())),
+        };
+        let attempt = parent.resubmission_attempt + 1;
+        if !policy.allows(attempt) {
+            return Err(error(&tags, "1003", "RESUBMISSION_LIMIT_REACHED").with_status(StatusCode::UNPROCESSABLE_ENTITY));
+        }
+
+        let reasons: Vec<String> = parent.reason_code.iter().chain(&parent.decision_reasons).cloned().collect();
+        let mut rejected = rejected_documents(&flow, &reasons, &parent.documents);
+
+        // A kept document that is gone from the bucket is asked for again
+        for document_type in flow.upload_documents {
+            if rejected.contains(document_type) {
+                continue;
+            }
+            let stored = match parent.documents.get(*document_type) {
+                Some(document) => self.minio_service.file_exists(document.document_name.clone()).await.unwrap_or(false),
+                None => false,
             };
+            if !stored {
+                rejected.push(*document_type);
+            }
+        }
 
-            let selfie_filename_existing = match selfie_doc_existing.get("documentName") {
-                Some(name) => name.as_str().unwrap_or(""),
-                None => {
-                    self.metrics.increment("process_submission.error", Some(tags.clone()));
-                    self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1004".to_string(),
-                        cause: "SELFIE_DOES_NOT_EXIST".to_string(),
-                    }]);
-                }
+        let child_id = Uuid::new_v4();
+        let mut documents = HashMap::new();
+        let mut documents_data = SubmissionDocuments::default();
+        let mut carried_over = Vec::new();
+
+        for document_type in flow.upload_documents.iter().filter(|document_type| rejected.contains(document_type)) {
+            let document_uuid = Uuid::new_v4();
+            let document_filename = MinioService::document_key(&parent.tenant_id, child_id, document_type, document_uuid);
+            let expiry = self.url_expiry.upload_expiry(*document_type);
+            let expires_at = UrlExpiryConfig::expires_at(expiry);
+            let policy = self.upload_policy.for_document(*document_type);
+            let upload = match self
+                .minio_service
+                .generate_upload_url(document_filename.clone(), self.url_expiry.signed_for(expiry), &policy)
+                .await
+            {
+                Ok(upload) => upload,
+                Err(e) => return Err(error(&tags, "1001", &e.to_string())),
             };
 
-            // 4. Check if selfie exists in MinIO
-            if !self.minio_service.file_exists(selfie_filename_existing.to_string()).await.unwrap_or(false) {
-                self.metrics.increment("process_submission.error", Some(tags.clone()));
-                self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1004".to_string(),
-                    cause: "SELFIE_DOES_NOT_EXIST".to_string(),
-                }]);
+            documents.insert(
+                document_type.to_string(),
+                Document {
+                    document_url: upload.url,
+                    document_reference: document_uuid.to_string(),
+                    expiry_in_seconds: expiry.as_secs().to_string(),
+                    expires_at,
+                    content_type: policy.content_type,
+                    max_size_in_bytes: policy.max_size_in_bytes,
+                    upload_form: upload.form,
+                },
+            );
+
+            documents_data.insert(*document_type, SubmissionData {
+                document_name: document_filename,
+                document_reference: document_uuid.to_string(),
+                upload_url_expires_at: Some(expires_at),
+                upload: None,
+            });
+        }
+
+        // Copies under the new submission's prefix, so deleting either
+        // submission leaves the other's documents alone. Copies left behind
+        // by a failed resubmission are removed by the bucket cleanup.
+        for (document_type, document) in parent.documents.iter().filter(|(document_type, _)| !rejected.contains(document_type)) {
+            let document_uuid = Uuid::new_v4();
+            let document_filename = MinioService::document_key(&parent.tenant_id, child_id, document_type, document_uuid);
+            if let Err(e) = self.minio_service.copy_file(&document.document_name, document_filename.clone()).await {
+                return Err(error(&tags, "1001", &e.to_string()));
             }
 
-            // 6. Generate URLs for face matching
-            let selfie_url_existing = match self.minio_service.generate_view_url(selfie_filename_existing.to_string()).await {
-                Ok(url) => url,
-                Err(e) => {
-                    self.metrics.increment("process_submission.error", Some(tags.clone()));
-                    self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                    return Err(vec![ApiError {
-                        entity: "HACKATHON_BI_2025".to_string(),
-                        code: "1001".to_string(),
-                        cause: e.to_string(),
-                    }]);
-                }
-            };
+            documents_data.insert(document_type, SubmissionData {
+                document_name: document_filename,
+                document_reference: document_uuid.to_string(),
+                upload_url_expires_at: None,
+                upload: document.upload.clone(),
+            });
+            carried_over.push(document_type.to_string());
+        }
 
-            log::info!("selfie_url_existing: {:?}", selfie_url_existing);
+        // Signals read from a document that is replaced no longer hold
+        let mut dropped_request_fields = Vec::new();
+        if rejected.contains(&DocumentType::Selfie) {
+            dropped_request_fields.push(LIVENESS_REQUEST_FIELD);
+        }
+        if rejected.contains(&DocumentType::Ktp) {
+            dropped_request_fields.extend([NIK_REQUEST_FIELD, BIRTH_DATE_REQUEST_FIELD]);
+        }
 
-            image_url_1 = selfie_url_existing;
-            image_url_2 = selfie_url;
+        if let Err(e) = self
+            .submission_repository
+            .create_resubmission(
+                &mut tx,
+                parent_id,
+                child_id,
+                &Uuid::new_v4().to_string(),
+                attempt,
+                &documents_data,
+                &dropped_request_fields,
+            )
+            .await
+        {
+            return Err(failed(&tags, e.into()));
+        }
 
-        } else {
-            return Err(vec![ApiError {
-                entity: "HACKATHON_BI_2025".to_string(),
-                code: "1004".to_string(),
-                cause: "INVALID_SUBMISSION_TYPE".to_string(),
-            }]);
+        let actor = user_actor(&user_id);
+        let events = [
+            (
+                child_id,
+                EVENT_SUBMISSION_CREATED,
+                json!({
+                    "status": { "from": null, "to": "INITIATED" },
+                    "tenantId": parent.tenant_id,
+                    "submissionType": parent.submission_type,
+                    "parentSubmissionId": submission_id,
+                    "resubmissionAttempt": attempt,
+                    "carriedOverDocuments": carried_over,
+                }),
+            ),
+            (
+                parent_id,
+                EVENT_SUBMISSION_RESUBMITTED,
+                json!({ "childSubmissionId": child_id.to_string(), "resubmissionAttempt": attempt }),
+            ),
+        ];
+        for (event_submission_id, event_type, payload_diff) in events {
+            if let Err(e) = self
+                .submission_event_repository
+                .append_in_tx(&mut tx, event_submission_id, event_type, &actor, payload_diff)
+                .await
+            {
+                return Err(failed(&tags, e.into()));
+            }
+        }
+
+        if let Err(e) = tx.commit().await {
+            return Err(failed(&tags, e.into()));
+        }
+
+        self.metrics.increment("resubmit.success", Some(tags.outcome("success")));
+
+        Ok(ResubmissionResponse {
+            submission_id: child_id.to_string(),
+            parent_submission_id: submission_id,
+            resubmission_attempt: attempt,
+            documents,
+            carried_over_documents: carried_over,
+            submission_token: None,
+            submission_token_expires_at: None,
+        })
+    }
+
+    /// Presigned download URL of a stored document. The access is written to
+    /// `access_log` before the URL is handed out, and no URL is handed out
+    /// when it can't be.
+    pub async fn document_download_url(
+        &self,
+        submission_id: String,
+        document_type: String,
+        accessor: DocumentAccessor,
+        purpose: DocumentAccessPurpose,
+        ip_address: Option<String>,
+        access_log: &DocumentAccessRepository,
+    ) -> Result<DocumentDownloadResponse, AppError> {
+        let tags = MetricTags::endpoint("document_download")
+            .document_type(&document_type)
+            .with("accessor_type", accessor.accessor_type())
+            .with("purpose", purpose.as_str());
+
+        let failed = |error: AppError| {
+            self.metrics.increment("document_download.error", Some(tags.clone().outcome("error")));
+            error
+        };
+        let error = |code: &str, cause: String| failed(AppError::from_code(code, cause));
+
+        let Ok(parsed_document_type) = document_type.parse::<DocumentType>() else {
+            return Err(error("1003", "INVALID_DOCUMENT_TYPE".to_string()));
+        };
+
+        let permitted = match &accessor {
+            DocumentAccessor::User(_) => purpose == DocumentAccessPurpose::SelfService,
+            DocumentAccessor::Admin(_) => purpose.permits_admin(parsed_document_type),
+        };
+        if !permitted {
+            return Err(error(
+                "1018",
+                format!("DOCUMENT_ACCESS_DENIED: {} doesn't cover {} documents", purpose, parsed_document_type),
+            ));
+        }
+
+        let submission = match self.submission_repository.find_submission_for_upload(&submission_id).await {
+            Ok(submission) => submission,
+            Err(sqlx::Error::RowNotFound) => None,
+            Err(e) => return Err(failed(e.into())),
+        };
+        // Someone else's submission is reported as missing rather than forbidden
+        let Some((_, _, _, _, documents)) = submission.filter(|(owner_id, _, _, _, _)| match &accessor {
+            DocumentAccessor::User(user_id) => owner_id == user_id,
+            DocumentAccessor::Admin(_) => true,
+        }) else {
+            return Err(error("1004", "SUBMISSION_NOT_FOUND".to_string()));
+        };
+
+        let Some(document) = documents.get(parsed_document_type) else {
+            return Err(error("1004", "DOCUMENT_NOT_FOUND".to_string()).with_status(StatusCode::NOT_FOUND));
+        };
+        match self.minio_service.file_exists(document.document_name.clone()).await {
+            Ok(true) => {}
+            Ok(false) => return Err(error("1004", "DOCUMENT_NOT_FOUND".to_string()).with_status(StatusCode::NOT_FOUND)),
+            Err(e) => return Err(error("1001", e.to_string())),
+        }
+
+        let submission_uuid = match Uuid::parse_str(&submission_id) {
+            Ok(submission_uuid) => submission_uuid,
+            Err(_) => return Err(error("1004", "SUBMISSION_NOT_FOUND".to_string())),
+        };
+        if let Err(e) = access_log
+            .record(submission_uuid, parsed_document_type, &accessor, purpose, ip_address.as_deref())
+            .await
+        {
+            return Err(failed(e.into()));
+        }
+
+        let expiry = self.url_expiry.download_expiry(parsed_document_type);
+        let expires_at = UrlExpiryConfig::expires_at(expiry);
+        let download_url = match self
+            .minio_service
+            .generate_presigned_url(document.document_name.clone(), self.url_expiry.signed_for(expiry))
+            .await
+        {
+            Ok(url) => url,
+            Err(e) => return Err(error("1001", e.to_string())),
+        };
+
+        self.metrics.increment("document_download.success", Some(tags.outcome("success")));
+
+        Ok(DocumentDownloadResponse {
+            submission_id,
+            document_type: parsed_document_type.to_string(),
+            download_url,
+            expiry_in_seconds: expiry.as_secs().to_string(),
+            expires_at,
+        })
+    }
+
+    /// Presigned URL of an approved submission's report, enqueueing the job
+    /// rendering it the first time it is asked for
+    pub async fn get_report(
+        &self,
+        submission_id: String,
+        user_id: String,
+        queue: &RedisQueue,
+    ) -> Result<SubmissionReportResponse, AppError> {
+        let tags = MetricTags::endpoint("submission_report");
+
+        let failed = |error: AppError| {
+            self.metrics.increment("submission_report.error", Some(tags.clone().outcome("error")));
+            error
+        };
+        let error = |code: &str, cause: String| failed(AppError::from_code(code, cause));
+
+        let summary = match Uuid::parse_str(&submission_id) {
+            Ok(submission_uuid) => match self.submission_repository.find_summary(submission_uuid).await {
+                Ok(summary) => summary,
+                Err(e) => return Err(failed(e.into())),
+            },
+            Err(_) => None,
+        };
+        // Someone else's submission is reported as missing rather than forbidden
+        let Some(summary) = summary.filter(|summary| summary.user_id == user_id) else {
+            return Err(error("1004", "SUBMISSION_NOT_FOUND".to_string()));
+        };
+
+        if summary.status != REPORTABLE_STATUS {
+            return Err(error("1003", format!("SUBMISSION_NOT_APPROVED: submission is {}", summary.status)).with_status(StatusCode::CONFLICT));
+        }
+
+        let report_tags = tags.clone().tenant(&summary.tenant_id).submission_type(&summary.submission_type);
+        let document_name = report_document_name(&summary.tenant_id, summary.submission_id);
+        let exists = match self.minio_service.file_exists(document_name.clone()).await {
+            Ok(exists) => exists,
+            Err(e) => return Err(error("1001", e.to_string())),
+        };
+
+        if exists {
+            let expiry = self.url_expiry.report;
+            let expires_at = UrlExpiryConfig::expires_at(expiry);
+            let report_url = match self
+                .minio_service
+                .generate_presigned_url(document_name, self.url_expiry.signed_for(expiry))
+                .await
+            {
+                Ok(url) => url,
+                Err(e) => return Err(error("1001", e.to_string())),
+            };
+            self.metrics.increment("submission_report.ready", Some(report_tags.outcome("success")));
+
+            return Ok(SubmissionReportResponse {
+                submission_id,
+                report_status: REPORT_STATUS_READY.to_string(),
+                report_url: Some(report_url),
+                expires_in_seconds: Some(expiry.as_secs()),
+                expires_at: Some(expires_at),
+                job_id: None,
+            });
         }
 
-        // 7. Perform face matching
-        let face_match_result = match face_match_service.compare_faces(
-            image_url_1,
-            image_url_2,
+        // Repeated requests while the job is queued are deduplicated by the
+        // queue, also when enqueue dedup is otherwise disabled
+        let mut queue = queue.clone().with_min_dedup_ttl(REPORT_ENQUEUE_DEDUP_TTL);
+        let job = FileUploadJob::report(
             submission_id.clone(),
-        ).await {
-            Ok(result) => result,
-            Err(e) => {
-                self.metrics.increment("process_submission.error", Some(tags.clone()));
-                self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1006".to_string(),
-                    cause: e.to_string(),
-                }]);
-            }
+            document_name,
+            json!({
+                "submissionId": summary.submission_id,
+                "tenantId": summary.tenant_id,
+                "submissionType": summary.submission_type,
+            }),
+        );
+        let job_id = match queue.enqueue_job(&job).await {
+            Ok(EnqueueResult::Enqueued) => Some(job.id),
+            Ok(EnqueueResult::AlreadyEnqueued) => None,
+            Err(e) => return Err(error("1000", e.to_string())),
         };
+        self.metrics.increment("submission_report.generating", Some(report_tags.outcome("success")));
 
-        // 8. Update submission status based on face match result
-        let new_status = if face_match_result.is_match { "APPROVED" } else { "REJECTED" };
-        
-        if let Err(e) = self.submission_repository.update_submission_status(&submission_id, new_status).await {
-            self.metrics.increment("process_submission.error", Some(tags.clone()));
-            self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
-            return Err(vec![ApiError {
-                entity: "HACKATHON_BI_2025".to_string(),
-                code: "1002".to_string(),
-                cause: e.to_string(),
-            }]);
+        Ok(SubmissionReportResponse {
+            submission_id,
+            report_status: REPORT_STATUS_GENERATING.to_string(),
+            report_url: None,
+            expires_in_seconds: None,
+            expires_at: None,
+            job_id,
+        })
+    }
+
+    /// Replace the draft of one of the caller's submissions while it is still
+    /// `INITIATED`
+    pub async fn save_draft(
+        &self,
+        submission_id: String,
+        user_id: String,
+        draft: serde_json::Value,
+        limits: &DraftLimits,
+    ) -> Result<SubmissionDraftResponse, AppError> {
+        let tags = MetricTags::endpoint("save_draft");
+
+        let failed = |error: AppError| {
+            self.metrics.increment("save_draft.error", Some(tags.clone().outcome("error")));
+            error
+        };
+        let error = |code: &str, cause: &str| failed(AppError::from_code(code, cause));
+
+        if let Err(e) = limits.validate(&draft) {
+            return Err(error(e.code(), &e.to_string()));
         }
 
-        // 9. Return response
-        let response = ProcessSubmissionResponse {
-            submission_status: new_status.to_string(),
+        let (owner_id, tenant_id, submission_type, status) =
+            match self.submission_repository.find_submission_for_upload(&submission_id).await {
+                Ok(Some((owner_id, tenant_id, submission_type, status, _))) => (owner_id, tenant_id, submission_type, status),
+                Ok(None) | Err(sqlx::Error::RowNotFound) => return Err(error("1004", "SUBMISSION_NOT_FOUND")),
+                Err(e) => return Err(failed(e.into())),
+            };
+
+        // Someone else's submission looks the same as a missing one
+        if owner_id != user_id {
+            return Err(error("1004", "SUBMISSION_NOT_FOUND"));
+        }
+
+        if status != "INITIATED" {
+            return Err(error("1004", "SUBMISSION_ALREADY_PROCESSED"));
+        }
+
+        // The status is checked again by the update, in case processing
+        // started in between
+        let draft_updated_at = match self.submission_repository.save_draft(&submission_id, draft).await {
+            Ok(Some(draft_updated_at)) => draft_updated_at,
+            Ok(None) => return Err(error("1004", "SUBMISSION_ALREADY_PROCESSED")),
+            Err(e) => return Err(failed(e.into())),
         };
 
-        self.metrics.increment("process_submission.success", Some(tags.clone()));
-        self.metrics.timing("process_submission.duration", start.elapsed(), Some(tags));
+        self.metrics.increment(
+            "save_draft.success",
+            Some(tags.tenant(&tenant_id).submission_type(&submission_type).outcome("success")),
+        );
 
-        Ok(response)
+        Ok(SubmissionDraftResponse {
+            submission_id,
+            draft_updated_at,
+        })
     }
 
+    /// Status of the latest submission of a type for an NFC identifier. The
+    /// draft is only included when `viewer` is the submission's owner.
     pub async fn get_submission_status(
         &self,
         submission_type: SubmissionType,
         nfc_identifier: String,
-    ) -> Result<GetSubmissionStatusResponse, Vec<ApiError>> {
-        let submission_data= match self.submission_repository.find_submission_by_nfc_identifier_and_submission_type(&submission_type.to_string(), &nfc_identifier.chars().take(500).collect::<String>()).await {
+        viewer: Option<&str>,
+    ) -> Result<GetSubmissionStatusResponse, AppError> {
+        let record = match self.submission_repository.find_submission_by_nfc_identifier_and_submission_type(&submission_type.to_string(), &nfc_identifier.chars().take(500).collect::<String>()).await {
             Ok(Some(status)) => status,
             Ok(None) => {
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1004".to_string(),
-                    cause: "SUBMISSION_NOT_FOUND".to_string(),
-                }]);
+                return Err(AppError::from_code("1004", "SUBMISSION_NOT_FOUND"));
             }
             Err(e) => {
-                return Err(vec![ApiError {
-                    entity: "HACKATHON_BI_2025".to_string(),
-                    code: "1002".to_string(),
-                    cause: e.to_string(),
-                }]);
+                return Err(AppError::from(e));
             }
         };
 
-        let mut status: String = String::from("NOT_KYC");
-        if submission_data == "APPROVED" {
-            status = String::from("KYC");
-        }
+        let submission_status = SubmissionFlow::for_type(&submission_type).reported_status(&record.status).to_string();
+        let owner = viewer == Some(record.user_id.as_str());
+        Ok(self.status_response(&submission_type.to_string(), record, submission_status, owner))
+    }
 
-        return Ok(GetSubmissionStatusResponse {
-            submission_status: status,
-        });
+    /// Every submission of a user, newest first, the way the status endpoint
+    /// shows each to its owner. Read-only, for support viewing the account
+    /// as the user sees it.
+    pub async fn submissions_as_seen_by_owner(&self, user_id: &str, limit: i64, offset: i64) -> Result<Vec<UserSubmissionView>, AppError> {
+        let records = self
+            .submission_repository
+            .find_status_records_by_user(user_id, limit, offset)
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                // Types the API no longer knows are reported with their raw status
+                let submission_status = match record.submission_type.parse::<SubmissionType>() {
+                    Ok(submission_type) => SubmissionFlow::for_type(&submission_type).reported_status(&record.status.status).to_string(),
+                    Err(()) => record.status.status.clone(),
+                };
+                let created_at = record.status.created_at;
+                let status = self.status_response(&record.submission_type, record.status, submission_status, true);
+                UserSubmissionView {
+                    submission_id: record.submission_id,
+                    submission_type: record.submission_type,
+                    created_at,
+                    status,
+                }
+            })
+            .collect())
+    }
+
+    /// What the status endpoint reports about a submission; the draft only
+    /// goes to its owner, deadlines only while the user still has to upload
+    fn status_response(&self, submission_type: &str, record: SubmissionStatusRecord, submission_status: String, owner: bool) -> GetSubmissionStatusResponse {
+        let (draft, draft_updated_at) = if owner {
+            (record.draft, record.draft_updated_at)
+        } else {
+            (None, None)
+        };
+
+        let pending = record.status == "INITIATED";
+        let expires_at = pending
+            .then(|| self.ttl_policy.deadline(submission_type, record.created_at))
+            .flatten();
+        let uploads_expire_at = record
+            .documents
+            .iter()
+            .filter(|(document_type, document)| pending && *document_type != DocumentType::Nfc && document.upload.is_none())
+            .map(|(document_type, document)| {
+                // Rows from before the expiry was kept got URLs at creation
+                let url_expires_at = document.upload_url_expires_at.unwrap_or_else(|| {
+                    record.created_at
+                        + chrono::Duration::from_std(self.url_expiry.upload_expiry(document_type)).unwrap_or_default()
+                });
+                let upload_expires_at = match expires_at {
+                    Some(expires_at) => url_expires_at.min(expires_at),
+                    None => url_expires_at,
+                };
+                (document_type.to_string(), upload_expires_at)
+            })
+            .collect();
+
+        GetSubmissionStatusResponse {
+            submission_status,
+            decision_reasons: record.decision_reasons,
+            draft,
+            draft_updated_at,
+            expires_at,
+            uploads_expire_at,
+            documents: record
+                .documents
+                .iter()
+                .map(|(document_type, document)| (document_type.to_string(), document.upload.clone()))
+                .collect(),
+        }
     }
 
 }
@@ -0,0 +1,169 @@
+use actix_web::{HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::errors::ErrorKind;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    commons::{
+        app_error::AppError,
+        redis_connection::{RedisConnection, RedisTopology},
+    },
+    services::{face_match_limiter::RETRY_LATER_CODE, key_provider::KeyProvider},
+};
+
+/// Header the submission token is sent back in, and the next one handed out
+pub const SUBMISSION_TOKEN_HEADER: &str = "x-submission-token";
+
+/// Header carrying when the next token handed out expires
+pub const SUBMISSION_TOKEN_EXPIRES_AT_HEADER: &str = "x-submission-token-expires-at";
+
+pub const SUBMISSION_TOKEN_CODE: &str = "1023";
+
+/// `typ` of submission tokens, which bearer tokens never have
+const TOKEN_TYPE: &str = "submission";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SubmissionTokenClaims {
+    /// The submission the token is good for
+    sub: String,
+    /// The user it was issued to
+    uid: i32,
+    typ: String,
+    exp: i64,
+    /// Marked as used in Redis the first time the token is redeemed
+    jti: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct IssuedSubmissionToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Short-lived tokens scoped to one submission, handed out with its upload
+/// URLs and required on the calls that upload or process its documents. A
+/// leaked bearer token alone can't act on the user's submissions.
+///
+/// They are signed with the JWT keys but can't stand in for a bearer token:
+/// the subject is the submission and there is no session. Each token is good
+/// for one call, which hands out the next one.
+#[derive(Clone)]
+pub struct SubmissionTokens {
+    keys: KeyProvider,
+    connection_manager: RedisConnection,
+    ttl: chrono::Duration,
+    required: bool,
+}
+
+impl SubmissionTokens {
+    pub fn new(keys: KeyProvider, connection_manager: RedisConnection, ttl: chrono::Duration, required: bool) -> Self {
+        Self {
+            keys,
+            connection_manager,
+            ttl,
+            required,
+        }
+    }
+
+    /// Lifetime from `SUBMISSION_TOKEN_TTL_SECONDS` (3600 by default).
+    /// `SUBMISSION_TOKEN_REQUIRED=false` lets calls without a token through
+    /// while clients move over; tokens that are sent are still checked.
+    pub async fn from_env(keys: KeyProvider, redis: &RedisTopology) -> anyhow::Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        let ttl_seconds = var("SUBMISSION_TOKEN_TTL_SECONDS")
+            .map(|v| v.parse::<i64>().context("SUBMISSION_TOKEN_TTL_SECONDS must be a number"))
+            .transpose()?
+            .unwrap_or(3600);
+        let required = var("SUBMISSION_TOKEN_REQUIRED").as_deref() != Some("false");
+
+        let connection_manager = redis.connect().await.context("Failed to connect to Redis for submission tokens")?;
+
+        Ok(Self::new(keys, connection_manager, chrono::Duration::seconds(ttl_seconds), required))
+    }
+
+    pub fn issue(&self, submission_id: &str, user_id: i32) -> Result<IssuedSubmissionToken, AppError> {
+        let expires_at = Utc::now() + self.ttl;
+        let claims = SubmissionTokenClaims {
+            sub: submission_id.to_string(),
+            uid: user_id,
+            typ: TOKEN_TYPE.to_string(),
+            exp: expires_at.timestamp(),
+            jti: Uuid::new_v4().to_string(),
+        };
+
+        match self.keys.sign(&claims) {
+            Ok(token) => Ok(IssuedSubmissionToken { token, expires_at }),
+            Err(e) => {
+                log::error!("Failed to sign submission token: {}", e);
+                Err(AppError::from_code("1000", "SYSTEM_ERROR"))
+            }
+        }
+    }
+
+    /// Let the call through if it carries an unused token for this submission
+    /// and user, using it up. The token to send on the next call comes back,
+    /// or None when no token was sent and none is required.
+    pub async fn redeem(&self, req: &HttpRequest, submission_id: &str, user_id: i32) -> Result<Option<IssuedSubmissionToken>, AppError> {
+        let error = |cause: &str| AppError::from_code(SUBMISSION_TOKEN_CODE, cause);
+
+        let token = req
+            .headers()
+            .get(SUBMISSION_TOKEN_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty());
+        let Some(token) = token else {
+            return if self.required { Err(error("SUBMISSION_TOKEN_REQUIRED")) } else { Ok(None) };
+        };
+
+        let claims = match self.keys.validate_as::<SubmissionTokenClaims>(token) {
+            Ok(claims) => claims,
+            Err(e) if matches!(e.kind(), ErrorKind::ExpiredSignature) => return Err(error("SUBMISSION_TOKEN_EXPIRED")),
+            Err(_) => return Err(error("SUBMISSION_TOKEN_INVALID")),
+        };
+
+        if claims.typ != TOKEN_TYPE || claims.sub != submission_id || claims.uid != user_id {
+            return Err(error("SUBMISSION_TOKEN_INVALID"));
+        }
+
+        // Remembered until the token would have expired anyway, validation
+        // leeway included
+        let remaining_seconds = (claims.exp - Utc::now().timestamp()).max(0) + 60;
+        let mut conn = self.connection_manager.clone();
+        let unused = redis::cmd("SET")
+            .arg(format!("submission_token:{}", claims.jti))
+            .arg(submission_id)
+            .arg("NX")
+            .arg("EX")
+            .arg(remaining_seconds)
+            .query_async::<_, Option<String>>(&mut conn)
+            .await;
+        match unused {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(error("SUBMISSION_TOKEN_USED")),
+            Err(e) => {
+                log::error!("Failed to redeem submission token: {}", e);
+                return Err(AppError::from_code(RETRY_LATER_CODE, "RETRY_LATER"));
+            }
+        }
+
+        self.issue(submission_id, user_id).map(Some)
+    }
+
+    /// Hand the next token to the client in the response headers
+    pub fn attach(response: &mut HttpResponse, token: Option<IssuedSubmissionToken>) {
+        let Some(token) = token else {
+            return;
+        };
+
+        let headers = response.headers_mut();
+        if let Ok(value) = token.token.parse() {
+            headers.insert(actix_web::http::header::HeaderName::from_static(SUBMISSION_TOKEN_HEADER), value);
+        }
+        if let Ok(value) = token.expires_at.to_rfc3339().parse() {
+            headers.insert(actix_web::http::header::HeaderName::from_static(SUBMISSION_TOKEN_EXPIRES_AT_HEADER), value);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use std::{collections::HashMap, time::Duration};
+
+/// How long a submission may stay `INITIATED` before the user is expected
+/// to have uploaded everything, by submission type
+#[derive(Debug, Clone, Default)]
+pub struct SubmissionTtlPolicy {
+    ttls: HashMap<String, Duration>,
+    default_ttl: Option<Duration>,
+}
+
+impl SubmissionTtlPolicy {
+    pub fn new(ttls: HashMap<String, Duration>, default_ttl: Option<Duration>) -> Self {
+        Self { ttls, default_ttl }
+    }
+
+    /// TTLs come from `SUBMISSION_TTLS` ("KYC=86400,ON_DEMAND=3600") and
+    /// `SUBMISSION_TTL_SECONDS` for the other types; 0 or unset means no
+    /// deadline
+    pub fn from_env() -> anyhow::Result<Self> {
+        let ttls = std::env::var("SUBMISSION_TTLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (submission_type, seconds) = entry.split_once('=').ok_or_else(|| {
+                    anyhow::anyhow!("SUBMISSION_TTLS entries must look like <submission type>=<seconds>, got {}", entry)
+                })?;
+                let seconds = seconds
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| anyhow::anyhow!("SUBMISSION_TTLS values must be seconds, got {}", entry))?;
+                Ok((submission_type.trim().to_string(), Duration::from_secs(seconds)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let default_ttl = std::env::var("SUBMISSION_TTL_SECONDS")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("SUBMISSION_TTL_SECONDS must be a number"))?
+            .map(Duration::from_secs);
+
+        Ok(Self::new(ttls, default_ttl))
+    }
+
+    fn ttl_for(&self, submission_type: &str) -> Option<Duration> {
+        self.ttls
+            .get(submission_type)
+            .copied()
+            .or(self.default_ttl)
+            .filter(|ttl| !ttl.is_zero())
+    }
+
+    /// When a submission of the type created at `created_at` is due
+    pub fn deadline(&self, submission_type: &str, created_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let ttl = chrono::Duration::from_std(self.ttl_for(submission_type)?).ok()?;
+        created_at.checked_add_signed(ttl)
+    }
+}
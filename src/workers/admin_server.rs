@@ -0,0 +1,237 @@
+use actix_web::{dev::Server, http::StatusCode, middleware::from_fn, web, App, HttpResponse, HttpServer};
+use serde::Serialize;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::{info, warn};
+
+use crate::{
+    admin::{
+        self,
+        admin_auth::{require_admin_key, AdminConfig},
+    },
+    commons::app_error::error_response,
+    models::user::ApiResponse,
+    workers::{
+        heartbeat::Heartbeat,
+        main_worker::MainWorker,
+        metrics::{WorkerMetricsDelta, WorkerMetricsSnapshot},
+        RedisQueue,
+    },
+};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthResponse {
+    pub status: String,
+    pub in_flight_jobs: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueDepthResponse {
+    pub queue: String,
+    pub queue_depth: u64,
+    /// Retries waiting out their backoff
+    pub delayed_depth: u64,
+    /// Jobs consumers have dequeued but not finished with
+    pub processing_depth: u64,
+    pub dlq: String,
+    pub dlq_depth: u64,
+    /// Entries set aside because they couldn't be read as jobs
+    pub malformed: String,
+    pub malformed_depth: u64,
+}
+
+/// Where `/metrics/delta` counts from; the counters themselves are never
+/// reset so Prometheus keeps seeing them grow
+pub struct MetricsBaseline(Mutex<WorkerMetricsSnapshot>);
+
+impl MetricsBaseline {
+    fn lock(&self) -> MutexGuard<'_, WorkerMetricsSnapshot> {
+        match self.0.lock() {
+            Ok(baseline) => baseline,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DrainResponse {
+    pub status: String,
+    pub in_flight_jobs: usize,
+}
+
+/// Start the worker-mode admin server on WORKER_ADMIN_HOST:WORKER_ADMIN_PORT.
+/// It runs on its own port so it never shares a listener with the API. Reads
+/// are open; routes changing the worker need the admin API key.
+pub fn start(main_worker: Arc<MainWorker>, admin_config: AdminConfig) -> std::io::Result<Server> {
+    let config = main_worker.config();
+    let bind_address = (config.worker_admin_host.clone(), config.worker_admin_port);
+    let baseline = web::Data::new(MetricsBaseline(Mutex::new(main_worker.metrics().snapshot())));
+    let main_worker = web::Data::from(main_worker);
+    let admin_config = web::Data::new(admin_config);
+
+    info!("Starting worker admin server on {}:{}", bind_address.0, bind_address.1);
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(main_worker.clone())
+            .app_data(baseline.clone())
+            .app_data(admin_config.clone())
+            .route("/healthz", web::get().to(healthz))
+            .route("/metrics", web::get().to(metrics))
+            .route("/metrics/snapshot", web::get().to(metrics_snapshot))
+            .route("/metrics/delta", web::get().to(metrics_delta))
+            .route("/queues", web::get().to(queues))
+            .route("/heartbeats", web::get().to(heartbeats))
+            .service(admin::log_level_controller::get_log_level)
+            // Last, as it takes every path the routes above don't
+            .service(
+                web::scope("")
+                    .wrap(from_fn(require_admin_key))
+                    .route("/metrics/delta/reset", web::post().to(reset_metrics_delta))
+                    .route("/drain", web::post().to(drain))
+                    .service(admin::log_level_controller::set_log_level),
+            )
+    })
+    .workers(1)
+    .disable_signals()
+    .bind(bind_address)?
+    .run();
+
+    Ok(server)
+}
+
+async fn healthz(main_worker: web::Data<MainWorker>) -> HttpResponse {
+    let status = if main_worker.is_draining() { "DRAINING" } else { "OK" };
+
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(HealthResponse {
+            status: status.to_string(),
+            in_flight_jobs: main_worker.heartbeats().in_flight(),
+        }),
+        errors: None,
+    })
+}
+
+async fn metrics(main_worker: web::Data<MainWorker>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(main_worker.metrics().render_prometheus())
+}
+
+async fn metrics_snapshot(main_worker: web::Data<MainWorker>) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(main_worker.metrics().snapshot()),
+        errors: None,
+    })
+}
+
+async fn metrics_delta(main_worker: web::Data<MainWorker>, baseline: web::Data<MetricsBaseline>) -> HttpResponse {
+    let current = main_worker.metrics().snapshot();
+    let delta = current.delta_since(&baseline.lock());
+
+    HttpResponse::Ok().json(ApiResponse::<WorkerMetricsDelta> {
+        success: true,
+        data: Some(delta),
+        errors: None,
+    })
+}
+
+/// The delta up to now, which then becomes where the next one starts
+async fn reset_metrics_delta(main_worker: web::Data<MainWorker>, baseline: web::Data<MetricsBaseline>) -> HttpResponse {
+    let current = main_worker.metrics().snapshot();
+    let mut baseline = baseline.lock();
+    let delta = current.delta_since(&baseline);
+    *baseline = current;
+
+    HttpResponse::Ok().json(ApiResponse::<WorkerMetricsDelta> {
+        success: true,
+        data: Some(delta),
+        errors: None,
+    })
+}
+
+async fn queues(main_worker: web::Data<MainWorker>) -> HttpResponse {
+    let config = main_worker.config();
+
+    let depths = async {
+        let mut queue = match main_worker.redis() {
+            Some(redis) => RedisQueue::from_connections(
+                redis.shared(),
+                redis.shared(),
+                config.worker_upload_file_queue.clone(),
+                config.worker_upload_file_dlq.clone(),
+            ),
+            None => {
+                RedisQueue::new(
+                    &config.redis,
+                    config.worker_upload_file_queue.clone(),
+                    config.worker_upload_file_dlq.clone(),
+                )
+                .await?
+            }
+        };
+        let queue_depth = queue.get_queue_length().await?;
+        let delayed_depth = queue.get_delayed_length().await?;
+        let processing_depth = queue.get_processing_length().await?;
+        let dlq_depth = queue.get_dlq_length().await?;
+        let malformed_depth = queue.get_malformed_length().await?;
+        Ok::<_, crate::workers::WorkerError>((
+            queue_depth,
+            delayed_depth,
+            processing_depth,
+            dlq_depth,
+            queue.malformed_name(),
+            malformed_depth,
+        ))
+    };
+
+    match depths.await {
+        Ok((queue_depth, delayed_depth, processing_depth, dlq_depth, malformed, malformed_depth)) => {
+            main_worker.metrics().update_queue_depth(queue_depth, dlq_depth);
+
+            HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                data: Some(QueueDepthResponse {
+                    queue: config.worker_upload_file_queue.clone(),
+                    queue_depth,
+                    delayed_depth,
+                    processing_depth,
+                    dlq: config.worker_upload_file_dlq.clone(),
+                    dlq_depth,
+                    malformed,
+                    malformed_depth,
+                }),
+                errors: None,
+            })
+        }
+        Err(e) => {
+            warn!("Failed to read queue depths: {}", e);
+            error_response(StatusCode::SERVICE_UNAVAILABLE, "1000", "SYSTEM_ERROR".to_string())
+        }
+    }
+}
+
+async fn heartbeats(main_worker: web::Data<MainWorker>) -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse::<Vec<Heartbeat>> {
+        success: true,
+        data: Some(main_worker.heartbeats().snapshot()),
+        errors: None,
+    })
+}
+
+async fn drain(main_worker: web::Data<MainWorker>) -> HttpResponse {
+    main_worker.drain();
+
+    HttpResponse::Accepted().json(ApiResponse {
+        success: true,
+        data: Some(DrainResponse {
+            status: "DRAINING".to_string(),
+            in_flight_jobs: main_worker.heartbeats().in_flight(),
+        }),
+        errors: None,
+    })
+}
@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    commons::minio_service::MinioService,
+    submissions::submission_repository::SubmissionRepository,
+    workers::{FileUploadJob, RedisQueue, WorkerError, WorkerResult},
+};
+
+/// Jobs pushed to Redis per pipeline
+pub const BACKFILL_BATCH_SIZE: usize = 500;
+
+/// How long a backfill run stays readable after its last update
+pub const BACKFILL_RUN_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Backfilled jobs fetch documents through presigned URLs, which have to
+/// outlive the queue they wait in
+const BACKFILL_DOCUMENT_URL_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub const BACKFILL_STATUS_RUNNING: &str = "RUNNING";
+pub const BACKFILL_STATUS_COMPLETED: &str = "COMPLETED";
+pub const BACKFILL_STATUS_FAILED: &str = "FAILED";
+
+pub fn backfill_run_key(run_id: Uuid) -> String {
+    format!("backfill_run:{}", run_id)
+}
+
+/// Which submissions a backfill re-processes. Missing bounds match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillFilter {
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillRun {
+    pub id: Uuid,
+    pub status: String,
+    pub filter: BackfillFilter,
+    pub submissions_matched: u64,
+    pub jobs_enqueued: u64,
+    pub jobs_skipped: u64,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl BackfillRun {
+    pub fn new(filter: BackfillFilter) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            status: BACKFILL_STATUS_RUNNING.to_string(),
+            filter,
+            submissions_matched: 0,
+            jobs_enqueued: 0,
+            jobs_skipped: 0,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+        }
+    }
+}
+
+/// Stream the submissions matching the run's filter and enqueue an upload job
+/// per stored document, publishing the run's counters after every batch
+pub async fn run(mut run: BackfillRun, repository: SubmissionRepository, minio_service: MinioService, mut queue: RedisQueue) {
+    info!("Backfill run {} started", run.id);
+
+    let result = enqueue_matching(&mut run, &repository, &minio_service, &mut queue).await;
+
+    run.finished_at = Some(Utc::now());
+    match result {
+        Ok(()) => {
+            run.status = BACKFILL_STATUS_COMPLETED.to_string();
+            info!(
+                "Backfill run {} completed: {} submissions, {} jobs enqueued, {} skipped",
+                run.id, run.submissions_matched, run.jobs_enqueued, run.jobs_skipped
+            );
+        }
+        Err(e) => {
+            run.status = BACKFILL_STATUS_FAILED.to_string();
+            run.error = Some(e.to_string());
+            error!("Backfill run {} failed: {}", run.id, e);
+        }
+    }
+
+    if let Err(e) = queue.save_backfill_run(&run).await {
+        error!("Failed to record the outcome of backfill run {}: {}", run.id, e);
+    }
+}
+
+async fn enqueue_matching(
+    run: &mut BackfillRun,
+    repository: &SubmissionRepository,
+    minio_service: &MinioService,
+    queue: &mut RedisQueue,
+) -> WorkerResult<()> {
+    let mut submissions = repository.stream_for_backfill(
+        run.filter.created_from,
+        run.filter.created_to,
+        run.filter.status.clone(),
+    );
+    let mut batch = Vec::with_capacity(BACKFILL_BATCH_SIZE);
+
+    while let Some(submission) = submissions.next().await {
+        let (submission_id, tenant_id, documents) = submission?;
+        run.submissions_matched += 1;
+
+        for (document_type, document) in documents.iter() {
+            let document_url = minio_service
+                .generate_presigned_url(document.document_name.clone(), BACKFILL_DOCUMENT_URL_EXPIRY)
+                .await
+                .map_err(|e| WorkerError::Storage(e.to_string()))?;
+
+            batch.push(FileUploadJob::new(
+                submission_id.to_string(),
+                document_url,
+                document.document_name.clone(),
+                document_type.to_string(),
+                serde_json::json!({
+                    "submissionId": submission_id,
+                    "tenantId": tenant_id,
+                    "backfillRunId": run.id,
+                }),
+            ));
+        }
+
+        if batch.len() >= BACKFILL_BATCH_SIZE {
+            flush(run, queue, &mut batch).await?;
+        }
+    }
+
+    flush(run, queue, &mut batch).await
+}
+
+async fn flush(run: &mut BackfillRun, queue: &mut RedisQueue, batch: &mut Vec<FileUploadJob>) -> WorkerResult<()> {
+    let enqueued = queue.enqueue_jobs(batch).await?;
+    run.jobs_enqueued += enqueued as u64;
+    run.jobs_skipped += (batch.len() - enqueued) as u64;
+    batch.clear();
+
+    queue.save_backfill_run(run).await
+}
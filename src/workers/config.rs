@@ -1,13 +1,43 @@
 use std::env;
 use std::time::Duration;
 
+use crate::commons::redis_connection::RedisTopology;
+use crate::workers::retry_policy::RetryPolicies;
+use crate::workers::upload_source::UploadSourcePolicy;
+
+/// How idle consumers wait for jobs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollStrategy {
+    /// BRPOP for up to the consumer wait interval
+    Blocking,
+    /// Sleep on the queue's wake-up channel, which every enqueue publishes to,
+    /// falling back to BRPOP while the subscription is down
+    Pubsub,
+}
+
+impl PollStrategy {
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "blocking" => Ok(Self::Blocking),
+            "pubsub" => Ok(Self::Pubsub),
+            other => anyhow::bail!("WORKER_POLL_STRATEGY must be blocking or pubsub, got {}", other),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WorkerConfig {
     // Main worker pool configuration
     pub background_worker_thread_enabled: bool,
     pub background_worker_consumer_thread_count: usize,
     pub worker_consumer_wait_interval: Duration,
-    pub worker_consumer_max_retry: u32,
+    pub poll_strategy: PollStrategy,
+    /// Longest a consumer sleeps on the wake-up channel before it checks
+    /// for due retries and expired leases again
+    pub pubsub_max_idle: Duration,
+    /// Retries, backoff and DLQ retention per job kind. `WORKER_CONSUMER_MAX_RETRY`
+    /// is the attempt budget of kinds without their own.
+    pub retry_policies: RetryPolicies,
 
     // DLQ worker pool configuration
     pub file_upload_worker_dlq_thread_enabled: bool,
@@ -15,20 +45,75 @@ pub struct WorkerConfig {
     pub file_upload_worker_dlq_wait_interval: Duration,
 
     // Redis configuration
-    pub redis_url: String,
+    /// Single node, sentinel or cluster, from `REDIS_URL`
+    pub redis: RedisTopology,
     pub worker_upload_file_queue: String,
     pub worker_upload_file_dlq: String,
+    pub redis_connection_max_retries: usize,
+    pub redis_connection_retry_factor: Duration,
+    /// How long an enqueued job's idempotency key is held; None disables dedup
+    pub enqueue_dedup_ttl: Option<Duration>,
 
     // Lock configuration
     pub lock_timeout: Duration,
     pub lock_retry_interval: Duration,
 
+    /// How long a job may run once it holds its lock before it is cancelled
+    pub job_timeout: Duration,
+
+    /// How long a dequeued job may stay on its consumer's processing list
+    /// before it is returned to the queue for another consumer
+    pub visibility_timeout: Duration,
+
     // Shutdown configuration
     pub graceful_shutdown_timeout: Duration,
+
+    /// Store downscaled, upright variants of KTP and selfie uploads
+    pub image_preprocessing_enabled: bool,
+
+    /// Schemes and hosts upload jobs may fetch their document from
+    pub upload_sources: UploadSourcePolicy,
+
+    // Admin server configuration (worker mode only)
+    pub worker_admin_host: String,
+    pub worker_admin_port: u16,
 }
 
 impl WorkerConfig {
     pub fn from_env() -> anyhow::Result<Self> {
+        let worker_consumer_max_retry = env::var("WORKER_CONSUMER_MAX_RETRY")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse()?;
+
+        let lock_timeout = Duration::from_secs(
+            env::var("WORKER_LOCK_TIMEOUT_SECONDS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()?
+        );
+        let job_timeout = Duration::from_secs(
+            env::var("WORKER_JOB_TIMEOUT_SECONDS")
+                .unwrap_or_else(|_| "120".to_string())
+                .parse()?
+        );
+        // Long enough by default for a job to wait for its lock and run out its timeout
+        let visibility_timeout = match env::var("WORKER_VISIBILITY_TIMEOUT_SECONDS").ok().filter(|v| !v.is_empty()) {
+            Some(seconds) => Duration::from_secs(seconds.parse()?),
+            None => lock_timeout + job_timeout + Duration::from_secs(60),
+        };
+        if visibility_timeout <= job_timeout {
+            anyhow::bail!("WORKER_VISIBILITY_TIMEOUT_SECONDS must be longer than WORKER_JOB_TIMEOUT_SECONDS");
+        }
+
+        let worker_consumer_wait_interval = Duration::from_millis(
+            env::var("WORKER_CONSUMER_WAIT_INTERVAL_IN_MILLISECONDS")
+                .unwrap_or_else(|_| "5000".to_string())
+                .parse()?
+        );
+        let pubsub_max_idle = match env::var("WORKER_PUBSUB_MAX_IDLE_MILLISECONDS").ok().filter(|v| !v.is_empty()) {
+            Some(milliseconds) => Duration::from_millis(milliseconds.parse()?),
+            None => worker_consumer_wait_interval,
+        };
+
         Ok(Self {
             background_worker_thread_enabled: env::var("BACKGROUND_WORKER_THREAD_ENABLED")
                 .unwrap_or_else(|_| "false".to_string())
@@ -38,15 +123,15 @@ impl WorkerConfig {
                 .unwrap_or_else(|_| "1".to_string())
                 .parse()?,
 
-            worker_consumer_wait_interval: Duration::from_millis(
-                env::var("WORKER_CONSUMER_WAIT_INTERVAL_IN_MILLISECONDS")
-                    .unwrap_or_else(|_| "5000".to_string())
-                    .parse()?
-            ),
+            worker_consumer_wait_interval,
 
-            worker_consumer_max_retry: env::var("WORKER_CONSUMER_MAX_RETRY")
-                .unwrap_or_else(|_| "3".to_string())
-                .parse()?,
+            poll_strategy: PollStrategy::parse(
+                &env::var("WORKER_POLL_STRATEGY").unwrap_or_else(|_| "blocking".to_string()),
+            )?,
+
+            pubsub_max_idle,
+
+            retry_policies: RetryPolicies::from_env(worker_consumer_max_retry)?,
 
             file_upload_worker_dlq_thread_enabled: env::var("FILE_UPLOAD_WORKER_DLQ_THREAD_ENABLED")
                 .unwrap_or_else(|_| "false".to_string())
@@ -62,8 +147,9 @@ impl WorkerConfig {
                     .parse()?
             ),
 
-            redis_url: env::var("REDIS_URL")
-                .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
+            redis: RedisTopology::parse(
+                &env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_string()),
+            )?,
 
             worker_upload_file_queue: env::var("WORKER_UPLOAD_FILE_QUEUE")
                 .unwrap_or_else(|_| "upload_file_queue".to_string()),
@@ -71,23 +157,53 @@ impl WorkerConfig {
             worker_upload_file_dlq: env::var("WORKER_UPLOAD_FILE_DLQ")
                 .unwrap_or_else(|_| "upload_file_dlq".to_string()),
 
-            lock_timeout: Duration::from_secs(
-                env::var("WORKER_LOCK_TIMEOUT_SECONDS")
-                    .unwrap_or_else(|_| "300".to_string())
+            redis_connection_max_retries: env::var("REDIS_CONNECTION_MAX_RETRIES")
+                .unwrap_or_else(|_| "6".to_string())
+                .parse()?,
+
+            redis_connection_retry_factor: Duration::from_millis(
+                env::var("REDIS_CONNECTION_RETRY_FACTOR_MILLISECONDS")
+                    .unwrap_or_else(|_| "100".to_string())
                     .parse()?
             ),
 
+            enqueue_dedup_ttl: match env::var("WORKER_ENQUEUE_DEDUP_TTL_SECONDS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse::<u64>()?
+            {
+                0 => None,
+                seconds => Some(Duration::from_secs(seconds)),
+            },
+
+            lock_timeout,
+
             lock_retry_interval: Duration::from_millis(
                 env::var("WORKER_LOCK_RETRY_INTERVAL_MILLISECONDS")
                     .unwrap_or_else(|_| "100".to_string())
                     .parse()?
             ),
 
+            job_timeout,
+            visibility_timeout,
+
             graceful_shutdown_timeout: Duration::from_secs(
                 env::var("WORKER_GRACEFUL_SHUTDOWN_TIMEOUT_SECONDS")
                     .unwrap_or_else(|_| "30".to_string())
                     .parse()?
             ),
+
+            image_preprocessing_enabled: env::var("IMAGE_PREPROCESSING_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()?,
+
+            upload_sources: UploadSourcePolicy::from_env()?,
+
+            worker_admin_host: env::var("WORKER_ADMIN_HOST")
+                .unwrap_or_else(|_| "127.0.0.1".to_string()),
+
+            worker_admin_port: env::var("WORKER_ADMIN_PORT")
+                .unwrap_or_else(|_| "9091".to_string())
+                .parse()?,
         })
     }
 }
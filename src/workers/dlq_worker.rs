@@ -1,119 +1,109 @@
 use crate::workers::{
-    FileUploadJob, RedisQueue, WorkerConfig, WorkerError, WorkerResult, WorkerMetrics
+    FailedJobRepository, FileUploadJob, JobStatus, RedisQueue, WorkerConfig, WorkerError, WorkerResult, WorkerMetrics
 };
-use redis::aio::ConnectionManager;
-use redis::Client;
+use crate::workers::error::ErrorClass;
+use crate::commons::telemetry;
+use crate::workers::heartbeat::{ConsumerState, WorkerHeartbeats};
+use crate::workers::redis_connections::RedisConnections;
+use crate::workers::supervisor::WorkerTasks;
+use chrono::Utc;
+use crate::commons::redis_connection::RedisConnection;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
 use std::time::Instant;
-use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tracing::{debug, error, info, instrument, warn};
 
 /// DlqWorker processes failed jobs from the Dead Letter Queue
 pub struct DlqWorker {
     config: WorkerConfig,
-    redis_client: Client,
+    redis: RedisConnections,
     shutdown_signal: Arc<AtomicBool>,
     metrics: Arc<WorkerMetrics>,
+    heartbeats: Arc<WorkerHeartbeats>,
+    failed_jobs: FailedJobRepository,
 }
 
 impl DlqWorker {
-    pub fn new(config: WorkerConfig, shutdown_signal: Arc<AtomicBool>, metrics: Arc<WorkerMetrics>) -> WorkerResult<Self> {
-        let redis_client = Client::open(&config.redis_url[..])?;
-
+    pub fn new(
+        config: WorkerConfig,
+        redis: RedisConnections,
+        shutdown_signal: Arc<AtomicBool>,
+        metrics: Arc<WorkerMetrics>,
+        heartbeats: Arc<WorkerHeartbeats>,
+        failed_jobs: FailedJobRepository,
+    ) -> WorkerResult<Self> {
         Ok(Self {
             config,
-            redis_client,
+            redis,
             shutdown_signal,
             metrics,
+            heartbeats,
+            failed_jobs,
         })
     }
 
-    /// Start the DLQ worker pool with the configured number of threads
-    pub async fn start(&self) -> WorkerResult<()> {
+    /// Start the DLQ worker pool with the configured number of threads, its
+    /// consumers supervised in `tasks`
+    pub async fn start(&self, tasks: &mut WorkerTasks) -> WorkerResult<()> {
         info!(
             "Starting DlqWorker with {} threads",
             self.config.file_upload_worker_dlq_thread_count
         );
 
-        let (tx, mut rx) = mpsc::channel(100);
-
-        // Spawn consumer threads
-        let mut handles = Vec::new();
         for i in 0..self.config.file_upload_worker_dlq_thread_count {
             let worker_id = format!("dlq-worker-{}", i);
-            let thread_config = self.config.clone();
-            let thread_client = self.redis_client.clone();
-            let thread_shutdown = self.shutdown_signal.clone();
-            let thread_tx = tx.clone();
-            let thread_metrics = self.metrics.clone();
+            let config = self.config.clone();
+            let redis = self.redis.clone();
+            let shutdown_signal = self.shutdown_signal.clone();
+            let metrics = self.metrics.clone();
+            let heartbeats = self.heartbeats.clone();
+            let failed_jobs = self.failed_jobs.clone();
 
-            let handle = tokio::spawn(async move {
-                let result = Self::run_consumer(
-                    worker_id,
-                    thread_config,
-                    thread_client,
-                    thread_shutdown,
-                    thread_tx,
-                    thread_metrics,
+            tasks.spawn_consumer(worker_id.clone(), move || {
+                Self::run_consumer(
+                    worker_id.clone(),
+                    config.clone(),
+                    redis.clone(),
+                    shutdown_signal.clone(),
+                    metrics.clone(),
+                    heartbeats.clone(),
+                    failed_jobs.clone(),
                 )
-                .await;
-
-                if let Err(e) = result {
-                    error!("DLQ worker thread exited with error: {}", e);
-                }
             });
-
-            handles.push(handle);
         }
 
-        // Drop the original sender so the channel can close when all senders are done
-        drop(tx);
-
-        // Wait for shutdown signal
-        tokio::spawn(async move {
-            // Wait for all threads to report completion
-            let mut completed_count = 0;
-            while let Some(worker_id) = rx.recv().await {
-                info!("DLQ Worker {} completed graceful shutdown", worker_id);
-                completed_count += 1;
-            }
-
-            info!(
-                "All {} DLQ worker threads completed graceful shutdown",
-                completed_count
-            );
-        });
-
         Ok(())
     }
 
-    #[instrument(skip(config, client, shutdown_signal, completion_tx, metrics), fields(worker_id = %worker_id))]
+    #[instrument(skip(config, redis, shutdown_signal, metrics, heartbeats, failed_jobs), fields(worker_id = %worker_id))]
     async fn run_consumer(
         worker_id: String,
         config: WorkerConfig,
-        client: Client,
+        redis: RedisConnections,
         shutdown_signal: Arc<AtomicBool>,
-        completion_tx: mpsc::Sender<String>,
         metrics: Arc<WorkerMetrics>,
+        heartbeats: Arc<WorkerHeartbeats>,
+        failed_jobs: FailedJobRepository,
     ) -> WorkerResult<()> {
         info!("DLQ worker thread started");
         
-        // Create Redis connection
-        let conn_manager = ConnectionManager::new(client).await?;
+        // Locks and enqueues go over the shared connection; dequeues block on our own
+        let conn_manager = redis.shared();
         
         // Create queue handler
-        let mut queue = RedisQueue::new(
-            &config.redis_url,
+        let mut queue = RedisQueue::from_connections(
+            conn_manager.clone(),
+            redis.blocking().await?,
             config.worker_upload_file_queue.clone(),
             config.worker_upload_file_dlq.clone(),
-        )
-        .await?;
+        );
 
         loop {
+            heartbeats.beat(&worker_id, ConsumerState::Idle, None);
+
             // Check if shutdown was requested
             if shutdown_signal.load(Ordering::Relaxed) {
                 info!("Shutdown signal received, stopping DLQ worker");
@@ -126,11 +116,27 @@ impl DlqWorker {
                 .await;
 
             match job_result {
-                Ok(Some(job)) => {
+                Ok(Some(mut job)) => {
+                    // Dead letters of kinds with a retention stay in Redis until it runs out
+                    let dead_lettered_at = *job.dead_lettered_at.get_or_insert_with(Utc::now);
+                    let policy = config.retry_policies.for_kind(job.kind);
+                    if let Some(remaining) = policy.dlq_retention_remaining(dead_lettered_at) {
+                        if let Err(e) = queue.return_to_dlq(&job).await {
+                            redis.record_error(&e);
+                            error!("Failed to return DLQ job {} to the DLQ: {}", job.id, e);
+                        }
+                        // Every entry left may still be retained, don't spin through them
+                        sleep(remaining.min(config.file_upload_worker_dlq_wait_interval)).await;
+                        continue;
+                    }
+
+                    heartbeats.beat(&worker_id, ConsumerState::Processing, Some(job.id));
+
                     // Process the DLQ job
-                    let process_result = Self::process_dlq_job(&worker_id, &mut queue, conn_manager.clone(), &config, job, metrics.clone()).await;
+                    let process_result = Self::process_dlq_job(&worker_id, &mut queue, conn_manager.clone(), &config, job, metrics.clone(), &failed_jobs).await;
                     
                     if let Err(e) = process_result {
+                        redis.record_error(&e);
                         error!("Error processing DLQ job: {}", e);
                     }
                 }
@@ -138,8 +144,13 @@ impl DlqWorker {
                     // No job available, continue polling
                     debug!("No DLQ job available, waiting for next job");
                 }
+                Err(WorkerError::MalformedJob(_)) => {
+                    // Already set aside; the next entry may be fine
+                    metrics.record_malformed_job();
+                }
                 Err(e) => {
                     // Error dequeuing job
+                    redis.record_error(&e);
                     error!("Error dequeuing DLQ job: {}", e);
                     
                     // Brief delay before retrying to prevent tight loops on persistent errors
@@ -148,30 +159,29 @@ impl DlqWorker {
             }
         }
 
-        // Signal completion
-        if let Err(e) = completion_tx.send(worker_id.clone()).await {
-            error!("Failed to signal DLQ worker completion: {}", e);
-        }
+        heartbeats.beat(&worker_id, ConsumerState::Stopped, None);
 
         info!("DLQ worker thread exiting");
         Ok(())
     }
 
-    #[instrument(skip(_worker_id, _queue, _conn_manager, _config, metrics), fields(job_id = %job.id, esign_id = %job.esign_id))]
+    #[instrument(skip(_worker_id, queue, _conn_manager, _config, metrics, failed_jobs), fields(job_id = %job.id, esign_id = %job.esign_id))]
     async fn process_dlq_job(
         _worker_id: &str,
-        _queue: &mut RedisQueue,
-        _conn_manager: ConnectionManager,
+        queue: &mut RedisQueue,
+        _conn_manager: RedisConnection,
         _config: &WorkerConfig,
-        job: FileUploadJob,
+        mut job: FileUploadJob,
         metrics: Arc<WorkerMetrics>,
+        failed_jobs: &FailedJobRepository,
     ) -> WorkerResult<()> {
+        telemetry::adopt_trace_context(&job.metadata);
         info!("Processing DLQ job: {}", job.id);
         let start_time = Instant::now();
         metrics.record_job_processed();
 
         // Analyze job failures
-        if Self::is_recoverable_error(&job) {
+        let reason = if Self::is_recoverable_error(&job) {
             info!("DLQ job {} appears to be recoverable, attempting special handling", job.id);
             
             // Try special handling for different error types
@@ -183,6 +193,7 @@ impl DlqWorker {
                         start_time.elapsed()
                     );
                     metrics.record_job_succeeded();
+                    queue.record_progress(&mut job, JobStatus::Completed, 100, "RECOVERED_FROM_DLQ").await;
                     return Ok(());
                 }
                 Err(e) => {
@@ -190,30 +201,33 @@ impl DlqWorker {
                         "DLQ job {} special handling failed: {}",
                         job.id, e
                     );
+                    metrics.record_error_class(&e);
                     metrics.record_general_error();
-                    
-                    // Log for manual intervention
-                    error!(
-                        "DLQ job {} requires manual intervention: {:?}",
-                        job.id,
-                        job
-                    );
+                    format!("Recovery failed: {}", e)
                 }
             }
         } else {
-            // Non-recoverable error, log for manual intervention
             warn!(
-                "DLQ job {} has non-recoverable error, flagging for manual intervention: {:?}",
-                job.id,
-                job
+                "DLQ job {} has non-recoverable error, flagging for manual intervention",
+                job.id
             );
             metrics.record_general_error();
+            "Non-recoverable error".to_string()
+        };
+
+        // Store the job for manual review; it can be replayed through the admin API
+        if let Err(e) = failed_jobs.save(&job, &reason).await {
+            error!(
+                "DLQ job {} could not be persisted for manual review, returning it to the DLQ: {}",
+                job.id, e
+            );
+            queue.return_to_dlq(&job).await?;
+            return Err(WorkerError::Persistence(e));
         }
 
-        // Here you might want to:
-        // 1. Store the job in a database for manual review
-        // 2. Send alerts or notifications for manual intervention
-        // 3. Implement more sophisticated recovery mechanisms
+        info!("DLQ job {} stored for manual review", job.id);
+        let percent_complete = job.progress.percent_complete;
+        queue.record_progress(&mut job, JobStatus::Failed, percent_complete, "PENDING_REVIEW").await;
 
         info!(
             "DLQ job {} processing completed in {:?}",
@@ -228,6 +242,12 @@ impl DlqWorker {
         // Implement logic to determine if an error is recoverable
         // For example, URL expiration might be recoverable if we can refresh the URL
         
+        // A URL off the allow-list stays off it, whatever else it looks like
+        let last_class = job.errors.last().and_then(|error| error.class.as_deref());
+        if last_class == Some(ErrorClass::DisallowedSource.as_str()) {
+            return false;
+        }
+
         // Document URL expired errors might be recoverable by requesting a new URL
         if job.document_url.contains("expired") {
             return true;
@@ -0,0 +1,64 @@
+use chrono::Utc;
+use sqlx::PgPool;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{
+    commons::crypto::FieldCipher,
+    submissions::{
+        submission_data_patch::SubmissionDataPatch,
+        submission_documents::{DocumentType, DocumentUploadProgress, DocumentUploadStatus},
+        submission_repository::{PatchOutcome, SubmissionRepository},
+    },
+    workers::{FileUploadJob, JobKind, WorkerResult},
+};
+
+/// Writes the upload worker's progress on a document back to its submission,
+/// so the status endpoint follows the worker instead of showing the row as it
+/// was when the URLs were handed out
+pub struct DocumentProgress {
+    submission_repository: SubmissionRepository,
+}
+
+impl DocumentProgress {
+    pub fn new(pool: PgPool, cipher: FieldCipher) -> Self {
+        Self {
+            submission_repository: SubmissionRepository::new(pool, cipher),
+        }
+    }
+
+    pub fn from_env(pool: PgPool) -> WorkerResult<Self> {
+        Ok(Self::new(pool, FieldCipher::from_env()?))
+    }
+
+    /// Record where the job's document is. Like job progress in Redis this is
+    /// best effort: a failed write is logged and the job carries on.
+    pub async fn record(&self, job: &FileUploadJob, status: DocumentUploadStatus, last_error: Option<String>) {
+        // Reports aren't submission documents
+        if job.kind != JobKind::Upload {
+            return;
+        }
+        let (Ok(submission_id), Ok(document_type)) =
+            (Uuid::parse_str(&job.esign_id), job.document_type.parse::<DocumentType>())
+        else {
+            debug!("Job {} is not tied to a submission document, skipping its progress", job.id);
+            return;
+        };
+
+        let progress = DocumentUploadProgress {
+            status,
+            last_error,
+            updated_at: Utc::now(),
+        };
+
+        match self
+            .submission_repository
+            .patch_submission_data(submission_id, &SubmissionDataPatch::new().upload(document_type, &progress), None)
+            .await
+        {
+            Ok(PatchOutcome::Applied(_)) => {}
+            Ok(_) => debug!("Submission {} has no {} document, skipping job {} progress", submission_id, document_type, job.id),
+            Err(e) => warn!("Failed to record {} progress of submission {}: {}", document_type, submission_id, e),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    commons::{crypto::FieldCipher, minio_service::MinioService},
+    services::scanner_service::{quarantined_document_name, ScanVerdict, ScannerService},
+    submissions::{
+        document_scan_repository::{DocumentScanRepository, SCAN_CLEAN, SCAN_INFECTED},
+        submission_event_repository::{SubmissionEventRepository, ACTOR_SCANNER, EVENT_STATUS_CHANGED},
+        submission_repository::SubmissionRepository,
+    },
+    workers::{FileUploadJob, WorkerError, WorkerResult},
+};
+
+pub const INFECTED_SUBMISSION_STATUS: &str = "REJECTED";
+pub const INFECTED_RESULT: &str = "INFECTED";
+pub const INFECTED_REASON_CODE: &str = "DOCUMENT_INFECTED";
+
+/// Scans each uploaded document before the job completes, recording the
+/// verdict processing waits for. Infected documents are moved under the
+/// quarantine prefix and their submission is rejected.
+pub struct DocumentScanner {
+    minio_service: MinioService,
+    scanner: ScannerService,
+    submission_repository: SubmissionRepository,
+    submission_event_repository: SubmissionEventRepository,
+    document_scan_repository: DocumentScanRepository,
+}
+
+impl DocumentScanner {
+    pub fn new(minio_service: MinioService, scanner: ScannerService, pool: PgPool, cipher: FieldCipher) -> Self {
+        Self {
+            minio_service,
+            scanner,
+            submission_repository: SubmissionRepository::new(pool.clone(), cipher),
+            submission_event_repository: SubmissionEventRepository::new(pool.clone()),
+            document_scan_repository: DocumentScanRepository::new(pool),
+        }
+    }
+
+    /// None when `DOCUMENT_SCANNER` isn't set
+    pub async fn from_env(pool: PgPool) -> WorkerResult<Option<Self>> {
+        let Some(scanner) = ScannerService::from_env()? else {
+            return Ok(None);
+        };
+
+        let var = |name: &str| std::env::var(name).map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be set", name)));
+
+        let minio_service = MinioService::new(
+            &var("MINIO_ENDPOINT")?,
+            &var("MINIO_ACCESS_KEY")?,
+            &var("MINIO_SECRET_KEY")?,
+            &var("MINIO_BUCKET_NAME")?,
+        )
+        .await
+        .map_err(WorkerError::Config)?;
+
+        Ok(Some(Self::new(minio_service, scanner, pool, FieldCipher::from_env()?)))
+    }
+
+    /// Scan the job's document, quarantining it if it is infected
+    pub async fn scan(&self, job: &FileUploadJob) -> WorkerResult<ScanVerdict> {
+        // Taken before the download: if the object is replaced in between,
+        // the verdict is recorded for the old version and processing asks
+        // for another scan
+        let etag = self
+            .minio_service
+            .object_etag(None, &job.document_name)
+            .await
+            .map_err(|e| WorkerError::Storage(e.to_string()))?
+            .ok_or_else(|| WorkerError::Storage(format!("Document {} does not exist", job.document_name)))?;
+
+        let content = self
+            .minio_service
+            .download_file(job.document_name.clone())
+            .await
+            .map_err(|e| WorkerError::Storage(e.to_string()))?;
+
+        let verdict = self.scanner.scan(&content).await.map_err(|e| WorkerError::Scan(e.to_string()))?;
+        self.record_verdict(job, &etag, &verdict).await?;
+
+        if let ScanVerdict::Infected { signature } = &verdict {
+            warn!("Document {} is infected with {}, quarantining it", job.document_name, signature);
+            self.quarantine(job, content).await?;
+            self.reject_submission(job, signature).await?;
+        } else {
+            info!("Document {} is clean", job.document_name);
+        }
+
+        Ok(verdict)
+    }
+
+    async fn record_verdict(&self, job: &FileUploadJob, etag: &str, verdict: &ScanVerdict) -> WorkerResult<()> {
+        let Ok(submission_id) = Uuid::parse_str(&job.esign_id) else {
+            return Ok(());
+        };
+        let (verdict, signature) = match verdict {
+            ScanVerdict::Clean => (SCAN_CLEAN, None),
+            ScanVerdict::Infected { signature } => (SCAN_INFECTED, Some(signature.as_str())),
+        };
+
+        self.document_scan_repository
+            .record(submission_id, &job.document_type, &job.document_name, etag, verdict, signature)
+            .await?;
+        Ok(())
+    }
+
+    /// Copy first so a failed delete leaves the document in both places
+    /// rather than in neither
+    async fn quarantine(&self, job: &FileUploadJob, content: Vec<u8>) -> WorkerResult<()> {
+        self.minio_service
+            .upload_file(quarantined_document_name(&job.document_name), content, None)
+            .await
+            .map_err(|e| WorkerError::Storage(e.to_string()))?;
+
+        self.minio_service
+            .delete_file(job.document_name.clone())
+            .await
+            .map_err(|e| WorkerError::Storage(e.to_string()))
+    }
+
+    async fn reject_submission(&self, job: &FileUploadJob, signature: &str) -> WorkerResult<()> {
+        let Ok(submission_id) = Uuid::parse_str(&job.esign_id) else {
+            warn!("Job {} is not tied to a submission, only the document was quarantined", job.id);
+            return Ok(());
+        };
+
+        let mut tx = self.submission_repository.begin().await?;
+
+        let Some(previous_status) = self.submission_repository.lock_submission(&mut tx, &job.esign_id).await? else {
+            warn!("Submission {} no longer exists, only the document was quarantined", submission_id);
+            return Ok(());
+        };
+
+        self.submission_repository
+            .update_submission_outcome(&mut tx, submission_id, INFECTED_SUBMISSION_STATUS, INFECTED_RESULT, INFECTED_REASON_CODE)
+            .await?;
+
+        self.submission_event_repository
+            .append_in_tx(
+                &mut tx,
+                submission_id,
+                EVENT_STATUS_CHANGED,
+                ACTOR_SCANNER,
+                json!({
+                    "status": { "from": previous_status, "to": INFECTED_SUBMISSION_STATUS },
+                    "result": { "to": INFECTED_RESULT },
+                    "reasonCode": { "to": INFECTED_REASON_CODE },
+                    "documentType": job.document_type,
+                    "signature": signature,
+                }),
+            )
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -26,8 +26,117 @@ pub enum WorkerError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Database error: {0}")]
+    Persistence(#[from] sqlx::Error),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
     #[error("HTTP request error: {0}")]
     Http(#[from] reqwest::Error),
+
+    #[error("Document scan failed: {0}")]
+    Scan(String),
+
+    #[error("Job timed out after {0:?}")]
+    JobTimeout(std::time::Duration),
+
+    #[error("Report generation failed: {0}")]
+    Report(String),
+
+    #[error("Notification failed: {0}")]
+    Notification(String),
+
+    /// The provider refused the notification; sending it again won't help
+    #[error("Notification rejected: {0}")]
+    NotificationRejected(String),
+
+    /// A copy job that can't be carried out, e.g. its source is gone
+    #[error("Copy failed: {0}")]
+    Copy(String),
+
+    /// The consumer panicked while the job was on its processing list
+    #[error("Consumer panicked while processing the job")]
+    ConsumerPanicked,
+
+    /// A queue entry that isn't a job; it was set aside on the malformed list
+    #[error("Malformed job: {0}")]
+    MalformedJob(String),
+
+    /// The job's document URL isn't on the upload source allow-list
+    #[error("Document URL not allowed: {0}")]
+    DisallowedSource(String),
 }
 
 pub type WorkerResult<T> = Result<T, WorkerError>;
+
+/// Broad cause of a failure, deciding whether a job is worth retrying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Redis, Postgres, storage or HTTP hiccups that may succeed on retry
+    Network,
+    /// The job itself is malformed; retrying can't help
+    Validation,
+    /// The job is well-formed but can never succeed, e.g. its URL expired
+    Permanent,
+    /// The job ran past `WORKER_JOB_TIMEOUT_SECONDS` and was cancelled
+    Timeout,
+    /// The job points at a source outside the upload allow-list, which may
+    /// be an attempt to reach internal hosts
+    DisallowedSource,
+}
+
+impl ErrorClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorClass::Network => "network",
+            ErrorClass::Validation => "validation",
+            ErrorClass::Permanent => "permanent",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::DisallowedSource => "disallowed_source",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl WorkerError {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            WorkerError::Redis(_)
+            | WorkerError::LockAcquisition(_)
+            | WorkerError::UploadFailed(_)
+            | WorkerError::Shutdown
+            | WorkerError::Io(_)
+            | WorkerError::Persistence(_)
+            | WorkerError::Storage(_)
+            | WorkerError::Scan(_)
+            | WorkerError::Notification(_) => ErrorClass::Network,
+            WorkerError::JobTimeout(_) => ErrorClass::Timeout,
+            WorkerError::DisallowedSource(_) => ErrorClass::DisallowedSource,
+            WorkerError::Json(_) | WorkerError::MalformedJob(_) => ErrorClass::Validation,
+            WorkerError::DocumentUrlExpired
+            | WorkerError::Config(_)
+            | WorkerError::Report(_)
+            | WorkerError::NotificationRejected(_)
+            | WorkerError::Copy(_)
+            | WorkerError::ConsumerPanicked => ErrorClass::Permanent,
+            // Client errors won't change on retry, except timeouts and rate limits
+            WorkerError::Http(e) => match e.status() {
+                Some(status) if status.is_client_error() && status.as_u16() != 408 && status.as_u16() != 429 => {
+                    ErrorClass::Permanent
+                }
+                _ => ErrorClass::Network,
+            },
+        }
+    }
+
+    /// A timed out job may have hit a slow dependency, so it gets retried too
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.class(), ErrorClass::Network | ErrorClass::Timeout)
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::Utc;
+use rdkafka::{
+    config::ClientConfig,
+    message::{Header, OwnedHeaders},
+    producer::{FutureProducer, FutureRecord},
+    util::Timeout,
+};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::{
+    submissions::submission_event_repository::{
+        SubmissionEventRepository, UnpublishedEvent, EVENT_STATUS_CHANGED, EVENT_SUBMISSION_CREATED,
+    },
+    workers::{DistributedLock, RedisConnections, WorkerError, WorkerMetrics, WorkerResult},
+};
+
+/// Bumped whenever a field of the published event changes meaning or goes away
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+pub const KAFKA_EVENT_SUBMISSION_CREATED: &str = "submission.created";
+pub const KAFKA_EVENT_SUBMISSION_UPDATED: &str = "submission.updated";
+pub const KAFKA_EVENT_SUBMISSION_DECIDED: &str = "submission.decided";
+
+/// Topic used when no `KAFKA_TOPIC` is set
+pub const DEFAULT_TOPIC: &str = "submission-events";
+
+/// How long to wait for the broker to acknowledge an event
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Only one worker instance publishes at a time, keeping the topic in outbox
+/// order. The lock is refreshed before every event, so it only has to outlive
+/// one delivery and the removal of its outbox row.
+const PUBLISH_LOCK_KEY: &str = "submission_event_publisher";
+const PUBLISH_LOCK_TTL: Duration = Duration::from_secs(DELIVERY_TIMEOUT.as_secs() * 2);
+
+/// Publishes the submission audit trail to Kafka from the
+/// `submission_event_outbox` table: every committed event is published at
+/// least once, keyed by its submission so consumers see a submission's events
+/// in order
+pub struct EventPublisher {
+    events: SubmissionEventRepository,
+    producer: FutureProducer,
+    topic: String,
+    redis: RedisConnections,
+    metrics: Arc<WorkerMetrics>,
+    batch_size: i64,
+    interval: Duration,
+}
+
+impl EventPublisher {
+    /// None unless `KAFKA_ENABLED=true`
+    pub fn from_env(pool: PgPool, redis: RedisConnections, metrics: Arc<WorkerMetrics>) -> WorkerResult<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        if var("KAFKA_ENABLED").as_deref() != Some("true") {
+            return Ok(None);
+        }
+
+        let number = |name: &str, default: u64| {
+            var(name)
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map(|v| v.unwrap_or(default))
+                .map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be a number", name)))
+        };
+
+        let brokers = var("KAFKA_BROKERS").ok_or_else(|| WorkerError::Config(anyhow::anyhow!("KAFKA_BROKERS must be set")))?;
+        let mut client_config = ClientConfig::new();
+        client_config
+            .set("bootstrap.servers", &brokers)
+            .set("enable.idempotence", "true")
+            .set("message.timeout.ms", DELIVERY_TIMEOUT.as_millis().to_string());
+
+        // Anything else librdkafka takes, e.g. "security.protocol=SASL_SSL,sasl.mechanism=PLAIN"
+        for entry in var("KAFKA_PRODUCER_CONFIG").unwrap_or_default().split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, value) = entry.split_once('=').ok_or_else(|| {
+                WorkerError::Config(anyhow::anyhow!("KAFKA_PRODUCER_CONFIG entries must look like <key>=<value>"))
+            })?;
+            client_config.set(key.trim(), value.trim());
+        }
+
+        let producer: FutureProducer = client_config
+            .create()
+            .map_err(|e| WorkerError::Config(anyhow::anyhow!("Failed to create Kafka producer: {}", e)))?;
+
+        Ok(Some(Self {
+            events: SubmissionEventRepository::new(pool),
+            producer,
+            topic: var("KAFKA_TOPIC").unwrap_or_else(|| DEFAULT_TOPIC.to_string()),
+            redis,
+            metrics,
+            batch_size: number("KAFKA_PUBLISH_BATCH_SIZE", 100)?.max(1) as i64,
+            interval: Duration::from_millis(number("KAFKA_PUBLISH_INTERVAL_MILLIS", 1000)?.max(1)),
+        }))
+    }
+
+    /// Publish until `shutdown_signal` is set
+    pub async fn run(&self, shutdown_signal: Arc<AtomicBool>) {
+        info!("Publishing submission events to Kafka topic {}", self.topic);
+
+        while !shutdown_signal.load(Ordering::SeqCst) {
+            let published = match self.publish_pending().await {
+                Ok(published) => published,
+                Err(e) => {
+                    error!("Failed to publish submission events: {}", e);
+                    0
+                }
+            };
+            self.record_backlog().await;
+
+            // A full batch means more are waiting
+            if published < self.batch_size as usize {
+                tokio::time::sleep(self.interval).await;
+            }
+        }
+
+        info!("Submission event publisher stopped");
+    }
+
+    /// Publish one batch from the outbox, returning how many were published.
+    /// A failed event stops the batch, so it is retried before anything
+    /// written after it.
+    async fn publish_pending(&self) -> WorkerResult<usize> {
+        let mut lock = DistributedLock::new(self.redis.shared(), PUBLISH_LOCK_KEY.to_string(), PUBLISH_LOCK_TTL);
+        if !lock.acquire(Duration::ZERO, Duration::ZERO).await? {
+            return Ok(0);
+        }
+
+        let result = self.publish_batch(&mut lock).await;
+        lock.release().await?;
+        result
+    }
+
+    /// Each event leaves the outbox as soon as Kafka acknowledged it, so a
+    /// publisher that stops halfway leaves only unpublished events behind
+    async fn publish_batch(&self, lock: &mut DistributedLock) -> WorkerResult<usize> {
+        let events = self.events.find_unpublished(self.batch_size).await?;
+
+        let mut published = 0;
+        for event in &events {
+            // Another instance may have taken over after a slow delivery
+            if !lock.refresh().await? {
+                warn!("Lost the submission event publisher lock, stopping the batch");
+                break;
+            }
+
+            let kafka_event_type = kafka_event_type(event);
+            let payload = to_kafka_event(event, kafka_event_type).to_string();
+            let key = event.submission_id.to_string();
+            let schema_version = EVENT_SCHEMA_VERSION.to_string();
+            let record = FutureRecord::to(&self.topic).key(&key).payload(&payload).headers(
+                OwnedHeaders::new()
+                    .insert(Header { key: "eventType", value: Some(kafka_event_type) })
+                    .insert(Header { key: "schemaVersion", value: Some(&schema_version) }),
+            );
+
+            if let Err((e, _)) = self.producer.send(record, Timeout::After(DELIVERY_TIMEOUT)).await {
+                self.metrics.record_event_publish_failure();
+                warn!("Kafka rejected submission event {}, retrying it next round: {}", event.id, e);
+                break;
+            }
+
+            self.events.mark_published(&[event.id]).await?;
+            self.metrics.record_events_published(1);
+            published += 1;
+        }
+
+        Ok(published)
+    }
+
+    async fn record_backlog(&self) {
+        match self.events.outbox_backlog().await {
+            Ok((backlog, oldest)) => {
+                let lag = oldest.map(|oldest| (Utc::now() - oldest).num_seconds().max(0) as u64).unwrap_or(0);
+                self.metrics.update_event_outbox(backlog as u64, lag);
+            }
+            Err(e) => warn!("Failed to read the submission event outbox backlog: {}", e),
+        }
+    }
+}
+
+/// Status changes that set a result are decisions; everything after creation
+/// that isn't is an update
+fn kafka_event_type(event: &UnpublishedEvent) -> &'static str {
+    match event.event_type.as_str() {
+        EVENT_SUBMISSION_CREATED => KAFKA_EVENT_SUBMISSION_CREATED,
+        EVENT_STATUS_CHANGED if !event.payload_diff["result"]["to"].is_null() => KAFKA_EVENT_SUBMISSION_DECIDED,
+        _ => KAFKA_EVENT_SUBMISSION_UPDATED,
+    }
+}
+
+/// The published event. `eventId` is the audit trail id, which stays the
+/// same when an event is published twice.
+fn to_kafka_event(event: &UnpublishedEvent, kafka_event_type: &str) -> Value {
+    json!({
+        "schemaVersion": EVENT_SCHEMA_VERSION,
+        "eventId": event.id,
+        "eventType": kafka_event_type,
+        "occurredAt": event.created_at,
+        "submissionId": event.submission_id,
+        "tenantId": event.tenant_id,
+        "submissionType": event.submission_type,
+        "auditEventType": event.event_type,
+        "actor": event.actor,
+        "changes": event.payload_diff,
+    })
+}
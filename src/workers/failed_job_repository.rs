@@ -0,0 +1,182 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{types::Json, PgPool};
+use uuid::Uuid;
+
+use crate::workers::FileUploadJob;
+
+pub const FAILED_JOB_STATUS_PENDING_REVIEW: &str = "PENDING_REVIEW";
+pub const FAILED_JOB_STATUS_REPLAYED: &str = "REPLAYED";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedJob {
+    pub id: i64,
+    pub job_id: Uuid,
+    pub esign_id: String,
+    pub document_type: String,
+    pub status: String,
+    pub reason: String,
+    pub retry_count: i32,
+    pub payload: Value,
+    pub error_history: Value,
+    pub replayed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct FailedJobRepository {
+    pool: PgPool,
+}
+
+impl FailedJobRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Persist a job for manual review. A job that fails again after a replay
+    /// keeps its row and gets the latest payload and error history, and can
+    /// be replayed again.
+    pub async fn save(&self, job: &FileUploadJob, reason: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO failed_jobs (
+                job_id,
+                esign_id,
+                document_type,
+                status,
+                reason,
+                retry_count,
+                payload,
+                error_history
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (job_id) DO UPDATE
+            SET status = EXCLUDED.status,
+                reason = EXCLUDED.reason,
+                retry_count = EXCLUDED.retry_count,
+                payload = EXCLUDED.payload,
+                error_history = EXCLUDED.error_history,
+                replayed_at = NULL,
+                updated_at = NOW()
+            "#,
+            job.id,
+            job.esign_id,
+            job.document_type,
+            FAILED_JOB_STATUS_PENDING_REVIEW,
+            reason,
+            job.retry_count as i32,
+            Json(job) as _,
+            Json(&job.errors) as _
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_all(&self, status: Option<&str>, limit: i64, offset: i64) -> Result<Vec<FailedJob>, sqlx::Error> {
+        sqlx::query_as!(
+            FailedJob,
+            r#"
+            SELECT
+                id,
+                job_id,
+                esign_id,
+                document_type,
+                status,
+                reason,
+                retry_count,
+                payload,
+                error_history,
+                replayed_at,
+                created_at,
+                updated_at
+            FROM failed_jobs
+            WHERE ($1::TEXT IS NULL OR status = $1)
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            status,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<FailedJob>, sqlx::Error> {
+        sqlx::query_as!(
+            FailedJob,
+            r#"
+            SELECT
+                id,
+                job_id,
+                esign_id,
+                document_type,
+                status,
+                reason,
+                retry_count,
+                payload,
+                error_history,
+                replayed_at,
+                created_at,
+                updated_at
+            FROM failed_jobs
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Mark the job replayed unless it already was, so concurrent replays
+    /// can't both enqueue it; None if it doesn't exist or was replayed
+    pub async fn claim_for_replay(&self, id: i64) -> Result<Option<FailedJob>, sqlx::Error> {
+        sqlx::query_as!(
+            FailedJob,
+            r#"
+            UPDATE failed_jobs
+            SET status = $2, replayed_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND replayed_at IS NULL
+            RETURNING
+                id,
+                job_id,
+                esign_id,
+                document_type,
+                status,
+                reason,
+                retry_count,
+                payload,
+                error_history,
+                replayed_at,
+                created_at,
+                updated_at
+            "#,
+            id,
+            FAILED_JOB_STATUS_REPLAYED
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Give back a claim whose replay never made it onto the queue
+    pub async fn release_replay_claim(&self, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE failed_jobs
+            SET status = $2, replayed_at = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            FAILED_JOB_STATUS_PENDING_REVIEW
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
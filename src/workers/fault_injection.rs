@@ -0,0 +1,170 @@
+//! Controlled faults in the upload stage, so retries and the DLQ can be
+//! exercised on purpose. Only built with the `chaos` feature.
+
+use anyhow::Context;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+use crate::workers::error::{WorkerError, WorkerResult};
+
+static INJECTOR: OnceLock<FaultInjector> = OnceLock::new();
+
+/// A fault an upload can be made to hit
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// The Redis connection drops mid-job
+    RedisDisconnect,
+    /// The document URL turns out to have expired
+    UrlExpired,
+    UploadFailed,
+    /// The upload goes through, but only after the delay
+    SlowUpload(Duration),
+}
+
+/// How often each fault is injected, as a rate between 0 and 1
+#[derive(Debug, Clone, Default)]
+pub struct FaultConfig {
+    pub redis_disconnect_rate: f64,
+    pub url_expired_rate: f64,
+    pub upload_failure_rate: f64,
+    pub slow_upload_rate: f64,
+    pub slow_upload_delay: Duration,
+    /// Seed of the random draws; the same seed injects the same faults in the
+    /// same order
+    pub seed: Option<u64>,
+}
+
+impl FaultConfig {
+    /// Read from `WORKER_CHAOS_*`; None when no fault has a rate above zero
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let rate = |name: &str| -> anyhow::Result<f64> {
+            let rate = var(name)
+                .map(|v| v.parse::<f64>().with_context(|| format!("{} must be a number", name)))
+                .transpose()?
+                .unwrap_or(0.0);
+            if !(0.0..=1.0).contains(&rate) {
+                anyhow::bail!("{} must be between 0 and 1", name);
+            }
+            Ok(rate)
+        };
+
+        let config = Self {
+            redis_disconnect_rate: rate("WORKER_CHAOS_REDIS_DISCONNECT_RATE")?,
+            url_expired_rate: rate("WORKER_CHAOS_URL_EXPIRED_RATE")?,
+            upload_failure_rate: rate("WORKER_CHAOS_UPLOAD_FAILURE_RATE")?,
+            slow_upload_rate: rate("WORKER_CHAOS_SLOW_UPLOAD_RATE")?,
+            slow_upload_delay: Duration::from_millis(
+                var("WORKER_CHAOS_SLOW_UPLOAD_MILLISECONDS")
+                    .map(|v| v.parse::<u64>().context("WORKER_CHAOS_SLOW_UPLOAD_MILLISECONDS must be a number"))
+                    .transpose()?
+                    .unwrap_or(5000),
+            ),
+            seed: var("WORKER_CHAOS_SEED")
+                .map(|v| v.parse::<u64>().context("WORKER_CHAOS_SEED must be a number"))
+                .transpose()?,
+        };
+
+        let enabled = [
+            config.redis_disconnect_rate,
+            config.url_expired_rate,
+            config.upload_failure_rate,
+            config.slow_upload_rate,
+        ]
+        .iter()
+        .any(|rate| *rate > 0.0);
+        Ok(enabled.then_some(config))
+    }
+}
+
+/// Draws the faults uploads hit
+pub struct FaultInjector {
+    config: FaultConfig,
+    rng: Mutex<StdRng>,
+}
+
+impl FaultInjector {
+    pub fn new(config: FaultConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Self {
+            config,
+            rng: Mutex::new(rng),
+        }
+    }
+
+    pub fn config(&self) -> &FaultConfig {
+        &self.config
+    }
+
+    /// The fault the next upload hits, if any. Every call draws once per
+    /// fault whatever the outcome, so a seed always gives the same sequence.
+    pub fn next_upload_fault(&self) -> Option<Fault> {
+        let draws: [f64; 4] = {
+            let mut rng = self.rng.lock().unwrap();
+            [rng.gen(), rng.gen(), rng.gen(), rng.gen()]
+        };
+
+        if draws[0] < self.config.redis_disconnect_rate {
+            Some(Fault::RedisDisconnect)
+        } else if draws[1] < self.config.url_expired_rate {
+            Some(Fault::UrlExpired)
+        } else if draws[2] < self.config.upload_failure_rate {
+            Some(Fault::UploadFailed)
+        } else if draws[3] < self.config.slow_upload_rate {
+            Some(Fault::SlowUpload(self.config.slow_upload_delay))
+        } else {
+            None
+        }
+    }
+
+    /// Have the current upload hit the next fault: fail it the way the real
+    /// fault would, or hold it up
+    pub async fn inject_upload_fault(&self) -> WorkerResult<()> {
+        let Some(fault) = self.next_upload_fault() else {
+            return Ok(());
+        };
+        debug!("Injecting {:?}", fault);
+
+        match fault {
+            Fault::RedisDisconnect => Err(WorkerError::Redis(redis::RedisError::from((
+                redis::ErrorKind::IoError,
+                "Injected disconnect",
+            )))),
+            Fault::UrlExpired => Err(WorkerError::DocumentUrlExpired),
+            Fault::UploadFailed => Err(WorkerError::UploadFailed("Injected upload failure".to_string())),
+            Fault::SlowUpload(delay) => {
+                sleep(delay).await;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Install the injector uploads go through; the first one installed stays
+pub fn install(injector: FaultInjector) {
+    let config = injector.config().clone();
+    if INJECTOR.set(injector).is_ok() {
+        warn!("Fault injection is enabled: {:?}", config);
+    }
+}
+
+/// Install an injector from `WORKER_CHAOS_*` when any fault is configured
+pub fn install_from_env() -> anyhow::Result<()> {
+    if let Some(config) = FaultConfig::from_env()? {
+        install(FaultInjector::new(config));
+    }
+    Ok(())
+}
+
+pub fn injector() -> Option<&'static FaultInjector> {
+    INJECTOR.get()
+}
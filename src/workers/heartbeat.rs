@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConsumerState {
+    Idle,
+    Processing,
+    /// Not taking jobs while the service is in maintenance
+    Paused,
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Heartbeat {
+    pub worker_id: String,
+    pub state: ConsumerState,
+    pub current_job_id: Option<Uuid>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+/// WorkerHeartbeats records the last reported state of every consumer thread
+#[derive(Default)]
+pub struct WorkerHeartbeats {
+    heartbeats: RwLock<HashMap<String, Heartbeat>>,
+    /// Job each consumer last panicked on, until its restart picks it up
+    panicked_jobs: RwLock<HashMap<String, Uuid>>,
+}
+
+impl WorkerHeartbeats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn beat(&self, worker_id: &str, state: ConsumerState, current_job_id: Option<Uuid>) {
+        let heartbeat = Heartbeat {
+            worker_id: worker_id.to_string(),
+            state,
+            current_job_id,
+            last_seen_at: Utc::now(),
+        };
+
+        if let Ok(mut heartbeats) = self.heartbeats.write() {
+            heartbeats.insert(worker_id.to_string(), heartbeat);
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<Heartbeat> {
+        let mut heartbeats: Vec<Heartbeat> = self
+            .heartbeats
+            .read()
+            .map(|heartbeats| heartbeats.values().cloned().collect())
+            .unwrap_or_default();
+        heartbeats.sort_by(|a, b| a.worker_id.cmp(&b.worker_id));
+        heartbeats
+    }
+
+    /// Job the consumer last reported working on
+    pub fn current_job(&self, worker_id: &str) -> Option<Uuid> {
+        self.heartbeats
+            .read()
+            .ok()
+            .and_then(|heartbeats| heartbeats.get(worker_id).and_then(|h| h.current_job_id))
+    }
+
+    /// Remember the job a consumer panicked on for its next run
+    pub fn record_panic(&self, worker_id: &str, job_id: Uuid) {
+        if let Ok(mut panicked_jobs) = self.panicked_jobs.write() {
+            panicked_jobs.insert(worker_id.to_string(), job_id);
+        }
+    }
+
+    /// The job the consumer panicked on last, if its restart hasn't taken it yet
+    pub fn take_panicked_job(&self, worker_id: &str) -> Option<Uuid> {
+        self.panicked_jobs
+            .write()
+            .ok()
+            .and_then(|mut panicked_jobs| panicked_jobs.remove(worker_id))
+    }
+
+    /// Number of consumers that have not stopped and are processing a job
+    pub fn in_flight(&self) -> usize {
+        self.snapshot()
+            .iter()
+            .filter(|h| h.state == ConsumerState::Processing)
+            .count()
+    }
+
+    /// Whether every consumer that reported in has exited its loop
+    pub fn all_stopped(&self) -> bool {
+        self.snapshot()
+            .iter()
+            .all(|h| h.state == ConsumerState::Stopped)
+    }
+}
@@ -0,0 +1,72 @@
+use tracing::{info, warn};
+
+use crate::{
+    commons::minio_service::MinioService,
+    services::image_processing_service::{processed_document_name, ImageProcessingService, PROCESSED_CONTENT_TYPE},
+    submissions::submission_documents::DocumentType,
+    workers::{FileUploadJob, WorkerError, WorkerResult},
+};
+
+/// Stores a downscaled, upright JPEG next to each uploaded KTP and selfie, so
+/// face matching doesn't send full-size camera images to the provider
+#[derive(Clone)]
+pub struct ImagePreprocessor {
+    minio_service: MinioService,
+    images: ImageProcessingService,
+}
+
+impl ImagePreprocessor {
+    pub fn new(minio_service: MinioService, images: ImageProcessingService) -> Self {
+        Self { minio_service, images }
+    }
+
+    pub async fn from_env() -> WorkerResult<Self> {
+        let var = |name: &str| std::env::var(name).map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be set", name)));
+
+        let minio_service = MinioService::new(
+            &var("MINIO_ENDPOINT")?,
+            &var("MINIO_ACCESS_KEY")?,
+            &var("MINIO_SECRET_KEY")?,
+            &var("MINIO_BUCKET_NAME")?,
+        )
+        .await
+        .map_err(WorkerError::Config)?;
+
+        Ok(Self::new(minio_service, ImageProcessingService::from_env()?))
+    }
+
+    /// Only the face match inputs are images worth preprocessing
+    pub fn applies_to(job: &FileUploadJob) -> bool {
+        matches!(
+            job.document_type.parse::<DocumentType>(),
+            Ok(DocumentType::Ktp) | Ok(DocumentType::Selfie)
+        )
+    }
+
+    /// Store the processed variant of the job's document, returning its key.
+    /// Documents that aren't decodable images are left alone.
+    pub async fn preprocess(&self, job: &FileUploadJob) -> WorkerResult<Option<String>> {
+        let original = self
+            .minio_service
+            .download_file(job.document_name.clone())
+            .await
+            .map_err(|e| WorkerError::Storage(e.to_string()))?;
+
+        let processed = match self.images.process(original).await {
+            Ok(processed) => processed,
+            Err(e) => {
+                warn!("Skipping preprocessing of {}: {}", job.document_name, e);
+                return Ok(None);
+            }
+        };
+
+        let processed_name = processed_document_name(&job.document_name);
+        self.minio_service
+            .upload_file(processed_name.clone(), processed, Some(PROCESSED_CONTENT_TYPE.to_string()))
+            .await
+            .map_err(|e| WorkerError::Storage(e.to_string()))?;
+
+        info!("Stored processed variant {} of {}", processed_name, job.document_name);
+        Ok(Some(processed_name))
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+use crate::{notifier::Channel, services::metrics_service::MetricTags, workers::WorkerError};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileUploadJob {
     pub id: Uuid,
@@ -13,9 +15,128 @@ pub struct FileUploadJob {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub metadata: serde_json::Value,
+    #[serde(default)]
+    pub errors: Vec<JobErrorRecord>,
+    #[serde(default)]
+    pub progress: JobProgress,
+    #[serde(default)]
+    pub kind: JobKind,
+    /// When the job last entered the DLQ, which its retention counts from
+    #[serde(default)]
+    pub dead_lettered_at: Option<DateTime<Utc>>,
+    /// When the job was last pushed onto the queue, or became due there when
+    /// scheduled, which its queue wait counts from. Reclaimed jobs keep the
+    /// time of their first push.
+    #[serde(default)]
+    pub enqueued_at: Option<DateTime<Utc>>,
+    /// What a `COPY` job copies; None for every other kind
+    #[serde(default)]
+    pub copy: Option<ObjectCopy>,
+}
+
+/// What the worker does with a job. Jobs enqueued before kinds existed are uploads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JobKind {
+    /// Upload, scan and preprocess a submission document
+    #[default]
+    Upload,
+    /// Render the PDF report of an approved submission into `document_name`
+    Report,
+    /// Tell the owner of a decided submission on the channel in `document_type`
+    Notification,
+    /// Copy or move a stored object server-side, as described in `copy`
+    Copy,
+    /// Scan a stored submission document for malware and record the verdict
+    Scan,
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::Upload => "UPLOAD",
+            JobKind::Report => "REPORT",
+            JobKind::Notification => "NOTIFICATION",
+            JobKind::Copy => "COPY",
+            JobKind::Scan => "SCAN",
+        }
+    }
+}
+
+/// Source and destination of a `COPY` job. Buckets default to
+/// `MINIO_BUCKET_NAME`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectCopy {
+    #[serde(default)]
+    pub source_bucket: Option<String>,
+    pub source_key: String,
+    #[serde(default)]
+    pub destination_bucket: Option<String>,
+    pub destination_key: String,
+    /// Delete the source once the copy is in place, making the copy a move
+    #[serde(default)]
+    pub delete_source: bool,
 }
 
+impl ObjectCopy {
+    /// Whether source and destination are the same object, which a move
+    /// would delete
+    pub fn is_in_place(&self) -> bool {
+        self.source_bucket == self.destination_bucket && self.source_key == self.destination_key
+    }
+}
+
+/// A single failed attempt, kept on the job so the DLQ has the full history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobErrorRecord {
+    pub message: String,
+    /// `ErrorClass` of the failure; missing on jobs recorded before it existed
+    #[serde(default)]
+    pub class: Option<String>,
+    pub retry_count: u32,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Where a job is in its lifecycle, mirrored to Redis for the progress API
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobProgress {
+    pub status: JobStatus,
+    pub percent_complete: u8,
+    pub current_step: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for JobProgress {
+    fn default() -> Self {
+        Self {
+            status: JobStatus::Pending,
+            percent_complete: 0,
+            current_step: "QUEUED".to_string(),
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// Progress of a job as read back from Redis
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobProgressSnapshot {
+    pub job_id: Uuid,
+    pub status: String,
+    pub percent_complete: u8,
+    pub current_step: String,
+    pub retry_count: u32,
+    pub last_error: Option<String>,
+    pub updated_at: String,
+    /// Whose submission the job works for, to authorize who may read it
+    #[serde(skip)]
+    pub submission_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum JobStatus {
     Pending,
     Processing,
@@ -23,6 +144,8 @@ pub enum JobStatus {
     Failed,
     UrlExpired,
     DeadLetter,
+    /// The document was infected and moved to quarantine
+    Quarantined,
 }
 
 impl FileUploadJob {
@@ -44,16 +167,123 @@ impl FileUploadJob {
             created_at: now,
             updated_at: now,
             metadata,
+            errors: Vec::new(),
+            progress: JobProgress::default(),
+            kind: JobKind::Upload,
+            dead_lettered_at: None,
+            enqueued_at: None,
+            copy: None,
+        }
+    }
+
+    /// A job rendering the report of `submission_id` into `document_name`
+    pub fn report(submission_id: String, document_name: String, metadata: serde_json::Value) -> Self {
+        Self {
+            kind: JobKind::Report,
+            ..Self::new(submission_id, String::new(), document_name, "REPORT".to_string(), metadata)
         }
     }
 
+    /// A job notifying the owner of `submission_id` on `channel`
+    pub fn notification(submission_id: String, channel: Channel, metadata: serde_json::Value) -> Self {
+        Self {
+            kind: JobKind::Notification,
+            ..Self::new(submission_id, String::new(), String::new(), channel.to_string(), metadata)
+        }
+    }
+
+    /// A job scanning the stored `document_name` of `submission_id`
+    pub fn scan(submission_id: String, document_name: String, document_type: String, metadata: serde_json::Value) -> Self {
+        Self {
+            kind: JobKind::Scan,
+            ..Self::new(submission_id, String::new(), document_name, document_type, metadata)
+        }
+    }
+
+    /// A job copying an object of `submission_id` to `copy.destination_key`
+    pub fn copy(submission_id: String, copy: ObjectCopy, metadata: serde_json::Value) -> Self {
+        Self {
+            kind: JobKind::Copy,
+            copy: Some(copy.clone()),
+            ..Self::new(submission_id, String::new(), copy.destination_key, "COPY".to_string(), metadata)
+        }
+    }
+
+    /// Submission the job works for: `submissionId` in its metadata, or else
+    /// its esign id, which jobs enqueued for a submission carry
+    pub fn submission_id(&self) -> &str {
+        self.metadata
+            .get("submissionId")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&self.esign_id)
+    }
+
+    /// Metric dimensions of the job. Tenant and submission type are only
+    /// known for jobs whose metadata carries them.
+    pub fn metric_tags(&self) -> MetricTags {
+        let mut tags = MetricTags::new().document_type(&self.document_type);
+        if let Some(tenant_id) = self.metadata.get("tenantId").and_then(|v| v.as_str()) {
+            tags = tags.tenant(tenant_id);
+        }
+        if let Some(submission_type) = self.metadata.get("submissionType").and_then(|v| v.as_str()) {
+            tags = tags.submission_type(submission_type);
+        }
+        tags
+    }
+
     pub fn increment_retry(&mut self) {
         self.retry_count += 1;
         self.updated_at = Utc::now();
     }
 
+    pub fn record_error(&mut self, error: &WorkerError) {
+        self.errors.push(JobErrorRecord {
+            message: error.to_string(),
+            class: Some(error.class().as_str().to_string()),
+            retry_count: self.retry_count,
+            occurred_at: Utc::now(),
+        });
+    }
+
+    /// Key identifying this upload attempt for enqueue deduplication. Two jobs
+    /// for the same document at the same retry count are the same upload, and
+    /// two copies between the same objects the same copy.
+    pub fn get_idempotency_key(&self) -> String {
+        if let Some(copy) = &self.copy {
+            return format!(
+                "copy_dedup:{}/{}:{}/{}:{}",
+                copy.source_bucket.as_deref().unwrap_or_default(),
+                copy.source_key,
+                copy.destination_bucket.as_deref().unwrap_or_default(),
+                copy.destination_key,
+                self.retry_count
+            );
+        }
+        format!(
+            "upload_dedup:{}:{}:{}:{}",
+            self.esign_id, self.document_type, self.document_url, self.retry_count
+        )
+    }
+
+    pub fn set_progress(&mut self, status: JobStatus, percent_complete: u8, current_step: &str) {
+        self.progress = JobProgress {
+            status,
+            percent_complete: percent_complete.min(100),
+            current_step: current_step.to_string(),
+            updated_at: Utc::now(),
+        };
+    }
+
+    pub fn get_progress_key(&self) -> String {
+        progress_key(self.id)
+    }
+
+    /// Notifications of a submission go out on each channel independently
     pub fn get_lock_key(&self) -> String {
-        format!("upload_lock:{}", self.esign_id)
+        match self.kind {
+            JobKind::Notification => format!("notification_lock:{}:{}", self.esign_id, self.document_type),
+            _ => format!("upload_lock:{}", self.esign_id),
+        }
     }
 
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
@@ -64,3 +294,23 @@ impl FileUploadJob {
         serde_json::from_str(json)
     }
 }
+
+/// Redis hash holding the progress of a job
+pub fn progress_key(job_id: Uuid) -> String {
+    format!("job_progress:{}", job_id)
+}
+
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            JobStatus::Pending => "PENDING",
+            JobStatus::Processing => "PROCESSING",
+            JobStatus::Completed => "COMPLETED",
+            JobStatus::Failed => "FAILED",
+            JobStatus::UrlExpired => "URL_EXPIRED",
+            JobStatus::DeadLetter => "DEAD_LETTER",
+            JobStatus::Quarantined => "QUARANTINED",
+        };
+        write!(f, "{}", status)
+    }
+}
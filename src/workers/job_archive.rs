@@ -0,0 +1,225 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{types::Json, PgPool};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::workers::{
+    job_execution_repository::{JobExecution, OUTCOME_QUARANTINED, OUTCOME_SUCCEEDED},
+    FileUploadJob, WorkerError, WorkerResult,
+};
+
+/// How often the wait between prunes checks the shutdown signal
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedJob {
+    pub id: i64,
+    pub job_id: Uuid,
+    pub job_type: String,
+    pub esign_id: String,
+    pub document_type: String,
+    pub outcome: String,
+    pub attempt: i32,
+    pub worker_id: String,
+    pub duration_ms: i64,
+    pub payload: Value,
+    pub replayed_at: Option<DateTime<Utc>>,
+    pub archived_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct ArchivedJobRepository {
+    pool: PgPool,
+}
+
+impl ArchivedJobRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Keep the job as it was dequeued, with how its last attempt went
+    pub async fn archive(&self, job: &FileUploadJob, execution: &JobExecution) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO archived_jobs (
+                job_id, job_type, esign_id, document_type, outcome, attempt, worker_id, duration_ms, payload
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            execution.job_id,
+            execution.job_type,
+            execution.esign_id,
+            execution.document_type,
+            execution.outcome,
+            execution.attempt,
+            execution.worker_id,
+            execution.elapsed().as_millis() as i64,
+            Json(job) as _
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Archived jobs, newest first, optionally of one job only
+    pub async fn find_all(&self, job_id: Option<Uuid>, limit: i64, offset: i64) -> Result<Vec<ArchivedJob>, sqlx::Error> {
+        sqlx::query_as!(
+            ArchivedJob,
+            r#"
+            SELECT id, job_id, job_type, esign_id, document_type, outcome, attempt, worker_id, duration_ms,
+                   payload, replayed_at, archived_at
+            FROM archived_jobs
+            WHERE ($1::UUID IS NULL OR job_id = $1)
+            ORDER BY archived_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            job_id,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<ArchivedJob>, sqlx::Error> {
+        sqlx::query_as!(
+            ArchivedJob,
+            r#"
+            SELECT id, job_id, job_type, esign_id, document_type, outcome, attempt, worker_id, duration_ms,
+                   payload, replayed_at, archived_at
+            FROM archived_jobs
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Mark the job replayed unless it already was, so concurrent replays
+    /// can't both enqueue it; None if it doesn't exist or was replayed
+    pub async fn claim_for_replay(&self, id: i64) -> Result<Option<ArchivedJob>, sqlx::Error> {
+        sqlx::query_as!(
+            ArchivedJob,
+            r#"
+            UPDATE archived_jobs
+            SET replayed_at = NOW()
+            WHERE id = $1 AND replayed_at IS NULL
+            RETURNING id, job_id, job_type, esign_id, document_type, outcome, attempt, worker_id, duration_ms,
+                      payload, replayed_at, archived_at
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Give back a claim whose replay never made it onto the queue
+    pub async fn release_replay_claim(&self, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query!("UPDATE archived_jobs SET replayed_at = NULL WHERE id = $1", id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete jobs archived before `cutoff` and all but the newest
+    /// `max_jobs`, returning how many went
+    pub async fn prune(&self, cutoff: DateTime<Utc>, max_jobs: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM archived_jobs
+            WHERE archived_at < $1
+               OR id <= (SELECT id FROM archived_jobs ORDER BY id DESC OFFSET $2 LIMIT 1)
+            "#,
+            cutoff,
+            max_jobs
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Keeps jobs that ran to completion, which otherwise leave no trace but
+/// their executions, so they can be looked at and replayed. The archive is
+/// capped by age and by size; the oldest jobs go first.
+pub struct JobArchive {
+    repository: ArchivedJobRepository,
+    retention: chrono::Duration,
+    max_jobs: i64,
+    prune_interval: Duration,
+}
+
+impl JobArchive {
+    /// None unless `JOB_ARCHIVE_ENABLED=true`
+    pub fn from_env(pool: PgPool) -> WorkerResult<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        if var("JOB_ARCHIVE_ENABLED").as_deref() != Some("true") {
+            return Ok(None);
+        }
+
+        let number = |name: &str, default: u64| {
+            var(name)
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map(|v| v.unwrap_or(default))
+                .map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be a number", name)))
+        };
+
+        Ok(Some(Self {
+            repository: ArchivedJobRepository::new(pool),
+            retention: chrono::Duration::days(number("JOB_ARCHIVE_RETENTION_DAYS", 7)? as i64),
+            max_jobs: number("JOB_ARCHIVE_MAX_JOBS", 100_000)?.max(1) as i64,
+            prune_interval: Duration::from_secs(number("JOB_ARCHIVE_PRUNE_INTERVAL_SECONDS", 3600)?.max(1)),
+        }))
+    }
+
+    /// Archive the job if its attempt finished it; retries and dead letters
+    /// have their own trail
+    pub async fn record(&self, job: &FileUploadJob, execution: &JobExecution) {
+        if ![OUTCOME_SUCCEEDED, OUTCOME_QUARANTINED].contains(&execution.outcome) {
+            return;
+        }
+        if let Err(e) = self.repository.archive(job, execution).await {
+            error!("Failed to archive job {}: {}", job.id, e);
+        }
+    }
+
+    /// Prune every `prune_interval` until `shutdown_signal` is set
+    pub async fn run(&self, shutdown_signal: Arc<AtomicBool>) {
+        info!(
+            "Archiving completed jobs for {} days, at most {}, pruning every {:?}",
+            self.retention.num_days(),
+            self.max_jobs,
+            self.prune_interval
+        );
+
+        while !shutdown_signal.load(Ordering::SeqCst) {
+            match self.repository.prune(Utc::now() - self.retention, self.max_jobs).await {
+                Ok(0) => {}
+                Ok(pruned) => info!("Pruned {} archived jobs", pruned),
+                Err(e) => error!("Failed to prune archived jobs: {}", e),
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < self.prune_interval && !shutdown_signal.load(Ordering::SeqCst) {
+                tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+                waited += SHUTDOWN_POLL_INTERVAL;
+            }
+        }
+
+        info!("Job archive pruning stopped");
+    }
+}
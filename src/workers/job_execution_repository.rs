@@ -0,0 +1,268 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::workers::{FileUploadJob, WorkerError, WorkerResult};
+
+pub const OUTCOME_SUCCEEDED: &str = "SUCCEEDED";
+pub const OUTCOME_QUARANTINED: &str = "QUARANTINED";
+pub const OUTCOME_RETRIED: &str = "RETRIED";
+pub const OUTCOME_DEAD_LETTERED: &str = "DEAD_LETTERED";
+/// Another worker held the submission's lock for the whole wait
+pub const OUTCOME_LOCK_UNAVAILABLE: &str = "LOCK_UNAVAILABLE";
+/// The outcome couldn't be recorded, so the job went back on the queue
+pub const OUTCOME_ERROR: &str = "ERROR";
+
+/// How often the wait between prunes checks the shutdown signal
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Rows deleted per statement, so a prune never holds a long lock
+const PRUNE_BATCH_SIZE: i64 = 10_000;
+
+/// One attempt at a job, filled in by the consumer as it goes
+#[derive(Debug)]
+pub struct JobExecution {
+    pub job_id: Uuid,
+    pub job_type: String,
+    pub esign_id: String,
+    pub document_type: String,
+    /// 1 for the first try
+    pub attempt: i32,
+    pub worker_id: String,
+    pub outcome: &'static str,
+    pub error: Option<String>,
+    pub queue_wait: Option<Duration>,
+    pub lock_wait: Option<Duration>,
+    pub started_at: DateTime<Utc>,
+    started: Instant,
+}
+
+impl JobExecution {
+    pub fn start(job: &FileUploadJob, worker_id: &str) -> Self {
+        Self {
+            job_id: job.id,
+            job_type: job.kind.as_str().to_string(),
+            esign_id: job.esign_id.clone(),
+            document_type: job.document_type.clone(),
+            attempt: job.retry_count as i32 + 1,
+            worker_id: worker_id.to_string(),
+            outcome: OUTCOME_ERROR,
+            error: None,
+            queue_wait: job.enqueued_at.map(|enqueued_at| (Utc::now() - enqueued_at).to_std().unwrap_or_default()),
+            lock_wait: None,
+            started_at: Utc::now(),
+            started: Instant::now(),
+        }
+    }
+
+    /// How the attempt ended, and the error it failed with if any
+    pub fn finish(&mut self, outcome: &'static str, error: Option<&WorkerError>) {
+        self.outcome = outcome;
+        self.error = error.map(|e| e.to_string());
+    }
+
+    /// Time since the attempt started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobExecutionRecord {
+    pub id: i64,
+    pub job_id: Uuid,
+    pub job_type: String,
+    pub esign_id: String,
+    pub document_type: String,
+    pub attempt: i32,
+    pub worker_id: String,
+    pub outcome: String,
+    pub error: Option<String>,
+    pub queue_wait_ms: Option<i64>,
+    pub lock_wait_ms: Option<i64>,
+    pub duration_ms: i64,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Filters of the executions listing; unset fields match everything and
+/// `started_to` is exclusive
+#[derive(Debug, Default)]
+pub struct JobExecutionFilter {
+    pub job_id: Option<Uuid>,
+    pub outcome: Option<String>,
+    pub job_type: Option<String>,
+    pub started_from: Option<DateTime<Utc>>,
+    pub started_to: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone)]
+pub struct JobExecutionRepository {
+    pool: PgPool,
+}
+
+impl JobExecutionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(&self, execution: &JobExecution) -> Result<(), sqlx::Error> {
+        let duration = execution.elapsed();
+        let millis = |duration: Duration| duration.as_millis() as i64;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO job_executions (
+                job_id, job_type, esign_id, document_type, attempt, worker_id, outcome, error,
+                queue_wait_ms, lock_wait_ms, duration_ms, started_at, finished_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            "#,
+            execution.job_id,
+            execution.job_type,
+            execution.esign_id,
+            execution.document_type,
+            execution.attempt,
+            execution.worker_id,
+            execution.outcome,
+            execution.error,
+            execution.queue_wait.map(millis),
+            execution.lock_wait.map(millis),
+            millis(duration),
+            execution.started_at,
+            execution.started_at + duration
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Executions matching `filter`, newest first
+    pub async fn find_all(
+        &self,
+        filter: &JobExecutionFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<JobExecutionRecord>, sqlx::Error> {
+        sqlx::query_as!(
+            JobExecutionRecord,
+            r#"
+            SELECT id, job_id, job_type, esign_id, document_type, attempt, worker_id, outcome, error,
+                   queue_wait_ms, lock_wait_ms, duration_ms, started_at, finished_at
+            FROM job_executions
+            WHERE ($1::UUID IS NULL OR job_id = $1)
+              AND ($2::TEXT IS NULL OR outcome = $2)
+              AND ($3::TEXT IS NULL OR job_type = $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR started_at >= $4)
+              AND ($5::TIMESTAMPTZ IS NULL OR started_at < $5)
+            ORDER BY started_at DESC, id DESC
+            LIMIT $6 OFFSET $7
+            "#,
+            filter.job_id,
+            filter.outcome,
+            filter.job_type,
+            filter.started_from,
+            filter.started_to,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Delete up to `limit` executions started before `cutoff`, returning
+    /// how many went
+    pub async fn prune(&self, cutoff: DateTime<Utc>, limit: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM job_executions
+            WHERE id IN (SELECT id FROM job_executions WHERE started_at < $1 LIMIT $2)
+            "#,
+            cutoff,
+            limit
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Drops executions older than the retention window, which would otherwise
+/// grow by a row per attempt forever
+pub struct JobExecutionRetention {
+    repository: JobExecutionRepository,
+    retention: chrono::Duration,
+    prune_interval: Duration,
+}
+
+impl JobExecutionRetention {
+    /// `JOB_EXECUTION_RETENTION_DAYS` (30) and
+    /// `JOB_EXECUTION_PRUNE_INTERVAL_SECONDS` (3600); None when the retention
+    /// is 0, which keeps executions forever
+    pub fn from_env(pool: PgPool) -> WorkerResult<Option<Self>> {
+        let number = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map(|v| v.unwrap_or(default))
+                .map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be a number", name)))
+        };
+
+        let retention_days = number("JOB_EXECUTION_RETENTION_DAYS", 30)?;
+        if retention_days == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            repository: JobExecutionRepository::new(pool),
+            retention: chrono::Duration::days(retention_days as i64),
+            prune_interval: Duration::from_secs(number("JOB_EXECUTION_PRUNE_INTERVAL_SECONDS", 3600)?.max(1)),
+        }))
+    }
+
+    /// Prune every `prune_interval` until `shutdown_signal` is set
+    pub async fn run(&self, shutdown_signal: Arc<AtomicBool>) {
+        info!(
+            "Keeping job executions for {} days, pruning every {:?}",
+            self.retention.num_days(),
+            self.prune_interval
+        );
+
+        while !shutdown_signal.load(Ordering::SeqCst) {
+            let cutoff = Utc::now() - self.retention;
+            let mut pruned = 0;
+            while !shutdown_signal.load(Ordering::SeqCst) {
+                match self.repository.prune(cutoff, PRUNE_BATCH_SIZE).await {
+                    Ok(0) => break,
+                    Ok(count) => pruned += count,
+                    Err(e) => {
+                        error!("Failed to prune job executions: {}", e);
+                        break;
+                    }
+                }
+            }
+            if pruned > 0 {
+                info!("Pruned {} job executions", pruned);
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < self.prune_interval && !shutdown_signal.load(Ordering::SeqCst) {
+                tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+                waited += SHUTDOWN_POLL_INTERVAL;
+            }
+        }
+
+        info!("Job execution pruning stopped");
+    }
+}
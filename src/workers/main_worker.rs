@@ -1,34 +1,95 @@
 use crate::workers::{
-    DlqWorker, FileUploadWorker, WorkerConfig, WorkerError, WorkerMetrics, WorkerResult,
+    DlqWorker, FailedJobRepository, FileUploadWorker, RedisConnections, RedisQueue, WorkerConfig, WorkerError, WorkerMetrics,
+    WorkerResult,
 };
+use crate::workers::heartbeat::WorkerHeartbeats;
+use crate::workers::job_archive::JobArchive;
+use crate::workers::job_execution_repository::{JobExecutionRepository, JobExecutionRetention};
+use crate::workers::document_scanning::DocumentScanner;
+use crate::workers::document_progress::DocumentProgress;
+use crate::workers::event_publisher::EventPublisher;
+use crate::workers::image_preprocessing::ImagePreprocessor;
+use crate::workers::notifications_listener::NotificationsListener;
+use crate::workers::report_generation::ReportGenerator;
+use crate::workers::object_copy::ObjectCopier;
+use crate::workers::notification_delivery::NotificationSender;
+use crate::workers::orphan_cleanup::OrphanCleaner;
+use crate::workers::sla_monitor::SlaMonitor;
+use crate::workers::supervisor::WorkerTasks;
+use crate::workers::user_purge::UserPurger;
+use crate::commons::minio_service::MinioService;
+use crate::submissions::submission_data_spill::SubmissionDataSpill;
+use sqlx::PgPool;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
 use tokio::time::timeout;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+/// How often drain mode checks whether the queues are empty
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Consecutive idle checks before drain mode stops the consumers, so a job
+/// that was just dequeued but not yet reported as in flight isn't missed
+const DRAIN_IDLE_CHECKS: u32 = 2;
+
+/// How often the worker metrics are logged
+const METRICS_LOG_INTERVAL: Duration = Duration::from_secs(300);
+
+/// What a drain run did, reported when the process exits
+#[derive(Debug)]
+pub struct DrainSummary {
+    pub jobs_processed: u64,
+    pub jobs_succeeded: u64,
+    pub jobs_failed: u64,
+    pub jobs_moved_to_dlq: u64,
+    pub elapsed: Duration,
+}
+
+impl std::fmt::Display for DrainSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "processed={}, succeeded={}, failed={}, moved_to_dlq={}, elapsed={:?}",
+            self.jobs_processed, self.jobs_succeeded, self.jobs_failed, self.jobs_moved_to_dlq, self.elapsed
+        )
+    }
+}
 
 /// MainWorker coordinates both the main file upload worker and DLQ worker pools
 pub struct MainWorker {
     config: WorkerConfig,
+    pool: PgPool,
     shutdown_signal: Arc<AtomicBool>,
     metrics: Arc<WorkerMetrics>,
+    heartbeats: Arc<WorkerHeartbeats>,
+    redis: Option<RedisConnections>,
     file_upload_worker: Option<FileUploadWorker>,
     dlq_worker: Option<DlqWorker>,
+    tasks: Mutex<WorkerTasks>,
 }
 
 impl MainWorker {
     /// Create a new MainWorker with the given configuration
-    pub fn new(config: WorkerConfig) -> Self {
+    pub fn new(config: WorkerConfig, pool: PgPool) -> Self {
         let shutdown_signal = Arc::new(AtomicBool::new(false));
         let metrics = Arc::new(WorkerMetrics::new());
+        let heartbeats = Arc::new(WorkerHeartbeats::new());
+        let tasks = WorkerTasks::new(shutdown_signal.clone(), metrics.clone(), heartbeats.clone());
 
         Self {
             config,
+            pool,
             shutdown_signal,
             metrics,
+            heartbeats,
+            redis: None,
             file_upload_worker: None,
             dlq_worker: None,
+            tasks: Mutex::new(tasks),
         }
     }
 
@@ -36,14 +97,24 @@ impl MainWorker {
     pub async fn start(&mut self) -> WorkerResult<()> {
         info!("Starting File Upload Worker System");
 
+        let tasks = self.tasks.get_mut();
+
         // Start metrics reporting background task
-        let metrics_clone = self.metrics.clone();
-        tokio::spawn(async move {
-            loop {
-                tokio::time::sleep(std::time::Duration::from_secs(300)).await;
-                metrics_clone.log_metrics();
-            }
-        });
+        let metrics = self.metrics.clone();
+        let shutdown_signal = self.shutdown_signal.clone();
+        tasks.spawn("metrics-log", move || Self::log_metrics_periodically(metrics.clone(), shutdown_signal.clone()));
+
+        // One shared Redis connection for both pools instead of one per consumer
+        let redis = RedisConnections::connect(&self.config, self.metrics.clone()).await?;
+        self.redis = Some(redis.clone());
+
+        // Documents the worker reads back may have been spilled to MinIO
+        if let Err(e) = Self::install_submission_data_spill().await {
+            warn!("Spilled submission data can't be read or written: {}", e);
+        }
+
+        #[cfg(feature = "chaos")]
+        crate::workers::fault_injection::install_from_env()?;
 
         // Start the main file upload worker if enabled
         if self.config.background_worker_thread_enabled {
@@ -52,13 +123,85 @@ impl MainWorker {
                 self.config.background_worker_consumer_thread_count
             );
             
+            let image_preprocessor = if self.config.image_preprocessing_enabled {
+                info!("Image preprocessing is enabled");
+                Some(Arc::new(ImagePreprocessor::from_env().await?))
+            } else {
+                None
+            };
+
+            let document_scanner = DocumentScanner::from_env(self.pool.clone()).await?.map(Arc::new);
+            if document_scanner.is_some() {
+                info!("Document scanning is enabled");
+            }
+
+            // Report jobs fail for good when this is missing, other jobs are unaffected
+            let report_generator = match ReportGenerator::from_env(self.pool.clone()).await {
+                Ok(report_generator) => Some(Arc::new(report_generator)),
+                Err(e) => {
+                    warn!("Report generation is disabled: {}", e);
+                    None
+                }
+            };
+
+            // Notification jobs fail for good when no channel is configured
+            let notification_sender = NotificationSender::from_env(self.pool.clone())?.map(Arc::new);
+            match &notification_sender {
+                Some(sender) => info!("Sending notifications on {:?}", sender.channels()),
+                None => warn!("Notifications are disabled: no channel is configured"),
+            }
+
+            // Copy jobs fail for good when this is missing, other jobs are unaffected
+            let object_copier = match ObjectCopier::from_env().await {
+                Ok(object_copier) => Some(Arc::new(object_copier)),
+                Err(e) => {
+                    warn!("Object copies are disabled: {}", e);
+                    None
+                }
+            };
+
+            let document_progress = Arc::new(DocumentProgress::from_env(self.pool.clone())?);
+
+            let job_archive = JobArchive::from_env(self.pool.clone())?.map(Arc::new);
+            if let Some(job_archive) = &job_archive {
+                let (job_archive, shutdown_signal) = (job_archive.clone(), self.shutdown_signal.clone());
+                tasks.spawn("job-archive-prune", move || {
+                    let (job_archive, shutdown_signal) = (job_archive.clone(), shutdown_signal.clone());
+                    async move {
+                        job_archive.run(shutdown_signal).await;
+                        Ok(())
+                    }
+                });
+            }
+
+            if let Some(retention) = JobExecutionRetention::from_env(self.pool.clone())?.map(Arc::new) {
+                let shutdown_signal = self.shutdown_signal.clone();
+                tasks.spawn("job-execution-prune", move || {
+                    let (retention, shutdown_signal) = (retention.clone(), shutdown_signal.clone());
+                    async move {
+                        retention.run(shutdown_signal).await;
+                        Ok(())
+                    }
+                });
+            }
+
             let file_upload_worker = FileUploadWorker::new(
                 self.config.clone(),
+                redis.clone(),
                 self.shutdown_signal.clone(),
                 self.metrics.clone(),
+                self.heartbeats.clone(),
+                image_preprocessor,
+                document_scanner,
+                report_generator,
+                notification_sender,
+                object_copier,
+                document_progress,
+                JobExecutionRepository::new(self.pool.clone()),
+                job_archive,
             )?;
             
-            file_upload_worker.start().await?;
+            file_upload_worker.start(tasks).await?;
             self.file_upload_worker = Some(file_upload_worker);
             
             info!("Main upload worker pool started successfully");
@@ -66,6 +209,69 @@ impl MainWorker {
             info!("Main upload worker pool is disabled");
         }
 
+        if let Some(listener) =
+            NotificationsListener::from_env(&self.config, self.pool.clone(), redis.clone(), self.metrics.clone()).await?
+        {
+            // Restarts reuse the listener, and with it its queue connection
+            let listener = Arc::new(Mutex::new(listener));
+            let shutdown_signal = self.shutdown_signal.clone();
+            tasks.spawn("notifications-listener", move || {
+                let (listener, shutdown_signal) = (listener.clone(), shutdown_signal.clone());
+                async move {
+                    listener.lock().await.run(shutdown_signal).await;
+                    Ok(())
+                }
+            });
+        }
+
+        if let Some(purger) = UserPurger::from_env(self.pool.clone(), redis.clone(), self.metrics.clone()).await? {
+            let purger = Arc::new(purger);
+            let shutdown_signal = self.shutdown_signal.clone();
+            tasks.spawn("user-purge", move || {
+                let (purger, shutdown_signal) = (purger.clone(), shutdown_signal.clone());
+                async move {
+                    purger.run(shutdown_signal).await;
+                    Ok(())
+                }
+            });
+        }
+
+        if let Some(publisher) = EventPublisher::from_env(self.pool.clone(), redis.clone(), self.metrics.clone())? {
+            let publisher = Arc::new(publisher);
+            let shutdown_signal = self.shutdown_signal.clone();
+            tasks.spawn("event-publisher", move || {
+                let (publisher, shutdown_signal) = (publisher.clone(), shutdown_signal.clone());
+                async move {
+                    publisher.run(shutdown_signal).await;
+                    Ok(())
+                }
+            });
+        }
+
+        if let Some(monitor) = SlaMonitor::from_env(self.pool.clone(), self.metrics.clone())? {
+            let monitor = Arc::new(monitor);
+            let shutdown_signal = self.shutdown_signal.clone();
+            tasks.spawn("sla-monitor", move || {
+                let (monitor, shutdown_signal) = (monitor.clone(), shutdown_signal.clone());
+                async move {
+                    monitor.run(shutdown_signal).await;
+                    Ok(())
+                }
+            });
+        }
+
+        if let Some(cleaner) = OrphanCleaner::from_env(&self.config, self.pool.clone(), redis.clone(), self.metrics.clone()).await? {
+            let cleaner = Arc::new(cleaner);
+            let shutdown_signal = self.shutdown_signal.clone();
+            tasks.spawn("orphan-cleanup", move || {
+                let (cleaner, shutdown_signal) = (cleaner.clone(), shutdown_signal.clone());
+                async move {
+                    cleaner.run(shutdown_signal).await;
+                    Ok(())
+                }
+            });
+        }
+
         // Start the DLQ worker if enabled
         if self.config.file_upload_worker_dlq_thread_enabled {
             info!(
@@ -75,11 +281,14 @@ impl MainWorker {
             
             let dlq_worker = DlqWorker::new(
                 self.config.clone(),
+                redis.clone(),
                 self.shutdown_signal.clone(),
                 self.metrics.clone(),
+                self.heartbeats.clone(),
+                FailedJobRepository::new(self.pool.clone()),
             )?;
             
-            dlq_worker.start().await?;
+            dlq_worker.start(tasks).await?;
             self.dlq_worker = Some(dlq_worker);
             
             info!("DLQ worker pool started successfully");
@@ -97,37 +306,146 @@ impl MainWorker {
         self.shutdown_signal.store(true, Ordering::SeqCst);
     }
 
-    /// Wait for all workers to complete in-progress jobs and shut down gracefully
+    /// Wait for all workers to complete in-progress jobs and shut down
+    /// gracefully, aborting the tasks still running once the grace period is over
     pub async fn await_shutdown(&self) -> WorkerResult<()> {
-        let grace_period = self.config.graceful_shutdown_timeout;
-        
-        info!("Waiting up to {:?} for workers to shutdown gracefully", grace_period);
-        
-        match timeout(grace_period, async {
-            // In a real implementation, you would wait for completion signals
-            // For now, just wait for a reasonable time to allow workers to finish
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-            Ok(())
-        })
-        .await
-        {
-            Ok(Ok(())) => {
+        // A consumer blocked in BRPOP only sees the signal once its wait ends
+        let consumer_wait = self
+            .config
+            .worker_consumer_wait_interval
+            .max(self.config.file_upload_worker_dlq_wait_interval);
+        let grace_period = self.config.graceful_shutdown_timeout + consumer_wait;
+
+        let mut tasks = self.tasks.lock().await;
+        info!(
+            "Waiting up to {:?} for {} worker tasks to shutdown gracefully",
+            grace_period,
+            tasks.len()
+        );
+
+        match timeout(grace_period, tasks.join_all()).await {
+            Ok(()) => {
                 info!("All worker pools shutdown gracefully");
                 // Log final metrics
                 self.metrics.log_metrics();
                 Ok(())
             }
-            Ok(Err(e)) => {
-                error!("Error during worker shutdown: {}", e);
-                Err(e)
-            }
             Err(_) => {
-                error!("Worker shutdown timed out after {:?}", grace_period);
+                error!(
+                    "Worker shutdown timed out after {:?}, aborting {} tasks",
+                    grace_period,
+                    tasks.len()
+                );
+                tasks.abort_all();
+                tasks.join_all().await;
                 Err(WorkerError::Shutdown)
             }
         }
     }
 
+    /// Make spilled submission data readable and writable by the worker
+    async fn install_submission_data_spill() -> WorkerResult<()> {
+        let var = |name: &str| std::env::var(name).map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be set", name)));
+
+        let minio_service = MinioService::new(
+            &var("MINIO_ENDPOINT")?,
+            &var("MINIO_ACCESS_KEY")?,
+            &var("MINIO_SECRET_KEY")?,
+            &var("MINIO_BUCKET_NAME")?,
+        )
+        .await
+        .map_err(WorkerError::Config)?;
+
+        if let Some(spill) = SubmissionDataSpill::from_env(minio_service)? {
+            spill.install();
+        }
+        Ok(())
+    }
+
+    /// Wait until the main queue, its delayed retries and the DLQ are empty
+    /// with no job in flight, then stop the consumers and report what was processed
+    pub async fn run_until_drained(&self) -> WorkerResult<DrainSummary> {
+        let started_at = Instant::now();
+        let redis = self.redis.clone().ok_or_else(|| WorkerError::Config(anyhow::anyhow!("worker system is not started")))?;
+        let mut queue = RedisQueue::from_connections(
+            redis.shared(),
+            redis.shared(),
+            self.config.worker_upload_file_queue.clone(),
+            self.config.worker_upload_file_dlq.clone(),
+        );
+
+        let mut idle_checks = 0;
+        while idle_checks < DRAIN_IDLE_CHECKS {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+
+            let main_depth = queue.get_queue_length().await? + queue.get_delayed_length().await?;
+            // Retained dead letters stay in the DLQ on purpose, so only wait for it without retention
+            let dlq_depth = match self.config.retry_policies.retains_dead_letters() {
+                true => 0,
+                false => queue.get_dlq_length().await?,
+            };
+            let idle = main_depth == 0 && dlq_depth == 0 && self.heartbeats.in_flight() == 0;
+            idle_checks = if idle { idle_checks + 1 } else { 0 };
+        }
+
+        info!("Queues are drained, stopping consumers");
+        self.signal_shutdown();
+        self.await_shutdown().await?;
+
+        Ok(DrainSummary {
+            jobs_processed: self.metrics.jobs_processed.load(Ordering::Relaxed),
+            jobs_succeeded: self.metrics.jobs_succeeded.load(Ordering::Relaxed),
+            jobs_failed: self.metrics.jobs_failed.load(Ordering::Relaxed),
+            jobs_moved_to_dlq: self.metrics.jobs_moved_to_dlq.load(Ordering::Relaxed),
+            elapsed: started_at.elapsed(),
+        })
+    }
+
+    /// Log the worker metrics every few minutes until shutdown
+    async fn log_metrics_periodically(metrics: Arc<WorkerMetrics>, shutdown_signal: Arc<AtomicBool>) -> WorkerResult<()> {
+        let mut next_log = Instant::now() + METRICS_LOG_INTERVAL;
+        let mut previous = metrics.snapshot();
+        while !shutdown_signal.load(Ordering::SeqCst) {
+            if Instant::now() >= next_log {
+                metrics.log_metrics();
+                // Totals only ever grow; the interval's delta gives the rates
+                let current = metrics.snapshot();
+                current.delta_since(&previous).log();
+                previous = current;
+                next_log = Instant::now() + METRICS_LOG_INTERVAL;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        Ok(())
+    }
+
+    /// Stop consuming new jobs while letting in-flight jobs finish. Unlike a
+    /// shutdown the process keeps running so it can still be inspected.
+    pub fn drain(&self) {
+        info!("Draining worker pools: consumers will stop after their in-flight jobs");
+        self.shutdown_signal.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether consumers have been told to stop picking up jobs
+    pub fn is_draining(&self) -> bool {
+        self.shutdown_signal.load(Ordering::SeqCst)
+    }
+
+    /// Get the worker configuration
+    pub fn config(&self) -> &WorkerConfig {
+        &self.config
+    }
+
+    /// Get the worker system's Redis connections, once started
+    pub fn redis(&self) -> Option<RedisConnections> {
+        self.redis.clone()
+    }
+
+    /// Get the per-consumer heartbeat registry
+    pub fn heartbeats(&self) -> Arc<WorkerHeartbeats> {
+        self.heartbeats.clone()
+    }
+
     /// Get a reference to the metrics collector
     pub fn metrics(&self) -> Arc<WorkerMetrics> {
         self.metrics.clone()
@@ -1,7 +1,14 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 use tracing::{info, warn};
 
+use crate::services::metrics_service::MetricTags;
+use crate::workers::error::{ErrorClass, WorkerError};
+
 /// WorkerMetrics tracks performance statistics for the worker pools
 pub struct WorkerMetrics {
     // Success/failure counters
@@ -13,13 +20,66 @@ pub struct WorkerMetrics {
     // Error type counters
     pub url_expired_errors: AtomicU64,
     pub general_errors: AtomicU64,
+    pub redis_connection_errors: AtomicU64,
+
+    // Job failures by error class
+    pub network_errors: AtomicU64,
+    pub validation_errors: AtomicU64,
+    pub permanent_errors: AtomicU64,
+    pub timeout_errors: AtomicU64,
+    pub disallowed_source_errors: AtomicU64,
+
+    // Documents moved to quarantine by the scanner
+    pub documents_quarantined: AtomicU64,
+
+    // Consumer tasks that panicked and were restarted
+    pub consumer_panics: AtomicU64,
+
+    // Worker tasks started again after failing or panicking
+    pub task_restarts: AtomicU64,
+
+    // Uploads enqueued from MinIO bucket notifications
+    pub bucket_notifications: AtomicU64,
+
+    // Closed accounts purged after their retention window
+    pub users_purged: AtomicU64,
+
+    // Submissions flagged for taking longer than their SLA
+    pub decision_sla_breaches: AtomicU64,
+    pub review_sla_breaches: AtomicU64,
+
+    // Bucket objects looked at by the orphan cleanup, the ones no submission
+    // references, and those of them deleted
+    pub orphan_objects_scanned: AtomicU64,
+    pub orphan_objects_found: AtomicU64,
+    pub orphan_objects_deleted: AtomicU64,
+
+    // Submission events published to Kafka, and failed attempts
+    pub events_published: AtomicU64,
+    pub event_publish_failures: AtomicU64,
+
+    // Queue entries that weren't jobs, set aside on the malformed list
+    pub malformed_jobs: AtomicU64,
+
+    // Jobs returned to the queue from processing lists whose consumer went away
+    pub jobs_reclaimed: AtomicU64,
+
+    // Finished jobs by their tags, outcome included
+    jobs_by_flow: Mutex<HashMap<MetricTags, u64>>,
     
-    // Timing metrics (stored as milliseconds)
-    pub total_processing_time_ms: AtomicU64,
+    // Latency distributions: time spent processing a job, waiting for its
+    // submission lock, and waiting in the queue between enqueue and dequeue
+    pub job_processing_time: LatencyHistogram,
+    pub lock_wait_time: LatencyHistogram,
+    pub queue_wait_time: LatencyHistogram,
     
     // Queue depth
     pub main_queue_depth: AtomicU64,
     pub dlq_depth: AtomicU64,
+
+    // Submission events waiting in the outbox, and the age of the oldest
+    pub event_outbox_backlog: AtomicU64,
+    pub event_publisher_lag_seconds: AtomicU64,
 }
 
 impl WorkerMetrics {
@@ -31,9 +91,34 @@ impl WorkerMetrics {
             jobs_moved_to_dlq: AtomicU64::new(0),
             url_expired_errors: AtomicU64::new(0),
             general_errors: AtomicU64::new(0),
-            total_processing_time_ms: AtomicU64::new(0),
+            redis_connection_errors: AtomicU64::new(0),
+            network_errors: AtomicU64::new(0),
+            validation_errors: AtomicU64::new(0),
+            permanent_errors: AtomicU64::new(0),
+            timeout_errors: AtomicU64::new(0),
+            disallowed_source_errors: AtomicU64::new(0),
+            documents_quarantined: AtomicU64::new(0),
+            consumer_panics: AtomicU64::new(0),
+            task_restarts: AtomicU64::new(0),
+            bucket_notifications: AtomicU64::new(0),
+            users_purged: AtomicU64::new(0),
+            decision_sla_breaches: AtomicU64::new(0),
+            review_sla_breaches: AtomicU64::new(0),
+            orphan_objects_scanned: AtomicU64::new(0),
+            orphan_objects_found: AtomicU64::new(0),
+            orphan_objects_deleted: AtomicU64::new(0),
+            events_published: AtomicU64::new(0),
+            event_publish_failures: AtomicU64::new(0),
+            malformed_jobs: AtomicU64::new(0),
+            jobs_reclaimed: AtomicU64::new(0),
+            jobs_by_flow: Mutex::new(HashMap::new()),
+            job_processing_time: LatencyHistogram::new(),
+            lock_wait_time: LatencyHistogram::new(),
+            queue_wait_time: LatencyHistogram::new(),
             main_queue_depth: AtomicU64::new(0),
             dlq_depth: AtomicU64::new(0),
+            event_outbox_backlog: AtomicU64::new(0),
+            event_publisher_lag_seconds: AtomicU64::new(0),
         }
     }
     
@@ -61,9 +146,95 @@ impl WorkerMetrics {
         self.general_errors.fetch_add(1, Ordering::Relaxed);
     }
     
+    pub fn record_redis_connection_error(&self) {
+        self.redis_connection_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a job failure under its error class
+    pub fn record_error_class(&self, error: &WorkerError) {
+        let counter = match error.class() {
+            ErrorClass::Network => &self.network_errors,
+            ErrorClass::Validation => &self.validation_errors,
+            ErrorClass::Permanent => &self.permanent_errors,
+            ErrorClass::Timeout => &self.timeout_errors,
+            ErrorClass::DisallowedSource => &self.disallowed_source_errors,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_document_quarantined(&self) {
+        self.documents_quarantined.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_consumer_panic(&self) {
+        self.consumer_panics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_task_restart(&self) {
+        self.task_restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_bucket_notification(&self) {
+        self.bucket_notifications.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_user_purged(&self) {
+        self.users_purged.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_malformed_job(&self) {
+        self.malformed_jobs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_jobs_reclaimed(&self, count: u64) {
+        self.jobs_reclaimed.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn record_decision_sla_breaches(&self, count: u64) {
+        self.decision_sla_breaches.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn record_review_sla_breaches(&self, count: u64) {
+        self.review_sla_breaches.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn record_orphan_objects(&self, scanned: u64, found: u64, deleted: u64) {
+        self.orphan_objects_scanned.fetch_add(scanned, Ordering::Relaxed);
+        self.orphan_objects_found.fetch_add(found, Ordering::Relaxed);
+        self.orphan_objects_deleted.fetch_add(deleted, Ordering::Relaxed);
+    }
+
+    pub fn record_events_published(&self, count: u64) {
+        self.events_published.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn record_event_publish_failure(&self) {
+        self.event_publish_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn update_event_outbox(&self, backlog: u64, lag_seconds: u64) {
+        self.event_outbox_backlog.store(backlog, Ordering::Relaxed);
+        self.event_publisher_lag_seconds.store(lag_seconds, Ordering::Relaxed);
+    }
+
+    /// Count a finished job under its tenant, submission type, document type
+    /// and `outcome`
+    pub fn record_job_outcome(&self, tags: MetricTags, outcome: &str) {
+        if let Ok(mut jobs) = self.jobs_by_flow.lock() {
+            *jobs.entry(tags.outcome(outcome)).or_insert(0) += 1;
+        }
+    }
+
     pub fn record_processing_time(&self, duration: Duration) {
-        let ms = duration.as_millis() as u64;
-        self.total_processing_time_ms.fetch_add(ms, Ordering::Relaxed);
+        self.job_processing_time.observe(duration);
+    }
+
+    pub fn record_lock_wait_time(&self, duration: Duration) {
+        self.lock_wait_time.observe(duration);
+    }
+
+    pub fn record_queue_wait_time(&self, duration: Duration) {
+        self.queue_wait_time.observe(duration);
     }
     
     pub fn update_queue_depth(&self, main_depth: u64, dlq_depth: u64) {
@@ -80,26 +251,39 @@ impl WorkerMetrics {
             let jobs_moved_to_dlq = self.jobs_moved_to_dlq.load(Ordering::Relaxed);
             let url_expired_errors = self.url_expired_errors.load(Ordering::Relaxed);
             let general_errors = self.general_errors.load(Ordering::Relaxed);
-            let total_time_ms = self.total_processing_time_ms.load(Ordering::Relaxed);
-            let avg_time_ms = if jobs_processed > 0 {
-                total_time_ms / jobs_processed
-            } else {
-                0
-            };
+            let network_errors = self.network_errors.load(Ordering::Relaxed);
+            let validation_errors = self.validation_errors.load(Ordering::Relaxed);
+            let permanent_errors = self.permanent_errors.load(Ordering::Relaxed);
+            let timeout_errors = self.timeout_errors.load(Ordering::Relaxed);
+            let disallowed_source_errors = self.disallowed_source_errors.load(Ordering::Relaxed);
+            let documents_quarantined = self.documents_quarantined.load(Ordering::Relaxed);
+            let consumer_panics = self.consumer_panics.load(Ordering::Relaxed);
             let main_depth = self.main_queue_depth.load(Ordering::Relaxed);
             let dlq_depth = self.dlq_depth.load(Ordering::Relaxed);
             
             info!(
                 "Worker metrics: processed={}, succeeded={}, failed={}, moved_to_dlq={}, \
-                 url_expired_errors={}, general_errors={}, avg_time_ms={}, \
-                 main_queue_depth={}, dlq_depth={}",
+                 url_expired_errors={}, general_errors={}, network_errors={}, \
+                 validation_errors={}, permanent_errors={}, timeout_errors={}, \
+                 disallowed_source_errors={}, \
+                 documents_quarantined={}, consumer_panics={}, processing_ms=[{}], \
+                 lock_wait_ms=[{}], queue_wait_ms=[{}], main_queue_depth={}, dlq_depth={}",
                 jobs_processed,
                 jobs_succeeded,
                 jobs_failed,
                 jobs_moved_to_dlq,
                 url_expired_errors,
                 general_errors,
-                avg_time_ms,
+                network_errors,
+                validation_errors,
+                permanent_errors,
+                timeout_errors,
+                disallowed_source_errors,
+                documents_quarantined,
+                consumer_panics,
+                self.job_processing_time.summary(),
+                self.lock_wait_time.summary(),
+                self.queue_wait_time.summary(),
                 main_depth,
                 dlq_depth
             );
@@ -122,6 +306,116 @@ impl WorkerMetrics {
         }
     }
     
+    /// Counters with their Prometheus name and help
+    fn counters(&self) -> Vec<(&'static str, &'static str, &AtomicU64)> {
+        vec![
+            ("worker_jobs_processed_total", "Jobs picked up by a consumer", &self.jobs_processed),
+            ("worker_jobs_succeeded_total", "Jobs completed successfully", &self.jobs_succeeded),
+            ("worker_jobs_failed_total", "Jobs that failed permanently", &self.jobs_failed),
+            ("worker_jobs_moved_to_dlq_total", "Jobs moved to the dead letter queue", &self.jobs_moved_to_dlq),
+            ("worker_url_expired_errors_total", "Jobs failed because the document URL expired", &self.url_expired_errors),
+            ("worker_general_errors_total", "Jobs failed with any other error", &self.general_errors),
+            ("worker_redis_connection_errors_total", "Redis connection failures seen by the workers", &self.redis_connection_errors),
+            ("worker_network_errors_total", "Job failures worth retrying (network, storage, database)", &self.network_errors),
+            ("worker_validation_errors_total", "Job failures caused by malformed jobs", &self.validation_errors),
+            ("worker_permanent_errors_total", "Job failures that can never succeed", &self.permanent_errors),
+            ("worker_timeout_errors_total", "Jobs cancelled for running past the job timeout", &self.timeout_errors),
+            ("worker_disallowed_source_errors_total", "Upload jobs whose document URL isn't on the allow-list", &self.disallowed_source_errors),
+            ("worker_documents_quarantined_total", "Infected documents moved to quarantine", &self.documents_quarantined),
+            ("worker_consumer_panics_total", "Consumer tasks that panicked and were restarted", &self.consumer_panics),
+            ("worker_task_restarts_total", "Worker tasks started again after failing or panicking", &self.task_restarts),
+            ("worker_bucket_notifications_total", "Uploads enqueued from MinIO bucket notifications", &self.bucket_notifications),
+            ("worker_users_purged_total", "Closed accounts purged after the retention window", &self.users_purged),
+            ("worker_malformed_jobs_total", "Queue entries that weren't jobs, set aside on the malformed list", &self.malformed_jobs),
+            ("worker_jobs_reclaimed_total", "Jobs returned to the queue after their visibility timeout expired", &self.jobs_reclaimed),
+            ("worker_decision_sla_breaches_total", "Submissions without a decision past the decision SLA", &self.decision_sla_breaches),
+            ("worker_review_sla_breaches_total", "Submissions left in manual review past the review SLA", &self.review_sla_breaches),
+            ("worker_orphan_objects_scanned_total", "Bucket objects checked by the orphan cleanup", &self.orphan_objects_scanned),
+            ("worker_orphan_objects_found_total", "Bucket objects no submission references", &self.orphan_objects_found),
+            ("worker_orphan_objects_deleted_total", "Orphaned bucket objects deleted", &self.orphan_objects_deleted),
+            ("worker_events_published_total", "Submission events published to Kafka", &self.events_published),
+            ("worker_event_publish_failures_total", "Submission events Kafka failed to acknowledge", &self.event_publish_failures),
+        ]
+    }
+
+    /// Gauges with their Prometheus name and help
+    fn gauges(&self) -> Vec<(&'static str, &'static str, &AtomicU64)> {
+        vec![
+            ("worker_main_queue_depth", "Last observed main queue depth", &self.main_queue_depth),
+            ("worker_dlq_depth", "Last observed dead letter queue depth", &self.dlq_depth),
+            ("worker_event_outbox_backlog", "Submission events waiting to be published", &self.event_outbox_backlog),
+            ("worker_event_publisher_lag_seconds", "Age of the oldest unpublished submission event", &self.event_publisher_lag_seconds),
+        ]
+    }
+
+    /// The counters and gauges as they are now
+    pub fn snapshot(&self) -> WorkerMetricsSnapshot {
+        let values = |metrics: Vec<(&'static str, &'static str, &AtomicU64)>| {
+            metrics
+                .into_iter()
+                .map(|(name, _, value)| (snapshot_name(name).to_string(), value.load(Ordering::Relaxed)))
+                .collect()
+        };
+
+        WorkerMetricsSnapshot {
+            taken_at: Utc::now(),
+            instant: Instant::now(),
+            counters: values(self.counters()),
+            gauges: values(self.gauges()),
+        }
+    }
+
+    /// What the counters did since `earlier`, with rates per second
+    pub fn delta_since(&self, earlier: &WorkerMetricsSnapshot) -> WorkerMetricsDelta {
+        self.snapshot().delta_since(earlier)
+    }
+
+    /// Render the counters in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let counters = self.counters();
+        let gauges = self.gauges();
+
+        let mut output = String::new();
+        for (name, help, value) in counters {
+            output.push_str(&format!(
+                "# HELP {name} {help}\n# TYPE {name} counter\n{name} {}\n",
+                value.load(Ordering::Relaxed)
+            ));
+        }
+        for (name, help, value) in gauges {
+            output.push_str(&format!(
+                "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {}\n",
+                value.load(Ordering::Relaxed)
+            ));
+        }
+
+        let histograms = [
+            ("worker_job_processing_seconds", "Time from a consumer picking a job up to it finishing", &self.job_processing_time),
+            ("worker_lock_wait_seconds", "Time spent waiting for a submission's processing lock", &self.lock_wait_time),
+            ("worker_queue_wait_seconds", "Time jobs waited in the queue between enqueue and dequeue", &self.queue_wait_time),
+        ];
+        for (name, help, histogram) in histograms {
+            output.push_str(&format!("# HELP {name} {help}\n# TYPE {name} histogram\n"));
+            histogram.render_prometheus(name, &mut output);
+        }
+
+        output.push_str(
+            "# HELP worker_jobs_total Finished jobs by tenant, submission type, document type and outcome\n\
+             # TYPE worker_jobs_total counter\n",
+        );
+        if let Ok(jobs) = self.jobs_by_flow.lock() {
+            for (tags, count) in jobs.iter() {
+                let labels = tags
+                    .iter()
+                    .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+                    .collect::<Vec<String>>()
+                    .join(",");
+                output.push_str(&format!("worker_jobs_total{{{}}} {}\n", labels, count));
+            }
+        }
+        output
+    }
+
     /// Create a timer that will record processing time when dropped
     pub fn start_timer(&self) -> MetricsTimer {
         MetricsTimer {
@@ -137,6 +431,102 @@ impl Default for WorkerMetrics {
     }
 }
 
+/// The worker counters and gauges at one point in time, keyed by their
+/// Prometheus name without the `worker_` prefix and `_total` suffix
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerMetricsSnapshot {
+    pub taken_at: DateTime<Utc>,
+    #[serde(skip)]
+    instant: Instant,
+    pub counters: BTreeMap<String, u64>,
+    pub gauges: BTreeMap<String, u64>,
+}
+
+impl WorkerMetricsSnapshot {
+    /// What the counters did between `earlier` and this snapshot; gauges are
+    /// this snapshot's
+    pub fn delta_since(&self, earlier: &WorkerMetricsSnapshot) -> WorkerMetricsDelta {
+        let elapsed = self.instant.saturating_duration_since(earlier.instant);
+        let counters: BTreeMap<String, u64> = self
+            .counters
+            .iter()
+            .map(|(name, value)| {
+                let before = earlier.counters.get(name).copied().unwrap_or_default();
+                (name.clone(), value.saturating_sub(before))
+            })
+            .collect();
+        let rates_per_second = counters
+            .iter()
+            .map(|(name, increase)| {
+                let rate = match elapsed.as_secs_f64() {
+                    seconds if seconds > 0.0 => *increase as f64 / seconds,
+                    _ => 0.0,
+                };
+                (name.clone(), rate)
+            })
+            .collect();
+
+        WorkerMetricsDelta {
+            since: earlier.taken_at,
+            until: self.taken_at,
+            elapsed_seconds: elapsed.as_secs_f64(),
+            counters,
+            rates_per_second,
+            gauges: self.gauges.clone(),
+        }
+    }
+}
+
+/// How much each counter grew over an interval, and how fast
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerMetricsDelta {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub elapsed_seconds: f64,
+    pub counters: BTreeMap<String, u64>,
+    pub rates_per_second: BTreeMap<String, f64>,
+    pub gauges: BTreeMap<String, u64>,
+}
+
+impl WorkerMetricsDelta {
+    pub fn increase(&self, counter: &str) -> u64 {
+        self.counters.get(counter).copied().unwrap_or_default()
+    }
+
+    pub fn rate(&self, counter: &str) -> f64 {
+        self.rates_per_second.get(counter).copied().unwrap_or_default()
+    }
+
+    /// Log the interval's throughput, warning when a large share of its
+    /// jobs failed
+    pub fn log(&self) {
+        let processed = self.increase("jobs_processed");
+        info!(
+            "Worker metrics over the last {:.0}s: processed={} ({:.2}/s), succeeded={} ({:.2}/s), \
+             failed={} ({:.2}/s), moved_to_dlq={}, retried_network_errors={}, timeout_errors={}",
+            self.elapsed_seconds,
+            processed,
+            self.rate("jobs_processed"),
+            self.increase("jobs_succeeded"),
+            self.rate("jobs_succeeded"),
+            self.increase("jobs_failed"),
+            self.rate("jobs_failed"),
+            self.increase("jobs_moved_to_dlq"),
+            self.increase("network_errors"),
+            self.increase("timeout_errors"),
+        );
+
+        if processed > 0 {
+            let error_rate = self.increase("jobs_failed") as f64 / processed as f64;
+            if error_rate > 0.1 {
+                warn!("Worker error rate over the last {:.0}s is high: {:.2}%", self.elapsed_seconds, error_rate * 100.0);
+            }
+        }
+    }
+}
+
 /// Timer that automatically records the duration when it goes out of scope
 pub struct MetricsTimer<'a> {
     metrics: &'a WorkerMetrics,
@@ -149,3 +539,110 @@ impl<'a> Drop for MetricsTimer<'a> {
         self.metrics.record_processing_time(duration);
     }
 }
+
+/// Upper bounds of the latency buckets in milliseconds, from a quick cache
+/// hit up to the longest a job may run; anything slower lands in `+Inf`
+const LATENCY_BUCKETS_MS: [u64; 16] = [
+    5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 120_000, 300_000, 600_000,
+];
+
+/// Fixed-bucket latency histogram, cheap enough to update from every consumer.
+/// Percentiles are estimated as the upper bound of the bucket they fall in,
+/// which is what Prometheus' `histogram_quantile` would get from the same
+/// buckets without interpolating.
+pub struct LatencyHistogram {
+    /// One counter per bucket plus the `+Inf` one, not cumulative
+    buckets: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+    count: AtomicU64,
+    sum_ms: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            count: AtomicU64::new(0),
+            sum_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let ms = duration.as_millis() as u64;
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_ms.fetch_add(ms, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Upper bound in milliseconds of the bucket holding the `quantile`
+    /// (0.0 to 1.0) observation; None without observations, `u64::MAX` when
+    /// it is past the last bucket
+    pub fn percentile(&self, quantile: f64) -> Option<u64> {
+        let counts: Vec<u64> = self.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(LATENCY_BUCKETS_MS.get(index).copied().unwrap_or(u64::MAX));
+            }
+        }
+        Some(u64::MAX)
+    }
+
+    /// `count=.. p50=.. p95=.. p99=..` for the periodic log line
+    fn summary(&self) -> String {
+        let format = |quantile: f64| match self.percentile(quantile) {
+            None => "-".to_string(),
+            Some(u64::MAX) => format!(">{}", LATENCY_BUCKETS_MS[LATENCY_BUCKETS_MS.len() - 1]),
+            Some(ms) => format!("<={}", ms),
+        };
+        format!("count={} p50{} p95{} p99{}", self.count(), format(0.5), format(0.95), format(0.99))
+    }
+
+    /// Cumulative `_bucket`, `_sum` and `_count` series in seconds
+    fn render_prometheus(&self, name: &str, output: &mut String) {
+        let mut cumulative = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let le = match LATENCY_BUCKETS_MS.get(index) {
+                Some(ms) => format!("{}", *ms as f64 / 1000.0),
+                None => "+Inf".to_string(),
+            };
+            output.push_str(&format!("{name}_bucket{{le=\"{le}\"}} {cumulative}\n"));
+        }
+        output.push_str(&format!(
+            "{name}_sum {}\n{name}_count {}\n",
+            self.sum_ms.load(Ordering::Relaxed) as f64 / 1000.0,
+            cumulative
+        ));
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `worker_jobs_processed_total` is `jobs_processed` in snapshots
+fn snapshot_name(prometheus_name: &str) -> &str {
+    let name = prometheus_name.strip_prefix("worker_").unwrap_or(prometheus_name);
+    name.strip_suffix("_total").unwrap_or(name)
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -3,16 +3,41 @@ pub mod job;
 pub mod queue;
 pub mod main_worker;
 pub mod dlq_worker;
-pub mod distributed_lock;
 pub mod metrics;
 pub mod error;
 pub mod upload_worker;
+pub mod failed_job_repository;
+pub mod heartbeat;
+pub mod admin_server;
+pub mod redis_connections;
+pub mod backfill;
+pub mod image_preprocessing;
+pub mod document_scanning;
+pub mod document_progress;
+pub mod report_generation;
+pub mod notification_delivery;
+pub mod notifications_listener;
+pub mod retry_policy;
+pub mod user_purge;
+pub mod event_publisher;
+pub mod sla_monitor;
+pub mod orphan_cleanup;
+pub mod supervisor;
+pub mod job_execution_repository;
+pub mod job_archive;
+pub mod wakeup;
+pub mod object_copy;
+pub mod upload_source;
+#[cfg(feature = "chaos")]
+pub mod fault_injection;
 
-pub use config::WorkerConfig;
-pub use job::{FileUploadJob, JobStatus};
+pub use config::{PollStrategy, WorkerConfig};
+pub use job::{FileUploadJob, JobKind, JobStatus, ObjectCopy};
 pub use queue::RedisQueue;
 pub use dlq_worker::DlqWorker;
-pub use distributed_lock::DistributedLock;
+pub use crate::commons::distributed_lock::DistributedLock;
 pub use metrics::WorkerMetrics;
 pub use error::{WorkerError, WorkerResult};
 pub use upload_worker::FileUploadWorker;
+pub use failed_job_repository::FailedJobRepository;
+pub use redis_connections::RedisConnections;
@@ -0,0 +1,114 @@
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    notifier::{
+        email::SmtpChannel,
+        gateway::HttpGatewayChannel,
+        notification_repository::NotificationRepository,
+        templates::{Template, TemplateValues},
+        Channel, Notification, NotificationChannel, NotifyError, Outcome,
+    },
+    workers::{FileUploadJob, WorkerError, WorkerResult},
+};
+
+/// Sends the notification jobs queued when a submission is decided
+pub struct NotificationSender {
+    channels: HashMap<Channel, Arc<dyn NotificationChannel>>,
+    repository: NotificationRepository,
+}
+
+impl NotificationSender {
+    /// None when no channel is configured: no `SMTP_HOST`,
+    /// `SMS_GATEWAY_URL` or `WHATSAPP_GATEWAY_URL`
+    pub fn from_env(pool: PgPool) -> WorkerResult<Option<Self>> {
+        let mut channels: Vec<Arc<dyn NotificationChannel>> = Vec::new();
+        if let Some(channel) = SmtpChannel::from_env()? {
+            channels.push(Arc::new(channel));
+        }
+        if let Some(channel) = HttpGatewayChannel::from_env(Channel::Sms, "SMS")? {
+            channels.push(Arc::new(channel));
+        }
+        if let Some(channel) = HttpGatewayChannel::from_env(Channel::WhatsApp, "WHATSAPP")? {
+            channels.push(Arc::new(channel));
+        }
+
+        if channels.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            channels: channels.into_iter().map(|channel| (channel.channel(), channel)).collect(),
+            repository: NotificationRepository::new(pool),
+        }))
+    }
+
+    pub fn channels(&self) -> Vec<Channel> {
+        self.channels.keys().copied().collect()
+    }
+
+    /// Render the job's message with the tenant's template and send it.
+    /// Users who closed their account or have no address for the channel
+    /// are skipped.
+    pub async fn send(&self, job: &FileUploadJob) -> WorkerResult<()> {
+        let rejected = |message: String| WorkerError::NotificationRejected(message);
+        let metadata = |key: &str| job.metadata.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+
+        let submission_id = Uuid::parse_str(&job.esign_id)
+            .map_err(|_| rejected(format!("{} is not a submission id", job.esign_id)))?;
+        let channel: Channel = job.document_type.parse().map_err(rejected)?;
+        let outcome = Outcome::from_status(metadata("outcome"))
+            .ok_or_else(|| rejected(format!("{} is not an outcome users are notified of", metadata("outcome"))))?;
+        let provider = self
+            .channels
+            .get(&channel)
+            .ok_or_else(|| rejected(format!("{} notifications aren't configured", channel)))?;
+
+        let Some(recipient) = self.repository.find_recipient(submission_id).await? else {
+            info!("Not notifying on submission {}: its owner is gone", submission_id);
+            return Ok(());
+        };
+        let address = match channel {
+            Channel::Email => Some(recipient.email.clone()),
+            Channel::Sms | Channel::WhatsApp => recipient.phone.clone(),
+        };
+        let Some(address) = address.filter(|address| !address.trim().is_empty()) else {
+            info!("Not notifying on submission {}: its owner has no {} address", submission_id, channel);
+            return Ok(());
+        };
+
+        let tenant_id = metadata("tenantId");
+        let template = self
+            .repository
+            .find_settings(tenant_id, outcome)
+            .await?
+            .and_then(|settings| settings.into_iter().find(|setting| setting.channel == channel))
+            .map(|setting| setting.template(outcome))
+            .unwrap_or_else(|| Template::default_for(channel, outcome));
+
+        let (subject, body) = template.render(&TemplateValues {
+            name: &recipient.name,
+            submission_id: &job.esign_id,
+            submission_type: metadata("submissionType"),
+            outcome,
+            reason_code: metadata("reasonCode"),
+        });
+
+        let notification = Notification {
+            reference: job.id.to_string(),
+            recipient: address,
+            subject,
+            body,
+        };
+        provider.send(&notification).await.map_err(|e| match e {
+            NotifyError::Transient(message) => WorkerError::Notification(message),
+            NotifyError::Rejected(message) => WorkerError::NotificationRejected(message),
+        })?;
+
+        info!("Sent {} {} notification of submission {}", channel, outcome, submission_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    commons::{crypto::FieldCipher, minio_service::MinioService},
+    submissions::{
+        submission_event_repository::{SubmissionEventRepository, ACTOR_STORAGE, EVENT_DOCUMENT_UPLOADED},
+        submission_repository::SubmissionRepository,
+    },
+    workers::{FileUploadJob, PollStrategy, RedisConnections, RedisQueue, WorkerConfig, WorkerError, WorkerMetrics, WorkerResult},
+};
+
+/// List MinIO pushes events to when no `MINIO_NOTIFICATIONS_REDIS_KEY` is set
+pub const DEFAULT_NOTIFICATIONS_KEY: &str = "minio_events";
+
+/// How long BLPOP waits before checking the shutdown signal again
+const POLL_TIMEOUT_SECONDS: f64 = 2.0;
+
+/// How long a delivered object version is remembered, so MinIO redelivering
+/// an event doesn't enqueue the document twice
+const DELIVERY_DEDUP_TTL_SECONDS: u64 = 24 * 60 * 60;
+
+/// Jobs fetch the document through a presigned URL, which has to outlive the
+/// queue it waits in
+const DOCUMENT_URL_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Wait before reading again after Redis failed
+const ERROR_BACKOFF: Duration = Duration::from_secs(5);
+
+/// One entry of a MinIO Redis target in `access` format
+#[derive(Debug, Deserialize)]
+struct NotificationEntry {
+    #[serde(rename = "Event", alias = "Records", default)]
+    records: Vec<EventRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EventRecord {
+    event_name: String,
+    s3: S3Entity,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Entity {
+    object: S3Object,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct S3Object {
+    key: String,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    e_tag: Option<String>,
+    #[serde(default)]
+    content_type: Option<String>,
+}
+
+/// Listens for MinIO bucket notifications so a document PUT straight to its
+/// presigned URL is picked up without the client confirming it. Created
+/// objects are matched to the submission document they were reserved for,
+/// recorded as uploaded and enqueued for the upload worker.
+pub struct NotificationsListener {
+    key: String,
+    minio_service: MinioService,
+    submission_repository: SubmissionRepository,
+    submission_event_repository: SubmissionEventRepository,
+    redis: RedisConnections,
+    queue: RedisQueue,
+    metrics: Arc<WorkerMetrics>,
+}
+
+impl NotificationsListener {
+    /// None unless `MINIO_NOTIFICATIONS_ENABLED=true`
+    pub async fn from_env(
+        config: &WorkerConfig,
+        pool: PgPool,
+        redis: RedisConnections,
+        metrics: Arc<WorkerMetrics>,
+    ) -> WorkerResult<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        if var("MINIO_NOTIFICATIONS_ENABLED").as_deref() != Some("true") {
+            return Ok(None);
+        }
+
+        let required = |name: &str| var(name).ok_or_else(|| WorkerError::Config(anyhow::anyhow!("{} must be set", name)));
+
+        let minio_service = MinioService::new(
+            &required("MINIO_ENDPOINT")?,
+            &required("MINIO_ACCESS_KEY")?,
+            &required("MINIO_SECRET_KEY")?,
+            &required("MINIO_BUCKET_NAME")?,
+        )
+        .await
+        .map_err(WorkerError::Config)?;
+
+        let queue = RedisQueue::from_connections(
+            redis.shared(),
+            redis.shared(),
+            config.worker_upload_file_queue.clone(),
+            config.worker_upload_file_dlq.clone(),
+        )
+        .with_dedup_ttl(config.enqueue_dedup_ttl)
+        .with_wakeups(config.poll_strategy == PollStrategy::Pubsub);
+
+        Ok(Some(Self {
+            key: var("MINIO_NOTIFICATIONS_REDIS_KEY").unwrap_or_else(|| DEFAULT_NOTIFICATIONS_KEY.to_string()),
+            minio_service,
+            submission_repository: SubmissionRepository::new(pool.clone(), FieldCipher::from_env()?),
+            submission_event_repository: SubmissionEventRepository::new(pool),
+            redis,
+            queue,
+            metrics,
+        }))
+    }
+
+    /// Read notifications until `shutdown_signal` is set
+    pub async fn run(&mut self, shutdown_signal: Arc<AtomicBool>) {
+        info!("Listening for MinIO bucket notifications on {}", self.key);
+
+        let mut connection = None;
+        while !shutdown_signal.load(Ordering::SeqCst) {
+            let conn = match connection.as_mut() {
+                Some(conn) => conn,
+                None => match self.redis.blocking().await {
+                    Ok(conn) => connection.insert(conn),
+                    Err(e) => {
+                        error!("Bucket notification listener can't reach Redis: {}", e);
+                        tokio::time::sleep(ERROR_BACKOFF).await;
+                        continue;
+                    }
+                },
+            };
+
+            let popped: Option<(String, String)> = match redis::cmd("BLPOP")
+                .arg(&self.key)
+                .arg(POLL_TIMEOUT_SECONDS)
+                .query_async(conn)
+                .await
+            {
+                Ok(popped) => popped,
+                Err(e) => {
+                    let e = WorkerError::Redis(e);
+                    self.redis.record_error(&e);
+                    error!("Failed to read bucket notifications: {}", e);
+                    tokio::time::sleep(ERROR_BACKOFF).await;
+                    continue;
+                }
+            };
+
+            if let Some((_, payload)) = popped {
+                for record in parse_records(&payload) {
+                    if let Err(e) = self.handle(record).await {
+                        error!("Failed to handle bucket notification: {}", e);
+                    }
+                }
+            }
+        }
+
+        info!("Bucket notification listener stopped");
+    }
+
+    async fn handle(&mut self, record: EventRecord) -> WorkerResult<()> {
+        if !record.event_name.starts_with("s3:ObjectCreated:") {
+            return Ok(());
+        }
+
+        let object_key = decode_object_key(&record.s3.object.key);
+        // Documents live under {tenant}/{submission_id}/, anything else isn't ours
+        let Some(submission_id) = object_key.split('/').nth(1).and_then(|id| Uuid::parse_str(id).ok()) else {
+            return Ok(());
+        };
+
+        let Some((_, tenant_id, submission_type, status, documents)) =
+            self.submission_repository.find_submission_for_upload(&submission_id.to_string()).await?
+        else {
+            return Ok(());
+        };
+
+        // Processed copies, reports and quarantined files match no document
+        let Some((document_type, _)) = documents.iter().find(|(_, document)| document.document_name == object_key) else {
+            return Ok(());
+        };
+
+        if status != "INITIATED" {
+            warn!(
+                "Ignoring {} uploaded to submission {} in status {}",
+                document_type, submission_id, status
+            );
+            return Ok(());
+        }
+
+        if !self.claim_delivery(&object_key, record.s3.object.e_tag.as_deref()).await? {
+            return Ok(());
+        }
+
+        self.submission_event_repository
+            .append(
+                submission_id,
+                EVENT_DOCUMENT_UPLOADED,
+                ACTOR_STORAGE,
+                json!({
+                    "documentType": document_type.as_str(),
+                    "sizeInBytes": record.s3.object.size,
+                    "contentType": record.s3.object.content_type,
+                    "source": "bucket_notification",
+                }),
+            )
+            .await?;
+
+        let document_url = self
+            .minio_service
+            .generate_presigned_url(object_key.clone(), DOCUMENT_URL_EXPIRY)
+            .await
+            .map_err(|e| WorkerError::Storage(e.to_string()))?;
+
+        let job = FileUploadJob::new(
+            submission_id.to_string(),
+            document_url,
+            object_key,
+            document_type.to_string(),
+            json!({
+                "submissionId": submission_id,
+                "tenantId": tenant_id,
+                "submissionType": submission_type,
+            }),
+        );
+        self.queue.enqueue_job(&job).await?;
+        self.metrics.record_bucket_notification();
+
+        info!("Enqueued {} of submission {} from a bucket notification", document_type, submission_id);
+        Ok(())
+    }
+
+    /// False when this version of the object was already handled
+    async fn claim_delivery(&self, object_key: &str, e_tag: Option<&str>) -> WorkerResult<bool> {
+        let mut conn = self.redis.shared();
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(format!("bucket_notification:{}:{}", object_key, e_tag.unwrap_or("")))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(DELIVERY_DEDUP_TTL_SECONDS)
+            .query_async(&mut conn)
+            .await?;
+        Ok(claimed.is_some())
+    }
+}
+
+/// Records of a list entry, which MinIO writes as an array of entries; a
+/// single entry or an S3 style `{"Records": [...]}` body is accepted too
+fn parse_records(payload: &str) -> Vec<EventRecord> {
+    let entries = match serde_json::from_str::<Value>(payload) {
+        Ok(Value::Array(entries)) => entries,
+        Ok(entry) => vec![entry],
+        Err(e) => {
+            warn!("Skipping bucket notification that isn't JSON: {}", e);
+            return Vec::new();
+        }
+    };
+
+    entries
+        .into_iter()
+        .filter_map(|entry| match serde_json::from_value::<NotificationEntry>(entry) {
+            Ok(entry) => Some(entry.records),
+            Err(e) => {
+                warn!("Skipping malformed bucket notification: {}", e);
+                None
+            }
+        })
+        .flatten()
+        .collect()
+}
+
+/// Object keys arrive URL encoded, with spaces as `+`
+fn decode_object_key(key: &str) -> String {
+    let bytes = key.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,110 @@
+use tracing::info;
+
+use crate::{
+    commons::minio_service::MinioService,
+    workers::{FileUploadJob, ObjectCopy, WorkerError, WorkerResult},
+};
+
+/// Carries out `COPY` jobs with MinIO's copy-object API, so objects never
+/// pass through the worker
+pub struct ObjectCopier {
+    minio_service: MinioService,
+}
+
+impl ObjectCopier {
+    pub fn new(minio_service: MinioService) -> Self {
+        Self { minio_service }
+    }
+
+    pub async fn from_env() -> WorkerResult<Self> {
+        let var = |name: &str| std::env::var(name).map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be set", name)));
+
+        let minio_service = MinioService::new(
+            &var("MINIO_ENDPOINT")?,
+            &var("MINIO_ACCESS_KEY")?,
+            &var("MINIO_SECRET_KEY")?,
+            &var("MINIO_BUCKET_NAME")?,
+        )
+        .await
+        .map_err(WorkerError::Config)?;
+
+        Ok(Self::new(minio_service))
+    }
+
+    /// Copy the job's object. Running it again is harmless: a destination
+    /// already holding the source's content isn't copied over, and a move
+    /// whose source is gone but whose destination exists already happened.
+    pub async fn copy(&self, job: &FileUploadJob) -> WorkerResult<()> {
+        let copy = job
+            .copy
+            .as_ref()
+            .ok_or_else(|| WorkerError::Copy(format!("Job {} doesn't say what to copy", job.id)))?;
+        if copy.is_in_place() {
+            return Err(WorkerError::Copy(format!("{} would be copied onto itself", describe_source(copy))));
+        }
+
+        let source_etag = self.etag(copy.source_bucket.as_deref(), &copy.source_key).await?;
+        let destination_etag = self.etag(copy.destination_bucket.as_deref(), &copy.destination_key).await?;
+
+        let Some(source_etag) = source_etag else {
+            if destination_etag.is_some() && copy.delete_source {
+                info!("{} was already moved to {}", describe_source(copy), describe_destination(copy));
+                return Ok(());
+            }
+            return Err(WorkerError::Copy(format!("{} doesn't exist", describe_source(copy))));
+        };
+
+        if destination_etag.as_deref() == Some(source_etag.as_str()) {
+            info!("{} already holds {}", describe_destination(copy), describe_source(copy));
+        } else {
+            self.minio_service
+                .copy_object(
+                    copy.source_bucket.as_deref(),
+                    &copy.source_key,
+                    copy.destination_bucket.as_deref(),
+                    &copy.destination_key,
+                )
+                .await
+                .map_err(|e| WorkerError::Storage(e.to_string()))?;
+        }
+
+        // Deleted only after the copy is in place, so a failure leaves the
+        // object in both places rather than in neither
+        if copy.delete_source {
+            self.minio_service
+                .delete_object(copy.source_bucket.as_deref(), &copy.source_key)
+                .await
+                .map_err(|e| WorkerError::Storage(e.to_string()))?;
+        }
+
+        info!(
+            "{} {} to {}",
+            if copy.delete_source { "Moved" } else { "Copied" },
+            describe_source(copy),
+            describe_destination(copy)
+        );
+        Ok(())
+    }
+
+    async fn etag(&self, bucket: Option<&str>, key: &str) -> WorkerResult<Option<String>> {
+        self.minio_service
+            .object_etag(bucket, key)
+            .await
+            .map_err(|e| WorkerError::Storage(e.to_string()))
+    }
+}
+
+fn describe_source(copy: &ObjectCopy) -> String {
+    describe(copy.source_bucket.as_deref(), &copy.source_key)
+}
+
+fn describe_destination(copy: &ObjectCopy) -> String {
+    describe(copy.destination_bucket.as_deref(), &copy.destination_key)
+}
+
+fn describe(bucket: Option<&str>, key: &str) -> String {
+    match bucket {
+        Some(bucket) => format!("{}/{}", bucket, key),
+        None => key.to_string(),
+    }
+}
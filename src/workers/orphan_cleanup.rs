@@ -0,0 +1,328 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    commons::{crypto::FieldCipher, minio_service::MinioService},
+    services::{
+        image_processing_service::processed_document_name, report_service::report_document_name,
+        scanner_service::QUARANTINE_PREFIX,
+    },
+    submissions::{submission_documents::SubmissionDocuments, submission_repository::SubmissionRepository},
+    workers::{DistributedLock, RedisConnections, RedisQueue, WorkerConfig, WorkerError, WorkerResult, WorkerMetrics},
+};
+
+/// Only one worker instance reconciles the bucket at a time
+const ORPHAN_CLEANUP_LOCK_KEY: &str = "orphan_cleanup";
+
+/// Where the latest run is kept for `GET /admin/storage/orphans`
+pub const ORPHAN_CLEANUP_RUN_KEY: &str = "orphan_cleanup:last_run";
+
+/// How long the latest run stays readable after its last update
+pub const ORPHAN_CLEANUP_RUN_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Orphaned keys kept on a run for inspection; the counts cover the rest
+const ORPHAN_SAMPLE_SIZE: usize = 100;
+
+/// How often the wait between runs checks the shutdown signal
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub const ORPHAN_CLEANUP_STATUS_RUNNING: &str = "RUNNING";
+pub const ORPHAN_CLEANUP_STATUS_COMPLETED: &str = "COMPLETED";
+pub const ORPHAN_CLEANUP_STATUS_FAILED: &str = "FAILED";
+
+/// Counters of one pass over the bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanCleanupRun {
+    pub id: Uuid,
+    pub status: String,
+    /// Orphans were only counted, not deleted
+    pub dry_run: bool,
+    /// Objects modified after this were skipped
+    pub cutoff: DateTime<Utc>,
+    pub objects_scanned: u64,
+    /// Newer than the cutoff, possibly still waiting for their submission
+    pub objects_too_recent: u64,
+    /// Quarantined or outside the `{tenant}/{submission_id}/` layout
+    pub objects_ignored: u64,
+    pub objects_referenced: u64,
+    pub objects_orphaned: u64,
+    pub orphaned_size_in_bytes: i64,
+    pub objects_deleted: u64,
+    pub delete_failures: u64,
+    /// The first orphaned keys found
+    pub orphaned_keys: Vec<String>,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl OrphanCleanupRun {
+    pub fn new(dry_run: bool, cutoff: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            status: ORPHAN_CLEANUP_STATUS_RUNNING.to_string(),
+            dry_run,
+            cutoff,
+            objects_scanned: 0,
+            objects_too_recent: 0,
+            objects_ignored: 0,
+            objects_referenced: 0,
+            objects_orphaned: 0,
+            orphaned_size_in_bytes: 0,
+            objects_deleted: 0,
+            delete_failures: 0,
+            orphaned_keys: Vec::new(),
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+        }
+    }
+}
+
+/// What a bucket object is to the submissions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ownership {
+    Referenced,
+    Orphaned,
+    /// Not ours to judge: quarantined files and keys of an older layout
+    Ignored,
+}
+
+/// Deletes bucket objects no submission references: documents of submissions
+/// that were never saved or have since been deleted, and anything under a
+/// submission's prefix that isn't one of its documents, their processed
+/// copies or its report. Only objects older than `min_age` are looked at, so
+/// uploads racing their submission's creation are left alone. Starts out in
+/// dry-run mode, which counts orphans without deleting them.
+pub struct OrphanCleaner {
+    submissions: SubmissionRepository,
+    minio_service: MinioService,
+    redis: RedisConnections,
+    queue: RedisQueue,
+    metrics: Arc<WorkerMetrics>,
+    dry_run: bool,
+    min_age: Duration,
+    interval: Duration,
+}
+
+impl OrphanCleaner {
+    /// None unless `ORPHAN_CLEANUP_ENABLED=true`
+    pub async fn from_env(
+        config: &WorkerConfig,
+        pool: PgPool,
+        redis: RedisConnections,
+        metrics: Arc<WorkerMetrics>,
+    ) -> WorkerResult<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        if var("ORPHAN_CLEANUP_ENABLED").as_deref() != Some("true") {
+            return Ok(None);
+        }
+
+        let required = |name: &str| var(name).ok_or_else(|| WorkerError::Config(anyhow::anyhow!("{} must be set", name)));
+        let number = |name: &str, default: u64| {
+            var(name)
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map(|v| v.unwrap_or(default))
+                .map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be a number", name)))
+        };
+
+        let minio_service = MinioService::new(
+            &required("MINIO_ENDPOINT")?,
+            &required("MINIO_ACCESS_KEY")?,
+            &required("MINIO_SECRET_KEY")?,
+            &required("MINIO_BUCKET_NAME")?,
+        )
+        .await
+        .map_err(WorkerError::Config)?;
+
+        let queue = RedisQueue::from_connections(
+            redis.shared(),
+            redis.shared(),
+            config.worker_upload_file_queue.clone(),
+            config.worker_upload_file_dlq.clone(),
+        );
+
+        Ok(Some(Self {
+            submissions: SubmissionRepository::new(pool, FieldCipher::from_env()?),
+            minio_service,
+            redis,
+            queue,
+            metrics,
+            // Deleting has to be asked for explicitly
+            dry_run: var("ORPHAN_CLEANUP_DRY_RUN").as_deref() != Some("false"),
+            min_age: Duration::from_secs(number("ORPHAN_CLEANUP_MIN_AGE_SECONDS", 24 * 60 * 60)?),
+            interval: Duration::from_secs(number("ORPHAN_CLEANUP_INTERVAL_SECONDS", 6 * 60 * 60)?.max(1)),
+        }))
+    }
+
+    /// Reconcile every `interval` until `shutdown_signal` is set
+    pub async fn run(&self, shutdown_signal: Arc<AtomicBool>) {
+        info!(
+            "Cleaning up orphaned objects older than {:?} every {:?}{}",
+            self.min_age,
+            self.interval,
+            if self.dry_run { " (dry run)" } else { "" }
+        );
+
+        while !shutdown_signal.load(Ordering::SeqCst) {
+            if let Err(e) = self.reconcile(&shutdown_signal).await {
+                error!("Failed to clean up orphaned objects: {}", e);
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < self.interval && !shutdown_signal.load(Ordering::SeqCst) {
+                tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+                waited += SHUTDOWN_POLL_INTERVAL;
+            }
+        }
+
+        info!("Orphan cleanup stopped");
+    }
+
+    /// One pass over the bucket, publishing the run's counters after every page
+    async fn reconcile(&self, shutdown_signal: &AtomicBool) -> WorkerResult<()> {
+        let mut lock = DistributedLock::new(self.redis.shared(), ORPHAN_CLEANUP_LOCK_KEY.to_string(), self.interval);
+        if !lock.acquire(Duration::ZERO, Duration::ZERO).await? {
+            return Ok(());
+        }
+
+        let cutoff = Utc::now() - chrono::Duration::seconds(self.min_age.as_secs() as i64);
+        let mut run = OrphanCleanupRun::new(self.dry_run, cutoff);
+        let mut queue = self.queue.clone();
+
+        let result = self.scan(&mut run, &mut queue, shutdown_signal).await;
+
+        run.finished_at = Some(Utc::now());
+        match &result {
+            Ok(()) => {
+                run.status = ORPHAN_CLEANUP_STATUS_COMPLETED.to_string();
+                info!(
+                    "Orphan cleanup {} scanned {} objects: {} orphaned, {} deleted, {} failed to delete{}",
+                    run.id,
+                    run.objects_scanned,
+                    run.objects_orphaned,
+                    run.objects_deleted,
+                    run.delete_failures,
+                    if run.dry_run { " (dry run)" } else { "" }
+                );
+            }
+            Err(e) => {
+                run.status = ORPHAN_CLEANUP_STATUS_FAILED.to_string();
+                run.error = Some(e.to_string());
+            }
+        }
+        if let Err(e) = queue.save_orphan_cleanup_run(&run).await {
+            error!("Failed to save orphan cleanup run {}: {}", run.id, e);
+        }
+
+        lock.release().await?;
+        result
+    }
+
+    async fn scan(&self, run: &mut OrphanCleanupRun, queue: &mut RedisQueue, shutdown_signal: &AtomicBool) -> WorkerResult<()> {
+        let mut continuation_token = None;
+        loop {
+            if shutdown_signal.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+
+            let (objects, next) = self
+                .minio_service
+                .list_objects(continuation_token)
+                .await
+                .map_err(|e| WorkerError::Storage(e.to_string()))?;
+
+            // Objects without a modification time are treated as recent
+            let (old, recent): (Vec<_>, Vec<_>) = objects
+                .into_iter()
+                .partition(|object| object.last_modified.is_some_and(|at| at < run.cutoff));
+            let scanned = (old.len() + recent.len()) as u64;
+            run.objects_scanned += scanned;
+            run.objects_too_recent += recent.len() as u64;
+
+            let submission_ids: Vec<Uuid> = old.iter().filter_map(|object| submission_id(&object.key)).collect();
+            let submissions = self.submissions.find_documents_by_ids(&submission_ids).await?;
+
+            let (mut orphaned, mut deleted) = (0, 0);
+            for object in old {
+                match ownership(&object.key, &submissions) {
+                    Ownership::Referenced => run.objects_referenced += 1,
+                    Ownership::Ignored => run.objects_ignored += 1,
+                    Ownership::Orphaned => {
+                        orphaned += 1;
+                        run.objects_orphaned += 1;
+                        run.orphaned_size_in_bytes += object.size_in_bytes;
+                        if run.orphaned_keys.len() < ORPHAN_SAMPLE_SIZE {
+                            run.orphaned_keys.push(object.key.clone());
+                        }
+                        if run.dry_run {
+                            continue;
+                        }
+
+                        match self.minio_service.delete_file(object.key.clone()).await {
+                            Ok(()) => {
+                                deleted += 1;
+                                run.objects_deleted += 1;
+                            }
+                            Err(e) => {
+                                warn!("Failed to delete orphaned object {}: {}", object.key, e);
+                                run.delete_failures += 1;
+                            }
+                        }
+                    }
+                }
+            }
+            self.metrics.record_orphan_objects(scanned, orphaned, deleted);
+
+            queue.save_orphan_cleanup_run(run).await?;
+
+            match next {
+                Some(token) => continuation_token = Some(token),
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Submission id of a key laid out as `{tenant}/{submission_id}/...`
+fn submission_id(key: &str) -> Option<Uuid> {
+    key.split('/').nth(1).and_then(|id| Uuid::parse_str(id).ok())
+}
+
+fn ownership(key: &str, submissions: &HashMap<Uuid, (String, SubmissionDocuments)>) -> Ownership {
+    if key.starts_with(QUARANTINE_PREFIX) {
+        return Ownership::Ignored;
+    }
+    let Some(submission_id) = submission_id(key) else {
+        return Ownership::Ignored;
+    };
+    let Some((tenant_id, documents)) = submissions.get(&submission_id) else {
+        return Ownership::Orphaned;
+    };
+    if !key.starts_with(&MinioService::submission_prefix(tenant_id, submission_id)) {
+        return Ownership::Orphaned;
+    }
+
+    let referenced = key == report_document_name(tenant_id, submission_id)
+        || documents.spilled.as_ref().is_some_and(|spilled| key == spilled.object_name)
+        || documents
+            .iter()
+            .any(|(_, document)| key == document.document_name || key == processed_document_name(&document.document_name));
+    if referenced {
+        Ownership::Referenced
+    } else {
+        Ownership::Orphaned
+    }
+}
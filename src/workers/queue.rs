@@ -1,71 +1,779 @@
-use redis::{AsyncCommands, Client, Connection};
-use redis::aio::ConnectionManager;
-use crate::workers::{FileUploadJob, WorkerError, WorkerResult};
+use redis::{AsyncCommands, Connection};
+use crate::commons::redis_connection::{RedisConnection, RedisTopology};
+use crate::commons::telemetry;
+use crate::submissions::submission_export::{
+    export_run_key, ExportRun, EXPORT_RUNNING_KEY, EXPORT_RUN_TTL_SECONDS, EXPORT_STATUS_RUNNING,
+};
+use crate::workers::backfill::{backfill_run_key, BackfillRun, BACKFILL_RUN_TTL_SECONDS};
+use crate::workers::orphan_cleanup::{OrphanCleanupRun, ORPHAN_CLEANUP_RUN_KEY, ORPHAN_CLEANUP_RUN_TTL_SECONDS};
+use crate::workers::job::{progress_key, JobProgressSnapshot};
+use crate::workers::{FileUploadJob, JobStatus, WorkerError, WorkerResult};
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
 use tracing::{info, warn, error};
 
+/// Outcome of an enqueue. `AlreadyEnqueued` means an identical job was pushed
+/// within the dedup window; callers can treat it as success.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnqueueResult {
+    Enqueued,
+    AlreadyEnqueued,
+}
+
+/// How long a job's progress stays readable after its last update
+const JOB_PROGRESS_TTL_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// Delayed jobs moved to the queue per promotion
+const PROMOTE_BATCH_SIZE: usize = 100;
+
+/// Malformed entries kept for inspection; older ones are dropped
+const MALFORMED_MAX_ENTRIES: isize = 10_000;
+
+/// Expired processing lists reclaimed per reap
+const REAP_BATCH_SIZE: usize = 100;
+
+/// Moves every entry of a processing list (KEYS[1]) to the consumer end of
+/// the queue (KEYS[2]), so they are dequeued next in the order they first were
+const REQUEUE_SCRIPT: &str = r#"
+    local moved = 0
+    local job = redis.call('LPOP', KEYS[1])
+    while job do
+        redis.call('RPUSH', KEYS[2], job)
+        moved = moved + 1
+        job = redis.call('LPOP', KEYS[1])
+    end
+    return moved
+"#;
+
 #[derive(Clone)]
 pub struct RedisQueue {
-    connection_manager: ConnectionManager,
+    connection_manager: RedisConnection,
+    blocking_connection: RedisConnection,
     queue_name: String,
     dlq_name: String,
+    dedup_ttl: Option<Duration>,
+    processing: Option<ProcessingList>,
+    /// Channel to publish to whenever jobs land on the queue
+    wakeup_channel: Option<String>,
+}
+
+/// Where a consumer keeps the job it is working on until it acks it, so a
+/// consumer that dies mid-job doesn't take the job with it
+#[derive(Clone)]
+struct ProcessingList {
+    name: String,
+    visibility_timeout: Duration,
+    /// The entry dequeued last, as it sits on the list, until it is acked
+    in_flight: Option<String>,
+    /// Whether the in-flight job was already scheduled for a retry or dead
+    /// lettered, so handing it back to the queue would run it twice
+    settled: bool,
 }
 
 impl RedisQueue {
-    pub async fn new(redis_url: &str, queue_name: String, dlq_name: String) -> WorkerResult<Self> {
-        let client = Client::open(redis_url)?;
-        let connection_manager = ConnectionManager::new(client).await?;
+    pub async fn new(redis: &RedisTopology, queue_name: String, dlq_name: String) -> WorkerResult<Self> {
+        let connection_manager = redis.connect().await?;
 
         Ok(Self {
+            blocking_connection: connection_manager.clone(),
             connection_manager,
             queue_name,
             dlq_name,
+            dedup_ttl: None,
+            processing: None,
+            wakeup_channel: None,
         })
     }
 
-    pub async fn enqueue_job(&mut self, job: &FileUploadJob) -> WorkerResult<()> {
+    /// Build a queue on existing connections. `blocking_connection` is used
+    /// only for BRPOP so blocking dequeues don't stall the shared connection.
+    pub fn from_connections(
+        connection_manager: RedisConnection,
+        blocking_connection: RedisConnection,
+        queue_name: String,
+        dlq_name: String,
+    ) -> Self {
+        Self {
+            connection_manager,
+            blocking_connection,
+            queue_name,
+            dlq_name,
+            dedup_ttl: None,
+            processing: None,
+            wakeup_channel: None,
+        }
+    }
+
+    /// Deduplicate enqueues by the job's idempotency key for `ttl`
+    pub fn with_dedup_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.dedup_ttl = ttl;
+        self
+    }
+
+    /// Deduplicate enqueues for at least `ttl`, even with dedup disabled
+    pub fn with_min_dedup_ttl(mut self, ttl: Duration) -> Self {
+        self.dedup_ttl = Some(self.dedup_ttl.map_or(ttl, |current| current.max(ttl)));
+        self
+    }
+
+    /// Publish to the queue's wake-up channel whenever jobs are pushed, so
+    /// consumers waiting on it pick them up right away
+    pub fn with_wakeups(mut self, enabled: bool) -> Self {
+        self.wakeup_channel = enabled.then(|| self.wakeup_channel_name());
+        self
+    }
+
+    /// Channel consumers subscribe to to hear about new jobs
+    pub fn wakeup_channel_name(&self) -> String {
+        self.connection_manager.related_key(&self.queue_name, "wakeup")
+    }
+
+    /// Tell waiting consumers `count` jobs were pushed. Consumers fall back
+    /// to polling, so a lost message only delays a job.
+    async fn wake_consumers(&mut self, count: usize) {
+        let Some(channel) = self.wakeup_channel.clone() else {
+            return;
+        };
+        if let Err(e) = self.connection_manager.publish::<_, _, ()>(&channel, count).await {
+            warn!("Failed to publish to {}: {}", channel, e);
+        }
+    }
+
+    /// Dequeue onto the processing list of `consumer_id` instead of popping
+    /// jobs outright. A job stays there until `ack_job` or `nack_job`; if
+    /// the consumer stops renewing its lease for `visibility_timeout`, any
+    /// consumer's `reap_expired_jobs` puts the job back on the queue.
+    pub fn with_processing_list(mut self, consumer_id: &str, visibility_timeout: Duration) -> Self {
+        self.processing = Some(ProcessingList {
+            name: self.connection_manager.related_key(&self.queue_name, &format!("processing:{}", consumer_id)),
+            visibility_timeout,
+            in_flight: None,
+            settled: false,
+        });
+        self
+    }
+
+    pub async fn enqueue_job(&mut self, job: &FileUploadJob) -> WorkerResult<EnqueueResult> {
+        // Carry the enqueuing request's trace so the worker's spans join it
+        let mut job = job.clone();
+        telemetry::inject_trace_context(&mut job.metadata);
+        job.enqueued_at = Some(chrono::Utc::now());
+        let job = &job;
+
         let job_json = job.to_json()?;
-        self.connection_manager
+
+        let dedup_key = match self.dedup_ttl {
+            Some(ttl) => {
+                let key = job.get_idempotency_key();
+                let claimed: Option<String> = redis::cmd("SET")
+                    .arg(&key)
+                    .arg(job.id.to_string())
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl.as_secs().max(1))
+                    .query_async(&mut self.connection_manager)
+                    .await?;
+
+                if claimed.is_none() {
+                    info!("Job {} already enqueued to {}, skipping", job.id, self.queue_name);
+                    return Ok(EnqueueResult::AlreadyEnqueued);
+                }
+                Some(key)
+            }
+            None => None,
+        };
+
+        if let Err(e) = self
+            .connection_manager
             .lpush::<_, _, ()>(&self.queue_name, job_json)
-            .await?;
+            .await
+        {
+            // Let a retry of this enqueue through
+            if let Some(key) = dedup_key {
+                let _: Result<(), _> = self.connection_manager.del(&key).await;
+            }
+            return Err(e.into());
+        }
 
         info!("Job {} enqueued to {}", job.id, self.queue_name);
+        self.wake_consumers(1).await;
+
+        if let Err(e) = self.update_job_progress(job).await {
+            warn!("Failed to record progress for job {}: {}", job.id, e);
+        }
+
+        Ok(EnqueueResult::Enqueued)
+    }
+
+    /// Enqueue a batch of jobs in one pipeline. Jobs dropped by enqueue dedup
+    /// are left out; returns how many were pushed.
+    pub async fn enqueue_jobs(&mut self, jobs: &[FileUploadJob]) -> WorkerResult<usize> {
+        // A pipeline has to stay within one cluster slot, and every job's
+        // progress and idempotency keys hash to a slot of their own
+        if self.connection_manager.is_cluster() {
+            let mut enqueued = 0;
+            for job in jobs {
+                if self.enqueue_job(job).await? == EnqueueResult::Enqueued {
+                    enqueued += 1;
+                }
+            }
+            return Ok(enqueued);
+        }
+
+        let mut jobs = jobs.to_vec();
+        let enqueued_at = chrono::Utc::now();
+        for job in &mut jobs {
+            telemetry::inject_trace_context(&mut job.metadata);
+            job.enqueued_at = Some(enqueued_at);
+        }
+
+        let jobs: Vec<FileUploadJob> = match self.dedup_ttl {
+            Some(ttl) if !jobs.is_empty() => {
+                let mut pipe = redis::pipe();
+                for job in &jobs {
+                    pipe.cmd("SET")
+                        .arg(job.get_idempotency_key())
+                        .arg(job.id.to_string())
+                        .arg("NX")
+                        .arg("EX")
+                        .arg(ttl.as_secs().max(1));
+                }
+                let claimed: Vec<Option<String>> = pipe.query_async(&mut self.connection_manager).await?;
+
+                jobs.into_iter()
+                    .zip(claimed)
+                    .filter_map(|(job, claimed)| claimed.map(|_| job))
+                    .collect()
+            }
+            _ => jobs,
+        };
+
+        if jobs.is_empty() {
+            return Ok(0);
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for job in &jobs {
+            let key = job.get_progress_key();
+            pipe.lpush(&self.queue_name, job.to_json()?)
+                .ignore()
+                .hset_multiple(&key, &progress_fields(job))
+                .ignore()
+                .expire(&key, JOB_PROGRESS_TTL_SECONDS)
+                .ignore();
+        }
+
+        if let Err(e) = pipe.query_async::<_, ()>(&mut self.connection_manager).await {
+            // Let a retry of this batch through
+            if self.dedup_ttl.is_some() {
+                let keys: Vec<String> = jobs.iter().map(|job| job.get_idempotency_key()).collect();
+                let _: Result<(), _> = self.connection_manager.del(keys).await;
+            }
+            return Err(e.into());
+        }
+
+        info!("{} jobs enqueued to {}", jobs.len(), self.queue_name);
+        self.wake_consumers(jobs.len()).await;
+        Ok(jobs.len())
+    }
+
+    /// Mirror the job's progress into its Redis hash
+    pub async fn update_job_progress(&mut self, job: &FileUploadJob) -> WorkerResult<()> {
+        let key = job.get_progress_key();
+
+        redis::pipe()
+            .atomic()
+            .hset_multiple(&key, &progress_fields(job))
+            .ignore()
+            .expire(&key, JOB_PROGRESS_TTL_SECONDS)
+            .ignore()
+            .query_async::<_, ()>(&mut self.connection_manager)
+            .await?;
+
         Ok(())
     }
 
-    pub async fn dequeue_job(&mut self, timeout_seconds: u64) -> WorkerResult<Option<FileUploadJob>> {
-        let result: Option<(String, String)> = self.connection_manager
-            .brpop(&self.queue_name, timeout_seconds as f64)
+    /// Move the job to a new step and publish it. Progress is informational,
+    /// so a failed write is logged instead of failing the job.
+    pub async fn record_progress(&mut self, job: &mut FileUploadJob, status: JobStatus, percent_complete: u8, current_step: &str) {
+        job.set_progress(status, percent_complete, current_step);
+        if let Err(e) = self.update_job_progress(job).await {
+            warn!("Failed to record progress for job {}: {}", job.id, e);
+        }
+    }
+
+    pub async fn get_job_progress(&mut self, job_id: Uuid) -> WorkerResult<Option<JobProgressSnapshot>> {
+        let fields: HashMap<String, String> = self.connection_manager
+            .hgetall(progress_key(job_id))
             .await?;
 
-        match result {
-            Some((_, job_json)) => {
+        if fields.is_empty() {
+            return Ok(None);
+        }
+
+        let field = |name: &str| fields.get(name).cloned().unwrap_or_default();
+        Ok(Some(JobProgressSnapshot {
+            job_id,
+            status: field("status"),
+            percent_complete: field("percent_complete").parse().unwrap_or(0),
+            current_step: field("current_step"),
+            retry_count: field("retry_count").parse().unwrap_or(0),
+            last_error: fields.get("last_error").filter(|e| !e.is_empty()).cloned(),
+            updated_at: field("updated_at"),
+            submission_id: fields.get("submission_id").cloned(),
+        }))
+    }
+
+    pub async fn save_backfill_run(&mut self, run: &BackfillRun) -> WorkerResult<()> {
+        self.connection_manager
+            .set_ex::<_, _, ()>(backfill_run_key(run.id), serde_json::to_string(run)?, BACKFILL_RUN_TTL_SECONDS)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_backfill_run(&mut self, run_id: Uuid) -> WorkerResult<Option<BackfillRun>> {
+        let run: Option<String> = self.connection_manager.get(backfill_run_key(run_id)).await?;
+        Ok(run.map(|run| serde_json::from_str(&run)).transpose()?)
+    }
+
+    /// Save the run, keeping it in the set of running exports until it ends
+    pub async fn save_export_run(&mut self, run: &ExportRun) -> WorkerResult<()> {
+        self.connection_manager
+            .set_ex::<_, _, ()>(export_run_key(run.id), serde_json::to_string(run)?, EXPORT_RUN_TTL_SECONDS)
+            .await?;
+        if run.status == EXPORT_STATUS_RUNNING {
+            self.connection_manager.sadd::<_, _, ()>(EXPORT_RUNNING_KEY, run.id.to_string()).await?;
+        } else {
+            self.connection_manager.srem::<_, _, ()>(EXPORT_RUNNING_KEY, run.id.to_string()).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_export_run(&mut self, run_id: Uuid) -> WorkerResult<Option<ExportRun>> {
+        let run: Option<String> = self.connection_manager.get(export_run_key(run_id)).await?;
+        Ok(run.map(|run| serde_json::from_str(&run)).transpose()?)
+    }
+
+    /// Mark every running export that lost its heartbeat as failed. Returns
+    /// how many were marked.
+    pub async fn fail_orphaned_export_runs(&mut self) -> WorkerResult<usize> {
+        let run_ids: Vec<String> = self.connection_manager.smembers(EXPORT_RUNNING_KEY).await?;
+        let now = chrono::Utc::now();
+        let mut failed = 0;
+
+        for run_id in run_ids {
+            let run = match Uuid::parse_str(&run_id) {
+                Ok(id) => self.get_export_run(id).await?,
+                Err(_) => None,
+            };
+            match run {
+                Some(mut run) if run.is_orphaned(now) => {
+                    run.mark_interrupted();
+                    self.save_export_run(&run).await?;
+                    warn!("Export run {} was interrupted, marked as failed", run.id);
+                    failed += 1;
+                }
+                Some(_) => {}
+                None => self.connection_manager.srem::<_, _, ()>(EXPORT_RUNNING_KEY, &run_id).await?,
+            }
+        }
+
+        Ok(failed)
+    }
+
+    pub async fn save_orphan_cleanup_run(&mut self, run: &OrphanCleanupRun) -> WorkerResult<()> {
+        self.connection_manager
+            .set_ex::<_, _, ()>(ORPHAN_CLEANUP_RUN_KEY, serde_json::to_string(run)?, ORPHAN_CLEANUP_RUN_TTL_SECONDS)
+            .await?;
+        Ok(())
+    }
+
+    /// The latest orphan cleanup run, finished or not
+    pub async fn get_orphan_cleanup_run(&mut self) -> WorkerResult<Option<OrphanCleanupRun>> {
+        let run: Option<String> = self.connection_manager.get(ORPHAN_CLEANUP_RUN_KEY).await?;
+        Ok(run.map(|run| serde_json::from_str(&run)).transpose()?)
+    }
+
+    /// Drop a job's idempotency key so the same upload can be enqueued again
+    pub async fn release_idempotency_key(&mut self, job: &FileUploadJob) -> WorkerResult<()> {
+        self.connection_manager
+            .del::<_, ()>(job.get_idempotency_key())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn dequeue_job(&mut self, timeout_seconds: u64) -> WorkerResult<Option<FileUploadJob>> {
+        self.dequeue(Some(timeout_seconds)).await
+    }
+
+    /// Dequeue the next job if there is one, without waiting
+    pub async fn try_dequeue_job(&mut self) -> WorkerResult<Option<FileUploadJob>> {
+        self.dequeue(None).await
+    }
+
+    /// Pop the next job, blocking for up to `timeout_seconds` if given
+    async fn dequeue(&mut self, timeout_seconds: Option<u64>) -> WorkerResult<Option<FileUploadJob>> {
+        if self.processing.is_some() {
+            return self.dequeue_to_processing(timeout_seconds).await;
+        }
+
+        let job_json: Option<String> = match timeout_seconds {
+            Some(timeout_seconds) => self
+                .blocking_connection
+                .brpop::<_, Option<(String, String)>>(&self.queue_name, timeout_seconds as f64)
+                .await?
+                .map(|(_, job_json)| job_json),
+            None => self.connection_manager.rpop(&self.queue_name, None).await?,
+        };
+
+        match job_json {
+            Some(job_json) => {
                 match FileUploadJob::from_json(&job_json) {
                     Ok(job) => {
                         info!("Job {} dequeued from {}", job.id, self.queue_name);
                         Ok(Some(job))
                     }
                     Err(e) => {
-                        error!("Failed to deserialize job from {}: {}", self.queue_name, e);
-                        Err(WorkerError::Json(e))
+                        let queue_name = self.queue_name.clone();
+                        Err(self.set_aside_malformed(&queue_name, &job_json, e).await)
                     }
                 }
             }
-            None => Ok(None), // Timeout reached
+            None => Ok(None), // Timeout reached or queue empty
+        }
+    }
+
+    /// Move the next job onto our processing list, renewing our lease so
+    /// the wait itself doesn't count against the visibility timeout
+    async fn dequeue_to_processing(&mut self, timeout_seconds: Option<u64>) -> WorkerResult<Option<FileUploadJob>> {
+        // A job we couldn't ack or nack is still on the list; hand it back first
+        if self.processing.as_ref().is_some_and(|processing| processing.in_flight.is_some()) {
+            self.nack_job().await?;
+        }
+
+        let Some(processing) = self.processing.clone() else {
+            return Ok(None);
+        };
+        let wait = Duration::from_secs(timeout_seconds.unwrap_or(0));
+        self.renew_lease(&processing.name, wait + processing.visibility_timeout)
+            .await?;
+
+        let job_json: Option<String> = match timeout_seconds {
+            Some(timeout_seconds) => {
+                self.blocking_connection
+                    .brpoplpush(&self.queue_name, &processing.name, timeout_seconds as f64)
+                    .await?
+            }
+            None => self.connection_manager.rpoplpush(&self.queue_name, &processing.name).await?,
+        };
+        let Some(job_json) = job_json else {
+            return Ok(None); // Timeout reached or queue empty
+        };
+
+        self.renew_lease(&processing.name, processing.visibility_timeout).await?;
+        if let Some(processing) = self.processing.as_mut() {
+            processing.in_flight = Some(job_json.clone());
+            processing.settled = false;
+        }
+
+        match FileUploadJob::from_json(&job_json) {
+            Ok(job) => {
+                info!("Job {} dequeued from {} onto {}", job.id, self.queue_name, processing.name);
+                Ok(Some(job))
+            }
+            Err(e) => {
+                let queue_name = self.queue_name.clone();
+                let error = self.set_aside_malformed(&queue_name, &job_json, e).await;
+                self.ack_job().await?;
+                Err(error)
+            }
+        }
+    }
+
+    /// Registry of processing lists, scored by when their lease runs out in
+    /// milliseconds since the epoch
+    fn processing_registry_name(&self) -> String {
+        self.connection_manager.related_key(&self.queue_name, "processing")
+    }
+
+    async fn renew_lease(&mut self, processing_name: &str, lease: Duration) -> WorkerResult<()> {
+        let expires_at = chrono::Utc::now().timestamp_millis() + lease.as_millis() as i64;
+        self.connection_manager
+            .zadd::<_, _, _, ()>(self.processing_registry_name(), processing_name, expires_at)
+            .await?;
+        Ok(())
+    }
+
+    /// Drop the job dequeued last from our processing list once it is done
+    /// with: completed, scheduled for a retry or dead lettered
+    pub async fn ack_job(&mut self) -> WorkerResult<()> {
+        let Some((processing_name, Some(job_json))) =
+            self.processing.as_ref().map(|processing| (processing.name.clone(), processing.in_flight.clone()))
+        else {
+            return Ok(());
+        };
+
+        self.connection_manager
+            .lrem::<_, _, ()>(&processing_name, 1, job_json)
+            .await?;
+        if let Some(processing) = self.processing.as_mut() {
+            processing.in_flight = None;
+            processing.settled = false;
+        }
+        Ok(())
+    }
+
+    /// Hand everything on our processing list back to the queue, to be
+    /// dequeued next, e.g. after failing to record what became of a job.
+    /// A job already scheduled for a retry or dead lettered is only dropped.
+    pub async fn nack_job(&mut self) -> WorkerResult<usize> {
+        let Some(processing_name) = self.processing.as_ref().map(|processing| processing.name.clone()) else {
+            return Ok(0);
+        };
+
+        if self.processing.as_ref().is_some_and(|processing| processing.settled) {
+            self.ack_job().await?;
+        }
+
+        let moved: usize = redis::Script::new(REQUEUE_SCRIPT)
+            .key(&processing_name)
+            .key(&self.queue_name)
+            .invoke_async(&mut self.connection_manager)
+            .await?;
+        if let Some(processing) = self.processing.as_mut() {
+            processing.in_flight = None;
+            processing.settled = false;
+        }
+
+        if moved > 0 {
+            warn!("{} jobs returned from {} to {}", moved, processing_name, self.queue_name);
+            self.wake_consumers(moved).await;
+        }
+        Ok(moved)
+    }
+
+    /// Dead letter `job_id` if it is on our processing list, recording
+    /// `error` on it, e.g. the job a consumer panicked on. Other jobs are
+    /// left for `nack_job`. Returns how many jobs were moved.
+    pub async fn dead_letter_unfinished(&mut self, job_id: Uuid, error: &WorkerError) -> WorkerResult<usize> {
+        let Some(processing_name) = self.processing.as_ref().map(|processing| processing.name.clone()) else {
+            return Ok(0);
+        };
+        if let Some(processing) = self.processing.as_mut() {
+            processing.in_flight = None;
+            processing.settled = false;
+        }
+
+        let entries: Vec<String> = self.connection_manager.lrange(&processing_name, 0, -1).await?;
+        let mut moved = 0;
+        for entry in entries {
+            match FileUploadJob::from_json(&entry) {
+                Ok(mut job) if job.id == job_id => {
+                    job.record_error(error);
+                    self.move_to_dlq(&job).await?;
+                    moved += 1;
+                }
+                Ok(_) => continue,
+                Err(e) => {
+                    self.set_aside_malformed(&processing_name, &entry, e).await;
+                }
+            }
+            self.connection_manager
+                .lrem::<_, _, ()>(&processing_name, 1, entry)
+                .await?;
+        }
+        Ok(moved)
+    }
+
+    /// Return what is left on our processing list to the queue and drop our
+    /// lease, when the consumer stops
+    pub async fn release_processing_list(&mut self) -> WorkerResult<()> {
+        let Some(processing_name) = self.processing.as_ref().map(|processing| processing.name.clone()) else {
+            return Ok(());
+        };
+
+        self.nack_job().await?;
+        self.connection_manager
+            .zrem::<_, _, ()>(self.processing_registry_name(), processing_name)
+            .await?;
+        Ok(())
+    }
+
+    /// Put the jobs of processing lists whose lease ran out, their consumer
+    /// having died or hung, back on the queue; returns how many moved
+    pub async fn reap_expired_jobs(&mut self) -> WorkerResult<usize> {
+        let registry_name = self.processing_registry_name();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let expired: Vec<String> = self
+            .connection_manager
+            .zrangebyscore_limit(&registry_name, "-inf", now, 0, REAP_BATCH_SIZE as isize)
+            .await?;
+
+        // Another consumer may reap the same list, or its owner may have
+        // renewed the lease since, so the lease is checked again atomically
+        let script = redis::Script::new(&format!(
+            r#"
+            local expires_at = redis.call('ZSCORE', KEYS[3], ARGV[1])
+            if not expires_at or tonumber(expires_at) > tonumber(ARGV[2]) then
+                return 0
+            end
+            redis.call('ZREM', KEYS[3], ARGV[1])
+            {}
+            "#,
+            REQUEUE_SCRIPT
+        ));
+
+        let mut reclaimed = 0;
+        for processing_name in expired {
+            let moved: usize = script
+                .key(&processing_name)
+                .key(&self.queue_name)
+                .key(&registry_name)
+                .arg(&processing_name)
+                .arg(now)
+                .invoke_async(&mut self.connection_manager)
+                .await?;
+
+            if moved > 0 {
+                warn!(
+                    "{} jobs reclaimed from {} after its visibility timeout expired",
+                    moved, processing_name
+                );
+            }
+            reclaimed += moved;
+        }
+        if reclaimed > 0 {
+            self.wake_consumers(reclaimed).await;
         }
+        Ok(reclaimed)
+    }
+
+    /// List of queue and DLQ entries that couldn't be read as jobs, newest
+    /// first, each with the raw payload and the parse error
+    pub fn malformed_name(&self) -> String {
+        self.connection_manager.related_key(&self.queue_name, "malformed")
+    }
+
+    /// Keep an unreadable entry on the malformed list instead of losing it.
+    /// Returns the error for the consumer to count before it moves on.
+    async fn set_aside_malformed(&mut self, source: &str, payload: &str, error: serde_json::Error) -> WorkerError {
+        error!("Failed to deserialize job from {}, setting it aside: {}", source, error);
+
+        let entry = serde_json::json!({
+            "source": source,
+            "payload": payload,
+            "error": error.to_string(),
+            "receivedAt": chrono::Utc::now(),
+        });
+        let malformed_name = self.malformed_name();
+        let result: redis::RedisResult<()> = redis::pipe()
+            .lpush(&malformed_name, entry.to_string())
+            .ignore()
+            .ltrim(&malformed_name, 0, MALFORMED_MAX_ENTRIES - 1)
+            .ignore()
+            .query_async(&mut self.connection_manager)
+            .await;
+        if let Err(e) = result {
+            error!("Failed to set aside malformed entry from {}, dropping it: {} ({})", source, e, payload);
+        }
+
+        WorkerError::MalformedJob(error.to_string())
+    }
+
+    /// Sorted set of jobs waiting out their retry backoff, scored by when
+    /// they are due in milliseconds since the epoch. It shares the queue's
+    /// cluster slot so `promote_due_jobs` can move jobs between them.
+    fn delayed_name(&self) -> String {
+        self.connection_manager.related_key(&self.queue_name, "delayed")
+    }
+
+    /// Enqueue the job once `delay` has passed. Consumers move due jobs to
+    /// the queue with `promote_due_jobs`, so the delay is only as precise as
+    /// their wait interval.
+    /// A job due straight away goes through the delayed set too, since its
+    /// idempotency key would keep `enqueue_job` from queueing it again.
+    pub async fn schedule_job(&mut self, job: &FileUploadJob, delay: Duration) -> WorkerResult<()> {
+        let mut job = job.clone();
+        telemetry::inject_trace_context(&mut job.metadata);
+
+        // Waiting out the backoff isn't queue wait
+        let due_at = chrono::Utc::now().timestamp_millis() + delay.as_millis() as i64;
+        job.enqueued_at = chrono::DateTime::from_timestamp_millis(due_at);
+        self.connection_manager
+            .zadd::<_, _, _, ()>(self.delayed_name(), job.to_json()?, due_at)
+            .await?;
+
+        self.settle_in_flight();
+        info!("Job {} scheduled on {} in {:?}", job.id, self.queue_name, delay);
+
+        if let Err(e) = self.update_job_progress(&job).await {
+            warn!("Failed to record progress for job {}: {}", job.id, e);
+        }
+        Ok(())
+    }
+
+    /// Move delayed jobs that are due to the queue; returns how many moved
+    pub async fn promote_due_jobs(&mut self) -> WorkerResult<usize> {
+        let script = r#"
+            local due = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, ARGV[2])
+            for _, job in ipairs(due) do
+                redis.call('ZREM', KEYS[1], job)
+                redis.call('LPUSH', KEYS[2], job)
+            end
+            return #due
+        "#;
+
+        let promoted: usize = redis::Script::new(script)
+            .key(self.delayed_name())
+            .key(&self.queue_name)
+            .arg(chrono::Utc::now().timestamp_millis())
+            .arg(PROMOTE_BATCH_SIZE)
+            .invoke_async(&mut self.connection_manager)
+            .await?;
+
+        if promoted > 0 {
+            info!("{} delayed jobs moved to {}", promoted, self.queue_name);
+            self.wake_consumers(promoted).await;
+        }
+        Ok(promoted)
     }
 
     pub async fn move_to_dlq(&mut self, job: &FileUploadJob) -> WorkerResult<()> {
+        let mut job = job.clone();
+        job.dead_lettered_at = Some(chrono::Utc::now());
+
         let job_json = job.to_json()?;
         self.connection_manager
             .lpush::<_, _, ()>(&self.dlq_name, job_json)
             .await?;
+        self.settle_in_flight();
 
         warn!("Job {} moved to DLQ: {}", job.id, self.dlq_name);
         Ok(())
     }
 
+    /// Note the in-flight job's fate was recorded, so a nack drops it
+    /// rather than handing it back to the queue
+    fn settle_in_flight(&mut self) {
+        if let Some(processing) = self.processing.as_mut().filter(|processing| processing.in_flight.is_some()) {
+            processing.settled = true;
+        }
+    }
+
+    /// Put a dead letter back at the far end of the DLQ without restarting
+    /// its retention
+    pub async fn return_to_dlq(&mut self, job: &FileUploadJob) -> WorkerResult<()> {
+        self.connection_manager
+            .lpush::<_, _, ()>(&self.dlq_name, job.to_json()?)
+            .await?;
+        Ok(())
+    }
+
     pub async fn dequeue_dlq_job(&mut self, timeout_seconds: u64) -> WorkerResult<Option<FileUploadJob>> {
-        let result: Option<(String, String)> = self.connection_manager
+        let result: Option<(String, String)> = self.blocking_connection
             .brpop(&self.dlq_name, timeout_seconds as f64)
             .await?;
 
@@ -77,8 +785,8 @@ impl RedisQueue {
                         Ok(Some(job))
                     }
                     Err(e) => {
-                        error!("Failed to deserialize job from DLQ {}: {}", self.dlq_name, e);
-                        Err(WorkerError::Json(e))
+                        let dlq_name = self.dlq_name.clone();
+                        Err(self.set_aside_malformed(&dlq_name, &job_json, e).await)
                     }
                 }
             }
@@ -93,10 +801,48 @@ impl RedisQueue {
         Ok(length)
     }
 
+    pub async fn get_delayed_length(&mut self) -> WorkerResult<u64> {
+        let length: u64 = self.connection_manager
+            .zcard(self.delayed_name())
+            .await?;
+        Ok(length)
+    }
+
+    /// Jobs dequeued by some consumer and not yet acked, across instances
+    pub async fn get_processing_length(&mut self) -> WorkerResult<u64> {
+        let processing_names: Vec<String> = self.connection_manager.zrange(self.processing_registry_name(), 0, -1).await?;
+        let mut length = 0;
+        for processing_name in processing_names {
+            length += self.connection_manager.llen::<_, u64>(processing_name).await?;
+        }
+        Ok(length)
+    }
+
     pub async fn get_dlq_length(&mut self) -> WorkerResult<u64> {
         let length: u64 = self.connection_manager
             .llen(&self.dlq_name)
             .await?;
         Ok(length)
     }
+
+    pub async fn get_malformed_length(&mut self) -> WorkerResult<u64> {
+        let length: u64 = self.connection_manager
+            .llen(self.malformed_name())
+            .await?;
+        Ok(length)
+    }
+}
+
+/// Fields of a job's progress hash
+fn progress_fields(job: &FileUploadJob) -> [(&'static str, String); 7] {
+    let last_error = job.errors.last().map(|e| e.message.clone()).unwrap_or_default();
+    [
+        ("status", job.progress.status.to_string()),
+        ("percent_complete", job.progress.percent_complete.to_string()),
+        ("current_step", job.progress.current_step.clone()),
+        ("retry_count", job.retry_count.to_string()),
+        ("last_error", last_error),
+        ("updated_at", job.progress.updated_at.to_rfc3339()),
+        ("submission_id", job.submission_id().to_string()),
+    ]
 }
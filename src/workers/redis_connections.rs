@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::commons::redis_connection::{RedisConnection, RedisTopology};
+use crate::workers::{WorkerConfig, WorkerError, WorkerMetrics, WorkerResult};
+
+/// RedisConnections owns the Redis connections of the worker system.
+///
+/// Regular commands (LPUSH, LLEN, locks) share one multiplexed connection.
+/// BRPOP holds its connection for the whole wait, which would stall every
+/// other caller on a multiplexed connection, so each consumer gets one
+/// dedicated connection for blocking dequeues.
+#[derive(Clone)]
+pub struct RedisConnections {
+    topology: RedisTopology,
+    shared: RedisConnection,
+    max_retries: usize,
+    retry_factor_ms: u64,
+    metrics: Arc<WorkerMetrics>,
+}
+
+impl RedisConnections {
+    pub async fn connect(config: &WorkerConfig, metrics: Arc<WorkerMetrics>) -> WorkerResult<Self> {
+        let topology = config.redis.clone();
+        let max_retries = config.redis_connection_max_retries;
+        let retry_factor_ms = config.redis_connection_retry_factor.as_millis() as u64;
+
+        let shared = Self::open(&topology, max_retries, retry_factor_ms, &metrics).await?;
+        info!("Shared Redis connection established");
+
+        Ok(Self {
+            topology,
+            shared,
+            max_retries,
+            retry_factor_ms,
+            metrics,
+        })
+    }
+
+    /// The multiplexed connection for non-blocking commands
+    pub fn shared(&self) -> RedisConnection {
+        self.shared.clone()
+    }
+
+    /// A new dedicated connection for blocking commands such as BRPOP
+    pub async fn blocking(&self) -> WorkerResult<RedisConnection> {
+        Self::open(&self.topology, self.max_retries, self.retry_factor_ms, &self.metrics).await
+    }
+
+    /// Count errors caused by a lost or refused Redis connection
+    pub fn record_error(&self, error: &WorkerError) {
+        if let WorkerError::Redis(e) = error {
+            if e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() || e.is_timeout() {
+                self.metrics.record_redis_connection_error();
+            }
+        }
+    }
+
+    /// Connect with exponential backoff. The returned connection reconnects
+    /// with the same backoff when the connection drops later on.
+    async fn open(
+        topology: &RedisTopology,
+        max_retries: usize,
+        retry_factor_ms: u64,
+        metrics: &WorkerMetrics,
+    ) -> WorkerResult<RedisConnection> {
+        topology
+            .connect_with_backoff(retry_factor_ms, max_retries)
+            .await
+            .map_err(|e| {
+                metrics.record_redis_connection_error();
+                error!("Failed to connect to Redis after {} retries: {}", max_retries, e);
+                WorkerError::Redis(e)
+            })
+    }
+}
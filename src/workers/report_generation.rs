@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::PgPool;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    commons::{crypto::FieldCipher, minio_service::MinioService},
+    services::report_service::{ReportService, REPORT_CONTENT_TYPE},
+    submissions::{submission_documents::DocumentType, submission_repository::SubmissionRepository},
+    workers::{FileUploadJob, WorkerError, WorkerResult},
+};
+
+/// Only approved submissions get a report
+pub const REPORTABLE_STATUS: &str = "APPROVED";
+
+/// How long repeated report requests are folded into the queued job,
+/// whatever `WORKER_ENQUEUE_DEDUP_TTL_SECONDS` says
+pub const REPORT_ENQUEUE_DEDUP_TTL: Duration = Duration::from_secs(300);
+
+/// Renders the PDF report of a submission and stores it in MinIO
+pub struct ReportGenerator {
+    minio_service: MinioService,
+    reports: ReportService,
+    submission_repository: SubmissionRepository,
+}
+
+impl ReportGenerator {
+    pub fn new(minio_service: MinioService, pool: PgPool, cipher: FieldCipher) -> Self {
+        Self {
+            minio_service,
+            reports: ReportService::new(),
+            submission_repository: SubmissionRepository::new(pool, cipher),
+        }
+    }
+
+    pub async fn from_env(pool: PgPool) -> WorkerResult<Self> {
+        let var = |name: &str| std::env::var(name).map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be set", name)));
+
+        let minio_service = MinioService::new(
+            &var("MINIO_ENDPOINT")?,
+            &var("MINIO_ACCESS_KEY")?,
+            &var("MINIO_SECRET_KEY")?,
+            &var("MINIO_BUCKET_NAME")?,
+        )
+        .await
+        .map_err(WorkerError::Config)?;
+
+        Ok(Self::new(minio_service, pool, FieldCipher::from_env()?))
+    }
+
+    /// Render the report of the job's submission into the job's document
+    pub async fn generate(&self, job: &FileUploadJob) -> WorkerResult<()> {
+        let submission_id = Uuid::parse_str(&job.esign_id)
+            .map_err(|_| WorkerError::Report(format!("{} is not a submission id", job.esign_id)))?;
+
+        let summary = self
+            .submission_repository
+            .find_summary(submission_id)
+            .await?
+            .ok_or_else(|| WorkerError::Report(format!("Submission {} not found", submission_id)))?;
+
+        if summary.status != REPORTABLE_STATUS {
+            return Err(WorkerError::Report(format!(
+                "Submission {} is {}, only {} submissions get a report",
+                submission_id, summary.status, REPORTABLE_STATUS
+            )));
+        }
+
+        // A document that can't be shown doesn't hold up the report
+        let mut thumbnails = Vec::new();
+        for (document_type, document) in summary.documents.iter() {
+            if document_type == DocumentType::Nfc {
+                continue;
+            }
+
+            let content = match self.minio_service.download_file(document.document_name.clone()).await {
+                Ok(content) => content,
+                Err(e) => {
+                    warn!("Leaving {} out of the report of {}: {}", document_type, submission_id, e);
+                    continue;
+                }
+            };
+            match self.reports.thumbnail(content).await {
+                Ok(thumbnail) => thumbnails.push((document_type, thumbnail)),
+                Err(e) => warn!("Leaving {} out of the report of {}: {}", document_type, submission_id, e),
+            }
+        }
+
+        let pdf = self
+            .reports
+            .render(&summary, thumbnails, Utc::now())
+            .map_err(|e| WorkerError::Report(e.to_string()))?;
+
+        self.minio_service
+            .upload_file(job.document_name.clone(), pdf, Some(REPORT_CONTENT_TYPE.to_string()))
+            .await
+            .map_err(|e| WorkerError::Storage(e.to_string()))?;
+
+        info!("Stored report {} of submission {}", job.document_name, submission_id);
+        Ok(())
+    }
+}
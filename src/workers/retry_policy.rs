@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use std::env;
+use std::time::Duration;
+
+use crate::workers::JobKind;
+
+/// How a job kind is retried and how long its dead letters stay in Redis
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts, the first one included, before the job is dead lettered
+    pub max_attempts: u32,
+    /// Wait before the first retry; zero re-enqueues straight away
+    pub backoff_initial: Duration,
+    /// Each further retry waits this many times longer than the previous one
+    pub backoff_multiplier: f64,
+    pub backoff_max: Duration,
+    /// How long a dead letter stays in the DLQ before it is archived to
+    /// Postgres; None archives it as soon as recovery fails
+    pub dlq_ttl: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Defaults match the behaviour from before policies: immediate retries,
+    /// dead letters archived right away
+    fn from_env(kind: &str, default_max_attempts: u32) -> anyhow::Result<Self> {
+        let var = |name: &str| env::var(format!("WORKER_RETRY_{}_{}", kind, name)).ok().filter(|v| !v.is_empty());
+
+        let dlq_ttl_seconds: u64 = env::var(format!("WORKER_DLQ_{}_TTL_SECONDS", kind))
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse())
+            .transpose()?
+            .unwrap_or(0);
+
+        let backoff_multiplier: f64 = var("BACKOFF_MULTIPLIER").map(|v| v.parse()).transpose()?.unwrap_or(2.0);
+        if backoff_multiplier < 1.0 {
+            anyhow::bail!("WORKER_RETRY_{}_BACKOFF_MULTIPLIER must be at least 1", kind);
+        }
+
+        Ok(Self {
+            max_attempts: var("MAX_ATTEMPTS").map(|v| v.parse()).transpose()?.unwrap_or(default_max_attempts),
+            backoff_initial: Duration::from_millis(
+                var("BACKOFF_INITIAL_MILLISECONDS").map(|v| v.parse()).transpose()?.unwrap_or(0),
+            ),
+            backoff_multiplier,
+            backoff_max: Duration::from_millis(
+                var("BACKOFF_MAX_MILLISECONDS").map(|v| v.parse()).transpose()?.unwrap_or(300_000),
+            ),
+            dlq_ttl: (dlq_ttl_seconds > 0).then(|| Duration::from_secs(dlq_ttl_seconds)),
+        })
+    }
+
+    /// Wait before retry number `retry_count`, counting from 1
+    pub fn backoff(&self, retry_count: u32) -> Duration {
+        if self.backoff_initial.is_zero() {
+            return Duration::ZERO;
+        }
+
+        let exponent = retry_count.saturating_sub(1).min(64) as i32;
+        let millis = self.backoff_initial.as_millis() as f64 * self.backoff_multiplier.powi(exponent);
+        Duration::from_millis(millis.min(self.backoff_max.as_millis() as f64) as u64)
+    }
+
+    /// How much longer a dead letter from `dead_lettered_at` stays in the
+    /// DLQ, or None once it is due for archiving
+    pub fn dlq_retention_remaining(&self, dead_lettered_at: DateTime<Utc>) -> Option<Duration> {
+        let ttl = chrono::Duration::from_std(self.dlq_ttl?).ok()?;
+        (dead_lettered_at + ttl - Utc::now()).to_std().ok().filter(|remaining| !remaining.is_zero())
+    }
+}
+
+/// Retry policy of every job kind
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicies {
+    upload: RetryPolicy,
+    report: RetryPolicy,
+    notification: RetryPolicy,
+    copy: RetryPolicy,
+    scan: RetryPolicy,
+}
+
+impl RetryPolicies {
+    /// Each kind reads `WORKER_RETRY_<KIND>_*` and `WORKER_DLQ_<KIND>_TTL_SECONDS`,
+    /// falling back to `default_max_attempts` for its retry budget
+    pub fn from_env(default_max_attempts: u32) -> anyhow::Result<Self> {
+        Ok(Self {
+            upload: RetryPolicy::from_env("UPLOAD", default_max_attempts)?,
+            report: RetryPolicy::from_env("REPORT", default_max_attempts)?,
+            notification: RetryPolicy::from_env("NOTIFICATION", default_max_attempts)?,
+            copy: RetryPolicy::from_env("COPY", default_max_attempts)?,
+            scan: RetryPolicy::from_env("SCAN", default_max_attempts)?,
+        })
+    }
+
+    pub fn for_kind(&self, kind: JobKind) -> &RetryPolicy {
+        match kind {
+            JobKind::Upload => &self.upload,
+            JobKind::Report => &self.report,
+            JobKind::Notification => &self.notification,
+            JobKind::Copy => &self.copy,
+            JobKind::Scan => &self.scan,
+        }
+    }
+
+    /// Whether some kind keeps its dead letters in the DLQ for a while
+    pub fn retains_dead_letters(&self) -> bool {
+        [&self.upload, &self.report, &self.notification, &self.copy, &self.scan]
+            .iter()
+            .any(|policy| policy.dlq_ttl.is_some())
+    }
+}
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::{
+    commons::crypto::FieldCipher,
+    services::webhook_service::{WebhookService, WEBHOOK_EVENT_SLA_BREACHED},
+    submissions::submission_repository::{SlaBreach, SubmissionRepository},
+    workers::{WorkerError, WorkerMetrics, WorkerResult},
+};
+
+/// Submissions flagged per query before looking for more
+const SLA_BATCH_SIZE: i64 = 100;
+
+/// How often the wait between checks looks at the shutdown signal
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Which SLA a submission breached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sla {
+    /// From creation to the first decision
+    Decision,
+    /// From a manual review decision to the reviewer's outcome
+    Review,
+}
+
+impl Sla {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sla::Decision => "DECISION",
+            Sla::Review => "REVIEW",
+        }
+    }
+}
+
+/// Flags submissions that take longer than their SLA: no decision within
+/// `decision` of being created, or no reviewer outcome within `review` of
+/// going to manual review. Each breach is logged, counted and, if enabled,
+/// sent as a webhook once.
+pub struct SlaMonitor {
+    submissions: SubmissionRepository,
+    webhooks: Option<WebhookService>,
+    metrics: Arc<WorkerMetrics>,
+    decision: Option<Duration>,
+    review: Option<Duration>,
+    interval: Duration,
+}
+
+impl SlaMonitor {
+    /// None unless `SUBMISSION_SLA_ENABLED=true`
+    pub fn from_env(pool: PgPool, metrics: Arc<WorkerMetrics>) -> WorkerResult<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        if var("SUBMISSION_SLA_ENABLED").as_deref() != Some("true") {
+            return Ok(None);
+        }
+
+        let number = |name: &str, default: u64| {
+            var(name)
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map(|v| v.unwrap_or(default))
+                .map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be a number", name)))
+        };
+        // 0 turns an SLA off
+        let sla = |name: &str, default: u64| number(name, default).map(|seconds| (seconds > 0).then(|| Duration::from_secs(seconds)));
+
+        let webhooks = if var("SUBMISSION_SLA_WEBHOOK_ENABLED").as_deref() == Some("true") {
+            Some(WebhookService::from_env(pool.clone())?)
+        } else {
+            None
+        };
+
+        Ok(Some(Self {
+            submissions: SubmissionRepository::new(pool, FieldCipher::from_env()?),
+            webhooks,
+            metrics,
+            decision: sla("SUBMISSION_SLA_DECISION_SECONDS", 15 * 60)?,
+            review: sla("SUBMISSION_SLA_REVIEW_SECONDS", 24 * 60 * 60)?,
+            interval: Duration::from_secs(number("SUBMISSION_SLA_CHECK_INTERVAL_SECONDS", 60)?.max(1)),
+        }))
+    }
+
+    /// Check every `interval` until `shutdown_signal` is set
+    pub async fn run(&self, shutdown_signal: Arc<AtomicBool>) {
+        info!(
+            "Tracking submission SLAs: decision {:?}, review {:?}, checking every {:?}",
+            self.decision, self.review, self.interval
+        );
+
+        while !shutdown_signal.load(Ordering::SeqCst) {
+            for (sla, limit) in [(Sla::Decision, self.decision), (Sla::Review, self.review)] {
+                if let Some(limit) = limit {
+                    if let Err(e) = self.check(sla, limit).await {
+                        error!("Failed to check the {} SLA: {}", sla.as_str(), e);
+                    }
+                }
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < self.interval && !shutdown_signal.load(Ordering::SeqCst) {
+                tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+                waited += SHUTDOWN_POLL_INTERVAL;
+            }
+        }
+
+        info!("SLA monitor stopped");
+    }
+
+    /// Flag every submission past `limit`. Flagging is a single update, so
+    /// several worker instances never report the same breach twice.
+    async fn check(&self, sla: Sla, limit: Duration) -> WorkerResult<()> {
+        let cutoff = Utc::now() - chrono::Duration::seconds(limit.as_secs() as i64);
+
+        loop {
+            let breaches = match sla {
+                Sla::Decision => self.submissions.flag_decision_sla_breaches(cutoff, SLA_BATCH_SIZE).await?,
+                Sla::Review => self.submissions.flag_review_sla_breaches(cutoff, SLA_BATCH_SIZE).await?,
+            };
+            match sla {
+                Sla::Decision => self.metrics.record_decision_sla_breaches(breaches.len() as u64),
+                Sla::Review => self.metrics.record_review_sla_breaches(breaches.len() as u64),
+            }
+
+            for breach in &breaches {
+                self.report(sla, limit, breach);
+            }
+
+            if (breaches.len() as i64) < SLA_BATCH_SIZE {
+                return Ok(());
+            }
+        }
+    }
+
+    fn report(&self, sla: Sla, limit: Duration, breach: &SlaBreach) {
+        let now = Utc::now();
+        let since = |at: Option<DateTime<Utc>>| at.map(|at| (now - at).num_seconds());
+        let between = |from: DateTime<Utc>, to: Option<DateTime<Utc>>| to.map(|to| (to - from).num_seconds());
+
+        // Elapsed time overall and per stage reached so far
+        let elapsed_seconds = match sla {
+            Sla::Decision => (now - breach.created_at).num_seconds(),
+            Sla::Review => since(breach.decided_at).unwrap_or_default(),
+        };
+        let stages = json!({
+            "createdAt": breach.created_at,
+            "processingStartedAt": breach.processing_started_at,
+            "decidedAt": breach.decided_at,
+            "secondsToProcessing": between(breach.created_at, breach.processing_started_at),
+            "secondsToDecision": between(breach.created_at, breach.decided_at),
+            "secondsInReview": if sla == Sla::Review { since(breach.decided_at) } else { None },
+        });
+
+        warn!(
+            "Submission {} ({} {}, tenant {}) breached the {} SLA of {}s: {}s elapsed, stages {}",
+            breach.submission_id,
+            breach.submission_type,
+            breach.status,
+            breach.tenant_id,
+            sla.as_str(),
+            limit.as_secs(),
+            elapsed_seconds,
+            stages
+        );
+
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.dispatch(
+                WEBHOOK_EVENT_SLA_BREACHED,
+                json!({
+                    "submissionId": breach.submission_id,
+                    "tenantId": breach.tenant_id,
+                    "submissionType": breach.submission_type,
+                    "status": breach.status,
+                    "sla": sla.as_str(),
+                    "slaSeconds": limit.as_secs(),
+                    "elapsedSeconds": elapsed_seconds,
+                    "stages": stages,
+                }),
+            );
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use futures::FutureExt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+use crate::workers::heartbeat::{ConsumerState, WorkerHeartbeats};
+use crate::workers::{WorkerMetrics, WorkerResult};
+
+/// Delay before restarting a task that failed, doubling on each failure in a
+/// row up to the maximum
+const RESTART_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const RESTART_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How often a task waiting out its restart backoff checks for shutdown
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The long-running tasks of the worker system. Each runs under a supervisor
+/// that logs its errors and panics and starts it again, until it returns
+/// `Ok` or shutdown is signalled; shutdown waits for all of them.
+pub struct WorkerTasks {
+    tasks: JoinSet<()>,
+    shutdown_signal: Arc<AtomicBool>,
+    metrics: Arc<WorkerMetrics>,
+    heartbeats: Arc<WorkerHeartbeats>,
+}
+
+impl WorkerTasks {
+    pub fn new(shutdown_signal: Arc<AtomicBool>, metrics: Arc<WorkerMetrics>, heartbeats: Arc<WorkerHeartbeats>) -> Self {
+        Self {
+            tasks: JoinSet::new(),
+            shutdown_signal,
+            metrics,
+            heartbeats,
+        }
+    }
+
+    /// Supervise a background task; `task` starts a fresh run of it
+    pub fn spawn<F, Fut>(&mut self, name: impl Into<String>, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = WorkerResult<()>> + Send + 'static,
+    {
+        let supervisor = Supervisor {
+            name: name.into(),
+            shutdown_signal: self.shutdown_signal.clone(),
+            metrics: self.metrics.clone(),
+            heartbeats: None,
+        };
+        self.tasks.spawn(supervisor.run(task));
+    }
+
+    /// Supervise a queue consumer reporting to the heartbeats as `worker_id`,
+    /// so a panic is logged with the job it happened on
+    pub fn spawn_consumer<F, Fut>(&mut self, worker_id: impl Into<String>, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = WorkerResult<()>> + Send + 'static,
+    {
+        let supervisor = Supervisor {
+            name: worker_id.into(),
+            shutdown_signal: self.shutdown_signal.clone(),
+            metrics: self.metrics.clone(),
+            heartbeats: Some(self.heartbeats.clone()),
+        };
+        self.tasks.spawn(supervisor.run(task));
+    }
+
+    /// Tasks that haven't finished yet
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Wait for every task to finish
+    pub async fn join_all(&mut self) {
+        while let Some(result) = self.tasks.join_next().await {
+            // Supervisors catch their task's panics, so this is a bug in one
+            if let Err(e) = result {
+                if !e.is_cancelled() {
+                    error!("Worker task supervisor failed: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Cancel the tasks still running. A consumer cancelled mid-job leaves
+    /// the job on its processing list, to be reclaimed once its lease expires.
+    pub fn abort_all(&mut self) {
+        self.tasks.abort_all();
+    }
+}
+
+struct Supervisor {
+    name: String,
+    shutdown_signal: Arc<AtomicBool>,
+    metrics: Arc<WorkerMetrics>,
+    heartbeats: Option<Arc<WorkerHeartbeats>>,
+}
+
+impl Supervisor {
+    async fn run<F, Fut>(self, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = WorkerResult<()>> + Send + 'static,
+    {
+        let mut backoff = RESTART_INITIAL_BACKOFF;
+
+        loop {
+            let started_at = Instant::now();
+
+            // Catch the panic here rather than in a task of its own, so
+            // aborting the supervisor also cancels the run
+            match AssertUnwindSafe(task()).catch_unwind().await {
+                Ok(Ok(())) => break,
+                Ok(Err(e)) => error!(task = %self.name, "Worker task failed: {}", e),
+                Err(payload) => {
+                    let job_id = self.heartbeats.as_ref().and_then(|heartbeats| heartbeats.current_job(&self.name));
+                    error!(task = %self.name, job_id = ?job_id, "Worker task panicked: {}", panic_message(payload));
+                    if let Some(heartbeats) = &self.heartbeats {
+                        self.metrics.record_consumer_panic();
+                        if let Some(job_id) = job_id {
+                            heartbeats.record_panic(&self.name, job_id);
+                        }
+                    }
+                }
+            }
+
+            if self.shutdown_signal.load(Ordering::SeqCst) {
+                break;
+            }
+            if let Some(heartbeats) = &self.heartbeats {
+                heartbeats.beat(&self.name, ConsumerState::Idle, None);
+            }
+
+            // A task that ran for a while before failing starts the backoff over
+            if started_at.elapsed() >= RESTART_MAX_BACKOFF {
+                backoff = RESTART_INITIAL_BACKOFF;
+            }
+            warn!(task = %self.name, "Restarting worker task in {:?}", backoff);
+            let mut waited = Duration::ZERO;
+            while waited < backoff && !self.shutdown_signal.load(Ordering::SeqCst) {
+                sleep(SHUTDOWN_POLL_INTERVAL).await;
+                waited += SHUTDOWN_POLL_INTERVAL;
+            }
+            if self.shutdown_signal.load(Ordering::SeqCst) {
+                break;
+            }
+            self.metrics.record_task_restart();
+            backoff = (backoff * 2).min(RESTART_MAX_BACKOFF);
+        }
+
+        // A consumer that failed on its way out never reported itself stopped
+        if let Some(heartbeats) = &self.heartbeats {
+            heartbeats.beat(&self.name, ConsumerState::Stopped, None);
+        }
+        info!(task = %self.name, "Worker task stopped");
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
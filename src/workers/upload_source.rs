@@ -0,0 +1,118 @@
+use reqwest::Url;
+use std::env;
+
+use crate::workers::{WorkerError, WorkerResult};
+
+/// Where upload jobs may fetch their document from. Job payloads can come
+/// from outside the API (gRPC, replays from the admin API), so the worker
+/// never follows a `document_url` to a host it wasn't told about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadSourcePolicy {
+    /// Lowercase schemes, e.g. `https`
+    schemes: Vec<String>,
+    /// Lowercase hosts, optionally with a port; `*.example.com` allows any
+    /// subdomain of `example.com`
+    hosts: Vec<String>,
+}
+
+impl UploadSourcePolicy {
+    pub fn new(schemes: Vec<String>, hosts: Vec<String>) -> Self {
+        let lowercase = |values: Vec<String>| {
+            values
+                .into_iter()
+                .map(|v| v.trim().to_ascii_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect()
+        };
+        Self {
+            schemes: lowercase(schemes),
+            hosts: lowercase(hosts),
+        }
+    }
+
+    /// `WORKER_UPLOAD_ALLOWED_SCHEMES` (`https` by default) and
+    /// `WORKER_UPLOAD_ALLOWED_HOSTS`, comma separated. The `MINIO_ENDPOINT`
+    /// host and scheme are always allowed, since the presigned URLs the API
+    /// enqueues point there.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let list = |name: &str, default: &str| -> Vec<String> {
+            env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| default.to_string())
+                .split(',')
+                .map(str::to_string)
+                .collect()
+        };
+
+        let mut schemes = list("WORKER_UPLOAD_ALLOWED_SCHEMES", "https");
+        let mut hosts = list("WORKER_UPLOAD_ALLOWED_HOSTS", "");
+
+        if let Some(endpoint) = env::var("MINIO_ENDPOINT").ok().filter(|v| !v.is_empty()) {
+            let endpoint = Url::parse(&endpoint).map_err(|e| anyhow::anyhow!("MINIO_ENDPOINT must be a URL: {}", e))?;
+            if let Some(host) = endpoint.host_str() {
+                schemes.push(endpoint.scheme().to_string());
+                hosts.push(match endpoint.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.to_string(),
+                });
+            }
+        }
+
+        let policy = Self::new(schemes, hosts);
+        if let Some(pattern) = policy.hosts.iter().find(|h| h.starts_with('*') && !h.starts_with("*.")) {
+            anyhow::bail!("WORKER_UPLOAD_ALLOWED_HOSTS wildcards must look like *.example.com, not {}", pattern);
+        }
+        Ok(policy)
+    }
+
+    /// Fail with `DisallowedSource` unless the URL uses an allowed scheme
+    /// and host and carries no credentials
+    pub fn check(&self, document_url: &str) -> WorkerResult<()> {
+        let disallowed = |reason: String| Err(WorkerError::DisallowedSource(reason));
+
+        let url = match Url::parse(document_url) {
+            Ok(url) => url,
+            Err(e) => return disallowed(format!("not a URL ({})", e)),
+        };
+        if !self.schemes.iter().any(|scheme| scheme == url.scheme()) {
+            return disallowed(format!("scheme {} isn't allowed", url.scheme()));
+        }
+        if !url.username().is_empty() || url.password().is_some() {
+            return disallowed("URLs with credentials aren't allowed".to_string());
+        }
+        let Some(host) = url.host_str() else {
+            return disallowed("URL has no host".to_string());
+        };
+        let host = host.to_ascii_lowercase();
+        let port = url.port_or_known_default();
+
+        if !self.hosts.iter().any(|pattern| host_matches(pattern, &host, port)) {
+            return disallowed(format!("host {} isn't allowed", host));
+        }
+        Ok(())
+    }
+}
+
+/// Whether `host` on `port` matches an allow-list entry; entries without a
+/// port allow any
+fn host_matches(pattern: &str, host: &str, port: Option<u16>) -> bool {
+    // IPv6 entries are bracketed like URL hosts, so `[::1]` has no port
+    let (pattern_host, pattern_port) = match pattern.rsplit_once(':') {
+        Some((pattern_host, pattern_port)) => match pattern_port.parse::<u16>() {
+            Ok(pattern_port) => (pattern_host, Some(pattern_port)),
+            Err(_) => (pattern, None),
+        },
+        None => (pattern, None),
+    };
+    if pattern_port.is_some() && pattern_port != port {
+        return false;
+    }
+
+    match pattern_host.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+        None => pattern_host == host,
+    }
+}
@@ -1,193 +1,414 @@
 use crate::workers::{
-    DistributedLock, FileUploadJob, RedisQueue, WorkerConfig, WorkerError, WorkerResult, WorkerMetrics
+    DistributedLock, FileUploadJob, JobKind, JobStatus, PollStrategy, RedisQueue, WorkerConfig, WorkerError, WorkerResult, WorkerMetrics
 };
-use redis::aio::ConnectionManager;
-use redis::Client;
+use crate::commons::{maintenance::MaintenanceMode, telemetry};
+use crate::workers::heartbeat::{ConsumerState, WorkerHeartbeats};
+use crate::services::scanner_service::ScanVerdict;
+use crate::workers::document_scanning::DocumentScanner;
+use crate::workers::document_progress::DocumentProgress;
+use crate::submissions::submission_documents::DocumentUploadStatus;
+use crate::workers::image_preprocessing::ImagePreprocessor;
+use crate::workers::report_generation::ReportGenerator;
+use crate::workers::notification_delivery::NotificationSender;
+use crate::workers::object_copy::ObjectCopier;
+use crate::workers::upload_source::UploadSourcePolicy;
+use crate::workers::redis_connections::RedisConnections;
+use crate::workers::supervisor::WorkerTasks;
+use crate::workers::wakeup::WakeupSubscriber;
+use crate::workers::job_archive::JobArchive;
+use crate::workers::job_execution_repository::{
+    JobExecution, JobExecutionRepository, OUTCOME_DEAD_LETTERED, OUTCOME_ERROR, OUTCOME_LOCK_UNAVAILABLE,
+    OUTCOME_QUARANTINED, OUTCOME_RETRIED, OUTCOME_SUCCEEDED,
+};
+use crate::commons::redis_connection::RedisConnection;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
-use std::time::Instant;
-use tokio::sync::mpsc;
-use tokio::time::sleep;
+use std::time::{Duration, Instant};
+use tokio::time::{sleep, timeout};
 use tracing::{debug, error, info, instrument, warn};
 
+/// How often the queue depth gauges are updated
+const QUEUE_DEPTH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How a job that ran all its stages ended
+enum StageOutcome {
+    Completed,
+    /// The scanner found the document infected and moved it to quarantine
+    Quarantined,
+}
+
 /// FileUploadWorker processes file upload jobs from a Redis queue
 pub struct FileUploadWorker {
     config: WorkerConfig,
-    redis_client: Client,
+    /// Tells this process's processing lists apart from other instances'
+    instance_id: String,
+    redis: RedisConnections,
     shutdown_signal: Arc<AtomicBool>,
     metrics: Arc<WorkerMetrics>,
+    heartbeats: Arc<WorkerHeartbeats>,
+    image_preprocessor: Option<Arc<ImagePreprocessor>>,
+    document_scanner: Option<Arc<DocumentScanner>>,
+    report_generator: Option<Arc<ReportGenerator>>,
+    notification_sender: Option<Arc<NotificationSender>>,
+    object_copier: Option<Arc<ObjectCopier>>,
+    document_progress: Arc<DocumentProgress>,
+    job_executions: JobExecutionRepository,
+    /// Keeps jobs that ran to completion when the archive is on
+    job_archive: Option<Arc<JobArchive>>,
+    /// Consumers stop taking jobs while a maintenance window is on
+    maintenance: MaintenanceMode,
 }
 
 impl FileUploadWorker {
-    pub fn new(config: WorkerConfig, shutdown_signal: Arc<AtomicBool>, metrics: Arc<WorkerMetrics>) -> WorkerResult<Self> {
-        let redis_client = Client::open(&config.redis_url[..])?;
-
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        config: WorkerConfig,
+        redis: RedisConnections,
+        shutdown_signal: Arc<AtomicBool>,
+        metrics: Arc<WorkerMetrics>,
+        heartbeats: Arc<WorkerHeartbeats>,
+        image_preprocessor: Option<Arc<ImagePreprocessor>>,
+        document_scanner: Option<Arc<DocumentScanner>>,
+        report_generator: Option<Arc<ReportGenerator>>,
+        notification_sender: Option<Arc<NotificationSender>>,
+        object_copier: Option<Arc<ObjectCopier>>,
+        document_progress: Arc<DocumentProgress>,
+        job_executions: JobExecutionRepository,
+        job_archive: Option<Arc<JobArchive>>,
+    ) -> WorkerResult<Self> {
         Ok(Self {
             config,
-            redis_client,
+            instance_id: uuid::Uuid::new_v4().simple().to_string(),
+            maintenance: MaintenanceMode::from_connection(redis.shared()),
+            redis,
             shutdown_signal,
             metrics,
+            heartbeats,
+            image_preprocessor,
+            document_scanner,
+            report_generator,
+            notification_sender,
+            object_copier,
+            document_progress,
+            job_executions,
+            job_archive,
         })
     }
 
-    /// Start the worker pool with the configured number of threads
-    pub async fn start(&self) -> WorkerResult<()> {
+    /// Start the worker pool with the configured number of threads, its
+    /// tasks supervised in `tasks`
+    pub async fn start(&self, tasks: &mut WorkerTasks) -> WorkerResult<()> {
         info!(
             "Starting FileUploadWorker with {} threads",
             self.config.background_worker_consumer_thread_count
         );
 
-        let (tx, mut rx) = mpsc::channel(100);
+        // Periodically update queue metrics, once for the whole pool
+        let metrics = self.metrics.clone();
+        let redis = self.redis.clone();
+        let config = self.config.clone();
+        let shutdown_signal = self.shutdown_signal.clone();
+        tasks.spawn("queue-depth", move || {
+            Self::report_queue_depth(redis.clone(), config.clone(), shutdown_signal.clone(), metrics.clone())
+        });
+
+        self.maintenance.spawn_refresh();
 
-        // Spawn consumer threads
-        let mut handles = Vec::new();
         for i in 0..self.config.background_worker_consumer_thread_count {
             let worker_id = format!("worker-{}", i);
+            let consumer_id = format!("{}:{}", self.instance_id, worker_id);
             let thread_config = self.config.clone();
-            let thread_client = self.redis_client.clone();
+            let thread_redis = self.redis.clone();
             let thread_shutdown = self.shutdown_signal.clone();
-            let thread_tx = tx.clone();
             let thread_metrics = self.metrics.clone();
-
-            let handle = tokio::spawn(async move {
-                let result = Self::run_consumer(
-                    worker_id,
-                    thread_config,
-                    thread_client,
-                    thread_shutdown,
-                    thread_tx,
-                    thread_metrics,
+            let thread_heartbeats = self.heartbeats.clone();
+            let thread_preprocessor = self.image_preprocessor.clone();
+            let thread_scanner = self.document_scanner.clone();
+            let thread_report_generator = self.report_generator.clone();
+            let thread_notification_sender = self.notification_sender.clone();
+            let thread_object_copier = self.object_copier.clone();
+            let thread_document_progress = self.document_progress.clone();
+            let thread_job_executions = self.job_executions.clone();
+            let thread_job_archive = self.job_archive.clone();
+            let thread_maintenance = self.maintenance.clone();
+
+            tasks.spawn_consumer(worker_id.clone(), move || {
+                Self::run_consumer(
+                    worker_id.clone(),
+                    consumer_id.clone(),
+                    thread_config.clone(),
+                    thread_redis.clone(),
+                    thread_shutdown.clone(),
+                    thread_metrics.clone(),
+                    thread_heartbeats.clone(),
+                    thread_preprocessor.clone(),
+                    thread_scanner.clone(),
+                    thread_report_generator.clone(),
+                    thread_notification_sender.clone(),
+                    thread_object_copier.clone(),
+                    thread_document_progress.clone(),
+                    thread_job_executions.clone(),
+                    thread_job_archive.clone(),
+                    thread_maintenance.clone(),
                 )
-                .await;
-
-                if let Err(e) = result {
-                    error!("Worker thread exited with error: {}", e);
-                }
             });
-
-            handles.push(handle);
         }
 
-        // Drop the original sender so the channel can close when all senders are done
-        drop(tx);
+        Ok(())
+    }
 
-        // Wait for shutdown signal
-        tokio::spawn(async move {
-            // Wait for all threads to report completion
-            let mut completed_count = 0;
-            while let Some(worker_id) = rx.recv().await {
-                info!("Worker {} completed graceful shutdown", worker_id);
-                completed_count += 1;
-            }
+    /// Update the queue depth gauges every minute until shutdown
+    async fn report_queue_depth(
+        redis: RedisConnections,
+        config: WorkerConfig,
+        shutdown_signal: Arc<AtomicBool>,
+        metrics: Arc<WorkerMetrics>,
+    ) -> WorkerResult<()> {
+        let mut queue = RedisQueue::from_connections(
+            redis.shared(),
+            redis.shared(),
+            config.worker_upload_file_queue.clone(),
+            config.worker_upload_file_dlq.clone(),
+        );
+        let mut next_update = Instant::now();
 
-            info!(
-                "All {} worker threads completed graceful shutdown",
-                completed_count
-            );
-        });
+        while !shutdown_signal.load(Ordering::Relaxed) {
+            if Instant::now() >= next_update {
+                if let (Ok(main_depth), Ok(dlq_depth)) = (queue.get_queue_length().await, queue.get_dlq_length().await) {
+                    metrics.update_queue_depth(main_depth, dlq_depth);
+                }
+                next_update = Instant::now() + QUEUE_DEPTH_INTERVAL;
+            }
+            sleep(Duration::from_secs(1)).await;
+        }
 
         Ok(())
     }
 
-    #[instrument(skip(config, client, shutdown_signal, completion_tx, metrics), fields(worker_id = %worker_id))]
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(
+        skip(
+            consumer_id, config, redis, shutdown_signal, metrics, heartbeats, image_preprocessor, document_scanner,
+            report_generator, notification_sender, object_copier, document_progress, job_executions, job_archive,
+            maintenance
+        ),
+        fields(worker_id = %worker_id)
+    )]
     async fn run_consumer(
         worker_id: String,
+        consumer_id: String,
         config: WorkerConfig,
-        client: Client,
+        redis: RedisConnections,
         shutdown_signal: Arc<AtomicBool>,
-        completion_tx: mpsc::Sender<String>,
         metrics: Arc<WorkerMetrics>,
+        heartbeats: Arc<WorkerHeartbeats>,
+        image_preprocessor: Option<Arc<ImagePreprocessor>>,
+        document_scanner: Option<Arc<DocumentScanner>>,
+        report_generator: Option<Arc<ReportGenerator>>,
+        notification_sender: Option<Arc<NotificationSender>>,
+        object_copier: Option<Arc<ObjectCopier>>,
+        document_progress: Arc<DocumentProgress>,
+        job_executions: JobExecutionRepository,
+        job_archive: Option<Arc<JobArchive>>,
+        maintenance: MaintenanceMode,
     ) -> WorkerResult<()> {
         info!("Worker thread started");
 
-        // Create Redis connection
-        let conn_manager = ConnectionManager::new(client).await?;
+        // Locks and enqueues go over the shared connection; dequeues block on our own
+        let conn_manager = redis.shared();
 
         // Create queue handler
-        let mut queue = RedisQueue::new(
-            &config.redis_url,
+        let mut queue = RedisQueue::from_connections(
+            conn_manager.clone(),
+            redis.blocking().await?,
             config.worker_upload_file_queue.clone(),
             config.worker_upload_file_dlq.clone(),
         )
-        .await?;
-
-        // Periodically update queue metrics
-        let metrics_clone = metrics.clone();
-        let mut queue_clone = queue.clone();
-        tokio::spawn(async move {
-            loop {
-                if let (Ok(main_depth), Ok(dlq_depth)) = (queue_clone.get_queue_length().await, queue_clone.get_dlq_length().await) {
-                    metrics_clone.update_queue_depth(main_depth, dlq_depth);
+        .with_dedup_ttl(config.enqueue_dedup_ttl)
+        .with_processing_list(&consumer_id, config.visibility_timeout)
+        .with_wakeups(config.poll_strategy == PollStrategy::Pubsub);
+
+        let mut wakeups = (config.poll_strategy == PollStrategy::Pubsub)
+            .then(|| WakeupSubscriber::new(config.redis.clone(), &queue, config.pubsub_max_idle));
+
+        // A consumer restarted after a panic finds the job it panicked on
+        // still on its list; dead letter it rather than panic on it again
+        if let Some(job_id) = heartbeats.take_panicked_job(&worker_id) {
+            match queue.dead_letter_unfinished(job_id, &WorkerError::ConsumerPanicked).await {
+                Ok(0) => {}
+                Ok(moved) => {
+                    warn!("Job {} the consumer panicked on moved to the DLQ", job_id);
+                    for _ in 0..moved {
+                        metrics.record_job_moved_to_dlq();
+                    }
+                }
+                Err(e) => {
+                    redis.record_error(&e);
+                    warn!("Failed to dead letter job {} the consumer panicked on: {}", job_id, e);
                 }
-                sleep(std::time::Duration::from_secs(60)).await;
             }
-        });
+        }
+
+        // Anything else left behind, e.g. by a run that failed mid-job, goes
+        // back on the queue
+        if let Err(e) = queue.nack_job().await {
+            redis.record_error(&e);
+            warn!("Failed to return unfinished jobs to the queue: {}", e);
+        }
 
         loop {
+            heartbeats.beat(&worker_id, ConsumerState::Idle, None);
+
             // Check if shutdown was requested
             if shutdown_signal.load(Ordering::Relaxed) {
                 info!("Shutdown signal received, stopping worker");
                 break;
             }
 
-            info!("Worker {} polling for jobs", worker_id);
-            let ran = uuid::Uuid::new_v4();
-            info!("UUID: {} -> hello bos!!!", ran);
-            sleep(std::time::Duration::from_millis(1000)).await;
-
-            // // Dequeue a job with timeout
-            // let job_result = queue
-            //     .dequeue_job(config.worker_consumer_wait_interval.as_secs())
-            //     .await;
-            //
-            // match job_result {
-            //     Ok(Some(job)) => {
-            //         // Process the job
-            //         let process_result = Self::process_job(&worker_id, &mut queue, conn_manager.clone(), &config, job, metrics.clone()).await;
-            //
-            //         if let Err(e) = process_result {
-            //             error!("Error processing job: {}", e);
-            //         }
-            //     }
-            //     Ok(None) => {
-            //         // No job available, continue polling
-            //         debug!("No job available, waiting for next job");
-            //     }
-            //     Err(e) => {
-            //         // Error dequeuing job
-            //         error!("Error dequeuing job: {}", e);
-            //
-            //         // Brief delay before retrying to prevent tight loops on persistent errors
-            //         sleep(std::time::Duration::from_millis(1000)).await;
-            //     }
-            // }
+            // Maintenance only holds back new jobs; the last one has finished by now
+            if maintenance.is_active() {
+                heartbeats.beat(&worker_id, ConsumerState::Paused, None);
+                sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+
+            // Retries whose backoff has passed go back on the queue first
+            if let Err(e) = queue.promote_due_jobs().await {
+                redis.record_error(&e);
+                warn!("Failed to promote delayed jobs: {}", e);
+            }
+
+            // So are jobs left behind by consumers that died mid-job
+            match queue.reap_expired_jobs().await {
+                Ok(reclaimed) => metrics.record_jobs_reclaimed(reclaimed as u64),
+                Err(e) => {
+                    redis.record_error(&e);
+                    warn!("Failed to reclaim expired jobs: {}", e);
+                }
+            }
+
+            // Dequeue a job with timeout
+            let job_result = match wakeups.as_mut() {
+                Some(wakeups) => wakeups.dequeue_job(&mut queue, config.worker_consumer_wait_interval).await,
+                None => queue.dequeue_job(config.worker_consumer_wait_interval.as_secs()).await,
+            };
+
+            match job_result {
+                Ok(Some(job)) => {
+                    heartbeats.beat(&worker_id, ConsumerState::Processing, Some(job.id));
+                    let mut execution = JobExecution::start(&job, &consumer_id);
+                    // Archived as dequeued, so a replay starts where this run did
+                    let archived_job = job_archive.is_some().then(|| job.clone());
+
+                    // Process the job
+                    let process_result = Self::process_job(
+                        &worker_id,
+                        &mut queue,
+                        conn_manager.clone(),
+                        &config,
+                        job,
+                        metrics.clone(),
+                        image_preprocessor.as_deref(),
+                        document_scanner.as_deref(),
+                        report_generator.as_deref(),
+                        notification_sender.as_deref(),
+                        object_copier.as_deref(),
+                        &document_progress,
+                        &mut execution,
+                    )
+                    .await;
+
+                    if let Err(e) = &process_result {
+                        execution.finish(OUTCOME_ERROR, Some(e));
+                    }
+                    if let Err(e) = job_executions.record(&execution).await {
+                        warn!("Failed to record execution of job {}: {}", execution.job_id, e);
+                    }
+                    if let (Some(job_archive), Some(job)) = (&job_archive, &archived_job) {
+                        job_archive.record(job, &execution).await;
+                    }
+
+                    // Retries and dead letters are queued by now; a job whose
+                    // outcome couldn't be recorded goes back for another try
+                    let settled = match process_result {
+                        Ok(()) => queue.ack_job().await,
+                        Err(e) => {
+                            redis.record_error(&e);
+                            error!("Error processing job: {}", e);
+                            queue.nack_job().await.map(|_| ())
+                        }
+                    };
+                    if let Err(e) = settled {
+                        redis.record_error(&e);
+                        error!("Failed to settle job on the processing list: {}", e);
+                    }
+                }
+                Ok(None) => {
+                    // No job available, continue polling
+                    debug!("No job available, waiting for next job");
+                }
+                Err(WorkerError::MalformedJob(_)) => {
+                    // Already set aside; the next entry may be fine
+                    metrics.record_malformed_job();
+                }
+                Err(e) => {
+                    // Error dequeuing job
+                    redis.record_error(&e);
+                    error!("Error dequeuing job: {}", e);
+
+                    // Brief delay before retrying to prevent tight loops on persistent errors
+                    sleep(std::time::Duration::from_millis(1000)).await;
+                }
+            }
         }
 
-        // Signal completion
-        if let Err(e) = completion_tx.send(worker_id.clone()).await {
-            error!("Failed to signal worker completion: {}", e);
+        if let Err(e) = queue.release_processing_list().await {
+            redis.record_error(&e);
+            warn!("Failed to release the processing list: {}", e);
         }
 
+        heartbeats.beat(&worker_id, ConsumerState::Stopped, None);
+
         info!("Worker thread exiting");
         Ok(())
     }
 
-    #[instrument(skip(queue, conn_manager, config, metrics), fields(job_id = %job.id, esign_id = %job.esign_id))]
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(
+        skip(
+            queue, conn_manager, config, metrics, image_preprocessor, document_scanner, report_generator,
+            notification_sender, object_copier, document_progress, execution
+        ),
+        fields(job_id = %job.id, esign_id = %job.esign_id)
+    )]
     async fn process_job(
         worker_id: &str,
         queue: &mut RedisQueue,
-        conn_manager: ConnectionManager,
+        conn_manager: RedisConnection,
         config: &WorkerConfig,
         mut job: FileUploadJob,
         metrics: Arc<WorkerMetrics>,
+        image_preprocessor: Option<&ImagePreprocessor>,
+        document_scanner: Option<&DocumentScanner>,
+        report_generator: Option<&ReportGenerator>,
+        notification_sender: Option<&NotificationSender>,
+        object_copier: Option<&ObjectCopier>,
+        document_progress: &DocumentProgress,
+        execution: &mut JobExecution,
     ) -> WorkerResult<()> {
+        telemetry::adopt_trace_context(&job.metadata);
         info!("Processing job: {}", job.id);
         let start_time = Instant::now();
         let _timer = metrics.start_timer();
         metrics.record_job_processed();
 
+        // Jobs enqueued before they were stamped have no queue wait to report
+        if let Some(queue_wait) = execution.queue_wait {
+            metrics.record_queue_wait_time(queue_wait);
+        }
+
         // Try to acquire a distributed lock based on esign_id to prevent concurrent processing
         let lock_key = job.get_lock_key();
         let mut lock = DistributedLock::new(
@@ -197,20 +418,65 @@ impl FileUploadWorker {
         );
 
         // Try to acquire the lock with retries
+        let lock_wait = Instant::now();
         let lock_acquired = lock
             .acquire(config.lock_retry_interval, config.lock_timeout)
             .await?;
+        metrics.record_lock_wait_time(lock_wait.elapsed());
+        execution.lock_wait = Some(lock_wait.elapsed());
 
         if !lock_acquired {
+            // Another consumer has the document; try again once it may be done
             warn!("Could not acquire lock for job {}, will retry later", job.id);
+            execution.finish(OUTCOME_LOCK_UNAVAILABLE, None);
+            job.set_progress(JobStatus::Pending, 0, "LOCK_UNAVAILABLE");
+            queue.schedule_job(&job, config.lock_retry_interval).await?;
             return Ok(());
         }
 
-        // We have the lock, process the job
-        let result = Self::upload_file(&job).await;
+        document_progress.record(&job, DocumentUploadStatus::Uploading, None).await;
+
+        // We have the lock, process the job within its time budget
+        let result = match timeout(
+            config.job_timeout,
+            Self::run_stages(
+                queue,
+                &mut job,
+                &config.upload_sources,
+                image_preprocessor,
+                document_scanner,
+                report_generator,
+                notification_sender,
+                object_copier,
+            ),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => {
+                // The abandoned stages can't finish anymore, so let a retry
+                // have the document now instead of after the lock expires
+                if let Err(e) = lock.release().await {
+                    warn!("Failed to release lock of timed out job {}: {}", job.id, e);
+                }
+                Err(WorkerError::JobTimeout(config.job_timeout))
+            }
+        };
 
         match result {
-            Ok(_) => {
+            Ok(StageOutcome::Quarantined) => {
+                queue.record_progress(&mut job, JobStatus::Quarantined, 100, "QUARANTINED").await;
+                document_progress
+                    .record(&job, DocumentUploadStatus::Failed, Some("Document is infected".to_string()))
+                    .await;
+                metrics.record_document_quarantined();
+                metrics.record_job_outcome(job.metric_tags(), "quarantined");
+                execution.finish(OUTCOME_QUARANTINED, None);
+            }
+            Ok(StageOutcome::Completed) => {
+                queue.record_progress(&mut job, JobStatus::Completed, 100, "COMPLETED").await;
+                document_progress.record(&job, DocumentUploadStatus::Uploaded, None).await;
+
                 // Job successful
                 info!(
                     "Job {} completed successfully in {:?}",
@@ -218,38 +484,65 @@ impl FileUploadWorker {
                     start_time.elapsed()
                 );
                 metrics.record_job_succeeded();
+                metrics.record_job_outcome(job.metric_tags(), "succeeded");
+                execution.finish(OUTCOME_SUCCEEDED, None);
 
                 // Lock will be released when it goes out of scope
                 return Ok(());
             }
-            Err(WorkerError::DocumentUrlExpired) => {
-                // Document URL has expired, move to DLQ without retries
+            Err(e) if !e.is_retryable() => {
+                // Validation and permanent failures can't succeed on retry,
+                // move to DLQ straight away
                 warn!(
-                    "Job {} failed: document URL expired, moving to DLQ",
-                    job.id
+                    "Job {} failed with {} error: {}, moving to DLQ",
+                    job.id,
+                    e.class(),
+                    e
                 );
+                job.record_error(&e);
+                metrics.record_error_class(&e);
+
+                let status = if matches!(e, WorkerError::DocumentUrlExpired) {
+                    metrics.record_url_expired_error();
+                    JobStatus::UrlExpired
+                } else {
+                    metrics.record_general_error();
+                    JobStatus::DeadLetter
+                };
+                let percent_complete = job.progress.percent_complete;
+                queue.record_progress(&mut job, status, percent_complete, "MOVED_TO_DLQ").await;
+                document_progress.record(&job, DocumentUploadStatus::Failed, Some(e.to_string())).await;
 
-                metrics.record_url_expired_error();
                 metrics.record_job_moved_to_dlq();
+                metrics.record_job_outcome(job.metric_tags(), "dead_lettered");
+                execution.finish(OUTCOME_DEAD_LETTERED, Some(&e));
                 queue.move_to_dlq(&job).await?;
             }
             Err(e) => {
-                // General error, implement retry logic
+                // Transient error, implement retry logic
                 job.increment_retry();
+                job.record_error(&e);
+                metrics.record_error_class(&e);
                 metrics.record_general_error();
 
-                if job.retry_count < config.worker_consumer_max_retry {
-                    // Retry the job
+                let policy = config.retry_policies.for_kind(job.kind);
+                if job.retry_count < policy.max_attempts {
+                    let backoff = policy.backoff(job.retry_count);
                     warn!(
-                        "Job {} failed: {}, retrying ({}/{})",
+                        "Job {} failed: {}, retrying ({}/{}) in {:?}",
                         job.id,
                         e,
                         job.retry_count,
-                        config.worker_consumer_max_retry
+                        policy.max_attempts,
+                        backoff
                     );
 
-                    // Re-enqueue the job
-                    queue.enqueue_job(&job).await?;
+                    // Re-enqueue the job after its backoff; scheduling publishes its progress
+                    job.set_progress(JobStatus::Pending, 0, "RETRY_SCHEDULED");
+                    document_progress.record(&job, DocumentUploadStatus::Uploading, Some(e.to_string())).await;
+                    metrics.record_job_outcome(job.metric_tags(), "retried");
+                    execution.finish(OUTCOME_RETRIED, Some(&e));
+                    queue.schedule_job(&job, backoff).await?;
                 } else {
                     // Max retries exceeded, move to DLQ
                     error!(
@@ -257,7 +550,13 @@ impl FileUploadWorker {
                         job.id, job.retry_count, e
                     );
 
+                    let percent_complete = job.progress.percent_complete;
+
+                    queue.record_progress(&mut job, JobStatus::DeadLetter, percent_complete, "MOVED_TO_DLQ").await;
+                    document_progress.record(&job, DocumentUploadStatus::Failed, Some(e.to_string())).await;
                     metrics.record_job_moved_to_dlq();
+                    metrics.record_job_outcome(job.metric_tags(), "dead_lettered");
+                    execution.finish(OUTCOME_DEAD_LETTERED, Some(&e));
                     queue.move_to_dlq(&job).await?;
                 }
             }
@@ -267,6 +566,79 @@ impl FileUploadWorker {
         Ok(())
     }
 
+    /// Upload, scan and preprocess the job's document, render its report,
+    /// send its notification, copy its object or only scan it, publishing
+    /// progress as each stage starts
+    #[allow(clippy::too_many_arguments)]
+    async fn run_stages(
+        queue: &mut RedisQueue,
+        job: &mut FileUploadJob,
+        upload_sources: &UploadSourcePolicy,
+        image_preprocessor: Option<&ImagePreprocessor>,
+        document_scanner: Option<&DocumentScanner>,
+        report_generator: Option<&ReportGenerator>,
+        notification_sender: Option<&NotificationSender>,
+        object_copier: Option<&ObjectCopier>,
+    ) -> WorkerResult<StageOutcome> {
+        if job.kind == JobKind::Report {
+            let report_generator = report_generator
+                .ok_or_else(|| WorkerError::Report("report generation isn't configured".to_string()))?;
+            queue.record_progress(job, JobStatus::Processing, 10, "RENDERING_REPORT").await;
+            report_generator.generate(job).await?;
+            return Ok(StageOutcome::Completed);
+        }
+
+        if job.kind == JobKind::Notification {
+            let notification_sender = notification_sender
+                .ok_or_else(|| WorkerError::NotificationRejected("notifications aren't configured".to_string()))?;
+            queue.record_progress(job, JobStatus::Processing, 10, "SENDING_NOTIFICATION").await;
+            notification_sender.send(job).await?;
+            return Ok(StageOutcome::Completed);
+        }
+
+        if job.kind == JobKind::Copy {
+            let object_copier =
+                object_copier.ok_or_else(|| WorkerError::Copy("object storage isn't configured".to_string()))?;
+            queue.record_progress(job, JobStatus::Processing, 10, "COPYING").await;
+            object_copier.copy(job).await?;
+            return Ok(StageOutcome::Completed);
+        }
+
+        if job.kind == JobKind::Scan {
+            let scanner =
+                document_scanner.ok_or_else(|| WorkerError::Scan("document scanning isn't configured".to_string()))?;
+            queue.record_progress(job, JobStatus::Processing, 10, "SCANNING").await;
+            return Ok(match scanner.scan(job).await? {
+                ScanVerdict::Infected { .. } => StageOutcome::Quarantined,
+                ScanVerdict::Clean => StageOutcome::Completed,
+            });
+        }
+
+        // Checked before anything is fetched, so a disallowed URL is never requested
+        upload_sources.check(&job.document_url)?;
+
+        queue.record_progress(job, JobStatus::Processing, 10, "UPLOADING").await;
+        Self::upload_file(job).await?;
+
+        // Infected documents never reach preprocessing or the submission flow
+        if let Some(scanner) = document_scanner {
+            queue.record_progress(job, JobStatus::Processing, 40, "SCANNING").await;
+            if let ScanVerdict::Infected { .. } = scanner.scan(job).await? {
+                return Ok(StageOutcome::Quarantined);
+            }
+        }
+
+        // Store the provider-friendly variant before the job counts as done
+        if let Some(preprocessor) = image_preprocessor {
+            if ImagePreprocessor::applies_to(job) {
+                queue.record_progress(job, JobStatus::Processing, 60, "PREPROCESSING").await;
+                preprocessor.preprocess(job).await?;
+            }
+        }
+
+        Ok(StageOutcome::Completed)
+    }
+
     async fn upload_file(job: &FileUploadJob) -> WorkerResult<()> {
         // This is where you would implement the actual document upload logic
         // For this example, we'll simulate the upload process
@@ -276,9 +648,10 @@ impl FileUploadWorker {
             return Err(WorkerError::DocumentUrlExpired);
         }
 
-        // Simulate random failures for testing retry logic
-        if rand::random::<f32>() < 0.1 {
-            return Err(WorkerError::UploadFailed("Random upload failure".to_string()));
+        // Faults injected on purpose to test retries and the DLQ
+        #[cfg(feature = "chaos")]
+        if let Some(injector) = crate::workers::fault_injection::injector() {
+            injector.inject_upload_fault().await?;
         }
 
         // Simulate successful upload (with some processing time)
@@ -0,0 +1,174 @@
+use chrono::Utc;
+use sqlx::PgPool;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::{
+    commons::{crypto::FieldCipher, minio_service::MinioService},
+    repositories::user_repository::UserRepository,
+    services::scanner_service::QUARANTINE_PREFIX,
+    submissions::submission_repository::SubmissionRepository,
+    workers::{DistributedLock, RedisConnections, WorkerError, WorkerMetrics, WorkerResult},
+};
+
+/// Only one worker instance purges at a time
+const PURGE_LOCK_KEY: &str = "user_purge";
+
+/// Accounts purged per batch before looking for more
+const PURGE_BATCH_SIZE: i64 = 50;
+
+/// How often the wait between runs checks the shutdown signal
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Hard-deletes accounts closed through `DELETE /v1/me` once the retention
+/// window has passed, together with their submissions, stored documents and
+/// audit trail
+pub struct UserPurger {
+    users: UserRepository,
+    submissions: SubmissionRepository,
+    minio_service: MinioService,
+    redis: RedisConnections,
+    metrics: Arc<WorkerMetrics>,
+    retention: chrono::Duration,
+    interval: Duration,
+}
+
+impl UserPurger {
+    /// None unless `USER_PURGE_ENABLED=true`
+    pub async fn from_env(pool: PgPool, redis: RedisConnections, metrics: Arc<WorkerMetrics>) -> WorkerResult<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        if var("USER_PURGE_ENABLED").as_deref() != Some("true") {
+            return Ok(None);
+        }
+
+        let required = |name: &str| var(name).ok_or_else(|| WorkerError::Config(anyhow::anyhow!("{} must be set", name)));
+        let number = |name: &str, default: u64| {
+            var(name)
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map(|v| v.unwrap_or(default))
+                .map_err(|_| WorkerError::Config(anyhow::anyhow!("{} must be a number", name)))
+        };
+
+        let minio_service = MinioService::new(
+            &required("MINIO_ENDPOINT")?,
+            &required("MINIO_ACCESS_KEY")?,
+            &required("MINIO_SECRET_KEY")?,
+            &required("MINIO_BUCKET_NAME")?,
+        )
+        .await
+        .map_err(WorkerError::Config)?;
+
+        Ok(Some(Self {
+            users: UserRepository::new(pool.clone()),
+            submissions: SubmissionRepository::new(pool, FieldCipher::from_env()?),
+            minio_service,
+            redis,
+            metrics,
+            retention: chrono::Duration::days(number("USER_RETENTION_DAYS", 30)? as i64),
+            interval: Duration::from_secs(number("USER_PURGE_INTERVAL_SECONDS", 3600)?.max(1)),
+        }))
+    }
+
+    /// Purge every `interval` until `shutdown_signal` is set
+    pub async fn run(&self, shutdown_signal: Arc<AtomicBool>) {
+        info!(
+            "Purging closed accounts after {} days, checking every {:?}",
+            self.retention.num_days(),
+            self.interval
+        );
+
+        while !shutdown_signal.load(Ordering::SeqCst) {
+            match self.purge_due(&shutdown_signal).await {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {} closed accounts", purged),
+                Err(e) => error!("Failed to purge closed accounts: {}", e),
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < self.interval && !shutdown_signal.load(Ordering::SeqCst) {
+                tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+                waited += SHUTDOWN_POLL_INTERVAL;
+            }
+        }
+
+        info!("User purge stopped");
+    }
+
+    /// Purge the accounts past their retention window, returning how many
+    async fn purge_due(&self, shutdown_signal: &AtomicBool) -> WorkerResult<u64> {
+        let mut lock = DistributedLock::new(self.redis.shared(), PURGE_LOCK_KEY.to_string(), self.interval);
+        if !lock.acquire(Duration::ZERO, Duration::ZERO).await? {
+            return Ok(0);
+        }
+
+        let cutoff = Utc::now() - self.retention;
+        let mut purged = 0;
+        // Accounts that failed this run; they're tried again on the next one
+        let mut failed = Vec::new();
+        let result = loop {
+            if shutdown_signal.load(Ordering::SeqCst) {
+                break Ok(purged);
+            }
+
+            let user_ids = match self.users.find_purgeable(cutoff, &failed, PURGE_BATCH_SIZE).await {
+                Ok(user_ids) => user_ids,
+                Err(e) => break Err(e.into()),
+            };
+            if user_ids.is_empty() {
+                break Ok(purged);
+            }
+
+            for user_id in user_ids {
+                match self.purge(user_id).await {
+                    Ok(()) => purged += 1,
+                    Err(e) => {
+                        error!("Failed to purge user {}, skipping it until the next run: {}", user_id, e);
+                        failed.push(user_id);
+                    }
+                }
+            }
+        };
+
+        lock.release().await?;
+        result
+    }
+
+    /// Documents go first: if deleting them fails the rows are kept, so the
+    /// next run still knows what to delete
+    async fn purge(&self, user_id: i32) -> WorkerResult<()> {
+        let owner = user_id.to_string();
+
+        for (submission_id, tenant_id, documents) in self.submissions.find_documents_by_user(&owner).await? {
+            let prefix = MinioService::submission_prefix(&tenant_id, submission_id);
+            for object_prefix in [prefix.clone(), format!("{}{}", QUARANTINE_PREFIX, prefix)] {
+                self.minio_service
+                    .delete_prefix(&object_prefix)
+                    .await
+                    .map_err(|e| WorkerError::Storage(e.to_string()))?;
+            }
+
+            // Documents stored before the per-submission layout live elsewhere
+            for (_, document) in documents.iter().filter(|(_, document)| !document.document_name.starts_with(&prefix)) {
+                self.minio_service
+                    .delete_file(document.document_name.clone())
+                    .await
+                    .map_err(|e| WorkerError::Storage(e.to_string()))?;
+            }
+        }
+
+        let mut tx = self.submissions.begin().await?;
+        let submissions = self.submissions.delete_by_user(&mut tx, &owner).await?;
+        self.users.hard_delete(&mut tx, user_id).await?;
+        tx.commit().await?;
+
+        self.metrics.record_user_purged();
+        info!("Purged user {} with {} submissions", user_id, submissions);
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use futures::{FutureExt, Stream, StreamExt};
+use redis::Msg;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::commons::redis_connection::RedisTopology;
+use crate::workers::{FileUploadJob, RedisQueue, WorkerResult};
+
+/// How long to poll with BRPOP after losing the subscription before
+/// subscribing again
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A consumer's subscription to its queue's wake-up channel. An idle
+/// consumer sleeps on the channel instead of holding a BRPOP, and checks the
+/// queue as soon as an enqueue publishes to it.
+pub struct WakeupSubscriber {
+    topology: RedisTopology,
+    channel: String,
+    max_idle: Duration,
+    messages: Option<Pin<Box<dyn Stream<Item = Msg> + Send>>>,
+    subscribe_at: Instant,
+}
+
+impl WakeupSubscriber {
+    pub fn new(topology: RedisTopology, queue: &RedisQueue, max_idle: Duration) -> Self {
+        Self {
+            topology,
+            channel: queue.wakeup_channel_name(),
+            max_idle,
+            messages: None,
+            subscribe_at: Instant::now(),
+        }
+    }
+
+    /// Take the next job, waiting for a wake-up for up to the max idle time.
+    /// Without a subscription this is a BRPOP for up to `fallback_wait`.
+    pub async fn dequeue_job(&mut self, queue: &mut RedisQueue, fallback_wait: Duration) -> WorkerResult<Option<FileUploadJob>> {
+        if self.messages.is_none() && Instant::now() >= self.subscribe_at {
+            self.subscribe().await;
+        }
+        let Some(messages) = self.messages.as_mut() else {
+            return queue.dequeue_job(fallback_wait.as_secs()).await;
+        };
+
+        // Wake-ups that arrived while we were busy only say to look at the
+        // queue, which we are about to do anyway
+        let mut closed = false;
+        while let Some(message) = messages.next().now_or_never() {
+            if message.is_none() {
+                closed = true;
+                break;
+            }
+        }
+
+        // Subscribed before looking, so a job pushed from here on still wakes us
+        if let Some(job) = queue.try_dequeue_job().await? {
+            return Ok(Some(job));
+        }
+        if closed {
+            self.unsubscribed();
+            return Ok(None);
+        }
+
+        match tokio::time::timeout(self.max_idle, messages.next()).await {
+            Ok(Some(_)) => queue.try_dequeue_job().await,
+            Ok(None) => {
+                self.unsubscribed();
+                Ok(None)
+            }
+            // Idle; the caller promotes due retries and reaps before waiting again
+            Err(_) => Ok(None),
+        }
+    }
+
+    async fn subscribe(&mut self) {
+        match self.topology.subscribe(&self.channel).await {
+            Ok(pubsub) => {
+                info!("Subscribed to {}", self.channel);
+                self.messages = Some(Box::pin(pubsub.into_on_message()));
+            }
+            Err(e) => {
+                warn!("Failed to subscribe to {}, polling the queue instead: {}", self.channel, e);
+                self.subscribe_at = Instant::now() + RESUBSCRIBE_INTERVAL;
+            }
+        }
+    }
+
+    fn unsubscribed(&mut self) {
+        warn!("Subscription to {} closed, polling the queue instead", self.channel);
+        self.messages = None;
+        self.subscribe_at = Instant::now() + RESUBSCRIBE_INTERVAL;
+    }
+}
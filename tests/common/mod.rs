@@ -0,0 +1,323 @@
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::{web, App, HttpResponse, HttpServer};
+use aws_sdk_s3::config::{Credentials, Region};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use testcontainers_modules::{
+    minio::MinIO,
+    postgres::Postgres,
+    redis::Redis,
+    testcontainers::{runners::AsyncRunner, ContainerAsync},
+};
+
+pub const BUCKET_NAME: &str = "hackathon-bi-2025-test";
+pub const UPLOAD_QUEUE: &str = "upload_file_queue";
+pub const UPLOAD_DLQ: &str = "upload_file_dlq";
+
+/// TestEnv owns the Postgres, Redis and MinIO containers plus a stub face match provider
+pub struct TestEnv {
+    _postgres: ContainerAsync<Postgres>,
+    _redis: ContainerAsync<Redis>,
+    _minio: ContainerAsync<MinIO>,
+    pub database_url: String,
+    pub redis_url: String,
+    pub minio_endpoint: String,
+    pub face_match_host: String,
+    pub smtp_port: u16,
+    /// Every email the SMTP stub accepted, as sent
+    pub mailbox: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestEnv {
+    pub async fn start() -> Self {
+        let postgres = Postgres::default().start().await.expect("Failed to start postgres");
+        let redis = Redis::default().start().await.expect("Failed to start redis");
+        let minio = MinIO::default().start().await.expect("Failed to start minio");
+
+        let database_url = format!(
+            "postgres://postgres:postgres@{}:{}/postgres",
+            postgres.get_host().await.unwrap(),
+            postgres.get_host_port_ipv4(5432).await.unwrap()
+        );
+        let redis_url = format!(
+            "redis://{}:{}",
+            redis.get_host().await.unwrap(),
+            redis.get_host_port_ipv4(6379).await.unwrap()
+        );
+        let minio_endpoint = format!(
+            "http://{}:{}",
+            minio.get_host().await.unwrap(),
+            minio.get_host_port_ipv4(9000).await.unwrap()
+        );
+
+        // Apply the schema the application expects
+        let pool = sqlx::PgPool::connect(&database_url).await.expect("Failed to connect to postgres");
+        sqlx::migrate!("./migrations").run(&pool).await.expect("Failed to run migrations");
+        pool.close().await;
+
+        // Create the bucket the application uploads into
+        let s3_config = aws_sdk_s3::config::Builder::new()
+            .endpoint_url(&minio_endpoint)
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("minioadmin", "minioadmin", None, None, "test"))
+            .force_path_style(true)
+            .behavior_version_latest()
+            .build();
+        aws_sdk_s3::Client::from_conf(s3_config)
+            .create_bucket()
+            .bucket(BUCKET_NAME)
+            .send()
+            .await
+            .expect("Failed to create bucket");
+
+        let face_match_host = start_face_match_stub();
+        let (smtp_port, mailbox) = start_smtp_stub().await;
+
+        Self {
+            _postgres: postgres,
+            _redis: redis,
+            _minio: minio,
+            database_url,
+            redis_url,
+            minio_endpoint,
+            face_match_host,
+            smtp_port,
+            mailbox,
+        }
+    }
+
+    /// Wait for the latest verification email sent to `email` and return
+    /// the token in its link
+    pub async fn emailed_verification_token(&self, email: &str, previous: Option<&str>) -> String {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(10) {
+            let token = self
+                .mailbox
+                .lock()
+                .unwrap()
+                .iter()
+                .rev()
+                .filter(|message| message.contains(email))
+                .find_map(|message| {
+                    // Undo quoted-printable soft line breaks around the link
+                    let message = message.replace("=\r\n", "");
+                    let token = message.split("token=").nth(1)?;
+                    Some(token.chars().take_while(char::is_ascii_alphanumeric).collect::<String>())
+                });
+            match token {
+                Some(token) if Some(token.as_str()) != previous => return token,
+                _ => tokio::time::sleep(Duration::from_millis(250)).await,
+            }
+        }
+        panic!("No verification email was sent to {}", email);
+    }
+
+    /// Read the email verification token the API issued at registration
+    pub async fn verification_token(&self, email: &str) -> String {
+        let pool = sqlx::PgPool::connect(&self.database_url).await.expect("Failed to connect to postgres");
+        let token: String = sqlx::query_scalar("SELECT verification_token FROM users WHERE email = $1")
+            .bind(email)
+            .fetch_one(&pool)
+            .await
+            .expect("Failed to read verification token");
+        pool.close().await;
+        token
+    }
+
+    /// Spawn the application binary in the given APP_MODE against this environment
+    pub async fn spawn_app(&self, app_mode: &str) -> AppProcess {
+        self.spawn_app_with_env(app_mode, &[]).await
+    }
+
+    /// Spawn the application binary with extra environment variables on top
+    /// of this environment's
+    pub async fn spawn_app_with_env(&self, app_mode: &str, envs: &[(&str, &str)]) -> AppProcess {
+        let port = free_port();
+        let child = Command::new(env!("CARGO_BIN_EXE_hackathon-bi-2025"))
+            .env("APP_MODE", app_mode)
+            .env("HOST", "127.0.0.1")
+            .env("PORT", port.to_string())
+            .env("DATABASE_URL", &self.database_url)
+            .env("JWT_SECRET", "integration-test-secret")
+            .env("PII_ENCRYPTION_KEYS", "test:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=")
+            .env("PII_ENCRYPTION_ACTIVE_KEY_ID", "test")
+            .env("PII_BLIND_INDEX_KEY", "aW50ZWdyYXRpb24tdGVzdC1pbmRleC1rZXk=")
+            .env("STATSD_HOST", "127.0.0.1")
+            .env("STATSD_PORT", "8125")
+            .env("STATSD_PREFIX", "hackathon_bi_2025_test")
+            .env("MINIO_ENDPOINT", &self.minio_endpoint)
+            .env("MINIO_ACCESS_KEY", "minioadmin")
+            .env("MINIO_SECRET_KEY", "minioadmin")
+            .env("MINIO_BUCKET_NAME", BUCKET_NAME)
+            .env("FACE_MATCH_HOST", &self.face_match_host)
+            .env("FACE_MATCH_THRESHOLD", "60")
+            .env("FACE_MATCH_TIMEOUT_MILLIS", "5000")
+            .env("SMTP_HOST", "127.0.0.1")
+            .env("SMTP_PORT", self.smtp_port.to_string())
+            .env("SMTP_SECURITY", "none")
+            .env("SMTP_FROM", "no-reply@example.com")
+            .env("EMAIL_VERIFICATION_URL", "http://app.test/verify")
+            .env("REDIS_URL", &self.redis_url)
+            .env("WORKER_UPLOAD_FILE_QUEUE", UPLOAD_QUEUE)
+            .env("WORKER_UPLOAD_FILE_DLQ", UPLOAD_DLQ)
+            .env("WORKER_UPLOAD_ALLOWED_HOSTS", "documents.example.com")
+            .env("WORKER_CONSUMER_WAIT_INTERVAL_IN_MILLISECONDS", "1000")
+            .env("FILE_UPLOAD_WORKER_DLQ_WAIT_INTERVAL_IN_MILLISECONDS", "1000")
+            .env("RUST_LOG", "info")
+            .envs(envs.iter().copied())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .expect("Failed to spawn application binary");
+
+        let app = AppProcess {
+            child,
+            base_url: format!("http://127.0.0.1:{}", port),
+        };
+
+        if app_mode == "api" {
+            wait_for_port(port).await;
+        }
+
+        app
+    }
+}
+
+/// AppProcess kills the spawned binary when dropped
+pub struct AppProcess {
+    child: Child,
+    pub base_url: String,
+}
+
+impl Drop for AppProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Poll `check` until it returns true or the timeout elapses
+pub async fn eventually<F, Fut>(timeout: Duration, mut check: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if check().await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    false
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .expect("Failed to bind ephemeral port")
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+async fn wait_for_port(port: u16) {
+    let ready = eventually(Duration::from_secs(30), || async move {
+        tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_ok()
+    })
+    .await;
+    assert!(ready, "Application did not start listening on port {}", port);
+}
+
+/// How long the face match stub takes to answer, long enough for a racing
+/// process request to find the submission still locked
+const FACE_MATCH_STUB_DELAY: Duration = Duration::from_millis(500);
+
+/// Start a face match provider stub that always reports a confident match,
+/// after `FACE_MATCH_STUB_DELAY`
+fn start_face_match_stub() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind face match stub");
+    let port = listener.local_addr().unwrap().port();
+
+    let server = HttpServer::new(|| {
+        App::new().route(
+            "/compare-faces",
+            web::post().to(|req: actix_web::HttpRequest| async move {
+                let submission_id = req
+                    .headers()
+                    .get("x-submission-id")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                tokio::time::sleep(FACE_MATCH_STUB_DELAY).await;
+                HttpResponse::Ok().json(json!({
+                    "submission_id": submission_id,
+                    "similarity_score": 0.92,
+                    "is_match": true,
+                    "threshold": 0.6,
+                }))
+            }),
+        )
+    })
+    .workers(1)
+    .listen(listener)
+    .expect("Failed to listen for face match stub")
+    .run();
+
+    tokio::spawn(server);
+
+    format!("http://127.0.0.1:{}", port)
+}
+
+/// Start an SMTP server that accepts every email into the returned mailbox
+async fn start_smtp_stub() -> (u16, Arc<Mutex<Vec<String>>>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind SMTP stub");
+    let port = listener.local_addr().unwrap().port();
+    let mailbox = Arc::new(Mutex::new(Vec::new()));
+
+    let inbox = mailbox.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let inbox = inbox.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                let _ = writer.write_all(b"220 stub\r\n").await;
+
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let command = line.to_ascii_uppercase();
+                    let reply: &[u8] = if command.starts_with("DATA") {
+                        let _ = writer.write_all(b"354 go ahead\r\n").await;
+                        let mut message = String::new();
+                        while let Ok(Some(line)) = lines.next_line().await {
+                            if line == "." {
+                                break;
+                            }
+                            message.push_str(&line);
+                            message.push_str("\r\n");
+                        }
+                        inbox.lock().unwrap().push(message);
+                        b"250 OK\r\n"
+                    } else if command.starts_with("QUIT") {
+                        let _ = writer.write_all(b"221 bye\r\n").await;
+                        break;
+                    } else {
+                        b"250 OK\r\n"
+                    };
+                    let _ = writer.write_all(reply).await;
+                }
+            });
+        }
+    });
+
+    (port, mailbox)
+}
+
+/// Extract `data` from a successful ApiResponse envelope
+pub fn data(body: &Value) -> &Value {
+    assert_eq!(body["success"], json!(true), "Unexpected error response: {}", body);
+    &body["data"]
+}
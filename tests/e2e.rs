@@ -0,0 +1,539 @@
+//! End-to-end tests running the compiled binary against Postgres, Redis and MinIO
+//! containers. They need a Docker daemon, so run them explicitly with
+//! `cargo test --test e2e -- --ignored`.
+
+mod common;
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::Utc;
+use redis::AsyncCommands;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{data, eventually, TestEnv, UPLOAD_DLQ, UPLOAD_QUEUE};
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires a Docker daemon"]
+async fn kyc_submission_flow() {
+    let env = TestEnv::start().await;
+    let app = env.spawn_app("api").await;
+    let client = reqwest::Client::new();
+
+    // Register and login
+    let email = format!("{}@example.com", Uuid::new_v4());
+    let credentials = json!({ "email": email, "password": "secret123", "name": "E2E User" });
+
+    let register: Value = client
+        .post(format!("{}/v1/register", app.base_url))
+        .json(&credentials)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(data(&register)["token"].is_string());
+
+    let login: Value = client
+        .post(format!("{}/v1/login", app.base_url))
+        .json(&json!({ "email": email, "password": "secret123" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let token = data(&login)["token"].as_str().unwrap().to_string();
+
+    // Unverified accounts can't start a submission
+    let nfc_identifier = STANDARD.encode(format!("nfc-chip-photo-{}", Uuid::new_v4()));
+    let blocked: Value = client
+        .post(format!("{}/v1/submissions/urls", app.base_url))
+        .bearer_auth(&token)
+        .json(&json!({ "submissionType": "KYC", "nfcIdentifier": nfc_identifier }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(blocked["errors"][0]["cause"], json!("EMAIL_NOT_VERIFIED"));
+
+    // Verify the email with the token issued at registration
+    let verification_token = env.verification_token(&email).await;
+    let verified: Value = client
+        .get(format!("{}/v1/verify-email", app.base_url))
+        .query(&[("token", verification_token.as_str())])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(data(&verified)["email"], json!(email));
+
+    // Request presigned URLs
+    let urls: Value = client
+        .post(format!("{}/v1/submissions/urls", app.base_url))
+        .bearer_auth(&token)
+        .json(&json!({ "submissionType": "KYC", "nfcIdentifier": nfc_identifier }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let urls = data(&urls);
+    let submission_id = urls["submissionId"].as_str().unwrap().to_string();
+    let submission_token = urls["submissionToken"].as_str().unwrap().to_string();
+    assert!(urls["documents"]["KTP"]["documentUrl"].is_string());
+
+    // Upload the selfie through its presigned URL
+    let selfie_url = urls["documents"]["SELFIE"]["documentUrl"].as_str().unwrap();
+    let upload = client
+        .put(selfie_url)
+        .header("Content-Type", "image/jpeg")
+        .body(vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10])
+        .send()
+        .await
+        .unwrap();
+    assert!(upload.status().is_success(), "Selfie upload failed: {}", upload.status());
+
+    // The bearer token alone can't process the submission
+    let unscoped = client
+        .put(format!("{}/v1/submissions/urls", app.base_url))
+        .bearer_auth(&token)
+        .json(&json!({ "submissionId": submission_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unscoped.status(), reqwest::StatusCode::FORBIDDEN);
+    let unscoped: Value = unscoped.json().await.unwrap();
+    assert_eq!(unscoped["errors"][0]["code"], json!("1023"));
+
+    // Process the submission against the face match stub
+    let processed: Value = client
+        .put(format!("{}/v1/submissions/urls", app.base_url))
+        .bearer_auth(&token)
+        .header("X-Submission-Token", &submission_token)
+        .json(&json!({ "submissionId": submission_id }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(data(&processed)["submissionStatus"], json!("APPROVED"));
+
+    // Status reflects the approved KYC
+    let status: Value = client
+        .get(format!("{}/v1/submissions/status", app.base_url))
+        .bearer_auth(&token)
+        .query(&[("submissionType", "KYC"), ("nfcIdentifier", nfc_identifier.as_str())])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(data(&status)["submissionStatus"], json!("KYC"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires a Docker daemon"]
+async fn emailed_verification_unblocks_submissions() {
+    let env = TestEnv::start().await;
+    let app = env.spawn_app("api").await;
+    let client = reqwest::Client::new();
+
+    // Register; the verification link arrives by email
+    let email = format!("{}@example.com", Uuid::new_v4());
+    let register: Value = client
+        .post(format!("{}/v1/register", app.base_url))
+        .json(&json!({ "email": email, "password": "secret123", "name": "E2E User" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let token = data(&register)["token"].as_str().unwrap().to_string();
+    let first_token = env.emailed_verification_token(&email, None).await;
+
+    // A resend mails a new link and replaces the old one, once per cooldown
+    let resend = || {
+        client
+            .post(format!("{}/v1/verify-email/resend", app.base_url))
+            .bearer_auth(&token)
+            .send()
+    };
+    assert_eq!(resend().await.unwrap().status(), reqwest::StatusCode::OK);
+    let verification_token = env.emailed_verification_token(&email, Some(&first_token)).await;
+
+    let throttled = resend().await.unwrap();
+    assert_eq!(throttled.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    let throttled: Value = throttled.json().await.unwrap();
+    assert_eq!(throttled["errors"][0]["cause"], json!("VERIFICATION_RESEND_TOO_SOON"));
+
+    let stale = client
+        .get(format!("{}/v1/verify-email", app.base_url))
+        .query(&[("token", first_token.as_str())])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stale.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+    let verified: Value = client
+        .get(format!("{}/v1/verify-email", app.base_url))
+        .query(&[("token", verification_token.as_str())])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(data(&verified)["email"], json!(email));
+
+    // The verified account can submit
+    let nfc_identifier = STANDARD.encode(format!("nfc-chip-photo-{}", Uuid::new_v4()));
+    let urls: Value = client
+        .post(format!("{}/v1/submissions/urls", app.base_url))
+        .bearer_auth(&token)
+        .json(&json!({ "submissionType": "KYC", "nfcIdentifier": nfc_identifier }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let urls = data(&urls);
+    let submission_id = urls["submissionId"].as_str().unwrap().to_string();
+    let submission_token = urls["submissionToken"].as_str().unwrap().to_string();
+    let selfie_url = urls["documents"]["SELFIE"]["documentUrl"].as_str().unwrap();
+    client
+        .put(selfie_url)
+        .header("Content-Type", "image/jpeg")
+        .body(vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10])
+        .send()
+        .await
+        .unwrap();
+
+    let processed: Value = client
+        .put(format!("{}/v1/submissions/urls", app.base_url))
+        .bearer_auth(&token)
+        .header("X-Submission-Token", &submission_token)
+        .json(&json!({ "submissionId": submission_id }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(data(&processed)["submissionStatus"], json!("APPROVED"));
+
+    // Nothing left to resend once verified
+    assert_eq!(resend().await.unwrap().status(), reqwest::StatusCode::CONFLICT);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires a Docker daemon"]
+async fn worker_drains_upload_queue() {
+    let env = TestEnv::start().await;
+    let _worker = env.spawn_app("worker").await;
+
+    let client = redis::Client::open(env.redis_url.as_str()).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+
+    push_upload_job(&mut conn, "http://documents.example.com/ktp.jpg").await;
+
+    let drained = eventually(Duration::from_secs(30), || {
+        let mut conn = conn.clone();
+        async move { conn.llen::<_, u64>(UPLOAD_QUEUE).await.unwrap_or(1) == 0 }
+    })
+    .await;
+    assert!(drained, "Worker did not consume the upload job");
+
+    let dlq_depth: u64 = conn.llen(UPLOAD_DLQ).await.unwrap();
+    assert_eq!(dlq_depth, 0, "A healthy upload job should not land in the DLQ");
+}
+
+/// Queue an upload job for `document_url` the way the API would
+async fn push_upload_job(conn: &mut redis::aio::MultiplexedConnection, document_url: &str) {
+    let now = Utc::now();
+    let job = json!({
+        "id": Uuid::new_v4(),
+        "esign_id": Uuid::new_v4().to_string(),
+        "document_url": document_url,
+        "document_name": "ktp.jpg",
+        "document_type": "KTP",
+        "retry_count": 0,
+        "created_at": now,
+        "updated_at": now,
+        "metadata": {},
+    });
+    conn.lpush::<_, _, ()>(UPLOAD_QUEUE, job.to_string()).await.unwrap();
+}
+
+/// The first dead letter, once one shows up. The DLQ worker briefly takes
+/// retained entries off the list, so a single look can miss it.
+async fn wait_for_dead_letter(conn: &mut redis::aio::MultiplexedConnection) -> Option<Value> {
+    let start = std::time::Instant::now();
+    while start.elapsed() < Duration::from_secs(30) {
+        if let Some(entry) = conn.lindex::<_, Option<String>>(UPLOAD_DLQ, 0).await.unwrap() {
+            return Some(serde_json::from_str(&entry).unwrap());
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    None
+}
+
+#[cfg(feature = "chaos")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires a Docker daemon"]
+async fn injected_upload_failures_exhaust_retries_into_the_dlq() {
+    let env = TestEnv::start().await;
+    let _worker = env
+        .spawn_app_with_env(
+            "worker",
+            &[
+                ("WORKER_CHAOS_UPLOAD_FAILURE_RATE", "1"),
+                ("WORKER_CHAOS_SEED", "7"),
+                ("WORKER_RETRY_UPLOAD_MAX_ATTEMPTS", "3"),
+                // Keep dead letters in the DLQ instead of archiving them
+                ("WORKER_DLQ_UPLOAD_TTL_SECONDS", "3600"),
+            ],
+        )
+        .await;
+
+    let client = redis::Client::open(env.redis_url.as_str()).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    push_upload_job(&mut conn, "http://documents.example.com/ktp.jpg").await;
+
+    let dead_letter = wait_for_dead_letter(&mut conn)
+        .await
+        .expect("A job failing every attempt should land in the DLQ");
+    assert_eq!(dead_letter["retry_count"], json!(3));
+}
+
+#[cfg(feature = "chaos")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires a Docker daemon"]
+async fn injected_url_expiry_is_not_retried() {
+    let env = TestEnv::start().await;
+    let _worker = env
+        .spawn_app_with_env(
+            "worker",
+            &[
+                ("WORKER_CHAOS_URL_EXPIRED_RATE", "1"),
+                ("WORKER_RETRY_UPLOAD_MAX_ATTEMPTS", "3"),
+                ("WORKER_DLQ_UPLOAD_TTL_SECONDS", "3600"),
+            ],
+        )
+        .await;
+
+    let client = redis::Client::open(env.redis_url.as_str()).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    push_upload_job(&mut conn, "http://documents.example.com/ktp.jpg").await;
+
+    let dead_letter = wait_for_dead_letter(&mut conn)
+        .await
+        .expect("An expired URL should be dead lettered");
+    assert_eq!(dead_letter["retry_count"], json!(0), "An expired URL can't succeed on retry");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires a Docker daemon"]
+async fn disallowed_document_urls_are_dead_lettered_unfetched() {
+    let env = TestEnv::start().await;
+    let _worker = env
+        .spawn_app_with_env("worker", &[("WORKER_DLQ_UPLOAD_TTL_SECONDS", "3600")])
+        .await;
+
+    let client = redis::Client::open(env.redis_url.as_str()).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    push_upload_job(&mut conn, "http://169.254.169.254/latest/meta-data/").await;
+
+    let dead_letter = wait_for_dead_letter(&mut conn)
+        .await
+        .expect("A URL off the allow-list should be dead lettered");
+    assert_eq!(dead_letter["retry_count"], json!(0), "A disallowed URL can't succeed on retry");
+    assert_eq!(dead_letter["errors"][0]["class"], json!("disallowed_source"));
+}
+
+/// Register, verify and log in a fresh user, returning its bearer token
+async fn verified_user(env: &TestEnv, base_url: &str, client: &reqwest::Client) -> String {
+    let email = format!("{}@example.com", Uuid::new_v4());
+    client
+        .post(format!("{}/v1/register", base_url))
+        .json(&json!({ "email": email, "password": "secret123", "name": "E2E User" }))
+        .send()
+        .await
+        .unwrap();
+
+    let verification_token = env.verification_token(&email).await;
+    client
+        .get(format!("{}/v1/verify-email", base_url))
+        .query(&[("token", verification_token.as_str())])
+        .send()
+        .await
+        .unwrap();
+
+    let login: Value = client
+        .post(format!("{}/v1/login", base_url))
+        .json(&json!({ "email": email, "password": "secret123" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    data(&login)["token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires a Docker daemon"]
+async fn racing_process_requests_conflict() {
+    let env = TestEnv::start().await;
+    // Both racing requests have to get past the single-use submission token
+    let app = env.spawn_app_with_env("api", &[("SUBMISSION_TOKEN_REQUIRED", "false")]).await;
+    let client = reqwest::Client::new();
+    let token = verified_user(&env, &app.base_url, &client).await;
+
+    let nfc_identifier = STANDARD.encode(format!("nfc-chip-photo-{}", Uuid::new_v4()));
+    let urls: Value = client
+        .post(format!("{}/v1/submissions/urls", app.base_url))
+        .bearer_auth(&token)
+        .json(&json!({ "submissionType": "KYC", "nfcIdentifier": nfc_identifier }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let submission_id = data(&urls)["submissionId"].as_str().unwrap().to_string();
+    let selfie_url = data(&urls)["documents"]["SELFIE"]["documentUrl"].as_str().unwrap();
+    client
+        .put(selfie_url)
+        .header("Content-Type", "image/jpeg")
+        .body(vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10])
+        .send()
+        .await
+        .unwrap();
+
+    // A request still holding the lock turns the next one away
+    let redis = redis::Client::open(env.redis_url.as_str()).unwrap();
+    let mut conn = redis.get_multiplexed_async_connection().await.unwrap();
+    let lock_key = format!("submission_process_lock:{}", submission_id);
+    conn.set_ex::<_, _, ()>(&lock_key, "in-flight", 60).await.unwrap();
+
+    let process = || {
+        client
+            .put(format!("{}/v1/submissions/urls", app.base_url))
+            .bearer_auth(&token)
+            .json(&json!({ "submissionId": submission_id }))
+            .send()
+    };
+
+    let blocked = process().await.unwrap();
+    assert_eq!(blocked.status(), reqwest::StatusCode::CONFLICT);
+    let blocked: Value = blocked.json().await.unwrap();
+    assert_eq!(blocked["errors"][0]["cause"], json!("CURRENTLY_PROCESSING"));
+    conn.del::<_, ()>(&lock_key).await.unwrap();
+
+    // A double tap: the face match stub answers slowly enough that the
+    // request losing the race always finds the lock taken
+    let (first, second) = tokio::join!(process(), process());
+    let mut statuses = vec![first.unwrap().status(), second.unwrap().status()];
+    statuses.sort();
+    assert_eq!(
+        statuses,
+        [reqwest::StatusCode::OK, reqwest::StatusCode::CONFLICT],
+        "Unexpected statuses for racing process requests"
+    );
+
+    // The lock is given back once processing is done
+    let released = eventually(Duration::from_secs(5), || {
+        let mut conn = conn.clone();
+        let lock_key = lock_key.clone();
+        async move { !conn.exists::<_, bool>(&lock_key).await.unwrap_or(true) }
+    })
+    .await;
+    assert!(released, "The process lock was not released");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires a Docker daemon"]
+async fn status_stream_pushes_the_decision() {
+    let env = TestEnv::start().await;
+    let app = env.spawn_app("api").await;
+    let client = reqwest::Client::new();
+    let token = verified_user(&env, &app.base_url, &client).await;
+
+    let nfc_identifier = STANDARD.encode(format!("nfc-chip-photo-{}", Uuid::new_v4()));
+    let urls: Value = client
+        .post(format!("{}/v1/submissions/urls", app.base_url))
+        .bearer_auth(&token)
+        .json(&json!({ "submissionType": "KYC", "nfcIdentifier": nfc_identifier }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let submission_id = data(&urls)["submissionId"].as_str().unwrap().to_string();
+    let submission_token = data(&urls)["submissionToken"].as_str().unwrap().to_string();
+    let selfie_url = data(&urls)["documents"]["SELFIE"]["documentUrl"].as_str().unwrap();
+    client
+        .put(selfie_url)
+        .header("Content-Type", "image/jpeg")
+        .body(vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10])
+        .send()
+        .await
+        .unwrap();
+
+    let mut stream = client
+        .get(format!("{}/v1/submissions/{}/events/stream", app.base_url, submission_id))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stream.status(), reqwest::StatusCode::OK);
+    assert_eq!(stream.headers()["content-type"], "text/event-stream");
+
+    // Read events until one matches, giving up after a while
+    async fn next_status(stream: &mut reqwest::Response, seen: &mut String, status: &str) -> bool {
+        let needle = format!("\"status\":\"{}\"", status);
+        tokio::time::timeout(Duration::from_secs(20), async {
+            while !seen.contains(&needle) {
+                match stream.chunk().await.unwrap() {
+                    Some(chunk) => seen.push_str(&String::from_utf8_lossy(&chunk)),
+                    None => return false,
+                }
+            }
+            true
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    let mut seen = String::new();
+    assert!(next_status(&mut stream, &mut seen, "INITIATED").await, "No initial status in {}", seen);
+
+    let processed = client
+        .put(format!("{}/v1/submissions/urls", app.base_url))
+        .bearer_auth(&token)
+        .header("X-Submission-Token", &submission_token)
+        .json(&json!({ "submissionId": submission_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(processed.status(), reqwest::StatusCode::OK);
+
+    assert!(next_status(&mut stream, &mut seen, "APPROVED").await, "No decision pushed in {}", seen);
+
+    // The stream ends with the final status
+    let ended = tokio::time::timeout(Duration::from_secs(5), stream.chunk()).await;
+    assert!(matches!(ended, Ok(Ok(None))), "The stream stayed open after the decision");
+}